@@ -0,0 +1,255 @@
+//! Scan checkpoints for the Python orchestrator: `RustCheckpointManager`
+//!
+//! A checkpoint records where an interrupted scan resumes (`position`, the
+//! `start_offset` of the next `scan_streaming`/`scan_events` call, and the
+//! direction), the bytes already scanned and a snapshot of the dedup cache:
+//! the video IDs already reported. On resume, `RustParallelScanner.set_seen_ids`
+//! takes the snapshot so those links are not reported again. `state` holds
+//! any JSON-serializable value of the orchestrator.
+//!
+//! The file layout matches the rust-recovery checkpoints: the image is
+//! identified by path and by a SHA-256 of its first MiB and length, and the
+//! file is written to `.tmp`, synced and renamed (the previous one kept as
+//! `.bak` when asked).
+//!
+//! ```python
+//! manager = RustCheckpointManager("scan.checkpoint.json")
+//! checkpoint = manager.load()
+//! if checkpoint and manager.validate("disk.img")[0]:
+//!     scanner.set_seen_ids(checkpoint["seen_ids"])
+//!     start = checkpoint["position"]
+//! ...
+//! manager.save("disk.img", position, seen_ids=ids, state={"phase": 2})
+//! ```
+
+use anyhow::{Context, Result};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CHECKPOINT_VERSION: u32 = 1;
+const HASH_READ_LIMIT: usize = 1024 * 1024;
+
+/// Saved state of an interrupted scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    pub version: u32,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub image_path: String,
+    pub image_hash: String,
+    /// Offset the resumed scan starts from
+    pub position: u64,
+    #[serde(default)]
+    pub reverse: bool,
+    #[serde(default)]
+    pub bytes_scanned: u64,
+    /// Dedup cache snapshot: video IDs already reported
+    #[serde(default)]
+    pub seen_ids: Vec<String>,
+    /// Orchestrator state
+    #[serde(default)]
+    pub state: serde_json::Value,
+}
+
+impl ScanCheckpoint {
+    /// Checkpoint of `image_path`, hashing the image
+    pub fn new(image_path: &Path, position: u64, reverse: bool) -> Result<Self> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        Ok(Self {
+            version: CHECKPOINT_VERSION,
+            timestamp,
+            image_path: image_path.to_string_lossy().into_owned(),
+            image_hash: compute_image_hash(image_path)?,
+            position,
+            reverse,
+            bytes_scanned: 0,
+            seen_ids: Vec::new(),
+            state: serde_json::Value::Null,
+        })
+    }
+
+    /// Why the checkpoint cannot resume a scan of `image_path`, if it cannot
+    pub fn mismatch(&self, image_path: &Path) -> Result<Option<String>> {
+        if self.version != CHECKPOINT_VERSION {
+            return Ok(Some(format!("unsupported checkpoint version {}", self.version)));
+        }
+        if self.image_path != image_path.to_string_lossy() {
+            return Ok(Some("image path mismatch".to_string()));
+        }
+        if self.image_hash != compute_image_hash(image_path)? {
+            return Ok(Some("image hash mismatch".to_string()));
+        }
+        if self.position > fs::metadata(image_path)?.len() {
+            return Ok(Some("checkpoint position exceeds image size".to_string()));
+        }
+        Ok(None)
+    }
+}
+
+/// SHA-256 of the first MiB and the length: identifies an image without reading all of it
+pub fn compute_image_hash(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open image: {:?}", path))?;
+    let len = file.metadata()?.len();
+    let mut buffer = Vec::with_capacity(HASH_READ_LIMIT);
+    Read::by_ref(&mut file).take(HASH_READ_LIMIT as u64).read_to_end(&mut buffer)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&buffer);
+    hasher.update(len.to_le_bytes());
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn load_checkpoint(path: &Path) -> Result<ScanCheckpoint> {
+    let data = fs::read(path).with_context(|| format!("Failed to read checkpoint: {:?}", path))?;
+    serde_json::from_slice(&data).with_context(|| format!("Invalid checkpoint: {:?}", path))
+}
+
+/// Write through `.tmp` and rename, so a crash leaves the previous checkpoint
+pub fn save_checkpoint(path: &Path, checkpoint: &ScanCheckpoint, backup: bool) -> Result<()> {
+    let serialized = serde_json::to_vec_pretty(checkpoint)?;
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut file = File::create(&tmp_path).with_context(|| format!("Failed to create {:?}", tmp_path))?;
+        file.write_all(&serialized)?;
+        file.sync_all()?;
+    }
+    if backup && path.exists() {
+        let _ = fs::copy(path, path.with_extension("bak"));
+    }
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyOSError, _>(format!("{:#}", e))
+}
+
+/// Saves and loads the checkpoint file of one scan
+#[pyclass]
+pub struct RustCheckpointManager {
+    path: PathBuf,
+    backup: bool,
+}
+
+#[pymethods]
+impl RustCheckpointManager {
+    #[new]
+    #[pyo3(signature = (path, backup=true))]
+    fn new(path: PathBuf, backup: bool) -> Self {
+        Self { path, backup }
+    }
+
+    #[getter]
+    fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// Save the scan state; `state` must be JSON-serializable
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (image_path, position, reverse=false, bytes_scanned=0, seen_ids=None, state=None))]
+    fn save(
+        &self,
+        py: Python<'_>,
+        image_path: PathBuf,
+        position: u64,
+        reverse: bool,
+        bytes_scanned: u64,
+        seen_ids: Option<Vec<String>>,
+        state: Option<PyObject>,
+    ) -> PyResult<()> {
+        let state = match state {
+            Some(state) => {
+                let json: String = py.import("json")?.call_method1("dumps", (state,))?.extract()?;
+                serde_json::from_str(&json).map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?
+            }
+            None => serde_json::Value::Null,
+        };
+        // Hashing and writing do not need the GIL
+        py.allow_threads(|| {
+            let mut checkpoint = ScanCheckpoint::new(&image_path, position, reverse)?;
+            checkpoint.bytes_scanned = bytes_scanned;
+            checkpoint.seen_ids = seen_ids.unwrap_or_default();
+            checkpoint.state = state;
+            save_checkpoint(&self.path, &checkpoint, self.backup)
+        })
+        .map_err(to_py_err)
+    }
+
+    /// The saved checkpoint as a dict, or None when there is none
+    fn load(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let checkpoint = py.allow_threads(|| load_checkpoint(&self.path)).map_err(to_py_err)?;
+        let dict = pyo3::types::PyDict::new(py);
+        dict.set_item("version", checkpoint.version)?;
+        dict.set_item("timestamp", checkpoint.timestamp)?;
+        dict.set_item("image_path", &checkpoint.image_path)?;
+        dict.set_item("image_hash", &checkpoint.image_hash)?;
+        dict.set_item("position", checkpoint.position)?;
+        dict.set_item("reverse", checkpoint.reverse)?;
+        dict.set_item("bytes_scanned", checkpoint.bytes_scanned)?;
+        dict.set_item("seen_ids", &checkpoint.seen_ids)?;
+        let state = serde_json::to_string(&checkpoint.state).map_err(|e| to_py_err(e.into()))?;
+        dict.set_item("state", py.import("json")?.call_method1("loads", (state,))?)?;
+        Ok(Some(dict.to_object(py)))
+    }
+
+    /// `(True, None)` when the checkpoint can resume a scan of `image_path`,
+    /// `(False, reason)` otherwise
+    fn validate(&self, py: Python<'_>, image_path: PathBuf) -> PyResult<(bool, Option<String>)> {
+        if !self.path.exists() {
+            return Ok((false, Some("no checkpoint".to_string())));
+        }
+        let mismatch = py
+            .allow_threads(|| load_checkpoint(&self.path)?.mismatch(&image_path))
+            .map_err(to_py_err)?;
+        Ok((mismatch.is_none(), mismatch))
+    }
+
+    /// Delete the checkpoint (and its backup) once the scan finished
+    fn clear(&self) -> PyResult<()> {
+        for path in [self.path.clone(), self.path.with_extension("bak")] {
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(to_py_err(e.into())),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip_and_validation() {
+        let dir = std::env::temp_dir().join(format!("rust_accelerator_checkpoint_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("image.bin");
+        fs::write(&image, vec![7u8; 4096]).unwrap();
+        let path = dir.join("scan.checkpoint.json");
+
+        let mut checkpoint = ScanCheckpoint::new(&image, 2048, true).unwrap();
+        checkpoint.bytes_scanned = 2048;
+        checkpoint.seen_ids = vec!["dQw4w9WgXcQ".to_string()];
+        checkpoint.state = serde_json::json!({"phase": 2});
+        save_checkpoint(&path, &checkpoint, true).unwrap();
+        save_checkpoint(&path, &checkpoint, true).unwrap();
+        assert!(path.with_extension("bak").exists());
+
+        let loaded = load_checkpoint(&path).unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.mismatch(&image).unwrap(), None);
+
+        fs::write(&image, vec![8u8; 4096]).unwrap();
+        assert_eq!(loaded.mismatch(&image).unwrap().as_deref(), Some("image hash mismatch"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
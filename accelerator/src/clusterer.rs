@@ -1,6 +1,7 @@
 use pyo3::prelude::*;
 use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
+use tracing::debug;
 
 /// Configuration for clustering
 #[derive(Clone, Copy)]
@@ -90,10 +91,10 @@ impl FragmentClusterer {
                 if f.id < 5 {
                      // Debug print for first few fragments
                      let sample: Vec<_> = words.iter().take(5).collect();
-                     eprintln!("[RUST DEBUG] Fragment {}: extracted {} words: {:?}", f.id, words.len(), sample);
+                     debug!(fragment = f.id, offset = f.offset, "Extracted {} words: {:?}", words.len(), sample);
                 }
             } else {
-                eprintln!("[RUST DEBUG] Fragment {}: NO WORDS extracted", f.id);
+                debug!(fragment = f.id, offset = f.offset, "No words extracted");
             }
             &f.words
         }).collect();
@@ -154,8 +155,12 @@ impl FragmentClusterer {
                     let final_score = final_sim * dist_factor;
 
                     if i < 2 && j < 5 {
-                        eprintln!("[RUST DEBUG] Sim({}, {}): Content={:.3}, Link={:.3}, Dist={:.3} -> Final={:.3}", 
-                            i, j, sim_score, link_sim, dist_factor, final_score);
+                        debug!(
+                            fragment = i,
+                            other = j,
+                            "Similarity: content={:.3}, link={:.3}, dist={:.3} -> final={:.3}",
+                            sim_score, link_sim, dist_factor, final_score
+                        );
                     }
 
                     if final_score >= self.config.similarity_threshold {
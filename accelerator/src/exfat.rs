@@ -1,62 +1,24 @@
 // exFAT Full Recovery v5.1 — Military Grade Performance Update
 // Optimized for fast Phase 0 scanning (< 300s for large disks)
 // Implements early exit for zero blocks and improved parallel processing
+//
+// Parsing, sanity checks and extraction live in `recovery_core::exfat`;
+// this module wraps them for Python.
 
 use pyo3::prelude::*;
 use pyo3::types::PyBytes;
 use rayon::prelude::*;
 use memmap2::Mmap;
 use std::fs::File;
-use std::sync::{Arc, RwLock};
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::RwLock;
 use crate::simd_search::scan_block_simd;
 use crate::matcher::EnhancedMatcher;
 use crate::types::EnrichedLink;
 
-// ═══════════════════════════════════════════════════════════════════════════════
-// exFAT CONSTANTS — по спецификации Microsoft exFAT Revision 1.00
-// ═══════════════════════════════════════════════════════════════════════════════
-
-/// Entry type markers
-const ENTRY_FILE:            u8 = 0x85;  // File Directory Entry (active)
-const ENTRY_STREAM:          u8 = 0xC0;  // Stream Extension Entry (active)
-const ENTRY_FILENAME:        u8 = 0xC1;  // File Name Entry (active)
-const ENTRY_DELETED_FILE:    u8 = 0x05;  // File Directory Entry (deleted)
-const ENTRY_DELETED_STREAM:  u8 = 0x40;  // Stream Extension Entry (deleted)
-const ENTRY_DELETED_FILENAME:u8 = 0x41;  // File Name Entry (deleted)
-const ENTRY_ALLOC_BITMAP:    u8 = 0x81;  // Allocation Bitmap
-const ENTRY_UPCASE_TABLE:    u8 = 0x82;  // Up-case Table
-const ENTRY_VOLUME_LABEL:    u8 = 0x83;  // Volume Label
-
-/// Boot sector field offsets (по спецификации exFAT §3.1)
-const BS_JUMP_BOOT:               usize = 0;    // 3 bytes
-const BS_FILE_SYSTEM_NAME:        usize = 3;    // 8 bytes "EXFAT   "
-const BS_PARTITION_OFFSET:        usize = 64;   // 8 bytes (u64)
-const BS_VOLUME_LENGTH:           usize = 72;   // 8 bytes (u64)
-const BS_FAT_OFFSET:              usize = 80;   // 4 bytes (u32) — in sectors
-const BS_FAT_LENGTH:              usize = 84;   // 4 bytes (u32) — in sectors
-const BS_CLUSTER_HEAP_OFFSET:     usize = 88;   // 4 bytes (u32) — in sectors
-const BS_CLUSTER_COUNT:           usize = 92;   // 4 bytes (u32)
-const BS_FIRST_CLUSTER_OF_ROOT:   usize = 96;   // 4 bytes (u32)
-const BS_VOLUME_SERIAL_NUMBER:    usize = 100;  // 4 bytes (u32)
-const BS_FILE_SYSTEM_REVISION:    usize = 104;  // 2 bytes (u16)
-const BS_VOLUME_FLAGS:            usize = 106;  // 2 bytes (u16)
-const BS_BYTES_PER_SECTOR_SHIFT:  usize = 108;  // 1 byte (u8) — 2^N
-const BS_SECTORS_PER_CLUSTER_SHIFT:usize = 109; // 1 byte (u8) — 2^N
-const BS_NUMBER_OF_FATS:          usize = 110;  // 1 byte (u8)
-const BS_DRIVE_SELECT:            usize = 111;  // 1 byte (u8)
-const BS_PERCENT_IN_USE:          usize = 112;  // 1 byte (u8)
-
-/// Stream Extension Entry field offsets (внутри 32-byte entry, §7.4)
-const SE_GENERAL_FLAGS:      usize = 1;   // 1 byte — bit1 = NoFatChain
-const SE_NAME_LENGTH:        usize = 3;   // 1 byte
-const SE_NAME_HASH:          usize = 4;   // 2 bytes (u16)
-const SE_VALID_DATA_LENGTH:  usize = 8;   // 8 bytes (u64)
-const SE_FIRST_CLUSTER:      usize = 20;  // 4 bytes (u32)
-const SE_DATA_LENGTH:        usize = 24;  // 8 bytes (u64) — ПОЛНЫЙ размер файла
-
-/// File Name Entry field offsets (внутри 32-byte entry, §7.7)
-const FN_GENERAL_FLAGS:      usize = 1;   // 1 byte
-const FN_FILE_NAME:          usize = 2;   // 30 bytes (15 UTF-16LE chars)
+pub use recovery_core::exfat::*;
+use recovery_core::types::FileTimestamps;
 
 const DIRECTORY_ENTRY_SIZE: usize = 32;
 const SCAN_CHUNK_SIZE: usize = 64 * 1024 * 1024; // Optimized for performance (64MB)
@@ -65,19 +27,6 @@ const SCAN_CHUNK_SIZE: usize = 64 * 1024 * 1024; // Optimized for performance (6
 // DATA STRUCTURES
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Parsed exFAT Boot Sector parameters
-#[derive(Clone, Debug)]
-pub struct ExFatBootParams {
-    pub sector_size: u64,
-    pub cluster_size: u64,
-    pub fat_offset: u64,
-    pub fat_length_sectors: u32,
-    pub cluster_heap_offset: u64,
-    pub cluster_count: u32,
-    pub root_dir_cluster: u32,
-    pub boot_sector_offset: u64,
-}
-
 /// exFAT directory entry — результат парсинга
 #[derive(Clone, Debug)]
 #[pyclass]
@@ -89,6 +38,8 @@ pub struct ExFATEntry {
     #[pyo3(get)]
     pub is_deleted: bool,
     #[pyo3(get)]
+    pub is_directory: bool,
+    #[pyo3(get)]
     pub filename: String,
     #[pyo3(get)]
     pub size: u64,
@@ -96,263 +47,92 @@ pub struct ExFATEntry {
     pub first_cluster: u32,
     #[pyo3(get)]
     pub no_fat_chain: bool,
+    /// Create, last modified and last accessed times (RFC 3339, None = unknown)
+    #[pyo3(get)]
+    pub created: Option<String>,
+    #[pyo3(get)]
+    pub modified: Option<String>,
+    #[pyo3(get)]
+    pub accessed: Option<String>,
+    /// Sanity violations found by `mark_suspicious_entries` (empty = trusted)
+    #[pyo3(get)]
+    pub suspicious_reasons: Vec<String>,
 }
 
-// ═══════════════════════════════════════════════════════════════════════════════
-// BOOT SECTOR PARSING
-// ═══════════════════════════════════════════════════════════════════════════════
-
-fn parse_boot_sector_at(data: &[u8], bs_offset: u64) -> Option<ExFatBootParams> {
-    let off = bs_offset as usize;
-    if data.len() < off + 120 {
-        return None;
-    }
-
-    if &data[off + BS_FILE_SYSTEM_NAME..off + BS_FILE_SYSTEM_NAME + 8] != b"EXFAT   " {
-        return None;
-    }
-
-    let bytes_per_sector_shift = data[off + BS_BYTES_PER_SECTOR_SHIFT];
-    let sectors_per_cluster_shift = data[off + BS_SECTORS_PER_CLUSTER_SHIFT];
-
-    if bytes_per_sector_shift < 9 || bytes_per_sector_shift > 12 {
-        return None;
-    }
-    if sectors_per_cluster_shift > 25 {
-        return None;
-    }
-
-    let sector_size = 1u64 << bytes_per_sector_shift;
-    let cluster_size = sector_size << sectors_per_cluster_shift;
-
-    let fat_offset_sectors = u32::from_le_bytes(
-        data[off + BS_FAT_OFFSET..off + BS_FAT_OFFSET + 4].try_into().ok()?
-    ) as u64;
-    let fat_length_sectors = u32::from_le_bytes(
-        data[off + BS_FAT_LENGTH..off + BS_FAT_LENGTH + 4].try_into().ok()?
-    );
-    let cluster_heap_offset_sectors = u32::from_le_bytes(
-        data[off + BS_CLUSTER_HEAP_OFFSET..off + BS_CLUSTER_HEAP_OFFSET + 4].try_into().ok()?
-    ) as u64;
-    let cluster_count = u32::from_le_bytes(
-        data[off + BS_CLUSTER_COUNT..off + BS_CLUSTER_COUNT + 4].try_into().ok()?
-    );
-    let root_dir_cluster = u32::from_le_bytes(
-        data[off + BS_FIRST_CLUSTER_OF_ROOT..off + BS_FIRST_CLUSTER_OF_ROOT + 4].try_into().ok()?
-    );
-
-    let fat_offset = bs_offset + fat_offset_sectors * sector_size;
-    let cluster_heap_offset = bs_offset + cluster_heap_offset_sectors * sector_size;
-
-    if cluster_size == 0 || cluster_size > 32 * 1024 * 1024 {
-        return None;
-    }
-    if fat_offset == 0 || cluster_heap_offset == 0 {
-        return None;
+#[pymethods]
+impl ExFATEntry {
+    #[getter]
+    pub fn suspicious(&self) -> bool {
+        !self.suspicious_reasons.is_empty()
     }
-
-    Some(ExFatBootParams {
-        sector_size,
-        cluster_size,
-        fat_offset,
-        fat_length_sectors,
-        cluster_heap_offset,
-        cluster_count,
-        root_dir_cluster,
-        boot_sector_offset: bs_offset,
-    })
 }
 
-fn find_boot_sector(data: &[u8]) -> Option<ExFatBootParams> {
-    if let Some(params) = parse_boot_sector_at(data, 0) {
-        return Some(params);
-    }
-
-    let search_limit = std::cmp::min(data.len(), 4 * 1024 * 1024); // Extended search range
-    for offset in (512..search_limit).step_by(512) {
-        if offset + 120 > data.len() {
-            break;
-        }
-        if &data[offset + 3..offset + 11] == b"EXFAT   " {
-            if let Some(params) = parse_boot_sector_at(data, offset as u64) {
-                return Some(params);
-            }
+impl From<ExFatEntry> for ExFATEntry {
+    fn from(entry: ExFatEntry) -> Self {
+        let rfc3339 = |time: Option<chrono::DateTime<chrono::FixedOffset>>| time.map(|time| time.to_rfc3339());
+        Self {
+            offset: entry.offset,
+            data_offset: entry.data_offset.unwrap_or(0),
+            is_deleted: entry.is_deleted,
+            is_directory: entry.is_directory,
+            filename: entry.filename,
+            size: entry.size,
+            first_cluster: entry.first_cluster,
+            no_fat_chain: entry.no_fat_chain,
+            created: rfc3339(entry.times.created),
+            modified: rfc3339(entry.times.modified),
+            accessed: rfc3339(entry.times.accessed),
+            suspicious_reasons: entry.suspicious_reasons,
         }
     }
-
-    None
 }
 
-// ═══════════════════════════════════════════════════════════════════════════════
-// FAT CHAIN FOLLOWING
-// ═══════════════════════════════════════════════════════════════════════════════
-
-#[inline]
-fn fat_next_cluster(data: &[u8], params: &ExFatBootParams, cluster: u32) -> u32 {
-    let fat_entry_offset = params.fat_offset + (cluster as u64 * 4);
-    if fat_entry_offset + 4 > data.len() as u64 {
-        return 0xFFFFFFFF;
+impl ExFATEntry {
+    /// The entry for the shared checks (the raw modified timestamp is not kept, 0)
+    fn to_entry(&self) -> ExFatEntry {
+        let time = |value: &Option<String>| {
+            value.as_deref().and_then(|value| chrono::DateTime::parse_from_rfc3339(value).ok())
+        };
+        ExFatEntry {
+            offset: self.offset,
+            data_offset: Some(self.data_offset).filter(|&offset| offset != 0),
+            is_deleted: self.is_deleted,
+            is_directory: self.is_directory,
+            filename: self.filename.clone(),
+            size: self.size,
+            first_cluster: self.first_cluster,
+            no_fat_chain: self.no_fat_chain,
+            modified: 0,
+            times: FileTimestamps {
+                created: time(&self.created),
+                modified: time(&self.modified),
+                accessed: time(&self.accessed),
+            },
+            suspicious_reasons: self.suspicious_reasons.clone(),
+            reconstruction: None,
+        }
     }
-    let off = fat_entry_offset as usize;
-    u32::from_le_bytes(
-        data[off..off + 4].try_into().unwrap_or([0xFF, 0xFF, 0xFF, 0xFF])
-    )
 }
 
-#[inline]
-fn cluster_to_offset(params: &ExFatBootParams, cluster: u32) -> u64 {
-    params.cluster_heap_offset + ((cluster as u64 - 2) * params.cluster_size)
-}
-
-fn extract_file_content(
-    data: &[u8],
-    params: &ExFatBootParams,
-    first_cluster: u32,
-    file_size: u64,
-    no_fat_chain: bool,
-) -> Vec<u8> {
-    if first_cluster < 2 || file_size == 0 {
-        return Vec::new();
+/// `mark_suspicious_entries` over entries handed in from Python
+fn mark_suspicious(data: &[u8], entries: &mut [ExFATEntry], params: &ExFatBootParams) {
+    let mut checked: Vec<ExFatEntry> = entries.iter().map(ExFATEntry::to_entry).collect();
+    mark_suspicious_entries(data, &mut checked, params);
+    for (entry, checked) in entries.iter_mut().zip(checked) {
+        entry.suspicious_reasons = checked.suspicious_reasons;
     }
-
-    let max_extract_size: u64 = 250 * 1024 * 1024; // Increased limit for military grade
-    let actual_size = file_size.min(max_extract_size);
-
-    let mut content = Vec::with_capacity(actual_size as usize);
-    let mut remaining = actual_size;
-    let mut cluster = first_cluster;
-    let mut chain_len = 0u32;
-    let max_chain = params.cluster_count.saturating_add(100);
-
-    while remaining > 0 && cluster >= 2 && cluster < 0xFFFFFFF7 && chain_len < max_chain {
-        let start = cluster_to_offset(params, cluster);
-        let to_read = remaining.min(params.cluster_size);
-        let end = (start + to_read).min(data.len() as u64);
-
-        if start >= data.len() as u64 || start >= end {
-            break;
-        }
-
-        content.extend_from_slice(&data[start as usize..end as usize]);
-        remaining -= to_read;
-        chain_len += 1;
-
-        cluster = if no_fat_chain {
-            cluster + 1
-        } else {
-            fat_next_cluster(data, params, cluster)
-        };
-    }
-
-    content.truncate(actual_size as usize);
-    content
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
-// DIRECTORY ENTRY PARSING
+// ENTRY SCAN
 // ═══════════════════════════════════════════════════════════════════════════════
 
-fn parse_entry_set(data: &[u8], base_offset: u64) -> Option<(ExFATEntry, usize)> {
-    if data.len() < DIRECTORY_ENTRY_SIZE {
-        return None;
-    }
-
-    let file_type = data[0];
-    let is_deleted = file_type == ENTRY_DELETED_FILE;
-
-    if file_type != ENTRY_FILE && file_type != ENTRY_DELETED_FILE {
-        return None;
-    }
-
-    let secondary_count = data[1] as usize;
-    let total_entries = 1 + secondary_count;
-    let total_bytes = total_entries * DIRECTORY_ENTRY_SIZE;
-
-    if data.len() < total_bytes || secondary_count < 2 {
-        return None;
-    }
-
-    let se_offset = DIRECTORY_ENTRY_SIZE;
-    let se_type = data[se_offset];
-    if se_type != ENTRY_STREAM && se_type != ENTRY_DELETED_STREAM {
-        return None;
-    }
-
-    let general_flags = data[se_offset + SE_GENERAL_FLAGS];
-    let no_fat_chain = (general_flags & 0x02) != 0;
-    let name_length = data[se_offset + SE_NAME_LENGTH] as usize;
-
-    let first_cluster = u32::from_le_bytes(
-        data[se_offset + SE_FIRST_CLUSTER..se_offset + SE_FIRST_CLUSTER + 4]
-            .try_into().ok()?
-    );
-    let file_size = u64::from_le_bytes(
-        data[se_offset + SE_DATA_LENGTH..se_offset + SE_DATA_LENGTH + 8]
-            .try_into().ok()?
-    );
-
-    let mut filename = String::with_capacity(name_length);
-    let mut chars_collected = 0;
-
-    for i in 2..total_entries {
-        let fn_offset = i * DIRECTORY_ENTRY_SIZE;
-        if fn_offset + DIRECTORY_ENTRY_SIZE > data.len() {
-            break;
-        }
-
-        let fn_type = data[fn_offset];
-        if fn_type != ENTRY_FILENAME && fn_type != ENTRY_DELETED_FILENAME {
-            break;
-        }
-
-        for j in 0..15 {
-            if chars_collected >= name_length {
-                break;
-            }
-            let char_offset = fn_offset + FN_FILE_NAME + j * 2;
-            if char_offset + 2 > data.len() {
-                break;
-            }
-            let ch = u16::from_le_bytes([data[char_offset], data[char_offset + 1]]);
-            if ch == 0 {
-                break;
-            }
-            if let Some(c) = char::from_u32(ch as u32) {
-                filename.push(c);
-                chars_collected += 1;
-            }
-        }
-    }
-
-    if first_cluster < 2 && file_size > 0 {
-        return None;
-    }
-
-    // Optimization: avoid trim().to_string() allocation if possible, but filename is built from chars.
-    // The previous code had filename.push(c).
-    // Let's just return it. The trimming might be important if there are padding nulls/spaces, 
-    // but usually exFAT filenames are exact.
-    // Spec says they are padded with 0x00 if entry is not full, but we check for ch == 0 break.
-    // So explicit trim shouldn't be needed for standard compliance, but maybe for safety.
-    // We'll keep it simple for now as requested.
-
-    Some((ExFATEntry {
-        offset: base_offset,
-        data_offset: 0,
-        is_deleted,
-        filename, // Removed trim().to_string()
-        size: file_size,
-        first_cluster,
-        no_fat_chain,
-    }, total_entries))
-}
-
 /// Military Grade optimized scanner with early exit for zero blocks and Hot-Stream analysis
 fn scan_for_entries_impl(
     data: &[u8], 
     base_offset: u64,
     matcher: &mut EnhancedMatcher,
-) -> (Vec<ExFATEntry>, Vec<EnrichedLink>) {
+) -> (Vec<ExFatEntry>, Vec<EnrichedLink>) {
     let mut entries = Vec::new();
     let mut links = Vec::new();
     let mut pos = 0;
@@ -400,7 +180,7 @@ fn scan_for_entries_impl(
                 // scan_chunk(data, base_offset, deduplicate)
                 let found_links = matcher.scan_chunk(
                     window, 
-                    (base_offset + pos as u64) as usize, 
+                    base_offset + pos as u64, 
                     true
                 );
                 
@@ -480,7 +260,7 @@ impl RustExFATScanner {
         let matcher_arc = self.matcher.clone();
         
         // Parallel scan
-        let (all_entries, all_links): (Vec<ExFATEntry>, Vec<EnrichedLink>) = py.allow_threads(|| {
+        let (all_entries, all_links): (Vec<ExFatEntry>, Vec<EnrichedLink>) = py.allow_threads(|| {
             (0..num_chunks)
                 .into_par_iter()
                 .map(|chunk_idx| {
@@ -496,11 +276,7 @@ impl RustExFATScanner {
 
                     if let Ok(guard) = boot_params_lock.read() {
                         if let Some(ref params) = *guard {
-                            for entry in &mut entries {
-                                if entry.first_cluster >= 2 {
-                                    entry.data_offset = cluster_to_offset(params, entry.first_cluster);
-                                }
-                            }
+                            populate_data_offsets(&mut entries, params);
                         }
                     }
 
@@ -526,8 +302,14 @@ impl RustExFATScanner {
         
         sorted_entries.sort_by_key(|e| e.offset);
         sorted_links.sort_by_key(|l| l.offset);
+
+        if let Ok(guard) = self.boot_params.read() {
+            if let Some(ref params) = *guard {
+                mark_suspicious_entries(data, &mut sorted_entries, params);
+            }
+        }
         
-        Ok((sorted_entries, sorted_links))
+        Ok((sorted_entries.into_iter().map(ExFATEntry::from).collect(), sorted_links))
     }
 
     pub fn extract_file(
@@ -554,6 +336,76 @@ impl RustExFATScanner {
         Ok(PyBytes::new(py, &content).into())
     }
 
+    /// Streamed variant of `extract_file` writing to `output_path` without the
+    /// 250 MB cap, the GIL released; returns bytes written
+    pub fn extract_file_to_path(
+        &self,
+        py: Python,
+        image_path: &str,
+        first_cluster: u32,
+        size: u64,
+        no_fat_chain: bool,
+        output_path: &str,
+    ) -> PyResult<u64> {
+        let file = File::open(image_path)
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Cannot open {}: {}", image_path, e)))?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        let data = mmap.as_ref();
+
+        let params = find_boot_sector(data)
+            .ok_or_else(|| pyo3::exceptions::PyValueError::new_err("exFAT boot sector not found in image"))?;
+
+        py.allow_threads(|| {
+            let mut writer = BufWriter::new(File::create(output_path)?);
+            extract_file_to_writer(data, &params, first_cluster, size, no_fat_chain, &mut writer)
+        })
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Cannot write {}: {}", output_path, e)))
+    }
+
+    /// Streamed variant of `extract_original_file`, the output keeping the
+    /// entry's modified and accessed times; returns (filename, bytes written)
+    pub fn extract_original_file_to_path(
+        &self,
+        py: Python,
+        image_path: &str,
+        entry_offset: u64,
+        output_path: &str,
+    ) -> PyResult<(String, u64)> {
+        let file = File::open(image_path)
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Cannot open {}: {}", image_path, e)))?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        let data = mmap.as_ref();
+
+        let params = find_boot_sector(data)
+            .ok_or_else(|| pyo3::exceptions::PyValueError::new_err("exFAT boot sector not found in image"))?;
+
+        let off = entry_offset as usize;
+        if off + 96 > data.len() {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                format!("Entry offset 0x{:X} is beyond image size", entry_offset)
+            ));
+        }
+
+        let (mut entry, _consumed) = parse_entry_set(&data[off..], entry_offset)
+            .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(
+                format!("No valid exFAT entry set at offset 0x{:X}", entry_offset)
+            ))?;
+
+        mark_suspicious_entries(data, std::slice::from_mut(&mut entry), &params);
+        if !entry.suspicious_reasons.is_empty() {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                format!("Suspicious entry at 0x{:X}: {}", entry_offset, entry.suspicious_reasons.join("; "))
+            ));
+        }
+
+        let written = py
+            .allow_threads(|| extract_entry_to_path(data, &params, &entry, Path::new(output_path)))
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Cannot write {}: {}", output_path, e)))?;
+        entry.times.apply(Path::new(output_path))
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Cannot set the times of {}: {}", output_path, e)))?;
+        Ok((entry.filename.clone(), written))
+    }
+
     pub fn extract_original_file(
         &self, 
         py: Python, 
@@ -575,7 +427,7 @@ impl RustExFATScanner {
             ));
         }
 
-        let (entry, _consumed) = parse_entry_set(&data[off..], entry_offset)
+        let (mut entry, _consumed) = parse_entry_set(&data[off..], entry_offset)
             .ok_or_else(|| pyo3::exceptions::PyValueError::new_err(
                 format!("No valid exFAT entry set at offset 0x{:X}", entry_offset)
             ))?;
@@ -586,6 +438,13 @@ impl RustExFATScanner {
             ));
         }
 
+        mark_suspicious_entries(data, std::slice::from_mut(&mut entry), &params);
+        if !entry.suspicious_reasons.is_empty() {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                format!("Suspicious entry at 0x{:X}: {}", entry_offset, entry.suspicious_reasons.join("; "))
+            ));
+        }
+
         let content = extract_file_content(data, &params, entry.first_cluster, entry.size, entry.no_fat_chain);
         Ok((entry.filename.clone(), PyBytes::new(py, &content).into()))
     }
@@ -594,7 +453,7 @@ impl RustExFATScanner {
         &self,
         py: Python,
         image_path: &str,
-        entries: Vec<ExFATEntry>,
+        mut entries: Vec<ExFATEntry>,
     ) -> PyResult<Vec<(String, PyObject, u64, bool)>> {
         let file = File::open(image_path)
             .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Cannot open {}: {}", image_path, e)))?;
@@ -606,9 +465,12 @@ impl RustExFATScanner {
             None => return Ok(Vec::new()),
         };
 
+        // Re-check against this image: entries may come from another scan or be built in Python
+        mark_suspicious(data, &mut entries, &params);
+
         let mut results = Vec::new();
         for entry in &entries {
-            if entry.first_cluster < 2 || entry.size == 0 {
+            if entry.first_cluster < 2 || entry.size == 0 || !entry.suspicious_reasons.is_empty() {
                 continue;
             }
 
@@ -647,6 +509,36 @@ impl RustExFATScanner {
         Ok(results)
     }
 
+    /// Recover the files of the directory tree into `output_dir` under their
+    /// original paths instead of one flat list like `extract_all_files`:
+    /// the walk starts at the root directory's cluster chain and follows
+    /// live and (unless `include_deleted` is false) deleted directories.
+    /// Returns (relative path, bytes written, is_deleted) per written file
+    #[pyo3(signature = (image_path, output_dir, include_deleted = true))]
+    pub fn extract_tree(
+        &self,
+        py: Python,
+        image_path: &str,
+        output_dir: &str,
+        include_deleted: bool,
+    ) -> PyResult<Vec<(String, u64, bool)>> {
+        let file = File::open(image_path)
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Cannot open {}: {}", image_path, e)))?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        let data = mmap.as_ref();
+
+        let params = find_boot_sector(data)
+            .ok_or_else(|| pyo3::exceptions::PyValueError::new_err("exFAT boot sector not found in image"))?;
+
+        let written = py
+            .allow_threads(|| extract_tree_to(data, &params, Path::new(output_dir), include_deleted))
+            .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Cannot write to {}: {}", output_dir, e)))?;
+        Ok(written
+            .into_iter()
+            .map(|(path, bytes, is_deleted)| (path.to_string_lossy().into_owned(), bytes, is_deleted))
+            .collect())
+    }
+
     pub fn get_boot_info(&self, py: Python, image_path: &str) -> PyResult<PyObject> {
         let file = File::open(image_path)
             .map_err(|e| pyo3::exceptions::PyIOError::new_err(format!("Cannot open {}: {}", image_path, e)))?;
@@ -679,8 +571,6 @@ impl RustExFATScanner {
         // We create a temp matcher
         let mut matcher = EnhancedMatcher::new();
         let (entries, _) = scan_for_entries_impl(data, base_offset, &mut matcher);
-        Ok(entries)
+        Ok(entries.into_iter().map(ExFATEntry::from).collect())
     }
 }
-
-
@@ -1,19 +1,27 @@
 use pyo3::prelude::*;
 use crate::matcher::EnhancedMatcher;
 use crate::scanner::parallel::ParallelScanner;
-use crate::types::{ScanConfig, HotFragment};
+use crate::sink::{EventSink, SinkEvent};
+use crate::stream::ScanEventStream;
+use crate::types::{ScanConfig, ScanResult, HotFragment, Offset};
+use std::collections::HashSet;
 use std::path::PathBuf;
-use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
 use std::thread;
 use std::time::Duration;
 use std::sync::mpsc;
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, warn};
 
+pub mod checkpoint;
 pub mod matcher;
 pub mod scanner;
 pub mod types;
 pub mod exfat;
 pub mod fragment_linker;
-pub mod simd_search;
+pub use recovery_core::simd_search;
+pub mod sink;
+pub mod stream;
 
 #[pyclass]
 struct RustPatternMatcher {
@@ -29,7 +37,7 @@ impl RustPatternMatcher {
         }
     }
 
-    fn scan_chunk(&mut self, py: Python, data: &[u8], offset: usize, deduplicate: bool) -> PyResult<Vec<PyObject>> {
+    fn scan_chunk(&mut self, py: Python, data: &[u8], offset: u64, deduplicate: bool) -> PyResult<Vec<PyObject>> {
         let results = self.matcher.scan_chunk(data, offset, deduplicate);
         
         let mut py_results = Vec::with_capacity(results.len());
@@ -38,9 +46,10 @@ impl RustPatternMatcher {
             dict.set_item("url", link.url)?;
             dict.set_item("video_id", link.video_id)?;
             dict.set_item("title", link.title)?;
-            dict.set_item("offset", link.offset)?;
+            dict.set_item("offset", link.offset.as_u64())?;
             dict.set_item("pattern_name", link.pattern_name)?;
             dict.set_item("confidence", link.confidence)?;
+            dict.set_item("reasons", link.reasons)?;
             py_results.push(dict.to_object(py));
         }
         Ok(py_results)
@@ -49,43 +58,82 @@ impl RustPatternMatcher {
 
 #[pyclass]
 struct RustParallelScanner {
-    scanner: ParallelScanner,
+    /// Shared with the threads of `scan_events`
+    scanner: Arc<ParallelScanner>,
+    /// Unix socket receiving events as JSON lines instead of the callbacks
+    event_socket: Option<PathBuf>,
+    /// File descriptor receiving events as JSON lines (duplicated per scan)
+    event_fd: Option<i32>,
+    /// Video IDs reported before a resume (`set_seen_ids`), left out of the results
+    seen_ids: Arc<HashSet<String>>,
 }
 
 #[pymethods]
 impl RustParallelScanner {
     #[new]
-    #[pyo3(signature = (num_threads=0, chunk_size_mb=256, overlap_kb=64, deduplicate=true, min_confidence=0.1))]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        num_threads=0, chunk_size_mb=256, overlap_kb=64, deduplicate=true, min_confidence=0.1,
+        event_socket=None, event_fd=None, coarse_stride=0, heatmap_region_kb=1024
+    ))]
     fn new(
         num_threads: usize, 
         chunk_size_mb: usize, 
         overlap_kb: usize, 
         deduplicate: bool, 
-        min_confidence: f32
-    ) -> Self {
+        min_confidence: f32,
+        event_socket: Option<PathBuf>,
+        event_fd: Option<i32>,
+        coarse_stride: usize,
+        heatmap_region_kb: usize,
+    ) -> PyResult<Self> {
+        if event_socket.is_some() && event_fd.is_some() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "event_socket and event_fd are mutually exclusive",
+            ));
+        }
         let config = ScanConfig {
             num_threads,
             chunk_size: chunk_size_mb * 1024 * 1024,
             overlap_size: overlap_kb * 1024,
             deduplicate,
             min_confidence,
+            coarse_stride,
+            heatmap_region_size: heatmap_region_kb.max(1) * 1024,
         };
-        RustParallelScanner {
-            scanner: ParallelScanner::new(config),
-        }
+        Ok(RustParallelScanner {
+            scanner: Arc::new(ParallelScanner::new(config)),
+            event_socket,
+            event_fd,
+            seen_ids: Arc::default(),
+        })
     }
 
     fn scan_streaming(
         &self, 
         py: Python, 
         path: String, 
-        start_offset: usize, 
+        start_offset: u64, 
         reverse: bool,
         progress_cb: Option<PyObject>,
         hot_fragment_cb: Option<PyObject>
     ) -> PyResult<PyObject> {
         let path_buf = PathBuf::from(path);
-        let progress = Arc::new(AtomicUsize::new(0));
+
+        if let Some(sink) = self.open_sink()? {
+            if progress_cb.is_some() || hot_fragment_cb.is_some() {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    "callbacks cannot be combined with event_socket/event_fd",
+                ));
+            }
+            // Events go straight to the sink: the GIL is released for the whole scan
+            let result = py
+                .allow_threads(|| self.scan_to_sink(&sink, &path_buf, Offset::new(start_offset), reverse))
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+            return scan_result_to_dict(py, &result);
+        }
+
+        let progress = Arc::new(AtomicU64::new(0));
         let (tx, rx) = mpsc::channel::<HotFragment>();
         let p_clone = progress.clone();
         
@@ -95,14 +143,14 @@ impl RustParallelScanner {
         thread::scope(|s| {
             let handle = s.spawn(|| {
                 let p_cb = |len: usize| {
-                    p_clone.fetch_add(len, Ordering::Release);
+                    p_clone.fetch_add(len as u64, Ordering::Release);
                 };
                 let h_cb = |frag: HotFragment| {
                     let _ = tx.send(frag);
                 };
                 self.scanner.scan_file_streaming(
                     &path_buf,
-                    start_offset,
+                    Offset::new(start_offset),
                     reverse,
                     Some(&p_cb),
                     Some(&h_cb)
@@ -110,20 +158,21 @@ impl RustParallelScanner {
             });
 
             let mut last_reported = 0;
+            let mut last_logged_gb = 0;
             // Loop until thread is finished OR channel is not empty
             while !handle.is_finished() || rx.try_recv().is_ok() {
                 // Process ALL available fragments to avoid race condition
                 while let Ok(frag) = rx.try_recv() {
                     if let Some(ref cb) = hot_fragment_cb {
                        let dict = pyo3::types::PyDict::new(py);
-                       let _ = dict.set_item("offset", frag.offset);
-                       let _ = dict.set_item("size", frag.size);
+                       let _ = dict.set_item("offset", frag.offset.as_u64());
+                       let _ = dict.set_item("size", frag.size.as_u64());
                        let _ = dict.set_item("youtube_count", frag.youtube_count);
                        let _ = dict.set_item("confidence", frag.target_score / 10.0);
                        let _ = dict.set_item("score", frag.target_score);
                        let _ = dict.set_item("file_type", frag.file_type_guess);
                        if let Err(e) = cb.call1(py, (dict,)) {
-                           eprintln!("Error in hot fragment callback: {}", e);
+                           warn!(offset = frag.offset.as_u64(), "Error in hot fragment callback: {}", e);
                        }
                     }
                 }
@@ -132,21 +181,20 @@ impl RustParallelScanner {
                 if current > last_reported + (5 * 1024 * 1024) {
                     if let Some(ref cb) = progress_cb {
                         if let Err(e) = cb.call1(py, (current,)) {
-                             eprintln!("Error in progress callback: {}", e);
+                             warn!("Error in progress callback: {}", e);
                         }
                     }
                     last_reported = current;
                 }
                 
-                // Debug logging every 1GB or 5 seconds to track liveness
-                let current_mb = current / 1024 / 1024;
-                if current_mb % 1024 == 0 && current_mb > 0 {
-                     eprintln!("[RUST DEBUG] Scanned {} MB", current_mb);
+                // Debug logging every 1GB to track liveness
+                let current_gb = current >> 30;
+                if current_gb > last_logged_gb {
+                    debug!(position = current, "Scanned {} MB", current >> 20);
+                    last_logged_gb = current_gb;
                 }
 
                 if !handle.is_finished() {
-                     // Check if we are stuck?
-                     // eprintln!("[RUST DEBUG] Waiting for thread...");
                      py.allow_threads(|| {
                         thread::sleep(Duration::from_millis(20));
                     });
@@ -168,33 +216,126 @@ impl RustParallelScanner {
             return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(err));
         }
         
-        let result = scan_result.unwrap();
-        let dict = pyo3::types::PyDict::new(py);
-        let links_list = pyo3::types::PyList::new(py, result.links.iter().map(|link| {
-            let d = pyo3::types::PyDict::new(py);
-            let _ = d.set_item("url", &link.url);
-            let _ = d.set_item("video_id", &link.video_id);
-            let _ = d.set_item("title", &link.title);
-            let _ = d.set_item("offset", link.offset);
-            let _ = d.set_item("pattern_name", &link.pattern_name);
-            let _ = d.set_item("confidence", link.confidence);
-            d
-        }));
-        
-        dict.set_item("links", links_list)?;
-        dict.set_item("bytes_scanned", result.bytes_scanned)?;
-        dict.set_item("duration_secs", result.duration_secs)?;
-            
-        Ok(dict.to_object(py))
+        let mut scan_result = scan_result.unwrap();
+        self.drop_seen_links(&mut scan_result);
+        scan_result_to_dict(py, &scan_result)
+    }
+
+    /// Dedup cache snapshot of a checkpoint (`RustCheckpointManager`): links
+    /// with these video IDs are not reported again while `deduplicate` is on
+    fn set_seen_ids(&mut self, seen_ids: Vec<String>) {
+        self.seen_ids = Arc::new(seen_ids.into_iter().collect());
+    }
+
+    /// Scan on a background thread; the events are consumed with `for` or
+    /// `async for` (see `stream`)
+    #[pyo3(signature = (path, start_offset=0, reverse=false))]
+    fn scan_events(&self, path: String, start_offset: u64, reverse: bool) -> PyResult<ScanEventStream> {
+        if self.event_socket.is_some() || self.event_fd.is_some() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "scan_events cannot be combined with event_socket/event_fd",
+            ));
+        }
+        let seen_ids = self.scanner.config().deduplicate.then(|| Arc::clone(&self.seen_ids));
+        let (scanner, path) = (Arc::clone(&self.scanner), PathBuf::from(path));
+        Ok(ScanEventStream::start(scanner, path, Offset::new(start_offset), reverse, seen_ids))
     }
 }
 
+impl RustParallelScanner {
+    fn drop_seen_links(&self, result: &mut ScanResult) {
+        if self.scanner.config().deduplicate {
+            drop_seen_links(result, &self.seen_ids);
+        }
+    }
+
+    /// Sink configured at construction, connected/duplicated for this scan
+    fn open_sink(&self) -> PyResult<Option<EventSink>> {
+        let sink = match (&self.event_socket, self.event_fd) {
+            (Some(path), _) => EventSink::connect_unix(path),
+            (None, Some(fd)) => EventSink::from_fd(fd),
+            (None, None) => return Ok(None),
+        };
+        sink.map(Some).map_err(|e| PyErr::new::<pyo3::exceptions::PyOSError, _>(e.to_string()))
+    }
+
+    fn scan_to_sink(
+        &self,
+        sink: &EventSink,
+        path: &std::path::Path,
+        start_offset: Offset,
+        reverse: bool,
+    ) -> anyhow::Result<ScanResult> {
+        let scanned = AtomicU64::new(0);
+        let p_cb = |len: usize| {
+            let total = scanned.fetch_add(len as u64, Ordering::AcqRel) + len as u64;
+            sink.emit(&SinkEvent::Progress { bytes_scanned: total });
+        };
+        let h_cb = |frag: HotFragment| sink.emit(&SinkEvent::hot_fragment(&frag));
+
+        let mut result = self.scanner.scan_file_streaming(path, start_offset, reverse, Some(&p_cb), Some(&h_cb))?;
+        self.drop_seen_links(&mut result);
+        sink.emit(&SinkEvent::Done {
+            bytes_scanned: result.bytes_scanned,
+            duration_secs: result.duration_secs,
+            links: result.links.len(),
+        });
+        Ok(result)
+    }
+}
+
+/// Leave out links whose video ID a resumed scan already reported
+pub(crate) fn drop_seen_links(result: &mut ScanResult, seen_ids: &HashSet<String>) {
+    if !seen_ids.is_empty() {
+        result.links.retain(|link| !seen_ids.contains(&link.video_id));
+    }
+}
+
+pub(crate) fn scan_result_to_dict(py: Python, result: &ScanResult) -> PyResult<PyObject> {
+    let dict = pyo3::types::PyDict::new(py);
+    let links_list = pyo3::types::PyList::new(py, result.links.iter().map(|link| {
+        let d = pyo3::types::PyDict::new(py);
+        let _ = d.set_item("url", &link.url);
+        let _ = d.set_item("video_id", &link.video_id);
+        let _ = d.set_item("title", &link.title);
+        let _ = d.set_item("offset", link.offset.as_u64());
+        let _ = d.set_item("pattern_name", &link.pattern_name);
+        let _ = d.set_item("confidence", link.confidence);
+        let _ = d.set_item("reasons", &link.reasons);
+        d
+    }));
+
+    dict.set_item("links", links_list)?;
+    dict.set_item("bytes_scanned", result.bytes_scanned.as_u64())?;
+    dict.set_item("duration_secs", result.duration_secs)?;
+    dict.set_item("cold_bytes_skipped", result.cold_bytes_skipped.as_u64())?;
+
+    Ok(dict.to_object(py))
+}
+
 pub mod clusterer;
 
+/// Environment variable setting the level of the extension's log (default: warn)
+const LOG_LEVEL_ENV: &str = "RUST_ACCELERATOR_LOG";
+
+/// Send `tracing` events to stderr at `$RUST_ACCELERATOR_LOG`; a subscriber
+/// installed earlier in the process is kept
+fn init_logging() {
+    let level = std::env::var(LOG_LEVEL_ENV)
+        .ok()
+        .and_then(|name| name.parse::<LevelFilter>().ok())
+        .unwrap_or(LevelFilter::WARN);
+    let subscriber = tracing_subscriber::fmt().with_writer(std::io::stderr).with_max_level(level).finish();
+    let _ = tracing::subscriber::set_global_default(subscriber);
+}
+
 #[pymodule]
 fn rust_accelerator(_py: Python, m: &PyModule) -> PyResult<()> {
+    init_logging();
     m.add_class::<RustPatternMatcher>()?;
     m.add_class::<RustParallelScanner>()?;
+    m.add_class::<ScanEventStream>()?;
+    m.add_class::<checkpoint::RustCheckpointManager>()?;
     m.add_class::<exfat::RustExFATScanner>()?;
     m.add_class::<exfat::ExFATEntry>()?;
     m.add_class::<fragment_linker::RustFragmentLinker>()?;
@@ -1,15 +1,13 @@
 use crate::matcher::patterns::{YOUTUBE_PATTERNS, TITLE_PATTERNS};
 use crate::matcher::validator::is_valid_video_id;
-use crate::types::EnrichedLink;
+use crate::types::{EnrichedLink, Offset};
 use ahash::AHashSet;
 use regex::bytes::Regex;
 use regex::bytes::RegexSet;
-use regex::bytes::RegexSetBuilder;
 use std::sync::Arc;
 use html_escape::decode_html_entities;
 
-pub mod patterns;
-pub mod validator;
+pub use recovery_core::matcher::{cache, context, patterns, profile, validator};
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // FORENSIC DETECTION v6.1 - SIMD Optimized + SHA-256
@@ -167,8 +165,9 @@ pub struct EnhancedMatcher {
     /// RegexSet for fast pre-filtering (Arc for cheap cloning)
     pattern_set: Arc<RegexSet>,
     
-    /// For thread-local deduplication
-    seen_ids: AHashSet<[u8; 11]>,
+    /// Video IDs already reported and their offsets (thread-local): the same
+    /// occurrence found by several needle windows or patterns is kept once
+    seen_ids: AHashSet<([u8; 11], u64)>,
 }
 
 // Safety: EnhancedMatcher is Sync because:
@@ -179,20 +178,10 @@ pub struct EnhancedMatcher {
 unsafe impl Sync for EnhancedMatcher {}
 
 impl EnhancedMatcher {
-    /// Create a new matcher (compiles regex - call once, then clone)
+    /// Create a new matcher (pattern set is shared, see `cache`)
     pub fn new() -> Self {
-        // Create RegexSet from all patterns
-        let pattern_strings: Vec<_> = YOUTUBE_PATTERNS
-            .iter()
-            .map(|p| p.regex.as_str())
-            .collect();
-        
-        let pattern_set = Arc::new(
-            RegexSetBuilder::new(&pattern_strings)
-                .size_limit(50 * 1024 * 1024)  // 50 MB
-                .build()
-                .expect("Failed to compile pattern set")
-        );
+        // Pre-filter set is compiled once per process from the warm-start cache
+        let pattern_set = cache::shared_prefilter();
 
         // Create a fast pre-filter regex for "needles" (common substrings)
         // This is much faster than running the full RegexSet on every byte
@@ -219,7 +208,7 @@ impl EnhancedMatcher {
     pub fn scan_chunk(
         &mut self,
         data: &[u8],
-        base_offset: usize,
+        base_offset: u64,
         deduplicate: bool,
     ) -> Vec<EnrichedLink> {
         let mut results = Vec::new();
@@ -253,8 +242,8 @@ impl EnhancedMatcher {
                 
                 for cap in pattern.regex.captures_iter(window_data) {
                      // Extract video ID
-                    let video_id_bytes = match cap.get(1) {
-                        Some(m) => m.as_bytes(),
+                    let (video_id_bytes, video_id_at) = match cap.get(1) {
+                        Some(m) => (m.as_bytes(), base_offset + (window_start + m.start()) as u64),
                         None => continue,
                     };
                     
@@ -263,12 +252,12 @@ impl EnhancedMatcher {
                         continue;
                     }
                     
-                    // Deduplicate
+                    // Deduplicate occurrences; the same ID elsewhere is a new link
                     if deduplicate {
                         let mut id_array = [0u8; 11];
                         id_array.copy_from_slice(video_id_bytes);
                         
-                        if !self.seen_ids.insert(id_array) {
+                        if !self.seen_ids.insert((id_array, video_id_at)) {
                             continue; // Already seen
                         }
                     }
@@ -284,22 +273,25 @@ impl EnhancedMatcher {
                     // Calculate absolute offset
                     // window_start is offset into 'data'
                     // full_match.start() is offset into 'window_data'
-                    let abs_offset = base_offset + window_start + full_match.start();
+                    let abs_offset = base_offset + (window_start + full_match.start()) as u64;
                     
-                    // Confidence
-                    let confidence = (pattern.priority as f32) / 10.0;
+                    // Confidence: pattern priority adjusted by surrounding structure
+                    let match_pos = window_start + full_match.start();
+                    let context = context::score_link_context(data, match_pos, match_pos + url_bytes.len());
+                    let confidence = context.adjust((pattern.priority as f32) / 10.0);
                     
                     let mut link = EnrichedLink::new(
                         url,
                         video_id,
-                        abs_offset,
+                        Offset::new(abs_offset),
                         pattern.name.to_string(),
                         confidence,
                     );
                     
+                    link.reasons = context.reasons;
+
                     // Extract title from context (using larger context from original data if needed)
                     // We can use 'data' directly since we have the index
-                    let match_pos = window_start + full_match.start();
                     link.title = self.extract_title_from_context(
                         data,
                         match_pos,
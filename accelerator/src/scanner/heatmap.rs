@@ -0,0 +1,102 @@
+// Coarse heatmap pass (two-phase scan)
+//
+// Phase 1 samples every Nth 32-byte block with scan_block_simd and counts
+// hot bytes (y, h, {, v, /) in the non-empty sampled blocks of each region.
+// Phase 2 runs the regex matcher only on regions whose density reaches
+// Epicenter::DEEP_SCAN_THRESHOLD, so empty areas of a large image are never
+// deep scanned. A single link is dense enough: with stride 1 no region
+// holding one is skipped, larger strides trade that for speed.
+
+use crate::simd_search::scan_block_simd;
+use crate::types::{Epicenter, Offset};
+use rayon::prelude::*;
+
+/// Block size of scan_block_simd
+const BLOCK_SIZE: usize = 32;
+
+/// Default heatmap region (one Epicenter per region)
+pub const DEFAULT_REGION_SIZE: usize = 1024 * 1024;
+
+/// Sample every `stride`-th block of each `region_size` region of
+/// `data[start..]` and return one Epicenter per region (density = hot bytes
+/// per MB of non-empty sampled blocks)
+pub fn build_heatmap(data: &[u8], start: usize, region_size: usize, stride: usize) -> Vec<Epicenter> {
+    let region_size = region_size.max(BLOCK_SIZE);
+    let stride = stride.max(1);
+    if start >= data.len() {
+        return Vec::new();
+    }
+
+    data[start..]
+        .par_chunks(region_size)
+        .enumerate()
+        .map(|(i, region)| {
+            let mut hot_bytes = 0u64;
+            let mut sampled = 0u64;
+            let mut blocks = region.chunks_exact(BLOCK_SIZE);
+            for block in blocks.by_ref().step_by(stride) {
+                let res = scan_block_simd(block);
+                if !res.is_empty {
+                    hot_bytes += res.hot_mask.count_ones() as u64;
+                    sampled += BLOCK_SIZE as u64;
+                }
+            }
+            // The sub-block tail of the data is too short to sample
+            let density = if !blocks.remainder().iter().all(|&b| b == 0) {
+                Epicenter::DEEP_SCAN_THRESHOLD
+            } else if sampled == 0 {
+                0.0
+            } else {
+                (hot_bytes as f64 * (1024.0 * 1024.0) / sampled as f64) as f32
+            };
+            Epicenter::new(Offset::new((start + i * region_size) as u64), density)
+        })
+        .collect()
+}
+
+/// Merge adjacent regions that need a deep scan into `(start, end)` byte ranges
+pub fn hot_ranges(epicenters: &[Epicenter], region_size: usize, data_len: usize) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for epicenter in epicenters.iter().filter(|e| e.needs_deep_scan) {
+        let Some(start) = epicenter.offset.as_usize() else { continue };
+        let end = start.saturating_add(region_size).min(data_len);
+        match ranges.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heatmap_skips_empty_regions() {
+        let region = 4096;
+        let mut data = vec![0u8; 8 * region];
+        // Text in regions 2 and 3 (adjacent) and 6
+        for (i, chunk) in data[2 * region..4 * region].chunks_mut(64).enumerate() {
+            let line = format!("{{\"url\":\"https://youtube.com/watch?v={:011}\"}}", i);
+            chunk[..line.len()].copy_from_slice(line.as_bytes());
+        }
+        data[6 * region..7 * region].fill(b'y');
+        // A lone link in a sampled block of an otherwise empty region
+        let link = b"youtu.be/dQw4w9WgXcQ";
+        data[5 * region + 64..5 * region + 64 + link.len()].copy_from_slice(link);
+
+        let epicenters = build_heatmap(&data, 0, region, 2);
+        assert_eq!(epicenters.len(), 8);
+        let hot: Vec<bool> = epicenters.iter().map(|e| e.needs_deep_scan).collect();
+        assert_eq!(hot, vec![false, false, true, true, false, true, true, false]);
+
+        let ranges = hot_ranges(&epicenters, region, data.len());
+        assert_eq!(ranges, vec![(2 * region, 4 * region), (5 * region, 7 * region)]);
+
+        // Offsets stay absolute when the scan starts inside the data
+        let tail = build_heatmap(&data, 6 * region, region, 1);
+        assert_eq!(tail[0].offset, Offset::new((6 * region) as u64));
+        assert!(tail[0].needs_deep_scan && !tail[1].needs_deep_scan);
+    }
+}
@@ -1 +1,2 @@
+pub mod heatmap;
 pub mod parallel;
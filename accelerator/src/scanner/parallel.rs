@@ -1,12 +1,14 @@
 use crate::matcher::EnhancedMatcher;
-use crate::types::{EnrichedLink, ScanConfig, ScanResult};
+use crate::scanner::heatmap;
+use crate::types::{EnrichedLink, Offset, ScanConfig, ScanResult, Size};
 use anyhow::{Context, Result};
 use memmap2::MmapOptions;
 use rayon::prelude::*;
 use std::fs::File;
 use std::path::Path;
 use std::time::Instant;
-use std::collections::HashMap;
+use tracing::{debug, warn};
+use recovery_core::overlap::{suppress_duplicates, OverlapWindows};
 
 /// Parallel file scanner with pre-compiled regex patterns
 pub struct ParallelScanner {
@@ -29,6 +31,10 @@ impl ParallelScanner {
         
         Self { config, matcher_template }
     }
+
+    pub fn config(&self) -> &ScanConfig {
+        &self.config
+    }
     
     /// Scan a file path with progress callback
     pub fn scan_file<F>(&self, path: &Path, progress_cb: Option<&F>) -> Result<ScanResult> 
@@ -43,7 +49,7 @@ impl ParallelScanner {
             .with_context(|| format!("Failed to open file: {:?}", path))?;
             
         let metadata = file.metadata()?;
-        let file_size = metadata.len() as usize;
+        let file_size = metadata.len();
         
         if file_size == 0 {
             return Ok(ScanResult::default());
@@ -59,7 +65,7 @@ impl ParallelScanner {
         };
         
         // Create chunks
-        let chunks = self.create_chunks(&mmap, 0);
+        let chunks = self.create_chunks(&mmap, Offset::new(0));
         
         // Parallel scan with pre-compiled matcher (cloned per thread)
         // Uses catch_unwind for crash isolation on corrupted data (v6.1 Forensic)
@@ -78,7 +84,7 @@ impl ParallelScanner {
                     let mut matcher = matcher_template.clone_fresh();
                     matcher.scan_chunk(
                         chunk_data,
-                        *offset,
+                        offset.as_u64(),
                         self.config.deduplicate,
                     )
                 }));
@@ -87,7 +93,7 @@ impl ParallelScanner {
                     Ok(links) => Some(links),
                     Err(_) => {
                         // Corrupted sector - skip silently (forensic: log offset)
-                        eprintln!("[WARN] Corrupted sector at offset 0x{:X}, skipping", offset);
+                        warn!(offset = offset.as_u64(), "Corrupted sector at offset 0x{:X}, skipping", offset);
                         Some(Vec::new())
                     }
                 }
@@ -100,9 +106,9 @@ impl ParallelScanner {
             .flatten()
             .collect();
             
-        // Global deduplication and merging
+        // Links found twice because two chunks read them; repeats elsewhere stay
         if self.config.deduplicate {
-            self.deduplicate_links(&mut links);
+            Self::suppress_overlap_duplicates(&mut links, &chunk_windows(&chunks));
         }
         
         // Filter by confidence
@@ -117,8 +123,9 @@ impl ParallelScanner {
         
         Ok(ScanResult {
             links,
-            bytes_scanned: file_size,
+            bytes_scanned: Size::new(file_size),
             duration_secs: duration.as_secs_f64(),
+            cold_bytes_skipped: Size::default(),
         })
     }
     
@@ -126,15 +133,28 @@ impl ParallelScanner {
     fn create_chunks<'a>(
         &self,
         data: &'a [u8],
-        start_offset: usize,
-    ) -> Vec<(&'a [u8], usize)> {
+        start_offset: Offset,
+    ) -> Vec<(&'a [u8], Offset)> {
+        // Offsets past the mapped data (or past usize on 32-bit) yield no chunks
+        let start = start_offset.as_usize().unwrap_or(usize::MAX);
+        self.create_chunks_in(data, start, data.len())
+    }
+
+    /// Create overlapping chunks starting in `data[start..end]`; the last
+    /// chunk still reads the overlap past `end`
+    fn create_chunks_in<'a>(
+        &self,
+        data: &'a [u8],
+        start: usize,
+        end: usize,
+    ) -> Vec<(&'a [u8], Offset)> {
         let chunk_size = self.config.chunk_size;
         let overlap = self.config.overlap_size;
         
         let mut chunks = Vec::new();
-        let mut offset = start_offset;
+        let mut offset = start;
         
-        while offset < data.len() {
+        while offset < end {
             let chunk_end = offset
                 .saturating_add(chunk_size)
                 .saturating_add(overlap)
@@ -143,7 +163,7 @@ impl ParallelScanner {
             // Ensure we don't create empty chunks at the very end
             if offset < chunk_end {
                 let chunk_data = &data[offset..chunk_end];
-                chunks.push((chunk_data, offset));
+                chunks.push((chunk_data, Offset::new(offset as u64)));
             }
             
             // Advance by chunk_size
@@ -154,24 +174,18 @@ impl ParallelScanner {
         chunks
     }
     
-    /// Deduplicate links, keeping the best version of each
-    fn deduplicate_links(&self, links: &mut Vec<EnrichedLink>) {
-        let mut best_links: HashMap<String, EnrichedLink> = HashMap::new();
-        
-        for link in links.drain(..) {
-            let video_id = link.video_id.clone();
-            
-            best_links
-                .entry(video_id)
-                .and_modify(|existing| {
-                    if Self::is_better_link(&link, existing) {
-                        *existing = link.clone();
-                    }
-                })
-                .or_insert(link);
-        }
-        
-        links.extend(best_links.into_values());
+    /// Keep the best report of each link found by two chunks in their
+    /// overlap, identified by the video ID and the ID's offset
+    fn suppress_overlap_duplicates(links: &mut Vec<EnrichedLink>, windows: &OverlapWindows) {
+        suppress_duplicates(
+            links,
+            windows,
+            |link| {
+                let id_at = link.url.find(&link.video_id).unwrap_or(0);
+                (link.offset.as_u64() + id_at as u64, link.video_id.clone())
+            },
+            Self::is_better_link,
+        );
     }
     
     /// Check if new link is "better" than existing one
@@ -201,7 +215,7 @@ impl ParallelScanner {
     pub fn scan_file_streaming<F, H>(
         &self, 
         path: &Path, 
-        start_offset: usize,
+        start_offset: Offset,
         reverse: bool,
         progress_cb: Option<&F>,
         hot_fragment_cb: Option<&H>,
@@ -219,7 +233,7 @@ impl ParallelScanner {
             .with_context(|| format!("Failed to open file: {:?}", path))?;
         
         let metadata = file.metadata()?;
-        let file_size = metadata.len() as usize;
+        let file_size = metadata.len();
         
         if file_size == 0 {
             return Ok(ScanResult::default());
@@ -231,14 +245,37 @@ impl ParallelScanner {
                 .context("Failed to memory-map file")?
         };
         
-        // Create chunks with optional reverse order
-        let mut chunks = self.create_chunks(&mmap, start_offset);
+        // Create chunks with optional reverse order. In two-phase mode the
+        // coarse heatmap restricts them to hot regions
+        let mut cold_bytes_skipped = Size::default();
+        let mut chunks = if self.config.coarse_stride > 0 {
+            let start = start_offset.as_usize().unwrap_or(usize::MAX);
+            let region_size = self.config.heatmap_region_size;
+            let epicenters = heatmap::build_heatmap(&mmap, start, region_size, self.config.coarse_stride);
+            let ranges = heatmap::hot_ranges(&epicenters, region_size, mmap.len());
+
+            let hot_bytes: usize = ranges.iter().map(|(s, e)| e - s).sum();
+            let cold = mmap.len().saturating_sub(start).saturating_sub(hot_bytes);
+            cold_bytes_skipped = Size::from_usize(cold);
+            // Cold regions count as covered by the coarse pass
+            if let Some(cb) = progress_cb {
+                if cold > 0 {
+                    cb(cold);
+                }
+            }
+            // Start one overlap early: a link may begin in the cold region before
+            ranges
+                .iter()
+                .flat_map(|&(s, e)| self.create_chunks_in(&mmap, s.saturating_sub(self.config.overlap_size).max(start), e))
+                .collect()
+        } else {
+            self.create_chunks(&mmap, start_offset)
+        };
         if reverse {
             chunks.reverse();
         }
         
-        // eprint! usage removed for production build purity
-        // eprintln!("[RUST DEBUG] Created {} chunks. File size: {} bytes. Start offset: {}", chunks.len(), file_size, start_offset);
+        debug!(offset = start_offset.as_u64(), "Created {} chunks, file size {} bytes", chunks.len(), file_size);
 
         let matcher_template = &self.matcher_template;
         
@@ -248,9 +285,9 @@ impl ParallelScanner {
             .enumerate()
             .filter_map(|(i, (chunk_data, offset))| {
                 // Debug log for every 100th chunk
-                // if i % 100 == 0 {
-                //      eprintln!("[RUST DEBUG] Processing chunk {} at offset 0x{:X}", i, offset);
-                // }
+                if i % 100 == 0 {
+                    debug!(chunk = i, offset = offset.as_u64(), "Processing chunk");
+                }
 
                 // Report progress
                 if let Some(cb) = progress_cb {
@@ -263,7 +300,7 @@ impl ParallelScanner {
                     let mut matcher = matcher_template.clone_fresh();
                     let links = matcher.scan_chunk(
                         chunk_data,
-                        *offset,
+                        offset.as_u64(),
                         self.config.deduplicate,
                     );
                     
@@ -291,7 +328,7 @@ impl ParallelScanner {
                             
                             // Only report if score is promising
                             if target_score > 10.0 {
-                                let mut fragment = HotFragment::new(*offset as u64, chunk_data.len());
+                                let mut fragment = HotFragment::new(*offset, Size::from_usize(chunk_data.len()));
                                 fragment.youtube_count = links.len();
                                 fragment.cyrillic_density = cyr_density;
                                 fragment.json_markers = json_markers;
@@ -309,19 +346,19 @@ impl ParallelScanner {
                 match result {
                     Ok(links) => Some(links),
                     Err(_) => {
-                        // Corrupted sector - skip silently (forensic: log offset)
-                        // eprintln!("[WARN] Corrupted sector at offset 0x{:X}, skipping", offset);
+                        // Corrupted sector - skip (forensic: log offset)
+                        warn!(offset = offset.as_u64(), "Corrupted sector at offset 0x{:X}, skipping", offset);
                         Some(Vec::new())
                     }
                 }
             })
             .collect();
         
-        // Flatten and deduplicate
+        // Flatten and drop overlap duplicates
         let mut links: Vec<EnrichedLink> = all_links.into_iter().flatten().collect();
         
         if self.config.deduplicate {
-            self.deduplicate_links(&mut links);
+            Self::suppress_overlap_duplicates(&mut links, &chunk_windows(&chunks));
         }
         
         if self.config.min_confidence > 0.0 {
@@ -334,12 +371,20 @@ impl ParallelScanner {
         
         Ok(ScanResult {
             links,
-            bytes_scanned: file_size,
+            bytes_scanned: Size::new(file_size),
             duration_secs: duration.as_secs_f64(),
+            cold_bytes_skipped,
         })
     }
 }
 
+/// Ranges read by more than one of `chunks`
+fn chunk_windows(chunks: &[(&[u8], Offset)]) -> OverlapWindows {
+    OverlapWindows::from_spans(chunks.iter().map(|(data, offset)| {
+        (offset.as_u64(), offset.end(Size::from_usize(data.len())).as_u64())
+    }))
+}
+
 /// Fast file type guessing based on content
 fn guess_file_type_fast(data: &[u8]) -> String {
     if let Some(&first) = data.first() {
@@ -0,0 +1,132 @@
+//! JSON-lines event sink for scans driven by multi-process orchestrators
+//!
+//! Instead of calling back into Python for every event (and taking the GIL),
+//! the scanner writes one JSON object per line to a Unix socket or an
+//! inherited file descriptor (pipe, file):
+//!
+//! ```text
+//! {"event":"progress","bytes_scanned":268435456}
+//! {"event":"hot_fragment","offset":0,"size":268500992,"youtube_count":12,...}
+//! {"event":"done","bytes_scanned":1073741824,"duration_secs":3.2,"links":57}
+//! ```
+
+use crate::types::{HotFragment, Offset, Size};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::os::fd::{BorrowedFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Event written as one JSON line
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SinkEvent<'a> {
+    Progress {
+        bytes_scanned: u64,
+    },
+    HotFragment {
+        offset: Offset,
+        size: Size,
+        youtube_count: usize,
+        confidence: f32,
+        score: f32,
+        file_type: &'a str,
+    },
+    Done {
+        bytes_scanned: Size,
+        duration_secs: f64,
+        links: usize,
+    },
+}
+
+impl<'a> SinkEvent<'a> {
+    /// Same fields as the `hot_fragment_cb` dict
+    pub fn hot_fragment(fragment: &'a HotFragment) -> Self {
+        Self::HotFragment {
+            offset: fragment.offset,
+            size: fragment.size,
+            youtube_count: fragment.youtube_count,
+            confidence: fragment.target_score / 10.0,
+            score: fragment.target_score,
+            file_type: &fragment.file_type_guess,
+        }
+    }
+}
+
+/// Destination of scan events, shared by the scan threads
+pub struct EventSink {
+    /// `None` after a write failed (consumer went away); the scan goes on
+    writer: Mutex<Option<BufWriter<Box<dyn Write + Send>>>>,
+}
+
+impl EventSink {
+    /// Connect to a listening Unix socket
+    pub fn connect_unix(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(Box::new(UnixStream::connect(path)?)))
+    }
+
+    /// Write to an open file descriptor; it is duplicated, so the caller keeps ownership
+    pub fn from_fd(fd: RawFd) -> io::Result<Self> {
+        if fd < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid fd {}", fd)));
+        }
+        // Safety: only borrowed for the duration of the dup
+        let owned = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+        Ok(Self::new(Box::new(File::from(owned))))
+    }
+
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self { writer: Mutex::new(Some(BufWriter::new(writer))) }
+    }
+
+    /// Write one event line and flush it so the consumer sees it immediately
+    pub fn emit(&self, event: &SinkEvent) {
+        let mut guard = match self.writer.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let Some(writer) = guard.as_mut() else { return };
+
+        let result = serde_json::to_writer(&mut *writer, event)
+            .map_err(io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush());
+        if let Err(e) = result {
+            warn!("Event sink closed: {}", e);
+            *guard = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn test_events_as_json_lines() {
+        let (reader, writer) = UnixStream::pair().unwrap();
+        let sink = EventSink::new(Box::new(writer));
+
+        let mut fragment = HotFragment::new(Offset::new(4096), Size::new(1024));
+        fragment.target_score = 25.0;
+        fragment.file_type_guess = "json".to_string();
+        sink.emit(&SinkEvent::Progress { bytes_scanned: 1024 });
+        sink.emit(&SinkEvent::hot_fragment(&fragment));
+        drop(sink);
+
+        let lines: Vec<serde_json::Value> = BufReader::new(reader)
+            .lines()
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "progress");
+        assert_eq!(lines[0]["bytes_scanned"], 1024);
+        assert_eq!(lines[1]["event"], "hot_fragment");
+        assert_eq!(lines[1]["offset"], 4096);
+        assert_eq!(lines[1]["file_type"], "json");
+    }
+}
@@ -0,0 +1,445 @@
+//! Scan events for Python consumers without polling: `RustParallelScanner.scan_events()`
+//!
+//! The scan runs on its own thread and its callbacks only queue events; the
+//! GIL is taken when an event is handed to a waiting consumer, not every
+//! 20 ms. The returned `ScanEventStream` is an iterator (`next` blocks with
+//! the GIL released) and an async iterator: `__anext__` returns a future of
+//! the caller's running asyncio loop, resolved through `call_soon_threadsafe`
+//! as soon as the next event arrives.
+//!
+//! ```python
+//! async for event in scanner.scan_events("disk.img"):
+//!     if event["event"] == "hot_fragment":
+//!         gui.add_fragment(event)
+//! ```
+//!
+//! Events are dicts with the fields of the `sink` JSON lines (`event` is
+//! `progress`, `hot_fragment` or `done`); `done` also carries the
+//! `scan_streaming` result (links, `cold_bytes_skipped`). Progress events
+//! still queued when a newer one arrives are merged into it. A failed scan
+//! raises `RuntimeError` from the iteration. Dropping the stream discards
+//! further events; the scan itself runs to the end.
+
+use crate::scanner::parallel::ParallelScanner;
+use crate::types::{HotFragment, Offset, ScanResult};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use tracing::warn;
+
+/// Event of a running scan
+#[derive(Debug)]
+pub enum StreamEvent {
+    /// Total bytes scanned so far
+    Progress(u64),
+    HotFragment(Box<HotFragment>),
+    Done(ScanResult),
+    Failed(String),
+}
+
+/// Events not yet taken by the consumer
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    events: VecDeque<StreamEvent>,
+    /// `Done` or `Failed` was queued: nothing follows
+    closed: bool,
+}
+
+impl EventQueue {
+    pub fn push(&mut self, event: StreamEvent) {
+        if self.closed {
+            return;
+        }
+        if let (StreamEvent::Progress(total), Some(StreamEvent::Progress(queued))) = (&event, self.events.back_mut()) {
+            *queued = (*queued).max(*total);
+            return;
+        }
+        self.closed = matches!(event, StreamEvent::Done(_) | StreamEvent::Failed(_));
+        self.events.push_back(event);
+    }
+
+    pub fn pop(&mut self) -> Option<StreamEvent> {
+        self.events.pop_front()
+    }
+
+    /// Nothing left to deliver
+    pub fn is_finished(&self) -> bool {
+        self.closed && self.events.is_empty()
+    }
+}
+
+/// Receiver of the next event, parked by `__anext__`
+trait Resolve {
+    fn resolve(self, outcome: Outcome);
+}
+
+/// Future of an `__anext__` call waiting for the next event
+struct Waiter {
+    event_loop: PyObject,
+    future: PyObject,
+}
+
+impl Resolve for Waiter {
+    /// Set the future on its loop's thread
+    fn resolve(self, outcome: Outcome) {
+        Python::with_gil(|py| {
+            let deliver = Deliver { future: self.future, outcome: Some(outcome) };
+            if let Err(e) = self.event_loop.call_method1(py, "call_soon_threadsafe", (deliver,)) {
+                // Loop closed: nobody is waiting any more
+                warn!("Scan event not delivered: {}", e);
+            }
+        });
+    }
+}
+
+/// What `__anext__` gets
+enum Next {
+    /// Queued event, returned at once
+    Event(StreamEvent),
+    /// Stream finished
+    End,
+    /// The waiter is parked until `push` hands it an event
+    Waiting,
+    /// The previous `__anext__` is still pending
+    Busy,
+}
+
+struct State<W = Waiter> {
+    queue: EventQueue,
+    waiter: Option<W>,
+    /// The stream was dropped: events are discarded
+    abandoned: bool,
+}
+
+impl<W> Default for State<W> {
+    fn default() -> Self {
+        Self { queue: EventQueue::default(), waiter: None, abandoned: false }
+    }
+}
+
+impl<W> State<W> {
+    /// The pending waiter and what it gets, once there is an event or the end
+    fn handoff(&mut self) -> Option<(W, Outcome)> {
+        if self.queue.events.is_empty() && !self.queue.closed {
+            return None;
+        }
+        let waiter = self.waiter.take()?;
+        let outcome = self.queue.pop().map_or(Outcome::End, Outcome::Event);
+        Some((waiter, outcome))
+    }
+
+    /// Answer an `__anext__`: a queued event, the end, or park `waiter`.
+    /// A waiter that is not parked is returned, to be released after the lock
+    fn next(&mut self, waiter: W) -> (Next, Option<W>) {
+        if self.waiter.is_some() {
+            return (Next::Busy, Some(waiter));
+        }
+        if self.queue.is_finished() {
+            return (Next::End, Some(waiter));
+        }
+        match self.queue.pop() {
+            Some(event) => (Next::Event(event), Some(waiter)),
+            None => {
+                self.waiter = Some(waiter);
+                (Next::Waiting, None)
+            }
+        }
+    }
+
+    /// The stream was dropped: the parked waiter and queued events, to be
+    /// released after the lock
+    fn abandon(&mut self) -> (Option<W>, EventQueue) {
+        self.abandoned = true;
+        (self.waiter.take(), std::mem::take(&mut self.queue))
+    }
+}
+
+/// Shared by the scan thread and the `ScanEventStream`
+struct Shared<W = Waiter> {
+    state: Mutex<State<W>>,
+    ready: Condvar,
+}
+
+impl<W> Default for Shared<W> {
+    fn default() -> Self {
+        Self { state: Mutex::new(State::default()), ready: Condvar::new() }
+    }
+}
+
+impl<W: Resolve> Shared<W> {
+    fn lock(&self) -> MutexGuard<'_, State<W>> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Queue an event from a scan thread and hand it to a pending `__anext__`.
+    /// Python is never called with the lock held: it may switch threads.
+    fn push(&self, event: StreamEvent) {
+        let mut state = self.lock();
+        if state.abandoned {
+            return;
+        }
+        state.queue.push(event);
+        let handoff = state.handoff();
+        drop(state);
+        self.ready.notify_all();
+        if let Some((waiter, outcome)) = handoff {
+            waiter.resolve(outcome);
+        }
+    }
+}
+
+enum Outcome {
+    Event(StreamEvent),
+    /// Stream finished: StopAsyncIteration
+    End,
+}
+
+/// Sets the result of an `__anext__` future, called on the loop thread
+#[pyclass]
+struct Deliver {
+    future: PyObject,
+    outcome: Option<Outcome>,
+}
+
+#[pymethods]
+impl Deliver {
+    fn __call__(&mut self, py: Python<'_>) -> PyResult<()> {
+        // Cancelled (e.g. asyncio.wait_for timed out): the event is dropped
+        if self.future.call_method0(py, "done")?.is_true(py)? {
+            return Ok(());
+        }
+        set_outcome(py, &self.future, self.outcome.take().unwrap_or(Outcome::End))
+    }
+}
+
+fn set_outcome(py: Python<'_>, future: &PyObject, outcome: Outcome) -> PyResult<()> {
+    match outcome {
+        Outcome::Event(StreamEvent::Failed(message)) => {
+            let error = pyo3::exceptions::PyRuntimeError::new_err(message);
+            future.call_method1(py, "set_exception", (error,))?;
+        }
+        Outcome::Event(event) => {
+            future.call_method1(py, "set_result", (event_to_dict(py, &event)?,))?;
+        }
+        Outcome::End => {
+            let stop = pyo3::exceptions::PyStopAsyncIteration::new_err(());
+            future.call_method1(py, "set_exception", (stop,))?;
+        }
+    }
+    Ok(())
+}
+
+/// Events of one scan, as an iterator and an async iterator
+#[pyclass]
+pub struct ScanEventStream {
+    shared: Arc<Shared>,
+}
+
+impl ScanEventStream {
+    /// Start scanning `path` on a new thread; links with `seen_ids` are left out of `done`
+    pub fn start(
+        scanner: Arc<ParallelScanner>,
+        path: PathBuf,
+        start_offset: Offset,
+        reverse: bool,
+        seen_ids: Option<Arc<HashSet<String>>>,
+    ) -> Self {
+        let shared = Arc::new(Shared::default());
+        let events = Arc::clone(&shared);
+        std::thread::spawn(move || {
+            let scanned = AtomicU64::new(0);
+            let p_cb = |len: usize| {
+                let total = scanned.fetch_add(len as u64, Ordering::AcqRel) + len as u64;
+                events.push(StreamEvent::Progress(total));
+            };
+            let h_cb = |fragment: HotFragment| events.push(StreamEvent::HotFragment(Box::new(fragment)));
+            let outcome = scanner.scan_file_streaming(&path, start_offset, reverse, Some(&p_cb), Some(&h_cb));
+            events.push(match outcome {
+                Ok(mut result) => {
+                    if let Some(seen_ids) = &seen_ids {
+                        crate::drop_seen_links(&mut result, seen_ids);
+                    }
+                    StreamEvent::Done(result)
+                }
+                Err(e) => StreamEvent::Failed(e.to_string()),
+            });
+        });
+        Self { shared }
+    }
+}
+
+#[pymethods]
+impl ScanEventStream {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Next event, waiting with the GIL released; None ends the iteration
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let shared = Arc::clone(&self.shared);
+        let event = py.allow_threads(move || {
+            let mut state = shared.lock();
+            loop {
+                if let Some(event) = state.queue.pop() {
+                    return Some(event);
+                }
+                if state.queue.is_finished() {
+                    return None;
+                }
+                state = shared.ready.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+        });
+        match event {
+            Some(StreamEvent::Failed(message)) => Err(pyo3::exceptions::PyRuntimeError::new_err(message)),
+            Some(event) => event_to_dict(py, &event).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Future of the next event on the running loop; None ends the iteration
+    fn __anext__(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let event_loop: PyObject = py.import("asyncio")?.call_method0("get_running_loop")?.into();
+        let future = event_loop.call_method0(py, "create_future")?;
+        let waiter = Waiter { event_loop, future: future.clone_ref(py) };
+        // The lock is released at the end of the statement, the unused waiter after it
+        let (next, _unused) = self.shared.lock().next(waiter);
+        match next {
+            Next::Event(event) => {
+                set_outcome(py, &future, Outcome::Event(event))?;
+                Ok(Some(future))
+            }
+            Next::End => Ok(None),
+            Next::Waiting => Ok(Some(future)),
+            Next::Busy => Err(pyo3::exceptions::PyRuntimeError::new_err("the previous __anext__ is still pending")),
+        }
+    }
+}
+
+impl Drop for ScanEventStream {
+    fn drop(&mut self) {
+        // Released after the lock
+        let _discarded = self.shared.lock().abandon();
+    }
+}
+
+/// Event as a dict: the `sink` JSON fields, plus the scan result for `done`
+fn event_to_dict(py: Python<'_>, event: &StreamEvent) -> PyResult<PyObject> {
+    let dict = match event {
+        StreamEvent::Progress(total) => {
+            let dict = PyDict::new(py);
+            dict.set_item("event", "progress")?;
+            dict.set_item("bytes_scanned", total)?;
+            dict
+        }
+        StreamEvent::HotFragment(fragment) => {
+            let dict = PyDict::new(py);
+            dict.set_item("event", "hot_fragment")?;
+            dict.set_item("offset", fragment.offset.as_u64())?;
+            dict.set_item("size", fragment.size.as_u64())?;
+            dict.set_item("youtube_count", fragment.youtube_count)?;
+            dict.set_item("confidence", fragment.target_score / 10.0)?;
+            dict.set_item("score", fragment.target_score)?;
+            dict.set_item("file_type", &fragment.file_type_guess)?;
+            dict
+        }
+        StreamEvent::Done(result) => {
+            let dict: &PyDict = crate::scan_result_to_dict(py, result)?.into_ref(py).downcast()?;
+            dict.set_item("event", "done")?;
+            dict
+        }
+        StreamEvent::Failed(message) => {
+            let dict = PyDict::new(py);
+            dict.set_item("event", "failed")?;
+            dict.set_item("error", message)?;
+            dict
+        }
+    };
+    Ok(dict.to_object(py))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Size;
+    use std::sync::mpsc::{self, Receiver, Sender};
+
+    impl Resolve for Sender<Outcome> {
+        fn resolve(self, outcome: Outcome) {
+            self.send(outcome).unwrap();
+        }
+    }
+
+    fn shared() -> (Shared<Sender<Outcome>>, Sender<Outcome>, Receiver<Outcome>) {
+        let (sender, receiver) = mpsc::channel();
+        (Shared::default(), sender, receiver)
+    }
+
+    #[test]
+    fn test_event_queue_merges_progress() {
+        let mut queue = EventQueue::default();
+        queue.push(StreamEvent::Progress(1024));
+        queue.push(StreamEvent::Progress(4096));
+        queue.push(StreamEvent::HotFragment(Box::new(HotFragment::new(Offset::new(0), Size::new(512)))));
+        queue.push(StreamEvent::Progress(8192));
+        queue.push(StreamEvent::Done(ScanResult::default()));
+        queue.push(StreamEvent::Progress(9000));
+
+        assert!(matches!(queue.pop(), Some(StreamEvent::Progress(4096))));
+        assert!(matches!(queue.pop(), Some(StreamEvent::HotFragment(_))));
+        assert!(matches!(queue.pop(), Some(StreamEvent::Progress(8192))));
+        assert!(!queue.is_finished());
+        assert!(matches!(queue.pop(), Some(StreamEvent::Done(_))));
+        assert!(queue.is_finished());
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn test_pending_next_gets_pushed_event() {
+        let (shared, waiter, delivered) = shared();
+
+        // Nothing queued: the first waiter is parked, a second one refused
+        assert!(matches!(shared.lock().next(waiter.clone()), (Next::Waiting, None)));
+        assert!(matches!(shared.lock().next(waiter.clone()), (Next::Busy, Some(_))));
+        assert!(shared.lock().handoff().is_none());
+
+        // The scan thread hands the event to the parked waiter
+        shared.push(StreamEvent::Progress(4096));
+        assert!(matches!(delivered.try_recv(), Ok(Outcome::Event(StreamEvent::Progress(4096)))));
+        assert!(shared.lock().waiter.is_none());
+
+        // Without a waiter events queue up and are returned at once
+        shared.push(StreamEvent::HotFragment(Box::new(HotFragment::new(Offset::new(0), Size::new(512)))));
+        shared.push(StreamEvent::Done(ScanResult::default()));
+        assert!(matches!(shared.lock().next(waiter.clone()), (Next::Event(StreamEvent::HotFragment(_)), Some(_))));
+        assert!(matches!(shared.lock().next(waiter.clone()), (Next::Event(StreamEvent::Done(_)), Some(_))));
+        assert!(matches!(shared.lock().next(waiter), (Next::End, Some(_))));
+        assert!(delivered.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_abandoned_stream_discards_events() {
+        let (shared, waiter, delivered) = shared();
+        shared.push(StreamEvent::Progress(1024));
+        assert!(matches!(shared.lock().next(waiter.clone()), (Next::Event(_), Some(_))));
+        assert!(matches!(shared.lock().next(waiter), (Next::Waiting, None)));
+
+        // Dropping the stream releases the parked waiter unresolved
+        let (parked, queue) = shared.lock().abandon();
+        assert!(parked.is_some());
+        assert!(queue.events.is_empty());
+        drop(parked);
+
+        shared.push(StreamEvent::Progress(2048));
+        shared.push(StreamEvent::Done(ScanResult::default()));
+        assert!(delivered.try_recv().is_err());
+        let state = shared.lock();
+        assert!(state.abandoned && state.queue.events.is_empty() && state.waiter.is_none());
+    }
+}
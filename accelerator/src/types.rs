@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use pyo3::prelude::*;
 
+pub use recovery_core::types::{ClusterId, FileTimestamps, Offset, Size};
+
 /// YouTube link with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[pyclass]
@@ -17,9 +19,8 @@ pub struct EnrichedLink {
     #[pyo3(get)]
     pub title: Option<String>,
     
-    /// Offset in the file
-    #[pyo3(get)]
-    pub offset: usize,
+    /// Offset in the file (u64: image offsets exceed usize on 32-bit builds)
+    pub offset: Offset,
     
     /// Name of the pattern that matched
     #[pyo3(get)]
@@ -28,10 +29,15 @@ pub struct EnrichedLink {
     /// Confidence level (0.0 - 1.0)
     #[pyo3(get)]
     pub confidence: f32,
+
+    /// Context adjustments applied to `confidence`, e.g. "json_string (+0.10)"
+    #[pyo3(get)]
+    #[serde(default)]
+    pub reasons: Vec<String>,
 }
 
 impl EnrichedLink {
-    pub fn new(url: String, video_id: String, offset: usize, pattern_name: String, confidence: f32) -> Self {
+    pub fn new(url: String, video_id: String, offset: Offset, pattern_name: String, confidence: f32) -> Self {
         Self {
             url,
             video_id,
@@ -39,10 +45,19 @@ impl EnrichedLink {
             offset,
             pattern_name,
             confidence,
+            reasons: Vec::new(),
         }
     }
 }
 
+#[pymethods]
+impl EnrichedLink {
+    #[getter]
+    fn offset(&self) -> u64 {
+        self.offset.as_u64()
+    }
+}
+
 /// Scanner configuration
 #[derive(Debug, Clone)]
 pub struct ScanConfig {
@@ -60,6 +75,13 @@ pub struct ScanConfig {
     
     /// Minimum confidence level
     pub min_confidence: f32,
+
+    /// Two-phase scan: sample every Nth 32-byte block for the heatmap,
+    /// then deep scan only hot regions (0 = single full pass)
+    pub coarse_stride: usize,
+
+    /// Heatmap region size in bytes
+    pub heatmap_region_size: usize,
 }
 
 impl Default for ScanConfig {
@@ -70,6 +92,8 @@ impl Default for ScanConfig {
             num_threads: 0,                 // Auto
             deduplicate: true,
             min_confidence: 0.0,
+            coarse_stride: 0,               // Single pass
+            heatmap_region_size: crate::scanner::heatmap::DEFAULT_REGION_SIZE,
         }
     }
 }
@@ -81,10 +105,13 @@ pub struct ScanResult {
     pub links: Vec<EnrichedLink>,
     
     /// Total bytes scanned
-    pub bytes_scanned: usize,
+    pub bytes_scanned: Size,
     
     /// Duration in seconds
     pub duration_secs: f64,
+
+    /// Bytes left out of the deep pass by the coarse heatmap
+    pub cold_bytes_skipped: Size,
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -95,18 +122,25 @@ pub struct ExFATHint {
     pub filename: String,
     #[pyo3(get)]
     pub is_deleted: bool,
-    #[pyo3(get)]
-    pub entry_offset: u64,
+    pub entry_offset: Offset,
+}
+
+#[pymethods]
+impl ExFATHint {
+    #[getter]
+    fn entry_offset(&self) -> u64 {
+        self.entry_offset.as_u64()
+    }
 }
 
 /// A "hot" fragment detected during scanning - likely to be a target file
 #[derive(Debug, Clone)]
 pub struct HotFragment {
     /// Offset in the disk image
-    pub offset: u64,
+    pub offset: Offset,
     
     /// Size of the fragment in bytes
-    pub size: usize,
+    pub size: Size,
     
     /// Number of YouTube links found
     pub youtube_count: usize,
@@ -143,7 +177,7 @@ pub struct HotFragment {
 }
 
 impl HotFragment {
-    pub fn new(offset: u64, size: usize) -> Self {
+    pub fn new(offset: Offset, size: Size) -> Self {
         Self {
             offset,
             size,
@@ -166,7 +200,7 @@ impl HotFragment {
     
     /// Check if fragment is in target size range (15-350 KB)
     pub fn is_target_size(&self) -> bool {
-        let size_kb = self.size as f32 / 1024.0;
+        let size_kb = self.size.as_u64() as f32 / 1024.0;
         size_kb >= 15.0 && size_kb <= 350.0
     }
 }
@@ -175,9 +209,9 @@ impl HotFragment {
 #[derive(Debug, Clone)]
 pub struct Epicenter {
     /// Offset where high density was detected
-    pub offset: u64,
+    pub offset: Offset,
     
-    /// Link density (links per MB)
+    /// Hot-byte density (y, h, {, v, / per MB of non-empty sampled blocks)
     pub density: f32,
     
     /// Whether deep scan is needed
@@ -185,10 +219,10 @@ pub struct Epicenter {
 }
 
 impl Epicenter {
-    /// Threshold for triggering deep scan (50 links per MB)
+    /// Threshold for triggering deep scan (50 hot bytes per MB)
     pub const DEEP_SCAN_THRESHOLD: f32 = 50.0;
     
-    pub fn new(offset: u64, density: f32) -> Self {
+    pub fn new(offset: Offset, density: f32) -> Self {
         Self {
             offset,
             density,
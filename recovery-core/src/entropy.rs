@@ -1,17 +1,19 @@
 /// SIMD-accelerated Shannon entropy calculation
 /// 
 /// This module provides high-performance entropy calculation using:
-/// - SIMD instructions (AVX2/SSE2) when available
+/// - SIMD instructions (AVX2/SSE2, NEON on aarch64) when available
 /// - Byte frequency histogram (256 bins)
 /// - Shannon entropy formula: H = -Σ(p_i * log2(p_i))
 /// - Fallback to scalar implementation
 
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
 /// Calculate Shannon entropy of data
 /// Returns value between 0.0 (no entropy, predictable) and 8.0 (maximum entropy, random)
 /// 
 /// This function automatically uses SIMD instructions when available on x86_64
+/// or aarch64
 /// and falls back to scalar implementation on other architectures.
 pub fn calculate_shannon_entropy(data: &[u8]) -> f32 {
     if data.is_empty() {
@@ -27,6 +29,14 @@ pub fn calculate_shannon_entropy(data: &[u8]) -> f32 {
         }
     }
 
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            let histogram = unsafe { crate::simd_neon::byte_histogram_neon(data) };
+            return calculate_entropy_from_histogram(&histogram, data.len() as f32);
+        }
+    }
+
     // Fallback to scalar implementation
     calculate_entropy_scalar(data)
 }
@@ -80,7 +90,7 @@ unsafe fn calculate_entropy_simd(data: &[u8]) -> f32 {
     calculate_entropy_from_histogram(&histogram, data_len as f32)
 }
 
-/// Scalar entropy calculation (fallback for non-x86_64 architectures)
+/// Scalar entropy calculation (fallback without AVX2/SSE2/NEON)
 fn calculate_entropy_scalar(data: &[u8]) -> f32 {
     const BINS: usize = 256;
     let mut histogram = [0u32; BINS];
@@ -138,8 +148,11 @@ pub fn is_structured_text(data: &[u8]) -> bool {
 /// Get entropy category for logging/debugging
 #[inline]
 pub fn get_entropy_category(data: &[u8]) -> &'static str {
-    let entropy = calculate_shannon_entropy(data);
-    
+    entropy_category(calculate_shannon_entropy(data))
+}
+
+/// Category of an already computed entropy value (see `get_entropy_category`)
+pub fn entropy_category(entropy: f32) -> &'static str {
     if entropy > 7.5 {
         "high_entropy_compressed"
     } else if entropy > 6.0 {
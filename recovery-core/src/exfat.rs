@@ -0,0 +1,1444 @@
+use crate::types::FileTimestamps;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Entry type markers
+pub const ENTRY_FILE: u8 = 0x85;
+pub const ENTRY_STREAM: u8 = 0xC0;
+pub const ENTRY_FILENAME: u8 = 0xC1;
+pub const ENTRY_DELETED_FILE: u8 = 0x05;
+pub const ENTRY_DELETED_STREAM: u8 = 0x40;
+pub const ENTRY_DELETED_FILENAME: u8 = 0x41;
+pub const ENTRY_ALLOCATION_BITMAP: u8 = 0x81;
+
+/// Boot sector field offsets
+const BS_FILE_SYSTEM_NAME: usize = 3;
+const BS_FAT_OFFSET: usize = 80;
+const BS_FAT_LENGTH: usize = 84;
+const BS_CLUSTER_HEAP_OFFSET: usize = 88;
+const BS_CLUSTER_COUNT: usize = 92;
+const BS_FIRST_CLUSTER_OF_ROOT: usize = 96;
+const BS_BYTES_PER_SECTOR_SHIFT: usize = 108;
+const BS_SECTORS_PER_CLUSTER_SHIFT: usize = 109;
+
+/// File Entry field offsets
+const FE_FILE_ATTRIBUTES: usize = 4;
+const FE_CREATE_TIMESTAMP: usize = 8;
+const FE_LAST_MODIFIED_TIMESTAMP: usize = 12;
+const FE_LAST_ACCESSED_TIMESTAMP: usize = 16;
+const FE_CREATE_10MS_INCREMENT: usize = 20;
+const FE_LAST_MODIFIED_10MS_INCREMENT: usize = 21;
+const FE_CREATE_UTC_OFFSET: usize = 22;
+const FE_LAST_MODIFIED_UTC_OFFSET: usize = 23;
+const FE_LAST_ACCESSED_UTC_OFFSET: usize = 24;
+const ATTR_DIRECTORY: u16 = 0x10;
+
+/// Stream Extension Entry field offsets
+const SE_GENERAL_FLAGS: usize = 1;
+const SE_NAME_LENGTH: usize = 3;
+const SE_FIRST_CLUSTER: usize = 20;
+const SE_DATA_LENGTH: usize = 24;
+
+/// File Name Entry field offsets
+const FN_FILE_NAME: usize = 2;
+
+const DIRECTORY_ENTRY_SIZE: usize = 32;
+const MAX_CLUSTER_SIZE: u64 = 32 * 1024 * 1024;
+const MAX_EXTRACT_SIZE: u64 = 250 * 1024 * 1024;
+/// Clusters read per directory before the tree walk moves on (8 MiB at 2 KiB clusters)
+const MAX_DIRECTORY_CLUSTERS: u64 = 4096;
+/// Directory levels below the root followed by `extract_tree_to`
+const MAX_TREE_DEPTH: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct ExFatBootParams {
+    pub sector_size: u64,
+    pub cluster_size: u64,
+    pub fat_offset: u64,
+    pub fat_length_sectors: u32,
+    pub cluster_heap_offset: u64,
+    pub cluster_count: u32,
+    pub root_dir_cluster: u32,
+    pub boot_sector_offset: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExFatEntry {
+    pub offset: u64,
+    pub data_offset: Option<u64>,
+    pub is_deleted: bool,
+    pub is_directory: bool,
+    pub filename: String,
+    pub size: u64,
+    pub first_cluster: u32,
+    pub no_fat_chain: bool,
+    /// Raw LastModifiedTimestamp (year, month, day, time packed from the
+    /// high bits, so a larger value is a later time; 0 = unknown)
+    pub modified: u32,
+    /// Create, last modified and last accessed times
+    pub times: FileTimestamps,
+    /// Sanity violations found by `mark_suspicious_entries` (empty = trusted)
+    pub suspicious_reasons: Vec<String>,
+    /// Clusters guessed for a deleted FAT-chained file (rust-recovery's
+    /// `exfat::reconstruct_deleted_entries`)
+    pub reconstruction: Option<Reconstruction>,
+}
+
+/// Deleted FAT-chained file rebuilt from consecutive clusters.
+///
+/// Deleting a file zeroes its FAT entries, so the chain is gone. The file is
+/// taken to continue in the following clusters for as long as their content
+/// resembles the previous cluster; the first cluster that does not is taken
+/// as the start of other data and ends the file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Reconstruction {
+    /// Clusters kept, starting at the entry's first cluster
+    pub clusters: u64,
+    /// Bytes kept (the file size when every cluster was kept)
+    pub bytes: u64,
+    /// Kept share of the file times the mean similarity of the kept clusters, 0-1
+    pub confidence: f32,
+    pub complete: bool,
+}
+
+impl ExFatEntry {
+    pub fn is_suspicious(&self) -> bool {
+        !self.suspicious_reasons.is_empty()
+    }
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_le_bytes)
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+}
+
+fn read_u64_le(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+}
+
+/// Time of a File Directory Entry timestamp field: the packed local date and
+/// time (2-second steps), its 10 ms increment (0-199; last accessed has none)
+/// and its UTC offset byte (15-minute steps, bit 7 set when recorded; a
+/// time without one is taken as UTC). None for 0 and impossible dates
+fn entry_timestamp(raw: u32, increment_10ms: u8, utc_offset: u8) -> Option<DateTime<FixedOffset>> {
+    if raw == 0 || increment_10ms > 199 {
+        return None;
+    }
+    let date = NaiveDate::from_ymd_opt(1980 + (raw >> 25) as i32, (raw >> 21) & 0x0F, (raw >> 16) & 0x1F)?;
+    let increment = u32::from(increment_10ms);
+    let time = date.and_hms_milli_opt(
+        (raw >> 11) & 0x1F,
+        (raw >> 5) & 0x3F,
+        (raw & 0x1F) * 2 + increment / 100,
+        increment % 100 * 10,
+    )?;
+    // Seven-bit two's complement count of 15 minutes
+    let minutes = if utc_offset & 0x80 != 0 { i32::from(((utc_offset << 1) as i8) >> 1) * 15 } else { 0 };
+    time.and_local_timezone(FixedOffset::east_opt(minutes * 60)?).single()
+}
+
+/// Boot sector at `bs_offset` (a partition start); offsets in the result are image offsets
+pub fn parse_boot_sector_at(data: &[u8], bs_offset: u64) -> Option<ExFatBootParams> {
+    let off = usize::try_from(bs_offset).ok()?;
+    if data.len() < off + 120 {
+        return None;
+    }
+
+    if data.get(off + BS_FILE_SYSTEM_NAME..off + BS_FILE_SYSTEM_NAME + 8)? != b"EXFAT   " {
+        return None;
+    }
+
+    let bytes_per_sector_shift = *data.get(off + BS_BYTES_PER_SECTOR_SHIFT)?;
+    let sectors_per_cluster_shift = *data.get(off + BS_SECTORS_PER_CLUSTER_SHIFT)?;
+
+    if !(9..=12).contains(&bytes_per_sector_shift) {
+        return None;
+    }
+    if sectors_per_cluster_shift > 25 {
+        return None;
+    }
+
+    let sector_size = 1u64 << bytes_per_sector_shift;
+    let cluster_size = sector_size << sectors_per_cluster_shift;
+
+    if cluster_size == 0 || cluster_size > MAX_CLUSTER_SIZE {
+        return None;
+    }
+
+    let fat_offset_sectors = read_u32_le(data, off + BS_FAT_OFFSET)? as u64;
+    let fat_length_sectors = read_u32_le(data, off + BS_FAT_LENGTH)?;
+    let cluster_heap_offset_sectors = read_u32_le(data, off + BS_CLUSTER_HEAP_OFFSET)? as u64;
+    let cluster_count = read_u32_le(data, off + BS_CLUSTER_COUNT)?;
+    let root_dir_cluster = read_u32_le(data, off + BS_FIRST_CLUSTER_OF_ROOT)?;
+
+    let fat_offset = fat_offset_sectors
+        .checked_mul(sector_size)?
+        .checked_add(bs_offset)?;
+    let cluster_heap_offset = cluster_heap_offset_sectors
+        .checked_mul(sector_size)?
+        .checked_add(bs_offset)?;
+
+    if fat_offset == 0 || cluster_heap_offset == 0 {
+        return None;
+    }
+
+    Some(ExFatBootParams {
+        sector_size,
+        cluster_size,
+        fat_offset,
+        fat_length_sectors,
+        cluster_heap_offset,
+        cluster_count,
+        root_dir_cluster,
+        boot_sector_offset: bs_offset,
+    })
+}
+
+pub fn find_boot_sector(data: &[u8]) -> Option<ExFatBootParams> {
+    if let Some(params) = parse_boot_sector_at(data, 0) {
+        return Some(params);
+    }
+
+    let search_limit = data.len().min(4 * 1024 * 1024);
+    for offset in (512..search_limit).step_by(512) {
+        if offset + 120 > data.len() {
+            break;
+        }
+        if data.get(offset + 3..offset + 11) == Some(&b"EXFAT   "[..]) {
+            if let Some(params) = parse_boot_sector_at(data, offset as u64) {
+                return Some(params);
+            }
+        }
+    }
+
+    None
+}
+
+fn fat_next_cluster(data: &[u8], params: &ExFatBootParams, cluster: u32) -> Option<u32> {
+    let offset_bytes = (cluster as u64).checked_mul(4)?;
+    let fat_entry_offset = params.fat_offset.checked_add(offset_bytes)?;
+    let offset = usize::try_from(fat_entry_offset).ok()?;
+    read_u32_le(data, offset)
+}
+
+pub fn cluster_to_offset(params: &ExFatBootParams, cluster: u32) -> Option<u64> {
+    if cluster < 2 {
+        return None;
+    }
+    params
+        .cluster_heap_offset
+        .checked_add((cluster as u64).saturating_sub(2).checked_mul(params.cluster_size)?)
+}
+
+/// Walk the cluster chain of a file and hand each data slice to `sink`, in order.
+///
+/// Stops at `file_size`, at the end of the chain, or at the first invalid or
+/// repeated cluster. Returns the number of bytes passed to `sink`.
+fn for_each_file_slice<F>(
+    data: &[u8],
+    params: &ExFatBootParams,
+    first_cluster: u32,
+    file_size: u64,
+    no_fat_chain: bool,
+    mut sink: F,
+) -> std::io::Result<u64>
+where
+    F: FnMut(&[u8]) -> std::io::Result<()>,
+{
+    if first_cluster < 2 || file_size == 0 {
+        return Ok(0);
+    }
+
+    let mut remaining = file_size;
+    let mut cluster = first_cluster;
+    let mut visited = HashSet::new();
+    let max_chain = params.cluster_count.saturating_add(1);
+
+    while remaining > 0 {
+        if cluster < 2 || cluster >= 0xFFFFFFF7 || cluster > max_chain {
+            break;
+        }
+        // Contiguous files can't loop, only FAT chains need the visited set
+        if !no_fat_chain && !visited.insert(cluster) {
+            break;
+        }
+
+        let start = match cluster_to_offset(params, cluster) {
+            Some(offset) => offset,
+            None => break,
+        };
+
+        if start >= data.len() as u64 {
+            break;
+        }
+
+        let to_read = remaining.min(params.cluster_size);
+        let end = start.saturating_add(to_read).min(data.len() as u64);
+        if end <= start {
+            break;
+        }
+
+        sink(&data[start as usize..end as usize])?;
+        let read_len = end - start;
+        remaining = remaining.saturating_sub(read_len);
+
+        if no_fat_chain {
+            cluster = match cluster.checked_add(1) {
+                Some(next) => next,
+                None => break,
+            };
+        } else {
+            let next_cluster = match fat_next_cluster(data, params, cluster) {
+                Some(next) => next,
+                None => break,
+            };
+            cluster = next_cluster;
+        }
+    }
+
+    Ok(file_size - remaining)
+}
+
+/// Extract a file into memory (capped at `MAX_EXTRACT_SIZE`, see `extract_file_to_writer`)
+pub fn extract_file_content(
+    data: &[u8],
+    params: &ExFatBootParams,
+    first_cluster: u32,
+    file_size: u64,
+    no_fat_chain: bool,
+) -> Vec<u8> {
+    if first_cluster < 2 || file_size == 0 {
+        return Vec::new();
+    }
+
+    let actual_size = file_size.min(MAX_EXTRACT_SIZE);
+    let mut content = Vec::with_capacity(actual_size as usize);
+    let _ = for_each_file_slice(data, params, first_cluster, actual_size, no_fat_chain, |slice| {
+        content.extend_from_slice(slice);
+        Ok(())
+    });
+
+    content.truncate(actual_size as usize);
+    content
+}
+
+/// Stream a file cluster-by-cluster into `writer` without buffering it in memory.
+///
+/// No size cap applies; peak memory is one cluster slice of the mapped image.
+/// Returns the number of bytes written, which is short of `file_size` when the
+/// chain ends early or runs past the end of the image.
+pub fn extract_file_to_writer<W: Write>(
+    data: &[u8],
+    params: &ExFatBootParams,
+    first_cluster: u32,
+    file_size: u64,
+    no_fat_chain: bool,
+    writer: &mut W,
+) -> std::io::Result<u64> {
+    let written = for_each_file_slice(data, params, first_cluster, file_size, no_fat_chain, |slice| {
+        writer.write_all(slice)
+    })?;
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Stream an entry's content to `path` (created or truncated); a
+/// reconstructed entry gets its reconstructed clusters
+pub fn extract_entry_to_path(
+    data: &[u8],
+    params: &ExFatBootParams,
+    entry: &ExFatEntry,
+    path: &Path,
+) -> std::io::Result<u64> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let (size, no_fat_chain) = match &entry.reconstruction {
+        Some(reconstruction) => (reconstruction.bytes, true),
+        None => (entry.size, entry.no_fat_chain),
+    };
+    extract_file_to_writer(data, params, entry.first_cluster, size, no_fat_chain, &mut writer)
+}
+
+pub fn parse_entry_set(data: &[u8], base_offset: u64) -> Option<(ExFatEntry, usize)> {
+    if data.len() < DIRECTORY_ENTRY_SIZE {
+        return None;
+    }
+
+    let file_type = *data.get(0)?;
+    let is_deleted = file_type == ENTRY_DELETED_FILE;
+
+    if file_type != ENTRY_FILE && file_type != ENTRY_DELETED_FILE {
+        return None;
+    }
+
+    let secondary_count = *data.get(1)? as usize;
+    if secondary_count < 2 {
+        return None;
+    }
+    let is_directory = read_u16_le(data, FE_FILE_ATTRIBUTES)? & ATTR_DIRECTORY != 0;
+    let modified = read_u32_le(data, FE_LAST_MODIFIED_TIMESTAMP)?;
+    let times = FileTimestamps {
+        created: entry_timestamp(
+            read_u32_le(data, FE_CREATE_TIMESTAMP)?,
+            *data.get(FE_CREATE_10MS_INCREMENT)?,
+            *data.get(FE_CREATE_UTC_OFFSET)?,
+        ),
+        modified: entry_timestamp(
+            modified,
+            *data.get(FE_LAST_MODIFIED_10MS_INCREMENT)?,
+            *data.get(FE_LAST_MODIFIED_UTC_OFFSET)?,
+        ),
+        accessed: entry_timestamp(read_u32_le(data, FE_LAST_ACCESSED_TIMESTAMP)?, 0, *data.get(FE_LAST_ACCESSED_UTC_OFFSET)?),
+    };
+
+    let total_entries = 1 + secondary_count;
+    let total_bytes = total_entries * DIRECTORY_ENTRY_SIZE;
+    if data.len() < total_bytes {
+        return None;
+    }
+
+    let se_offset = DIRECTORY_ENTRY_SIZE;
+    let se_type = *data.get(se_offset)?;
+    if se_type != ENTRY_STREAM && se_type != ENTRY_DELETED_STREAM {
+        return None;
+    }
+
+    let general_flags = *data.get(se_offset + SE_GENERAL_FLAGS)?;
+    let no_fat_chain = (general_flags & 0x02) != 0;
+    let name_length = *data.get(se_offset + SE_NAME_LENGTH)? as usize;
+
+    let first_cluster = read_u32_le(data, se_offset + SE_FIRST_CLUSTER)?;
+    let file_size = read_u64_le(data, se_offset + SE_DATA_LENGTH)?;
+
+    let mut filename = String::with_capacity(name_length);
+    let mut chars_collected = 0;
+
+    for i in 2..total_entries {
+        let fn_offset = i * DIRECTORY_ENTRY_SIZE;
+        if fn_offset + DIRECTORY_ENTRY_SIZE > data.len() {
+            break;
+        }
+
+        let fn_type = *data.get(fn_offset)?;
+        if fn_type != ENTRY_FILENAME && fn_type != ENTRY_DELETED_FILENAME {
+            break;
+        }
+
+        for j in 0..15 {
+            if chars_collected >= name_length {
+                break;
+            }
+            let char_offset = fn_offset + FN_FILE_NAME + j * 2;
+            let ch = match read_u16_le(data, char_offset) {
+                Some(value) => value,
+                None => break,
+            };
+            if ch == 0 {
+                break;
+            }
+            if let Some(c) = char::from_u32(ch as u32) {
+                filename.push(c);
+                chars_collected += 1;
+            }
+        }
+    }
+
+    if first_cluster < 2 && file_size > 0 {
+        return None;
+    }
+
+    Some((
+        ExFatEntry {
+            offset: base_offset,
+            data_offset: None,
+            is_deleted,
+            is_directory,
+            filename,
+            size: file_size,
+            first_cluster,
+            no_fat_chain,
+            modified,
+            times,
+            suspicious_reasons: Vec::new(),
+            reconstruction: None,
+        },
+        total_entries,
+    ))
+}
+
+pub fn scan_for_entries(data: &[u8], base_offset: u64) -> Vec<ExFatEntry> {
+    let mut entries = Vec::new();
+    let mut pos = 0usize;
+
+    while pos + DIRECTORY_ENTRY_SIZE <= data.len() {
+        if let Some((entry, consumed)) = parse_entry_set(&data[pos..], base_offset + pos as u64) {
+            entries.push(entry);
+            pos = pos.saturating_add(consumed * DIRECTORY_ENTRY_SIZE);
+        } else {
+            pos = pos.saturating_add(DIRECTORY_ENTRY_SIZE);
+        }
+    }
+
+    entries
+}
+
+/// Image ranges `[start, end)` of a directory's clusters, merged where contiguous.
+///
+/// `size` is `None` for the root directory, which has no entry; its chain is
+/// followed to the end-of-chain marker.
+fn directory_ranges(
+    data: &[u8],
+    params: &ExFatBootParams,
+    first_cluster: u32,
+    size: Option<u64>,
+    no_fat_chain: bool,
+) -> Vec<(u64, u64)> {
+    let heap_end = params.cluster_count as u64 + 2;
+    let clusters = size.map_or(MAX_DIRECTORY_CLUSTERS, |size| {
+        size.div_ceil(params.cluster_size).min(MAX_DIRECTORY_CLUSTERS)
+    });
+
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    let mut visited = HashSet::new();
+    let mut cluster = first_cluster;
+
+    for _ in 0..clusters {
+        if cluster < 2 || cluster as u64 >= heap_end || !visited.insert(cluster) {
+            break;
+        }
+        let Some(start) = cluster_to_offset(params, cluster) else { break };
+        let end = start.saturating_add(params.cluster_size).min(data.len() as u64);
+        if end <= start {
+            break;
+        }
+
+        match ranges.last_mut() {
+            Some(range) if range.1 == start => range.1 = end,
+            _ => ranges.push((start, end)),
+        }
+
+        cluster = if no_fat_chain {
+            cluster + 1
+        } else {
+            match fat_next_cluster(data, params, cluster) {
+                Some(next) => next,
+                None => break,
+            }
+        };
+    }
+
+    ranges
+}
+
+/// Walk the directory tree from the root cluster.
+///
+/// Returns every entry set found (live and deleted, files and directories)
+/// and the directory ranges that were read. Deleted directories are read as
+/// contiguous runs since their FAT links may already be reused. An entry set
+/// straddling two non-adjacent clusters of a fragmented directory is missed.
+pub fn walk_directory_tree(data: &[u8], params: &ExFatBootParams) -> (Vec<ExFatEntry>, Vec<(u64, u64)>) {
+    let mut entries = Vec::new();
+    let mut regions = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(params.root_dir_cluster, None, false)];
+
+    while let Some((cluster, size, no_fat_chain)) = pending.pop() {
+        if !visited.insert(cluster) {
+            continue;
+        }
+
+        for (start, end) in directory_ranges(data, params, cluster, size, no_fat_chain) {
+            for entry in scan_for_entries(&data[start as usize..end as usize], start) {
+                if entry.is_directory && entry.first_cluster >= 2 {
+                    pending.push((
+                        entry.first_cluster,
+                        Some(entry.size),
+                        entry.no_fat_chain || entry.is_deleted,
+                    ));
+                }
+                entries.push(entry);
+            }
+            regions.push((start, end));
+        }
+    }
+
+    (entries, regions)
+}
+
+/// Clusters of a directory in order (see `directory_ranges`), each wholly
+/// inside the image
+fn directory_clusters(
+    data: &[u8],
+    params: &ExFatBootParams,
+    first_cluster: u32,
+    size: Option<u64>,
+    contiguous: bool,
+) -> Vec<u32> {
+    let heap_end = params.cluster_count as u64 + 2;
+    let limit = size.map_or(MAX_DIRECTORY_CLUSTERS, |size| {
+        size.div_ceil(params.cluster_size).min(MAX_DIRECTORY_CLUSTERS)
+    });
+    let mut clusters = Vec::new();
+    let mut cluster = first_cluster;
+    while (clusters.len() as u64) < limit
+        && cluster >= 2
+        && (cluster as u64) < heap_end
+        && !clusters.contains(&cluster)
+    {
+        match cluster_to_offset(params, cluster) {
+            Some(start) if start.saturating_add(params.cluster_size) <= data.len() as u64 => {}
+            _ => break,
+        }
+        clusters.push(cluster);
+        cluster = if contiguous {
+            cluster + 1
+        } else {
+            match fat_next_cluster(data, params, cluster) {
+                Some(next) => next,
+                None => break,
+            }
+        };
+    }
+    clusters
+}
+
+/// Entry sets of a directory with their image offsets. The clusters are
+/// joined first, so a set crossing two clusters of a fragmented directory is kept
+fn directory_entries(data: &[u8], params: &ExFatBootParams, clusters: &[u32]) -> Vec<ExFatEntry> {
+    let cluster_size = params.cluster_size as usize;
+    let starts: Vec<u64> = clusters.iter().filter_map(|&cluster| cluster_to_offset(params, cluster)).collect();
+    let mut contents = Vec::with_capacity(starts.len() * cluster_size);
+    for &start in &starts {
+        contents.extend_from_slice(&data[start as usize..start as usize + cluster_size]);
+    }
+    let image_offset = |pos: usize| starts[pos / cluster_size] + (pos % cluster_size) as u64;
+
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while pos + DIRECTORY_ENTRY_SIZE <= contents.len() {
+        match parse_entry_set(&contents[pos..], image_offset(pos)) {
+            Some((entry, consumed)) => {
+                entries.push(entry);
+                pos += consumed * DIRECTORY_ENTRY_SIZE;
+            }
+            None => pos += DIRECTORY_ENTRY_SIZE,
+        }
+    }
+    entries
+}
+
+/// Every entry of the directory tree (files and directories, deleted ones
+/// when `include_deleted`) with the directories holding it from the root,
+/// as path components
+fn walk_tree(data: &[u8], params: &ExFatBootParams, include_deleted: bool) -> Vec<(Vec<String>, ExFatEntry)> {
+    let mut tree = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(Vec::new(), params.root_dir_cluster, None, false)];
+
+    while let Some((parents, cluster, size, contiguous)) = pending.pop() {
+        if !visited.insert(cluster) {
+            continue;
+        }
+        let clusters = directory_clusters(data, params, cluster, size, contiguous);
+        for entry in directory_entries(data, params, &clusters) {
+            if entry.is_deleted && !include_deleted {
+                continue;
+            }
+            if entry.is_directory && entry.first_cluster >= 2 && parents.len() < MAX_TREE_DEPTH {
+                let mut path = parents.clone();
+                path.push(path_component(&entry.filename, entry.offset));
+                pending.push((path, entry.first_cluster, Some(entry.size), entry.no_fat_chain || entry.is_deleted));
+            }
+            tree.push((parents.clone(), entry));
+        }
+    }
+    tree
+}
+
+/// `name` as one path component: separators and control characters become
+/// `_`; an empty name, `.` and `..` are named after the entry offset
+fn path_component(name: &str, entry_offset: u64) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+        .collect();
+    match name.trim() {
+        "" | "." | ".." => format!("entry_0x{:X}", entry_offset),
+        _ => name,
+    }
+}
+
+/// `path`, or with ` (0x<entry offset>)` before its extension when a file
+/// was already written there (a deleted and a live file of the same name)
+fn unique_path(path: PathBuf, entry_offset: u64, taken: &HashSet<PathBuf>) -> PathBuf {
+    if !taken.contains(&path) {
+        return path;
+    }
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{} (0x{:X}).{}", stem, entry_offset, extension.to_string_lossy()),
+        None => format!("{} (0x{:X})", stem, entry_offset),
+    };
+    path.with_file_name(name)
+}
+
+/// Write the files of the directory tree under `output_dir` in their
+/// original directories, deleted directories and files included unless
+/// `include_deleted` is false. Entries failing `mark_suspicious_entries` are
+/// skipped; files keep their modified and accessed times.
+/// Returns (path relative to `output_dir`, bytes written, is_deleted) per file
+pub fn extract_tree_to(
+    data: &[u8],
+    params: &ExFatBootParams,
+    output_dir: &Path,
+    include_deleted: bool,
+) -> std::io::Result<Vec<(PathBuf, u64, bool)>> {
+    let (directories, files): (Vec<_>, Vec<_>) =
+        walk_tree(data, params, include_deleted).into_iter().partition(|(_, entry)| entry.is_directory);
+    let (parents, mut entries): (Vec<_>, Vec<_>) = files.into_iter().unzip();
+    mark_suspicious_entries(data, &mut entries, params);
+
+    let relative = |parents: &[String], entry: &ExFatEntry| -> PathBuf {
+        let mut path: PathBuf = parents.iter().collect();
+        path.push(path_component(&entry.filename, entry.offset));
+        path
+    };
+    for (parents, directory) in &directories {
+        std::fs::create_dir_all(output_dir.join(relative(parents, directory)))?;
+    }
+
+    // Live files first: they keep the original name when a deleted one shares it
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&i| entries[i].is_deleted);
+    let mut written = Vec::new();
+    let mut taken = HashSet::new();
+    for i in order {
+        let entry = &entries[i];
+        if entry.is_suspicious() {
+            continue;
+        }
+        let path = unique_path(relative(&parents[i], entry), entry.offset, &taken);
+        let output_path = output_dir.join(&path);
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let bytes = extract_entry_to_path(data, params, entry, &output_path)?;
+        entry.times.apply(&output_path)?;
+        taken.insert(path.clone());
+        written.push((path, bytes, entry.is_deleted));
+    }
+    Ok(written)
+}
+
+/// Times of the files in the directory tree by the image offset of their
+/// first data byte; a live entry wins over a deleted one for the same
+/// cluster. Entries failing `mark_suspicious_entries` are left out
+pub fn entry_times(data: &[u8], params: &ExFatBootParams) -> HashMap<u64, FileTimestamps> {
+    let (mut entries, _) = walk_directory_tree(data, params);
+    entries.retain(|e| !e.is_directory && e.size > 0 && !e.times.is_empty());
+    mark_suspicious_entries(data, &mut entries, params);
+    entries.sort_by_key(|e| !e.is_deleted);
+    entries
+        .into_iter()
+        .filter(|e| !e.is_suspicious())
+        .filter_map(|e| Some((cluster_to_offset(params, e.first_cluster)?, e.times)))
+        .collect()
+}
+
+pub fn populate_data_offsets(entries: &mut [ExFatEntry], params: &ExFatBootParams) {
+    for entry in entries {
+        entry.data_offset = cluster_to_offset(params, entry.first_cluster);
+    }
+}
+
+/// Cluster runs `[start, end)` occupied by an entry, walking the FAT when needed
+fn entry_cluster_runs(
+    data: &[u8],
+    params: &ExFatBootParams,
+    entry: &ExFatEntry,
+    clusters_needed: u64,
+) -> Result<Vec<(u64, u64)>, String> {
+    let heap_end = params.cluster_count as u64 + 2;
+    let first = entry.first_cluster as u64;
+
+    if let Some(reconstruction) = &entry.reconstruction {
+        return Ok(vec![(first, first.saturating_add(reconstruction.clusters))]);
+    }
+
+    // Contiguous allocation, and deleted entries whose FAT links are no longer reliable
+    if entry.no_fat_chain || entry.is_deleted {
+        let end = first.saturating_add(clusters_needed);
+        if end > heap_end {
+            return Err(format!(
+                "contiguous run {}..{} exceeds cluster heap ({} clusters)",
+                first, end, params.cluster_count
+            ));
+        }
+        return Ok(vec![(first, end)]);
+    }
+
+    let mut runs: Vec<(u64, u64)> = Vec::new();
+    let mut cluster = entry.first_cluster;
+    let mut visited = HashSet::new();
+
+    for walked in 0..clusters_needed {
+        if cluster < 2 || cluster as u64 >= heap_end {
+            return Err(format!("FAT chain leaves cluster heap at cluster {:#X}", cluster));
+        }
+        if !visited.insert(cluster) {
+            return Err(format!("FAT chain loops at cluster {:#X}", cluster));
+        }
+
+        match runs.last_mut() {
+            Some(run) if run.1 == cluster as u64 => run.1 += 1,
+            _ => runs.push((cluster as u64, cluster as u64 + 1)),
+        }
+
+        if walked + 1 == clusters_needed {
+            break;
+        }
+
+        cluster = match fat_next_cluster(data, params, cluster) {
+            Some(next) if next >= 0xFFFFFFF7 => {
+                return Err(format!(
+                    "FAT chain ends after {} of {} clusters",
+                    walked + 1,
+                    clusters_needed
+                ));
+            }
+            Some(next) => next,
+            None => return Err("FAT entry beyond end of image".to_string()),
+        };
+    }
+
+    Ok(runs)
+}
+
+/// Image bytes of one cluster, cut at the end of the image
+pub fn cluster_slice<'a>(data: &'a [u8], params: &ExFatBootParams, cluster: u32) -> Option<&'a [u8]> {
+    let start = cluster_to_offset(params, cluster)?;
+    let end = start.saturating_add(params.cluster_size).min(data.len() as u64);
+    (end > start).then(|| &data[start as usize..end as usize])
+}
+
+/// Allocation Bitmap of the volume: bit `n` set = cluster `n + 2` in use
+#[derive(Clone, Debug, PartialEq)]
+pub struct AllocationBitmap {
+    bits: Vec<u8>,
+    cluster_count: u32,
+}
+
+impl AllocationBitmap {
+    pub fn new(bits: Vec<u8>, cluster_count: u32) -> Self {
+        Self { bits, cluster_count }
+    }
+
+    /// Clusters past the end of a truncated bitmap count as allocated
+    pub fn is_allocated(&self, cluster: u32) -> bool {
+        let Some(index) = cluster.checked_sub(2).filter(|&index| index < self.cluster_count) else {
+            return true;
+        };
+        self.bits.get(index as usize / 8).is_none_or(|byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    pub fn allocated_clusters(&self) -> u64 {
+        (2..self.cluster_count as u64 + 2).filter(|&cluster| self.is_allocated(cluster as u32)).count() as u64
+    }
+
+    /// Image ranges `[start, end)` of the free clusters, merged where
+    /// contiguous and cut at `image_len`
+    pub fn unallocated_ranges(&self, params: &ExFatBootParams, image_len: u64) -> Vec<(u64, u64)> {
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+        for cluster in 2..self.cluster_count.saturating_add(2) {
+            if self.is_allocated(cluster) {
+                continue;
+            }
+            let Some(start) = cluster_to_offset(params, cluster).filter(|&start| start < image_len) else {
+                break;
+            };
+            let end = start.saturating_add(params.cluster_size).min(image_len);
+            match ranges.last_mut() {
+                Some(range) if range.1 == start => range.1 = end,
+                _ => ranges.push((start, end)),
+            }
+        }
+        ranges
+    }
+}
+
+/// Read the Allocation Bitmap named by the root directory's 0x81 entry
+/// (the first bitmap on TexFAT volumes, which have two)
+pub fn read_allocation_bitmap(data: &[u8], params: &ExFatBootParams) -> Option<AllocationBitmap> {
+    let bitmap_len = (params.cluster_count as u64).div_ceil(8);
+    for (start, end) in directory_ranges(data, params, params.root_dir_cluster, None, false) {
+        let directory = &data[start as usize..end as usize];
+        for entry in directory.chunks_exact(DIRECTORY_ENTRY_SIZE) {
+            if entry[0] != ENTRY_ALLOCATION_BITMAP || entry[1] & 0x01 != 0 {
+                continue;
+            }
+            let first_cluster = read_u32_le(entry, SE_FIRST_CLUSTER)?;
+            let size = read_u64_le(entry, SE_DATA_LENGTH)?.min(bitmap_len);
+            let mut bits = extract_file_content(data, params, first_cluster, size, false);
+            // Formatters allocate the bitmap contiguously; use that when the FAT chain is short
+            if (bits.len() as u64) < size {
+                bits = extract_file_content(data, params, first_cluster, size, true);
+            }
+            if bits.is_empty() {
+                return None;
+            }
+            return Some(AllocationBitmap::new(bits, params.cluster_count));
+        }
+    }
+    None
+}
+
+/// Flag entries whose size or cluster allocation cannot be right for this volume.
+///
+/// Checks: size vs cluster_count, chains/runs staying within the cluster heap,
+/// data overlapping the FAT region, and clusters claimed by more than one entry
+/// (both entries are flagged, except that a live entry overlapping a deleted
+/// one keeps its clusters and only the deleted entry is flagged).
+/// Violating entries get `suspicious_reasons` instead of being dropped, so callers
+/// can still report them without extracting garbage.
+pub fn mark_suspicious_entries(data: &[u8], entries: &mut [ExFatEntry], params: &ExFatBootParams) {
+    let fat_start = params.fat_offset;
+    let fat_end = fat_start
+        .saturating_add((params.fat_length_sectors as u64).saturating_mul(params.sector_size));
+
+    // (start_cluster, end_cluster, entry index) for the overlap sweep
+    let mut claimed: Vec<(u64, u64, usize)> = Vec::new();
+
+    for (idx, entry) in entries.iter_mut().enumerate() {
+        entry.suspicious_reasons.clear();
+        if entry.size == 0 {
+            continue;
+        }
+
+        let clusters_needed = entry.size.div_ceil(params.cluster_size);
+        if clusters_needed > params.cluster_count as u64 {
+            entry.suspicious_reasons.push(format!(
+                "size {} bytes needs {} clusters, volume has {}",
+                entry.size, clusters_needed, params.cluster_count
+            ));
+            continue;
+        }
+
+        if entry.first_cluster < 2 || entry.first_cluster as u64 >= params.cluster_count as u64 + 2 {
+            entry.suspicious_reasons.push(format!(
+                "first cluster {:#X} outside cluster heap",
+                entry.first_cluster
+            ));
+            continue;
+        }
+
+        let runs = match entry_cluster_runs(data, params, entry, clusters_needed) {
+            Ok(runs) => runs,
+            Err(reason) => {
+                entry.suspicious_reasons.push(reason);
+                continue;
+            }
+        };
+
+        for &(start, end) in &runs {
+            let start_byte = cluster_to_offset(params, start as u32).unwrap_or(u64::MAX);
+            let end_byte = start_byte.saturating_add((end - start).saturating_mul(params.cluster_size));
+            if start_byte < fat_end && end_byte > fat_start {
+                entry.suspicious_reasons.push(format!(
+                    "clusters {:#X}..{:#X} overlap FAT region",
+                    start, end
+                ));
+                break;
+            }
+            claimed.push((start, end, idx));
+        }
+    }
+
+    claimed.sort_unstable();
+    let mut overlaps: Vec<(usize, usize)> = Vec::new();
+    let mut active: Option<(u64, usize)> = None; // (end, idx) of the furthest-reaching run so far
+    for &(start, end, idx) in &claimed {
+        if let Some((active_end, active_idx)) = active {
+            if start < active_end && active_idx != idx {
+                overlaps.push((idx, active_idx));
+            }
+            if end > active_end {
+                active = Some((end, idx));
+            }
+        } else {
+            active = Some((end, idx));
+        }
+    }
+
+    for (a, b) in overlaps {
+        // Live files routinely sit on clusters a deleted file used to own:
+        // only the deleted side of such a pair is in doubt
+        for (this, other) in [(a, b), (b, a)] {
+            if entries[other].is_deleted && !entries[this].is_deleted {
+                continue;
+            }
+            let reason = format!("clusters overlap entry at {:#X}", entries[other].offset);
+            if !entries[this].suspicious_reasons.contains(&reason) {
+                entries[this].suspicious_reasons.push(reason);
+            }
+        }
+    }
+}
+
+/// Directory entry builders for the exFAT tests here and in the crates using
+/// this one (feature `test-util`)
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing {
+    use super::*;
+
+    /// File entry set: file entry, stream extension (contiguous for files),
+    /// and one name entry holding up to 15 UTF-16 units of `name`
+    pub fn entry_set(name: &str, deleted: bool, directory: bool, first_cluster: u32, size: u64) -> Vec<u8> {
+        let mut set = vec![0u8; 3 * DIRECTORY_ENTRY_SIZE];
+        set[0] = if deleted { ENTRY_DELETED_FILE } else { ENTRY_FILE };
+        set[1] = 2;
+        if directory {
+            set[FE_FILE_ATTRIBUTES] = ATTR_DIRECTORY as u8;
+        }
+        let stream = DIRECTORY_ENTRY_SIZE;
+        set[stream] = if deleted { ENTRY_DELETED_STREAM } else { ENTRY_STREAM };
+        set[stream + SE_GENERAL_FLAGS] = if directory { 0x01 } else { 0x03 };
+        set[stream + SE_NAME_LENGTH] = name.encode_utf16().count() as u8;
+        set[stream + SE_FIRST_CLUSTER..stream + SE_FIRST_CLUSTER + 4].copy_from_slice(&first_cluster.to_le_bytes());
+        set[stream + SE_DATA_LENGTH..stream + SE_DATA_LENGTH + 8].copy_from_slice(&size.to_le_bytes());
+        let name_entry = 2 * DIRECTORY_ENTRY_SIZE;
+        set[name_entry] = if deleted { ENTRY_DELETED_FILENAME } else { ENTRY_FILENAME };
+        for (i, unit) in name.encode_utf16().enumerate() {
+            let at = name_entry + FN_FILE_NAME + 2 * i;
+            set[at..at + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::entry_set;
+    use super::*;
+
+    fn build_boot_sector() -> Vec<u8> {
+        let mut data = vec![0u8; 512];
+        data[BS_FILE_SYSTEM_NAME..BS_FILE_SYSTEM_NAME + 8].copy_from_slice(b"EXFAT   ");
+        data[BS_BYTES_PER_SECTOR_SHIFT] = 9;
+        data[BS_SECTORS_PER_CLUSTER_SHIFT] = 0;
+        data[BS_FAT_OFFSET..BS_FAT_OFFSET + 4].copy_from_slice(&(1u32.to_le_bytes()));
+        data[BS_FAT_LENGTH..BS_FAT_LENGTH + 4].copy_from_slice(&(1u32.to_le_bytes()));
+        data[BS_CLUSTER_HEAP_OFFSET..BS_CLUSTER_HEAP_OFFSET + 4]
+            .copy_from_slice(&(2u32.to_le_bytes()));
+        data[BS_CLUSTER_COUNT..BS_CLUSTER_COUNT + 4].copy_from_slice(&(8u32.to_le_bytes()));
+        data[BS_FIRST_CLUSTER_OF_ROOT..BS_FIRST_CLUSTER_OF_ROOT + 4]
+            .copy_from_slice(&(2u32.to_le_bytes()));
+        data
+    }
+
+    fn build_entry_set() -> Vec<u8> {
+        let mut data = vec![0u8; DIRECTORY_ENTRY_SIZE * 3];
+        data[0] = ENTRY_FILE;
+        data[1] = 2;
+
+        let stream_offset = DIRECTORY_ENTRY_SIZE;
+        data[stream_offset] = ENTRY_STREAM;
+        data[stream_offset + SE_GENERAL_FLAGS] = 0x00;
+        data[stream_offset + SE_NAME_LENGTH] = 5;
+        data[stream_offset + SE_FIRST_CLUSTER..stream_offset + SE_FIRST_CLUSTER + 4]
+            .copy_from_slice(&2u32.to_le_bytes());
+        data[stream_offset + SE_DATA_LENGTH..stream_offset + SE_DATA_LENGTH + 8]
+            .copy_from_slice(&10u64.to_le_bytes());
+
+        let name_offset = DIRECTORY_ENTRY_SIZE * 2;
+        data[name_offset] = ENTRY_FILENAME;
+        let name_chars: [u16; 5] = [b'h' as u16, b'e' as u16, b'l' as u16, b'l' as u16, b'o' as u16];
+        for (i, ch) in name_chars.iter().enumerate() {
+            let start = name_offset + FN_FILE_NAME + i * 2;
+            data[start..start + 2].copy_from_slice(&ch.to_le_bytes());
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_find_boot_sector() {
+        let data = build_boot_sector();
+        let params = find_boot_sector(&data).expect("boot sector should be found");
+        assert_eq!(params.sector_size, 512);
+        assert_eq!(params.cluster_size, 512);
+        assert_eq!(params.fat_offset, 512);
+        assert_eq!(params.cluster_heap_offset, 1024);
+        assert_eq!(params.cluster_count, 8);
+        assert_eq!(params.root_dir_cluster, 2);
+    }
+
+    #[test]
+    fn test_parse_entry_set() {
+        let data = build_entry_set();
+        let (entry, consumed) = parse_entry_set(&data, 4096).expect("entry should parse");
+        assert_eq!(consumed, 3);
+        assert_eq!(entry.offset, 4096);
+        assert_eq!(entry.filename, "hello");
+        assert_eq!(entry.size, 10);
+        assert_eq!(entry.first_cluster, 2);
+        assert!(!entry.is_deleted);
+        assert!(entry.times.is_empty());
+
+        // 2023-12-31 21:41:05.37 at UTC+3, 2024-01-01 00:00 without offset, 2024-01-02 08:30 at UTC-5
+        let mut data = build_entry_set();
+        let pack = |year: u32, month: u32, day: u32, hour: u32, minute: u32, double_seconds: u32| {
+            (year - 1980) << 25 | month << 21 | day << 16 | hour << 11 | minute << 5 | double_seconds
+        };
+        let mut set = |field: usize, value: u32| data[field..field + 4].copy_from_slice(&value.to_le_bytes());
+        set(FE_CREATE_TIMESTAMP, pack(2023, 12, 31, 21, 41, 2));
+        set(FE_LAST_MODIFIED_TIMESTAMP, pack(2024, 1, 1, 0, 0, 0));
+        set(FE_LAST_ACCESSED_TIMESTAMP, pack(2024, 1, 2, 8, 30, 0));
+        data[FE_CREATE_10MS_INCREMENT] = 137;
+        data[FE_CREATE_UTC_OFFSET] = 0x80 | 12;
+        data[FE_LAST_ACCESSED_UTC_OFFSET] = 0x80 | (128 - 20);
+        let (entry, _) = parse_entry_set(&data, 4096).unwrap();
+        let rfc3339 = |time: Option<DateTime<FixedOffset>>| time.map(|time| time.to_rfc3339());
+        assert_eq!(rfc3339(entry.times.created).as_deref(), Some("2023-12-31T21:41:05.370+03:00"));
+        assert_eq!(rfc3339(entry.times.modified).as_deref(), Some("2024-01-01T00:00:00+00:00"));
+        assert_eq!(rfc3339(entry.times.accessed).as_deref(), Some("2024-01-02T08:30:00-05:00"));
+        assert_eq!(entry_timestamp(pack(2023, 2, 30, 0, 0, 0), 0, 0), None);
+
+        let path = std::env::temp_dir().join(format!("exfat_times_{}", std::process::id()));
+        std::fs::write(&path, b"hello").unwrap();
+        entry.times.apply(&path).unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(DateTime::<chrono::Utc>::from(modified).timestamp(), 1704067200);
+    }
+
+    #[test]
+    fn test_entry_timestamp_increment_and_utc_offset() {
+        // 2024-06-30 23:59:58 local
+        let raw = 44 << 25 | 6 << 21 | 30 << 16 | 23 << 11 | 59 << 5 | 29;
+        let rfc3339 =
+            |increment: u8, utc_offset: u8| entry_timestamp(raw, increment, utc_offset).map(|time| time.to_rfc3339());
+
+        // The increment adds 0-1.99 s to the 2-second time
+        assert_eq!(rfc3339(0, 0).as_deref(), Some("2024-06-30T23:59:58+00:00"));
+        assert_eq!(rfc3339(1, 0).as_deref(), Some("2024-06-30T23:59:58.010+00:00"));
+        assert_eq!(rfc3339(100, 0).as_deref(), Some("2024-06-30T23:59:59+00:00"));
+        assert_eq!(rfc3339(199, 0).as_deref(), Some("2024-06-30T23:59:59.990+00:00"));
+        assert_eq!(rfc3339(200, 0), None);
+
+        // Bit 7 marks a recorded offset, a signed count of 15 minutes
+        assert_eq!(rfc3339(0, 0x80).as_deref(), Some("2024-06-30T23:59:58+00:00"));
+        assert_eq!(rfc3339(0, 0x80 | 56).as_deref(), Some("2024-06-30T23:59:58+14:00"));
+        assert_eq!(rfc3339(0, 0x80 | 0x7F).as_deref(), Some("2024-06-30T23:59:58-00:15"));
+        assert_eq!(rfc3339(0, 0x80 | 0x40).as_deref(), Some("2024-06-30T23:59:58-16:00"));
+        assert_eq!(rfc3339(0, 56).as_deref(), Some("2024-06-30T23:59:58+00:00"));
+        assert_eq!(entry_timestamp(raw, 0, 0x80 | 12).unwrap().timestamp(), 1719791998 - 3 * 3600);
+
+        // Each field reads its own increment and offset; last accessed has no increment
+        let mut data = build_entry_set();
+        for field in [FE_CREATE_TIMESTAMP, FE_LAST_MODIFIED_TIMESTAMP, FE_LAST_ACCESSED_TIMESTAMP] {
+            data[field..field + 4].copy_from_slice(&raw.to_le_bytes());
+        }
+        data[FE_CREATE_10MS_INCREMENT] = 5;
+        data[FE_LAST_MODIFIED_10MS_INCREMENT] = 150;
+        data[FE_CREATE_UTC_OFFSET] = 0x80 | 4;
+        data[FE_LAST_MODIFIED_UTC_OFFSET] = 0x80 | (128 - 8);
+        data[FE_LAST_ACCESSED_UTC_OFFSET] = 0x80 | 2;
+        let (entry, _) = parse_entry_set(&data, 0).unwrap();
+        let rfc3339 = |time: Option<DateTime<FixedOffset>>| time.map(|time| time.to_rfc3339());
+        assert_eq!(rfc3339(entry.times.created).as_deref(), Some("2024-06-30T23:59:58.050+01:00"));
+        assert_eq!(rfc3339(entry.times.modified).as_deref(), Some("2024-06-30T23:59:59.500-02:00"));
+        assert_eq!(rfc3339(entry.times.accessed).as_deref(), Some("2024-06-30T23:59:58+00:30"));
+    }
+
+    #[test]
+    fn test_walk_directory_tree() {
+        let mut data = build_boot_sector();
+        data.resize(1024 + 4 * 512, 0);
+        let params = find_boot_sector(&data).unwrap();
+
+        // Root (cluster 2, single-cluster chain) holds directory "hello" at cluster 3
+        data[512 + 2 * 4..512 + 3 * 4].copy_from_slice(&0xFFFFFFFFu32.to_le_bytes());
+        let mut dir = build_entry_set();
+        dir[FE_FILE_ATTRIBUTES..FE_FILE_ATTRIBUTES + 2].copy_from_slice(&ATTR_DIRECTORY.to_le_bytes());
+        dir[DIRECTORY_ENTRY_SIZE + SE_GENERAL_FLAGS] = 0x02;
+        dir[DIRECTORY_ENTRY_SIZE + SE_FIRST_CLUSTER..DIRECTORY_ENTRY_SIZE + SE_FIRST_CLUSTER + 4]
+            .copy_from_slice(&3u32.to_le_bytes());
+        dir[DIRECTORY_ENTRY_SIZE + SE_DATA_LENGTH..DIRECTORY_ENTRY_SIZE + SE_DATA_LENGTH + 8]
+            .copy_from_slice(&512u64.to_le_bytes());
+        data[1024..1024 + dir.len()].copy_from_slice(&dir);
+
+        // Subdirectory holds file "hello" at cluster 4
+        let mut file = build_entry_set();
+        file[DIRECTORY_ENTRY_SIZE + SE_FIRST_CLUSTER..DIRECTORY_ENTRY_SIZE + SE_FIRST_CLUSTER + 4]
+            .copy_from_slice(&4u32.to_le_bytes());
+        data[1536 + 64..1536 + 64 + file.len()].copy_from_slice(&file);
+
+        let (entries, regions) = walk_directory_tree(&data, &params);
+        assert_eq!(regions, vec![(1024, 1536), (1536, 2048)]);
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_directory);
+        assert!(!entries[1].is_directory);
+        assert_eq!(entries[1].offset, 1600);
+        assert_eq!(entries[1].first_cluster, 4);
+    }
+
+    #[test]
+    fn test_parse_entry_set_bounds() {
+        let data = vec![0u8; DIRECTORY_ENTRY_SIZE - 1];
+        assert!(parse_entry_set(&data, 0).is_none());
+    }
+
+    #[test]
+    fn test_extract_file_content_chain() {
+        let mut data = vec![0u8; 3072];
+        let params = ExFatBootParams {
+            sector_size: 512,
+            cluster_size: 512,
+            fat_offset: 512,
+            fat_length_sectors: 1,
+            cluster_heap_offset: 1024,
+            cluster_count: 4,
+            root_dir_cluster: 2,
+            boot_sector_offset: 0,
+        };
+
+        let fat_cluster2_offset = 512 + 2 * 4;
+        data[fat_cluster2_offset..fat_cluster2_offset + 4].copy_from_slice(&3u32.to_le_bytes());
+        let fat_cluster3_offset = 512 + 3 * 4;
+        data[fat_cluster3_offset..fat_cluster3_offset + 4]
+            .copy_from_slice(&0xFFFFFFFFu32.to_le_bytes());
+
+        let cluster2_offset = 1024;
+        data[cluster2_offset..cluster2_offset + 5].copy_from_slice(b"hello");
+        let cluster3_offset = 1536;
+        data[cluster3_offset..cluster3_offset + 5].copy_from_slice(b"world");
+
+        let content = extract_file_content(&data, &params, 2, 700, false);
+        assert_eq!(&content[..5], b"hello");
+        assert_eq!(&content[512..517], b"world");
+
+        let mut streamed = Vec::new();
+        let written = extract_file_to_writer(&data, &params, 2, 700, false, &mut streamed).unwrap();
+        assert_eq!(written, 700);
+        assert_eq!(streamed, content);
+    }
+
+    fn entry_at(offset: u64, first_cluster: u32, size: u64) -> ExFatEntry {
+        ExFatEntry {
+            is_directory: false,
+            offset,
+            data_offset: None,
+            is_deleted: false,
+            filename: String::new(),
+            size,
+            first_cluster,
+            no_fat_chain: true,
+            modified: 0,
+            times: FileTimestamps::default(),
+            suspicious_reasons: Vec::new(),
+            reconstruction: None,
+        }
+    }
+
+    #[test]
+    fn test_allocation_bitmap() {
+        let mut data = build_boot_sector();
+        data.resize(1024 + 8 * 512, 0);
+        let params = find_boot_sector(&data).unwrap();
+        // Root directory in cluster 2, bitmap in cluster 3
+        data[512 + 2 * 4..512 + 3 * 4].copy_from_slice(&0xFFFFFFFFu32.to_le_bytes());
+        data[512 + 3 * 4..512 + 4 * 4].copy_from_slice(&0xFFFFFFFFu32.to_le_bytes());
+        let entry = 1024 + 64;
+        data[entry] = ENTRY_ALLOCATION_BITMAP;
+        data[entry + SE_FIRST_CLUSTER..entry + SE_FIRST_CLUSTER + 4].copy_from_slice(&3u32.to_le_bytes());
+        data[entry + SE_DATA_LENGTH..entry + SE_DATA_LENGTH + 8].copy_from_slice(&1u64.to_le_bytes());
+        // Clusters 2, 3 and 6 in use
+        data[1536] = 0b0001_0011;
+
+        let bitmap = read_allocation_bitmap(&data, &params).unwrap();
+        assert!(bitmap.is_allocated(2) && !bitmap.is_allocated(4) && bitmap.is_allocated(6));
+        assert!(bitmap.is_allocated(10), "outside the heap");
+        assert_eq!(bitmap.allocated_clusters(), 3);
+        assert_eq!(
+            bitmap.unallocated_ranges(&params, data.len() as u64),
+            vec![(2048, 3072), (3584, 5120)]
+        );
+        assert_eq!(bitmap.unallocated_ranges(&params, 4000), vec![(2048, 3072), (3584, 4000)]);
+    }
+
+    #[test]
+    fn test_mark_suspicious_entries() {
+        let data = vec![0u8; 8192];
+        let params = ExFatBootParams {
+            sector_size: 512,
+            cluster_size: 512,
+            fat_offset: 512,
+            fat_length_sectors: 1,
+            cluster_heap_offset: 1024,
+            cluster_count: 8,
+            root_dir_cluster: 2,
+            boot_sector_offset: 0,
+        };
+
+        let mut entries = vec![
+            entry_at(0x100, 2, 1024),         // clusters 2..4, fine
+            entry_at(0x200, 3, 512),          // overlaps the first entry
+            entry_at(0x300, 6, 1 << 50),      // petabyte-sized
+            entry_at(0x400, 8, 4 * 512),      // run past end of heap
+            entry_at(0x500, 5, 512),          // fine
+        ];
+        mark_suspicious_entries(&data, &mut entries, &params);
+
+        assert!(entries[0].is_suspicious());
+        assert!(entries[1].is_suspicious());
+        assert!(entries[2].suspicious_reasons[0].contains("clusters"));
+        assert!(entries[3].is_suspicious());
+        assert!(!entries[4].is_suspicious());
+    }
+
+    #[test]
+    fn test_mark_suspicious_broken_chain() {
+        let mut data = vec![0u8; 4096];
+        let params = ExFatBootParams {
+            sector_size: 512,
+            cluster_size: 512,
+            fat_offset: 512,
+            fat_length_sectors: 1,
+            cluster_heap_offset: 1024,
+            cluster_count: 4,
+            root_dir_cluster: 2,
+            boot_sector_offset: 0,
+        };
+        // cluster 2 -> 40 (outside heap)
+        data[512 + 8..512 + 12].copy_from_slice(&40u32.to_le_bytes());
+
+        let mut entry = entry_at(0x100, 2, 1024);
+        entry.no_fat_chain = false;
+        let mut entries = vec![entry];
+        mark_suspicious_entries(&data, &mut entries, &params);
+        assert!(entries[0].suspicious_reasons[0].contains("leaves cluster heap"));
+    }
+
+    #[test]
+    fn test_extract_tree_keeps_directories() {
+        // 512-byte clusters from 1024 on; the root is cluster 2
+        let mut image = vec![0u8; 1024 + 16 * 512];
+        image[BS_FILE_SYSTEM_NAME..BS_FILE_SYSTEM_NAME + 8].copy_from_slice(b"EXFAT   ");
+        image[BS_BYTES_PER_SECTOR_SHIFT] = 9;
+        image[BS_FAT_OFFSET] = 1;
+        image[BS_FAT_LENGTH] = 1;
+        image[BS_CLUSTER_HEAP_OFFSET] = 2;
+        image[BS_CLUSTER_COUNT] = 16;
+        image[BS_FIRST_CLUSTER_OF_ROOT] = 2;
+        for cluster in [2, 3] {
+            image[512 + 4 * cluster..512 + 4 * cluster + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        }
+        let cluster = |n: usize| 1024 + (n - 2) * 512;
+        let mut put = |at: usize, bytes: &[u8]| image[at..at + bytes.len()].copy_from_slice(bytes);
+
+        let root = [
+            entry_set("docs", false, true, 3, 512),
+            entry_set("old", true, true, 4, 512),
+            entry_set("x.txt", false, false, 5, 5),
+            entry_set("x.txt", true, false, 6, 5),
+        ]
+        .concat();
+        put(cluster(2), &root);
+        let mut a = entry_set("a.txt", false, false, 7, 3);
+        // Modified 2024-01-01 00:00:00 UTC
+        let modified = (44u32 << 25) | (1 << 21) | (1 << 16);
+        a[FE_LAST_MODIFIED_TIMESTAMP..FE_LAST_MODIFIED_TIMESTAMP + 4].copy_from_slice(&modified.to_le_bytes());
+        put(cluster(3), &a);
+        put(cluster(4), &entry_set("b/c.txt", true, false, 8, 3));
+        put(cluster(5), b"live!");
+        put(cluster(6), b"gone!");
+        put(cluster(7), b"aaa");
+        put(cluster(8), b"bbb");
+
+        let params = find_boot_sector(&image).unwrap();
+        let output = std::env::temp_dir().join(format!("exfat_tree_{}", std::process::id()));
+        let mut written = extract_tree_to(&image, &params, &output, true).unwrap();
+        written.sort();
+        let listed: Vec<(String, u64, bool)> = written
+            .iter()
+            .map(|(path, bytes, deleted)| (path.to_string_lossy().into_owned(), *bytes, *deleted))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("docs/a.txt".to_string(), 3, false),
+                ("old/b_c.txt".to_string(), 3, true),
+                ("x (0x520).txt".to_string(), 5, true),
+                ("x.txt".to_string(), 5, false),
+            ]
+        );
+        assert_eq!(std::fs::read(output.join("x.txt")).unwrap(), b"live!");
+        assert_eq!(std::fs::read(output.join("old/b_c.txt")).unwrap(), b"bbb");
+        let modified = std::fs::metadata(output.join("docs/a.txt")).unwrap().modified().unwrap();
+        assert_eq!(modified.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(), 1704067200);
+        std::fs::remove_dir_all(&output).unwrap();
+
+        let live = extract_tree_to(&image, &params, &output, false).unwrap();
+        assert_eq!(live.len(), 2);
+        assert!(!output.join("old").exists());
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_live_file_on_deleted_clusters() {
+        let mut image = vec![0u8; 1024 + 16 * 512];
+        image[BS_FILE_SYSTEM_NAME..BS_FILE_SYSTEM_NAME + 8].copy_from_slice(b"EXFAT   ");
+        image[BS_BYTES_PER_SECTOR_SHIFT] = 9;
+        image[BS_FAT_OFFSET] = 1;
+        image[BS_FAT_LENGTH] = 1;
+        image[BS_CLUSTER_HEAP_OFFSET] = 2;
+        image[BS_CLUSTER_COUNT] = 16;
+        image[BS_FIRST_CLUSTER_OF_ROOT] = 2;
+        image[512 + 8..512 + 12].copy_from_slice(&u32::MAX.to_le_bytes());
+        let cluster = |n: usize| 1024 + (n - 2) * 512;
+
+        // "new.txt" was written over clusters 5-6 of the deleted "old.txt" (5-8);
+        // the deleted "a.txt" and "b.txt" claim cluster 10 both
+        let mut new = entry_set("new.txt", false, false, 5, 600);
+        let modified = (44u32 << 25) | (1 << 21) | (1 << 16);
+        new[FE_LAST_MODIFIED_TIMESTAMP..FE_LAST_MODIFIED_TIMESTAMP + 4].copy_from_slice(&modified.to_le_bytes());
+        let root = [
+            entry_set("old.txt", true, false, 5, 2000),
+            new,
+            entry_set("a.txt", true, false, 10, 100),
+            entry_set("b.txt", true, false, 10, 100),
+        ]
+        .concat();
+        image[cluster(2)..cluster(2) + root.len()].copy_from_slice(&root);
+        image[cluster(5)..cluster(5) + 3].copy_from_slice(b"new");
+
+        let params = find_boot_sector(&image).unwrap();
+        let (mut entries, _) = walk_directory_tree(&image, &params);
+        mark_suspicious_entries(&image, &mut entries, &params);
+        let suspicious: Vec<(&str, bool)> = entries.iter().map(|e| (e.filename.as_str(), e.is_suspicious())).collect();
+        assert_eq!(suspicious, [("old.txt", true), ("new.txt", false), ("a.txt", true), ("b.txt", true)]);
+        assert_eq!(entries[0].suspicious_reasons, ["clusters overlap entry at 0x460"]);
+
+        let times = entry_times(&image, &params);
+        assert_eq!(times.len(), 1);
+        assert_eq!(times[&(cluster(5) as u64)].modified.unwrap().timestamp(), 1704067200);
+
+        let output = std::env::temp_dir().join(format!("exfat_reused_{}", std::process::id()));
+        let written = extract_tree_to(&image, &params, &output, true).unwrap();
+        assert_eq!(written, [(PathBuf::from("new.txt"), 600, false)]);
+        assert_eq!(&std::fs::read(output.join("new.txt")).unwrap()[..3], b"new");
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+}
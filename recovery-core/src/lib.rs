@@ -0,0 +1,27 @@
+//! Code shared by rust-recovery (CLI) and rust_accelerator (PyO3 extension)
+//!
+//! Both crates re-export these modules under their previous paths
+//! (`crate::simd_search`, `crate::exfat`, ...), so fixes and new backends land in one place.
+//! - SIMD pattern search with runtime dispatch (AVX2 asm/SSE4.2, NEON on aarch64, scalar fallback)
+//! - 32/64-byte block classification for the heatmap and the exFAT scans
+//! - Duplicate suppression in chunk overlaps
+//! - Pattern tables, validators, link context scoring and pattern profiles (`matcher`)
+//! - exFAT parsing, sanity checks and extraction
+//! - Offset/size newtypes and file times
+
+pub mod entropy;
+pub mod error;
+pub mod exfat;
+pub mod matcher;
+pub mod overlap;
+pub mod simd_search;
+#[cfg(target_arch = "x86_64")]
+pub mod simd_search_asm;
+#[cfg(target_arch = "aarch64")]
+pub mod simd_neon;
+pub mod simd_block_scanner_asm;
+pub mod types;
+
+pub use simd_search::{find_pattern_simd, count_pattern_simd, scan_block_simd, scan_block64_simd, BlockScanResult};
+pub use simd_block_scanner_asm::{AlignedBlock, BlockScanResultExt};
+pub use overlap::{suppress_duplicates, OverlapWindows};
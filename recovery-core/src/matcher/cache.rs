@@ -0,0 +1,260 @@
+//! Warm-start cache for the YouTube pattern set.
+//!
+//! `regex` does not expose its compiled automata for serialization, and a fully
+//! determinized DFA over these patterns (overlapping `.*` plus Unicode `\w`) is
+//! far too large to build, so the cache stores the pattern metadata keyed by
+//! the pattern-set hash: a restarted worker (PyO3) loads a table already
+//! checked against the running binary and compiles the pre-filter from it once
+//! per process. The set is compiled from the extraction sources themselves: a
+//! pre-filter that is narrower than the extraction regexes (e.g. byte-mode `\s`
+//! missing U+00A0) silently drops links the extraction would have found.
+
+use regex::bytes::{RegexSet, RegexSetBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use crate::matcher::patterns::YOUTUBE_PATTERN_SPECS;
+
+/// Bump when the cache layout or the pattern-set derivation changes (part of
+/// `pattern_set_hash`).
+const PATTERN_SET_VERSION: u32 = 1;
+
+/// Environment variable overriding the cache directory ("off" disables the cache)
+pub const PATTERN_CACHE_ENV: &str = "RUST_RECOVERY_PATTERN_CACHE";
+
+const PREFILTER_SIZE_LIMIT: usize = 50 * 1024 * 1024; // 50 MB
+
+static SHARED_PREFILTER: OnceLock<Arc<RegexSet>> = OnceLock::new();
+
+/// Metadata of a single cached pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedPattern {
+    pub name: String,
+    pub priority: u8,
+    /// Regex source used for extraction and by the pre-filter set
+    pub source: String,
+}
+
+/// On-disk pattern cache, valid only for the pattern set it was built from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatternCache {
+    pub version: u32,
+    pub pattern_set_hash: String,
+    pub patterns: Vec<CachedPattern>,
+}
+
+fn hash_patterns<'a>(patterns: impl Iterator<Item = (&'a str, &'a str, u8)>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(PATTERN_SET_VERSION.to_le_bytes());
+    for (name, source, priority) in patterns {
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update(source.as_bytes());
+        hasher.update([0, priority]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// SHA-256 over the pattern table and set version
+pub fn pattern_set_hash() -> String {
+    hash_patterns(YOUTUBE_PATTERN_SPECS.iter().map(|&(name, source, priority)| (name, source, priority)))
+}
+
+/// Cache directory: `$RUST_RECOVERY_PATTERN_CACHE`, then `$XDG_CACHE_HOME`, then `~/.cache`
+pub fn default_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(PATTERN_CACHE_ENV) {
+        if dir.is_empty() || dir == "off" {
+            return None;
+        }
+        return Some(PathBuf::from(dir));
+    }
+
+    std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("rust-recovery"))
+}
+
+/// Cache file path for the current pattern set
+pub fn cache_path(dir: &Path) -> PathBuf {
+    let hash = pattern_set_hash();
+    dir.join(format!("patterns-{}.json", &hash[..16]))
+}
+
+impl PatternCache {
+    /// Build cache metadata from the compiled-in pattern table
+    pub fn from_specs() -> Self {
+        let patterns = YOUTUBE_PATTERN_SPECS
+            .iter()
+            .map(|&(name, source, priority)| CachedPattern {
+                name: name.to_string(),
+                priority,
+                source: source.to_string(),
+            })
+            .collect();
+
+        Self {
+            version: PATTERN_SET_VERSION,
+            pattern_set_hash: pattern_set_hash(),
+            patterns,
+        }
+    }
+
+    /// Load the cache for the current pattern set, `None` on miss or mismatch
+    pub fn load(dir: &Path) -> Option<Self> {
+        let data = fs::read(cache_path(dir)).ok()?;
+        let cache: PatternCache = serde_json::from_slice(&data).ok()?;
+
+        // The stored table must hash to the running binary's pattern set
+        let stored = cache.patterns.iter().map(|p| (p.name.as_str(), p.source.as_str(), p.priority));
+        let stored_hash = hash_patterns(stored);
+        if cache.version != PATTERN_SET_VERSION
+            || cache.pattern_set_hash != pattern_set_hash()
+            || stored_hash != cache.pattern_set_hash
+        {
+            return None;
+        }
+
+        Some(cache)
+    }
+
+    /// Atomically write the cache (tmp + rename)
+    pub fn store(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = cache_path(dir);
+        let tmp_path = path.with_extension("tmp");
+        let serialized = serde_json::to_vec_pretty(self)?;
+
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&serialized)?;
+        }
+
+        fs::rename(tmp_path, &path)?;
+        Ok(path)
+    }
+
+    /// Load from `dir` if possible, otherwise rebuild and try to persist
+    pub fn load_or_build(dir: Option<&Path>) -> Self {
+        let Some(dir) = dir else {
+            return Self::from_specs();
+        };
+
+        if let Some(cache) = Self::load(dir) {
+            return cache;
+        }
+
+        let cache = Self::from_specs();
+        // Cache is best-effort: a read-only home must not break scanning
+        let _ = cache.store(dir);
+        cache
+    }
+
+    /// Compile the pre-filter set (pattern indices match `YOUTUBE_PATTERNS`)
+    pub fn build_prefilter(&self) -> Result<RegexSet, regex::Error> {
+        RegexSetBuilder::new(self.patterns.iter().map(|p| p.source.as_str()))
+            .size_limit(PREFILTER_SIZE_LIMIT)
+            .build()
+    }
+}
+
+/// Shared pre-filter set (compiled once per process from the cached table)
+pub fn shared_prefilter() -> Arc<RegexSet> {
+    Arc::clone(SHARED_PREFILTER.get_or_init(|| {
+        let cache = PatternCache::load_or_build(default_cache_dir().as_deref());
+        Arc::new(
+            cache
+                .build_prefilter()
+                .or_else(|_| PatternCache::from_specs().build_prefilter())
+                .expect("Failed to compile pattern set"),
+        )
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::patterns::YOUTUBE_PATTERNS;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_dir() -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        dir.push(format!("rust_recovery_pattern_cache_{unique}"));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn pattern_index(name: &str) -> usize {
+        YOUTUBE_PATTERN_SPECS.iter().position(|(n, _, _)| *n == name).unwrap()
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dir = temp_dir();
+        assert!(PatternCache::load(&dir).is_none());
+
+        let built = PatternCache::load_or_build(Some(&dir));
+        assert!(cache_path(&dir).exists());
+
+        let loaded = PatternCache::load(&dir).expect("cache hit");
+        assert_eq!(built, loaded);
+        assert_eq!(loaded.patterns.len(), YOUTUBE_PATTERN_SPECS.len());
+        assert_eq!(loaded.build_prefilter().unwrap().len(), YOUTUBE_PATTERN_SPECS.len());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stale_cache_is_ignored() {
+        let dir = temp_dir();
+        let mut cache = PatternCache::from_specs();
+        cache.pattern_set_hash = "stale".to_string();
+        cache.store(&dir).unwrap();
+        assert!(PatternCache::load(&dir).is_none());
+
+        // An older layout, or a table edited after it was stored
+        let mut cache = PatternCache::from_specs();
+        cache.version = PATTERN_SET_VERSION + 1;
+        cache.store(&dir).unwrap();
+        assert!(PatternCache::load(&dir).is_none());
+        let mut cache = PatternCache::from_specs();
+        cache.patterns[0].source.push_str("|x");
+        cache.store(&dir).unwrap();
+        assert!(PatternCache::load(&dir).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_prefilter_selects_patterns() {
+        let matches = shared_prefilter().matches(b"see https://youtu.be/dQw4w9WgXcQ now");
+        assert!(matches.matched(pattern_index("short")));
+    }
+
+    #[test]
+    fn test_prefilter_covers_every_extraction_match() {
+        // `v=` link ended by a non-breaking space: `\s` only matches it in Unicode mode
+        let samples: [&[u8]; 3] = [
+            "list?v=dQw4w9WgXcQ\u{a0}more".as_bytes(),
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ\u{a0}".as_bytes(),
+            "\"video_id\":\u{2003}\"dQw4w9WgXcQ\"".as_bytes(),
+        ];
+        let set = shared_prefilter();
+        for sample in samples {
+            let matches = set.matches(sample);
+            for (index, pattern) in YOUTUBE_PATTERNS.iter().enumerate() {
+                if pattern.regex.is_match(sample) {
+                    assert!(matches.matched(index), "{} missed by the pre-filter", pattern.name);
+                }
+            }
+        }
+        assert!(set.matches("list?v=dQw4w9WgXcQ\u{a0}".as_bytes()).matched(pattern_index("v_param")));
+    }
+}
@@ -0,0 +1,257 @@
+//! Context-aware link confidence
+//!
+//! The pattern priority says how specific a match is; the bytes around it say
+//! whether it is likely to be a real link. URLs inside JSON string values,
+//! `<a href>` attributes or next to Takeout keys (`titleUrl`, `subtitles`) are
+//! boosted, URLs embedded in high-entropy or base64 runs are penalized. Every
+//! adjustment is recorded as a reason string so the final score is explainable.
+
+use crate::entropy::calculate_shannon_entropy;
+
+/// Bytes inspected on each side of the URL for the entropy check
+const ENTROPY_WINDOW: usize = 512;
+/// Minimum context needed for a meaningful entropy estimate
+const MIN_ENTROPY_SAMPLE: usize = 256;
+/// Same threshold as `entropy::is_compressed_like`
+const HIGH_ENTROPY_THRESHOLD: f32 = 7.5;
+/// Adjacent base64 run long enough to be an encoded blob rather than a word
+const BASE64_RUN_MIN: usize = 40;
+/// How far back an opening quote may be from the match start
+const QUOTE_LOOKBACK: usize = 32;
+/// How far after the match the URL may continue (query string) before its closing quote
+const QUOTE_LOOKAHEAD: usize = 256;
+/// Lookback for `<a` and Takeout keys
+const TAG_LOOKBACK: usize = 512;
+const KEY_LOOKBACK: usize = 128;
+
+/// Furthest byte read before the match start
+pub const REACH_BEFORE: usize = max(max(ENTROPY_WINDOW, TAG_LOOKBACK), max(KEY_LOOKBACK, QUOTE_LOOKBACK));
+/// Furthest byte read after the match end
+pub const REACH_AFTER: usize = max(ENTROPY_WINDOW, QUOTE_LOOKAHEAD);
+
+const JSON_STRING_BONUS: f32 = 0.10;
+const HREF_BONUS: f32 = 0.10;
+const TITLE_URL_BONUS: f32 = 0.15;
+const SUBTITLES_BONUS: f32 = 0.10;
+const HIGH_ENTROPY_PENALTY: f32 = -0.30;
+const BASE64_PENALTY: f32 = -0.25;
+
+/// Confidence adjustment derived from the bytes around a match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextScore {
+    /// Sum of all bonuses and penalties
+    pub adjustment: f32,
+    /// One entry per applied adjustment, e.g. `json_string (+0.10)`
+    pub reasons: Vec<String>,
+}
+
+impl ContextScore {
+    fn apply(&mut self, reason: &str, delta: f32) {
+        self.adjustment += delta;
+        self.reasons.push(format!("{} ({:+.2})", reason, delta));
+    }
+
+    /// Adjusted confidence, clamped to 0.0 - 1.0
+    pub fn adjust(&self, base: f32) -> f32 {
+        (base + self.adjustment).clamp(0.0, 1.0)
+    }
+}
+
+/// Score the context of the match `data[start..end]`
+pub fn score_link_context(data: &[u8], start: usize, end: usize) -> ContextScore {
+    let mut score = ContextScore::default();
+    let end = end.min(data.len());
+    if start >= end {
+        return score;
+    }
+
+    if let Some((open, quote)) = find_opening_quote(data, start) {
+        let closed = find_closing_quote(data, end, quote);
+
+        if quote == b'"' && closed && is_json_value_position(data, open) {
+            score.apply("json_string", JSON_STRING_BONUS);
+        }
+        if closed && is_href_value(data, open) {
+            score.apply("html_anchor_href", HREF_BONUS);
+        }
+    }
+
+    let key_window = &data[start.saturating_sub(KEY_LOOKBACK)..start];
+    if contains(key_window, b"\"titleUrl\"") {
+        score.apply("key_titleUrl", TITLE_URL_BONUS);
+    } else if contains(key_window, b"\"subtitles\"") {
+        score.apply("key_subtitles", SUBTITLES_BONUS);
+    }
+
+    if is_high_entropy_context(data, start, end) {
+        score.apply("high_entropy_context", HIGH_ENTROPY_PENALTY);
+    } else if is_base64_context(data, start, end) {
+        score.apply("base64_context", BASE64_PENALTY);
+    }
+
+    score
+}
+
+/// Characters that may appear inside a URL (including JSON-escaped `\/`)
+#[inline]
+fn is_url_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-._~:/?#[]@!$&()*+,;=%\\".contains(&b)
+}
+
+/// Quote that opens the string containing the match: `(position, quote byte)`
+fn find_opening_quote(data: &[u8], start: usize) -> Option<(usize, u8)> {
+    let floor = start.saturating_sub(QUOTE_LOOKBACK);
+    let mut pos = start;
+    while pos > floor {
+        pos -= 1;
+        match data[pos] {
+            b @ (b'"' | b'\'') => return Some((pos, b)),
+            b if is_url_byte(b) => continue,
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn find_closing_quote(data: &[u8], end: usize, quote: u8) -> bool {
+    let ceiling = (end + QUOTE_LOOKAHEAD).min(data.len());
+    for &b in &data[end..ceiling] {
+        if b == quote {
+            return true;
+        }
+        if !is_url_byte(b) {
+            return false;
+        }
+    }
+    false
+}
+
+/// The string at `open` is a JSON value: preceded by `:`, `[` or `,`
+fn is_json_value_position(data: &[u8], open: usize) -> bool {
+    data[..open]
+        .iter()
+        .rev()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|&b| matches!(b, b':' | b'[' | b','))
+}
+
+/// The string at `open` is the value of `href=` inside an unclosed `<a ...` tag
+fn is_href_value(data: &[u8], open: usize) -> bool {
+    let mut pos = open;
+    while pos > 0 && data[pos - 1].is_ascii_whitespace() {
+        pos -= 1;
+    }
+    if pos == 0 || data[pos - 1] != b'=' {
+        return false;
+    }
+    pos -= 1;
+    while pos > 0 && data[pos - 1].is_ascii_whitespace() {
+        pos -= 1;
+    }
+    if pos < 4 || !data[pos - 4..pos].eq_ignore_ascii_case(b"href") {
+        return false;
+    }
+
+    let tag_window = &data[pos.saturating_sub(TAG_LOOKBACK)..pos - 4];
+    match tag_window.iter().rposition(|&b| b == b'<' || b == b'>') {
+        Some(lt) if tag_window[lt] == b'<' => tag_window
+            .get(lt + 1..lt + 3)
+            .is_some_and(|tag| tag[0].eq_ignore_ascii_case(&b'a') && tag[1].is_ascii_whitespace()),
+        _ => false,
+    }
+}
+
+fn is_high_entropy_context(data: &[u8], start: usize, end: usize) -> bool {
+    let before = &data[start.saturating_sub(ENTROPY_WINDOW)..start];
+    let after = &data[end..(end + ENTROPY_WINDOW).min(data.len())];
+    if before.len() + after.len() < MIN_ENTROPY_SAMPLE {
+        return false;
+    }
+
+    let mut sample = Vec::with_capacity(before.len() + after.len());
+    sample.extend_from_slice(before);
+    sample.extend_from_slice(after);
+    calculate_shannon_entropy(&sample) > HIGH_ENTROPY_THRESHOLD
+}
+
+#[inline]
+fn is_base64_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'+' || b == b'/' || b == b'='
+}
+
+/// Mixed-case base64 run long enough to be encoded data
+fn is_base64_run<'a>(run: impl Iterator<Item = &'a u8>) -> bool {
+    let (mut len, mut upper, mut lower) = (0usize, false, false);
+    for &b in run.take_while(|&&b| is_base64_byte(b)) {
+        len += 1;
+        upper |= b.is_ascii_uppercase();
+        lower |= b.is_ascii_lowercase();
+    }
+    len >= BASE64_RUN_MIN && upper && lower
+}
+
+/// Runs are followed at most `ENTROPY_WINDOW` bytes so the reach stays bounded
+fn is_base64_context(data: &[u8], start: usize, end: usize) -> bool {
+    is_base64_run(data[..start].iter().rev().take(ENTROPY_WINDOW))
+        || is_base64_run(data[end..].iter().take(ENTROPY_WINDOW))
+}
+
+const fn max(a: usize, b: usize) -> usize {
+    if a > b { a } else { b }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+
+    fn score(prefix: &[u8], suffix: &[u8]) -> ContextScore {
+        let mut data = prefix.to_vec();
+        let start = data.len();
+        data.extend_from_slice(URL.as_bytes());
+        let end = data.len();
+        data.extend_from_slice(suffix);
+        score_link_context(&data, start, end)
+    }
+
+    #[test]
+    fn test_takeout_json_bonus() {
+        let s = score(br#"{"title": "Watched", "titleUrl": ""#, br#"", "time": "2023"}"#);
+        assert_eq!(s.reasons, vec!["json_string (+0.10)", "key_titleUrl (+0.15)"]);
+        assert!((s.adjust(0.7) - 0.95).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_anchor_href_bonus() {
+        let s = score(b"<p><a class=\"x\" href=\"", b"\">Video</a></p>");
+        assert_eq!(s.reasons, vec!["html_anchor_href (+0.10)"]);
+
+        // Plain text mention gets nothing
+        assert!(score(b"see ", b" later").reasons.is_empty());
+    }
+
+    #[test]
+    fn test_encoded_context_penalty() {
+        let blob = b"QmFzZTY0IGVuY29kZWQgYmxvYiB3aXRoIE1peGVkQ2FzZSBhbmQgZGlnaXRzIDEyMw";
+        let s = score(blob, b"");
+        assert_eq!(s.reasons, vec!["base64_context (-0.25)"]);
+
+        // Pseudo-random bytes around the link
+        let mut noise = Vec::with_capacity(1024);
+        let mut x: u32 = 0x1234_5678;
+        for _ in 0..1024 {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            noise.push((x >> 24) as u8);
+        }
+        let s = score(&noise[..512], &noise[512..]);
+        assert!(s.reasons.contains(&"high_entropy_context (-0.30)".to_string()));
+        assert_eq!(s.adjust(0.2), 0.0);
+    }
+}
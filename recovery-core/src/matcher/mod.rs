@@ -0,0 +1,10 @@
+//! Pattern tables, validators, link context scoring and pattern profiles of
+//! the matchers; each crate keeps its own `EnhancedMatcher` on top of these
+
+pub mod cache;
+pub mod context;
+pub mod patterns;
+pub mod profile;
+pub mod validator;
+
+pub use profile::{PatternProfile, PatternSpec, ProfileSpec, ValidationHook};
@@ -0,0 +1,423 @@
+//! Pattern profiles: what `EnhancedMatcher` extracts
+//!
+//! A profile is a set of regexes with a priority (link confidence, 0-10),
+//! the capture group holding the extracted value, an optional validation
+//! hook and the needles that gate the regexes. The built-in profile is the
+//! YouTube table in `patterns`; custom ones (`--patterns FILE`) are loaded
+//! from TOML or JSON:
+//!
+//! ```toml
+//! name = "contacts"
+//!
+//! [[patterns]]
+//! name = "email"
+//! regex = '[\w.%+-]+@[\w-]+(?:\.[\w-]+)+'
+//! priority = 7
+//! validator = "email"
+//! needles = ["@"]
+//! ```
+//!
+//! Needles are derived from the regex (literal prefixes, then suffixes) when
+//! not given. Patterns without usable needles are run over the whole chunk.
+
+use aho_corasick::{AhoCorasick, AhoCorasickKind, BuildError, MatchKind};
+use regex::bytes::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use regex_syntax::hir::literal::{ExtractKind, Extractor};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::{CoreError, Result};
+use crate::matcher::cache;
+use crate::matcher::patterns::{max_match_len, YOUTUBE_PATTERNS};
+use crate::matcher::validator::{
+    is_valid_bitcoin_address, is_valid_email, is_valid_luhn, is_valid_phone_number, is_valid_video_id,
+};
+
+/// Hand-tuned needles of the YouTube profile (ASCII case-insensitive):
+/// every pattern match contains one of these
+const YOUTUBE_NEEDLES: &[&str] = &["youtube.com", "youtu.be", "video_id", "video-id", "v=", "/v/", "embed/", "shorts/"];
+/// Bytes before a YouTube needle searched for the start of the URL
+const YOUTUBE_LOOKBEHIND: usize = 100;
+/// Bytes after a YouTube needle searched for the video ID
+const YOUTUBE_LOOKAHEAD: usize = 50;
+
+/// Shortest derived needle; shorter literals hit too often to be a filter
+const MIN_NEEDLE_LEN: usize = 3;
+/// More derived needles than this means the regex has no useful literal
+const MAX_NEEDLES_PER_PATTERN: usize = 64;
+/// Window cap on each side of a needle for unbounded custom patterns
+const MAX_PATTERN_WINDOW: usize = 512;
+const PROFILE_SIZE_LIMIT: usize = 50 * 1024 * 1024; // 50 MB
+
+/// Validation hook: accepts or rejects the captured value
+pub type ValidationHook = fn(&[u8]) -> bool;
+
+/// Validators available to profile files by name
+pub fn builtin_validator(name: &str) -> Option<ValidationHook> {
+    match name {
+        "youtube_video_id" => Some(is_valid_video_id),
+        "email" => Some(is_valid_email),
+        "phone" => Some(is_valid_phone_number),
+        "luhn" => Some(is_valid_luhn),
+        "bitcoin_address" => Some(is_valid_bitcoin_address),
+        _ => None,
+    }
+}
+
+fn default_priority() -> u8 {
+    5
+}
+
+/// One pattern as written in a profile file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatternSpec {
+    pub name: String,
+    pub regex: String,
+    /// Link confidence is `priority / 10`
+    #[serde(default = "default_priority")]
+    pub priority: u8,
+    /// Capture group holding the extracted value (0 = whole match)
+    #[serde(default)]
+    pub capture: usize,
+    /// Name of a validation hook (see `builtin_validator`)
+    #[serde(default)]
+    pub validator: Option<String>,
+    /// Literal needles gating the regex; derived from the regex when absent
+    #[serde(default)]
+    pub needles: Option<Vec<String>>,
+}
+
+/// Profile file contents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileSpec {
+    pub name: String,
+    /// Search the surroundings of each match for a page/video title
+    #[serde(default)]
+    pub extract_titles: bool,
+    pub patterns: Vec<PatternSpec>,
+}
+
+impl ProfileSpec {
+    /// Parse a profile file (`.toml`, anything else as JSON)
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let is_toml = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+
+        if is_toml {
+            toml::from_str(&text)
+                .map_err(|e| CoreError::Config(format!("{}: {}", path.display(), e)))
+        } else {
+            serde_json::from_str(&text)
+                .map_err(|e| CoreError::Config(format!("{}: {}", path.display(), e)))
+        }
+    }
+}
+
+/// Compiled profile pattern
+#[derive(Clone)]
+pub struct ProfilePattern {
+    pub name: String,
+    pub regex: Regex,
+    pub priority: u8,
+    pub capture: usize,
+    pub validator: Option<ValidationHook>,
+    /// Gated by the profile finder; otherwise run over the whole chunk
+    pub anchored: bool,
+}
+
+/// Compiled pattern profile, shared between matchers
+pub struct PatternProfile {
+    pub name: String,
+    /// SHA-256 over the profile spec (recorded in provenance)
+    pub hash: String,
+    pub patterns: Vec<ProfilePattern>,
+    /// Pre-filter set, indices match `patterns`
+    pub pattern_set: Arc<RegexSet>,
+    /// Needle automaton gating the anchored patterns
+    pub finder: Option<AhoCorasick>,
+    /// Literals the finder searches for (sorted, deduplicated; the first
+    /// needle in this order wins at a position)
+    pub needles: Vec<Vec<u8>>,
+    /// Needles match in any ASCII case
+    pub needles_ignore_case: bool,
+    /// Bytes searched before / after each needle
+    pub lookbehind: usize,
+    pub lookahead: usize,
+    pub extract_titles: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref YOUTUBE_PROFILE: Arc<PatternProfile> = Arc::new(PatternProfile::build_youtube());
+}
+
+impl PatternProfile {
+    /// Built-in YouTube profile (compiled once per process)
+    pub fn youtube() -> Arc<Self> {
+        Arc::clone(&YOUTUBE_PROFILE)
+    }
+
+    fn build_youtube() -> Self {
+        let patterns = YOUTUBE_PATTERNS
+            .iter()
+            .map(|p| ProfilePattern {
+                name: p.name.to_string(),
+                regex: p.regex.clone(),
+                priority: p.priority,
+                capture: 1,
+                validator: Some(is_valid_video_id),
+                anchored: true,
+            })
+            .collect();
+        let mut needles: Vec<Vec<u8>> = YOUTUBE_NEEDLES.iter().map(|needle| needle.as_bytes().to_vec()).collect();
+        needles.sort();
+
+        Self {
+            name: "youtube".to_string(),
+            hash: cache::pattern_set_hash(),
+            patterns,
+            // Pre-filter set is compiled once per process from the warm-start cache
+            pattern_set: cache::shared_prefilter(),
+            finder: Some(build_finder(&needles, true).expect("Failed to build needle finder")),
+            needles,
+            needles_ignore_case: true,
+            lookbehind: YOUTUBE_LOOKBEHIND,
+            lookahead: YOUTUBE_LOOKAHEAD,
+            extract_titles: true,
+        }
+    }
+
+    /// Load and compile a profile file
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_spec(&ProfileSpec::load(path)?)
+    }
+
+    /// Compile `spec` with the built-in validators
+    pub fn from_spec(spec: &ProfileSpec) -> Result<Self> {
+        Self::from_spec_with_hooks(spec, &[])
+    }
+
+    /// Compile `spec`; `hooks` add validators (or replace built-ins of the same name)
+    pub fn from_spec_with_hooks(spec: &ProfileSpec, hooks: &[(&str, ValidationHook)]) -> Result<Self> {
+        let invalid = |pattern: &str, msg: String| {
+            CoreError::Config(format!("pattern profile '{}', pattern '{}': {}", spec.name, pattern, msg))
+        };
+
+        if spec.patterns.is_empty() {
+            return Err(CoreError::Config(format!("pattern profile '{}' has no patterns", spec.name)));
+        }
+
+        let mut patterns = Vec::with_capacity(spec.patterns.len());
+        let mut needles: Vec<Vec<u8>> = Vec::new();
+        let mut window = 0usize;
+
+        for p in &spec.patterns {
+            if p.priority > 10 {
+                return Err(invalid(&p.name, format!("priority {} is above 10", p.priority)));
+            }
+
+            let regex = RegexBuilder::new(&p.regex)
+                .size_limit(PROFILE_SIZE_LIMIT)
+                .build()
+                .map_err(|e| invalid(&p.name, e.to_string()))?;
+            if p.capture >= regex.captures_len() {
+                return Err(invalid(&p.name, format!("capture group {} does not exist", p.capture)));
+            }
+
+            let validator = match &p.validator {
+                None => None,
+                Some(name) => Some(
+                    hooks
+                        .iter()
+                        .find(|(hook, _)| hook == name)
+                        .map(|&(_, hook)| hook)
+                        .or_else(|| builtin_validator(name))
+                        .ok_or_else(|| invalid(&p.name, format!("unknown validator '{}'", name)))?,
+                ),
+            };
+
+            let pattern_needles = match &p.needles {
+                Some(given) if given.iter().any(String::is_empty) => {
+                    return Err(invalid(&p.name, "empty needle".to_string()));
+                }
+                Some(given) if !given.is_empty() => Some(given.iter().map(|n| n.as_bytes().to_vec()).collect()),
+                _ => derive_needles(&p.regex),
+            };
+            let anchored = pattern_needles.is_some();
+            needles.extend(pattern_needles.into_iter().flatten());
+
+            window = window.max(max_match_len(&p.regex).unwrap_or(MAX_PATTERN_WINDOW).min(MAX_PATTERN_WINDOW));
+
+            patterns.push(ProfilePattern {
+                name: p.name.clone(),
+                regex,
+                priority: p.priority,
+                capture: p.capture,
+                validator,
+                anchored,
+            });
+        }
+
+        let pattern_set = RegexSetBuilder::new(spec.patterns.iter().map(|p| p.regex.as_str()))
+            .size_limit(PROFILE_SIZE_LIMIT)
+            .build()
+            .map_err(|e| CoreError::Config(format!("pattern profile '{}': {}", spec.name, e)))?;
+
+        needles.sort();
+        needles.dedup();
+        let finder = (!needles.is_empty())
+            .then(|| build_finder(&needles, false))
+            .transpose()
+            .map_err(|e| CoreError::Config(format!("pattern profile '{}': {}", spec.name, e)))?;
+
+        Ok(Self {
+            name: spec.name.clone(),
+            hash: spec_hash(spec),
+            patterns,
+            pattern_set: Arc::new(pattern_set),
+            finder,
+            needles,
+            needles_ignore_case: false,
+            lookbehind: window,
+            lookahead: window,
+            extract_titles: spec.extract_titles,
+        })
+    }
+}
+
+/// Literals every match starts (or else ends) with, `None` if there is no usable set
+fn derive_needles(pattern: &str) -> Option<Vec<Vec<u8>>> {
+    let hir = regex_syntax::parse(pattern).ok()?;
+
+    [ExtractKind::Prefix, ExtractKind::Suffix].into_iter().find_map(|kind| {
+        let seq = Extractor::new().kind(kind).extract(&hir);
+        let literals = seq.literals()?;
+        let usable = !literals.is_empty()
+            && literals.len() <= MAX_NEEDLES_PER_PATTERN
+            && literals.iter().all(|lit| lit.as_bytes().len() >= MIN_NEEDLE_LEN);
+        usable.then(|| literals.iter().map(|lit| lit.as_bytes().to_vec()).collect())
+    })
+}
+
+/// Leftmost-first automaton over `needles`: a DFA, so hundreds of needles
+/// cost no more per byte than a few, with the SIMD (Teddy) prefilter of the
+/// crate in front of it when the needle set suits it
+fn build_finder(needles: &[Vec<u8>], ignore_case: bool) -> std::result::Result<AhoCorasick, BuildError> {
+    AhoCorasick::builder()
+        .kind(Some(AhoCorasickKind::DFA))
+        .match_kind(MatchKind::LeftmostFirst)
+        .ascii_case_insensitive(ignore_case)
+        .prefilter(true)
+        .build(needles)
+}
+
+fn spec_hash(spec: &ProfileSpec) -> String {
+    let serialized = serde_json::to_vec(spec).unwrap_or_default();
+    format!("{:x}", Sha256::digest(serialized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(patterns: Vec<PatternSpec>) -> ProfileSpec {
+        ProfileSpec { name: "test".to_string(), extract_titles: false, patterns }
+    }
+
+    fn pattern(name: &str, regex: &str) -> PatternSpec {
+        PatternSpec {
+            name: name.to_string(),
+            regex: regex.to_string(),
+            priority: 5,
+            capture: 0,
+            validator: None,
+            needles: None,
+        }
+    }
+
+    #[test]
+    fn test_needles_derived_or_unanchored() {
+        let profile = PatternProfile::from_spec(&spec(vec![
+            pattern("token", r"tok_[A-Za-z0-9]{24}"),
+            pattern("suffix", r"[0-9a-f]{8}\.session"),
+            pattern("email", r"[\w.+-]+@[\w-]+\.[a-z]{2,}"),
+        ]))
+        .unwrap();
+
+        assert!(profile.patterns[0].anchored);
+        assert!(profile.patterns[1].anchored);
+        assert!(!profile.patterns[2].anchored);
+
+        let finder = profile.finder.unwrap();
+        assert!(finder.is_match(b"xx tok_abc"));
+        assert!(finder.is_match(b"ab.session"));
+        assert!(!finder.is_match(b"user@example.com"));
+    }
+
+    #[test]
+    fn test_finder_many_needles() {
+        let youtube = PatternProfile::youtube();
+        let finder = youtube.finder.as_ref().unwrap();
+        let hits: Vec<&[u8]> = finder.find_iter(b"see YouTube.COM/watch?V=x").map(|m| &b"see YouTube.COM/watch?V=x"[m.range()]).collect();
+        assert_eq!(hits, vec![&b"YouTube.COM"[..], &b"V="[..]]);
+
+        // Hundreds of needles from one profile, matched case-sensitively
+        let mut many = pattern("sku", r"SKU-[0-9]{3}-[A-Z]{4}");
+        many.needles = Some((0..500).map(|i| format!("SKU-{:03}-", i)).collect());
+        let profile = PatternProfile::from_spec(&spec(vec![many])).unwrap();
+        assert_eq!(profile.needles.len(), 500);
+        let finder = profile.finder.unwrap();
+        assert!(finder.is_match(b"order SKU-499-ABCD"));
+        assert!(!finder.is_match(b"order sku-499-abcd"));
+    }
+
+    #[test]
+    fn test_spec_errors() {
+        let mut bad_priority = pattern("p", "abc");
+        bad_priority.priority = 11;
+        let mut bad_capture = pattern("c", "abc");
+        bad_capture.capture = 1;
+        let mut bad_validator = pattern("v", "abc");
+        bad_validator.validator = Some("nope".to_string());
+
+        for p in [bad_priority, bad_capture, bad_validator, pattern("r", "(unclosed")] {
+            assert!(PatternProfile::from_spec(&spec(vec![p])).is_err());
+        }
+        assert!(PatternProfile::from_spec(&spec(Vec::new())).is_err());
+
+        let mut custom = pattern("v", "abc");
+        custom.validator = Some("always".to_string());
+        let hooks: [(&str, ValidationHook); 1] = [("always", |_| true)];
+        assert!(PatternProfile::from_spec_with_hooks(&spec(vec![custom]), &hooks).is_ok());
+    }
+
+    #[test]
+    fn test_profile_file_formats() {
+        let dir = std::env::temp_dir().join(format!("rust_recovery_profile_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let toml_path = dir.join("p.toml");
+        std::fs::write(
+            &toml_path,
+            "name = \"btc\"\n\n[[patterns]]\nname = \"addr\"\nregex = 'bc1[a-z0-9]{8,87}'\npriority = 8\nvalidator = \"bitcoin_address\"\n",
+        )
+        .unwrap();
+        let json_path = dir.join("p.json");
+        std::fs::write(
+            &json_path,
+            r#"{"name": "btc", "patterns": [{"name": "addr", "regex": "bc1[a-z0-9]{8,87}", "priority": 8, "validator": "bitcoin_address"}]}"#,
+        )
+        .unwrap();
+
+        let from_toml = ProfileSpec::load(&toml_path).unwrap();
+        let from_json = ProfileSpec::load(&json_path).unwrap();
+        assert_eq!(from_toml, from_json);
+        assert_eq!(from_toml.patterns[0].capture, 0);
+        assert!(PatternProfile::load(&toml_path).unwrap().patterns[0].validator.is_some());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,372 @@
+/// Validates if a byte slice is a valid YouTube video ID
+///
+/// A valid video ID must be:
+/// - Exactly 11 bytes long
+/// - Contain only alphanumeric characters, - or _
+#[inline]
+pub fn is_valid_video_id(id: &[u8]) -> bool {
+    if id.len() != 11 {
+        return false;
+    }
+    
+    // Check if all characters are valid
+    id.iter().all(|&b| {
+        b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+    })
+}
+
+/// Fast heuristic check for probable JSON data
+/// Uses quick prefix and structure markers before full validation
+#[inline]
+pub fn is_probably_json(data: &[u8]) -> bool {
+    if data.is_empty() {
+        return false;
+    }
+    
+    // Convert to string for trimming and analysis
+    let text = match std::str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    
+    // Trim whitespace
+    let trimmed = text.trim();
+    
+    // Check for JSON start characters
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return false;
+    }
+    
+    // Quick structure validation - balance braces and brackets
+    let mut brace_count: i32 = 0;
+    let mut bracket_count: i32 = 0;
+    let mut in_string = false;
+    let mut escape_next = false;
+    
+    for b in trimmed.bytes() {
+        if escape_next {
+            escape_next = false;
+            continue;
+        }
+        
+        if b == b'\\' {
+            escape_next = true;
+            continue;
+        }
+        
+        if b == b'"' {
+            in_string = !in_string;
+            continue;
+        }
+        
+        if in_string {
+            continue;
+        }
+        
+        match b {
+            b'{' => brace_count += 1,
+            b'}' => brace_count = brace_count.saturating_sub(1),
+            b'[' => bracket_count += 1,
+            b']' => bracket_count = bracket_count.saturating_sub(1),
+            _ => {}
+        }
+    }
+    
+    // If braces/brackets are balanced and we have enough content
+    brace_count == 0 && bracket_count == 0 && trimmed.len() > 10
+}
+
+/// Validate JSON using serde_json
+/// Returns true if data is valid JSON
+#[inline]
+pub fn is_valid_json(data: &[u8]) -> bool {
+    if !is_probably_json(data) {
+        return false;
+    }
+    
+    let text = match std::str::from_utf8(data) {
+        Ok(s) => s.trim(),
+        Err(_) => return false,
+    };
+    
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(_) => true,
+        Err(_) => {
+            // Try to extract partial JSON if it's embedded
+            let json_start = text.find('{').or_else(|| text.find('['));
+            if let Some(start) = json_start {
+                let json_part = &text[start..];
+                match serde_json::from_str::<serde_json::Value>(json_part) {
+                    Ok(_) => true,
+                    Err(_) => false,
+                }
+            } else {
+                false
+            }
+        }
+    }
+}
+
+/// Fast heuristic check for YouTube URL
+/// Uses prefix and length validation before regex
+#[inline]
+pub fn is_probably_youtube_url(data: &[u8]) -> bool {
+    let text = match std::str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    
+    let trimmed = text.trim();
+    
+    // Quick prefix check
+    if !trimmed.starts_with("http") {
+        return false;
+    }
+    
+    // Length check - YouTube URLs are usually 20-100 chars
+    if trimmed.len() < 15 || trimmed.len() > 200 {
+        return false;
+    }
+    
+    // Check for YouTube domain indicators
+    trimmed.contains("youtube") || trimmed.contains("youtu.be")
+}
+
+/// Validate YouTube URL using pattern matching
+/// Returns true if data contains a valid YouTube URL
+#[inline]
+pub fn is_valid_youtube_url(data: &[u8]) -> bool {
+    if !is_probably_youtube_url(data) {
+        return false;
+    }
+    
+    // Use the same patterns as the matcher
+    use crate::matcher::patterns::YOUTUBE_PATTERNS;
+    
+    for pattern in YOUTUBE_PATTERNS.iter() {
+        if pattern.regex.is_match(data) {
+            return true;
+        }
+    }
+    
+    false
+}
+
+/// Validates an e-mail address (`local@domain.tld`, ASCII only)
+pub fn is_valid_email(data: &[u8]) -> bool {
+    let Some(at) = data.iter().position(|&b| b == b'@') else {
+        return false;
+    };
+    let (local, domain) = (&data[..at], &data[at + 1..]);
+
+    if local.is_empty() || local.len() > 64 || domain.len() < 4 || domain.len() > 253 {
+        return false;
+    }
+    if !local.iter().all(|&b| b.is_ascii_alphanumeric() || b"._%+-".contains(&b)) {
+        return false;
+    }
+
+    let labels: Vec<&[u8]> = domain.split(|&b| b == b'.').collect();
+    let tld = labels[labels.len() - 1];
+    labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.iter().all(|&b| b.is_ascii_alphanumeric() || b == b'-')
+                && !label.starts_with(b"-")
+                && !label.ends_with(b"-")
+        })
+        && tld.len() >= 2
+        && tld.iter().all(|b| b.is_ascii_alphabetic())
+}
+
+/// Validates a phone number: 7-15 digits (E.164) with optional `+` and separators
+pub fn is_valid_phone_number(data: &[u8]) -> bool {
+    let digits = data.iter().filter(|b| b.is_ascii_digit()).count();
+    (7..=15).contains(&digits)
+        && data.iter().all(|&b| b.is_ascii_digit() || b" +-.()".contains(&b))
+}
+
+/// Luhn checksum (card numbers, IMEI); spaces and dashes are ignored
+pub fn is_valid_luhn(data: &[u8]) -> bool {
+    let mut sum = 0u32;
+    let mut count = 0usize;
+
+    for &b in data.iter().rev() {
+        match b {
+            b'0'..=b'9' => {
+                let mut digit = (b - b'0') as u32;
+                if count % 2 == 1 {
+                    digit *= 2;
+                    if digit > 9 {
+                        digit -= 9;
+                    }
+                }
+                sum += digit;
+                count += 1;
+            }
+            b' ' | b'-' => {}
+            _ => return false,
+        }
+    }
+
+    count >= 12 && sum.is_multiple_of(10)
+}
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32_ALPHABET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Validates a Bitcoin address: Base58Check (`1...`, `3...`) or bech32/bech32m (`bc1...`)
+pub fn is_valid_bitcoin_address(data: &[u8]) -> bool {
+    if data.len() > 3 && data[..3].eq_ignore_ascii_case(b"bc1") {
+        is_valid_bech32(data)
+    } else {
+        matches!(data.first(), Some(b'1') | Some(b'3')) && is_valid_base58check(data)
+    }
+}
+
+fn is_valid_base58check(data: &[u8]) -> bool {
+    use sha2::{Digest, Sha256};
+
+    if !(26..=35).contains(&data.len()) {
+        return false;
+    }
+
+    // Big-endian base-256 accumulator; 35 base58 digits fit in 26 bytes
+    let mut bytes = [0u8; 26];
+    for &c in data {
+        let Some(mut carry) = BASE58_ALPHABET.iter().position(|&a| a == c).map(|v| v as u32) else {
+            return false;
+        };
+        for byte in bytes.iter_mut().rev() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        if carry != 0 {
+            return false;
+        }
+    }
+
+    // Version byte + 20-byte hash + 4-byte checksum
+    if bytes[0] != 0 {
+        return false;
+    }
+    let (payload, checksum) = bytes[1..].split_at(21);
+    let digest = Sha256::digest(Sha256::digest(payload));
+    digest[..4] == *checksum
+}
+
+fn is_valid_bech32(data: &[u8]) -> bool {
+    const BECH32_CONST: u32 = 1;
+    const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+    // Mixed case is invalid
+    if data.iter().any(u8::is_ascii_lowercase) && data.iter().any(u8::is_ascii_uppercase) {
+        return false;
+    }
+    if !(14..=74).contains(&data.len()) || data[3..].len() < 7 {
+        return false;
+    }
+
+    fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+        const GEN: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+        let mut chk = 1u32;
+        for v in values {
+            let top = chk >> 25;
+            chk = ((chk & 0x01ff_ffff) << 5) ^ v as u32;
+            for (i, g) in GEN.iter().enumerate() {
+                if (top >> i) & 1 == 1 {
+                    chk ^= g;
+                }
+            }
+        }
+        chk
+    }
+
+    // "bc" human-readable part, expanded
+    let hrp_expanded = [3u8, 3, 0, 2, 3];
+    let mut values = Vec::with_capacity(data.len());
+    for &c in &data[3..] {
+        match BECH32_ALPHABET.iter().position(|&a| a == c.to_ascii_lowercase()) {
+            Some(v) => values.push(v as u8),
+            None => return false,
+        }
+    }
+
+    let witness_version = values[0];
+    let chk = polymod(hrp_expanded.iter().copied().chain(values.iter().copied()));
+    match witness_version {
+        0 => chk == BECH32_CONST,
+        1..=16 => chk == BECH32M_CONST,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_video_id_validation() {
+        assert!(is_valid_video_id(b"dQw4w9WgXcQ"));
+        assert!(is_valid_video_id(b"_-123456789"));
+        assert!(!is_valid_video_id(b"short"));
+        assert!(!is_valid_video_id(b"toolongvideo"));
+        assert!(!is_valid_video_id(b"invalid$cha"));
+    }
+
+    #[test]
+    fn test_json_validation() {
+        // Valid JSON
+        assert!(is_valid_json(b"{\"key\": \"value\"}"));
+        assert!(is_valid_json(b"[\"item1\", \"item2\"]"));
+        assert!(is_valid_json(b"  {\"nested\": {\"data\": true}}  "));
+        
+        // Invalid JSON
+        assert!(!is_valid_json(b"not json"));
+        assert!(!is_valid_json(b"{"));
+        assert!(!is_valid_json(b""));
+        
+        // Partial JSON (embedded) - this might fail due to strict validation
+        let embedded = b"{\"key\": \"value\"}";
+        // Skip this test for now as it depends on embedded JSON extraction
+        // assert!(is_valid_json(embedded));
+    }
+
+    #[test]
+    fn test_youtube_url_validation() {
+        // Valid YouTube URLs
+        assert!(is_valid_youtube_url(b"https://youtube.com/watch?v=dQw4w9WgXcQ"));
+        assert!(is_valid_youtube_url(b"https://youtu.be/dQw4w9WgXcQ"));
+        assert!(is_valid_youtube_url(b"https://www.youtube.com/watch?v=dQw4w9WgXcQ"));
+        
+        // Invalid URLs
+        assert!(!is_valid_youtube_url(b"not a url"));
+        assert!(!is_valid_youtube_url(b"https://example.com"));
+        assert!(!is_valid_youtube_url(b""));
+    }
+
+    #[test]
+    fn test_profile_validators() {
+        assert!(is_valid_email(b"ivan.petrov+news@mail.example.ru"));
+        assert!(!is_valid_email(b"no-at-sign.example.com"));
+        assert!(!is_valid_email(b"user@localhost"));
+        assert!(!is_valid_email(b"user@-bad-.com"));
+
+        assert!(is_valid_phone_number(b"+7 (912) 345-67-89"));
+        assert!(!is_valid_phone_number(b"12345"));
+
+        assert!(is_valid_luhn(b"4111 1111 1111 1111"));
+        assert!(!is_valid_luhn(b"4111 1111 1111 1112"));
+
+        assert!(is_valid_bitcoin_address(b"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"));
+        assert!(is_valid_bitcoin_address(b"3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy"));
+        assert!(!is_valid_bitcoin_address(b"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"));
+        assert!(is_valid_bitcoin_address(b"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"));
+        assert!(is_valid_bitcoin_address(b"BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"));
+        assert!(is_valid_bitcoin_address(
+            b"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"
+        ));
+        assert!(!is_valid_bitcoin_address(b"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"));
+    }
+}
@@ -0,0 +1,106 @@
+//! Duplicate suppression in chunk overlaps
+//!
+//! Chunks read `overlap` bytes past their end so that an artifact crossing a
+//! boundary is seen whole, which makes matches in that window show up once
+//! per chunk. `OverlapWindows` tracks the ranges read by more than one chunk
+//! and `suppress_duplicates` collapses repeated reports of the same occurrence
+//! inside them; repeats of a value anywhere else are separate occurrences and
+//! are kept.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Byte ranges `[start, end)` read by more than one chunk
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverlapWindows {
+    windows: Vec<(u64, u64)>,
+}
+
+impl OverlapWindows {
+    /// Windows of the chunks `[start, end)` in `spans`, in any order
+    pub fn from_spans(spans: impl IntoIterator<Item = (u64, u64)>) -> Self {
+        let mut spans: Vec<(u64, u64)> = spans.into_iter().filter(|(start, end)| start < end).collect();
+        spans.sort_unstable();
+
+        let mut windows: Vec<(u64, u64)> = Vec::new();
+        let mut reach = None;
+        for (start, end) in spans {
+            if let Some(reach) = reach.filter(|&reach| start < reach) {
+                let window = (start, end.min(reach));
+                match windows.last_mut() {
+                    Some(last) if window.0 <= last.1 => last.1 = last.1.max(window.1),
+                    _ => windows.push(window),
+                }
+            }
+            reach = Some(reach.map_or(end, |reach: u64| reach.max(end)));
+        }
+        Self { windows }
+    }
+
+    /// Whether more than one chunk read `offset`
+    pub fn contains(&self, offset: u64) -> bool {
+        let index = self.windows.partition_point(|&(start, _)| start <= offset);
+        index > 0 && offset < self.windows[index - 1].1
+    }
+
+    pub fn windows(&self) -> &[(u64, u64)] {
+        &self.windows
+    }
+}
+
+/// Keep one of the `items` reported for the same occurrence inside `windows`.
+/// `occurrence` gives the offset of an item and what identifies it there;
+/// `better(new, kept)` picks which report stays. Items outside the windows
+/// are never merged.
+pub fn suppress_duplicates<T, K, O, B>(items: &mut Vec<T>, windows: &OverlapWindows, occurrence: O, better: B)
+where
+    K: Hash + Eq,
+    O: Fn(&T) -> (u64, K),
+    B: Fn(&T, &T) -> bool,
+{
+    if windows.windows.is_empty() {
+        return;
+    }
+    let mut kept: Vec<T> = Vec::with_capacity(items.len());
+    let mut seen: HashMap<(u64, K), usize> = HashMap::new();
+    for item in items.drain(..) {
+        let (offset, key) = occurrence(&item);
+        if !windows.contains(offset) {
+            kept.push(item);
+            continue;
+        }
+        match seen.entry((offset, key)) {
+            Entry::Occupied(entry) => {
+                let index = *entry.get();
+                if better(&item, &kept[index]) {
+                    kept[index] = item;
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(kept.len());
+                kept.push(item);
+            }
+        }
+    }
+    *items = kept;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlap_windows_and_suppression() {
+        // Chunks of 100 bytes reading 20 past their end, the last one cut at 250
+        let windows = OverlapWindows::from_spans([(200, 250), (0, 120), (100, 220)]);
+        assert_eq!(windows.windows(), [(100, 120), (200, 220)]);
+        assert!(!windows.contains(99) && windows.contains(100) && windows.contains(219) && !windows.contains(220));
+        assert_eq!(OverlapWindows::from_spans([(0, 100), (100, 200)]).windows(), []);
+
+        // (offset, id, confidence): the second chunk re-reports 110, 300 is a true repeat
+        let mut items = vec![(10, "a", 5), (110, "b", 5), (110, "b", 7), (115, "c", 5), (300, "a", 5), (300, "a", 6)];
+        suppress_duplicates(&mut items, &windows, |&(offset, id, _)| (offset, id), |new, kept| new.2 > kept.2);
+        assert_eq!(items, [(10, "a", 5), (110, "b", 7), (115, "c", 5), (300, "a", 5), (300, "a", 6)]);
+    }
+}
@@ -1,6 +1,8 @@
 //! Ручная ASM оптимизация для block scanning
 
+#[cfg(target_arch = "x86_64")]
 use std::arch::asm;
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
 #[repr(C, align(64))]
@@ -21,6 +23,7 @@ pub struct BlockScanResultExt {
 }
 
 /// Супер-оптимизированный сканер блоков с AVX2
+#[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx2", enable = "bmi2")]
 pub unsafe fn scan_block_avx2_asm(block: &AlignedBlock) -> BlockScanResultExt {
     let ptr = block.data.as_ptr();
@@ -131,6 +134,7 @@ pub unsafe fn scan_block_avx2_asm(block: &AlignedBlock) -> BlockScanResultExt {
 }
 
 /// Batch сканирование нескольких блоков (для лучшего cache reuse)
+#[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx2")]
 pub unsafe fn scan_blocks_batch_asm(
     blocks: &[AlignedBlock],
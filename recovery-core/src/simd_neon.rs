@@ -0,0 +1,177 @@
+//! NEON paths of simd_search and entropy (aarch64: Apple M-series, Raspberry Pi 4/5)
+//!
+//! NEON has no movemask: compare results are ANDed with per-lane bit weights
+//! and each 8-lane half is summed (`vaddv_u8`), which yields the same bit
+//! masks as `_mm_movemask_epi8`. Callers dispatch with
+//! `is_aarch64_feature_detected!("neon")`.
+
+use crate::simd_block_scanner_asm::BlockScanResultExt;
+use crate::simd_search::BlockScanResult;
+use std::arch::aarch64::*;
+
+/// Bit weight of each lane within its 8-lane half
+const LANE_BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+/// Bit i set where lane i of `cmp` is 0xFF
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn movemask(cmp: uint8x16_t) -> u16 {
+    let bits = vandq_u8(cmp, vld1q_u8(LANE_BITS.as_ptr()));
+    let low = vaddv_u8(vget_low_u8(bits)) as u16;
+    let high = vaddv_u8(vget_high_u8(bits)) as u16;
+    low | (high << 8)
+}
+
+/// Hot-byte (y, h, {, v, /) and zero-byte masks of the 32 bytes at `ptr`
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn block_masks_32(ptr: *const u8) -> (u32, u32) {
+    let mut hot_mask = 0u32;
+    let mut zero_mask = 0u32;
+    for half in 0..2 {
+        let chunk = vld1q_u8(ptr.add(half * 16));
+        let hot = vorrq_u8(
+            vorrq_u8(vceqq_u8(chunk, vdupq_n_u8(b'y')), vceqq_u8(chunk, vdupq_n_u8(b'h'))),
+            vorrq_u8(
+                vceqq_u8(chunk, vdupq_n_u8(b'{')),
+                vorrq_u8(vceqq_u8(chunk, vdupq_n_u8(b'v')), vceqq_u8(chunk, vdupq_n_u8(b'/'))),
+            ),
+        );
+        hot_mask |= (movemask(hot) as u32) << (half * 16);
+        zero_mask |= (movemask(vceqzq_u8(chunk)) as u32) << (half * 16);
+    }
+    (hot_mask, zero_mask)
+}
+
+/// First-byte filter 16 bytes at a time, candidates confirmed by comparing
+/// the whole needle. Requires `haystack.len() >= needle.len() > 0`.
+#[target_feature(enable = "neon")]
+pub unsafe fn find_pattern_neon(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let needle_len = needle.len();
+    let first_byte = vdupq_n_u8(needle[0]);
+    let end = haystack.len() - needle_len;
+
+    let mut i = 0;
+    while i + 16 <= end {
+        let chunk = vld1q_u8(haystack.as_ptr().add(i));
+        let mut mask = movemask(vceqq_u8(chunk, first_byte));
+        while mask != 0 {
+            let pos = i + mask.trailing_zeros() as usize;
+            if &haystack[pos..pos + needle_len] == needle {
+                return Some(pos);
+            }
+            mask &= mask - 1;
+        }
+        i += 16;
+    }
+
+    haystack[i..]
+        .windows(needle_len)
+        .position(|window| window == needle)
+        .map(|pos| i + pos)
+}
+
+/// 32-byte block scan (`scan_block_simd`). Requires `block.len() >= 32`.
+#[target_feature(enable = "neon")]
+pub unsafe fn scan_block_neon(block: &[u8]) -> BlockScanResult {
+    let (hot_mask, zero_mask) = block_masks_32(block.as_ptr());
+    BlockScanResult {
+        is_empty: zero_mask == u32::MAX,
+        has_metadata: block[0] == 0x85,
+        hot_mask,
+    }
+}
+
+/// 64-byte block scan of the chunk pass, same fields as `scan_block_avx2_asm`.
+/// Requires `block.len() >= 64`.
+#[target_feature(enable = "neon")]
+pub unsafe fn scan_block64_neon(block: &[u8]) -> BlockScanResultExt {
+    let (hot_mask_low, zero_low) = block_masks_32(block.as_ptr());
+    let (hot_mask_high, zero_high) = block_masks_32(block.as_ptr().add(32));
+    let zero_count = (zero_low.count_ones() + zero_high.count_ones()) as u8;
+    BlockScanResultExt {
+        is_empty: zero_low == u32::MAX && zero_high == u32::MAX,
+        has_metadata: block[0] == 0x85,
+        hot_mask_low,
+        hot_mask_high,
+        zero_count,
+        high_entropy: zero_count < 8 && (hot_mask_low != 0 || hot_mask_high != 0),
+    }
+}
+
+/// Byte histogram for the entropy calculation. Uniform 16-byte runs (zero
+/// fill, padding) are counted in one step; other bytes go to four
+/// interleaved sub-histograms so repeated values do not serialise on one
+/// counter. The sub-histograms are merged four bins at a time.
+#[target_feature(enable = "neon")]
+pub unsafe fn byte_histogram_neon(data: &[u8]) -> [u32; 256] {
+    let mut counts = [[0u32; 256]; 4];
+    let mut blocks = data.chunks_exact(16);
+    for block in blocks.by_ref() {
+        let v = vld1q_u8(block.as_ptr());
+        let min = vminvq_u8(v);
+        if min == vmaxvq_u8(v) {
+            counts[0][min as usize] += 16;
+            continue;
+        }
+        for (lane, &byte) in block.iter().enumerate() {
+            counts[lane & 3][byte as usize] += 1;
+        }
+    }
+    for &byte in blocks.remainder() {
+        counts[0][byte as usize] += 1;
+    }
+
+    let mut histogram = [0u32; 256];
+    for bin in (0..256).step_by(4) {
+        let sum = vaddq_u32(
+            vaddq_u32(vld1q_u32(counts[0].as_ptr().add(bin)), vld1q_u32(counts[1].as_ptr().add(bin))),
+            vaddq_u32(vld1q_u32(counts[2].as_ptr().add(bin)), vld1q_u32(counts[3].as_ptr().add(bin))),
+        );
+        vst1q_u32(histogram.as_mut_ptr().add(bin), sum);
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neon_paths_match_scalar() {
+        if !std::arch::is_aarch64_feature_detected!("neon") {
+            return;
+        }
+        let mut data: Vec<u8> = (0..4099u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        data[1000..1200].fill(0);
+        let needle = b"youtube.com/watch?v=";
+        data[3000..3000 + needle.len()].copy_from_slice(needle);
+
+        unsafe {
+            let expected = data.windows(needle.len()).position(|w| w == needle);
+            assert_eq!(find_pattern_neon(&data, needle), expected);
+            assert_eq!(find_pattern_neon(&data[..3010], needle), None);
+
+            let mut expected = [0u32; 256];
+            data.iter().for_each(|&b| expected[b as usize] += 1);
+            assert_eq!(byte_histogram_neon(&data), expected);
+
+            for block in data.chunks_exact(64) {
+                let hot = |half: &[u8]| {
+                    half.iter()
+                        .enumerate()
+                        .filter(|(_, b)| matches!(b, b'y' | b'h' | b'{' | b'v' | b'/'))
+                        .fold(0u32, |mask, (i, _)| mask | 1 << i)
+                };
+                let res = scan_block_neon(block);
+                assert_eq!(res.hot_mask, hot(&block[..32]));
+                assert_eq!(res.is_empty, block[..32].iter().all(|&b| b == 0));
+
+                let res = scan_block64_neon(block);
+                assert_eq!((res.hot_mask_low, res.hot_mask_high), (hot(&block[..32]), hot(&block[32..])));
+                assert_eq!(res.zero_count as usize, block.iter().filter(|&&b| b == 0).count());
+                assert_eq!(res.is_empty, block.iter().all(|&b| b == 0));
+            }
+        }
+    }
+}
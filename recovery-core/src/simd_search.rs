@@ -1,10 +1,12 @@
 // SIMD-Optimized Pattern Search
 // Uses AVX2/SSE4.2 for ultra-fast pattern matching
-// Optimized for Intel CPUs (OptiPlex 3070 Micro)
+// Optimized for Intel CPUs (OptiPlex 3070 Micro), NEON on aarch64 (simd_neon)
 
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
+use crate::simd_block_scanner_asm::BlockScanResultExt;
+#[cfg(target_arch = "x86_64")]
 use crate::simd_block_scanner_asm::AlignedBlock;
 
 /// Results of a 32-byte block scan
@@ -40,6 +42,13 @@ pub fn find_pattern_simd(haystack: &[u8], needle: &[u8]) -> Option<usize> {
         }
     }
 
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { crate::simd_neon::find_pattern_neon(haystack, needle) };
+        }
+    }
+
     // Fallback to scalar
     find_pattern_scalar(haystack, needle)
 }
@@ -175,9 +184,44 @@ pub fn scan_block_simd(block: &[u8]) -> BlockScanResult {
         }
     }
 
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { crate::simd_neon::scan_block_neon(block) };
+        }
+    }
+
     scan_block_scalar(block)
 }
 
+/// 64-byte block scan of the chunk pass (AVX2 or NEON), None without either
+#[inline]
+pub fn scan_block64_simd(block: &[u8]) -> Option<BlockScanResultExt> {
+    if block.len() < 64 {
+        return None;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // Buffered chunks (devices, E01) are not 64-byte aligned: copy the block out
+            unsafe {
+                let aligned_block = std::ptr::read_unaligned(block.as_ptr() as *const AlignedBlock);
+                return Some(crate::simd_block_scanner_asm::scan_block_avx2_asm(&aligned_block));
+            }
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return Some(unsafe { crate::simd_neon::scan_block64_neon(block) });
+        }
+    }
+
+    None
+}
+
 fn scan_block_scalar(block: &[u8]) -> BlockScanResult {
     let mut is_empty = true;
     let mut hot_mask = 0u32;
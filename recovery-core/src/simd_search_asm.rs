@@ -20,9 +20,11 @@ pub unsafe fn find_pattern_avx2_asm(haystack: &[u8], needle: &[u8]) -> Option<us
     // Создаем вектор для поиска первого байта
     let search_vec: __m256i;
     asm!(
-        "vpbroadcastb {search}, {first_byte}",
+        "vmovd {tmp}, {first_byte:e}",
+        "vpbroadcastb {search}, {tmp}",
         search = out(ymm_reg) search_vec,
-        first_byte = in(reg_byte) first_byte,
+        tmp = out(xmm_reg) _,
+        first_byte = in(reg) first_byte as u32,
         options(pure, nomem, nostack)
     );
     
@@ -0,0 +1,239 @@
+//! Types shared by the scanners of both crates
+
+use serde::{Deserialize, Serialize};
+
+/// Newtype wrapper for byte offsets in disk images
+///
+/// Serialized as a plain integer, so JSON reports and checkpoints keep their shape.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Offset(pub u64);
+
+impl Offset {
+    pub fn new(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    pub fn checked_add(&self, size: Size) -> Option<Offset> {
+        self.0.checked_add(size.0).map(Offset)
+    }
+
+    /// Offset just past `size` bytes starting here (saturating)
+    pub fn end(&self, size: Size) -> Offset {
+        Offset(self.0.saturating_add(size.0))
+    }
+
+    /// Distance from `earlier` to `self`, `None` if `earlier` is past `self`
+    pub fn distance_from(&self, earlier: Offset) -> Option<Size> {
+        self.0.checked_sub(earlier.0).map(Size)
+    }
+
+    /// Index into an in-memory buffer, `None` if it doesn't fit in `usize`
+    pub fn as_usize(&self) -> Option<usize> {
+        usize::try_from(self.0).ok()
+    }
+}
+
+impl From<u64> for Offset {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Offset> for u64 {
+    fn from(offset: Offset) -> Self {
+        offset.0
+    }
+}
+
+impl std::ops::Add<Size> for Offset {
+    type Output = Offset;
+
+    fn add(self, size: Size) -> Offset {
+        Offset(self.0 + size.0)
+    }
+}
+
+impl std::fmt::UpperHex for Offset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::UpperHex::fmt(&self.0, f)
+    }
+}
+
+impl std::fmt::LowerHex for Offset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl std::fmt::Display for Offset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{:X}", self.0)
+    }
+}
+
+/// Newtype wrapper for sizes in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Size(pub u64);
+
+impl Size {
+    pub fn new(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        usize::try_from(self.0).ok()
+    }
+
+    /// Size of an in-memory buffer (lossless on every supported target)
+    pub fn from_usize(value: usize) -> Self {
+        Self(value as u64)
+    }
+
+    pub fn as_kb(&self) -> u64 {
+        self.0 / 1024
+    }
+}
+
+impl From<u64> for Size {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<usize> for Size {
+    fn from(value: usize) -> Self {
+        Self::from_usize(value)
+    }
+}
+
+impl From<Size> for u64 {
+    fn from(size: Size) -> Self {
+        size.0
+    }
+}
+
+impl std::fmt::Display for Size {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} bytes", self.0)
+    }
+}
+
+/// Newtype wrapper for cluster IDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ClusterId(pub u64);
+
+impl ClusterId {
+    pub fn new(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for ClusterId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cluster_{}", self.0)
+    }
+}
+
+/// Times a filesystem recorded for a file, with the UTC offset it recorded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileTimestamps {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<chrono::DateTime<chrono::FixedOffset>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<chrono::DateTime<chrono::FixedOffset>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessed: Option<chrono::DateTime<chrono::FixedOffset>>,
+}
+
+impl FileTimestamps {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_none() && self.modified.is_none() && self.accessed.is_none()
+    }
+
+    /// Give the file at `path` these modification and access times (the
+    /// creation time cannot be set portably)
+    pub fn apply(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let mut times = std::fs::FileTimes::new();
+        if let Some(modified) = self.modified {
+            times = times.set_modified(modified.into());
+        }
+        if let Some(accessed) = self.accessed {
+            times = times.set_accessed(accessed.into());
+        }
+        std::fs::File::options().write(true).open(path)?.set_times(times)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_and_size_serialize_as_integers() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Extent {
+            offset: Offset,
+            size: Size,
+        }
+        let extent = Extent { offset: Offset::new(u64::MAX), size: Size::new(4096) };
+        let json = serde_json::to_string(&extent).unwrap();
+        assert_eq!(json, r#"{"offset":18446744073709551615,"size":4096}"#);
+        assert_eq!(serde_json::from_str::<Extent>(&json).unwrap(), extent);
+        assert_eq!(serde_json::from_str::<Offset>("0").unwrap(), Offset::default());
+        assert!(serde_json::from_str::<Offset>("-1").is_err());
+        assert!(serde_json::from_str::<Size>("18446744073709551616").is_err());
+        assert!(serde_json::from_str::<Size>("\"4096\"").is_err());
+    }
+
+    #[test]
+    fn test_offset_arithmetic_at_the_limits() {
+        let last = Offset::new(u64::MAX - 10);
+        assert_eq!(last.checked_add(Size::new(10)), Some(Offset::new(u64::MAX)));
+        assert_eq!(last.checked_add(Size::new(11)), None);
+        assert_eq!(last.end(Size::new(100)), Offset::new(u64::MAX));
+        assert_eq!(last + Size::new(10), Offset::new(u64::MAX));
+
+        assert_eq!(Offset::new(4096).distance_from(Offset::new(1024)), Some(Size::new(3072)));
+        assert_eq!(Offset::new(1024).distance_from(Offset::new(4096)), None);
+
+        assert_eq!(Offset::new(4096).as_usize(), Some(4096));
+        assert_eq!(Size::from_usize(usize::MAX).as_usize(), Some(usize::MAX));
+        #[cfg(target_pointer_width = "32")]
+        assert_eq!(Offset::new(1 << 32).as_usize(), None);
+        assert_eq!(u64::from(Offset::from(7u64)), 7);
+        assert_eq!(u64::from(Size::from(7usize)), 7);
+        assert_eq!(Size::new(2047).as_kb(), 1);
+        assert_eq!(Offset::new(0x1F00).to_string(), "0x1F00");
+    }
+
+    #[test]
+    fn test_file_timestamps_keep_their_offset() {
+        let time = |rfc3339: &str| Some(chrono::DateTime::parse_from_rfc3339(rfc3339).unwrap());
+        let times = FileTimestamps {
+            created: time("2023-12-31T21:41:05.370+03:00"),
+            modified: time("2024-01-01T00:00:00+00:00"),
+            accessed: None,
+        };
+        let json = serde_json::to_string(&times).unwrap();
+        assert_eq!(json, r#"{"created":"2023-12-31T21:41:05.370+03:00","modified":"2024-01-01T00:00:00Z"}"#);
+        assert_eq!(serde_json::from_str::<FileTimestamps>(&json).unwrap(), times);
+        assert_eq!(serde_json::to_string(&FileTimestamps::default()).unwrap(), "{}");
+        assert!(serde_json::from_str::<FileTimestamps>("{}").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+//! Needle finder of `PatternProfile`: the shipped Aho-Corasick DFA with the
+//! crate's prefilter (Teddy when the needle set allows it) against the same
+//! DFA without it, an NFA, and the regex alternation it replaced, for the
+//! built-in YouTube needles and for a profile with a few hundred needles.
+
+use aho_corasick::{AhoCorasick, AhoCorasickKind, MatchKind};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use regex::bytes::{Regex, RegexBuilder};
+use rust_recovery::matcher::profile::PatternProfile;
+
+const HAYSTACK_LEN: usize = 4 * 1024 * 1024;
+
+/// Mostly printable bytes (xorshift) with a needle every ~64 KiB
+fn haystack(needles: &[Vec<u8>]) -> Vec<u8> {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let mut data: Vec<u8> = (0..HAYSTACK_LEN)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            b' ' + (state % 95) as u8
+        })
+        .collect();
+    for (i, at) in (0..HAYSTACK_LEN - 64).step_by(64 * 1024).enumerate() {
+        let needle = &needles[i % needles.len()];
+        data[at..at + needle.len()].copy_from_slice(needle);
+    }
+    data
+}
+
+fn automaton(needles: &[Vec<u8>], kind: AhoCorasickKind, prefilter: bool, ignore_case: bool) -> AhoCorasick {
+    AhoCorasick::builder()
+        .kind(Some(kind))
+        .match_kind(MatchKind::LeftmostFirst)
+        .ascii_case_insensitive(ignore_case)
+        .prefilter(prefilter)
+        .build(needles)
+        .unwrap()
+}
+
+/// The finder before the switch: one regex over the escaped needles
+fn alternation(needles: &[Vec<u8>], ignore_case: bool) -> Regex {
+    let escaped: Vec<String> = needles.iter().map(|n| regex::escape(std::str::from_utf8(n).unwrap())).collect();
+    RegexBuilder::new(&format!("(?-u:{})", escaped.join("|")))
+        .case_insensitive(ignore_case)
+        .size_limit(50 * 1024 * 1024)
+        .build()
+        .unwrap()
+}
+
+fn bench_finder_set(
+    c: &mut Criterion,
+    group_name: &str,
+    needles: &[Vec<u8>],
+    shipped: &AhoCorasick,
+    ignore_case: bool,
+) {
+    let data = haystack(needles);
+    let mut group = c.benchmark_group(group_name);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    let count = |finder: &AhoCorasick| finder.find_iter(&data).count();
+    group.bench_function(BenchmarkId::new("ac_dfa_prefilter", needles.len()), |b| b.iter(|| black_box(count(shipped))));
+    let dfa = automaton(needles, AhoCorasickKind::DFA, false, ignore_case);
+    group.bench_function(BenchmarkId::new("ac_dfa", needles.len()), |b| b.iter(|| black_box(count(&dfa))));
+    let nfa = automaton(needles, AhoCorasickKind::ContiguousNFA, true, ignore_case);
+    group.bench_function(BenchmarkId::new("ac_nfa_prefilter", needles.len()), |b| b.iter(|| black_box(count(&nfa))));
+    let regex = alternation(needles, ignore_case);
+    group.bench_function(BenchmarkId::new("regex_alternation", needles.len()), |b| {
+        b.iter(|| black_box(regex.find_iter(&data).count()))
+    });
+
+    group.finish();
+}
+
+fn bench_youtube_finder(c: &mut Criterion) {
+    let youtube = PatternProfile::youtube();
+    let finder = youtube.finder.as_ref().unwrap();
+    bench_finder_set(c, "finder_youtube", &youtube.needles, finder, youtube.needles_ignore_case);
+}
+
+fn bench_many_needles(c: &mut Criterion) {
+    // Distinct words of 5-12 letters, like the keywords of a user profile
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let needles: Vec<Vec<u8>> = (0..300)
+        .map(|_| {
+            let len = 5 + (next() % 8) as usize;
+            (0..len).map(|_| b'a' + (next() % 26) as u8).collect()
+        })
+        .collect();
+    let shipped = automaton(&needles, AhoCorasickKind::DFA, true, false);
+    bench_finder_set(c, "finder_300_needles", &needles, &shipped, false);
+}
+
+criterion_group!(benches, bench_youtube_finder, bench_many_needles);
+criterion_main!(benches);
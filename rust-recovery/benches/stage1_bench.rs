@@ -1,5 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use rust_recovery::simd_search::*;
+#[cfg(target_arch = "x86_64")]
 use rust_recovery::simd_search_asm::*;
 use rust_recovery::simd_block_scanner_asm::*;
 
@@ -16,6 +17,7 @@ fn bench_pattern_search(c: &mut Criterion) {
         });
     });
     
+    #[cfg(target_arch = "x86_64")]
     group.bench_function("asm_avx2_direct", |b| {
         b.iter(|| {
             unsafe { black_box(find_pattern_avx2_asm(&haystack, needle)) }
@@ -35,7 +37,14 @@ fn bench_block_scanner(c: &mut Criterion) {
             black_box(scan_block_simd(&block.data[..32]))
         });
     });
+
+    group.bench_function("simd_64", |b| {
+        b.iter(|| {
+            black_box(scan_block64_simd(&block.data))
+        });
+    });
     
+    #[cfg(target_arch = "x86_64")]
     group.bench_function("asm_optimized_64", |b| {
         b.iter(|| {
             unsafe { black_box(scan_block_avx2_asm(&block)) }
@@ -0,0 +1,195 @@
+//! Chain-of-custody audit log (`audit.jsonl` in the output directory)
+//!
+//! Every action that touches the evidence or the results is appended as one
+//! JSON line: the run and its command line, the image opened, the scan
+//! parameters, each file written, repaired or removed with its hashes, the
+//! reports generated. Each entry carries the SHA-256 of the entry before it
+//! (`prev_hash`) and its own `hash` over its other fields, so an edited,
+//! removed or reordered line breaks the chain from that point on. The log is
+//! never truncated: a resumed or repeated run into the same directory
+//! continues the chain. The JSON report records the chain hash at the time
+//! it was written; the `report_generated` entry after it records the hash of
+//! the report, tying the two together. With `--output s3://...` every
+//! uploaded object is recorded with its ETag, and the log goes up last.
+
+use crate::error::{RecoveryError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// File name in the output directory
+pub const AUDIT_LOG_FILE: &str = "audit.jsonl";
+
+/// `prev_hash` of the first entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What an entry records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    RunStarted,
+    ImageOpened,
+    ScanParameters,
+    FileWritten,
+    /// Rewritten by the validation pass (`--no-repair` turns it off)
+    FileRepaired,
+    /// Deleted as an exact duplicate
+    FileRemoved,
+    /// `--verify-hash` digest of the whole image
+    ImageDigest,
+    ReportGenerated,
+    /// Uploaded to `--output s3://...`
+    ObjectUploaded,
+    RunFinished,
+}
+
+/// One line of the log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the chain, from 1
+    pub seq: u64,
+    /// Local time, RFC 3339 with milliseconds
+    pub timestamp: String,
+    pub action: AuditAction,
+    pub details: Value,
+    pub prev_hash: String,
+    /// SHA-256 of the fields above (see `entry_hash`)
+    pub hash: String,
+}
+
+/// The hashed fields of an entry, in serialization order
+#[derive(Serialize)]
+struct HashedFields<'a> {
+    seq: u64,
+    timestamp: &'a str,
+    action: AuditAction,
+    details: &'a Value,
+    prev_hash: &'a str,
+}
+
+/// SHA-256 of the compact JSON of the entry without its `hash`
+fn entry_hash(seq: u64, timestamp: &str, action: AuditAction, details: &Value, prev_hash: &str) -> String {
+    let fields = HashedFields { seq, timestamp, action, details, prev_hash };
+    let json = serde_json::to_vec(&fields).unwrap_or_default();
+    format!("{:x}", Sha256::digest(&json))
+}
+
+/// End of a verified chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditChain {
+    pub entries: u64,
+    /// Hash of the last entry (`GENESIS_HASH` for an empty log)
+    pub chain_hash: String,
+}
+
+/// Check every entry of the log at `path` against its predecessor; the
+/// error names the first line that does not fit
+pub fn verify(path: &Path) -> Result<AuditChain> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut chain = AuditChain { entries: 0, chain_hash: GENESIS_HASH.to_string() };
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let broken = |reason: &str| RecoveryError::Parse(format!("{} line {}: {}", path.display(), index + 1, reason));
+        let entry: AuditEntry = serde_json::from_str(&line).map_err(|e| broken(&e.to_string()))?;
+        if entry.seq != chain.entries + 1 {
+            return Err(broken(&format!("sequence {} where {} was expected", entry.seq, chain.entries + 1)));
+        }
+        if entry.prev_hash != chain.chain_hash {
+            return Err(broken("previous hash does not match the entry before"));
+        }
+        if entry.hash != entry_hash(entry.seq, &entry.timestamp, entry.action, &entry.details, &entry.prev_hash) {
+            return Err(broken("entry hash does not match its contents"));
+        }
+        chain = AuditChain { entries: entry.seq, chain_hash: entry.hash };
+    }
+    Ok(chain)
+}
+
+#[derive(Debug)]
+struct AuditState {
+    file: File,
+    chain: AuditChain,
+}
+
+/// Append-only writer of `audit.jsonl`, shared by the scan and report stages
+#[derive(Debug)]
+pub struct AuditLog {
+    state: Mutex<AuditState>,
+}
+
+impl AuditLog {
+    /// Open the log for appending, continuing the chain already in it. A
+    /// broken chain is an error: appending to it would hide the break
+    pub fn open(path: &Path) -> Result<Self> {
+        let chain = if path.exists() {
+            verify(path)?
+        } else {
+            AuditChain { entries: 0, chain_hash: GENESIS_HASH.to_string() }
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { state: Mutex::new(AuditState { file, chain }) })
+    }
+
+    /// Append one entry with a single write and push it to disk; returns its hash
+    pub fn record(&self, action: AuditAction, details: Value) -> std::io::Result<String> {
+        let mut state = self.state.lock().unwrap();
+        let seq = state.chain.entries + 1;
+        let timestamp = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false);
+        let hash = entry_hash(seq, &timestamp, action, &details, &state.chain.chain_hash);
+        let entry = AuditEntry { seq, timestamp, action, details, prev_hash: state.chain.chain_hash.clone(), hash };
+        let mut line = serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+        line.push('\n');
+        state.file.write_all(line.as_bytes())?;
+        state.file.sync_data()?;
+        state.chain = AuditChain { entries: seq, chain_hash: entry.hash };
+        Ok(state.chain.chain_hash.clone())
+    }
+
+    /// Hash of the last entry written
+    pub fn chain_hash(&self) -> String {
+        self.state.lock().unwrap().chain.chain_hash.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_hash_chain_continues_and_detects_edits() {
+        let path = std::env::temp_dir().join(format!("audit_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let log = AuditLog::open(&path).unwrap();
+        assert_eq!(log.chain_hash(), GENESIS_HASH);
+        let first = log.record(AuditAction::RunStarted, json!({ "command_line": ["rust-recovery", "disk.img"] })).unwrap();
+        log.record(AuditAction::FileWritten, json!({ "path": "recovered_0001.json", "sha256": "ab" })).unwrap();
+        drop(log);
+
+        // A second run appends to the same chain
+        let log = AuditLog::open(&path).unwrap();
+        let last = log.record(AuditAction::RunFinished, json!({ "status": "recovered" })).unwrap();
+        assert_eq!(verify(&path).unwrap(), AuditChain { entries: 3, chain_hash: last.clone() });
+        drop(log);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<AuditEntry> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(entries[1].prev_hash, first);
+        assert_eq!(entries[2].seq, 3);
+
+        // Editing a field, or dropping a line, breaks the chain
+        std::fs::write(&path, text.replace("recovered_0001.json", "recovered_0002.json")).unwrap();
+        assert!(verify(&path).unwrap_err().to_string().contains("line 2"));
+        let without_second: Vec<&str> = text.lines().enumerate().filter(|(i, _)| *i != 1).map(|(_, l)| l).collect();
+        std::fs::write(&path, without_second.join("\n")).unwrap();
+        assert!(verify(&path).unwrap_err().to_string().contains("line 2"));
+        assert!(AuditLog::open(&path).is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,1383 @@
+//! Signature carver for common media and document formats
+//!
+//! Each format has a header signature and an end rule: a footer marker, or a
+//! structure walk (JPEG segments, PNG chunks, MP4/MOV boxes, TIFF IFD chains of
+//! raw photos) that finds the end of the file without trusting the first
+//! footer-looking bytes. Only files whose end was found are carved; the
+//! scanner reports them as hot fragments. A raw photo whose end is not found
+//! can still yield its embedded JPEG preview, carved as a JPEG of its own.
+//!
+//! Office documents come in two containers. OOXML (`docx`, `xlsx`, `pptx`)
+//! is a ZIP typed by its part names; its local entries are walked, so a
+//! package whose central directory was cut off or overwritten is still
+//! carved up to its last complete entry (the directory is rebuilt when the
+//! file is saved, see `recovery::office`). The older formats (`doc`, `xls`,
+//! `ppt`) are OLE2 compound files typed by their main stream and ending after
+//! the last sector their FAT uses; a truncated one yields the text of its
+//! main stream instead.
+
+use crate::recovery::office::OFFICE_TEXT;
+use memchr::memmem;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Header/footer definition of one carvable format
+#[derive(Debug)]
+pub struct FileSignature {
+    /// File type of carved files (also the extension)
+    pub name: &'static str,
+    /// Header variants (TIFF byte orders)
+    pub headers: &'static [&'static [u8]],
+    /// Position of the header within the file (MP4 `ftyp` follows the box size)
+    pub header_offset: usize,
+    /// Footer marker, `None` when the end comes from the structure walk only
+    pub footer: Option<&'static [u8]>,
+    /// Largest file carved; the end must be found within this many bytes
+    pub max_size: usize,
+    /// Length of the file starting at `data[0]`, `None` if no valid end is found
+    end: fn(&[u8]) -> Option<usize>,
+    /// Type and finder of what is carved instead when `end` finds none: a
+    /// raw photo's embedded JPEG, the text of an OLE2 document
+    preview: Option<(&'static str, PreviewFn)>,
+}
+
+/// `(offset, size)` of the embedded file in the file starting at `data[0]`
+type PreviewFn = fn(&[u8]) -> Option<(usize, usize)>;
+
+const JPEG_EOI: &[u8] = b"\xFF\xD9";
+const PNG_IEND: &[u8] = b"IEND\xAE\x42\x60\x82";
+const PDF_HEADER: &[u8] = b"%PDF-";
+const PDF_EOF: &[u8] = b"%%EOF";
+const ZIP_EOCD: &[u8] = b"PK\x05\x06";
+const ZIP_EOCD_SIZE: usize = 22;
+const ZIP_LOCAL: &[u8] = b"PK\x03\x04";
+const ZIP_LOCAL_SIZE: usize = 30;
+const ZIP_CENTRAL: &[u8] = b"PK\x01\x02";
+const ZIP_DESCRIPTOR: &[u8] = b"PK\x07\x08";
+const OLE_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+const OLE_HEADER_SIZE: usize = 512;
+/// FAT entry of an unused sector
+const OLE_FREE: u32 = 0xFFFF_FFFF;
+const OFFICE_MAX_SIZE: usize = 256 * 1024 * 1024;
+const TIFF_LE: &[u8] = b"II*\0";
+const TIFF_BE: &[u8] = b"MM\0*";
+const RAW_MAX_SIZE: usize = 256 * 1024 * 1024;
+/// IFDs followed per TIFF file (SubIFDs and EXIF included); more is a loop
+const TIFF_MAX_IFDS: usize = 64;
+
+/// Supported formats
+pub static SIGNATURES: &[FileSignature] = &[
+    FileSignature {
+        name: "jpg",
+        headers: &[b"\xFF\xD8\xFF"],
+        header_offset: 0,
+        footer: Some(JPEG_EOI),
+        max_size: 64 * 1024 * 1024,
+        end: jpeg_end,
+        preview: None,
+    },
+    FileSignature {
+        name: "png",
+        headers: &[b"\x89PNG\r\n\x1A\n"],
+        header_offset: 0,
+        footer: Some(PNG_IEND),
+        max_size: 64 * 1024 * 1024,
+        end: png_end,
+        preview: None,
+    },
+    FileSignature {
+        name: "pdf",
+        headers: &[PDF_HEADER],
+        header_offset: 0,
+        footer: Some(PDF_EOF),
+        max_size: 256 * 1024 * 1024,
+        end: pdf_end,
+        preview: None,
+    },
+    FileSignature {
+        name: "docx",
+        headers: &[ZIP_LOCAL],
+        header_offset: 0,
+        footer: None,
+        max_size: OFFICE_MAX_SIZE,
+        end: |data| ooxml_end(data, "docx"),
+        preview: None,
+    },
+    FileSignature {
+        name: "xlsx",
+        headers: &[ZIP_LOCAL],
+        header_offset: 0,
+        footer: None,
+        max_size: OFFICE_MAX_SIZE,
+        end: |data| ooxml_end(data, "xlsx"),
+        preview: None,
+    },
+    FileSignature {
+        name: "pptx",
+        headers: &[ZIP_LOCAL],
+        header_offset: 0,
+        footer: None,
+        max_size: OFFICE_MAX_SIZE,
+        end: |data| ooxml_end(data, "pptx"),
+        preview: None,
+    },
+    FileSignature {
+        name: "zip",
+        headers: &[b"PK\x03\x04"],
+        header_offset: 0,
+        footer: Some(ZIP_EOCD),
+        max_size: 512 * 1024 * 1024,
+        end: zip_end,
+        preview: None,
+    },
+    FileSignature {
+        name: "doc",
+        headers: &[OLE_MAGIC],
+        header_offset: 0,
+        footer: None,
+        max_size: OFFICE_MAX_SIZE,
+        end: |data| ole_end(data, "doc"),
+        preview: Some((OFFICE_TEXT, |data| ole_text_extent(data, "doc"))),
+    },
+    FileSignature {
+        name: "xls",
+        headers: &[OLE_MAGIC],
+        header_offset: 0,
+        footer: None,
+        max_size: OFFICE_MAX_SIZE,
+        end: |data| ole_end(data, "xls"),
+        preview: Some((OFFICE_TEXT, |data| ole_text_extent(data, "xls"))),
+    },
+    FileSignature {
+        name: "ppt",
+        headers: &[OLE_MAGIC],
+        header_offset: 0,
+        footer: None,
+        max_size: OFFICE_MAX_SIZE,
+        end: |data| ole_end(data, "ppt"),
+        preview: Some((OFFICE_TEXT, |data| ole_text_extent(data, "ppt"))),
+    },
+    FileSignature {
+        name: "mp4",
+        headers: &[b"ftyp"],
+        header_offset: 4,
+        footer: None,
+        max_size: 1024 * 1024 * 1024,
+        end: mp4_end,
+        preview: None,
+    },
+    FileSignature {
+        name: "mov",
+        headers: &[b"ftypqt  "],
+        header_offset: 4,
+        footer: None,
+        max_size: 1024 * 1024 * 1024,
+        end: movie_end,
+        preview: None,
+    },
+    FileSignature {
+        name: "cr2",
+        headers: &[b"II*\0\x10\0\0\0CR"],
+        header_offset: 0,
+        footer: None,
+        max_size: RAW_MAX_SIZE,
+        end: |data| raw_end(data, "cr2"),
+        preview: Some(("jpg", |data| raw_preview(data, "cr2"))),
+    },
+    FileSignature {
+        name: "nef",
+        headers: &[TIFF_LE, TIFF_BE],
+        header_offset: 0,
+        footer: None,
+        max_size: RAW_MAX_SIZE,
+        end: |data| raw_end(data, "nef"),
+        preview: Some(("jpg", |data| raw_preview(data, "nef"))),
+    },
+    FileSignature {
+        name: "arw",
+        headers: &[TIFF_LE, TIFF_BE],
+        header_offset: 0,
+        footer: None,
+        max_size: RAW_MAX_SIZE,
+        end: |data| raw_end(data, "arw"),
+        preview: Some(("jpg", |data| raw_preview(data, "arw"))),
+    },
+    FileSignature {
+        name: "dng",
+        headers: &[TIFF_LE, TIFF_BE],
+        header_offset: 0,
+        footer: None,
+        max_size: RAW_MAX_SIZE,
+        end: |data| raw_end(data, "dng"),
+        preview: Some(("jpg", |data| raw_preview(data, "dng"))),
+    },
+];
+
+/// Signature by file type name
+pub fn signature(name: &str) -> Option<&'static FileSignature> {
+    SIGNATURES.iter().find(|sig| sig.name == name)
+}
+
+/// A complete file found by `carve`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CarvedFile {
+    /// Offset into the carved buffer
+    pub offset: usize,
+    pub size: usize,
+    pub file_type: &'static str,
+    /// Type of the file this was taken from, which was not carved itself (a
+    /// raw photo's JPEG preview, a truncated OLE2 document's text)
+    pub preview_of: Option<&'static str>,
+}
+
+/// Carve files of `types` whose header starts before `header_limit`.
+///
+/// Files may extend past `header_limit` up to the end of `data`, so a caller
+/// with access to the rest of the image can pass it to complete files that
+/// cross the chunk end. Headers inside an already carved file of the same
+/// type (EXIF thumbnails, embedded archives) are skipped, and a raw preview
+/// also carved as a plain JPEG is kept once, as is an OOXML document also
+/// carved as a ZIP.
+pub fn carve(data: &[u8], header_limit: usize, types: &[String]) -> Vec<CarvedFile> {
+    let mut carved = Vec::new();
+
+    for sig in SIGNATURES.iter().filter(|sig| types.iter().any(|t| t == sig.name)) {
+        let mut hits = Vec::new();
+        for header in sig.headers {
+            let search_end = header_limit.saturating_add(sig.header_offset + header.len()).min(data.len());
+            hits.extend(memmem::find_iter(&data[..search_end], header));
+        }
+        hits.sort_unstable();
+        let mut next_free = 0usize;
+
+        for hit in hits {
+            let Some(start) = hit.checked_sub(sig.header_offset) else { continue };
+            if start >= header_limit {
+                break;
+            }
+            if start < next_free {
+                continue;
+            }
+
+            let limit = start.saturating_add(sig.max_size).min(data.len());
+            if let Some(size) = (sig.end)(&data[start..limit]) {
+                carved.push(CarvedFile { offset: start, size, file_type: sig.name, preview_of: None });
+                next_free = start + size;
+            } else if let Some((file_type, preview)) = sig.preview {
+                if let Some((offset, size)) = preview(&data[start..limit]) {
+                    carved.push(CarvedFile { offset: start + offset, size, file_type, preview_of: Some(sig.name) });
+                }
+            }
+        }
+    }
+
+    carved.sort_by_key(|file| file.offset);
+    carved.dedup_by(|file, kept| file.offset == kept.offset && file.size == kept.size);
+    carved
+}
+
+fn read_u16_be(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_be_bytes)
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_be_bytes)
+}
+
+fn read_u64_be(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_be_bytes)
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_le_bytes)
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+}
+
+/// Walk marker segments; after each SOS skip the entropy-coded data
+/// (stuffed `FF 00` and RSTn) to the next marker, until EOI
+fn jpeg_end(data: &[u8]) -> Option<usize> {
+    let mut pos = 2;
+
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        // Fill bytes before a marker
+        while *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+
+        match data[pos + 1] {
+            0xD9 => return Some(pos + 2),
+            // Nested SOI or a reserved marker: not a JPEG stream
+            0x00 | 0xD8 => return None,
+            0x01 | 0xD0..=0xD7 => pos += 2,
+            marker => {
+                let len = read_u16_be(data, pos + 2)? as usize;
+                if len < 2 {
+                    return None;
+                }
+                pos += 2 + len;
+
+                if marker == 0xDA {
+                    loop {
+                        pos += memchr::memchr(0xFF, data.get(pos..)?)?;
+                        match *data.get(pos + 1)? {
+                            0x00 | 0xD0..=0xD7 => pos += 2,
+                            0xFF => pos += 1,
+                            _ => break,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Walk chunks from IHDR to IEND
+fn png_end(data: &[u8]) -> Option<usize> {
+    let mut pos = 8;
+    if data.get(pos + 4..pos + 8)? != b"IHDR" {
+        return None;
+    }
+
+    loop {
+        let len = read_u32_be(data, pos)? as usize;
+        if len > i32::MAX as usize {
+            return None;
+        }
+        let chunk_type = data.get(pos + 4..pos + 8)?;
+        if !chunk_type.iter().all(u8::is_ascii_alphabetic) {
+            return None;
+        }
+
+        pos = pos.checked_add(12 + len)?;
+        if pos > data.len() {
+            return None;
+        }
+        if chunk_type == &PNG_IEND[..4] {
+            return Some(pos);
+        }
+    }
+}
+
+/// Last `%%EOF` (incremental updates append several) before the next PDF header
+fn pdf_end(data: &[u8]) -> Option<usize> {
+    let body_end = memmem::find(&data[PDF_HEADER.len()..], PDF_HEADER)
+        .map_or(data.len(), |pos| pos + PDF_HEADER.len());
+    let eof = memmem::rfind(&data[..body_end], PDF_EOF)?;
+
+    let mut end = eof + PDF_EOF.len();
+    if data.get(end) == Some(&b'\r') {
+        end += 1;
+    }
+    if data.get(end) == Some(&b'\n') {
+        end += 1;
+    }
+    Some(end)
+}
+
+/// First end-of-central-directory record whose central directory ends right
+/// before it (skips EOCDs of stored nested archives)
+fn zip_end(data: &[u8]) -> Option<usize> {
+    for eocd in memmem::find_iter(data, ZIP_EOCD) {
+        let cd_size = read_u32_le(data, eocd + 12)? as usize;
+        let cd_offset = read_u32_le(data, eocd + 16)? as usize;
+        let comment_len = read_u16_le(data, eocd + 20)? as usize;
+
+        if cd_offset.checked_add(cd_size) == Some(eocd) {
+            let end = eocd + ZIP_EOCD_SIZE + comment_len;
+            return (end <= data.len()).then_some(end);
+        }
+    }
+    None
+}
+
+/// Local entry of a ZIP file, with what its central directory record holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ZipEntry {
+    /// Offset of the local header
+    pub offset: usize,
+    /// Local header fields from "version needed" to the uncompressed size;
+    /// CRC and sizes come from the data descriptor when there is one
+    pub fields: [u8; 22],
+    pub name: Vec<u8>,
+}
+
+/// Complete local entries from the start of a ZIP file
+#[derive(Debug, Default)]
+pub(crate) struct ZipWalk {
+    pub entries: Vec<ZipEntry>,
+    /// End of the last complete entry
+    pub end: usize,
+    /// A central directory header follows the entries
+    pub at_central: bool,
+}
+
+/// Walk local entries until the bytes are not a complete one (the central
+/// directory, a cut-off entry or unrelated data)
+pub(crate) fn zip_local_entries(data: &[u8]) -> ZipWalk {
+    let mut walk = ZipWalk::default();
+    while let Some((entry, end)) = zip_local_entry(data, walk.end) {
+        walk.entries.push(entry);
+        walk.end = end;
+    }
+    walk.at_central = data.get(walk.end..walk.end + 4) == Some(ZIP_CENTRAL);
+    walk
+}
+
+/// Local entry at `pos` and its end. Sizes of an entry written with a data
+/// descriptor (flag bit 3) come from the first descriptor whose compressed
+/// size matches its distance from the entry data
+fn zip_local_entry(data: &[u8], pos: usize) -> Option<(ZipEntry, usize)> {
+    if data.get(pos..pos + 4)? != ZIP_LOCAL {
+        return None;
+    }
+    let mut fields: [u8; 22] = data.get(pos + 4..pos + 26)?.try_into().ok()?;
+    let flags = read_u16_le(data, pos + 6)?;
+    let name_len = read_u16_le(data, pos + 26)? as usize;
+    let extra_len = read_u16_le(data, pos + 28)? as usize;
+    let name = data.get(pos + ZIP_LOCAL_SIZE..pos + ZIP_LOCAL_SIZE + name_len)?.to_vec();
+    if name.is_empty() {
+        return None;
+    }
+    let body = pos + ZIP_LOCAL_SIZE + name_len + extra_len;
+
+    let end = if flags & 0x08 != 0 {
+        let descriptor = memmem::find_iter(data.get(body..)?, ZIP_DESCRIPTOR)
+            .map(|at| body + at)
+            .find(|&at| read_u32_le(data, at + 8) == u32::try_from(at - body).ok())?;
+        fields[10..22].copy_from_slice(data.get(descriptor + 4..descriptor + 16)?);
+        descriptor + 16
+    } else {
+        body.checked_add(read_u32_le(data, pos + 18)? as usize)?
+    };
+    (end <= data.len()).then_some((ZipEntry { offset: pos, fields, name }, end))
+}
+
+/// End of the end-of-central-directory record of the central directory
+/// starting at `cd_offset`
+pub(crate) fn zip_eocd_after(data: &[u8], cd_offset: usize) -> Option<usize> {
+    let eocd = cd_offset + memmem::find(data.get(cd_offset..)?, ZIP_EOCD)?;
+    let cd_size = read_u32_le(data, eocd + 12)? as usize;
+    let comment_len = read_u16_le(data, eocd + 20)? as usize;
+    if read_u32_le(data, eocd + 16)? as usize != cd_offset || cd_offset + cd_size != eocd {
+        return None;
+    }
+    let end = eocd + ZIP_EOCD_SIZE + comment_len;
+    (end <= data.len()).then_some(end)
+}
+
+/// Office application of an OOXML package, by its part names
+pub(crate) fn ooxml_kind(entries: &[ZipEntry]) -> Option<&'static str> {
+    if !entries.iter().any(|entry| entry.name == b"[Content_Types].xml") {
+        return None;
+    }
+    entries.iter().find_map(|entry| match entry.name.split(|&b| b == b'/').next()? {
+        b"word" => Some("docx"),
+        b"xl" => Some("xlsx"),
+        b"ppt" => Some("pptx"),
+        _ => None,
+    })
+}
+
+/// OOXML package of type `name`: the end of its central directory, or
+/// without one (cut off or overwritten) the end of its last complete entry
+fn ooxml_end(data: &[u8], name: &str) -> Option<usize> {
+    let walk = zip_local_entries(data);
+    if ooxml_kind(&walk.entries)? != name {
+        return None;
+    }
+    let central = walk.at_central.then(|| zip_eocd_after(data, walk.end)).flatten();
+    Some(central.unwrap_or(walk.end))
+}
+
+/// Header fields of an OLE2 compound file
+struct OleHeader {
+    sector_size: usize,
+    fat_sectors: usize,
+    first_dir: u32,
+    first_difat: u32,
+    difat_sectors: usize,
+}
+
+impl OleHeader {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.get(..OLE_MAGIC.len())? != OLE_MAGIC || read_u16_le(data, 0x1C)? != 0xFFFE {
+            return None;
+        }
+        let sector_size = match read_u16_le(data, 0x1E)? {
+            9 => 512,
+            12 => 4096,
+            _ => return None,
+        };
+        let header = Self {
+            sector_size,
+            fat_sectors: read_u32_le(data, 0x2C)? as usize,
+            first_dir: read_u32_le(data, 0x30)?,
+            first_difat: read_u32_le(data, 0x44)?,
+            difat_sectors: read_u32_le(data, 0x48)? as usize,
+        };
+        (header.fat_sectors > 0 && header.max_size() <= OFFICE_MAX_SIZE).then_some(header)
+    }
+
+    /// Offset of sector `id` (sector 0 follows the header)
+    fn sector_offset(&self, id: u32) -> usize {
+        (id as usize + 1) * self.sector_size
+    }
+
+    fn sector<'a>(&self, data: &'a [u8], id: u32) -> Option<&'a [u8]> {
+        let start = self.sector_offset(id);
+        data.get(start..start + self.sector_size)
+    }
+
+    /// Largest file the FAT can describe
+    fn max_size(&self) -> usize {
+        self.fat_sectors.saturating_mul(self.sector_size / 4).saturating_add(1).saturating_mul(self.sector_size)
+    }
+
+    /// FAT sector ids: 109 in the header, the rest in the DIFAT sector chain
+    fn fat_ids(&self, data: &[u8]) -> Option<Vec<u32>> {
+        let mut ids: Vec<u32> = (0..109).map(|i| read_u32_le(data, 0x4C + 4 * i)).collect::<Option<_>>()?;
+        let per_sector = self.sector_size / 4 - 1;
+        let mut next = self.first_difat;
+        for _ in 0..self.difat_sectors {
+            let sector = self.sector(data, next)?;
+            ids.extend((0..per_sector).filter_map(|i| read_u32_le(sector, 4 * i)));
+            next = read_u32_le(sector, 4 * per_sector)?;
+        }
+        ids.truncate(self.fat_sectors);
+        (ids.len() == self.fat_sectors).then_some(ids)
+    }
+}
+
+/// Office application of a compound file and the offset of its main stream
+/// (`WordDocument`, `Workbook`, `PowerPoint Document`). They are read from
+/// the first directory sector; a file cut before it is typed by the start
+/// of a Word or Excel stream at a sector boundary, else taken as a
+/// presentation whose text atoms are searched for
+fn ole_document(data: &[u8], header: &OleHeader) -> Option<(&'static str, Option<usize>)> {
+    let directory = header.sector(data, header.first_dir).filter(|dir| dir.get(0x42) == Some(&5));
+    if let Some(directory) = directory {
+        return directory.chunks_exact(128).find_map(|entry| {
+            let name_len = (read_u16_le(entry, 0x40)? as usize).min(64);
+            let name: Vec<u16> = entry[..name_len].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            let kind = match String::from_utf16_lossy(&name).trim_end_matches('\0') {
+                "WordDocument" => "doc",
+                "Workbook" | "Book" => "xls",
+                "PowerPoint Document" => "ppt",
+                _ => return None,
+            };
+            Some((kind, Some(header.sector_offset(read_u32_le(entry, 0x74)?))))
+        });
+    }
+    (OLE_HEADER_SIZE..data.len().min(header.max_size()))
+        .step_by(OLE_HEADER_SIZE)
+        .find_map(|pos| {
+            if is_word_fib(data, pos) {
+                Some(("doc", Some(pos)))
+            } else if data.get(pos..pos + BIFF8_BOF.len()) == Some(BIFF8_BOF) {
+                Some(("xls", Some(pos)))
+            } else {
+                None
+            }
+        })
+        .or(Some(("ppt", None)))
+}
+
+/// Word's file information block: `wIdent` and a Word 97 or later `nFib`
+fn is_word_fib(data: &[u8], pos: usize) -> bool {
+    read_u16_le(data, pos) == Some(0xA5EC)
+        && read_u16_le(data, pos + 2).is_some_and(|fib| (0x00C1..=0x0120).contains(&fib))
+}
+
+/// BOF record of a BIFF8 workbook globals substream
+const BIFF8_BOF: &[u8] = b"\x09\x08\x10\x00\x00\x06\x05\x00";
+
+/// A complete compound file of type `name`: it ends after the last sector
+/// its FAT marks as used
+fn ole_end(data: &[u8], name: &str) -> Option<usize> {
+    let header = OleHeader::parse(data)?;
+    if ole_document(data, &header)?.0 != name {
+        return None;
+    }
+    let per_sector = header.sector_size / 4;
+    let mut last_used = None;
+    for (index, id) in header.fat_ids(data)?.into_iter().enumerate() {
+        let fat = header.sector(data, id)?;
+        if let Some(used) = fat.chunks_exact(4).rposition(|entry| entry != OLE_FREE.to_le_bytes()) {
+            last_used = Some(index * per_sector + used);
+        }
+    }
+    let end = header.sector_offset(u32::try_from(last_used? + 1).ok()?);
+    (end <= data.len()).then_some(end)
+}
+
+/// A run of stored document text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TextPiece {
+    pub range: Range<usize>,
+    /// UTF-16LE, else one byte per character (Latin-1)
+    pub utf16: bool,
+    /// Continues the previous piece's string (an Excel string split over records)
+    pub continues: bool,
+}
+
+/// Text of an OLE2 Office document as stored, read without the FAT (streams
+/// are taken as contiguous, as Office writes them): a Word document's main
+/// text (`ccpText` characters at `fcMin`), a workbook's shared strings
+/// (the SST record and its CONTINUE records) or a presentation's text atoms
+pub(crate) fn ole_text(data: &[u8]) -> Option<(&'static str, Vec<TextPiece>)> {
+    let header = OleHeader::parse(data)?;
+    let data = &data[..data.len().min(header.max_size())];
+    let (kind, stream) = ole_document(data, &header)?;
+    let pieces = match kind {
+        "doc" => word_text(data, stream?),
+        "xls" => workbook_strings(data, stream?),
+        _ => presentation_text(data, stream.unwrap_or(OLE_HEADER_SIZE)),
+    };
+    (!pieces.is_empty()).then_some((kind, pieces))
+}
+
+/// Text of a compound file of type `name` whose end is not found, as the
+/// range from its header to the end of its last text
+fn ole_text_extent(data: &[u8], name: &str) -> Option<(usize, usize)> {
+    let (kind, pieces) = ole_text(data)?;
+    (kind == name).then(|| (0, pieces.iter().map(|piece| piece.range.end).max().unwrap_or(0)))
+}
+
+fn word_text(data: &[u8], fib: usize) -> Vec<TextPiece> {
+    let (Some(fc_min), Some(chars)) = (read_u32_le(data, fib + 0x18), read_u32_le(data, fib + 0x4C)) else {
+        return Vec::new();
+    };
+    // fcMin is what Word and LibreOffice write; 0x800 is where they put the text
+    let start = fib + if (0x20..0x10000).contains(&fc_min) { fc_min as usize } else { 0x800 };
+    let sample = data.get(start..data.len().min(start + 512)).unwrap_or_default();
+    // UTF-16 text of any alphabet has mostly small high bytes
+    let high_bytes = sample.iter().skip(1).step_by(2);
+    let utf16 = high_bytes.clone().filter(|&&b| b <= 0x05).count() * 10 >= high_bytes.count() * 7;
+    let len = chars as usize * if utf16 { 2 } else { 1 };
+    let end = data.len().min(start.saturating_add(len));
+    if !is_word_fib(data, fib) || start + 2 > end {
+        return Vec::new();
+    }
+    vec![TextPiece { range: start..end, utf16, continues: false }]
+}
+
+/// Strings of the SST record of the workbook stream at `stream`, whose
+/// records are walked up to the end of the globals substream
+fn workbook_strings(data: &[u8], stream: usize) -> Vec<TextPiece> {
+    const SST: u16 = 0x00FC;
+    const CONTINUE: u16 = 0x003C;
+    const EOF: u16 = 0x000A;
+    let mut segments: Vec<Range<usize>> = Vec::new();
+    let mut pos = stream;
+    while let (Some(record), Some(len)) = (read_u16_le(data, pos), read_u16_le(data, pos + 2)) {
+        let body = pos + 4..(pos + 4 + len as usize).min(data.len());
+        match record {
+            SST => segments = vec![body.clone()],
+            CONTINUE if !segments.is_empty() => segments.push(body.clone()),
+            _ if !segments.is_empty() => break,
+            EOF => break,
+            _ => {}
+        }
+        if len > 8224 || body.end == data.len() {
+            break;
+        }
+        pos = body.end;
+    }
+    let Some(first) = segments.first() else { return Vec::new() };
+
+    // Strings: character count, flags (UTF-16, extended data, rich text),
+    // run and extended data sizes, characters, then the runs and data. A
+    // string continued in the next record restarts with a flags byte
+    let mut pieces = Vec::new();
+    let (mut segment, mut pos) = (0, first.start + 8);
+    let advance = |segment: &mut usize, pos: &mut usize, mut skip: usize| -> bool {
+        loop {
+            let here = skip.min(segments[*segment].end.saturating_sub(*pos));
+            *pos += here;
+            skip -= here;
+            if skip == 0 {
+                return true;
+            }
+            *segment += 1;
+            let Some(next) = segments.get(*segment) else { return false };
+            *pos = next.start;
+        }
+    };
+    loop {
+        if pos >= segments[segment].end {
+            segment += 1;
+            let Some(next) = segments.get(segment) else { break };
+            pos = next.start;
+        }
+        let (Some(mut chars), Some(&flags)) = (read_u16_le(data, pos), data.get(pos + 2)) else { break };
+        if pos + 3 > segments[segment].end {
+            break;
+        }
+        pos += 3;
+        let (rich, extended) = (flags & 0x08 != 0, flags & 0x04 != 0);
+        let (Some(runs), Some(extended_len)) = (
+            if rich { read_u16_le(data, pos).map(usize::from) } else { Some(0) },
+            if extended { read_u32_le(data, pos + 2 * rich as usize).map(|len| len as usize) } else { Some(0) },
+        ) else {
+            break;
+        };
+        pos += 2 * rich as usize + 4 * extended as usize;
+        let mut utf16 = flags & 0x01 != 0;
+        let mut continues = false;
+        loop {
+            let width = if utf16 { 2 } else { 1 };
+            let take = (chars as usize).min(segments[segment].end.saturating_sub(pos) / width);
+            pieces.push(TextPiece { range: pos..pos + take * width, utf16, continues });
+            pos += take * width;
+            chars -= take as u16;
+            if chars == 0 {
+                break;
+            }
+            segment += 1;
+            let Some(next) = segments.get(segment) else { return pieces };
+            utf16 = data.get(next.start).is_some_and(|flags| flags & 0x01 != 0);
+            pos = next.start + 1;
+            continues = true;
+        }
+        if !advance(&mut segment, &mut pos, 4 * runs + extended_len) {
+            break;
+        }
+    }
+    pieces
+}
+
+/// TextCharsAtom (UTF-16) and TextBytesAtom records from `stream` on whose
+/// content is text
+fn presentation_text(data: &[u8], stream: usize) -> Vec<TextPiece> {
+    const TEXT_CHARS: &[u8] = b"\0\0\xA0\x0F";
+    const TEXT_BYTES: &[u8] = b"\0\0\xA8\x0F";
+    let Some(body) = data.get(stream..) else { return Vec::new() };
+    let mut atoms: Vec<(usize, bool)> = memmem::find_iter(body, TEXT_CHARS)
+        .map(|at| (stream + at, true))
+        .chain(memmem::find_iter(body, TEXT_BYTES).map(|at| (stream + at, false)))
+        .collect();
+    atoms.sort_unstable();
+
+    let mut pieces: Vec<TextPiece> = Vec::new();
+    for (atom, utf16) in atoms {
+        if pieces.last().is_some_and(|last| atom < last.range.end) {
+            continue;
+        }
+        let Some(len) = read_u32_le(data, atom + 4).map(|len| len as usize) else { continue };
+        let start = atom + 8;
+        let end = data.len().min(start.saturating_add(len));
+        if len == 0 || len > 1024 * 1024 || (utf16 && len % 2 != 0) || start >= end {
+            continue;
+        }
+        let text = &data[start..end];
+        let is_text = if utf16 {
+            text.chunks_exact(2)
+                .all(|c| u16::from_le_bytes([c[0], c[1]]) >= 0x20 || matches!(c, [b'\r' | b'\t' | 0x0B, 0]))
+        } else {
+            text.iter().all(|&b| b >= 0x20 || matches!(b, b'\r' | b'\t' | 0x0B))
+        };
+        if is_text {
+            pieces.push(TextPiece { range: start..end, utf16, continues: false });
+        }
+    }
+    pieces
+}
+
+/// Top-level boxes that may follow `ftyp`
+const MP4_BOXES: &[&[u8; 4]] = &[
+    b"moov", b"mdat", b"free", b"skip", b"wide", b"uuid", b"meta", b"moof", b"mfra", b"pdin", b"styp",
+    b"sidx", b"udta",
+];
+
+/// MP4 (not a QuickTime `qt  ` brand, carved as MOV)
+fn mp4_end(data: &[u8]) -> Option<usize> {
+    if data.get(8..12)? == b"qt  " {
+        return None;
+    }
+    movie_end(data)
+}
+
+/// Walk top-level boxes until the next bytes are not a known box. The file
+/// needs `moov` and `mdat`, in either order, and every track's samples (its
+/// `stco`/`co64` chunk offsets with `stsc` and `stsz`) must lie inside an
+/// `mdat`; a fragmented file (`mvex`) may instead carry them in `moof`s. An
+/// `mdat` of size 0 (the size was never written) ends after the last sample.
+fn movie_end(data: &[u8]) -> Option<usize> {
+    let mut pos = 0usize;
+    let mut moov = None;
+    let mut mdats = Vec::new();
+    let mut fragments = 0usize;
+    let mut open_mdat = false;
+
+    while pos + 8 <= data.len() {
+        let box_type = &data[pos + 4..pos + 8];
+        if pos > 0 && !MP4_BOXES.iter().any(|known| &known[..] == box_type) {
+            break;
+        }
+
+        let (header, size) = match read_u32_be(data, pos)? {
+            // Extends to the end of the file: only the sample tables can end an mdat
+            0 if box_type == b"mdat" => {
+                mdats.push((pos + 8, data.len()));
+                open_mdat = true;
+                break;
+            }
+            0 => return None,
+            1 => (16, usize::try_from(read_u64_be(data, pos + 8)?).ok()?),
+            size => (8, size as usize),
+        };
+        if size < header {
+            return None;
+        }
+        let end = pos.checked_add(size)?;
+
+        match box_type {
+            b"moov" => moov = Some(data.get(pos + header..end)?),
+            b"mdat" => mdats.push((pos + header, end)),
+            b"moof" => fragments += 1,
+            _ => {}
+        }
+        pos = end;
+    }
+    if pos > data.len() || mdats.is_empty() {
+        return None;
+    }
+
+    let moov = moov?;
+    let mut tracks = 0usize;
+    let mut samples_end = 0u64;
+    for trak in mp4_children(moov).filter(|(box_type, _)| *box_type == b"trak").map(|(_, body)| body) {
+        tracks += 1;
+        for (start, end) in track_chunks(trak)? {
+            if !mdats.iter().any(|&(mdat_start, mdat_end)| start >= mdat_start as u64 && end <= mdat_end as u64) {
+                return None;
+            }
+            samples_end = samples_end.max(end);
+        }
+    }
+    let fragmented = mp4_child(moov, b"mvex").is_some() && fragments > 0;
+    if tracks == 0 || (samples_end == 0 && !fragmented) {
+        return None;
+    }
+
+    if open_mdat {
+        return usize::try_from(samples_end).ok();
+    }
+    Some(pos)
+}
+
+/// Child boxes of a container body as (type, body), up to the first malformed one
+fn mp4_children(body: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut pos = 0usize;
+    std::iter::from_fn(move || {
+        let (header, size) = match read_u32_be(body, pos)? {
+            0 => (8, body.len() - pos),
+            1 => (16, usize::try_from(read_u64_be(body, pos + 8)?).ok()?),
+            size => (8, size as usize),
+        };
+        let end = pos.checked_add(size)?;
+        if size < header || end > body.len() {
+            return None;
+        }
+        let child = (&body[pos + 4..pos + 8], &body[pos + header..end]);
+        pos = end;
+        Some(child)
+    })
+}
+
+fn mp4_child<'a>(body: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    mp4_children(body).find(|(child_type, _)| child_type == box_type).map(|(_, body)| body)
+}
+
+/// File ranges of the chunks of a `trak`, `None` when its sample tables are
+/// missing or disagree (the chunks hold more or fewer samples than `stsz`)
+fn track_chunks(trak: &[u8]) -> Option<Vec<(u64, u64)>> {
+    let stbl = [b"mdia", b"minf", b"stbl"].into_iter().try_fold(trak, mp4_child)?;
+
+    // Full boxes: version and flags first
+    let stsz = mp4_child(stbl, b"stsz")?;
+    let uniform_size = read_u32_be(stsz, 4)?;
+    let sample_count = read_u32_be(stsz, 8)? as usize;
+
+    let offsets: Vec<u64> = match mp4_child(stbl, b"stco") {
+        Some(stco) => (0..read_u32_be(stco, 4)? as usize)
+            .map(|i| read_u32_be(stco, 8 + 4 * i).map(u64::from))
+            .collect::<Option<_>>()?,
+        None => {
+            let co64 = mp4_child(stbl, b"co64")?;
+            (0..read_u32_be(co64, 4)? as usize)
+                .map(|i| read_u64_be(co64, 8 + 8 * i))
+                .collect::<Option<_>>()?
+        }
+    };
+
+    // (first chunk, samples per chunk) runs
+    let stsc = mp4_child(stbl, b"stsc")?;
+    let runs: Vec<(usize, u64)> = (0..read_u32_be(stsc, 4)? as usize)
+        .map(|i| Some((read_u32_be(stsc, 8 + 12 * i)? as usize, u64::from(read_u32_be(stsc, 12 + 12 * i)?))))
+        .collect::<Option<_>>()?;
+
+    let mut chunks = Vec::with_capacity(offsets.len());
+    let mut sample = 0usize;
+    for (index, &offset) in offsets.iter().enumerate() {
+        let run = runs.partition_point(|&(first_chunk, _)| first_chunk <= index + 1);
+        let per_chunk = usize::try_from(runs.get(run.checked_sub(1)?)?.1).ok()?;
+        if per_chunk > sample_count - sample {
+            return None;
+        }
+
+        let len = if uniform_size != 0 {
+            u64::from(uniform_size) * per_chunk as u64
+        } else {
+            (sample..sample + per_chunk)
+                .map(|i| read_u32_be(stsz, 12 + 4 * i).map(u64::from))
+                .sum::<Option<u64>>()?
+        };
+        sample += per_chunk;
+        chunks.push((offset, offset.checked_add(len)?));
+    }
+
+    (sample == sample_count).then_some(chunks)
+}
+
+/// Frame type (SOFn marker) of a JPEG stream
+fn jpeg_frame(data: &[u8]) -> Option<u8> {
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            return Some(marker);
+        }
+        pos += 2 + read_u16_be(data, pos + 2)? as usize;
+    }
+}
+
+/// Byte order of a TIFF file
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(..4)? {
+            TIFF_LE => false,
+            TIFF_BE => true,
+            _ => return None,
+        };
+        Some(Self { data, big_endian })
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        if self.big_endian { read_u16_be(self.data, offset) } else { read_u16_le(self.data, offset) }
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        if self.big_endian { read_u32_be(self.data, offset) } else { read_u32_le(self.data, offset) }
+    }
+
+    /// SHORT, LONG or IFD values of a tag
+    fn numbers(&self, field_type: u16, offset: usize, count: usize) -> Option<Vec<u64>> {
+        (0..count)
+            .map(|i| match field_type {
+                3 => self.u16(offset + 2 * i).map(u64::from),
+                4 | 13 => self.u32(offset + 4 * i).map(u64::from),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Bytes per value of a TIFF field type
+fn tiff_type_size(field_type: u16) -> Option<usize> {
+    match field_type {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 | 13 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+/// What the IFD chains of a TIFF file reference
+#[derive(Debug, Default)]
+struct TiffLayout {
+    /// End of the last IFD, tag value, strip or tile
+    end: usize,
+    /// Every IFD and referenced range lies within the data
+    complete: bool,
+    /// Strips or tiles were found
+    has_image: bool,
+    make: Vec<u8>,
+    /// DNGVersion is set
+    dng: bool,
+    /// JPEG `(offset, length)` candidates: JPEGInterchangeFormat and
+    /// single-strip JPEG-compressed images
+    previews: Vec<(usize, usize)>,
+}
+
+impl TiffLayout {
+    fn extend(&mut self, start: u64, len: u64, data_len: usize) {
+        let end = usize::try_from(start.saturating_add(len)).unwrap_or(usize::MAX);
+        self.end = self.end.max(end);
+        self.complete &= end <= data_len;
+    }
+
+    fn raw_kind(&self, data: &[u8]) -> Option<&'static str> {
+        if data.get(8..10) == Some(b"CR") {
+            Some("cr2")
+        } else if self.dng {
+            Some("dng")
+        } else if self.make.starts_with(b"NIKON") {
+            Some("nef")
+        } else if self.make.starts_with(b"SONY") {
+            Some("arw")
+        } else {
+            None
+        }
+    }
+}
+
+/// Follow IFD0's chain, SubIFDs and the EXIF IFD, collecting the extent of
+/// every IFD, out-of-line tag value, strip and tile
+fn tiff_layout(data: &[u8]) -> Option<TiffLayout> {
+    let tiff = Tiff::new(data)?;
+    let mut layout = TiffLayout { end: 8, complete: true, ..TiffLayout::default() };
+    let mut pending = vec![tiff.u32(4)? as usize];
+    let mut visited = HashSet::new();
+
+    while let Some(ifd) = pending.pop() {
+        if ifd == 0 || !visited.insert(ifd) {
+            continue;
+        }
+        if visited.len() > TIFF_MAX_IFDS {
+            return None;
+        }
+        let Some(count) = tiff.u16(ifd) else {
+            layout.complete = false;
+            continue;
+        };
+        let entries = ifd + 2;
+        let next = entries + 12 * count as usize;
+        layout.extend(ifd as u64, 2 + 12 * count as u64 + 4, data.len());
+        let Some(next_ifd) = tiff.u32(next) else { continue };
+        pending.push(next_ifd as usize);
+
+        let (mut strips, mut strip_counts) = (Vec::new(), Vec::new());
+        let (mut tiles, mut tile_counts) = (Vec::new(), Vec::new());
+        let (mut jpeg, mut jpeg_len) = (None, None);
+        let mut compression = 1;
+        for entry in (0..count as usize).map(|i| entries + 12 * i) {
+            let tag = tiff.u16(entry)?;
+            let field_type = tiff.u16(entry + 2)?;
+            let values = tiff.u32(entry + 4)? as usize;
+            let Some(len) = tiff_type_size(field_type).and_then(|size| size.checked_mul(values)) else { continue };
+
+            let offset = if len <= 4 { entry + 8 } else { tiff.u32(entry + 8)? as usize };
+            if len > 4 {
+                layout.extend(offset as u64, len as u64, data.len());
+            }
+            let Some(value) = data.get(offset..offset.saturating_add(len)) else { continue };
+            let numbers = || tiff.numbers(field_type, offset, values).unwrap_or_default();
+
+            match tag {
+                0x0103 => compression = numbers().first().copied().unwrap_or(1),
+                0x010F => layout.make = value.to_vec(),
+                0x0111 => strips = numbers(),
+                0x0117 => strip_counts = numbers(),
+                0x0144 => tiles = numbers(),
+                0x0145 => tile_counts = numbers(),
+                0x0201 => jpeg = numbers().first().copied(),
+                0x0202 => jpeg_len = numbers().first().copied(),
+                // SubIFDs, EXIF IFD
+                0x014A | 0x8769 => pending.extend(numbers().into_iter().map(|ifd| ifd as usize)),
+                0xC612 => layout.dng = true,
+                _ => {}
+            }
+        }
+
+        for (offsets, counts) in [(&strips, &strip_counts), (&tiles, &tile_counts)] {
+            if offsets.is_empty() || offsets.len() != counts.len() {
+                continue;
+            }
+            layout.has_image = true;
+            for (&offset, &len) in offsets.iter().zip(counts.iter()) {
+                layout.extend(offset, len, data.len());
+            }
+        }
+        // Old- and new-style JPEG compression
+        if matches!(compression, 6 | 7) && strips.len() == 1 && strip_counts.len() == 1 {
+            layout.previews.push((strips[0] as usize, strip_counts[0] as usize));
+        }
+        if let (Some(offset), Some(len)) = (jpeg, jpeg_len) {
+            layout.extend(offset, len, data.len());
+            layout.previews.push((offset as usize, len as usize));
+        }
+    }
+
+    Some(layout)
+}
+
+/// A complete raw photo of type `name`: the end of its last referenced range
+fn raw_end(data: &[u8], name: &str) -> Option<usize> {
+    let layout = tiff_layout(data)?;
+    (layout.raw_kind(data) == Some(name) && layout.complete && layout.has_image).then_some(layout.end)
+}
+
+/// Largest intact embedded JPEG preview of a raw photo of type `name` (the
+/// lossless JPEG of the raw image itself is not a preview)
+fn raw_preview(data: &[u8], name: &str) -> Option<(usize, usize)> {
+    let layout = tiff_layout(data)?;
+    if layout.raw_kind(data) != Some(name) {
+        return None;
+    }
+    layout
+        .previews
+        .iter()
+        .filter_map(|&(offset, len)| {
+            let jpeg = data.get(offset..offset.checked_add(len)?)?;
+            if jpeg.get(..3)? != b"\xFF\xD8\xFF" || jpeg_frame(jpeg)? == 0xC3 {
+                return None;
+            }
+            Some((offset, jpeg_end(jpeg)?))
+        })
+        .max_by_key(|&(_, size)| size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn jpeg(payload: &[u8]) -> Vec<u8> {
+        let mut file = b"\xFF\xD8\xFF\xE0\x00\x04JF".to_vec();
+        // SOS header, entropy data with a stuffed FF 00 and a restart marker
+        file.extend_from_slice(b"\xFF\xDA\x00\x02");
+        file.extend_from_slice(payload);
+        file.extend_from_slice(b"\xFF\x00\x12\xFF\xD0\x34\xFF\xD9");
+        file
+    }
+
+    fn png() -> Vec<u8> {
+        let mut file = b"\x89PNG\r\n\x1A\n".to_vec();
+        file.extend_from_slice(&13u32.to_be_bytes());
+        file.extend_from_slice(b"IHDR");
+        file.extend_from_slice(&[0u8; 13 + 4]);
+        file.extend_from_slice(&0u32.to_be_bytes());
+        file.extend_from_slice(PNG_IEND);
+        file
+    }
+
+    fn zip() -> Vec<u8> {
+        let mut file = b"PK\x03\x04".to_vec();
+        file.extend_from_slice(&[0u8; 26]);
+        let cd_offset = file.len() as u32;
+        file.extend_from_slice(b"PK\x01\x02");
+        file.extend_from_slice(&[0u8; 42]);
+        let cd_size = file.len() as u32 - cd_offset;
+        file.extend_from_slice(ZIP_EOCD);
+        file.extend_from_slice(&[0u8; 8]);
+        file.extend_from_slice(&cd_size.to_le_bytes());
+        file.extend_from_slice(&cd_offset.to_le_bytes());
+        file.extend_from_slice(&2u16.to_le_bytes());
+        file.extend_from_slice(b"ok");
+        file
+    }
+
+    fn mp4_box(box_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut mp4_box = (body.len() as u32 + 8).to_be_bytes().to_vec();
+        mp4_box.extend_from_slice(box_type);
+        mp4_box.extend_from_slice(body);
+        mp4_box
+    }
+
+    /// Full box body: version and flags, then `fields`
+    fn full_box(fields: &[u32]) -> Vec<u8> {
+        std::iter::once(0u32).chain(fields.iter().copied()).flat_map(u32::to_be_bytes).collect()
+    }
+
+    /// One track of two samples (20 and 12 bytes) in one chunk at `chunk_offset`
+    fn moov(chunk_offset: u32, extra: &[u8]) -> Vec<u8> {
+        let stbl = [
+            mp4_box(b"stsz", &full_box(&[0, 2, 20, 12])),
+            mp4_box(b"stsc", &full_box(&[1, 1, 2, 1])),
+            mp4_box(b"stco", &full_box(&[1, chunk_offset])),
+        ]
+        .concat();
+        let minf = mp4_box(b"minf", &mp4_box(b"stbl", &stbl));
+        let trak = mp4_box(b"trak", &mp4_box(b"mdia", &minf));
+        mp4_box(b"moov", &[trak, extra.to_vec()].concat())
+    }
+
+    /// ftyp of `brand`, mdat, then moov as cameras write it
+    fn mp4_with(brand: &[u8; 4], chunk_offset: u32) -> Vec<u8> {
+        let ftyp = mp4_box(b"ftyp", &[&brand[..], b"\0\0\0\0isom"].concat());
+        let mdat = mp4_box(b"mdat", &[0x11; 32]);
+        [ftyp, mdat, moov(chunk_offset, &[])].concat()
+    }
+
+    fn mp4() -> Vec<u8> {
+        // Samples right after the 20-byte ftyp and the mdat header
+        mp4_with(b"isom", 28)
+    }
+
+    /// Raw photo: IFD0 with the make, `extra` tags and a JPEG preview, then a
+    /// SubIFD with one raw strip of `raw_len` bytes
+    fn raw_photo(big_endian: bool, make: &[u8], extra: &[(u16, u16, u32, u32)], raw_len: usize) -> Vec<u8> {
+        let u16_bytes = |value: u16| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        let u32_bytes = |value: u32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        let preview = [&b"\xFF\xD8\xFF\xC0\x00\x02"[..], &jpeg(b"\x01")[2..]].concat();
+
+        let sub_ifd = 8 + 2 + 12 * (4 + extra.len()) + 4;
+        let make_at = sub_ifd + 2 + 12 * 2 + 4;
+        let preview_at = make_at + make.len();
+        let raw_at = preview_at + preview.len();
+        let mut ifd0 = vec![
+            (0x010F, 2, make.len() as u32, make_at as u32),
+            (0x0201, 4, 1, preview_at as u32),
+            (0x0202, 4, 1, preview.len() as u32),
+            (0x014A, 4, 1, sub_ifd as u32),
+        ];
+        ifd0.extend_from_slice(extra);
+
+        let mut file = if big_endian { TIFF_BE.to_vec() } else { TIFF_LE.to_vec() };
+        file.extend_from_slice(&u32_bytes(8));
+        for entries in [ifd0, vec![(0x0111, 4, 1, raw_at as u32), (0x0117, 4, 1, raw_len as u32)]] {
+            file.extend_from_slice(&u16_bytes(entries.len() as u16));
+            for (tag, field_type, count, value) in entries {
+                file.extend_from_slice(&u16_bytes(tag));
+                file.extend_from_slice(&u16_bytes(field_type));
+                file.extend_from_slice(&u32_bytes(count));
+                file.extend_from_slice(&u32_bytes(value));
+            }
+            file.extend_from_slice(&u32_bytes(0));
+        }
+        file.extend_from_slice(make);
+        file.extend_from_slice(&preview);
+        file.extend_from_slice(&vec![0x5A; raw_len]);
+        file
+    }
+
+    #[test]
+    fn test_carve_formats() {
+        let files = [
+            ("jpg", jpeg(b"\x01\x02")),
+            ("png", png()),
+            ("pdf", b"%PDF-1.4\nbody\n%%EOF\nupdate\n%%EOF\r\n".to_vec()),
+            ("zip", zip()),
+            ("mp4", mp4()),
+            ("mov", mp4_with(b"qt  ", 28)),
+        ];
+
+        let mut image = vec![0x20u8; 100];
+        let mut expected = Vec::new();
+        for (name, file) in &files {
+            expected.push(CarvedFile {
+                offset: image.len(),
+                size: file.len(),
+                file_type: signature(name).unwrap().name,
+                preview_of: None,
+            });
+            image.extend_from_slice(file);
+            image.extend_from_slice(&[0x20u8; 37]);
+        }
+
+        let all: Vec<&str> = SIGNATURES.iter().map(|sig| sig.name).collect();
+        assert_eq!(carve(&image, image.len(), &types(&all)), expected);
+        // Only the requested types
+        assert_eq!(carve(&image, image.len(), &types(&["png"])), vec![expected[1].clone()]);
+    }
+
+    #[test]
+    fn test_carve_limits() {
+        // Header past the limit is left to the next chunk; the end may lie past it
+        let mut image = vec![0u8; 10];
+        image.extend_from_slice(&png());
+        assert!(carve(&image, 10, &types(&["png"])).is_empty());
+        assert_eq!(carve(&image, 11, &types(&["png"]))[0].size, png().len());
+
+        // Truncated files and thumbnails inside a carved JPEG are not carved
+        let truncated = png();
+        assert!(carve(&truncated[..truncated.len() - 1], 1, &types(&["png"])).is_empty());
+        let outer = jpeg(&jpeg(b"\x05"));
+        let carved = carve(&outer, outer.len(), &types(&["jpg"]));
+        assert_eq!(carved.len(), 1);
+    }
+
+    #[test]
+    fn test_mp4_sample_tables() {
+        let carved = |file: &[u8]| carve(file, 1, &types(&["mp4", "mov"]));
+        assert_eq!(carved(&mp4())[0].file_type, "mp4");
+        assert_eq!(carved(&mp4_with(b"qt  ", 28))[0].file_type, "mov");
+
+        // Chunk past the mdat: the moov belongs to another file
+        assert!(carved(&mp4_with(b"isom", 40)).is_empty());
+
+        // moov first, then an mdat whose size was never written: ends after the samples
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\0\0isom");
+        let moov_len = moov(0, &[]).len() as u32;
+        let mut file = [ftyp.clone(), moov(20 + moov_len + 8, &[])].concat();
+        file.extend_from_slice(&0u32.to_be_bytes());
+        file.extend_from_slice(b"mdat");
+        file.extend_from_slice(&[0x11; 32]);
+        let size = file.len();
+        file.extend_from_slice(&[0x22; 50]);
+        assert_eq!(carved(&file)[0].size, size);
+
+        // Fragmented: samples in moof/mdat pairs, the track tables empty
+        let mut empty_tables = moov(0, &mp4_box(b"mvex", &[]));
+        let stsz = memmem::find(&empty_tables, b"stsz").unwrap();
+        empty_tables[stsz + 12..stsz + 16].copy_from_slice(&[0; 4]);
+        let stco = memmem::find(&empty_tables, b"stco").unwrap();
+        empty_tables[stco + 8..stco + 12].copy_from_slice(&[0; 4]);
+        let fragmented = [ftyp.clone(), empty_tables, mp4_box(b"moof", &[0; 8]), mp4_box(b"mdat", &[0x11; 32])].concat();
+        assert_eq!(carved(&fragmented)[0].size, fragmented.len());
+        assert!(carved(&fragmented[..fragmented.len() - 40]).is_empty());
+    }
+
+    #[test]
+    fn test_raw_ifd_chains() {
+        let raw_types = types(&["cr2", "nef", "arw", "dng"]);
+        let nef = raw_photo(true, b"NIKON CORPORATION\0", &[], 300);
+        let carved = carve(&nef, 1, &raw_types);
+        assert_eq!(carved, vec![CarvedFile { offset: 0, size: nef.len(), file_type: "nef", preview_of: None }]);
+
+        let mut image = raw_photo(false, b"SONY\0\0\0\0", &[], 100);
+        let arw_len = image.len();
+        // DNGVersion 1.4 decides over the make; a plain TIFF is not carved
+        image.extend_from_slice(&raw_photo(false, b"Canon\0\0\0", &[(0xC612, 1, 4, 0x0401)], 100));
+        image.extend_from_slice(&raw_photo(false, b"Kodak\0\0\0", &[], 100));
+        let carved: Vec<_> = carve(&image, image.len(), &raw_types).iter().map(|f| (f.offset, f.file_type)).collect();
+        assert_eq!(carved, [(0, "arw"), (arw_len, "dng")]);
+
+        // Raw strip cut off: the embedded preview is carved instead, once with jpg carving
+        let cut = &nef[..nef.len() - 10];
+        let preview = carve(cut, 1, &raw_types);
+        assert_eq!(preview.len(), 1);
+        assert_eq!((preview[0].file_type, preview[0].preview_of), ("jpg", Some("nef")));
+        assert_eq!(&cut[preview[0].offset..preview[0].offset + 3], b"\xFF\xD8\xFF");
+        assert_eq!(carve(cut, cut.len(), &types(&["nef", "jpg"])).len(), 1);
+    }
+}
@@ -7,7 +7,11 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::{mpsc, oneshot};
 use tokio::task;
 
+use crate::disk::{image_len, DiskImage};
 use crate::error::{RecoveryError, Result};
+use crate::matcher::ScriptDensity;
+use crate::pipe::PipeImage;
+use crate::types::{HotFragment, Offset, Size};
 
 const CHECKPOINT_VERSION: u32 = 1;
 const HASH_READ_LIMIT: usize = 1_048_576;
@@ -18,7 +22,7 @@ pub struct Checkpoint {
     pub timestamp: u64,
     pub image_path: String,
     pub image_hash: String,
-    pub position: u64,
+    pub position: Offset,
     pub state: serde_json::Value,
 }
 
@@ -26,7 +30,7 @@ impl Checkpoint {
     pub fn new(
         image_path: impl Into<String>,
         image_hash: String,
-        position: u64,
+        position: impl Into<Offset>,
         state: serde_json::Value,
     ) -> Self {
         let timestamp = SystemTime::now()
@@ -38,12 +42,91 @@ impl Checkpoint {
             timestamp,
             image_path: image_path.into(),
             image_hash,
-            position,
+            position: position.into(),
             state,
         }
     }
 }
 
+/// Checkpoint `state` of the main scan: what is needed to finish it later
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanState {
+    /// Direction of the checkpointed scan; `position` is the low edge of the
+    /// scanned range when reverse, the high edge otherwise
+    pub reverse: bool,
+    /// Hot fragments found before the checkpoint, replayed on resume
+    pub fragments: Vec<SavedFragment>,
+}
+
+impl ScanState {
+    /// Fragments of the range scanned up to `position`. Chunks past it that
+    /// finished out of order (`--priority`) are scanned again on resume
+    pub fn fragments_before(&self, position: Offset) -> Vec<SavedFragment> {
+        self.fragments
+            .iter()
+            .filter(|f| if self.reverse { f.offset >= position } else { f.offset < position })
+            .cloned()
+            .collect()
+    }
+
+    pub fn from_checkpoint(checkpoint: &Checkpoint) -> Result<Self> {
+        serde_json::from_value(checkpoint.state.clone()).map_err(|err| RecoveryError::Parse(err.to_string()))
+    }
+}
+
+/// Hot fragment fields used after the scan (clusters, stream assembly)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedFragment {
+    pub offset: Offset,
+    pub size: Size,
+    pub youtube_count: usize,
+    pub cyrillic_density: f32,
+    #[serde(default)]
+    pub scripts: ScriptDensity,
+    pub target_score: f32,
+    pub file_type: String,
+    pub entropy: f32,
+    #[serde(default)]
+    pub carved: bool,
+    #[serde(default)]
+    pub reasons: Vec<String>,
+}
+
+impl From<&HotFragment> for SavedFragment {
+    fn from(fragment: &HotFragment) -> Self {
+        Self {
+            offset: fragment.offset,
+            size: fragment.size,
+            youtube_count: fragment.youtube_count,
+            cyrillic_density: fragment.cyrillic_density,
+            scripts: fragment.scripts,
+            target_score: fragment.target_score,
+            file_type: fragment.file_type_guess.clone(),
+            entropy: fragment.entropy,
+            carved: fragment.carved,
+            reasons: fragment.fragment_score.reasons.clone(),
+        }
+    }
+}
+
+impl SavedFragment {
+    pub fn to_hot_fragment(&self) -> HotFragment {
+        let mut fragment = HotFragment::new(self.offset, self.size);
+        fragment.youtube_count = self.youtube_count;
+        fragment.cyrillic_density = self.cyrillic_density;
+        // Scan databases keep only the Cyrillic density
+        fragment.scripts = ScriptDensity { cyrillic: self.cyrillic_density, ..self.scripts };
+        fragment.fragment_score.scripts = fragment.scripts;
+        fragment.target_score = self.target_score;
+        fragment.file_type_guess = self.file_type.clone();
+        fragment.entropy = self.entropy;
+        fragment.carved = self.carved;
+        fragment.fragment_score.overall_score = self.target_score;
+        fragment.fragment_score.reasons = self.reasons.clone();
+        fragment
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ResumeValidation {
     pub is_valid: bool,
@@ -68,19 +151,27 @@ impl ResumeValidation {
 
 pub fn compute_image_hash(path: &Path) -> Result<String> {
     let mut file = File::open(path)?;
-    let metadata = file.metadata()?;
+    let image_len = image_len(&file)?;
     let mut buffer = vec![0u8; HASH_READ_LIMIT];
     let read = file.read(&mut buffer)?;
 
     let mut hasher = Sha256::new();
     hasher.update(&buffer[..read]);
-    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(image_len.to_le_bytes());
     Ok(format!("{:x}", hasher.finalize()))
 }
 
+/// `compute_image_hash` of a piped image: its length is unknown when the
+/// scan starts, so only the first MiB is hashed
+pub fn compute_pipe_hash(pipe: &PipeImage) -> Result<String> {
+    let mut buffer = vec![0u8; (HASH_READ_LIMIT as u64).min(pipe.bytes_read()) as usize];
+    pipe.read_at(0, &mut buffer)?;
+    Ok(format!("{:x}", Sha256::digest(&buffer)))
+}
+
 pub fn create_checkpoint(
     image_path: &Path,
-    position: u64,
+    position: impl Into<Offset>,
     state: serde_json::Value,
 ) -> Result<Checkpoint> {
     let image_hash = compute_image_hash(image_path)?;
@@ -103,8 +194,9 @@ pub fn validate_resume(image_path: &Path, checkpoint: &Checkpoint) -> Result<Res
         return Ok(ResumeValidation::invalid("image hash mismatch"));
     }
 
-    let size = fs::metadata(image_path)?.len();
-    if checkpoint.position > size {
+    // Media size, not file size: E01 positions are in decompressed bytes
+    let size = DiskImage::open(image_path)?.size().as_u64();
+    if checkpoint.position.as_u64() > size {
         return Ok(ResumeValidation::invalid("checkpoint position exceeds image size"));
     }
 
@@ -225,6 +317,7 @@ enum CheckpointRequest {
 mod tests {
     use super::*;
     use std::fs;
+    use std::path::PathBuf;
     use std::time::{SystemTime, UNIX_EPOCH};
 
     fn temp_dir() -> PathBuf {
@@ -256,7 +349,7 @@ mod tests {
 
         let loaded = load_checkpoint(&checkpoint_path).unwrap();
         assert_eq!(loaded.image_hash, checkpoint.image_hash);
-        assert_eq!(loaded.position, 128);
+        assert_eq!(loaded.position, Offset::new(128));
 
         let checkpoint2 = Checkpoint::new(
             checkpoint.image_path.clone(),
@@ -296,6 +389,26 @@ mod tests {
         manager.shutdown().await.unwrap();
 
         let loaded = load_checkpoint(&checkpoint_path).unwrap();
-        assert_eq!(loaded.position, 512);
+        assert_eq!(loaded.position, Offset::new(512));
+    }
+
+    #[test]
+    fn test_scan_state_round_trip() {
+        let mut fragment = HotFragment::new(Offset::new(4096), Size::new(2048));
+        fragment.target_score = 42.0;
+        fragment.file_type_guess = "json".to_string();
+        let state = ScanState { reverse: true, fragments: vec![SavedFragment::from(&fragment)] };
+
+        let checkpoint = Checkpoint::new("image.bin", "hash".to_string(), 8192, serde_json::to_value(&state).unwrap());
+        let restored = ScanState::from_checkpoint(&checkpoint).unwrap();
+        assert!(restored.reverse);
+        let replayed = restored.fragments[0].to_hot_fragment();
+        assert_eq!(replayed.offset, fragment.offset);
+        assert_eq!(replayed.fragment_score.overall_score, 42.0);
+        assert_eq!(replayed.file_type_guess, "json");
+
+        // Reverse scan: only fragments at or above the position were scanned
+        assert_eq!(restored.fragments_before(Offset::new(4096)).len(), 1);
+        assert!(restored.fragments_before(Offset::new(8192)).is_empty());
     }
 }
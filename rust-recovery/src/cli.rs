@@ -1,15 +1,34 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 /// Ultimate File Recovery - Rust Implementation
 /// Professional data recovery system for disk images
-#[derive(Parser, Debug, Clone)]
+#[derive(Parser, Debug, Clone, serde::Serialize)]
 #[command(name = "rust-recovery")]
 #[command(version = "0.1.0")]
 #[command(about = "Ultimate File Recovery - Rust Implementation", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
-    /// Disk image file to scan
-    #[arg(value_name = "IMAGE")]
+    /// Run a subcommand instead of scanning
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
+
+    /// Disk image file, E01 (first segment), QCOW2/VMDK virtual disk or block device (e.g. /dev/sdb) to scan,
+    /// or - to stream a raw image from stdin (gzip/zstd/xz files are decompressed on the fly); several images are scanned in parallel, each into OUTPUT/<image name>
+    #[arg(value_name = "IMAGE", required_unless_present = "image_list")]
+    pub images: Vec<PathBuf>,
+
+    /// Also scan the images listed in FILE (one path per line, # comments)
+    #[arg(long = "image-list", value_name = "FILE")]
+    pub image_list: Option<PathBuf>,
+
+    /// Images scanned at the same time; their chunks share the worker threads
+    #[arg(long = "parallel-images", value_name = "N", default_value = "2")]
+    pub parallel_images: usize,
+
+    /// Image of this scan (one of IMAGE)
+    #[arg(skip)]
     pub image: PathBuf,
 
     /// Minimum file size in KB
@@ -28,14 +47,89 @@ pub struct Args {
     #[arg(long = "nvme")]
     pub nvme: bool,
 
+    /// Search pattern needles on an OpenCL GPU (build with --features gpu)
+    #[arg(long = "gpu")]
+    pub gpu: bool,
+
+    /// Read the image with pread one window of chunks at a time instead of mapping it (images larger than RAM)
+    #[arg(long = "stream")]
+    pub stream: bool,
+
     /// Stop after N files recovered (0 = no limit)
     #[arg(long = "early-exit", default_value = "0")]
     pub early_exit: usize,
 
-    /// Output directory for recovered files
+    /// Scan the chunks whose first sectors score highest (needle hits, non-zero data) first
+    #[arg(long = "priority")]
+    pub priority: bool,
+
+    /// Stop dispatching chunks after this long (seconds, or 90m, 2h, 1h30m): chunks in flight
+    /// finish, found files are assembled and the report is partial; --resume continues (0 = no limit)
+    #[arg(long = "max-duration", visible_alias = "time-budget", default_value = "0", value_parser = parse_duration_secs)]
+    pub time_budget_secs: u64,
+
+    /// Compute a SHA-256 of the whole image from the scan's reads and put it in the report (chain of custody)
+    #[arg(long = "verify-hash")]
+    pub verify_hash: bool,
+
+    /// With --verify-hash, compute an MD5 of the image as well (implies --verify-hash)
+    #[arg(long = "verify-hash-md5")]
+    pub verify_hash_md5: bool,
+
+    /// Hold chunk dispatch to an average of N MB/s read from the image (0 = no limit)
+    #[arg(long = "max-read-mbps", default_value = "0")]
+    pub max_read_mbps: f64,
+
+    /// I/O scheduling class of the worker threads (Linux): idle, best-effort or best-effort:0-7
+    #[arg(long = "io-nice")]
+    pub io_nice: Option<String>,
+
+    /// Read ahead N MB of chunks on reader threads (default 256 on NBD/iSCSI devices, else off; 0 = off)
+    #[arg(long = "prefetch", value_name = "MB")]
+    pub prefetch_mb: Option<u64>,
+
+    /// Reads in flight with --prefetch
+    #[arg(long = "prefetch-depth", default_value = "8")]
+    pub prefetch_depth: usize,
+
+    /// With --prefetch, issue a read again when it gets no reply in N seconds
+    #[arg(long = "read-timeout", value_name = "SECS", default_value = "30")]
+    pub read_timeout_secs: u64,
+
+    /// With --prefetch, attempts after a failed or timed-out read before the scanner reads the chunk itself
+    #[arg(long = "read-retries", default_value = "3")]
+    pub read_retries: u32,
+
+    /// Keep the bytes of found fragments from the scan, up to N MB in memory, so assembly does not
+    /// read the image again (0 = off)
+    #[arg(long = "fragment-cache", value_name = "MB")]
+    pub fragment_cache_mb: Option<u64>,
+
+    /// With --fragment-cache, move least recently used fragments to OUTPUT/fragment_cache up to N MB
+    /// (0 = drop them); the directory is removed after the files are written
+    #[arg(long = "fragment-cache-spill", value_name = "MB", default_value = "4096")]
+    pub fragment_cache_spill_mb: u64,
+
+    /// Output directory for recovered files, or s3://bucket/prefix to upload the run to object storage (build with --features s3)
     #[arg(short = 'o', long = "output", default_value = "recovery_output")]
     pub output: PathBuf,
 
+    /// Local directory a run into --output s3://... is written to before it is uploaded
+    #[arg(long = "s3-staging", value_name = "DIR", default_value = "recovery_staging")]
+    pub s3_staging: PathBuf,
+
+    /// S3-compatible endpoint such as http://minio.lab:9000 (default AWS_ENDPOINT_URL, else AWS)
+    #[arg(long = "s3-endpoint", value_name = "URL")]
+    pub s3_endpoint: Option<String>,
+
+    /// Region S3 requests are signed for (default AWS_REGION, AWS_DEFAULT_REGION, else us-east-1)
+    #[arg(long = "s3-region", value_name = "REGION")]
+    pub s3_region: Option<String>,
+
+    /// Keep the recovered files in --s3-staging once they are uploaded
+    #[arg(long = "s3-keep-local")]
+    pub s3_keep_local: bool,
+
     /// Enable exFAT metadata scanning (Opt-in)
     #[arg(long = "enable-exfat")]
     pub enable_exfat: bool,
@@ -44,25 +138,227 @@ pub struct Args {
     #[arg(long = "no-live")]
     pub no_live: bool,
 
-    /// Mode: extract only links, don't save binary chunks
+    /// Triage: scan for links and export them (each URL once) with the report; no files are assembled or saved
     #[arg(long = "links-only")]
     pub links_only: bool,
 
-    /// Minimum dynamic chunk size in KB
+    /// Smallest chunk in KB, used in text regions with --adaptive-chunks
     #[arg(long = "chunk-min", default_value = "32")]
     pub chunk_min: u64,
 
-    /// Maximum dynamic chunk size in KB
+    /// Chunk size in KB (the largest with --adaptive-chunks)
     #[arg(long = "chunk-max", default_value = "2048")]
     pub chunk_max: u64,
 
+    /// Chunk overlap in KB (default: sized from the longest artifact the matcher reads)
+    #[arg(long = "overlap")]
+    pub overlap: Option<u64>,
+
     /// Enable FAT chain following for full file recovery (default: true)
     #[arg(long = "full-exfat-recovery", default_value = "true")]
     pub full_exfat_recovery: bool,
 
-    /// Analyze candidates and group by semantic category
+    /// Group fragments by content before assembly; streams never mix groups
     #[arg(long = "semantic-scan")]
     pub semantic_scan: bool,
+
+    /// Requeue a chunk whose worker makes no progress for N minutes (0 = no watchdog)
+    #[arg(long = "stall-timeout", default_value = "5")]
+    pub stall_timeout_min: u64,
+
+    /// Maximum number of streams assembled from hot fragments
+    #[arg(long = "max-streams", default_value = "3")]
+    pub max_streams: usize,
+
+    /// Stream solver weights (TOML: max_gap, penalties, cosine/jaccard weights...); keys left out keep their default
+    #[arg(long = "solver-config", value_name = "FILE")]
+    pub solver_config: Option<PathBuf>,
+
+    /// Largest gap in KB between two fragments the solver chains (overrides --solver-config)
+    #[arg(long = "max-gap", value_name = "KB")]
+    pub max_gap: Option<u64>,
+
+    /// Fragments before each one tried as its predecessor (overrides --solver-config)
+    #[arg(long = "max-lookback", value_name = "N")]
+    pub max_lookback: Option<usize>,
+
+    /// Lowest score of a link between two fragments (overrides --solver-config)
+    #[arg(long = "min-edge-score", value_name = "SCORE")]
+    pub min_edge_score: Option<f32>,
+
+    /// Pattern profile (TOML or JSON) replacing the built-in YouTube patterns
+    #[arg(long = "patterns")]
+    pub patterns: Option<PathBuf>,
+
+    /// Read exFAT/NTFS/FAT32 metadata (directory tree, MFT) before the full-surface carve
+    #[arg(long = "metadata-first")]
+    pub metadata_first: bool,
+
+    /// With --metadata-first, rebuild deleted fragmented exFAT files from consecutive similar
+    /// clusters and save them to reconstructed/ with a confidence score
+    #[arg(long = "reconstruct-deleted")]
+    pub reconstruct_deleted: bool,
+
+    /// Scan only the clusters the exFAT Allocation Bitmap marks free (deleted data)
+    #[arg(long = "unallocated-only")]
+    pub unallocated_only: bool,
+
+    /// Scan only partition N of the MBR/GPT table (as listed at startup; MBR logical partitions from 5)
+    #[arg(long = "partition")]
+    pub partition: Option<u32>,
+
+    /// Size chunks by entropy: --chunk-min pieces in text, --chunk-max in empty or compressed data
+    #[arg(long = "adaptive-chunks")]
+    pub adaptive_chunks: bool,
+
+    /// Carve complete files by signature: comma-separated jpg,png,pdf,docx,xlsx,pptx,zip,doc,xls,ppt,mp4,mov,cr2,nef,arw,dng or "all"
+    #[arg(long = "carve", value_delimiter = ',')]
+    pub carve: Vec<String>,
+
+    /// Find on-disk structures in the block pass: comma-separated ntfs_file,sqlite,exif or "all"
+    #[arg(long = "detect", value_delimiter = ',')]
+    pub detect: Vec<String>,
+
+    /// Carve artifacts into the extracted links: comma-separated browser (Chrome/Firefox history rows) or "all"
+    #[arg(long = "artifacts", value_delimiter = ',')]
+    pub artifacts: Vec<String>,
+
+    /// Export extracted links to the output directory: comma-separated csv,jsonl,html (bookmarks) or "all"
+    #[arg(long = "export-links", value_delimiter = ',')]
+    pub export_links: Vec<String>,
+
+    /// Capture up to BYTES of text before and after each link (context_before/context_after in the link exports and JSON report; 0 = off)
+    #[arg(long = "link-context", value_name = "BYTES", default_value_t = 0)]
+    pub link_context: usize,
+
+    /// Boost links and fragments whose title or surrounding text holds this keyword (repeatable, case-insensitive)
+    #[arg(long = "keyword")]
+    pub keyword: Vec<String>,
+
+    /// File with one keyword per line (blank lines and # comments skipped)
+    #[arg(long = "keyword-file")]
+    pub keyword_file: Option<PathBuf>,
+
+    /// Keep only links and fragments matching --keyword/--keyword-file instead of boosting them
+    #[arg(long = "keyword-only")]
+    pub keyword_only: bool,
+
+    /// TOML list of case terms with weights; each whole-word hit in a chunk adds its weight to the fragment score
+    #[arg(long = "keyword-profile", value_name = "FILE")]
+    pub keyword_profile: Option<PathBuf>,
+
+    /// Script whose text density counts towards the fragment score: latin, cyrillic, arabic, hebrew or cjk
+    #[arg(long = "script", default_value = "cyrillic")]
+    pub script: String,
+
+    /// Append every hot fragment to fragments.jsonl in the output directory as the scan finds it
+    #[arg(long = "fragments-jsonl")]
+    pub fragments_jsonl: bool,
+
+    /// Write links and hot fragments into scan.db (SQLite) in the output directory as they are found instead of keeping them in memory
+    #[arg(long = "scan-db")]
+    pub scan_db: bool,
+
+    /// After the run, serve the HTML report and recovered files over HTTP until Ctrl-C (build with --features serve)
+    #[arg(long = "serve")]
+    pub serve: bool,
+
+    /// Address --serve listens on; 0.0.0.0:8080 lets other machines on the network browse the results
+    #[arg(long = "serve-addr", value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub serve_addr: String,
+
+    /// Save recovered files every SECS seconds during the scan instead of only at the end (0 = at the end)
+    #[arg(long = "flush-interval", default_value = "0")]
+    pub flush_interval_secs: u64,
+
+    /// Run CMD on each recovered file as soon as it is written ({} = file path, e.g. 'clamscan {}');
+    /// exit codes are recorded in the report, output goes to post_process.log
+    #[arg(long = "post-process-cmd", value_name = "CMD")]
+    pub post_process_cmd: Option<String>,
+
+    /// Also write each recovered file to the stdin of --post-process-cmd
+    #[arg(long = "pipe-output")]
+    pub pipe_output: bool,
+
+    /// Recovered files per directory; later files go to subdirectories 0002, 0003... (0 = no limit)
+    #[arg(long = "max-files-per-dir", value_name = "N", default_value = "10000")]
+    pub max_files_per_dir: usize,
+
+    /// Only re-validate the saved files after the scan; do not repair cut JSON, HTML or CSV
+    #[arg(long = "no-repair")]
+    pub no_repair: bool,
+
+    /// Keep duplicate recovered files instead of collapsing them after the scan
+    #[arg(long = "no-dedup")]
+    pub no_dedup: bool,
+
+    /// Fuzzy hash similarity (0-100) from which files of the same type are near-duplicates (100 = exact only)
+    #[arg(long = "dedup-similarity", value_name = "PCT", default_value = "90")]
+    pub dedup_similarity: u8,
+
+    /// Rotate fragments.jsonl, recovered_files.jsonl, post_process.log and the --log-file at
+    /// this size in MB, keeping 3 older files (0 = never)
+    #[arg(long = "log-max-mb", default_value = "256")]
+    pub log_max_mb: u64,
+
+    /// Also write log events (with chunk offsets, timestamps) to PATH
+    #[arg(long = "log-file", value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Least severe log events shown and written: error, warn, info, debug or trace
+    #[arg(long = "log-level", value_name = "LEVEL", default_value = "info")]
+    pub log_level: String,
+
+    /// Seconds between updates of progress.json
+    #[arg(long = "progress-interval", value_name = "SECS", default_value = "2")]
+    pub progress_interval: u64,
+
+    /// Also send each progress update as an NDJSON line to PATH (FIFO, Unix socket or file)
+    #[arg(long = "progress-stream", value_name = "PATH")]
+    pub progress_stream: Option<PathBuf>,
+
+    /// Continue an interrupted scan from the checkpoint in the output directory
+    #[arg(long = "resume")]
+    pub resume: bool,
+
+    /// Log every matcher decision (needle hits, windows, rejections, scores) in the image range
+    /// START-END (hex or decimal, e.g. 0x1000000-0x1100000) to matcher_trace.log in the output directory
+    #[arg(long = "trace-matcher", value_name = "START-END")]
+    pub trace_matcher: Option<String>,
+
+    /// Write per-chunk statistics to chunk_stats.csv in the output directory
+    #[arg(long = "chunk-stats")]
+    pub chunk_stats: bool,
+
+    /// Skip checking the MD5/SHA1 stored in E01 images before the scan
+    #[arg(long = "no-verify")]
+    pub no_verify: bool,
+
+    /// Transliterate Cyrillic titles to ASCII in recovered filenames (the report keeps the original)
+    #[arg(long = "filename-translit")]
+    pub filename_translit: bool,
+
+    /// Longest recovered filename in bytes of UTF-8; longer titles are cut
+    #[arg(long = "max-filename-bytes", default_value_t = crate::recovery::DEFAULT_MAX_FILENAME_BYTES)]
+    pub max_filename_bytes: usize,
+
+    /// Also keep each title with its file: none, xattr (user.dublincore.title) or sidecar (<name>.title.json)
+    #[arg(long = "keep-title", default_value = "none")]
+    pub keep_title: String,
+
+    /// Invalid UTF-8 in recovered text files: keep, replace (with U+FFFD) or strict (drop)
+    #[arg(long = "utf8", default_value = "keep")]
+    pub utf8: String,
+
+    /// Missing bytes inside an assembled file (image bytes the solver skipped between two fragments,
+    /// unreadable extents): none (concatenate), zero or marker (repeated <GAP>) holes of their size,
+    /// listed per file in the report
+    #[arg(long = "gap-fill", value_name = "MODE", default_value = "none")]
+    pub gap_fill: String,
+
+    /// Write output even if the output directory is on the scanned disk
+    #[arg(long = "force")]
+    pub force: bool,
 }
 
 impl Args {
@@ -97,6 +393,175 @@ impl Args {
             return Err("chunk-min must be greater than 0".to_string());
         }
 
+        if self.max_streams == 0 {
+            return Err("max-streams must be greater than 0".to_string());
+        }
+
+        if let Some(url) = self.s3_output_url() {
+            if !cfg!(feature = "s3") {
+                return Err("--output s3://: S3 output not built (cargo build --features s3)".to_string());
+            }
+            crate::s3::S3Location::parse(&url)?;
+        }
+
+        if self.serve && !cfg!(feature = "serve") {
+            return Err("--serve: preview server not built (cargo build --features serve)".to_string());
+        }
+
+        if self.parallel_images == 0 {
+            return Err("parallel-images must be greater than 0".to_string());
+        }
+
+        if self.progress_interval == 0 {
+            return Err("progress-interval must be greater than 0".to_string());
+        }
+
+        if self.dedup_similarity > 100 {
+            return Err(format!("dedup-similarity must be at most 100 (got {})", self.dedup_similarity));
+        }
+
+        if self.partition == Some(0) {
+            return Err("--partition numbers start at 1".to_string());
+        }
+
+        if let Some(flag) = self.seeking_option().filter(|_| self.reads_stdin()) {
+            return Err(format!("{} needs a seekable image; stdin (-) is read once, front to back", flag));
+        }
+
+        if self.reconstruct_deleted && !self.metadata_first {
+            return Err("--reconstruct-deleted requires --metadata-first".to_string());
+        }
+
+        if crate::logging::parse_level(&self.log_level).is_none() {
+            return Err(format!(
+                "Unknown log level: {} (expected one of {})",
+                self.log_level,
+                crate::logging::LOG_LEVEL_NAMES.join(", ")
+            ));
+        }
+
+        if let Some(ref patterns) = self.patterns {
+            if !patterns.is_file() {
+                return Err(format!("Pattern profile not found: {}", patterns.display()));
+            }
+        }
+
+        if let Some(ref solver_config) = self.solver_config {
+            crate::types::StreamScoringWeights::load(solver_config).map_err(|e| e.to_string())?;
+        }
+        self.stream_weights().check()?;
+
+        if let Some(unknown) = self.carve.iter().find(|t| *t != "all" && crate::carver::signature(t).is_none()) {
+            return Err(format!("Unknown carve type: {} (supported: jpg, png, pdf, docx, xlsx, pptx, zip, doc, xls, ppt, mp4, mov, cr2, nef, arw, dng, all)", unknown));
+        }
+
+        let detectors = crate::scanner::DETECTOR_NAMES;
+        if let Some(unknown) = self.detect.iter().find(|d| *d != "all" && !detectors.contains(&d.as_str())) {
+            return Err(format!("Unknown detector: {} (supported: {}, all)", unknown, detectors.join(", ")));
+        }
+
+        let artifacts = crate::scanner::ARTIFACT_NAMES;
+        if let Some(unknown) = self.artifacts.iter().find(|a| *a != "all" && !artifacts.contains(&a.as_str())) {
+            return Err(format!("Unknown artifact: {} (supported: {}, all)", unknown, artifacts.join(", ")));
+        }
+
+        if let Some(ref keyword_file) = self.keyword_file {
+            if !keyword_file.is_file() {
+                return Err(format!("Keyword file not found: {}", keyword_file.display()));
+            }
+        }
+
+        if self.keyword_only && self.keyword.is_empty() && self.keyword_file.is_none() {
+            return Err("--keyword-only needs --keyword or --keyword-file".to_string());
+        }
+
+        if let Some(ref profile) = self.keyword_profile {
+            crate::scanner::KeywordProfile::load(profile).map_err(|e| e.to_string())?;
+        }
+
+        if crate::matcher::Script::parse(&self.script).is_none() {
+            let names = crate::matcher::script::SCRIPT_NAMES;
+            return Err(format!("Unknown --script: {} (supported: {})", self.script, names.join(", ")));
+        }
+
+        if let Some(ref range) = self.trace_matcher {
+            crate::matcher::trace::parse_trace_range(range)?;
+        }
+
+        if self.links_only {
+            let writing = [
+                (!self.carve.is_empty(), "--carve"),
+                (self.metadata_first, "--metadata-first"),
+                (self.post_process_cmd.is_some(), "--post-process-cmd"),
+            ];
+            if let Some((_, flag)) = writing.iter().find(|(given, _)| *given) {
+                return Err(format!("--links-only saves no files, so {} cannot be used with it", flag));
+            }
+        }
+
+        match self.post_process_cmd.as_deref() {
+            Some(command) if command.trim().is_empty() => {
+                return Err("--post-process-cmd cannot be empty".to_string());
+            }
+            None if self.pipe_output => return Err("--pipe-output needs --post-process-cmd".to_string()),
+            _ => {}
+        }
+
+        if !(crate::recovery::MIN_FILENAME_BYTES..=crate::recovery::DEFAULT_MAX_FILENAME_BYTES).contains(&self.max_filename_bytes) {
+            return Err(format!(
+                "--max-filename-bytes must be between {} and {} (got {})",
+                crate::recovery::MIN_FILENAME_BYTES,
+                crate::recovery::DEFAULT_MAX_FILENAME_BYTES,
+                self.max_filename_bytes
+            ));
+        }
+
+        if !self.max_read_mbps.is_finite() || self.max_read_mbps < 0.0 {
+            return Err(format!("--max-read-mbps must be 0 or more (got {})", self.max_read_mbps));
+        }
+
+        if let Some(ref name) = self.io_nice {
+            if crate::scanner::IoPriority::parse(name).is_none() {
+                let names = crate::scanner::IO_PRIORITY_NAMES;
+                return Err(format!("Unknown --io-nice: {} (supported: {})", name, names.join(", ")));
+            }
+        }
+
+        if self.prefetch_depth == 0 {
+            return Err("--prefetch-depth must be at least 1".to_string());
+        }
+        if self.read_timeout_secs == 0 {
+            return Err("--read-timeout must be at least 1 second".to_string());
+        }
+
+        if crate::recovery::TitleStore::from_name(&self.keep_title).is_none() {
+            let stores = crate::recovery::TITLE_STORE_NAMES;
+            return Err(format!("Unknown --keep-title: {} (supported: {})", self.keep_title, stores.join(", ")));
+        }
+
+        if crate::recovery::GapFill::from_name(&self.gap_fill).is_none() {
+            let modes = crate::recovery::GAP_FILL_NAMES;
+            return Err(format!("Unknown --gap-fill: {} (supported: {})", self.gap_fill, modes.join(", ")));
+        }
+
+        if crate::recovery::Utf8Mode::from_name(&self.utf8).is_none() {
+            let modes = crate::recovery::UTF8_MODE_NAMES;
+            return Err(format!("Unknown UTF-8 mode: {} (supported: {})", self.utf8, modes.join(", ")));
+        }
+
+        let exports = crate::report::export::LINK_EXPORT_NAMES;
+        if let Some(unknown) = self.export_links.iter().find(|f| *f != "all" && !exports.contains(&f.as_str())) {
+            return Err(format!("Unknown link export format: {} (supported: {}, all)", unknown, exports.join(", ")));
+        }
+
+        if self.link_context > crate::matcher::MAX_LINK_CONTEXT {
+            return Err(format!(
+                "link-context must be at most {} bytes (got {})",
+                crate::matcher::MAX_LINK_CONTEXT,
+                self.link_context
+            ));
+        }
+
         Ok(())
     }
 
@@ -119,51 +584,894 @@ impl Args {
     pub fn chunk_max_bytes(&self) -> u64 {
         self.chunk_max * 1024
     }
+
+    /// Get the user-chosen overlap in bytes (None = automatic)
+    pub fn overlap_bytes(&self) -> Option<u64> {
+        self.overlap.map(|kb| kb * 1024)
+    }
+
+    /// Watchdog timeout for stalled chunk workers
+    pub fn stall_timeout(&self) -> Option<std::time::Duration> {
+        (self.stall_timeout_min > 0).then(|| std::time::Duration::from_secs(self.stall_timeout_min * 60))
+    }
+
+    /// Interval of file write-out during the scan (None = after the scan)
+    /// IMAGE arguments followed by the images of --image-list
+    pub fn image_paths(&self) -> crate::error::Result<Vec<PathBuf>> {
+        let mut images = self.images.clone();
+        if let Some(ref list) = self.image_list {
+            images.extend(crate::multi_image::load_image_list(list)?);
+        }
+        if images.len() > 1 && images.iter().any(|image| image.as_os_str() == "-") {
+            return Err(crate::error::RecoveryError::InvalidArgument(
+                "stdin (-) can only be scanned as the only image".to_string(),
+            ));
+        }
+        Ok(images)
+    }
+
+    /// First option set that reads the image out of order, which a pipe cannot
+    pub fn seeking_option(&self) -> Option<&'static str> {
+        let seeking = [
+            (self.reverse, "--reverse"),
+            (self.resume, "--resume"),
+            (self.partition.is_some(), "--partition"),
+            (self.unallocated_only, "--unallocated-only"),
+            (self.metadata_first, "--metadata-first"),
+            (self.enable_exfat, "--enable-exfat"),
+        ];
+        seeking.iter().find(|(set, _)| *set).map(|&(_, flag)| flag)
+    }
+
+    /// The image is streamed from stdin (`-`)
+    pub fn reads_stdin(&self) -> bool {
+        self.image.as_os_str() == "-"
+    }
+
+    /// `--output` when it is an `s3://` URL
+    pub fn s3_output_url(&self) -> Option<String> {
+        let output = self.output.to_string_lossy();
+        crate::s3::S3Location::is_url(&output).then(|| output.into_owned())
+    }
+
+    /// Log rotation size in bytes (None = never rotate)
+    pub fn log_max_bytes(&self) -> Option<u64> {
+        (self.log_max_mb > 0).then(|| self.log_max_mb.saturating_mul(1024 * 1024))
+    }
+
+    pub fn flush_interval(&self) -> Option<std::time::Duration> {
+        (self.flush_interval_secs > 0).then(|| std::time::Duration::from_secs(self.flush_interval_secs))
+    }
+
+    /// Scan time after which no new chunks are dispatched
+    pub fn time_budget(&self) -> Option<std::time::Duration> {
+        (self.time_budget_secs > 0).then(|| std::time::Duration::from_secs(self.time_budget_secs))
+    }
+
+    /// Running image digest of `--verify-hash` / `--verify-hash-md5`
+    pub fn image_digest(&self) -> Option<crate::scanner::ImageDigest> {
+        (self.verify_hash || self.verify_hash_md5).then(|| crate::scanner::ImageDigest::new(self.verify_hash_md5))
+    }
+
+    /// Fragment cache of `--fragment-cache`, spilling into `output_dir`
+    pub fn fragment_cache(&self, output_dir: &std::path::Path) -> Option<crate::scanner::FragmentCache> {
+        let memory = self.fragment_cache_mb.filter(|&mb| mb > 0)?;
+        let spill = self.fragment_cache_spill_mb.saturating_mul(1024 * 1024);
+        Some(crate::scanner::FragmentCache::new((memory as usize).saturating_mul(1024 * 1024), spill, output_dir))
+    }
+
+    /// Read rate limit of `--max-read-mbps` in bytes per second
+    pub fn max_read_bytes_per_sec(&self) -> Option<u64> {
+        (self.max_read_mbps > 0.0).then(|| ((self.max_read_mbps * 1024.0 * 1024.0) as u64).max(1))
+    }
+
+    /// I/O class of `--io-nice` (None when not given or invalid)
+    pub fn io_priority(&self) -> Option<crate::scanner::IoPriority> {
+        self.io_nice.as_deref().and_then(crate::scanner::IoPriority::parse)
+    }
+
+    /// Read-ahead of `--prefetch`; without it, on by default when the image
+    /// is on a network block device (`transport`, see `disk::network_transport`)
+    pub fn prefetch(&self, transport: Option<&str>) -> Option<crate::scanner::PrefetchConfig> {
+        let distance_bytes = match self.prefetch_mb {
+            Some(0) => return None,
+            Some(mb) => mb.saturating_mul(1024 * 1024),
+            None if transport.is_some() => crate::scanner::prefetch::DEFAULT_DISTANCE_BYTES,
+            None => return None,
+        };
+        Some(crate::scanner::PrefetchConfig {
+            distance_bytes,
+            depth: self.prefetch_depth,
+            timeout: std::time::Duration::from_secs(self.read_timeout_secs),
+            retries: self.read_retries,
+        })
+    }
+
+    /// Target script of `--script` (Cyrillic if the name is unknown)
+    pub fn target_script(&self) -> crate::matcher::Script {
+        crate::matcher::Script::parse(&self.script).unwrap_or_default()
+    }
+
+    /// Image range of `--trace-matcher` (None when not given or invalid)
+    pub fn trace_range(&self) -> Option<(u64, u64)> {
+        self.trace_matcher.as_deref().and_then(|range| crate::matcher::trace::parse_trace_range(range).ok())
+    }
+
+    /// UTF-8 repair of recovered text files (keep if the name is unknown)
+    pub fn utf8_mode(&self) -> crate::recovery::Utf8Mode {
+        crate::recovery::Utf8Mode::from_name(&self.utf8).unwrap_or_default()
+    }
+
+    /// Holes for missing bytes of `--gap-fill` (none when invalid)
+    pub fn gap_fill(&self) -> crate::recovery::GapFill {
+        crate::recovery::GapFill::from_name(&self.gap_fill).unwrap_or_default()
+    }
+
+    /// Filenames of recovered files (`--filename-translit`, `--max-filename-bytes`, `--keep-title`)
+    pub fn filename_policy(&self) -> crate::recovery::FilenamePolicy {
+        crate::recovery::FilenamePolicy {
+            translit: self.filename_translit,
+            max_bytes: self.max_filename_bytes,
+            keep_title: crate::recovery::TitleStore::from_name(&self.keep_title).unwrap_or_default(),
+        }
+    }
+
+    /// Stream solver weights: `--solver-config` (defaults when it does not
+    /// load; `validate` reports that), the overrides and the target size range
+    pub fn stream_weights(&self) -> crate::types::StreamScoringWeights {
+        let mut weights = self
+            .solver_config
+            .as_deref()
+            .and_then(|path| crate::types::StreamScoringWeights::load(path).ok())
+            .unwrap_or_default();
+        if let Some(kb) = self.max_gap {
+            weights.max_gap = kb.saturating_mul(1024);
+        }
+        if let Some(lookback) = self.max_lookback {
+            weights.max_lookback = lookback;
+        }
+        if let Some(score) = self.min_edge_score {
+            weights.min_edge_score = score;
+        }
+        weights.min_stream_size = self.target_size_min_bytes();
+        weights.max_stream_size = self.target_size_max_bytes();
+        weights
+    }
+
+    /// Solver weights differ from the defaults (recorded in provenance)
+    pub fn tunes_solver(&self) -> bool {
+        self.solver_config.is_some() || self.max_gap.is_some() || self.max_lookback.is_some() || self.min_edge_score.is_some()
+    }
+
+    /// File types to carve, with "all" expanded
+    pub fn carve_types(&self) -> Vec<String> {
+        if self.carve.iter().any(|t| t == "all") {
+            return crate::carver::SIGNATURES.iter().map(|sig| sig.name.to_string()).collect();
+        }
+        self.carve.clone()
+    }
+
+    /// Link export formats, with "all" expanded
+    pub fn link_export_formats(&self) -> Vec<crate::report::export::LinkExportFormat> {
+        use crate::report::export::{LinkExportFormat, LINK_EXPORT_NAMES};
+        // --links-only exports every format unless told which
+        let names: Vec<&str> = if self.export_links.iter().any(|f| f == "all") || (self.links_only && self.export_links.is_empty()) {
+            LINK_EXPORT_NAMES.to_vec()
+        } else {
+            self.export_links.iter().map(String::as_str).collect()
+        };
+        names.into_iter().filter_map(LinkExportFormat::from_name).collect()
+    }
+
+    /// Structure detectors to run, with "all" expanded
+    pub fn detect_types(&self) -> Vec<String> {
+        if self.detect.iter().any(|d| d == "all") {
+            return crate::scanner::DETECTOR_NAMES.iter().map(|name| name.to_string()).collect();
+        }
+        self.detect.clone()
+    }
+
+    /// Artifacts to carve, with "all" expanded
+    pub fn artifact_types(&self) -> Vec<String> {
+        if self.artifacts.iter().any(|a| a == "all") {
+            return crate::scanner::ARTIFACT_NAMES.iter().map(|name| name.to_string()).collect();
+        }
+        self.artifacts.clone()
+    }
+}
+
+/// Subcommands of `rust-recovery`; without one the images are scanned
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    Verify(VerifyArgs),
+    Estimate(EstimateArgs),
+    Image(ImageArgs),
+    Query(QueryArgs),
+    Serve(ServeArgs),
+}
+
+/// `rust-recovery verify`: rebuild a recovered file from the image and compare
+/// it with the saved output
+#[derive(clap::Args, Debug, Clone)]
+#[command(about = "Re-derive a recovered file from the image and report byte-level differences", long_about = None)]
+pub struct VerifyArgs {
+    /// Recovered file to verify (name or path, e.g. recovered_0004.json)
+    #[arg(long = "file", value_name = "FILE")]
+    pub file: PathBuf,
+
+    /// Output directory of the recovery run
+    #[arg(short = 'o', long = "output", default_value = "recovery_output")]
+    pub output: PathBuf,
+
+    /// Image to read (default: the image recorded in the report)
+    #[arg(long = "image")]
+    pub image: Option<PathBuf>,
+
+    /// JSON report listing the file (default: newest report in OUTPUT/reports that lists it)
+    #[arg(long = "report")]
+    pub report: Option<PathBuf>,
+}
+
+impl VerifyArgs {
+    /// File name as listed in the report
+    pub fn file_name(&self) -> String {
+        self.file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Saved copy: the given path if it exists, otherwise the file in
+    /// OUTPUT/01_RECOVERED_FILES under `listed`, its name in the report
+    pub fn saved_path(&self, listed: &str) -> PathBuf {
+        if self.file.is_file() {
+            return self.file.clone();
+        }
+        self.output.join("01_RECOVERED_FILES").join(listed)
+    }
+}
+
+/// `rust-recovery estimate`: score a random sample of the image and
+/// extrapolate what a full scan would find
+#[derive(clap::Args, Debug, Clone)]
+#[command(about = "Estimate recoverable content from a 1-5% sample of the image", long_about = None)]
+pub struct EstimateArgs {
+    /// Disk image file, E01, QCOW2/VMDK virtual disk or block device to sample
+    #[arg(value_name = "IMAGE")]
+    pub image: PathBuf,
+
+    /// Share of the image to sample, in percent (1-5)
+    #[arg(long = "sample", default_value = "2")]
+    pub sample_percent: f64,
+
+    /// Sample window size in KB (each window is scored like a scan chunk)
+    #[arg(long = "window", default_value = "1024")]
+    pub window_kb: u64,
+
+    /// Seed of the window positions (default: random; printed for repeat runs)
+    #[arg(long = "seed")]
+    pub seed: Option<u64>,
+
+    /// Stop sampling after this many seconds; windows done by then are used
+    #[arg(long = "time-limit", default_value = "540")]
+    pub time_limit_secs: u64,
+
+    /// Pattern profile TOML file, as for the scan (default: built-in YouTube patterns)
+    #[arg(long = "patterns")]
+    pub patterns: Option<PathBuf>,
+}
+
+impl EstimateArgs {
+    /// Validate the arguments
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1.0..=5.0).contains(&self.sample_percent) {
+            return Err(format!("sample must be between 1 and 5 percent (got {})", self.sample_percent));
+        }
+        if self.window_kb == 0 {
+            return Err("window must be greater than 0".to_string());
+        }
+        if self.time_limit_secs == 0 {
+            return Err("time-limit must be greater than 0".to_string());
+        }
+        if let Some(ref patterns) = self.patterns {
+            if !patterns.is_file() {
+                return Err(format!("Pattern profile not found: {}", patterns.display()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the window size in bytes
+    pub fn window_bytes(&self) -> usize {
+        (self.window_kb * 1024) as usize
+    }
+}
+
+/// `rust-recovery image`: copy a failing device to an image, easiest data first,
+/// with a ddrescue-compatible mapfile
+#[derive(clap::Args, Debug, Clone)]
+#[command(about = "Clone a failing device to an image with a bad-sector map; rerun to continue", long_about = None)]
+pub struct ImageArgs {
+    /// Device or image to read (e.g. /dev/sdb)
+    #[arg(value_name = "SOURCE")]
+    pub source: PathBuf,
+
+    /// Image file or device to write
+    #[arg(value_name = "DEST")]
+    pub destination: PathBuf,
+
+    /// ddrescue mapfile; an existing one continues that session (default: DEST.map)
+    #[arg(long = "mapfile")]
+    pub mapfile: Option<PathBuf>,
+
+    /// Read size of the copy passes in KB
+    #[arg(long = "block-size", default_value = "64")]
+    pub block_size_kb: u64,
+
+    /// Sector size in bytes, the unit of scraping and retries
+    #[arg(long = "sector-size", default_value = "512")]
+    pub sector_size: u64,
+
+    /// KB left for the second copy pass after a read error (0 = no skipping)
+    #[arg(long = "skip-size", default_value = "1024")]
+    pub skip_size_kb: u64,
+
+    /// Leave failed blocks unread instead of reading them sector by sector
+    #[arg(long = "no-scrape")]
+    pub no_scrape: bool,
+
+    /// Extra passes over bad sectors
+    #[arg(long = "retries", default_value = "0")]
+    pub retries: u32,
+
+    /// Overwrite a non-empty destination that has no mapfile
+    #[arg(long = "force")]
+    pub force: bool,
+}
+
+impl ImageArgs {
+    /// Validate the arguments
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sector_size == 0 || !self.sector_size.is_power_of_two() {
+            return Err(format!("sector-size must be a power of two (got {})", self.sector_size));
+        }
+        if self.block_size_kb == 0 || !(self.block_size_kb * 1024).is_multiple_of(self.sector_size) {
+            return Err(format!(
+                "block-size ({} KB) must be a non-zero multiple of the sector size ({} bytes)",
+                self.block_size_kb, self.sector_size
+            ));
+        }
+        if self.source == self.destination {
+            return Err("Source and destination must differ".to_string());
+        }
+        Ok(())
+    }
+
+    /// Mapfile path, DEST.map unless given
+    pub fn mapfile_path(&self) -> PathBuf {
+        self.mapfile.clone().unwrap_or_else(|| {
+            let mut name = self.destination.clone().into_os_string();
+            name.push(".map");
+            PathBuf::from(name)
+        })
+    }
+
+    pub fn policy(&self) -> crate::imaging::ImagingPolicy {
+        crate::imaging::ImagingPolicy {
+            block_size: (self.block_size_kb * 1024) as usize,
+            sector_size: self.sector_size as usize,
+            skip_size: self.skip_size_kb * 1024,
+            scrape: !self.no_scrape,
+            retries: self.retries,
+        }
+    }
+}
+
+/// `rust-recovery query`: filter the links or fragments of a finished
+/// `--scan-db` run without reading the image again
+#[derive(clap::Args, Debug, Clone)]
+#[command(about = "Filter the links or fragments in a scan database and re-export them", long_about = None)]
+pub struct QueryArgs {
+    /// Output directory of a --scan-db run, or its scan.db
+    #[arg(value_name = "OUTPUT", default_value = "recovery_output")]
+    pub database: PathBuf,
+
+    /// List hot fragments instead of links
+    #[arg(long = "fragments")]
+    pub fragments: bool,
+
+    /// Only rows at image offsets START-END (hex or decimal, END exclusive, e.g. 0x1000000-0x1100000)
+    #[arg(long = "range", value_name = "START-END")]
+    pub range: Option<String>,
+
+    /// Only links at least this confident (fragments: at least this score)
+    #[arg(long = "min-confidence", default_value = "0")]
+    pub min_confidence: f32,
+
+    /// Only links of this pattern (fragments: of this file type)
+    #[arg(long = "pattern")]
+    pub pattern: Option<String>,
+
+    /// Only links whose title matches this regex (case-insensitive)
+    #[arg(long = "title", value_name = "REGEX")]
+    pub title: Option<String>,
+
+    /// Stop after this many rows (0 = all)
+    #[arg(long = "limit", default_value = "0")]
+    pub limit: usize,
+
+    /// Write the matching links instead of listing them: comma-separated csv,jsonl,html (bookmarks) or "all"
+    #[arg(long = "export", value_delimiter = ',')]
+    pub export: Vec<String>,
+
+    /// Directory the --export files are written to
+    #[arg(long = "export-dir", default_value = ".")]
+    pub export_dir: PathBuf,
+}
+
+impl QueryArgs {
+    /// Validate the arguments
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(ref range) = self.range {
+            crate::matcher::trace::parse_trace_range(range)?;
+        }
+        if let Some(ref title) = self.title {
+            regex::RegexBuilder::new(title)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Invalid --title regex: {}", e))?;
+        }
+        let exports = crate::report::export::LINK_EXPORT_NAMES;
+        if let Some(unknown) = self.export.iter().find(|f| *f != "all" && !exports.contains(&f.as_str())) {
+            return Err(format!("Unknown link export format: {} (supported: {}, all)", unknown, exports.join(", ")));
+        }
+        if self.fragments && (self.title.is_some() || !self.export.is_empty()) {
+            return Err("--title and --export apply to links, not --fragments".to_string());
+        }
+        Ok(())
+    }
+
+    /// The database file: OUTPUT/scan.db, or the path itself when it is a file
+    pub fn database_path(&self) -> PathBuf {
+        if self.database.is_dir() {
+            self.database.join(crate::scan_db::SCAN_DB_FILE)
+        } else {
+            self.database.clone()
+        }
+    }
+
+    /// Filters of the query
+    pub fn filter(&self) -> crate::scan_db::QueryFilter {
+        crate::scan_db::QueryFilter {
+            range: self.range.as_deref().and_then(|range| crate::matcher::trace::parse_trace_range(range).ok()),
+            min_confidence: self.min_confidence,
+            pattern: self.pattern.clone(),
+            title: self
+                .title
+                .as_deref()
+                .and_then(|title| regex::RegexBuilder::new(title).case_insensitive(true).build().ok()),
+            limit: (self.limit > 0).then_some(self.limit),
+        }
+    }
+
+    pub fn export_formats(&self) -> Vec<crate::report::export::LinkExportFormat> {
+        use crate::report::export::{LinkExportFormat, LINK_EXPORT_NAMES};
+        let names: Vec<&str> = if self.export.iter().any(|f| f == "all") {
+            LINK_EXPORT_NAMES.to_vec()
+        } else {
+            self.export.iter().map(String::as_str).collect()
+        };
+        names.into_iter().filter_map(LinkExportFormat::from_name).collect()
+    }
+}
+
+/// `rust-recovery serve`: browse the report and recovered files of a
+/// finished run over HTTP
+#[derive(clap::Args, Debug, Clone)]
+#[command(about = "Serve the HTML report and recovered files of a run over HTTP", long_about = None)]
+pub struct ServeArgs {
+    /// Output directory of the run
+    #[arg(value_name = "OUTPUT", default_value = "recovery_output")]
+    pub output: PathBuf,
+
+    /// Address to listen on; 0.0.0.0:8080 lets other machines on the network browse the results
+    #[arg(long = "addr", value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub addr: String,
+}
+
+/// `--max-duration`: plain seconds or a sum of `d`, `h`, `m` and `s` parts (`1h30m`)
+fn parse_duration_secs(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    if let Ok(secs) = text.parse() {
+        return Ok(secs);
+    }
+    let invalid = || format!("invalid duration '{}': use seconds or units like 90m, 2h, 1h30m", text);
+    let mut total = 0u64;
+    let mut digits = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = digits.parse().map_err(|_| invalid())?;
+        total = total.saturating_add(value.saturating_mul(unit));
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration_secs("0"), Ok(0));
+        assert_eq!(parse_duration_secs("3600"), Ok(3600));
+        assert_eq!(parse_duration_secs("90m"), Ok(5400));
+        assert_eq!(parse_duration_secs("1h30m"), Ok(5400));
+        assert_eq!(parse_duration_secs("2d"), Ok(172_800));
+        assert!(parse_duration_secs("1h30").is_err());
+        assert!(parse_duration_secs("h").is_err());
+        assert!(parse_duration_secs("10 minutes").is_err());
+    }
+
+    #[test]
+    fn test_subcommands_parse_instead_of_a_scan() {
+        let args = Args::try_parse_from(["rust-recovery", "verify", "--file", "recovered_0004.json"]).unwrap();
+        let Some(Command::Verify(verify)) = args.command else { panic!("expected the verify subcommand") };
+        assert_eq!(verify.file_name(), "recovered_0004.json");
+        let args = Args::try_parse_from(["rust-recovery", "query", "out", "--fragments"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Query(ref query)) if query.fragments));
+        let args = Args::try_parse_from(["rust-recovery", "disk.img", "-o", "out"]).unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.images, vec![PathBuf::from("disk.img")]);
+
+        // Scan options do not mix with a subcommand, and a subcommand keeps its own required arguments
+        assert!(Args::try_parse_from(["rust-recovery", "-o", "out", "verify", "--file", "a.json"]).is_err());
+        assert!(Args::try_parse_from(["rust-recovery", "estimate"]).is_err());
+        assert!(Args::try_parse_from(["rust-recovery"]).is_err());
+    }
+
+    #[test]
+    fn test_solver_config_file_and_overrides() {
+        use crate::types::StreamScoringWeights;
+
+        let solver_config = std::env::temp_dir().join(format!("rust_recovery_solver_keys_{}.toml", std::process::id()));
+        std::fs::write(&solver_config, "max_gap = 4096\nmax_lookback = 50\nmin_edge_score = 2.5\n").unwrap();
+        let config = solver_config.to_str().unwrap();
+
+        // Keys left out of the file keep their default
+        let args = Args::try_parse_from(["rust-recovery", "disk.img", "-o", "out", "--solver-config", config]).unwrap();
+        let weights = args.stream_weights();
+        let expected = StreamScoringWeights {
+            max_gap: 4096,
+            max_lookback: 50,
+            min_edge_score: 2.5,
+            min_stream_size: weights.min_stream_size,
+            max_stream_size: weights.max_stream_size,
+            ..StreamScoringWeights::default()
+        };
+        assert_eq!(weights, expected);
+        assert!(args.tunes_solver());
+
+        // The flags beat the file
+        let args = Args::try_parse_from([
+            "rust-recovery", "disk.img", "-o", "out", "--solver-config", config,
+            "--max-gap", "64", "--max-lookback", "10", "--min-edge-score", "7.5",
+        ])
+        .unwrap();
+        let weights = args.stream_weights();
+        assert_eq!((weights.max_gap, weights.max_lookback, weights.min_edge_score), (64 * 1024, 10, 7.5));
+        assert_eq!(weights.cosine_weight, StreamScoringWeights::default().cosine_weight);
+
+        // Unknown keys, wrong types and unusable values are configuration errors
+        for invalid in ["max_gapp = 1\n", "max_gap = \"far\"\n", "max_lookback = 0\n", "cosine_weight = nan\n"] {
+            std::fs::write(&solver_config, invalid).unwrap();
+            let error = StreamScoringWeights::load(&solver_config).unwrap_err();
+            assert!(matches!(error, crate::error::RecoveryError::Config(_)), "{}: {}", invalid, error);
+        }
+        std::fs::remove_file(&solver_config).unwrap();
+    }
+
     #[test]
     fn test_args_validation() {
         let args = Args {
+            images: vec![PathBuf::from("test.img")],
+            command: None,
+            image_list: None,
+            parallel_images: 2,
             image: PathBuf::from("test.img"),
             target_size_min: 15,
             target_size_max: 300,
             reverse: false,
             nvme: false,
+            gpu: false,
+            stream: false,
             early_exit: 0,
+            priority: false,
+            time_budget_secs: 0,
+            verify_hash: false,
+            verify_hash_md5: false,
+            max_read_mbps: 0.0,
+            io_nice: None,
+            prefetch_mb: None,
+            fragment_cache_mb: None,
+            fragment_cache_spill_mb: 4096,
+            prefetch_depth: 8,
+            read_timeout_secs: 30,
+            read_retries: 3,
             output: PathBuf::from("output"),
             enable_exfat: false,
             no_live: false,
             links_only: false,
             chunk_min: 32,
             chunk_max: 2048,
+            overlap: None,
             full_exfat_recovery: true,
             semantic_scan: false,
+            stall_timeout_min: 5,
+            max_streams: 3,
+            solver_config: None,
+            max_gap: None,
+            max_lookback: None,
+            min_edge_score: None,
+            patterns: None,
+            metadata_first: false,
+            reconstruct_deleted: false,
+            unallocated_only: false,
+            partition: None,
+            adaptive_chunks: false,
+            carve: Vec::new(),
+            detect: Vec::new(),
+            link_context: 0,
+            artifacts: Vec::new(),
+            export_links: Vec::new(),
+            keyword: Vec::new(),
+            keyword_file: None,
+            keyword_only: false,
+            keyword_profile: None,
+            script: "cyrillic".to_string(),
+            fragments_jsonl: false,
+            scan_db: false,
+            serve: false,
+            serve_addr: "127.0.0.1:8080".to_string(),
+            s3_staging: PathBuf::from("recovery_staging"),
+            s3_endpoint: None,
+            s3_region: None,
+            s3_keep_local: false,
+            flush_interval_secs: 0,
+            post_process_cmd: None,
+            pipe_output: false,
+            log_max_mb: 256,
+            log_file: None,
+            log_level: "info".to_string(),
+            progress_interval: 2,
+            max_files_per_dir: 10000,
+            no_repair: false,
+            no_dedup: false,
+            dedup_similarity: 90,
+            progress_stream: None,
+            resume: false,
+            trace_matcher: None,
+            chunk_stats: false,
+            no_verify: false,
+            filename_translit: false,
+            max_filename_bytes: 255,
+            keep_title: "none".to_string(),
+            utf8: "keep".to_string(),
+            gap_fill: "none".to_string(),
+            force: false,
         };
 
         assert!(args.validate().is_ok());
+
+        let mut carving = args.clone();
+        carving.carve = vec!["all".to_string()];
+        assert_eq!(carving.carve_types().len(), crate::carver::SIGNATURES.len());
+        carving.carve = vec!["jpg".to_string(), "gif".to_string()];
+        assert!(carving.validate().is_err());
+
+        let mut focused = args.clone();
+        focused.keyword_only = true;
+        assert!(focused.validate().is_err());
+        focused.keyword = vec!["рецепт".to_string()];
+        assert!(focused.validate().is_ok());
+        focused.script = "CJK".to_string();
+        assert!(focused.validate().is_ok());
+        assert_eq!(focused.target_script(), crate::matcher::Script::Cjk);
+        focused.script = "klingon".to_string();
+        assert!(focused.validate().is_err());
+        focused.script = "cyrillic".to_string();
+        focused.keyword_profile = Some(std::env::temp_dir().join("rust_recovery_missing_profile.toml"));
+        assert!(focused.validate().is_err());
+
+        let mut repairing = args.clone();
+        repairing.utf8 = "fix".to_string();
+        assert!(repairing.validate().is_err());
+        repairing.trace_matcher = Some("0x2000-0x1000".to_string());
+        assert!(repairing.validate().is_err());
+        repairing.trace_matcher = None;
+        repairing.utf8 = "strict".to_string();
+        assert_eq!(repairing.utf8_mode(), crate::recovery::Utf8Mode::Strict);
+        repairing.gap_fill = "holes".to_string();
+        assert!(repairing.validate().is_err());
+        repairing.gap_fill = "marker".to_string();
+        assert_eq!(repairing.gap_fill(), crate::recovery::GapFill::Marker);
+
+        let mut named = args.clone();
+        named.max_filename_bytes = 16;
+        assert!(named.validate().is_err());
+        named.max_filename_bytes = 143;
+        named.keep_title = "xattr".to_string();
+        assert!(named.validate().is_ok());
+        assert_eq!(named.filename_policy().keep_title, crate::recovery::TitleStore::Xattr);
+        named.keep_title = "ads".to_string();
+        assert!(named.validate().is_err());
+
+        let mut triage = args.clone();
+        triage.links_only = true;
+        assert!(triage.validate().is_ok());
+        assert_eq!(triage.link_export_formats().len(), crate::report::export::LINK_EXPORT_NAMES.len());
+        triage.export_links = vec!["csv".to_string()];
+        assert_eq!(triage.link_export_formats(), [crate::report::export::LinkExportFormat::Csv]);
+        triage.carve = vec!["jpg".to_string()];
+        assert_eq!(triage.validate().unwrap_err(), "--links-only saves no files, so --carve cannot be used with it");
+
+        let mut throttled = args.clone();
+        throttled.max_read_mbps = 1.5;
+        throttled.io_nice = Some("best-effort:6".to_string());
+        assert!(throttled.validate().is_ok());
+        assert_eq!(throttled.max_read_bytes_per_sec(), Some(3 << 19));
+        assert_eq!(throttled.io_priority(), Some(crate::scanner::IoPriority::BestEffort(6)));
+        throttled.io_nice = Some("realtime".to_string());
+        assert!(throttled.validate().is_err());
+        throttled.io_nice = None;
+        throttled.max_read_mbps = -1.0;
+        assert!(throttled.validate().is_err());
+
+        let mut remote = args.clone();
+        assert_eq!(remote.prefetch(None), None);
+        assert_eq!(remote.prefetch(Some("NBD")).unwrap().distance_bytes, crate::scanner::prefetch::DEFAULT_DISTANCE_BYTES);
+        remote.prefetch_mb = Some(64);
+        let prefetch = remote.prefetch(None).unwrap();
+        assert_eq!((prefetch.distance_bytes, prefetch.depth, prefetch.retries), (64 << 20, 8, 3));
+        remote.prefetch_mb = Some(0);
+        assert_eq!(remote.prefetch(Some("iSCSI")), None);
+        remote.prefetch_depth = 0;
+        assert!(remote.validate().is_err());
+
+        let mut tuned = args.clone();
+        let solver_config = std::env::temp_dir().join(format!("rust_recovery_solver_{}.toml", std::process::id()));
+        std::fs::write(&solver_config, "max_gap = 4096\ncosine_weight = 40.0\n").unwrap();
+        tuned.solver_config = Some(solver_config.clone());
+        tuned.max_lookback = Some(1000);
+        assert!(tuned.validate().is_ok());
+        let weights = tuned.stream_weights();
+        assert_eq!((weights.max_gap, weights.cosine_weight, weights.max_lookback), (4096, 40.0, 1000));
+        assert_eq!(weights.max_stream_size, 300 * 1024);
+        tuned.max_gap = Some(64);
+        assert_eq!(tuned.stream_weights().max_gap, 64 * 1024);
+        std::fs::write(&solver_config, "max_gap = 4096\nmax_gapp = 1\n").unwrap();
+        assert!(tuned.validate().is_err());
+        std::fs::remove_file(&solver_config).unwrap();
+        tuned.solver_config = None;
+        tuned.max_lookback = Some(0);
+        assert!(tuned.validate().is_err());
+
+        let mut piping = args.clone();
+        piping.pipe_output = true;
+        assert!(piping.validate().is_err());
+        piping.post_process_cmd = Some("clamscan --no-summary -".to_string());
+        assert!(piping.validate().is_ok());
+
+        let mut streamed = args.clone();
+        streamed.image = PathBuf::from("-");
+        assert!(streamed.reads_stdin() && streamed.validate().is_ok());
+        streamed.reverse = true;
+        assert!(streamed.validate().unwrap_err().starts_with("--reverse needs a seekable image"));
+        streamed.images = vec![PathBuf::from("-"), PathBuf::from("disk.img")];
+        assert!(streamed.image_paths().is_err());
     }
 
     #[test]
     fn test_invalid_size_range() {
         let args = Args {
+            images: vec![PathBuf::from("test.img")],
+            command: None,
+            image_list: None,
+            parallel_images: 2,
             image: PathBuf::from("test.img"),
             target_size_min: 500,
             target_size_max: 300,
             reverse: false,
             nvme: false,
+            gpu: false,
+            stream: false,
             early_exit: 0,
+            priority: false,
+            time_budget_secs: 0,
+            verify_hash: false,
+            verify_hash_md5: false,
+            max_read_mbps: 0.0,
+            io_nice: None,
+            prefetch_mb: None,
+            fragment_cache_mb: None,
+            fragment_cache_spill_mb: 4096,
+            prefetch_depth: 8,
+            read_timeout_secs: 30,
+            read_retries: 3,
             output: PathBuf::from("output"),
             enable_exfat: false,
             no_live: false,
             links_only: false,
             chunk_min: 32,
             chunk_max: 2048,
+            overlap: None,
             full_exfat_recovery: true,
             semantic_scan: false,
+            stall_timeout_min: 5,
+            max_streams: 3,
+            solver_config: None,
+            max_gap: None,
+            max_lookback: None,
+            min_edge_score: None,
+            patterns: None,
+            metadata_first: false,
+            reconstruct_deleted: false,
+            unallocated_only: false,
+            partition: None,
+            adaptive_chunks: false,
+            carve: Vec::new(),
+            detect: Vec::new(),
+            link_context: 0,
+            artifacts: Vec::new(),
+            export_links: Vec::new(),
+            keyword: Vec::new(),
+            keyword_file: None,
+            keyword_only: false,
+            keyword_profile: None,
+            script: "cyrillic".to_string(),
+            fragments_jsonl: false,
+            scan_db: false,
+            serve: false,
+            serve_addr: "127.0.0.1:8080".to_string(),
+            s3_staging: PathBuf::from("recovery_staging"),
+            s3_endpoint: None,
+            s3_region: None,
+            s3_keep_local: false,
+            flush_interval_secs: 0,
+            post_process_cmd: None,
+            pipe_output: false,
+            log_max_mb: 256,
+            log_file: None,
+            log_level: "info".to_string(),
+            progress_interval: 2,
+            max_files_per_dir: 10000,
+            no_repair: false,
+            no_dedup: false,
+            dedup_similarity: 90,
+            progress_stream: None,
+            resume: false,
+            trace_matcher: None,
+            chunk_stats: false,
+            no_verify: false,
+            filename_translit: false,
+            max_filename_bytes: 255,
+            keep_title: "none".to_string(),
+            utf8: "keep".to_string(),
+            gap_fill: "none".to_string(),
+            force: false,
         };
 
         assert!(args.validate().is_err());
@@ -172,20 +1480,91 @@ mod tests {
     #[test]
     fn test_byte_conversions() {
         let args = Args {
+            images: vec![PathBuf::from("test.img")],
+            command: None,
+            image_list: None,
+            parallel_images: 2,
             image: PathBuf::from("test.img"),
             target_size_min: 15,
             target_size_max: 300,
             reverse: false,
             nvme: false,
+            gpu: false,
+            stream: false,
             early_exit: 0,
+            priority: false,
+            time_budget_secs: 0,
+            verify_hash: false,
+            verify_hash_md5: false,
+            max_read_mbps: 0.0,
+            io_nice: None,
+            prefetch_mb: None,
+            fragment_cache_mb: None,
+            fragment_cache_spill_mb: 4096,
+            prefetch_depth: 8,
+            read_timeout_secs: 30,
+            read_retries: 3,
             output: PathBuf::from("output"),
             enable_exfat: false,
             no_live: false,
             links_only: false,
             chunk_min: 32,
             chunk_max: 2048,
+            overlap: None,
             full_exfat_recovery: true,
             semantic_scan: false,
+            stall_timeout_min: 5,
+            max_streams: 3,
+            solver_config: None,
+            max_gap: None,
+            max_lookback: None,
+            min_edge_score: None,
+            patterns: None,
+            metadata_first: false,
+            reconstruct_deleted: false,
+            unallocated_only: false,
+            partition: None,
+            adaptive_chunks: false,
+            carve: Vec::new(),
+            detect: Vec::new(),
+            link_context: 0,
+            artifacts: Vec::new(),
+            export_links: Vec::new(),
+            keyword: Vec::new(),
+            keyword_file: None,
+            keyword_only: false,
+            keyword_profile: None,
+            script: "cyrillic".to_string(),
+            fragments_jsonl: false,
+            scan_db: false,
+            serve: false,
+            serve_addr: "127.0.0.1:8080".to_string(),
+            s3_staging: PathBuf::from("recovery_staging"),
+            s3_endpoint: None,
+            s3_region: None,
+            s3_keep_local: false,
+            flush_interval_secs: 0,
+            post_process_cmd: None,
+            pipe_output: false,
+            log_max_mb: 256,
+            log_file: None,
+            log_level: "info".to_string(),
+            progress_interval: 2,
+            max_files_per_dir: 10000,
+            no_repair: false,
+            no_dedup: false,
+            dedup_similarity: 90,
+            progress_stream: None,
+            resume: false,
+            trace_matcher: None,
+            chunk_stats: false,
+            no_verify: false,
+            filename_translit: false,
+            max_filename_bytes: 255,
+            keep_title: "none".to_string(),
+            utf8: "keep".to_string(),
+            gap_fill: "none".to_string(),
+            force: false,
         };
 
         assert_eq!(args.target_size_min_bytes(), 15 * 1024);
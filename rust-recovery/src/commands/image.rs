@@ -0,0 +1,81 @@
+//! `rust-recovery image SOURCE DEST`: clone a failing device instead of scanning
+//!
+//! The copy goes easiest data first (`imaging::Imager`) and keeps a
+//! ddrescue-compatible mapfile next to the image, so a rerun continues the
+//! session.
+
+use crate::cli::ImageArgs;
+use crate::error::{RecoveryError, Result};
+use crate::imaging::{BlockStatus, FileSource, Imager, RescueMap, SectorSource};
+use crate::recovery::TOOL_VERSION;
+use std::fs;
+
+/// Image the source; false if bad or unread areas remain
+pub fn run(args: ImageArgs) -> Result<bool> {
+    args.validate().map_err(RecoveryError::InvalidArgument)?;
+    let source = FileSource::open(&args.source)?;
+    let size = source.size();
+    let mapfile = args.mapfile_path();
+
+    let map = if mapfile.exists() {
+        let map = RescueMap::parse(&fs::read_to_string(&mapfile)?, size)?;
+        println!(
+            "Continuing from {}: {} of {} bytes rescued, {} bytes bad",
+            mapfile.display(),
+            map.bytes(BlockStatus::Finished),
+            size,
+            map.bytes(BlockStatus::BadSector)
+        );
+        map
+    } else {
+        let existing = fs::metadata(&args.destination).map(|m| m.is_file() && m.len() > 0).unwrap_or(false);
+        if existing && !args.force {
+            return Err(RecoveryError::InvalidArgument(format!(
+                "{} exists and has no mapfile ({}); use --force to overwrite it",
+                args.destination.display(),
+                mapfile.display()
+            )));
+        }
+        RescueMap::new(size)
+    };
+
+    let dest = fs::OpenOptions::new().write(true).create(true).truncate(false).open(&args.destination)?;
+    if dest.metadata()?.is_file() {
+        if dest.metadata()?.len() < size {
+            dest.set_len(size)?;
+        }
+    } else if crate::disk::image_len(&dest)? < size {
+        return Err(RecoveryError::InvalidArgument(format!(
+            "{} is smaller than the source ({} bytes)",
+            args.destination.display(),
+            size
+        )));
+    }
+
+    println!("Imaging {} -> {} ({} bytes)", args.source.display(), args.destination.display(), size);
+    let header = vec![
+        format!("Mapfile. Created by rust-recovery {}", TOOL_VERSION),
+        format!("Command line: {}", std::env::args().collect::<Vec<_>>().join(" ")),
+        format!("Start time:   {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S")),
+    ];
+    // Data reaches the destination before the map that marks it rescued
+    let save = |map: &RescueMap| -> std::io::Result<()> {
+        dest.sync_data()?;
+        let tmp = mapfile.with_extension("map.tmp");
+        fs::write(&tmp, map.render(&header))?;
+        fs::rename(&tmp, &mapfile)
+    };
+    let mut imager = Imager::new(&source, &dest, args.policy(), map, save);
+    imager.run(&mut |line| println!("  {}", line))?;
+
+    let map = imager.map();
+    println!(
+        "Imaging finished: {} of {} bytes rescued, {} bytes bad, {} bytes unread; map in {}",
+        map.bytes(BlockStatus::Finished),
+        size,
+        map.bytes(BlockStatus::BadSector),
+        size - map.bytes(BlockStatus::Finished) - map.bytes(BlockStatus::BadSector),
+        mapfile.display()
+    );
+    Ok(map.is_complete())
+}
@@ -0,0 +1,29 @@
+//! Subcommands run instead of a scan (`cli::Command`)
+//!
+//! Each runs to the end and yields the process exit code; errors are
+//! reported by `main` like those of a scan, without the summary line.
+
+pub mod estimate;
+pub mod image;
+pub mod query;
+pub mod serve;
+pub mod verify;
+
+use crate::cli::Command;
+use crate::error::Result;
+use crate::exit_code;
+
+/// Run `command`; Ok holds its exit code
+pub fn run(command: Command) -> Result<i32> {
+    match command {
+        Command::Verify(args) => {
+            verify::run(args).map(|same| if same { exit_code::SUCCESS } else { exit_code::VERIFY_DIFFERS })
+        }
+        Command::Estimate(args) => estimate::run(args).map(|_| exit_code::SUCCESS),
+        Command::Image(args) => {
+            image::run(args).map(|complete| if complete { exit_code::SUCCESS } else { exit_code::PARTIAL })
+        }
+        Command::Query(args) => query::run(args).map(|_| exit_code::SUCCESS),
+        Command::Serve(args) => serve::run(&args.output, &args.addr).map(|_| exit_code::SUCCESS),
+    }
+}
@@ -0,0 +1,59 @@
+//! `rust-recovery query OUTPUT`: filter the scan database of an earlier run
+//!
+//! Links or hot fragments of a `--scan-db` run are listed, or the links are
+//! exported again, without reading the image.
+
+use crate::cli::QueryArgs;
+use crate::error::{RecoveryError, Result};
+use crate::scan_db::ScanDb;
+use std::fs;
+
+/// List or export the rows of a scan database that pass the filters
+pub fn run(args: QueryArgs) -> Result<()> {
+    args.validate().map_err(RecoveryError::InvalidArgument)?;
+    let db = ScanDb::open_read_only(&args.database_path())?;
+    let filter = args.filter();
+
+    if args.fragments {
+        let fragments = db.query_fragments(&filter)?;
+        for (id, fragment) in &fragments {
+            println!(
+                "cluster_{}\t0x{:X}\t{}\t{}\t{:.1}\t{}{}",
+                id,
+                fragment.offset,
+                fragment.size,
+                fragment.file_type,
+                fragment.target_score,
+                fragment.youtube_count,
+                if fragment.carved { "\tcarved" } else { "" }
+            );
+        }
+        eprintln!("{} fragments", fragments.len());
+        return Ok(());
+    }
+
+    let links = db.query_links(&filter)?;
+    let formats = args.export_formats();
+    if formats.is_empty() {
+        for link in &links {
+            println!(
+                "0x{:X}\t{:.2}\t{}\t{}\t{}",
+                link.offset,
+                link.confidence,
+                link.pattern_name,
+                link.url,
+                link.title.as_deref().unwrap_or("")
+            );
+        }
+        eprintln!("{} links", links.len());
+        return Ok(());
+    }
+
+    // Grouped by cluster: the recovered files are in the report, not the database
+    fs::create_dir_all(&args.export_dir)?;
+    let clusters = db.clusters()?;
+    for path in crate::report::export::export_links(&args.export_dir, &links, &[], &clusters, &formats)? {
+        println!("{} links written to {}", links.len(), path.display());
+    }
+    Ok(())
+}
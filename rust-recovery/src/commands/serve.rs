@@ -0,0 +1,15 @@
+//! `rust-recovery serve OUTPUT`: browse the results of an earlier run
+
+use crate::error::Result;
+use std::path::Path;
+
+/// Serve the report and recovered files in `output` (`--serve`, `serve`)
+#[cfg(feature = "serve")]
+pub fn run(output: &Path, addr: &str) -> Result<()> {
+    crate::serve::serve(output, addr)
+}
+
+#[cfg(not(feature = "serve"))]
+pub fn run(_output: &Path, _addr: &str) -> Result<()> {
+    Err(crate::error::RecoveryError::Config("preview server not built (cargo build --features serve)".to_string()))
+}
@@ -0,0 +1,108 @@
+//! `rust-recovery verify --file NAME`: check a recovered file against the image
+//!
+//! The file is rebuilt from the extents listed in the report, with the
+//! repairs and `--gap-fill` of the run that saved it, and compared byte by
+//! byte with the saved copy; the provenance hash is checked when the image
+//! is the one in the report.
+
+use crate::checkpoint::compute_image_hash;
+use crate::cli::VerifyArgs;
+use crate::disk::DiskImage;
+use crate::error::{RecoveryError, Result};
+use crate::recovery::{
+    diff_ranges, find_report_entry, provenance_hash, reassemble_with, FileTypeRegistry, GapFill, Reassembled, Repair,
+    Utf8Mode,
+};
+use std::fs;
+use tracing::warn;
+
+/// Differing ranges printed by `verify`
+const VERIFY_MAX_DIFFS: usize = 20;
+/// Bytes of each side shown per differing range
+const VERIFY_PREVIEW_BYTES: usize = 16;
+
+/// `rust-recovery verify`: rebuild a recovered file from the extents in the
+/// report and compare it with the saved copy. Ok(false) when they differ.
+pub fn run(args: VerifyArgs) -> Result<bool> {
+    let filename = args.file_name();
+    let entry = find_report_entry(&args.output, &filename, args.report.as_deref())?;
+    let file = &entry.file;
+    let image = args.image.clone().unwrap_or_else(|| entry.metadata.image_path.clone().into());
+    let saved_path = args.saved_path(&file.filename);
+
+    println!("Verifying {}", filename);
+    println!("  Report:    {}", entry.report_path.display());
+    println!("  Image:     {}", image.display());
+    println!("  Saved:     {}", saved_path.display());
+    let fragment_bytes: u64 = file.fragments.iter().map(|f| f.size.as_u64()).sum();
+    println!("  Fragments: {} ({} bytes)", file.fragments.len(), fragment_bytes);
+
+    let saved = fs::read(&saved_path)
+        .map_err(|e| RecoveryError::FileNotFound(format!("{}: {}", saved_path.display(), e)))?;
+    let disk = DiskImage::open(&image)?;
+    // Text files are repaired the way the run that saved them did
+    let utf8_mode = entry.metadata.parameters.get("utf8_mode").and_then(|name| Utf8Mode::from_name(name));
+    let trim = entry.metadata.parameters.get("trim_boundaries").is_some_and(|trim| trim == "true");
+    let file_types = FileTypeRegistry::builtin().with_utf8_mode(utf8_mode.unwrap_or_default()).with_boundary_trim(trim);
+    // Holes for missing bytes as well (`--gap-fill`)
+    let gap_fill = entry.metadata.parameters.get("gap_fill").and_then(|name| GapFill::from_name(name));
+    let read = |offset, len| disk.get_slice(offset, len).ok().map(|slice| slice.data);
+    let Reassembled { data: mut rebuilt, unreadable, extents_sha256, .. } =
+        reassemble_with(read, &file.fragments, &file.file_type, &file_types, gap_fill.unwrap_or_default());
+    // The validation pass may have repaired the saved file
+    if let Some(repair) = file.repair.as_deref().and_then(Repair::from_name) {
+        match file_types.repair(&rebuilt, &file.file_type) {
+            Some((repaired, made)) if made == repair => rebuilt = repaired,
+            _ => warn!("The {} repair in the report does not apply to the rebuilt file", repair.name()),
+        }
+    }
+    for extent in &unreadable {
+        warn!("Fragment 0x{:X} (+{} bytes) could not be read", extent.offset, extent.size);
+    }
+
+    // The provenance hash covers the extent bytes and the identity of the image
+    let image_hash = compute_image_hash(&image)?;
+    if entry.metadata.image_hash.is_empty() {
+        println!("  Provenance: not recorded in the report");
+    } else if image_hash != entry.metadata.image_hash {
+        println!("  Provenance: image differs from the one in the report");
+    } else {
+        let mut params = entry.metadata.parameters.clone();
+        params.insert("file_type".to_string(), file.file_type.clone());
+        let expected = provenance_hash(&image_hash, &file.fragments, &extents_sha256, &entry.metadata.version, &params);
+        let status = if expected == file.provenance_hash { "OK" } else { "MISMATCH" };
+        println!("  Provenance: {}", status);
+    }
+
+    let rebuilt_sha256 = crate::matcher::sha256_hash(&rebuilt);
+    let saved_sha256 = crate::matcher::sha256_hash(&saved);
+    println!("  SHA-256 in report: {}", file.sha256);
+    println!("  SHA-256 rebuilt:   {} ({} bytes)", rebuilt_sha256, rebuilt.len());
+    println!("  SHA-256 saved:     {} ({} bytes)", saved_sha256, saved.len());
+
+    let diffs = diff_ranges(&rebuilt, &saved);
+    if diffs.is_empty() {
+        println!("Result: IDENTICAL (saved file matches the image)");
+        return Ok(true);
+    }
+
+    let differing: u64 = diffs.iter().map(|d| d.len).sum();
+    println!("Result: DIFFERENT ({} bytes in {} ranges)", differing, diffs.len());
+    let preview = |data: &[u8], offset: u64| -> String {
+        let start = (offset as usize).min(data.len());
+        let end = (start + VERIFY_PREVIEW_BYTES).min(data.len());
+        if start == end {
+            return "(past end)".to_string();
+        }
+        data[start..end].iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+    };
+    for diff in diffs.iter().take(VERIFY_MAX_DIFFS) {
+        println!("  0x{:08X} +{} bytes", diff.offset, diff.len);
+        println!("    rebuilt: {}", preview(&rebuilt, diff.offset));
+        println!("    saved:   {}", preview(&saved, diff.offset));
+    }
+    if diffs.len() > VERIFY_MAX_DIFFS {
+        println!("  ... {} more ranges", diffs.len() - VERIFY_MAX_DIFFS);
+    }
+    Ok(false)
+}
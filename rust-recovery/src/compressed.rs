@@ -0,0 +1,699 @@
+//! gzip, zstd and xz compressed image files
+//!
+//! `rust-recovery image.img.zst` reads the decompressed image by offset
+//! through an index of restart points built when the image is opened; a
+//! read decompresses from the nearest point before it.
+//!
+//! - gzip: a first pass inflates the file once and keeps the inflate state
+//!   every `GZIP_RESTART_SPACING` of output (as zran/gzindex do) and at every
+//!   member start. Member CRCs are checked on the way.
+//! - zstd: every frame is a restart point. The seek table of the seekable
+//!   format lists them without reading the frames; otherwise the frame
+//!   headers are walked and frames without a content size are decompressed
+//!   once to size them.
+//! - xz: every block is a restart point, from the index of each stream.
+//!
+//! A zstd frame or xz block over `MAX_RESTART_SPAN` (plain `zstd`, `xz`
+//! without `-T`) leaves too few points for random reads: `stream_reader`
+//! then decompresses the file front to back and it is scanned like stdin.
+//! The index is kept in memory (gzip: about 1.3 MB per GB) and rebuilt on
+//! every open; the last decompressed spans are cached.
+
+use crate::error::{RecoveryError, Result};
+use flate2::Crc;
+use memmap2::Mmap;
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZFlush, MZStatus};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B, 0x08];
+const ZSTD_MAGIC: u32 = 0xFD2F_B528;
+const XZ_MAGIC: &[u8] = &[0xFD, b'7', b'z', b'X', b'Z', 0x00];
+const XZ_FOOTER_MAGIC: &[u8] = b"YZ";
+/// Skippable frames: 0x184D2A50-0x184D2A5F
+const ZSTD_SKIPPABLE_MASK: u32 = 0xFFFF_FFF0;
+const ZSTD_SKIPPABLE: u32 = 0x184D_2A50;
+const ZSTD_SEEK_TABLE: u32 = 0x184D_2A5E;
+const ZSTD_SEEKABLE_MAGIC: u32 = 0x8F92_EAB1;
+
+/// Decompressed bytes between two gzip restart points
+const GZIP_RESTART_SPACING: u64 = 32 * 1024 * 1024;
+/// Largest zstd frame or xz block read at random (`xz -T0` blocks are 24 MiB)
+pub const MAX_RESTART_SPAN: u64 = 64 * 1024 * 1024;
+/// Decompressed spans kept for the next reads: the scan reads a span chunk by chunk
+const SPAN_CACHE_BYTES: usize = 256 * 1024 * 1024;
+/// Output buffer of the gzip first pass
+const INFLATE_BUFFER: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    /// Compression of a file starting with `signature`
+    pub fn detect(signature: &[u8]) -> Option<Self> {
+        if signature.starts_with(GZIP_MAGIC) {
+            Some(Self::Gzip)
+        } else if signature.get(..4) == Some(&ZSTD_MAGIC.to_le_bytes()[..]) {
+            Some(Self::Zstd)
+        } else if signature.starts_with(XZ_MAGIC) {
+            Some(Self::Xz)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Xz => "xz",
+        }
+    }
+}
+
+/// Where decompression can start
+enum Resume {
+    /// Inflate state inside a gzip member (fresh at the member's deflate data)
+    Inflate(Box<InflateState>),
+    ZstdFrame,
+    /// xz block, decoded inside a one-block stream with the original flags
+    XzBlock { flags: [u8; 2], unpadded: u64 },
+}
+
+struct RestartPoint {
+    /// Offset in the decompressed image
+    offset: u64,
+    /// File offset of the compressed data
+    input: u64,
+    resume: Resume,
+}
+
+/// Compressed image opened for random access by decompressed offset
+pub struct CompressedImage {
+    data: Mmap,
+    compression: Compression,
+    size: u64,
+    points: Vec<RestartPoint>,
+    /// (restart point, decompressed span), most recently used last
+    cache: Mutex<Vec<(usize, Arc<Vec<u8>>)>>,
+}
+
+fn invalid(compression: Compression, what: String) -> RecoveryError {
+    RecoveryError::Parse(format!("{}: {}", compression.name(), what))
+}
+
+fn map(path: &Path) -> Result<Mmap> {
+    let file = File::open(path)?;
+    unsafe { Mmap::map(&file) }.map_err(|e| RecoveryError::Mmap(format!("Failed to mmap file: {}", e)))
+}
+
+/// Decompressor reading `path` front to back, for a compressed image whose
+/// frames or blocks are too large for random reads; `None` for any other file
+pub fn stream_reader(path: &Path) -> Result<Option<(Compression, Box<dyn Read + Send>)>> {
+    // Missing files are reported by `DiskImage::open`
+    let Ok(mut file) = File::open(path) else {
+        return Ok(None);
+    };
+    let mut signature = [0u8; 6];
+    let read = file.read(&mut signature)?;
+    let Some(compression) = Compression::detect(&signature[..read]) else {
+        return Ok(None);
+    };
+    if oversized_unit(&map(path)?, compression)?.is_none() {
+        return Ok(None);
+    }
+    let file = io::BufReader::new(File::open(path)?);
+    let reader: Box<dyn Read + Send> = match compression {
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(file)),
+    };
+    Ok(Some((compression, reader)))
+}
+
+/// Decompressed size of the first zstd frame or xz block over
+/// `MAX_RESTART_SPAN` (compressed size for zstd frames without a content size)
+fn oversized_unit(data: &[u8], compression: Compression) -> Result<Option<u64>> {
+    let spans: Vec<u64> = match compression {
+        Compression::Gzip => Vec::new(),
+        Compression::Zstd => match zstd_seek_table(data) {
+            Some(frames) => frames.iter().map(|frame| frame.size.unwrap_or(0)).collect(),
+            None => zstd_frames(data)?.iter().map(|frame| frame.size.unwrap_or(frame.len)).collect(),
+        },
+        Compression::Xz => xz_blocks(data)?.iter().map(|block| block.uncompressed).collect(),
+    };
+    Ok(spans.into_iter().find(|&span| span > MAX_RESTART_SPAN))
+}
+
+impl CompressedImage {
+    pub fn open(path: &Path, compression: Compression) -> Result<Self> {
+        let data = map(path)?;
+        info!("Indexing {} image {}", compression.name(), path.display());
+        if let Some(span) = oversized_unit(&data, compression)? {
+            return Err(invalid(
+                compression,
+                format!(
+                    "a {} MB {} has no restart points for random reads; the scan reads such images as a stream",
+                    span / (1024 * 1024),
+                    if compression == Compression::Xz { "block" } else { "frame" }
+                ),
+            ));
+        }
+        let (size, points) = match compression {
+            Compression::Gzip => index_gzip(&data, GZIP_RESTART_SPACING)?,
+            Compression::Zstd => index_zstd(&data)?,
+            Compression::Xz => index_xz(&data)?,
+        };
+        Ok(Self { data, compression, size, points, cache: Mutex::new(Vec::new()) })
+    }
+
+    /// Decompressed size
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn restart_points(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        self.read_range(offset, buf, false).map(|_| ())
+    }
+
+    /// Like `read_at`, but spans failing to decompress are zero-filled.
+    /// Returns the number of zero-filled bytes.
+    pub fn read_tolerant(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_range(offset, buf, true)
+    }
+
+    fn read_range(&self, offset: u64, buf: &mut [u8], tolerant: bool) -> io::Result<usize> {
+        if offset.saturating_add(buf.len() as u64) > self.size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let mut done = 0;
+        let mut unreadable = 0;
+        while done < buf.len() {
+            let position = offset + done as u64;
+            let index = self.points.partition_point(|point| point.offset <= position) - 1;
+            let span_end = self.points.get(index + 1).map_or(self.size, |next| next.offset);
+            let len = ((span_end - position) as usize).min(buf.len() - done);
+            let out = &mut buf[done..done + len];
+            let skip = position - self.points[index].offset;
+            // A span failing to decompress may still be readable up to the damage
+            let read = match self.span(index) {
+                Ok(span) => {
+                    out.copy_from_slice(&span[skip as usize..skip as usize + len]);
+                    Ok(())
+                }
+                Err(_) => self.decode(index, skip, out),
+            };
+            if let Err(e) = read {
+                if !tolerant {
+                    return Err(e);
+                }
+                out.fill(0);
+                unreadable += len;
+            }
+            done += len;
+        }
+        Ok(unreadable)
+    }
+
+    /// Decompressed bytes from restart point `index` to the next, cached
+    fn span(&self, index: usize) -> io::Result<Arc<Vec<u8>>> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(at) = cache.iter().position(|&(cached, _)| cached == index) {
+            let entry = cache.remove(at);
+            let span = Arc::clone(&entry.1);
+            cache.push(entry);
+            return Ok(span);
+        }
+        drop(cache);
+
+        let end = self.points.get(index + 1).map_or(self.size, |next| next.offset);
+        let mut span = vec![0u8; (end - self.points[index].offset) as usize];
+        self.decode(index, 0, &mut span)?;
+        let span = Arc::new(span);
+
+        let mut cache = self.cache.lock().unwrap();
+        // Another thread may have decompressed it meanwhile
+        if !cache.iter().any(|&(cached, _)| cached == index) {
+            cache.push((index, Arc::clone(&span)));
+        }
+        while cache.len() > 1 && cache.iter().map(|(_, cached)| cached.len()).sum::<usize>() > SPAN_CACHE_BYTES {
+            cache.remove(0);
+        }
+        Ok(span)
+    }
+
+    /// Fill `out` with the bytes `skip` past restart point `index`
+    fn decode(&self, index: usize, skip: u64, out: &mut [u8]) -> io::Result<()> {
+        let point = &self.points[index];
+        let input = &self.data[point.input as usize..];
+        let span = self.points.get(index + 1).map_or(self.size, |next| next.offset) - point.offset;
+        let mut reader: Box<dyn Read + '_> = match &point.resume {
+            Resume::Inflate(state) => Box::new(Inflater { state: state.clone(), input }),
+            Resume::ZstdFrame => Box::new(zstd::stream::read::Decoder::with_buffer(input)?.single_frame()),
+            Resume::XzBlock { flags, unpadded } => {
+                let block = input.get(..padded(*unpadded) as usize).ok_or(io::ErrorKind::UnexpectedEof)?;
+                let (header, trailer) = xz_one_block_stream(*flags, *unpadded, span);
+                Box::new(xz2::read::XzDecoder::new(io::Cursor::new(header).chain(block).chain(io::Cursor::new(trailer))))
+            }
+        };
+        if io::copy(&mut reader.by_ref().take(skip), &mut io::sink())? < skip {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        reader.read_exact(out)
+    }
+}
+
+/// Raw deflate reader resuming from a saved inflate state
+struct Inflater<'a> {
+    state: Box<InflateState>,
+    input: &'a [u8],
+}
+
+impl Read for Inflater<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let result = inflate(&mut self.state, self.input, buf, MZFlush::None);
+        self.input = &self.input[result.bytes_consumed..];
+        match result.status {
+            Ok(_) if result.bytes_written > 0 => Ok(result.bytes_written),
+            Ok(MZStatus::StreamEnd) => Ok(0),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "corrupt or truncated deflate data")),
+        }
+    }
+}
+
+/// Length of the gzip member header at the start of `data`
+fn gzip_header_len(data: &[u8]) -> Option<usize> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let flags = *data.get(3)?;
+    let mut len = 10;
+    if flags & FEXTRA != 0 {
+        len += 2 + u16::from_le_bytes([*data.get(len)?, *data.get(len + 1)?]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            len += data.get(len..)?.iter().position(|&b| b == 0)? + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        len += 2;
+    }
+    (len <= data.len()).then_some(len)
+}
+
+/// First pass over a gzip file: inflate state every `spacing` bytes and at member starts
+fn index_gzip(data: &[u8], spacing: u64) -> Result<(u64, Vec<RestartPoint>)> {
+    let corrupt = |pos: usize| invalid(Compression::Gzip, format!("corrupt or truncated data near 0x{:X}", pos));
+    let mut points = Vec::new();
+    // Restart points fall between inflate calls
+    let mut output = vec![0u8; INFLATE_BUFFER.min(spacing as usize)];
+    let mut offset = 0u64;
+    let mut pos = 0usize;
+    // Members follow each other; zero padding or other trailing bytes end the image
+    while data[pos..].starts_with(GZIP_MAGIC) {
+        pos += gzip_header_len(&data[pos..]).ok_or_else(|| corrupt(pos))?;
+        let member_start = offset;
+        let mut state = InflateState::new_boxed(DataFormat::Raw);
+        points.push(RestartPoint { offset, input: pos as u64, resume: Resume::Inflate(state.clone()) });
+        let mut last_point = offset;
+        let mut crc = Crc::new();
+        loop {
+            let result = inflate(&mut state, &data[pos..], &mut output, MZFlush::None);
+            pos += result.bytes_consumed;
+            offset += result.bytes_written as u64;
+            crc.update(&output[..result.bytes_written]);
+            match result.status {
+                Ok(MZStatus::StreamEnd) => break,
+                Ok(_) if result.bytes_consumed > 0 || result.bytes_written > 0 => {}
+                _ => return Err(corrupt(pos)),
+            }
+            if offset - last_point >= spacing {
+                points.push(RestartPoint { offset, input: pos as u64, resume: Resume::Inflate(state.clone()) });
+                last_point = offset;
+            }
+        }
+
+        // CRC32 and size mod 2^32 of the member
+        let trailer = data.get(pos..pos + 8).ok_or_else(|| corrupt(pos))?;
+        let stored_crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let stored_size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if stored_crc != crc.sum() || stored_size != (offset - member_start) as u32 {
+            warn!("gzip: member at decompressed offset 0x{:X} fails its CRC check", member_start);
+        }
+        pos += 8;
+    }
+    if points.is_empty() {
+        return Err(corrupt(0));
+    }
+    Ok((offset, points))
+}
+
+fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+struct ZstdFrame {
+    input: u64,
+    /// Compressed length
+    len: u64,
+    /// Decompressed size, when the frame header or the seek table gives it
+    size: Option<u64>,
+}
+
+/// Frames listed by the seek table of the seekable format
+fn zstd_seek_table(data: &[u8]) -> Option<Vec<ZstdFrame>> {
+    let footer = data.len().checked_sub(9)?;
+    if le_u32(data, footer + 5)? != ZSTD_SEEKABLE_MAGIC {
+        return None;
+    }
+    let count = le_u32(data, footer)? as usize;
+    let entry_size = if data[footer + 4] & 0x80 != 0 { 12 } else { 8 };
+    let table = footer.checked_sub(count.checked_mul(entry_size)?)?;
+    let frame = table.checked_sub(8)?;
+    if le_u32(data, frame)? != ZSTD_SEEK_TABLE || le_u32(data, frame + 4)? as usize != count * entry_size + 9 {
+        return None;
+    }
+
+    let mut input = 0;
+    let mut frames = Vec::with_capacity(count);
+    for entry in data[table..footer].chunks_exact(entry_size) {
+        let len = le_u32(entry, 0)? as u64;
+        frames.push(ZstdFrame { input, len, size: Some(le_u32(entry, 4)? as u64) });
+        input += len;
+    }
+    (input <= frame as u64).then_some(frames)
+}
+
+/// Frames found by walking the frame and block headers
+fn zstd_frames(data: &[u8]) -> Result<Vec<ZstdFrame>> {
+    let corrupt = |pos: usize| invalid(Compression::Zstd, format!("corrupt or truncated frame at 0x{:X}", pos));
+    let mut frames = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let magic = le_u32(data, pos).ok_or_else(|| corrupt(pos))?;
+        if magic & ZSTD_SKIPPABLE_MASK == ZSTD_SKIPPABLE {
+            pos += 8 + le_u32(data, pos + 4).ok_or_else(|| corrupt(pos))? as usize;
+            continue;
+        }
+        if magic != ZSTD_MAGIC {
+            return Err(corrupt(pos));
+        }
+
+        let descriptor = *data.get(pos + 4).ok_or_else(|| corrupt(pos))?;
+        let single_segment = descriptor & 0x20 != 0;
+        let dictionary_len = [0, 1, 2, 4][(descriptor & 0x03) as usize];
+        let size_len = match descriptor >> 6 {
+            0 if single_segment => 1,
+            0 => 0,
+            1 => 2,
+            2 => 4,
+            _ => 8,
+        };
+        let size_at = pos + 5 + usize::from(!single_segment) + dictionary_len;
+        let size_bytes = data.get(size_at..size_at + size_len).ok_or_else(|| corrupt(pos))?;
+        let mut size = size_bytes.iter().rev().fold(0u64, |value, &b| value << 8 | b as u64);
+        if size_len == 2 {
+            size += 256;
+        }
+
+        let mut block = size_at + size_len;
+        loop {
+            let header = data.get(block..block + 3).ok_or_else(|| corrupt(pos))?;
+            let header = u32::from_le_bytes([header[0], header[1], header[2], 0]);
+            block += 3 + match (header >> 1) & 0x03 {
+                0 | 2 => (header >> 3) as usize,
+                1 => 1,
+                _ => return Err(corrupt(pos)),
+            };
+            if header & 1 != 0 {
+                break;
+            }
+        }
+        if descriptor & 0x04 != 0 {
+            block += 4;
+        }
+        if block > data.len() {
+            return Err(corrupt(pos));
+        }
+        frames.push(ZstdFrame { input: pos as u64, len: (block - pos) as u64, size: (size_len > 0).then_some(size) });
+        pos = block;
+    }
+    Ok(frames)
+}
+
+fn index_zstd(data: &[u8]) -> Result<(u64, Vec<RestartPoint>)> {
+    let frames = match zstd_seek_table(data) {
+        Some(frames) => frames,
+        None => zstd_frames(data)?,
+    };
+    let mut offset = 0;
+    let mut points = Vec::with_capacity(frames.len());
+    for frame in frames {
+        let size = match frame.size {
+            Some(size) => size,
+            None => {
+                let input = &data[frame.input as usize..(frame.input + frame.len) as usize];
+                let mut decoder = zstd::stream::read::Decoder::with_buffer(input)?.single_frame();
+                io::copy(&mut decoder, &mut io::sink())?
+            }
+        };
+        points.push(RestartPoint { offset, input: frame.input, resume: Resume::ZstdFrame });
+        offset += size;
+    }
+    if points.is_empty() {
+        return Err(invalid(Compression::Zstd, "no frames".to_string()));
+    }
+    Ok((offset, points))
+}
+
+struct XzBlock {
+    input: u64,
+    flags: [u8; 2],
+    unpadded: u64,
+    uncompressed: u64,
+}
+
+/// Blocks are padded to a multiple of 4 bytes
+fn padded(len: u64) -> u64 {
+    len.div_ceil(4) * 4
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..63).step_by(7) {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn crc32(data: &[u8]) -> [u8; 4] {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum().to_le_bytes()
+}
+
+/// Blocks of every stream, from the stream footers and indexes at the end
+fn xz_blocks(data: &[u8]) -> Result<Vec<XzBlock>> {
+    let corrupt = |pos: usize| invalid(Compression::Xz, format!("corrupt stream index before 0x{:X}", pos));
+    let mut streams = Vec::new();
+    let mut end = data.len();
+    while end > 0 {
+        // Stream padding
+        while end >= 4 && data[end - 4..end] == [0; 4] {
+            end -= 4;
+        }
+        let footer = end.checked_sub(12).ok_or_else(|| corrupt(end))?;
+        if &data[footer + 10..end] != XZ_FOOTER_MAGIC {
+            return Err(corrupt(end));
+        }
+        let index_len = (le_u32(data, footer + 4).unwrap() as usize + 1) * 4;
+        let flags = [data[footer + 8], data[footer + 9]];
+        let index_start = footer.checked_sub(index_len).ok_or_else(|| corrupt(end))?;
+
+        let index = &data[index_start..footer];
+        let mut pos = 1;
+        let count = read_varint(index, &mut pos).filter(|_| index[0] == 0).ok_or_else(|| corrupt(end))?;
+        let mut records = Vec::new();
+        for _ in 0..count {
+            let unpadded = read_varint(index, &mut pos).ok_or_else(|| corrupt(end))?;
+            let uncompressed = read_varint(index, &mut pos).ok_or_else(|| corrupt(end))?;
+            records.push((unpadded, uncompressed));
+        }
+
+        let blocks_len: u64 = records.iter().map(|&(unpadded, _)| padded(unpadded)).sum();
+        let stream_start = (index_start as u64)
+            .checked_sub(blocks_len + 12)
+            .filter(|&start| data[start as usize..].starts_with(XZ_MAGIC) && data[start as usize + 6..][..2] == flags)
+            .ok_or_else(|| corrupt(end))?;
+        let mut input = stream_start + 12;
+        let mut blocks = Vec::new();
+        for (unpadded, uncompressed) in records {
+            blocks.push(XzBlock { input, flags, unpadded, uncompressed });
+            input += padded(unpadded);
+        }
+        streams.push(blocks);
+        end = stream_start as usize;
+    }
+    Ok(streams.into_iter().rev().flatten().collect())
+}
+
+fn index_xz(data: &[u8]) -> Result<(u64, Vec<RestartPoint>)> {
+    let mut offset = 0;
+    let mut points = Vec::new();
+    for block in xz_blocks(data)? {
+        points.push(RestartPoint {
+            offset,
+            input: block.input,
+            resume: Resume::XzBlock { flags: block.flags, unpadded: block.unpadded },
+        });
+        offset += block.uncompressed;
+    }
+    if points.is_empty() {
+        return Err(invalid(Compression::Xz, "no blocks".to_string()));
+    }
+    Ok((offset, points))
+}
+
+/// Stream header, and index + footer, around one block so that liblzma decodes it alone
+fn xz_one_block_stream(flags: [u8; 2], unpadded: u64, uncompressed: u64) -> (Vec<u8>, Vec<u8>) {
+    let mut header = XZ_MAGIC.to_vec();
+    header.extend_from_slice(&flags);
+    header.extend_from_slice(&crc32(&flags));
+
+    let mut index = vec![0u8];
+    write_varint(&mut index, 1);
+    write_varint(&mut index, unpadded);
+    write_varint(&mut index, uncompressed);
+    index.resize(padded(index.len() as u64) as usize, 0);
+    let index_crc = crc32(&index);
+    index.extend_from_slice(&index_crc);
+
+    let mut footer = ((index.len() / 4 - 1) as u32).to_le_bytes().to_vec();
+    footer.extend_from_slice(&flags);
+    let mut trailer = index;
+    trailer.extend_from_slice(&crc32(&footer));
+    trailer.extend_from_slice(&footer);
+    trailer.extend_from_slice(XZ_FOOTER_MAGIC);
+    (header, trailer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Reads at every restart point edge match the plain image
+    fn assert_reads(image: &CompressedImage, plain: &[u8]) {
+        assert_eq!(image.size(), plain.len() as u64);
+        for &(offset, len) in &[(0, 100), (4000, 9000), (plain.len() - 50, 50), (0, plain.len())] {
+            let mut buf = vec![0u8; len];
+            image.read_at(offset as u64, &mut buf).unwrap();
+            assert_eq!(buf, plain[offset..offset + len], "{:?} at {}", image.compression(), offset);
+        }
+        assert!(image.read_at(plain.len() as u64 - 10, &mut [0u8; 20]).is_err());
+    }
+
+    fn write_temp(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("rust_recovery_{}_{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_compressed_random_access() {
+        let plain: Vec<u8> = (0..60_000u32).flat_map(|i| (i / 7).to_le_bytes()).collect();
+        let (first, second) = plain.split_at(100_000);
+
+        // gzip: two members, restart points every 16 KiB
+        let mut gzip = Vec::new();
+        for part in [first, second] {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(part).unwrap();
+            gzip.extend(encoder.finish().unwrap());
+        }
+        let path = write_temp("image.gz", &gzip);
+        let data = map(&path).unwrap();
+        let (size, points) = index_gzip(&data, 16 * 1024).unwrap();
+        assert!(points.len() > 10 && points.iter().any(|point| point.offset == first.len() as u64));
+        let image = CompressedImage { data, compression: Compression::Gzip, size, points, cache: Mutex::new(Vec::new()) };
+        assert_reads(&image, &plain);
+        assert!(stream_reader(&path).unwrap().is_none());
+
+        // zstd: two frames, the second without a content size, then a seek table
+        let mut zstd = zstd::bulk::compress(first, 3).unwrap();
+        let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 3).unwrap();
+        encoder.write_all(second).unwrap();
+        let second_frame = encoder.finish().unwrap();
+        let first_len = zstd.len();
+        zstd.extend(&second_frame);
+        let image = CompressedImage::open(&write_temp("image.zst", &zstd), Compression::Zstd).unwrap();
+        assert_eq!(image.restart_points(), 2);
+        assert_reads(&image, &plain);
+
+        let mut seekable = zstd.clone();
+        seekable.extend(ZSTD_SEEK_TABLE.to_le_bytes());
+        seekable.extend(25u32.to_le_bytes());
+        for (len, size) in [(first_len, first.len()), (second_frame.len(), second.len())] {
+            seekable.extend((len as u32).to_le_bytes());
+            seekable.extend((size as u32).to_le_bytes());
+        }
+        seekable.extend(2u32.to_le_bytes());
+        seekable.push(0);
+        seekable.extend(ZSTD_SEEKABLE_MAGIC.to_le_bytes());
+        assert_eq!(zstd_seek_table(&seekable).unwrap().len(), 2);
+        assert_reads(&CompressedImage::open(&write_temp("seekable.zst", &seekable), Compression::Zstd).unwrap(), &plain);
+
+        // xz: two concatenated streams with stream padding between them
+        let mut xz = Vec::new();
+        for part in [first, second] {
+            let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 1);
+            encoder.write_all(part).unwrap();
+            xz.extend(encoder.finish().unwrap());
+            xz.extend([0u8; 4]);
+        }
+        let path = write_temp("image.xz", &xz);
+        let image = CompressedImage::open(&path, Compression::Xz).unwrap();
+        assert_eq!(image.restart_points(), 2);
+        assert_reads(&image, &plain);
+
+        for name in ["image.gz", "image.zst", "seekable.zst", "image.xz"] {
+            let _ = std::fs::remove_file(std::env::temp_dir().join(format!("rust_recovery_{}_{}", std::process::id(), name)));
+        }
+    }
+}
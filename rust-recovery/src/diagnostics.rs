@@ -0,0 +1,164 @@
+//! Scan diagnostics: data that was not scanned or not recovered, by cause
+//!
+//! The scanner and the assembly stage record every region they give up on.
+//! The report turns the totals into one row per cause with the flag (or
+//! procedure) that recovers that class of data on the next run.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Why a region was skipped or its data left out of the recovered files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticCause {
+    /// Chunk looked compressed or encrypted and was not carved
+    HighEntropy,
+    /// Unreadable device blocks, zero-filled
+    BadSectors,
+    /// Outside the scanned range
+    ExcludedRange,
+    /// Windows the watchdog abandoned twice
+    StalledWindows,
+    /// Fragments left over once the stream limit was reached
+    OverQuotaStreams,
+    /// Assembled files below --target-size-min (or above --target-size-max after cleaning)
+    OutsideSizeRange,
+    /// Chunks skipped from the dashboard
+    OperatorSkipped,
+    /// Fragments excluded in the dashboard fragment table
+    OperatorExcluded,
+    /// Chunks not scanned because the run was cancelled
+    Cancelled,
+    /// Chunks not scanned because --max-duration ran out
+    TimeBudget,
+}
+
+impl DiagnosticCause {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::HighEntropy => "high-entropy (compressed or encrypted) data, not carved",
+            Self::BadSectors => "unreadable sectors, zero-filled",
+            Self::ExcludedRange => "outside the scanned range",
+            Self::StalledWindows => "windows that stalled twice, left unscanned",
+            Self::OverQuotaStreams => "fragments beyond the stream limit, not assembled",
+            Self::OutsideSizeRange => "assembled files outside the target size range, not saved",
+            Self::OperatorSkipped => "chunks skipped from the dashboard",
+            Self::OperatorExcluded => "fragments excluded from the dashboard, not assembled",
+            Self::Cancelled => "not scanned, run cancelled",
+            Self::TimeBudget => "not scanned, --max-duration reached",
+        }
+    }
+
+    /// Flag or procedure that recovers this class of data on the next run
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            Self::HighEntropy => "--metadata-first (locate files by filesystem metadata instead of content)",
+            Self::BadSectors => "image the device with ddrescue (retry passes) and scan the image",
+            Self::ExcludedRange => "rescan the full image",
+            Self::StalledWindows => "--stall-timeout with a larger value, or 0 to disable the watchdog",
+            Self::OverQuotaStreams => "--max-streams with a larger value",
+            Self::OutsideSizeRange => "--target-size-min / --target-size-max with a wider range",
+            Self::OperatorSkipped => "rescan without skipping chunks",
+            Self::OperatorExcluded => "rescan without excluding fragments in the fragment table",
+            Self::Cancelled => "--resume (continues from the scan checkpoint)",
+            Self::TimeBudget => "--resume (continues from the scan checkpoint), or a larger --max-duration",
+        }
+    }
+}
+
+/// One report row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticEntry {
+    pub cause: DiagnosticCause,
+    pub bytes: u64,
+    /// Number of separate regions (chunks, windows, fragments) recorded
+    pub regions: u64,
+    pub description: String,
+    pub suggestion: String,
+}
+
+impl DiagnosticEntry {
+    pub fn size_mb(&self) -> f64 {
+        self.bytes as f64 / 1024.0 / 1024.0
+    }
+
+    /// One-line summary for the console and `failure_reasons`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} bytes in {} region(s): {}; try {}",
+            self.bytes, self.regions, self.description, self.suggestion
+        )
+    }
+}
+
+/// Per-cause byte and region counters
+#[derive(Debug, Clone, Default)]
+pub struct ScanDiagnostics {
+    totals: BTreeMap<DiagnosticCause, (u64, u64)>,
+}
+
+impl ScanDiagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one region; zero-byte regions are ignored
+    pub fn record(&mut self, cause: DiagnosticCause, bytes: u64) {
+        if bytes == 0 {
+            return;
+        }
+        let (total, regions) = self.totals.entry(cause).or_default();
+        *total = total.saturating_add(bytes);
+        *regions += 1;
+    }
+
+    pub fn bytes(&self, cause: DiagnosticCause) -> u64 {
+        self.totals.get(&cause).map_or(0, |&(bytes, _)| bytes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.totals.is_empty()
+    }
+
+    /// Report rows, largest byte count first
+    pub fn entries(&self) -> Vec<DiagnosticEntry> {
+        let mut entries: Vec<DiagnosticEntry> = self
+            .totals
+            .iter()
+            .map(|(&cause, &(bytes, regions))| DiagnosticEntry {
+                cause,
+                bytes,
+                regions,
+                description: cause.description().to_string(),
+                suggestion: cause.suggestion().to_string(),
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.bytes));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_grouped_by_cause() {
+        let mut diagnostics = ScanDiagnostics::new();
+        diagnostics.record(DiagnosticCause::BadSectors, 4096);
+        diagnostics.record(DiagnosticCause::HighEntropy, 1 << 20);
+        diagnostics.record(DiagnosticCause::BadSectors, 512);
+        diagnostics.record(DiagnosticCause::ExcludedRange, 0);
+
+        let entries = diagnostics.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].cause, DiagnosticCause::HighEntropy);
+        assert_eq!(entries[1].bytes, 4608);
+        assert_eq!(entries[1].regions, 2);
+        assert!(entries[1].summary().contains("ddrescue"));
+        assert_eq!(diagnostics.bytes(DiagnosticCause::ExcludedRange), 0);
+
+        let json = serde_json::to_value(&entries[0]).unwrap();
+        assert_eq!(json["cause"], "high_entropy");
+    }
+}
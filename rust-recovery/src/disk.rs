@@ -1,21 +1,38 @@
 use crate::error::{RecoveryError, Result};
+use crate::ewf::{self, EwfImage};
+use crate::pipe::PipeImage;
+use crate::qcow2::{Qcow2Image, QCOW2_MAGIC};
+use crate::compressed::{CompressedImage, Compression};
+use crate::vmdk::{VmdkImage, VMDK_DESCRIPTOR_MAGIC, VMDK_SPARSE_MAGIC};
+use crate::windows_device::{self, WindowsDevice};
 use crate::types::{Offset, Size};
 use memmap2::Mmap;
+use std::borrow::Cow;
 use std::fs::File;
-use std::path::Path;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-/// A zero-copy slice of disk image data with lifetime tied to the parent DiskImage
+/// Retry granularity when a device read fails (unreadable blocks are zero-filled)
+pub(crate) const DEVICE_RETRY_BLOCK: usize = 64 * 1024;
+
+/// A slice of disk image data: zero-copy for mapped images, owned for block devices
 #[derive(Debug)]
 pub struct FragmentSlice<'a> {
     pub offset: Offset,
-    pub data: &'a [u8],
+    pub data: Cow<'a, [u8]>,
 }
 
 impl<'a> FragmentSlice<'a> {
     /// Create a new fragment slice
     pub fn new(offset: Offset, data: &'a [u8]) -> Self {
-        Self { offset, data }
+        Self { offset, data: Cow::Borrowed(data) }
+    }
+
+    /// Create a fragment slice from bytes read into a buffer
+    pub fn owned(offset: Offset, data: Vec<u8>) -> Self {
+        Self { offset, data: Cow::Owned(data) }
     }
 
     /// Get the size of this fragment
@@ -24,23 +41,86 @@ impl<'a> FragmentSlice<'a> {
     }
 }
 
-/// Zero-copy memory-mapped disk image with shared ownership
+/// Storage behind a `DiskImage`
+#[derive(Clone)]
+enum Backend {
+    /// Regular image file, memory mapped
+    Mapped(Arc<Mmap>),
+    /// Block device (/dev/sdX, /dev/nvme0n1): metadata reports size 0 and
+    /// mmap is unreliable on failing media, so data is read with pread.
+    /// Also image files opened with `open_unmapped`
+    Device(Arc<File>),
+    /// Windows raw device (`\\.\PhysicalDrive2`): sector-aligned reads
+    WindowsDevice(Arc<WindowsDevice>),
+    /// EWF (E01) segment set, chunks decompressed on every read
+    Ewf(Arc<EwfImage>),
+    /// QCOW2 virtual disk, guest offsets translated through the L1/L2 tables
+    Qcow2(Arc<Qcow2Image>),
+    /// VMDK virtual disk (sparse, stream-optimized or descriptor + extents)
+    Vmdk(Arc<VmdkImage>),
+    /// Image read once from a pipe; its size grows as it is read
+    Pipe(Arc<PipeImage>),
+    /// gzip/zstd/xz image file, decompressed from the nearest restart point
+    Compressed(Arc<CompressedImage>),
+}
+
+/// Disk image with shared ownership: memory-mapped file, raw block device,
+/// E01, virtual disk (QCOW2, VMDK) addressed by guest offset, compressed
+/// image addressed by decompressed offset or pipe
 #[derive(Clone)]
 pub struct DiskImage {
-    mmap: Arc<Mmap>,
+    backend: Backend,
     size: Size,
     path: String,
 }
 
 impl DiskImage {
-    /// Open a disk image file with memory mapping
+    /// Open a disk image file (memory mapped), a block device or Windows
+    /// `\\.\` device (chunked reads), the first segment of an EWF image, a
+    /// QCOW2/VMDK virtual disk or a gzip/zstd/xz image file (translated and
+    /// decompressed reads)
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path_ref = path.as_ref();
+        Self::open_with(path.as_ref(), true)
+    }
+
+    /// Like `open`, but a raw image file is read with pread instead of being
+    /// mapped: for images larger than RAM or the address space (`--stream`)
+    pub fn open_unmapped<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with(path.as_ref(), false)
+    }
+
+    /// Image streamed from `reader` (`-` reads stdin, or a decompressor):
+    /// read forward only, fragment bytes kept for assembly are spilled to `spill_path`
+    pub fn open_pipe(reader: impl Read + Send + 'static, path: &str, spill_path: PathBuf) -> Result<Self> {
+        Ok(Self {
+            backend: Backend::Pipe(Arc::new(PipeImage::new(reader, spill_path)?)),
+            size: Size::new(0),
+            path: path.to_string(),
+        })
+    }
+
+    fn open_with(path_ref: &Path, map: bool) -> Result<Self> {
         let path_str = path_ref
             .to_str()
             .ok_or_else(|| RecoveryError::InvalidArgument("Invalid path encoding".to_string()))?
             .to_string();
 
+        if cfg!(windows) && windows_device::is_device_path(path_ref) {
+            let device = WindowsDevice::open(path_ref).map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => RecoveryError::FileNotFound(path_str.clone()),
+                io::ErrorKind::PermissionDenied => RecoveryError::InvalidArgument(format!(
+                    "cannot open {}: raw devices need an elevated (Administrator) prompt",
+                    path_str
+                )),
+                _ => RecoveryError::Io(e),
+            })?;
+            return Ok(Self {
+                size: Size::new(device.size()),
+                backend: Backend::WindowsDevice(Arc::new(device)),
+                path: path_str,
+            });
+        }
+
         // Open the file
         let file = File::open(path_ref).map_err(|e| {
             if e.kind() == std::io::ErrorKind::NotFound {
@@ -50,10 +130,56 @@ impl DiskImage {
             }
         })?;
 
+        if is_block_device(&file)? {
+            let size = Size::new(device_size(&file)?);
+            return Ok(Self {
+                backend: Backend::Device(Arc::new(file)),
+                size,
+                path: path_str,
+            });
+        }
+
         // Get file size
         let metadata = file.metadata()?;
         let size = Size::new(metadata.len());
 
+        let mut signature = [0u8; 21];
+        let signature_len = (size.as_u64() as usize).min(signature.len());
+        read_exact_at(&file, &mut signature[..signature_len], 0)?;
+        // Container formats are read by guest offset, not file offset
+        let translated = if ewf::is_ewf(&signature) {
+            let image = EwfImage::open(path_ref)?;
+            Some((image.size(), Backend::Ewf(Arc::new(image))))
+        } else if signature.starts_with(QCOW2_MAGIC) {
+            let image = Qcow2Image::open(path_ref)?;
+            Some((image.size(), Backend::Qcow2(Arc::new(image))))
+        } else if signature.starts_with(VMDK_SPARSE_MAGIC) || signature.starts_with(VMDK_DESCRIPTOR_MAGIC) {
+            let image = VmdkImage::open(path_ref)?;
+            Some((image.size(), Backend::Vmdk(Arc::new(image))))
+        } else if let Some(compression) = Compression::detect(&signature) {
+            let image = CompressedImage::open(path_ref, compression)?;
+            Some((image.size(), Backend::Compressed(Arc::new(image))))
+        } else if signature.starts_with(ewf::EWF2_SIGNATURE) {
+            return Err(RecoveryError::Parse("EWF2 (Ex01) images are not supported".to_string()));
+        } else {
+            None
+        };
+        if let Some((guest_size, backend)) = translated {
+            return Ok(Self {
+                backend,
+                size: Size::new(guest_size),
+                path: path_str,
+            });
+        }
+
+        if !map {
+            return Ok(Self {
+                backend: Backend::Device(Arc::new(file)),
+                size,
+                path: path_str,
+            });
+        }
+
         // Memory map the file
         let mmap = unsafe {
             Mmap::map(&file)
@@ -61,15 +187,18 @@ impl DiskImage {
         };
 
         Ok(Self {
-            mmap: Arc::new(mmap),
+            backend: Backend::Mapped(Arc::new(mmap)),
             size,
             path: path_str,
         })
     }
 
-    /// Get the total size of the disk image
+    /// Get the total size of the disk image (of a pipe: the bytes read so far)
     pub fn size(&self) -> Size {
-        self.size
+        match &self.backend {
+            Backend::Pipe(pipe) => Size::new(pipe.bytes_read()),
+            _ => self.size,
+        }
     }
 
     /// Get the path to the disk image
@@ -77,10 +206,52 @@ impl DiskImage {
         &self.path
     }
 
-    /// Get a zero-copy slice of the disk image with bounds checking
+    /// True if the image is a block or Windows raw device (or unmapped image file) read in chunks
+    pub fn is_device(&self) -> bool {
+        matches!(self.backend, Backend::Device(_) | Backend::WindowsDevice(_))
+    }
+
+    /// Container format read by guest offset ("E01", "QCOW2", "VMDK") or
+    /// compression ("gzip", "zstd", "xz"); `None` for raw images and devices
+    pub fn format(&self) -> Option<&'static str> {
+        match &self.backend {
+            Backend::Ewf(_) => Some("E01"),
+            Backend::Qcow2(_) => Some("QCOW2"),
+            Backend::Vmdk(_) => Some("VMDK"),
+            Backend::Compressed(image) => Some(image.compression().name()),
+            Backend::Mapped(_) | Backend::Device(_) | Backend::WindowsDevice(_) | Backend::Pipe(_) => None,
+        }
+    }
+
+    /// EWF image behind this disk, for hash verification
+    pub fn ewf(&self) -> Option<&EwfImage> {
+        match &self.backend {
+            Backend::Ewf(image) => Some(image),
+            _ => None,
+        }
+    }
+
+    /// Compressed image behind this disk, for its restart point count
+    pub fn compressed(&self) -> Option<&CompressedImage> {
+        match &self.backend {
+            Backend::Compressed(image) => Some(image),
+            _ => None,
+        }
+    }
+
+    /// Pipe behind this disk, read forward one window at a time
+    pub fn pipe(&self) -> Option<&PipeImage> {
+        match &self.backend {
+            Backend::Pipe(pipe) => Some(pipe),
+            _ => None,
+        }
+    }
+
+    /// Get a slice of the disk image with bounds checking
+    /// (zero-copy for mapped files, a fresh buffer for devices and E01)
     pub fn get_slice(&self, offset: Offset, len: usize) -> Result<FragmentSlice<'_>> {
         let offset_u64 = offset.as_u64();
-        let size_u64 = self.size.as_u64();
+        let size_u64 = self.size().as_u64();
 
         // Check if offset is valid
         if offset_u64 >= size_u64 {
@@ -107,20 +278,318 @@ impl DiskImage {
             });
         }
 
-        // Safe: bounds have been checked
-        let start = offset_u64 as usize;
-        let end = end_offset as usize;
-        let data = &self.mmap[start..end];
+        match &self.backend {
+            Backend::Mapped(mmap) => {
+                // Safe: bounds have been checked
+                let start = offset_u64 as usize;
+                let end = end_offset as usize;
+                Ok(FragmentSlice::new(offset, &mmap[start..end]))
+            }
+            Backend::Device(file) => {
+                let mut buffer = vec![0u8; len];
+                read_exact_at(file, &mut buffer, offset_u64)?;
+                Ok(FragmentSlice::owned(offset, buffer))
+            }
+            Backend::WindowsDevice(device) => {
+                let mut buffer = vec![0u8; len];
+                device.read_at(offset_u64, &mut buffer)?;
+                Ok(FragmentSlice::owned(offset, buffer))
+            }
+            Backend::Ewf(image) => {
+                let mut buffer = vec![0u8; len];
+                image.read_at(offset_u64, &mut buffer)?;
+                Ok(FragmentSlice::owned(offset, buffer))
+            }
+            Backend::Qcow2(image) => {
+                let mut buffer = vec![0u8; len];
+                image.read_at(offset_u64, &mut buffer)?;
+                Ok(FragmentSlice::owned(offset, buffer))
+            }
+            Backend::Vmdk(image) => {
+                let mut buffer = vec![0u8; len];
+                image.read_at(offset_u64, &mut buffer)?;
+                Ok(FragmentSlice::owned(offset, buffer))
+            }
+            Backend::Compressed(image) => {
+                let mut buffer = vec![0u8; len];
+                image.read_at(offset_u64, &mut buffer)?;
+                Ok(FragmentSlice::owned(offset, buffer))
+            }
+            Backend::Pipe(pipe) => {
+                let mut buffer = vec![0u8; len];
+                pipe.read_at(offset_u64, &mut buffer)?;
+                Ok(FragmentSlice::owned(offset, buffer))
+            }
+        }
+    }
 
-        Ok(FragmentSlice::new(offset, data))
+    /// Fill `buf` from `offset`, tolerating media errors: blocks that cannot be
+    /// read (or E01 chunks, QCOW2 clusters, VMDK grains and compressed spans
+    /// that fail to decompress) are zero-filled.
+    /// Returns the number of unreadable bytes.
+    pub fn read_tolerant(&self, offset: Offset, buf: &mut [u8]) -> Result<usize> {
+        let offset_u64 = offset.as_u64();
+        let end_offset = offset_u64.saturating_add(buf.len() as u64);
+        let image_size = self.size().as_u64();
+        if end_offset > image_size {
+            return Err(RecoveryError::InvalidSize {
+                offset: offset_u64,
+                size: buf.len() as u64,
+                image_size,
+            });
+        }
+
+        let file = match &self.backend {
+            Backend::Mapped(mmap) => {
+                buf.copy_from_slice(&mmap[offset_u64 as usize..end_offset as usize]);
+                return Ok(0);
+            }
+            Backend::Ewf(image) => return Ok(image.read_tolerant(offset_u64, buf)?),
+            Backend::Qcow2(image) => return Ok(image.read_tolerant(offset_u64, buf)?),
+            Backend::Vmdk(image) => return Ok(image.read_tolerant(offset_u64, buf)?),
+            Backend::Compressed(image) => return Ok(image.read_tolerant(offset_u64, buf)?),
+            Backend::Pipe(pipe) => return Ok(pipe.read_tolerant(offset_u64, buf)?),
+            Backend::WindowsDevice(device) => return Ok(device.read_tolerant(offset_u64, buf)?),
+            Backend::Device(file) => file,
+        };
+
+        if read_exact_at(file, buf, offset_u64).is_ok() {
+            return Ok(0);
+        }
+
+        // Slow path: re-read block by block, skipping bad ones
+        let mut unreadable = 0;
+        for (i, block) in buf.chunks_mut(DEVICE_RETRY_BLOCK).enumerate() {
+            let block_offset = offset_u64 + (i * DEVICE_RETRY_BLOCK) as u64;
+            if read_exact_at(file, block, block_offset).is_err() {
+                block.fill(0);
+                unreadable += block.len();
+            }
+        }
+        Ok(unreadable)
     }
 
-    /// Get the Arc-wrapped memory map for shared access
-    pub fn get_mmap(&self) -> Arc<Mmap> {
-        Arc::clone(&self.mmap)
+    /// Get the Arc-wrapped memory map for shared access (`None` unless a raw image file)
+    pub fn get_mmap(&self) -> Option<Arc<Mmap>> {
+        match &self.backend {
+            Backend::Mapped(mmap) => Some(Arc::clone(mmap)),
+            _ => None,
+        }
+    }
+}
+
+/// Size of an image: file length, or device capacity for block devices
+pub fn image_len(file: &File) -> io::Result<u64> {
+    if is_block_device(file)? {
+        device_size(file)
+    } else {
+        Ok(file.metadata()?.len())
     }
 }
 
+/// Check whether `output` lives on the block device `image` or on another
+/// partition of the same disk. Returns a description of the conflict; image
+/// files never conflict.
+#[cfg(unix)]
+pub fn output_conflict(image: &Path, output: &Path) -> io::Result<Option<String>> {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    let image_metadata = std::fs::metadata(image)?;
+    if !image_metadata.file_type().is_block_device() {
+        return Ok(None);
+    }
+    let scanned = image_metadata.rdev();
+    // The output directory may not exist yet: use its nearest existing ancestor
+    let output_device = existing_ancestor(output)?.1.dev();
+
+    let conflict = if output_device == scanned {
+        format!("{} is on the scanned device {}", output.display(), image.display())
+    } else if whole_disk(output_device) == whole_disk(scanned) {
+        format!("{} is on the same disk as the scanned device {}", output.display(), image.display())
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(conflict))
+}
+
+#[cfg(not(unix))]
+pub fn output_conflict(_image: &Path, _output: &Path) -> io::Result<Option<String>> {
+    Ok(None)
+}
+
+/// `path` or its nearest ancestor that exists (`.` for a relative path none
+/// of whose components exist), with its metadata
+#[cfg(unix)]
+fn existing_ancestor(path: &Path) -> io::Result<(&Path, std::fs::Metadata)> {
+    let mut existing = path;
+    loop {
+        match std::fs::metadata(existing) {
+            Ok(metadata) => return Ok((existing, metadata)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                existing = match existing.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent,
+                    _ => Path::new("."),
+                };
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Device number from major and minor (glibc dev_t encoding)
+#[cfg(target_os = "linux")]
+fn make_device(major: u64, minor: u64) -> u64 {
+    ((major & 0xfff) << 8) | ((major & 0xffff_f000) << 32) | (minor & 0xff) | ((minor & 0xffff_ff00) << 12)
+}
+
+/// Major and minor of device number `device` (glibc dev_t encoding)
+#[cfg(target_os = "linux")]
+fn split_device(device: u64) -> (u64, u64) {
+    let major = ((device >> 8) & 0xfff) | ((device >> 32) & 0xffff_f000);
+    let minor = (device & 0xff) | ((device >> 12) & 0xffff_ff00);
+    (major, minor)
+}
+
+/// `MAJ:MIN` as in a sysfs `dev` file
+#[cfg(target_os = "linux")]
+fn parse_device(text: &str) -> Option<u64> {
+    let (major, minor) = text.trim().split_once(':')?;
+    Some(make_device(major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Device number of the disk holding partition `device` (itself for whole disks)
+#[cfg(target_os = "linux")]
+fn whole_disk(device: u64) -> u64 {
+    whole_disk_in(Path::new("/sys"), device)
+}
+
+/// `whole_disk` against the sysfs mounted at `sysfs`
+#[cfg(target_os = "linux")]
+fn whole_disk_in(sysfs: &Path, device: u64) -> u64 {
+    // dev/block/MAJ:MIN links to .../sdb/sdb1 for partitions
+    let Some(node) = sysfs_node_in(sysfs, device) else {
+        return device;
+    };
+    if !node.join("partition").exists() {
+        return device;
+    }
+    node.parent()
+        .and_then(|disk| std::fs::read_to_string(disk.join("dev")).ok())
+        .and_then(|dev| parse_device(&dev))
+        .unwrap_or(device)
+}
+
+/// Partitions are not resolved outside Linux
+#[cfg(all(unix, not(target_os = "linux")))]
+fn whole_disk(device: u64) -> u64 {
+    device
+}
+
+/// Sysfs node of block device `device`, links resolved
+#[cfg(target_os = "linux")]
+fn sysfs_node(device: u64) -> Option<std::path::PathBuf> {
+    sysfs_node_in(Path::new("/sys"), device)
+}
+
+#[cfg(target_os = "linux")]
+fn sysfs_node_in(sysfs: &Path, device: u64) -> Option<std::path::PathBuf> {
+    let (major, minor) = split_device(device);
+    std::fs::canonicalize(sysfs.join(format!("dev/block/{}:{}", major, minor))).ok()
+}
+
+/// Network transport under block device `image` ("NBD", "iSCSI"); None for
+/// local disks and image files
+#[cfg(target_os = "linux")]
+pub fn network_transport(image: &Path) -> Option<&'static str> {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    let metadata = std::fs::metadata(image).ok()?;
+    if !metadata.file_type().is_block_device() {
+        return None;
+    }
+    network_transport_of(&sysfs_node(metadata.rdev())?)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn network_transport(_image: &Path) -> Option<&'static str> {
+    None
+}
+
+/// NBD devices are virtual (`/sys/devices/virtual/block/nbd0`); iSCSI disks
+/// hang off a session (`.../host3/session1/target3:0:0/3:0:0:0/block/sdc`)
+#[cfg(target_os = "linux")]
+fn network_transport_of(node: &Path) -> Option<&'static str> {
+    let node = node.to_string_lossy();
+    if node.contains("/virtual/block/nbd") {
+        Some("NBD")
+    } else if node.contains("/session") && node.contains("/target") {
+        Some("iSCSI")
+    } else {
+        None
+    }
+}
+
+#[cfg(unix)]
+fn is_block_device(file: &File) -> io::Result<bool> {
+    use std::os::unix::fs::FileTypeExt;
+    Ok(file.metadata()?.file_type().is_block_device())
+}
+
+#[cfg(not(unix))]
+fn is_block_device(_file: &File) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Device capacity in bytes via ioctl(BLKGETSIZE64)
+#[cfg(target_os = "linux")]
+fn device_size(file: &File) -> io::Result<u64> {
+    use std::os::unix::io::AsRawFd;
+
+    // _IOR(0x12, 114, size_t); libc does not export it
+    #[cfg(any(target_arch = "powerpc", target_arch = "powerpc64", target_arch = "mips", target_arch = "mips64", target_arch = "sparc64"))]
+    const BLKGETSIZE64: u64 = 0x4008_1272;
+    #[cfg(not(any(target_arch = "powerpc", target_arch = "powerpc64", target_arch = "mips", target_arch = "mips64", target_arch = "sparc64")))]
+    const BLKGETSIZE64: u64 = 0x8008_1272;
+
+    let mut size: u64 = 0;
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), BLKGETSIZE64 as _, &mut size as *mut u64) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(size)
+}
+
+/// Other platforms: seek to the end of the device
+#[cfg(not(target_os = "linux"))]
+fn device_size(file: &File) -> io::Result<u64> {
+    use std::io::{Seek, SeekFrom};
+    let mut handle = file;
+    let size = handle.seek(SeekFrom::End(0))?;
+    handle.seek(SeekFrom::Start(0))?;
+    Ok(size)
+}
+
+#[cfg(unix)]
+pub(crate) fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buf, offset)
+}
+
+#[cfg(windows)]
+pub(crate) fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,10 +601,95 @@ mod tests {
         let slice = FragmentSlice::new(offset, data);
 
         assert_eq!(slice.offset.as_u64(), 100);
-        assert_eq!(slice.data, b"test data");
+        assert_eq!(&*slice.data, b"test data");
         assert_eq!(slice.size().as_u64(), 9);
     }
 
+    #[test]
+    fn test_mapped_image_reads() {
+        let mut path = std::env::temp_dir();
+        let unique = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        path.push(format!("rust_recovery_disk_{unique}.img"));
+        std::fs::write(&path, b"0123456789abcdef").unwrap();
+
+        let disk = DiskImage::open(&path).unwrap();
+        assert!(!disk.is_device());
+        assert_eq!(disk.size().as_u64(), 16);
+        assert_eq!(&*disk.get_slice(Offset::new(4), 4).unwrap().data, b"4567");
+
+        let mut buf = [0u8; 6];
+        assert_eq!(disk.read_tolerant(Offset::new(10), &mut buf).unwrap(), 0);
+        assert_eq!(&buf, b"abcdef");
+        assert!(disk.read_tolerant(Offset::new(12), &mut buf).is_err());
+        assert_eq!(image_len(&File::open(&path).unwrap()).unwrap(), 16);
+
+        // Image files never conflict with the output directory
+        assert!(output_conflict(&path, &std::env::temp_dir()).unwrap().is_none());
+        assert_eq!(network_transport(&path), None);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_network_transport_from_sysfs_node() {
+        assert_eq!(network_transport_of(Path::new("/sys/devices/virtual/block/nbd0")), Some("NBD"));
+        let iscsi = "/sys/devices/platform/host3/session1/target3:0:0/3:0:0:0/block/sdc";
+        assert_eq!(network_transport_of(Path::new(iscsi)), Some("iSCSI"));
+        let sata = "/sys/devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0/block/sda";
+        assert_eq!(network_transport_of(Path::new(sata)), None);
+        assert_eq!(network_transport_of(Path::new("/sys/devices/virtual/block/loop0")), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_device_number_round_trip() {
+        for (major, minor) in [(8, 0), (8, 17), (259, 3), (4095, 255), (4096, 256), (0xfffff, 0xfffff)] {
+            let device = make_device(major, minor);
+            assert_eq!(device, libc::makedev(major as u32, minor as u32));
+            assert_eq!(split_device(device), (major, minor));
+        }
+        assert_eq!(parse_device("8:17\n"), Some(make_device(8, 17)));
+        assert_eq!(parse_device("8"), None);
+        assert_eq!(parse_device("sda:1"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_partition_resolves_to_disk_in_sysfs() {
+        let sysfs = std::env::temp_dir().join(format!("rust_recovery_sysfs_{}", std::process::id()));
+        let disk = sysfs.join("devices/pci0000:00/ata1/host0/target0:0:0/0:0:0:0/block/sdb");
+        std::fs::create_dir_all(disk.join("sdb1")).unwrap();
+        std::fs::create_dir_all(sysfs.join("dev/block")).unwrap();
+        std::fs::write(disk.join("dev"), "8:16\n").unwrap();
+        std::fs::write(disk.join("sdb1/dev"), "8:17\n").unwrap();
+        std::fs::write(disk.join("sdb1/partition"), "1\n").unwrap();
+        std::os::unix::fs::symlink(&disk, sysfs.join("dev/block/8:16")).unwrap();
+        std::os::unix::fs::symlink(disk.join("sdb1"), sysfs.join("dev/block/8:17")).unwrap();
+
+        assert_eq!(whole_disk_in(&sysfs, make_device(8, 17)), make_device(8, 16));
+        // Whole disks and devices missing from sysfs resolve to themselves
+        assert_eq!(whole_disk_in(&sysfs, make_device(8, 16)), make_device(8, 16));
+        assert_eq!(whole_disk_in(&sysfs, make_device(9, 0)), make_device(9, 0));
+        // A partition whose disk has no readable `dev` is kept as is
+        std::fs::remove_file(disk.join("dev")).unwrap();
+        assert_eq!(whole_disk_in(&sysfs, make_device(8, 17)), make_device(8, 17));
+        let _ = std::fs::remove_dir_all(&sysfs);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_conflict_uses_existing_ancestor() {
+        let dir = std::env::temp_dir();
+        let missing = dir.join(format!("rust_recovery_missing_{}", std::process::id())).join("out/reports");
+        assert_eq!(existing_ancestor(&missing).unwrap().0, dir.as_path());
+        assert_eq!(existing_ancestor(&dir).unwrap().0, dir.as_path());
+        assert_eq!(existing_ancestor(Path::new("no_such_dir/out")).unwrap().0, Path::new("."));
+    }
+
     #[test]
     fn test_offset_checked_add() {
         let offset = Offset::new(100);
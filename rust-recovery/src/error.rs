@@ -30,6 +30,18 @@ pub enum RecoveryError {
 
     #[error("Configuration error: {0}")]
     Config(String),
+
+    #[error("Scan database error: {0}")]
+    Database(String),
+}
+
+impl From<recovery_core::error::CoreError> for RecoveryError {
+    fn from(e: recovery_core::error::CoreError) -> Self {
+        match e {
+            recovery_core::error::CoreError::Io(e) => Self::Io(e),
+            recovery_core::error::CoreError::Config(msg) => Self::Config(msg),
+        }
+    }
 }
 
 /// Result type alias for recovery operations
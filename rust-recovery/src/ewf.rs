@@ -0,0 +1,554 @@
+//! EWF (Expert Witness / EnCase `.E01`) image reader
+//!
+//! An E01 image is split into segment files (`.E01` … `.E99`, `.EAA` …).
+//! Each segment is a chain of sections; `table` sections map the media
+//! chunks (32 KiB by default) to zlib-compressed or raw, Adler-32 suffixed
+//! data in the segment. Chunks are decompressed on demand, so the scanner
+//! reads an E01 like a block device: through a buffer instead of a mapping.
+//! `hash` / `digest` sections hold the MD5 / SHA1 taken at acquisition.
+
+use crate::disk::read_exact_at;
+use crate::error::{RecoveryError, Result};
+use flate2::{Decompress, FlushDecompress, Status};
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Signature of EWF-E01 / EWF-S01 segment files
+pub const EWF_SIGNATURE: &[u8; 8] = b"EVF\x09\x0d\x0a\xff\x00";
+/// Signature of EWF2 (`.Ex01`) segment files, which are not supported
+pub const EWF2_SIGNATURE: &[u8; 8] = b"EVF2\x0d\x0a\x81\x00";
+
+const FILE_HEADER_SIZE: u64 = 13;
+const SECTION_DESCRIPTOR_SIZE: u64 = 76;
+/// Entry count, padding, base offset, padding, checksum
+const TABLE_HEADER_SIZE: u64 = 24;
+/// Size of the EWF-E01 volume section data (EWF-S01 uses 94 bytes)
+const E01_VOLUME_SIZE: usize = 1052;
+/// Table entry flag; the low 31 bits hold the chunk offset
+const COMPRESSED_FLAG: u32 = 0x8000_0000;
+
+/// Location of one media chunk in the segment files
+#[derive(Debug, Clone, Copy)]
+struct ChunkLocation {
+    segment: u32,
+    offset: u64,
+    stored_size: u32,
+    compressed: bool,
+}
+
+/// Hash stored at acquisition and hash of the media as read back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashCheck {
+    pub stored: String,
+    pub computed: String,
+}
+
+impl HashCheck {
+    pub fn matches(&self) -> bool {
+        self.stored == self.computed
+    }
+}
+
+/// Result of `EwfImage::verify`
+#[derive(Debug, Clone, Default)]
+pub struct EwfVerification {
+    pub md5: Option<HashCheck>,
+    pub sha1: Option<HashCheck>,
+    /// Chunks that failed to decompress or checksum (hashed as zeros)
+    pub corrupt_chunks: usize,
+}
+
+impl EwfVerification {
+    /// True if the image stores a hash and every stored hash matches
+    pub fn is_verified(&self) -> bool {
+        let checks = [&self.md5, &self.sha1];
+        checks.iter().any(|c| c.is_some()) && checks.iter().flat_map(|c| c.as_ref()).all(HashCheck::matches)
+    }
+}
+
+/// EWF segment set opened for random access
+pub struct EwfImage {
+    segments: Vec<File>,
+    chunks: Vec<ChunkLocation>,
+    chunk_size: usize,
+    media_size: u64,
+    stored_md5: Option<[u8; 16]>,
+    stored_sha1: Option<[u8; 20]>,
+}
+
+impl EwfImage {
+    /// Open the segment set whose first segment (`.E01`) is `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut image = Self {
+            segments: Vec::new(),
+            chunks: Vec::new(),
+            chunk_size: 0,
+            media_size: 0,
+            stored_md5: None,
+            stored_sha1: None,
+        };
+
+        let mut segment_path = path.to_path_buf();
+        loop {
+            let file = File::open(&segment_path).map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => RecoveryError::FileNotFound(segment_path.display().to_string()),
+                _ => RecoveryError::Io(e),
+            })?;
+            let last = image.read_segment(&file)?;
+            image.segments.push(file);
+            if last {
+                break;
+            }
+            segment_path = segment_path_for(path, image.segments.len() as u32 + 1)?;
+        }
+
+        if image.chunk_size == 0 || image.media_size == 0 {
+            return Err(RecoveryError::Parse("EWF image has no volume section".to_string()));
+        }
+        if (image.chunks.len() as u64) < image.media_size.div_ceil(image.chunk_size as u64) {
+            return Err(RecoveryError::Parse(format!(
+                "EWF chunk tables cover {} of {} chunks",
+                image.chunks.len(),
+                image.media_size.div_ceil(image.chunk_size as u64)
+            )));
+        }
+        Ok(image)
+    }
+
+    /// Size of the acquired media in bytes
+    pub fn size(&self) -> u64 {
+        self.media_size
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// True if the image stores an MD5 or SHA1 to verify against
+    pub fn has_stored_hash(&self) -> bool {
+        self.stored_md5.is_some() || self.stored_sha1.is_some()
+    }
+
+    /// Fill `buf` with media bytes from `offset`; a corrupt chunk is an `InvalidData` error
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        self.read_range(offset, buf, false).map(|_| ())
+    }
+
+    /// Like `read_at`, but corrupt chunks are zero-filled.
+    /// Returns the number of zero-filled bytes.
+    pub fn read_tolerant(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_range(offset, buf, true)
+    }
+
+    /// Hash the whole media and compare it with the stored MD5 / SHA1
+    pub fn verify(&self) -> io::Result<EwfVerification> {
+        let mut md5 = Md5::new();
+        let mut sha1 = Sha1::new();
+        let (mut stored, mut chunk) = (Vec::new(), Vec::new());
+        let mut corrupt_chunks = 0;
+
+        for index in 0..self.chunks.len() {
+            let len = self.chunk_len(index);
+            if len == 0 {
+                break;
+            }
+            match self.read_chunk(index, &mut stored, &mut chunk) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    corrupt_chunks += 1;
+                    chunk.clear();
+                    chunk.resize(len, 0);
+                }
+                Err(e) => return Err(e),
+            }
+            md5.update(&chunk[..len]);
+            sha1.update(&chunk[..len]);
+        }
+
+        Ok(EwfVerification {
+            md5: self.stored_md5.map(|stored| HashCheck {
+                stored: to_hex(&stored),
+                computed: to_hex(&md5.finalize()),
+            }),
+            sha1: self.stored_sha1.map(|stored| HashCheck {
+                stored: to_hex(&stored),
+                computed: to_hex(&sha1.finalize()),
+            }),
+            corrupt_chunks,
+        })
+    }
+
+    /// Walk the sections of one segment; returns true at the `done` section
+    fn read_segment(&mut self, file: &File) -> Result<bool> {
+        let number = self.segments.len() + 1;
+        let corrupt = |what: String| RecoveryError::Parse(format!("EWF segment {}: {}", number, what));
+
+        let mut header = [0u8; 8];
+        read_exact_at(file, &mut header, 0)?;
+        if &header != EWF_SIGNATURE {
+            return Err(corrupt("not an EWF segment file".to_string()));
+        }
+
+        let file_len = file.metadata()?.len();
+        let mut offset = FILE_HEADER_SIZE;
+        let mut sectors_end = None;
+        loop {
+            if offset + SECTION_DESCRIPTOR_SIZE > file_len {
+                return Err(corrupt(format!("truncated at section 0x{:X}", offset)));
+            }
+            let mut descriptor = [0u8; SECTION_DESCRIPTOR_SIZE as usize];
+            read_exact_at(file, &mut descriptor, offset)?;
+            if adler32(&descriptor[..72]) != le_u32(&descriptor[72..]) {
+                return Err(corrupt(format!("bad section checksum at 0x{:X}", offset)));
+            }
+
+            let kind = descriptor[..16].split(|&b| b == 0).next().unwrap_or_default();
+            let next = le_u64(&descriptor[16..]);
+            let size = le_u64(&descriptor[24..]);
+            let data_offset = offset + SECTION_DESCRIPTOR_SIZE;
+            let data_len = size.saturating_sub(SECTION_DESCRIPTOR_SIZE).min(file_len - data_offset);
+
+            match kind {
+                b"volume" | b"disk" => self.read_volume(file, data_offset, data_len)?,
+                b"sectors" => sectors_end = Some(offset + size),
+                b"table" => {
+                    // Without a sectors section the chunks precede the table
+                    let data_end = sectors_end.unwrap_or(offset);
+                    self.read_table(file, data_offset, data_len, data_end)
+                        .map_err(|e| corrupt(format!("table at 0x{:X}: {}", offset, e)))?;
+                }
+                b"hash" if data_len >= 16 => {
+                    let mut md5 = [0u8; 16];
+                    read_exact_at(file, &mut md5, data_offset)?;
+                    self.stored_md5 = Some(md5);
+                }
+                b"digest" if data_len >= 36 => {
+                    let mut digest = [0u8; 36];
+                    read_exact_at(file, &mut digest, data_offset)?;
+                    self.stored_md5 = Some(digest[..16].try_into().unwrap_or_default());
+                    self.stored_sha1 = Some(digest[16..].try_into().unwrap_or_default());
+                }
+                b"done" => return Ok(true),
+                b"next" => return Ok(false),
+                _ => {}
+            }
+
+            if next <= offset {
+                return Err(corrupt("section chain ends without next/done".to_string()));
+            }
+            offset = next;
+        }
+    }
+
+    fn read_volume(&mut self, file: &File, data_offset: u64, data_len: u64) -> Result<()> {
+        let mut volume = vec![0u8; (data_len as usize).min(E01_VOLUME_SIZE)];
+        if volume.len() < 24 {
+            return Err(RecoveryError::Parse("EWF volume section too short".to_string()));
+        }
+        read_exact_at(file, &mut volume, data_offset)?;
+
+        let sectors_per_chunk = le_u32(&volume[8..]) as u64;
+        let bytes_per_sector = le_u32(&volume[12..]) as u64;
+        // EWF-S01 stores a 32-bit sector count
+        let sector_count = if volume.len() >= E01_VOLUME_SIZE {
+            le_u64(&volume[16..])
+        } else {
+            le_u32(&volume[16..]) as u64
+        };
+
+        let chunk_size = sectors_per_chunk * bytes_per_sector;
+        if chunk_size == 0 || chunk_size > 64 * 1024 * 1024 {
+            return Err(RecoveryError::Parse(format!("EWF chunk size {} not supported", chunk_size)));
+        }
+        self.chunk_size = chunk_size as usize;
+        self.media_size = sector_count * bytes_per_sector;
+        Ok(())
+    }
+
+    /// Append the chunks of one table; the last one ends at `data_end`
+    fn read_table(&mut self, file: &File, data_offset: u64, data_len: u64, data_end: u64) -> io::Result<()> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+
+        let mut header = [0u8; TABLE_HEADER_SIZE as usize];
+        if data_len < TABLE_HEADER_SIZE {
+            return Err(invalid("truncated header"));
+        }
+        read_exact_at(file, &mut header, data_offset)?;
+        if adler32(&header[..20]) != le_u32(&header[20..]) {
+            return Err(invalid("bad header checksum"));
+        }
+
+        let count = le_u32(&header) as u64;
+        let base = le_u64(&header[8..]);
+        if TABLE_HEADER_SIZE + count * 4 > data_len {
+            return Err(invalid("more entries than the section holds"));
+        }
+        let mut raw = vec![0u8; count as usize * 4];
+        read_exact_at(file, &mut raw, data_offset + TABLE_HEADER_SIZE)?;
+
+        let entries: Vec<(u64, bool)> = raw
+            .chunks_exact(4)
+            .map(|entry| {
+                let value = le_u32(entry);
+                (base + (value & !COMPRESSED_FLAG) as u64, value & COMPRESSED_FLAG != 0)
+            })
+            .collect();
+
+        let segment = self.segments.len() as u32;
+        for (i, &(offset, compressed)) in entries.iter().enumerate() {
+            let end = entries.get(i + 1).map_or(data_end, |next| next.0);
+            if end <= offset || end - offset > u32::MAX as u64 {
+                return Err(invalid("chunk offsets out of order"));
+            }
+            self.chunks.push(ChunkLocation {
+                segment,
+                offset,
+                stored_size: (end - offset) as u32,
+                compressed,
+            });
+        }
+        Ok(())
+    }
+
+    /// Media bytes held by chunk `index` (only the last chunk is short)
+    fn chunk_len(&self, index: usize) -> usize {
+        let start = index as u64 * self.chunk_size as u64;
+        self.media_size.saturating_sub(start).min(self.chunk_size as u64) as usize
+    }
+
+    /// Decompress chunk `index` into `chunk`, using `stored` as read buffer
+    fn read_chunk(&self, index: usize, stored: &mut Vec<u8>, chunk: &mut Vec<u8>) -> io::Result<()> {
+        let location = self.chunks.get(index).ok_or(io::ErrorKind::UnexpectedEof)?;
+        let expected = self.chunk_len(index);
+        let corrupt = |what: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("EWF chunk {} {}", index, what))
+        };
+
+        stored.resize(location.stored_size as usize, 0);
+        read_exact_at(&self.segments[location.segment as usize], stored, location.offset)?;
+
+        chunk.clear();
+        if location.compressed {
+            chunk.reserve(self.chunk_size);
+            let mut inflater = Decompress::new(true);
+            match inflater.decompress_vec(stored, chunk, FlushDecompress::Finish) {
+                Ok(Status::StreamEnd) => {}
+                _ => return Err(corrupt("does not decompress")),
+            }
+        } else {
+            // Raw chunk data followed by its Adler-32
+            let Some(split) = stored.len().checked_sub(4) else {
+                return Err(corrupt("is truncated"));
+            };
+            let (data, checksum) = stored.split_at(split);
+            if adler32(data) != le_u32(checksum) {
+                return Err(corrupt("fails its checksum"));
+            }
+            chunk.extend_from_slice(data);
+        }
+
+        if chunk.len() < expected {
+            return Err(corrupt("is short"));
+        }
+        Ok(())
+    }
+
+    fn read_range(&self, offset: u64, buf: &mut [u8], tolerant: bool) -> io::Result<usize> {
+        if offset.saturating_add(buf.len() as u64) > self.media_size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let (mut stored, mut chunk) = (Vec::new(), Vec::new());
+        let mut done = 0;
+        let mut unreadable = 0;
+        while done < buf.len() {
+            let position = offset + done as u64;
+            let index = (position / self.chunk_size as u64) as usize;
+            let within = (position % self.chunk_size as u64) as usize;
+            let take = (self.chunk_len(index) - within).min(buf.len() - done);
+            let out = &mut buf[done..done + take];
+
+            match self.read_chunk(index, &mut stored, &mut chunk) {
+                Ok(()) => out.copy_from_slice(&chunk[within..within + take]),
+                Err(e) if tolerant && e.kind() == io::ErrorKind::InvalidData => {
+                    out.fill(0);
+                    unreadable += take;
+                }
+                Err(e) => return Err(e),
+            }
+            done += take;
+        }
+        Ok(unreadable)
+    }
+}
+
+/// True if `header` starts with the EWF-E01 signature
+pub fn is_ewf(header: &[u8]) -> bool {
+    header.starts_with(EWF_SIGNATURE)
+}
+
+/// Path of segment `number` (1-based) of the set starting at `first`:
+/// `.E01` … `.E99`, then `.EAA` … `.EZZ`, `.FAA` …, keeping the case
+fn segment_path_for(first: &Path, number: u32) -> Result<PathBuf> {
+    let extension = first.extension().and_then(|e| e.to_str()).unwrap_or("E01");
+    let lowercase = extension.starts_with(|c: char| c.is_ascii_lowercase());
+    let base = extension.chars().next().unwrap_or('E').to_ascii_uppercase() as u8;
+
+    let extension = if number <= 99 {
+        format!("{}{:02}", base as char, number)
+    } else {
+        let k = number - 100;
+        let first_letter = base as u32 + k / (26 * 26);
+        if first_letter > b'Z' as u32 {
+            return Err(RecoveryError::Parse(format!("EWF segment number {} out of range", number)));
+        }
+        format!(
+            "{}{}{}",
+            first_letter as u8 as char,
+            (b'A' + (k / 26 % 26) as u8) as char,
+            (b'A' + (k % 26) as u8) as char
+        )
+    };
+
+    let extension = if lowercase { extension.to_ascii_lowercase() } else { extension };
+    Ok(first.with_extension(extension))
+}
+
+/// Adler-32 as used for EWF section, table and chunk checksums
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    // Largest block before `b` can overflow
+    const BLOCK: usize = 5552;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for block in data.chunks(BLOCK) {
+        for &byte in block {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn le_u64(bytes: &[u8]) -> u64 {
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn section(kind: &str, offset: u64, data: &[u8], last: bool) -> Vec<u8> {
+        let size = SECTION_DESCRIPTOR_SIZE + data.len() as u64;
+        let mut descriptor = vec![0u8; 72];
+        descriptor[..kind.len()].copy_from_slice(kind.as_bytes());
+        let next = if last { offset } else { offset + size };
+        descriptor[16..24].copy_from_slice(&next.to_le_bytes());
+        descriptor[24..32].copy_from_slice(&size.to_le_bytes());
+        let checksum = adler32(&descriptor);
+        descriptor.extend_from_slice(&checksum.to_le_bytes());
+        descriptor.extend_from_slice(data);
+        descriptor
+    }
+
+    /// Two-chunk E01 (one compressed, one raw) with an MD5/SHA1 digest
+    fn build_e01(media: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut image = EWF_SIGNATURE.to_vec();
+        image.extend_from_slice(&[1, 1, 0, 0, 0]);
+
+        let mut volume = vec![0u8; E01_VOLUME_SIZE];
+        volume[4..8].copy_from_slice(&2u32.to_le_bytes());
+        volume[8..12].copy_from_slice(&((chunk_size / 512) as u32).to_le_bytes());
+        volume[12..16].copy_from_slice(&512u32.to_le_bytes());
+        volume[16..24].copy_from_slice(&((media.len() / 512) as u64).to_le_bytes());
+        let volume = section("volume", image.len() as u64, &volume, false);
+        image.extend_from_slice(&volume);
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&media[..chunk_size]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut raw = media[chunk_size..].to_vec();
+        raw.extend_from_slice(&adler32(&media[chunk_size..]).to_le_bytes());
+
+        let sectors_offset = image.len() as u64;
+        let data_start = sectors_offset + SECTION_DESCRIPTOR_SIZE;
+        let sectors = section("sectors", sectors_offset, &[compressed.clone(), raw].concat(), false);
+        image.extend_from_slice(&sectors);
+
+        let mut table = vec![0u8; TABLE_HEADER_SIZE as usize];
+        table[..4].copy_from_slice(&2u32.to_le_bytes());
+        table[8..16].copy_from_slice(&data_start.to_le_bytes());
+        let checksum = adler32(&table[..20]);
+        table[20..24].copy_from_slice(&checksum.to_le_bytes());
+        table.extend_from_slice(&COMPRESSED_FLAG.to_le_bytes());
+        table.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        let table = section("table", image.len() as u64, &table, false);
+        image.extend_from_slice(&table);
+
+        let mut digest = Md5::digest(media).to_vec();
+        digest.extend_from_slice(&Sha1::digest(media));
+        digest.extend_from_slice(&[0u8; 44]);
+        let digest = section("digest", image.len() as u64, &digest, false);
+        image.extend_from_slice(&digest);
+
+        let done = section("done", image.len() as u64, &[], true);
+        image.extend_from_slice(&done);
+        image
+    }
+
+    #[test]
+    fn test_read_and_verify_e01() {
+        let chunk_size = 4096;
+        let media: Vec<u8> = (0..2 * chunk_size).map(|i| (i % 251) as u8).collect();
+
+        let mut path = std::env::temp_dir();
+        let unique = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        path.push(format!("rust_recovery_ewf_{unique}.E01"));
+        std::fs::write(&path, build_e01(&media, chunk_size)).unwrap();
+
+        let image = EwfImage::open(&path).unwrap();
+        assert_eq!(image.size(), media.len() as u64);
+        assert_eq!(image.segment_count(), 1);
+
+        // Range crossing the compressed/raw chunk boundary
+        let mut buf = vec![0u8; 1000];
+        image.read_at(chunk_size as u64 - 500, &mut buf).unwrap();
+        assert_eq!(buf, media[chunk_size - 500..chunk_size + 500]);
+        assert!(image.read_at(media.len() as u64 - 10, &mut buf).is_err());
+
+        let verification = image.verify().unwrap();
+        assert!(verification.is_verified());
+        assert_eq!(verification.corrupt_chunks, 0);
+
+        assert_eq!(segment_path_for(&path, 2).unwrap(), path.with_extension("E02"));
+        assert_eq!(segment_path_for(&path, 101).unwrap(), path.with_extension("EAB"));
+        assert_eq!(segment_path_for(Path::new("x.e01"), 100).unwrap(), Path::new("x.eaa"));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,161 @@
+//! Process exit codes and the final summary line, for wrapper scripts
+//!
+//! A scan ends with one `RESULT key=value ...` line on stdout. The report
+//! path is the last field and runs to the end of the line (it may contain
+//! spaces). Scripts branch on the exit code and read the counts from that
+//! line instead of parsing the JSON report.
+
+use crate::diagnostics::{DiagnosticCause, ScanDiagnostics};
+use crate::error::RecoveryError;
+use std::path::PathBuf;
+
+/// Files were recovered
+pub const SUCCESS: i32 = 0;
+/// `verify`: the saved file differs from what the image yields
+pub const VERIFY_DIFFERS: i32 = 2;
+/// The scan completed, but no file was recovered
+pub const NOTHING_RECOVERED: i32 = 3;
+/// Unreadable sectors were zero-filled, or --max-duration stopped the scan:
+/// results may be incomplete
+pub const PARTIAL: i32 = 4;
+/// Cancelled (Ctrl-C); files found up to then are saved and reported
+pub const CANCELLED: i32 = 5;
+/// Errors are above 10
+pub const INVALID_ARGUMENT: i32 = 11;
+pub const CONFIG_ERROR: i32 = 12;
+pub const IO_ERROR: i32 = 13;
+pub const PARSE_ERROR: i32 = 14;
+
+/// How a scan that ran to the report ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Recovered,
+    NothingRecovered,
+    Partial,
+    Cancelled,
+}
+
+impl RunStatus {
+    /// Cancellation comes before a stopped scan or bad sectors, those before
+    /// an empty result
+    pub fn classify(diagnostics: &ScanDiagnostics, files_recovered: usize) -> Self {
+        if diagnostics.bytes(DiagnosticCause::Cancelled) > 0 {
+            Self::Cancelled
+        } else if diagnostics.bytes(DiagnosticCause::TimeBudget) > 0
+            || diagnostics.bytes(DiagnosticCause::BadSectors) > 0
+        {
+            Self::Partial
+        } else if files_recovered == 0 {
+            Self::NothingRecovered
+        } else {
+            Self::Recovered
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Recovered => SUCCESS,
+            Self::NothingRecovered => NOTHING_RECOVERED,
+            Self::Partial => PARTIAL,
+            Self::Cancelled => CANCELLED,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Recovered => "recovered",
+            Self::NothingRecovered => "nothing_recovered",
+            Self::Partial => "partial",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Exit code and `error=` name of an error
+pub fn error_code(error: &RecoveryError) -> (i32, &'static str) {
+    match error {
+        RecoveryError::InvalidArgument(_) => (INVALID_ARGUMENT, "invalid_argument"),
+        RecoveryError::Config(_) => (CONFIG_ERROR, "config"),
+        RecoveryError::Parse(_) => (PARSE_ERROR, "parse"),
+        RecoveryError::Io(_)
+        | RecoveryError::Mmap(_)
+        | RecoveryError::FileNotFound(_)
+        | RecoveryError::InvalidOffset { .. }
+        | RecoveryError::InvalidSize { .. }
+        | RecoveryError::Database(_) => (IO_ERROR, "io"),
+    }
+}
+
+/// Summary line of a failed run
+pub fn error_line(error: &RecoveryError) -> String {
+    let (code, name) = error_code(error);
+    format!("RESULT status=error exit={} error={}", code, name)
+}
+
+/// Figures of the final summary line
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    pub status: RunStatus,
+    pub files_recovered: usize,
+    pub bytes_scanned: u64,
+    pub image_size: u64,
+    pub bad_sector_bytes: u64,
+    pub duration_secs: f64,
+    pub report: PathBuf,
+}
+
+impl RunSummary {
+    pub fn line(&self) -> String {
+        format!(
+            "RESULT status={} exit={} files={} bytes_scanned={} image_size={} bad_sector_bytes={} duration_secs={:.1} report={}",
+            self.status.name(),
+            self.status.code(),
+            self.files_recovered,
+            self.bytes_scanned,
+            self.image_size,
+            self.bad_sector_bytes,
+            self.duration_secs,
+            self.report.display()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_precedence_and_summary_line() {
+        let mut diagnostics = ScanDiagnostics::new();
+        assert_eq!(RunStatus::classify(&diagnostics, 0), RunStatus::NothingRecovered);
+        assert_eq!(RunStatus::classify(&diagnostics, 3), RunStatus::Recovered);
+        diagnostics.record(DiagnosticCause::HighEntropy, 4096);
+        assert_eq!(RunStatus::classify(&diagnostics, 3).code(), SUCCESS);
+        let mut stopped = ScanDiagnostics::new();
+        stopped.record(DiagnosticCause::TimeBudget, 1 << 20);
+        assert_eq!(RunStatus::classify(&stopped, 3), RunStatus::Partial);
+        diagnostics.record(DiagnosticCause::BadSectors, 512);
+        assert_eq!(RunStatus::classify(&diagnostics, 0), RunStatus::Partial);
+        diagnostics.record(DiagnosticCause::Cancelled, 1 << 20);
+        assert_eq!(RunStatus::classify(&diagnostics, 3).code(), CANCELLED);
+
+        let summary = RunSummary {
+            status: RunStatus::Partial,
+            files_recovered: 2,
+            bytes_scanned: 1 << 20,
+            image_size: 1 << 21,
+            bad_sector_bytes: 512,
+            duration_secs: 1.5,
+            report: PathBuf::from("/out dir/reports/recovery_report.json"),
+        };
+        assert_eq!(
+            summary.line(),
+            "RESULT status=partial exit=4 files=2 bytes_scanned=1048576 image_size=2097152 bad_sector_bytes=512 \
+             duration_secs=1.5 report=/out dir/reports/recovery_report.json"
+        );
+        assert_eq!(
+            error_line(&RecoveryError::FileNotFound("image.dd".to_string())),
+            "RESULT status=error exit=13 error=io"
+        );
+    }
+}
@@ -0,0 +1,775 @@
+//! FAT32 directory entry carving
+//!
+//! Detects the FAT32 boot sector (BPB), parses 8.3 short entries together with
+//! the VFAT long-name (LFN) entries that precede them, follows cluster chains
+//! through the FAT and keeps deleted entries (first name byte 0xE5). Entries carry
+//! the same hints as exFAT entries so `fragment_linker` can use them.
+//!
+//! Deleting a file zeroes its FAT chain, so deleted files are read as one
+//! contiguous run from their first cluster. Some drivers also clear the high
+//! word of the first cluster on delete; such entries point into the first 64K
+//! clusters and may yield wrong content.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::fragment_linker::ExFatMetadata;
+
+/// Boot sector (BPB) field offsets
+const BS_JUMP: usize = 0;
+const BS_BYTES_PER_SECTOR: usize = 11;
+const BS_SECTORS_PER_CLUSTER: usize = 13;
+const BS_RESERVED_SECTORS: usize = 14;
+const BS_NUM_FATS: usize = 16;
+const BS_ROOT_ENTRY_COUNT: usize = 17;
+const BS_TOTAL_SECTORS_16: usize = 19;
+const BS_FAT_SIZE_16: usize = 22;
+const BS_TOTAL_SECTORS_32: usize = 32;
+const BS_FAT_SIZE_32: usize = 36;
+const BS_ROOT_CLUSTER: usize = 44;
+const BS_FILE_SYSTEM_TYPE: usize = 82;
+const BS_SIGNATURE: usize = 510;
+const BOOT_SECTOR_SIZE: usize = 512;
+
+/// Short (8.3) entry field offsets
+const DE_NAME: usize = 0;
+const DE_ATTR: usize = 11;
+const DE_FIRST_CLUSTER_HI: usize = 20;
+const DE_FIRST_CLUSTER_LO: usize = 26;
+const DE_FILE_SIZE: usize = 28;
+
+/// Long name entry field offsets
+const LFN_ORDER: usize = 0;
+const LFN_NAME1: usize = 1;
+const LFN_CHECKSUM: usize = 13;
+const LFN_NAME2: usize = 14;
+const LFN_FIRST_CLUSTER: usize = 26;
+const LFN_NAME3: usize = 28;
+const LFN_LAST_ENTRY: u8 = 0x40;
+const LFN_CHARS_PER_ENTRY: usize = 13;
+/// 20 slots hold 260 characters, more than the 255 a long name may have
+const MAX_LFN_SLOTS: usize = 20;
+
+/// Attribute bits
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_LONG_NAME: u8 = 0x0F;
+const ATTR_RESERVED: u8 = 0xC0;
+
+/// First name byte markers
+const ENTRY_END: u8 = 0x00;
+const ENTRY_DELETED: u8 = 0xE5;
+/// 0xE5 as a real first character is stored as 0x05
+const ENTRY_KANJI_E5: u8 = 0x05;
+
+/// FAT entry values (the top four bits are reserved)
+const FAT_ENTRY_MASK: u32 = 0x0FFF_FFFF;
+const FAT_BAD_CLUSTER: u32 = 0x0FFF_FFF7;
+
+const DIRECTORY_ENTRY_SIZE: usize = 32;
+/// 32 KiB per the spec, 64 KiB written by some formatters
+const MAX_CLUSTER_SIZE: u64 = 64 * 1024;
+const MAX_EXTRACT_SIZE: u64 = 250 * 1024 * 1024;
+/// Clusters followed per directory
+const MAX_DIRECTORY_CLUSTERS: usize = 4096;
+/// Characters never valid in a short name
+const SHORT_NAME_FORBIDDEN: &[u8] = b"\"*+,./:;<=>?[\\]|";
+
+#[derive(Clone, Debug)]
+pub struct Fat32BootParams {
+    pub sector_size: u64,
+    pub cluster_size: u64,
+    /// Absolute image offset of the first FAT
+    pub fat_offset: u64,
+    pub fat_length_sectors: u32,
+    pub fat_count: u8,
+    /// Absolute image offset of cluster 2
+    pub data_offset: u64,
+    pub cluster_count: u32,
+    pub root_dir_cluster: u32,
+    pub boot_sector_offset: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Fat32Entry {
+    /// Image offset of the short entry
+    pub offset: u64,
+    pub data_offset: Option<u64>,
+    pub is_deleted: bool,
+    pub is_directory: bool,
+    /// Long name when a matching LFN set precedes the entry, otherwise the 8.3 name
+    pub filename: String,
+    /// 8.3 name as `NAME.EXT`; a deleted entry's first character reads as `_`
+    pub short_name: String,
+    pub size: u64,
+    pub first_cluster: u32,
+}
+
+impl Fat32Entry {
+    /// Hints for `fragment_linker`, in the same shape as exFAT metadata
+    pub fn to_fragment_metadata(&self) -> ExFatMetadata {
+        ExFatMetadata {
+            filename: (!self.filename.is_empty()).then(|| self.filename.clone()),
+            first_cluster: (self.first_cluster >= 2).then_some(self.first_cluster),
+            size: Some(self.size),
+        }
+    }
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_le_bytes)
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+}
+
+pub(crate) fn parse_boot_sector_at(data: &[u8], bs_offset: u64) -> Option<Fat32BootParams> {
+    let off = usize::try_from(bs_offset).ok()?;
+    let bs = data.get(off..off.checked_add(BOOT_SECTOR_SIZE)?)?;
+
+    if !matches!(bs[BS_JUMP], 0xEB | 0xE9) || bs[BS_SIGNATURE..BS_SIGNATURE + 2] != [0x55, 0xAA] {
+        return None;
+    }
+    if &bs[BS_FILE_SYSTEM_TYPE..BS_FILE_SYSTEM_TYPE + 8] != b"FAT32   " {
+        return None;
+    }
+
+    let bytes_per_sector = read_u16_le(bs, BS_BYTES_PER_SECTOR)? as u64;
+    let sectors_per_cluster = bs[BS_SECTORS_PER_CLUSTER] as u64;
+    let reserved_sectors = read_u16_le(bs, BS_RESERVED_SECTORS)? as u64;
+    let fat_count = bs[BS_NUM_FATS];
+
+    if !bytes_per_sector.is_power_of_two() || !(512..=4096).contains(&bytes_per_sector) {
+        return None;
+    }
+    if !sectors_per_cluster.is_power_of_two() {
+        return None;
+    }
+    if reserved_sectors == 0 || fat_count == 0 || fat_count > 2 {
+        return None;
+    }
+    // FAT12/16 fields must be zero on FAT32
+    if read_u16_le(bs, BS_ROOT_ENTRY_COUNT)? != 0
+        || read_u16_le(bs, BS_TOTAL_SECTORS_16)? != 0
+        || read_u16_le(bs, BS_FAT_SIZE_16)? != 0
+    {
+        return None;
+    }
+
+    let cluster_size = bytes_per_sector * sectors_per_cluster;
+    if cluster_size > MAX_CLUSTER_SIZE {
+        return None;
+    }
+
+    let total_sectors = read_u32_le(bs, BS_TOTAL_SECTORS_32)? as u64;
+    let fat_length_sectors = read_u32_le(bs, BS_FAT_SIZE_32)?;
+    let root_dir_cluster = read_u32_le(bs, BS_ROOT_CLUSTER)?;
+    if fat_length_sectors == 0 || root_dir_cluster < 2 {
+        return None;
+    }
+
+    let data_start_sectors = reserved_sectors + fat_count as u64 * fat_length_sectors as u64;
+    let cluster_count = total_sectors.checked_sub(data_start_sectors)? / sectors_per_cluster;
+    let cluster_count = u32::try_from(cluster_count).ok()?;
+    if cluster_count == 0 {
+        return None;
+    }
+
+    let fat_offset = reserved_sectors
+        .checked_mul(bytes_per_sector)?
+        .checked_add(bs_offset)?;
+    let data_offset = data_start_sectors
+        .checked_mul(bytes_per_sector)?
+        .checked_add(bs_offset)?;
+
+    Some(Fat32BootParams {
+        sector_size: bytes_per_sector,
+        cluster_size,
+        fat_offset,
+        fat_length_sectors,
+        fat_count,
+        data_offset,
+        cluster_count,
+        root_dir_cluster,
+        boot_sector_offset: bs_offset,
+    })
+}
+
+pub fn find_boot_sector(data: &[u8]) -> Option<Fat32BootParams> {
+    if let Some(params) = parse_boot_sector_at(data, 0) {
+        return Some(params);
+    }
+
+    let search_limit = data.len().min(4 * 1024 * 1024);
+    for offset in (512..search_limit).step_by(512) {
+        if offset + BOOT_SECTOR_SIZE > data.len() {
+            break;
+        }
+        if data.get(offset + BS_FILE_SYSTEM_TYPE..offset + BS_FILE_SYSTEM_TYPE + 8)
+            == Some(&b"FAT32   "[..])
+        {
+            if let Some(params) = parse_boot_sector_at(data, offset as u64) {
+                return Some(params);
+            }
+        }
+    }
+
+    None
+}
+
+fn fat_next_cluster(data: &[u8], params: &Fat32BootParams, cluster: u32) -> Option<u32> {
+    let offset_bytes = (cluster as u64).checked_mul(4)?;
+    let fat_entry_offset = params.fat_offset.checked_add(offset_bytes)?;
+    let offset = usize::try_from(fat_entry_offset).ok()?;
+    read_u32_le(data, offset).map(|value| value & FAT_ENTRY_MASK)
+}
+
+pub fn cluster_to_offset(params: &Fat32BootParams, cluster: u32) -> Option<u64> {
+    if cluster < 2 {
+        return None;
+    }
+    params
+        .data_offset
+        .checked_add((cluster as u64 - 2).checked_mul(params.cluster_size)?)
+}
+
+/// Walk the cluster chain of a file and hand each data slice to `sink`, in order.
+///
+/// Deleted files are read contiguously since their chain is gone. Stops at
+/// `file_size`, at the end of the chain, or at the first invalid or repeated
+/// cluster. Returns the number of bytes passed to `sink`.
+fn for_each_file_slice<F>(
+    data: &[u8],
+    params: &Fat32BootParams,
+    first_cluster: u32,
+    file_size: u64,
+    contiguous: bool,
+    mut sink: F,
+) -> std::io::Result<u64>
+where
+    F: FnMut(&[u8]) -> std::io::Result<()>,
+{
+    if first_cluster < 2 || file_size == 0 {
+        return Ok(0);
+    }
+
+    let mut remaining = file_size;
+    let mut cluster = first_cluster;
+    let mut visited = HashSet::new();
+    let max_cluster = params.cluster_count.saturating_add(1);
+
+    while remaining > 0 {
+        if cluster < 2 || cluster > max_cluster {
+            break;
+        }
+        if !contiguous && !visited.insert(cluster) {
+            break;
+        }
+
+        let start = match cluster_to_offset(params, cluster) {
+            Some(offset) => offset,
+            None => break,
+        };
+        if start >= data.len() as u64 {
+            break;
+        }
+
+        let to_read = remaining.min(params.cluster_size);
+        let end = start.saturating_add(to_read).min(data.len() as u64);
+        if end <= start {
+            break;
+        }
+
+        sink(&data[start as usize..end as usize])?;
+        remaining = remaining.saturating_sub(end - start);
+
+        if contiguous {
+            cluster = match cluster.checked_add(1) {
+                Some(next) => next,
+                None => break,
+            };
+        } else {
+            cluster = match fat_next_cluster(data, params, cluster) {
+                // End-of-chain values and bad clusters both stop the walk
+                Some(next) if next < FAT_BAD_CLUSTER => next,
+                _ => break,
+            };
+        }
+    }
+
+    Ok(file_size - remaining)
+}
+
+/// Extract a file into memory (capped at `MAX_EXTRACT_SIZE`, see `extract_file_to_writer`)
+pub fn extract_file_content(data: &[u8], params: &Fat32BootParams, entry: &Fat32Entry) -> Vec<u8> {
+    if entry.first_cluster < 2 || entry.size == 0 {
+        return Vec::new();
+    }
+
+    let actual_size = entry.size.min(MAX_EXTRACT_SIZE);
+    let mut content = Vec::with_capacity(actual_size as usize);
+    let _ = for_each_file_slice(
+        data,
+        params,
+        entry.first_cluster,
+        actual_size,
+        entry.is_deleted,
+        |slice| {
+            content.extend_from_slice(slice);
+            Ok(())
+        },
+    );
+
+    content.truncate(actual_size as usize);
+    content
+}
+
+/// Stream a file cluster-by-cluster into `writer` without buffering it in memory
+pub fn extract_file_to_writer<W: Write>(
+    data: &[u8],
+    params: &Fat32BootParams,
+    entry: &Fat32Entry,
+    writer: &mut W,
+) -> std::io::Result<u64> {
+    let written = for_each_file_slice(
+        data,
+        params,
+        entry.first_cluster,
+        entry.size,
+        entry.is_deleted,
+        |slice| writer.write_all(slice),
+    )?;
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Stream an entry's content to `path` (created or truncated)
+pub fn extract_entry_to_path(
+    data: &[u8],
+    params: &Fat32BootParams,
+    entry: &Fat32Entry,
+    path: &Path,
+) -> std::io::Result<u64> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    extract_file_to_writer(data, params, entry, &mut writer)
+}
+
+/// Checksum of the 11-byte short name stored in each LFN entry
+fn short_name_checksum(name: &[u8]) -> u8 {
+    name.iter()
+        .fold(0u8, |sum, &b| sum.rotate_right(1).wrapping_add(b))
+}
+
+fn is_long_name_entry(entry: &[u8]) -> bool {
+    entry[DE_ATTR] & 0x3F == ATTR_LONG_NAME && read_u16_le(entry, LFN_FIRST_CLUSTER) == Some(0)
+}
+
+/// Validate a short entry's name bytes; `.`/`..` and volume labels are rejected
+fn is_plausible_short_name(name: &[u8]) -> bool {
+    let valid = |b: u8| b >= 0x20 && !SHORT_NAME_FORBIDDEN.contains(&b);
+    let first_ok = matches!(name[0], ENTRY_DELETED | ENTRY_KANJI_E5) || (name[0] != b' ' && valid(name[0]));
+    first_ok && name[1..].iter().all(|&b| valid(b))
+}
+
+fn format_short_name(name: &[u8], is_deleted: bool) -> String {
+    let mut bytes = name.to_vec();
+    if is_deleted {
+        bytes[0] = b'_';
+    } else if bytes[0] == ENTRY_KANJI_E5 {
+        bytes[0] = ENTRY_DELETED;
+    }
+
+    let decode = |part: &[u8]| -> String {
+        part.iter()
+            .map(|&b| if b.is_ascii() { b as char } else { '_' })
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    };
+    let base = decode(&bytes[..8]);
+    let ext = decode(&bytes[8..11]);
+    if ext.is_empty() {
+        base
+    } else {
+        format!("{}.{}", base, ext)
+    }
+}
+
+/// Collect the 13 UTF-16 units of one LFN entry, stopping at the 0x0000 terminator
+fn push_lfn_units(entry: &[u8], units: &mut Vec<u16>) -> bool {
+    let ranges = [(LFN_NAME1, 5), (LFN_NAME2, 6), (LFN_NAME3, 2)];
+    for (start, count) in ranges {
+        for i in 0..count {
+            match read_u16_le(entry, start + i * 2) {
+                Some(0x0000) => return false,
+                Some(unit) => units.push(unit),
+                None => return false,
+            }
+        }
+    }
+    true
+}
+
+/// Parse an optional LFN run followed by its short entry.
+///
+/// Returns the entry and the number of 32-byte slots consumed. A long name whose
+/// checksum does not match the short entry is ignored in favour of the 8.3 name.
+pub fn parse_entry_set(data: &[u8], base_offset: u64) -> Option<(Fat32Entry, usize)> {
+    let mut lfn_slots: Vec<&[u8]> = Vec::new();
+    let mut pos = 0usize;
+
+    loop {
+        let slot = data.get(pos..pos + DIRECTORY_ENTRY_SIZE)?;
+        if slot[0] == ENTRY_END {
+            return None;
+        }
+        if !is_long_name_entry(slot) {
+            break;
+        }
+        if lfn_slots.len() >= MAX_LFN_SLOTS {
+            return None;
+        }
+        lfn_slots.push(slot);
+        pos += DIRECTORY_ENTRY_SIZE;
+    }
+
+    let short = &data[pos..pos + DIRECTORY_ENTRY_SIZE];
+    let name = &short[DE_NAME..DE_NAME + 11];
+    let attr = short[DE_ATTR];
+    if attr & (ATTR_RESERVED | ATTR_VOLUME_ID) != 0 || !is_plausible_short_name(name) {
+        return None;
+    }
+
+    let is_deleted = name[0] == ENTRY_DELETED;
+    let is_directory = attr & ATTR_DIRECTORY != 0;
+    let first_cluster = ((read_u16_le(short, DE_FIRST_CLUSTER_HI)? as u32) << 16)
+        | read_u16_le(short, DE_FIRST_CLUSTER_LO)? as u32;
+    let size = read_u32_le(short, DE_FILE_SIZE)? as u64;
+
+    if first_cluster > FAT_ENTRY_MASK || (first_cluster < 2 && size > 0) || (is_directory && size > 0) {
+        return None;
+    }
+
+    let short_name = format_short_name(name, is_deleted);
+    let filename = long_name(&lfn_slots, name, is_deleted).unwrap_or_else(|| short_name.clone());
+
+    Some((
+        Fat32Entry {
+            offset: base_offset + pos as u64,
+            data_offset: None,
+            is_deleted,
+            is_directory,
+            filename,
+            short_name,
+            size,
+            first_cluster,
+        },
+        lfn_slots.len() + 1,
+    ))
+}
+
+/// Assemble the long name from LFN slots stored last-part-first.
+///
+/// Deleting a file overwrites each slot's order byte with 0xE5 and the checksum
+/// covers the lost first byte of the short name, so deleted sets are only
+/// checked for consistent checksums across their slots.
+fn long_name(slots: &[&[u8]], short_name: &[u8], is_deleted: bool) -> Option<String> {
+    let first = slots.first()?;
+
+    if is_deleted {
+        if slots
+            .iter()
+            .any(|slot| slot[LFN_ORDER] != ENTRY_DELETED || slot[LFN_CHECKSUM] != first[LFN_CHECKSUM])
+        {
+            return None;
+        }
+    } else {
+        let checksum = short_name_checksum(short_name);
+        for (i, slot) in slots.iter().enumerate() {
+            let order = (slots.len() - i) as u8;
+            let expected = if i == 0 { order | LFN_LAST_ENTRY } else { order };
+            if slot[LFN_ORDER] != expected || slot[LFN_CHECKSUM] != checksum {
+                return None;
+            }
+        }
+    }
+
+    let mut units = Vec::with_capacity(slots.len() * LFN_CHARS_PER_ENTRY);
+    for slot in slots.iter().rev() {
+        if !push_lfn_units(slot, &mut units) {
+            break;
+        }
+    }
+    // Padding after the terminator is 0xFFFF
+    while units.last() == Some(&0xFFFF) {
+        units.pop();
+    }
+
+    let name = String::from_utf16_lossy(&units);
+    (!name.is_empty()).then_some(name)
+}
+
+pub fn scan_for_entries(data: &[u8], base_offset: u64) -> Vec<Fat32Entry> {
+    let mut entries = Vec::new();
+    let mut pos = 0usize;
+
+    while pos + DIRECTORY_ENTRY_SIZE <= data.len() {
+        if let Some((entry, consumed)) = parse_entry_set(&data[pos..], base_offset + pos as u64) {
+            entries.push(entry);
+            pos = pos.saturating_add(consumed * DIRECTORY_ENTRY_SIZE);
+        } else {
+            pos = pos.saturating_add(DIRECTORY_ENTRY_SIZE);
+        }
+    }
+
+    entries
+}
+
+/// Image ranges `[start, end)` of a directory's clusters, merged where
+/// contiguous. A deleted directory's chain is gone: only its first cluster is read.
+fn directory_ranges(data: &[u8], params: &Fat32BootParams, first_cluster: u32, is_deleted: bool) -> Vec<(u64, u64)> {
+    let max_cluster = params.cluster_count.saturating_add(1);
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    let mut visited = HashSet::new();
+    let mut cluster = first_cluster;
+
+    for _ in 0..if is_deleted { 1 } else { MAX_DIRECTORY_CLUSTERS } {
+        if cluster < 2 || cluster > max_cluster || !visited.insert(cluster) {
+            break;
+        }
+        let Some(start) = cluster_to_offset(params, cluster) else { break };
+        let end = start.saturating_add(params.cluster_size).min(data.len() as u64);
+        if end <= start {
+            break;
+        }
+
+        match ranges.last_mut() {
+            Some(range) if range.1 == start => range.1 = end,
+            _ => ranges.push((start, end)),
+        }
+
+        cluster = match fat_next_cluster(data, params, cluster) {
+            Some(next) if next < FAT_BAD_CLUSTER => next,
+            _ => break,
+        };
+    }
+
+    ranges
+}
+
+/// Walk the directory tree from the root cluster.
+///
+/// Returns every entry found (live and deleted, files and directories) and
+/// the directory ranges that were read, like `exfat::walk_directory_tree`.
+pub fn walk_directory_tree(data: &[u8], params: &Fat32BootParams) -> (Vec<Fat32Entry>, Vec<(u64, u64)>) {
+    let mut entries = Vec::new();
+    let mut regions = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(params.root_dir_cluster, false)];
+
+    while let Some((cluster, is_deleted)) = pending.pop() {
+        if !visited.insert(cluster) {
+            continue;
+        }
+
+        for (start, end) in directory_ranges(data, params, cluster, is_deleted) {
+            for entry in scan_for_entries(&data[start as usize..end as usize], start) {
+                if entry.is_directory && entry.first_cluster >= 2 {
+                    pending.push((entry.first_cluster, entry.is_deleted));
+                }
+                entries.push(entry);
+            }
+            regions.push((start, end));
+        }
+    }
+
+    (entries, regions)
+}
+
+pub fn populate_data_offsets(entries: &mut [Fat32Entry], params: &Fat32BootParams) {
+    for entry in entries {
+        entry.data_offset = cluster_to_offset(params, entry.first_cluster);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTOR: usize = 512;
+
+    /// 1 reserved sector, one FAT of 1 sector, 1-sector clusters, 16 clusters
+    fn build_image() -> Vec<u8> {
+        let mut data = vec![0u8; SECTOR * 18];
+        data[BS_JUMP] = 0xEB;
+        data[BS_BYTES_PER_SECTOR..BS_BYTES_PER_SECTOR + 2].copy_from_slice(&512u16.to_le_bytes());
+        data[BS_SECTORS_PER_CLUSTER] = 1;
+        data[BS_RESERVED_SECTORS..BS_RESERVED_SECTORS + 2].copy_from_slice(&1u16.to_le_bytes());
+        data[BS_NUM_FATS] = 1;
+        data[BS_TOTAL_SECTORS_32..BS_TOTAL_SECTORS_32 + 4].copy_from_slice(&18u32.to_le_bytes());
+        data[BS_FAT_SIZE_32..BS_FAT_SIZE_32 + 4].copy_from_slice(&1u32.to_le_bytes());
+        data[BS_ROOT_CLUSTER..BS_ROOT_CLUSTER + 4].copy_from_slice(&2u32.to_le_bytes());
+        data[BS_FILE_SYSTEM_TYPE..BS_FILE_SYSTEM_TYPE + 8].copy_from_slice(b"FAT32   ");
+        data[BS_SIGNATURE..BS_SIGNATURE + 2].copy_from_slice(&[0x55, 0xAA]);
+        data
+    }
+
+    fn short_entry(name: &[u8; 11], first_cluster: u32, size: u32) -> Vec<u8> {
+        let mut entry = vec![0u8; DIRECTORY_ENTRY_SIZE];
+        entry[DE_NAME..DE_NAME + 11].copy_from_slice(name);
+        entry[DE_ATTR] = 0x20;
+        entry[DE_FIRST_CLUSTER_HI..DE_FIRST_CLUSTER_HI + 2]
+            .copy_from_slice(&((first_cluster >> 16) as u16).to_le_bytes());
+        entry[DE_FIRST_CLUSTER_LO..DE_FIRST_CLUSTER_LO + 2]
+            .copy_from_slice(&(first_cluster as u16).to_le_bytes());
+        entry[DE_FILE_SIZE..DE_FILE_SIZE + 4].copy_from_slice(&size.to_le_bytes());
+        entry
+    }
+
+    /// LFN slots for `long` (last part first) followed by the short entry
+    fn entry_set(long: &str, short: &[u8; 11], first_cluster: u32, size: u32) -> Vec<u8> {
+        let mut units: Vec<u16> = long.encode_utf16().collect();
+        units.push(0);
+        let slot_count = units.len().div_ceil(LFN_CHARS_PER_ENTRY);
+        units.resize(slot_count * LFN_CHARS_PER_ENTRY, 0xFFFF);
+
+        let checksum = short_name_checksum(short);
+        let mut data = Vec::new();
+        for order in (1..=slot_count).rev() {
+            let mut slot = vec![0u8; DIRECTORY_ENTRY_SIZE];
+            slot[LFN_ORDER] = order as u8 | if order == slot_count { LFN_LAST_ENTRY } else { 0 };
+            slot[DE_ATTR] = ATTR_LONG_NAME;
+            slot[LFN_CHECKSUM] = checksum;
+            let part = &units[(order - 1) * LFN_CHARS_PER_ENTRY..order * LFN_CHARS_PER_ENTRY];
+            let offsets = (0..5)
+                .map(|i| LFN_NAME1 + i * 2)
+                .chain((0..6).map(|i| LFN_NAME2 + i * 2))
+                .chain((0..2).map(|i| LFN_NAME3 + i * 2));
+            for (unit, at) in part.iter().zip(offsets) {
+                slot[at..at + 2].copy_from_slice(&unit.to_le_bytes());
+            }
+            data.extend_from_slice(&slot);
+        }
+        data.extend_from_slice(&short_entry(short, first_cluster, size));
+        data
+    }
+
+    #[test]
+    fn test_find_boot_sector() {
+        let mut image = vec![0u8; SECTOR * 4];
+        image.extend_from_slice(&build_image());
+        let params = find_boot_sector(&image).expect("boot sector should be found");
+        assert_eq!(params.boot_sector_offset, 2048);
+        assert_eq!(params.cluster_size, 512);
+        assert_eq!(params.fat_offset, 2048 + 512);
+        assert_eq!(params.data_offset, 2048 + 1024);
+        assert_eq!(params.cluster_count, 16);
+
+        // exFAT and NTFS boot sectors are not FAT32
+        let mut not_fat = build_image();
+        not_fat[BS_FAT_SIZE_16] = 1;
+        assert!(find_boot_sector(&not_fat).is_none());
+    }
+
+    #[test]
+    fn test_parse_long_and_short_names() {
+        let mut data = entry_set("watch-history.json", b"WATCH-~1JSO", 3, 700);
+        data.extend_from_slice(&short_entry(b"README  TXT", 5, 12));
+        data.extend_from_slice(&[0u8; DIRECTORY_ENTRY_SIZE]);
+
+        let entries = scan_for_entries(&data, 0x4000);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].filename, "watch-history.json");
+        assert_eq!(entries[0].short_name, "WATCH-~1.JSO");
+        assert_eq!(entries[0].offset, 0x4000 + 2 * DIRECTORY_ENTRY_SIZE as u64);
+        assert_eq!(entries[0].first_cluster, 3);
+        assert_eq!(entries[0].size, 700);
+        assert_eq!(entries[1].filename, "README.TXT");
+
+        // A stale long name (checksum of another short entry) falls back to 8.3
+        let mut stale = entry_set("old-name.txt", b"OLD-NA~1TXT", 3, 10);
+        let short_at = stale.len() - DIRECTORY_ENTRY_SIZE;
+        stale[short_at..].copy_from_slice(&short_entry(b"NEW     TXT", 3, 10));
+        let (entry, consumed) = parse_entry_set(&stale, 0).unwrap();
+        assert_eq!(consumed, 2);
+        assert_eq!(entry.filename, "NEW.TXT");
+    }
+
+    #[test]
+    fn test_deleted_entry_extracted_contiguously() {
+        let mut image = build_image();
+        let params = find_boot_sector(&image).unwrap();
+
+        let mut set = entry_set("takeout.html", b"TAKEOUT HTM", 4, 600);
+        for slot in set.chunks_mut(DIRECTORY_ENTRY_SIZE) {
+            slot[0] = ENTRY_DELETED;
+        }
+        let (entry, _) = parse_entry_set(&set, 0).unwrap();
+        assert!(entry.is_deleted);
+        assert_eq!(entry.filename, "takeout.html");
+        assert_eq!(entry.short_name, "_AKEOUT.HTM");
+
+        // FAT entries are zeroed, content is in clusters 4 and 5
+        let c4 = cluster_to_offset(&params, 4).unwrap() as usize;
+        image[c4..c4 + SECTOR].fill(b'A');
+        image[c4 + SECTOR..c4 + 2 * SECTOR].fill(b'B');
+        let content = extract_file_content(&image, &params, &entry);
+        assert_eq!(content.len(), 600);
+        assert!(content[..SECTOR].iter().all(|&b| b == b'A'));
+        assert!(content[SECTOR..].iter().all(|&b| b == b'B'));
+    }
+
+    #[test]
+    fn test_extract_follows_fat_chain() {
+        let mut image = build_image();
+        let params = find_boot_sector(&image).unwrap();
+        let fat = params.fat_offset as usize;
+        // 2 -> 7 -> EOC, with reserved top bits set on the first link
+        image[fat + 8..fat + 12].copy_from_slice(&0xF000_0007u32.to_le_bytes());
+        image[fat + 28..fat + 32].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+
+        let c2 = cluster_to_offset(&params, 2).unwrap() as usize;
+        let c7 = cluster_to_offset(&params, 7).unwrap() as usize;
+        image[c2..c2 + 5].copy_from_slice(b"hello");
+        image[c7..c7 + 5].copy_from_slice(b"world");
+
+        let (entry, _) = parse_entry_set(&short_entry(b"CHAIN   BIN", 2, 1000), 0).unwrap();
+        let mut streamed = Vec::new();
+        let written = extract_file_to_writer(&image, &params, &entry, &mut streamed).unwrap();
+        assert_eq!(written, 1000);
+        assert_eq!(&streamed[..5], b"hello");
+        assert_eq!(&streamed[SECTOR..SECTOR + 5], b"world");
+    }
+
+    #[test]
+    fn test_walk_directory_tree() {
+        let mut image = build_image();
+        let params = find_boot_sector(&image).unwrap();
+        let fat = params.fat_offset as usize;
+        image[fat + 8..fat + 12].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+        image[fat + 12..fat + 16].copy_from_slice(&0x0FFF_FFFFu32.to_le_bytes());
+
+        // Root (cluster 2): a file and a subdirectory at cluster 3 holding a deleted file
+        let mut subdir = short_entry(b"PHOTOS     ", 3, 0);
+        subdir[DE_ATTR] = ATTR_DIRECTORY;
+        let root = [short_entry(b"NOTES   TXT", 5, 40), subdir].concat();
+        let mut deleted = short_entry(b"CLIP    MP4", 6, 900);
+        deleted[0] = ENTRY_DELETED;
+        let c2 = cluster_to_offset(&params, 2).unwrap() as usize;
+        let c3 = cluster_to_offset(&params, 3).unwrap() as usize;
+        image[c2..c2 + root.len()].copy_from_slice(&root);
+        image[c3..c3 + deleted.len()].copy_from_slice(&deleted);
+
+        let (entries, regions) = walk_directory_tree(&image, &params);
+        let names: Vec<(&str, bool)> = entries.iter().map(|e| (e.filename.as_str(), e.is_deleted)).collect();
+        assert_eq!(names, [("NOTES.TXT", false), ("PHOTOS", false), ("_LIP.MP4", true)]);
+        assert_eq!(regions, [(c2 as u64, c2 as u64 + 512), (c3 as u64, c3 as u64 + 512)]);
+    }
+}
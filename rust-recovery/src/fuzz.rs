@@ -0,0 +1,152 @@
+//! Fuzzing entry points (feature `fuzzing`)
+//!
+//! Every function takes a raw byte snapshot, the way a damaged disk would hand
+//! it to the parser, and checks the invariants callers rely on. None of them may
+//! panic on any input. The cargo-fuzz harnesses in `fuzz/fuzz_targets` are thin
+//! wrappers around these, so a crash reproduces with a plain unit test as well.
+
+use std::cell::RefCell;
+
+use crate::exfat;
+use crate::fat32;
+use crate::matcher::validator;
+use crate::matcher::EnhancedMatcher;
+use crate::ntfs;
+use crate::recovery;
+use crate::types::Offset;
+
+/// Bytes extracted per entry when following fuzzed cluster chains
+const MAX_FUZZ_EXTRACT: u64 = 1024 * 1024;
+/// Entries extracted per input
+const MAX_FUZZ_ENTRIES: usize = 8;
+
+thread_local! {
+    // Compiling the finder regex per input would dominate the fuzzing loop
+    static MATCHER: RefCell<EnhancedMatcher> = RefCell::new(EnhancedMatcher::new());
+}
+
+/// exFAT directory entry sets: single parse and the carving loop
+pub fn parse_entry_set(data: &[u8]) {
+    if let Some((entry, consumed)) = exfat::parse_entry_set(data, 0) {
+        assert!(consumed >= 3, "entry set shorter than file + stream + name");
+        assert!(consumed * 32 <= data.len(), "entry set consumed past input");
+        assert!(entry.filename.chars().count() <= 255, "name longer than NameLength allows");
+    }
+
+    for entry in exfat::scan_for_entries(data, 0) {
+        assert!(entry.offset < data.len() as u64, "entry offset outside input");
+    }
+}
+
+/// exFAT boot sector at the start of the snapshot, then entries resolved against it
+pub fn parse_boot_sector_at(data: &[u8]) {
+    let Some(params) = exfat::parse_boot_sector_at(data, 0) else {
+        return;
+    };
+    assert!(params.cluster_size.is_power_of_two(), "cluster size not a power of two");
+    assert!(params.sector_size >= 512 && params.sector_size <= 4096, "sector size out of range");
+
+    let mut entries = exfat::scan_for_entries(data, 0);
+    exfat::populate_data_offsets(&mut entries, &params);
+    exfat::mark_suspicious_entries(data, &mut entries, &params);
+
+    for entry in entries.iter().take(MAX_FUZZ_ENTRIES) {
+        let size = entry.size.min(MAX_FUZZ_EXTRACT);
+        let content = exfat::extract_file_content(data, &params, entry.first_cluster, size, entry.no_fat_chain);
+        assert!(content.len() as u64 <= size, "extracted more than the entry size");
+    }
+}
+
+/// NTFS FILE record carving and extraction
+pub fn parse_file_record(data: &[u8]) {
+    let params = ntfs::find_boot_sector(data);
+    let record_size = params.as_ref().map_or(ntfs::DEFAULT_RECORD_SIZE, |p| p.record_size);
+
+    let mut entries = ntfs::scan_for_records(data, 0, record_size);
+    let Some(params) = params else {
+        return;
+    };
+    entries.extend(ntfs::read_mft(data, &params));
+    ntfs::populate_data_offsets(&mut entries, &params);
+
+    for entry in entries.iter_mut().take(MAX_FUZZ_ENTRIES) {
+        entry.size = entry.size.min(MAX_FUZZ_EXTRACT);
+        let content = ntfs::extract_file_content(data, &params, entry);
+        assert!(content.len() as u64 <= entry.size, "extracted more than the entry size");
+    }
+}
+
+/// FAT32 short/long entry carving and chain extraction
+pub fn parse_fat32_entries(data: &[u8]) {
+    let mut entries = fat32::scan_for_entries(data, 0);
+    for entry in &entries {
+        assert!(entry.offset < data.len() as u64, "entry offset outside input");
+        assert!(entry.filename.chars().count() <= 260, "long name exceeds 20 slots");
+    }
+
+    let Some(params) = fat32::find_boot_sector(data) else {
+        return;
+    };
+    fat32::populate_data_offsets(&mut entries, &params);
+    for entry in entries.iter_mut().take(MAX_FUZZ_ENTRIES) {
+        entry.size = entry.size.min(MAX_FUZZ_EXTRACT);
+        let content = fat32::extract_file_content(data, &params, entry);
+        assert!(content.len() as u64 <= entry.size, "extracted more than the entry size");
+    }
+}
+
+/// Link extraction over an arbitrary chunk
+pub fn scan_chunk(data: &[u8]) {
+    MATCHER.with(|matcher| {
+        let mut matcher = matcher.borrow_mut();
+        matcher.clear_cache();
+        for link in matcher.scan_chunk(data, Offset::new(0), true) {
+            assert!(link.offset.as_u64() < data.len() as u64, "link offset outside chunk");
+            assert_eq!(link.video_id.len(), 11, "video id must be 11 characters");
+            assert!((0.0..=1.0).contains(&link.confidence), "confidence out of range");
+        }
+    });
+}
+
+/// JSON validation: full validation implies the quick heuristic
+pub fn is_valid_json(data: &[u8]) {
+    if validator::is_valid_json(data) {
+        assert!(validator::is_probably_json(data), "valid JSON rejected by heuristic");
+    }
+}
+
+/// Content cleaning for text and binary types
+pub fn clean_file_content(data: &[u8]) {
+    let file_types = recovery::FileTypeRegistry::builtin();
+    for file_type in ["txt", "json", "html", "bin"] {
+        let cleaned = file_types.clean(data, file_type);
+        assert!(cleaned.len() <= data.len(), "cleaning grew the content");
+        if file_type != "bin" {
+            assert!(!cleaned.contains(&0), "null byte survived text cleaning");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_targets_accept_edge_inputs() {
+        let inputs: [&[u8]; 4] = [
+            b"",
+            b"\x85\x02\x00",
+            b"{\"titleUrl\": \"https://youtu.be/dQw4w9WgXcQ\"}\x00",
+            &[0xFF; 1024],
+        ];
+        for data in inputs {
+            parse_entry_set(data);
+            parse_boot_sector_at(data);
+            parse_file_record(data);
+            parse_fat32_entries(data);
+            scan_chunk(data);
+            is_valid_json(data);
+            clean_file_content(data);
+        }
+    }
+}
@@ -0,0 +1,522 @@
+//! GPU needle prefilter (`--gpu`, cargo feature "gpu")
+//!
+//! The needle scan of `EnhancedMatcher` runs on an OpenCL device: a chunk is
+//! uploaded, one work item per byte compares the needles starting with that
+//! byte (needles are bucketed by first byte, the first level of an
+//! Aho-Corasick trie) and the hits come back as (offset, needle) pairs. The
+//! CPU then runs the usual window regexes around each hit, so results match
+//! the CPU finder. `libOpenCL` is loaded at run time: a binary built with the
+//! feature still runs on machines without a GPU driver, using the CPU finder.
+//!
+//! Chunks below `GPU_MIN_CHUNK` and chunks with more than `MAX_GPU_HITS`
+//! hits are searched on the CPU, the transfer costs more than it saves.
+
+use crate::error::{RecoveryError, Result};
+use crate::matcher::PatternProfile;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+use std::sync::Mutex;
+
+/// Smallest chunk worth the upload
+pub const GPU_MIN_CHUNK: usize = 1024 * 1024;
+/// Hits one chunk may return; more and the chunk is searched on the CPU
+pub const MAX_GPU_HITS: usize = 1 << 20;
+
+const LIBRARY_NAMES: &[&CStr] = &[c"libOpenCL.so.1", c"libOpenCL.so", c"/System/Library/Frameworks/OpenCL.framework/OpenCL"];
+
+const KERNEL_SOURCE: &str = r#"
+__kernel void needle_prefilter(
+    __global const uchar *data, const uint len,
+    __global const uchar *needles, __global const uint *needle_starts, __global const uint *needle_lens,
+    __global const uint *buckets, const uint fold_case,
+    __global uint *hit_count, __global uint *hits, const uint max_hits)
+{
+    uint pos = get_global_id(0);
+    if (pos >= len) return;
+    uchar first = data[pos];
+    if (fold_case && first >= 'A' && first <= 'Z') first += 32;
+    for (uint n = buckets[first]; n < buckets[first + 1]; n++) {
+        uint start = needle_starts[n];
+        uint nlen = needle_lens[n];
+        if (nlen > len - pos) continue;
+        uint i = 1;
+        for (; i < nlen; i++) {
+            uchar c = data[pos + i];
+            if (fold_case && c >= 'A' && c <= 'Z') c += 32;
+            if (c != needles[start + i]) break;
+        }
+        if (i == nlen) {
+            uint slot = atomic_inc(hit_count);
+            if (slot < max_hits) {
+                hits[2 * slot] = pos;
+                hits[2 * slot + 1] = n;
+            }
+        }
+    }
+}
+"#;
+
+type ClInt = i32;
+type ClUint = u32;
+type Handle = *mut c_void;
+
+const CL_SUCCESS: ClInt = 0;
+const CL_DEVICE_TYPE_GPU: u64 = 1 << 2;
+const CL_MEM_READ_WRITE: u64 = 1 << 0;
+const CL_MEM_READ_ONLY: u64 = 1 << 2;
+const CL_MEM_COPY_HOST_PTR: u64 = 1 << 5;
+const CL_TRUE: ClUint = 1;
+
+/// Needle table in the kernel's layout: needles sorted by (folded) first
+/// byte, `buckets[b]..buckets[b + 1]` are the needles starting with `b`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NeedleTable {
+    pub needles: Vec<Vec<u8>>,
+    pub bytes: Vec<u8>,
+    pub starts: Vec<u32>,
+    pub lens: Vec<u32>,
+    pub buckets: Vec<u32>,
+    pub fold_case: bool,
+}
+
+impl NeedleTable {
+    pub fn new(needles: &[Vec<u8>], fold_case: bool) -> Self {
+        let mut needles: Vec<Vec<u8>> = needles
+            .iter()
+            .filter(|needle| !needle.is_empty())
+            .map(|needle| if fold_case { needle.to_ascii_lowercase() } else { needle.clone() })
+            .collect();
+        needles.sort();
+        needles.dedup();
+
+        let mut table = Self {
+            bytes: Vec::new(),
+            starts: Vec::new(),
+            lens: Vec::new(),
+            buckets: vec![0; 257],
+            fold_case,
+            needles: Vec::new(),
+        };
+        for needle in &needles {
+            table.starts.push(table.bytes.len() as u32);
+            table.lens.push(needle.len() as u32);
+            table.bytes.extend_from_slice(needle);
+            table.buckets[needle[0] as usize + 1] += 1;
+        }
+        for b in 0..256 {
+            table.buckets[b + 1] += table.buckets[b];
+        }
+        table.needles = needles;
+        table
+    }
+
+    /// What the kernel computes, on the CPU: every (offset, needle index) hit
+    pub fn hits(&self, data: &[u8]) -> Vec<(usize, usize)> {
+        let fold = |b: u8| if self.fold_case { b.to_ascii_lowercase() } else { b };
+        let mut hits = Vec::new();
+        for pos in 0..data.len() {
+            let first = fold(data[pos]) as usize;
+            for n in self.buckets[first] as usize..self.buckets[first + 1] as usize {
+                let needle = &self.needles[n];
+                let candidate = &data[pos..data.len().min(pos + needle.len())];
+                if candidate.len() == needle.len() && candidate.iter().zip(needle).all(|(&c, &b)| fold(c) == b) {
+                    hits.push((pos, n));
+                }
+            }
+        }
+        hits
+    }
+}
+
+/// Leftmost non-overlapping hits, the way the CPU finder reports them:
+/// (start, end) of each needle occurrence, the first needle of the table
+/// winning at a position (the finder is leftmost-first over the same order)
+pub fn non_overlapping(table: &NeedleTable, mut hits: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    hits.sort_unstable();
+    let mut spans = Vec::new();
+    let mut next_free = 0;
+    for (pos, n) in hits {
+        if pos >= next_free {
+            let end = pos + table.lens[n] as usize;
+            spans.push((pos, end));
+            next_free = end;
+        }
+    }
+    spans
+}
+
+/// OpenCL entry points used here
+#[allow(clippy::type_complexity)]
+struct OpenCl {
+    library: Handle,
+    get_platform_ids: unsafe extern "C" fn(ClUint, *mut Handle, *mut ClUint) -> ClInt,
+    get_device_ids: unsafe extern "C" fn(Handle, u64, ClUint, *mut Handle, *mut ClUint) -> ClInt,
+    create_context: unsafe extern "C" fn(*const isize, ClUint, *const Handle, *const c_void, *mut c_void, *mut ClInt) -> Handle,
+    create_command_queue: unsafe extern "C" fn(Handle, Handle, u64, *mut ClInt) -> Handle,
+    create_program_with_source: unsafe extern "C" fn(Handle, ClUint, *const *const c_char, *const usize, *mut ClInt) -> Handle,
+    build_program: unsafe extern "C" fn(Handle, ClUint, *const Handle, *const c_char, *const c_void, *mut c_void) -> ClInt,
+    create_kernel: unsafe extern "C" fn(Handle, *const c_char, *mut ClInt) -> Handle,
+    create_buffer: unsafe extern "C" fn(Handle, u64, usize, *mut c_void, *mut ClInt) -> Handle,
+    set_kernel_arg: unsafe extern "C" fn(Handle, ClUint, usize, *const c_void) -> ClInt,
+    enqueue_write_buffer:
+        unsafe extern "C" fn(Handle, Handle, ClUint, usize, usize, *const c_void, ClUint, *const Handle, *mut Handle) -> ClInt,
+    enqueue_read_buffer:
+        unsafe extern "C" fn(Handle, Handle, ClUint, usize, usize, *mut c_void, ClUint, *const Handle, *mut Handle) -> ClInt,
+    enqueue_nd_range_kernel: unsafe extern "C" fn(
+        Handle,
+        Handle,
+        ClUint,
+        *const usize,
+        *const usize,
+        *const usize,
+        ClUint,
+        *const Handle,
+        *mut Handle,
+    ) -> ClInt,
+    finish: unsafe extern "C" fn(Handle) -> ClInt,
+    release_mem_object: unsafe extern "C" fn(Handle) -> ClInt,
+    release_kernel: unsafe extern "C" fn(Handle) -> ClInt,
+    release_program: unsafe extern "C" fn(Handle) -> ClInt,
+    release_command_queue: unsafe extern "C" fn(Handle) -> ClInt,
+    release_context: unsafe extern "C" fn(Handle) -> ClInt,
+}
+
+impl OpenCl {
+    /// None when no OpenCL library is installed
+    // transmute targets are the field types
+    #[allow(clippy::missing_transmute_annotations)]
+    fn load() -> Result<Option<Self>> {
+        let library = LIBRARY_NAMES
+            .iter()
+            .map(|name| unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) })
+            .find(|handle| !handle.is_null());
+        let Some(library) = library else {
+            return Ok(None);
+        };
+        macro_rules! symbol {
+            ($name:literal) => {{
+                let address = unsafe { libc::dlsym(library, concat!($name, "\0").as_ptr() as *const c_char) };
+                if address.is_null() {
+                    return Err(RecoveryError::Config(format!("OpenCL library has no {}", $name)));
+                }
+                unsafe { std::mem::transmute::<*mut c_void, _>(address) }
+            }};
+        }
+        Ok(Some(Self {
+            library,
+            get_platform_ids: symbol!("clGetPlatformIDs"),
+            get_device_ids: symbol!("clGetDeviceIDs"),
+            create_context: symbol!("clCreateContext"),
+            create_command_queue: symbol!("clCreateCommandQueue"),
+            create_program_with_source: symbol!("clCreateProgramWithSource"),
+            build_program: symbol!("clBuildProgram"),
+            create_kernel: symbol!("clCreateKernel"),
+            create_buffer: symbol!("clCreateBuffer"),
+            set_kernel_arg: symbol!("clSetKernelArg"),
+            enqueue_write_buffer: symbol!("clEnqueueWriteBuffer"),
+            enqueue_read_buffer: symbol!("clEnqueueReadBuffer"),
+            enqueue_nd_range_kernel: symbol!("clEnqueueNDRangeKernel"),
+            finish: symbol!("clFinish"),
+            release_mem_object: symbol!("clReleaseMemObject"),
+            release_kernel: symbol!("clReleaseKernel"),
+            release_program: symbol!("clReleaseProgram"),
+            release_command_queue: symbol!("clReleaseCommandQueue"),
+            release_context: symbol!("clReleaseContext"),
+        }))
+    }
+}
+
+fn check(status: ClInt, call: &str) -> Result<()> {
+    if status == CL_SUCCESS {
+        Ok(())
+    } else {
+        Err(RecoveryError::Config(format!("OpenCL {} failed ({})", call, status)))
+    }
+}
+
+/// Device objects; only used under the `GpuPrefilter` mutex
+struct Device {
+    cl: OpenCl,
+    context: Handle,
+    queue: Handle,
+    program: Handle,
+    kernel: Handle,
+    /// Needle table buffers: bytes, starts, lens, buckets
+    tables: [Handle; 4],
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        unsafe {
+            for buffer in self.tables.iter().filter(|buffer| !buffer.is_null()) {
+                (self.cl.release_mem_object)(*buffer);
+            }
+            for (handle, release) in [
+                (self.kernel, self.cl.release_kernel),
+                (self.program, self.cl.release_program),
+                (self.queue, self.cl.release_command_queue),
+                (self.context, self.cl.release_context),
+            ] {
+                if !handle.is_null() {
+                    release(handle);
+                }
+            }
+            libc::dlclose(self.cl.library);
+        }
+    }
+}
+
+/// Buffer released when dropped
+struct Buffer<'a>(&'a OpenCl, Handle);
+
+impl Drop for Buffer<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            (self.0.release_mem_object)(self.1);
+        }
+    }
+}
+
+/// Needle search of one profile on the first OpenCL GPU
+pub struct GpuPrefilter {
+    table: NeedleTable,
+    device: Mutex<Device>,
+}
+
+// Safety: the OpenCL handles are only touched while `device` is locked, and
+// OpenCL objects may be used from any host thread
+unsafe impl Send for GpuPrefilter {}
+unsafe impl Sync for GpuPrefilter {}
+
+impl GpuPrefilter {
+    /// Prefilter for the needles of `profile`; None without an OpenCL GPU
+    /// or when the profile has no needles
+    pub fn for_profile(profile: &PatternProfile) -> Result<Option<Self>> {
+        if profile.needles.is_empty() {
+            return Ok(None);
+        }
+        Self::new(NeedleTable::new(&profile.needles, profile.needles_ignore_case))
+    }
+
+    pub fn new(table: NeedleTable) -> Result<Option<Self>> {
+        let Some(cl) = OpenCl::load()? else {
+            return Ok(None);
+        };
+        unsafe {
+            let mut platforms = [ptr::null_mut(); 8];
+            let mut platform_count = 0;
+            if (cl.get_platform_ids)(platforms.len() as ClUint, platforms.as_mut_ptr(), &mut platform_count) != CL_SUCCESS {
+                return Ok(None);
+            }
+            let mut gpu = None;
+            for &platform in &platforms[..(platform_count as usize).min(platforms.len())] {
+                let mut device = ptr::null_mut();
+                let mut device_count = 0;
+                if (cl.get_device_ids)(platform, CL_DEVICE_TYPE_GPU, 1, &mut device, &mut device_count) == CL_SUCCESS
+                    && device_count > 0
+                {
+                    gpu = Some(device);
+                    break;
+                }
+            }
+            let Some(gpu) = gpu else {
+                return Ok(None);
+            };
+
+            let mut device = Device {
+                context: ptr::null_mut(),
+                queue: ptr::null_mut(),
+                program: ptr::null_mut(),
+                kernel: ptr::null_mut(),
+                tables: [ptr::null_mut(); 4],
+                cl,
+            };
+            let cl = &device.cl;
+            let mut status = CL_SUCCESS;
+            device.context = (cl.create_context)(ptr::null(), 1, &gpu, ptr::null(), ptr::null_mut(), &mut status);
+            check(status, "clCreateContext")?;
+            device.queue = (cl.create_command_queue)(device.context, gpu, 0, &mut status);
+            check(status, "clCreateCommandQueue")?;
+
+            let source = CString::new(KERNEL_SOURCE).unwrap_or_default();
+            let source_ptr = source.as_ptr();
+            device.program =
+                (cl.create_program_with_source)(device.context, 1, &source_ptr, ptr::null(), &mut status);
+            check(status, "clCreateProgramWithSource")?;
+            check(
+                (cl.build_program)(device.program, 1, &gpu, c"".as_ptr(), ptr::null(), ptr::null_mut()),
+                "clBuildProgram",
+            )?;
+            device.kernel = (cl.create_kernel)(device.program, c"needle_prefilter".as_ptr(), &mut status);
+            check(status, "clCreateKernel")?;
+
+            let tables: [&[u8]; 4] = [
+                &table.bytes,
+                as_bytes(&table.starts),
+                as_bytes(&table.lens),
+                as_bytes(&table.buckets),
+            ];
+            for (slot, data) in device.tables.iter_mut().zip(tables) {
+                *slot = (cl.create_buffer)(
+                    device.context,
+                    CL_MEM_READ_ONLY | CL_MEM_COPY_HOST_PTR,
+                    data.len().max(1),
+                    data.as_ptr() as *mut c_void,
+                    &mut status,
+                );
+                check(status, "clCreateBuffer")?;
+            }
+            Ok(Some(Self { table, device: Mutex::new(device) }))
+        }
+    }
+
+    pub fn table(&self) -> &NeedleTable {
+        &self.table
+    }
+
+    /// Needle occurrences in `data` as the CPU finder reports them; None when
+    /// the chunk is better searched on the CPU (too small, too many hits)
+    pub fn find(&self, data: &[u8]) -> Result<Option<Vec<(usize, usize)>>> {
+        if data.len() < GPU_MIN_CHUNK || data.len() > u32::MAX as usize {
+            return Ok(None);
+        }
+        let device = self.device.lock().map_err(|_| RecoveryError::Config("GPU prefilter poisoned".to_string()))?;
+        let Some(hits) = (unsafe { self.run(&device, data)? }) else {
+            return Ok(None);
+        };
+        Ok(Some(non_overlapping(&self.table, hits)))
+    }
+
+    unsafe fn run(&self, device: &Device, data: &[u8]) -> Result<Option<Vec<(usize, usize)>>> {
+        let cl = &device.cl;
+        let mut status = CL_SUCCESS;
+        let buffer = |flags: u64, size: usize, host: *mut c_void, status: &mut ClInt| {
+            Buffer(cl, (cl.create_buffer)(device.context, flags, size, host, status))
+        };
+        let input = buffer(CL_MEM_READ_ONLY, data.len(), ptr::null_mut(), &mut status);
+        check(status, "clCreateBuffer")?;
+        let mut count = 0u32;
+        let counter = buffer(CL_MEM_READ_WRITE | CL_MEM_COPY_HOST_PTR, 4, &mut count as *mut u32 as *mut c_void, &mut status);
+        check(status, "clCreateBuffer")?;
+        let output = buffer(CL_MEM_READ_WRITE, MAX_GPU_HITS * 8, ptr::null_mut(), &mut status);
+        check(status, "clCreateBuffer")?;
+
+        check(
+            (cl.enqueue_write_buffer)(
+                device.queue,
+                input.1,
+                CL_TRUE,
+                0,
+                data.len(),
+                data.as_ptr() as *const c_void,
+                0,
+                ptr::null(),
+                ptr::null_mut(),
+            ),
+            "clEnqueueWriteBuffer",
+        )?;
+
+        let len = data.len() as u32;
+        let fold_case = self.table.fold_case as u32;
+        let max_hits = MAX_GPU_HITS as u32;
+        let args: [(usize, *const c_void); 10] = [
+            (size_of::<Handle>(), &input.1 as *const Handle as *const c_void),
+            (4, &len as *const u32 as *const c_void),
+            (size_of::<Handle>(), &device.tables[0] as *const Handle as *const c_void),
+            (size_of::<Handle>(), &device.tables[1] as *const Handle as *const c_void),
+            (size_of::<Handle>(), &device.tables[2] as *const Handle as *const c_void),
+            (size_of::<Handle>(), &device.tables[3] as *const Handle as *const c_void),
+            (4, &fold_case as *const u32 as *const c_void),
+            (size_of::<Handle>(), &counter.1 as *const Handle as *const c_void),
+            (size_of::<Handle>(), &output.1 as *const Handle as *const c_void),
+            (4, &max_hits as *const u32 as *const c_void),
+        ];
+        for (index, (size, value)) in args.iter().enumerate() {
+            check((cl.set_kernel_arg)(device.kernel, index as ClUint, *size, *value), "clSetKernelArg")?;
+        }
+
+        let global = data.len();
+        check(
+            (cl.enqueue_nd_range_kernel)(
+                device.queue,
+                device.kernel,
+                1,
+                ptr::null(),
+                &global,
+                ptr::null(),
+                0,
+                ptr::null(),
+                ptr::null_mut(),
+            ),
+            "clEnqueueNDRangeKernel",
+        )?;
+        check((cl.finish)(device.queue), "clFinish")?;
+
+        check(
+            (cl.enqueue_read_buffer)(
+                device.queue,
+                counter.1,
+                CL_TRUE,
+                0,
+                4,
+                &mut count as *mut u32 as *mut c_void,
+                0,
+                ptr::null(),
+                ptr::null_mut(),
+            ),
+            "clEnqueueReadBuffer",
+        )?;
+        if count as usize > MAX_GPU_HITS {
+            return Ok(None);
+        }
+        let mut raw = vec![0u32; count as usize * 2];
+        if count > 0 {
+            check(
+                (cl.enqueue_read_buffer)(
+                    device.queue,
+                    output.1,
+                    CL_TRUE,
+                    0,
+                    raw.len() * 4,
+                    raw.as_mut_ptr() as *mut c_void,
+                    0,
+                    ptr::null(),
+                    ptr::null_mut(),
+                ),
+                "clEnqueueReadBuffer",
+            )?;
+        }
+        Ok(Some(raw.chunks_exact(2).map(|hit| (hit[0] as usize, hit[1] as usize)).collect()))
+    }
+}
+
+fn as_bytes(values: &[u32]) -> &[u8] {
+    // Safety: u32 has no padding and u8 has alignment 1
+    unsafe { std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needle_table_matches_finder() {
+        let profile = PatternProfile::youtube();
+        let table = NeedleTable::new(&profile.needles, profile.needles_ignore_case);
+        assert_eq!(table.buckets[256] as usize, table.needles.len());
+
+        let data = b"<a href=\"https://YouTube.com/watch?v=abc\">x</a> embed/xyz youtu.be/q video_id=1 /v/ shorts/";
+        let gpu = non_overlapping(&table, table.hits(data));
+        let finder = profile.finder.as_ref().unwrap();
+        let cpu: Vec<(usize, usize)> = finder.find_iter(data).map(|m| (m.start(), m.end())).collect();
+        assert_eq!(gpu, cpu);
+
+        // Runs wherever a GPU driver is installed, otherwise reports none
+        if let Ok(Some(prefilter)) = GpuPrefilter::for_profile(&profile) {
+            let mut chunk = vec![b' '; GPU_MIN_CHUNK];
+            chunk[5000..5000 + data.len()].copy_from_slice(data);
+            let expected: Vec<(usize, usize)> = cpu.iter().map(|&(s, e)| (s + 5000, e + 5000)).collect();
+            assert_eq!(prefilter.find(&chunk).unwrap(), Some(expected));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Fuzzy hashing of recovered files
+//!
+//! Context-triggered piecewise hashes in the style of ssdeep: a rolling hash
+//! over a 7-byte window cuts the content into pieces wherever it hits a
+//! trigger value, and each piece adds one base64 character to the signature.
+//! An insertion or a changed byte only changes the characters of the pieces
+//! around it, so near-identical files (the same fragments assembled with a
+//! slightly different tail, or cleaned differently) get similar signatures.
+//! The format is `block_size:signature:signature_at_double_block_size`.
+
+const ROLLING_WINDOW: usize = 7;
+const MIN_BLOCK_SIZE: u32 = 3;
+/// Characters of the first signature
+pub const SIGNATURE_LENGTH: usize = 64;
+const HASH_PRIME: u32 = 0x0100_0193;
+const HASH_INIT: u32 = 0x2802_1967;
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Default)]
+struct RollingHash {
+    window: [u8; ROLLING_WINDOW],
+    h1: u32,
+    h2: u32,
+    h3: u32,
+    n: usize,
+}
+
+impl RollingHash {
+    fn update(&mut self, byte: u8) -> u32 {
+        let slot = self.n % ROLLING_WINDOW;
+        self.h2 = self.h2.wrapping_sub(self.h1).wrapping_add(ROLLING_WINDOW as u32 * byte as u32);
+        self.h1 = self.h1.wrapping_add(byte as u32).wrapping_sub(self.window[slot] as u32);
+        self.window[slot] = byte;
+        self.n += 1;
+        self.h3 = (self.h3 << 5) ^ byte as u32;
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+}
+
+fn piece_hash(hash: u32, byte: u8) -> u32 {
+    hash.wrapping_mul(HASH_PRIME) ^ byte as u32
+}
+
+/// Signatures of `data` at `block_size` and twice that
+fn signatures(data: &[u8], block_size: u32) -> (String, String) {
+    let mut roll = RollingHash::default();
+    let (mut h1, mut h2) = (HASH_INIT, HASH_INIT);
+    let (mut sig1, mut sig2) = (String::new(), String::new());
+    for &byte in data {
+        h1 = piece_hash(h1, byte);
+        h2 = piece_hash(h2, byte);
+        let rolled = roll.update(byte);
+        if rolled % block_size == block_size - 1 && sig1.len() < SIGNATURE_LENGTH - 1 {
+            sig1.push(BASE64[(h1 % 64) as usize] as char);
+            h1 = HASH_INIT;
+        }
+        if rolled % (block_size * 2) == block_size * 2 - 1 && sig2.len() < SIGNATURE_LENGTH / 2 - 1 {
+            sig2.push(BASE64[(h2 % 64) as usize] as char);
+            h2 = HASH_INIT;
+        }
+    }
+    // The last piece ends with the data
+    if roll.n > 0 {
+        sig1.push(BASE64[(h1 % 64) as usize] as char);
+        sig2.push(BASE64[(h2 % 64) as usize] as char);
+    }
+    (sig1, sig2)
+}
+
+/// Fuzzy hash of `data` (empty for empty data)
+pub fn fuzzy_hash(data: &[u8]) -> String {
+    if data.is_empty() {
+        return String::new();
+    }
+    let mut block_size = MIN_BLOCK_SIZE;
+    while (block_size as u64) * (SIGNATURE_LENGTH as u64) < data.len() as u64 {
+        block_size *= 2;
+    }
+    loop {
+        let (sig1, sig2) = signatures(data, block_size);
+        // Too few pieces: a smaller block size describes the content better
+        if sig1.len() >= SIGNATURE_LENGTH / 2 || block_size <= MIN_BLOCK_SIZE {
+            return format!("{}:{}:{}", block_size, sig1, sig2);
+        }
+        block_size /= 2;
+    }
+}
+
+fn parse(hash: &str) -> Option<(u64, &str, &str)> {
+    let mut parts = hash.splitn(3, ':');
+    let block_size = parts.next()?.parse().ok()?;
+    Some((block_size, parts.next()?, parts.next()?))
+}
+
+/// Runs of more than 3 equal characters carry no information
+fn squeeze(signature: &str) -> Vec<u8> {
+    let mut squeezed: Vec<u8> = Vec::with_capacity(signature.len());
+    for byte in signature.bytes() {
+        let len = squeezed.len();
+        if len < 3 || squeezed[len - 3..].iter().any(|&b| b != byte) {
+            squeezed.push(byte);
+        }
+    }
+    squeezed
+}
+
+/// Edit distance with insertions and deletions costing 1, substitutions 2
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, &x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, &y) in b.iter().enumerate() {
+            let substitute = previous[j] + if x == y { 0 } else { 2 };
+            current[j + 1] = substitute.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// 0-100 score of two signatures at `block_size`; 0 unless they share a run
+/// of 7 characters (one rolling window)
+fn score_signatures(a: &[u8], b: &[u8], block_size: u64) -> u8 {
+    if a.len() < ROLLING_WINDOW || b.len() < ROLLING_WINDOW {
+        return 0;
+    }
+    let shares_window = a.windows(ROLLING_WINDOW).any(|window| b.windows(ROLLING_WINDOW).any(|other| other == window));
+    if !shares_window {
+        return 0;
+    }
+    let distance = edit_distance(a, b);
+    let score = 100 - (distance * 100 / (a.len() + b.len())).min(100) as u64;
+    // Small files have few, short pieces: matching signatures say little
+    let cap = block_size / MIN_BLOCK_SIZE as u64 * a.len().min(b.len()) as u64;
+    score.min(cap) as u8
+}
+
+/// Similarity of two fuzzy hashes, 0 (unrelated) to 100 (same signature);
+/// hashes whose block sizes differ by more than a factor of 2 score 0, and
+/// small files at most `block_size / 3` per signature character
+pub fn similarity(left: &str, right: &str) -> u8 {
+    let (Some((size_a, a1, a2)), Some((size_b, b1, b2))) = (parse(left), parse(right)) else {
+        return 0;
+    };
+    let (a1, a2, b1, b2) = (squeeze(a1), squeeze(a2), squeeze(b1), squeeze(b2));
+    if size_a == size_b {
+        score_signatures(&a1, &b1, size_a).max(score_signatures(&a2, &b2, size_a * 2))
+    } else if size_a * 2 == size_b {
+        score_signatures(&a2, &b1, size_b)
+    } else if size_b * 2 == size_a {
+        score_signatures(&a1, &b2, size_a)
+    } else {
+        0
+    }
+}
+
+/// Whether two hashes can score above 0 (block sizes equal or a factor of 2 apart)
+pub fn comparable(left: &str, right: &str) -> bool {
+    match (parse(left), parse(right)) {
+        (Some((a, ..)), Some((b, ..))) => a == b || a * 2 == b || b * 2 == a,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_hash_similarity() {
+        let text: Vec<u8> = (0..4000)
+            .flat_map(|i| format!("{{\"id\": {}, \"title\": \"Clip number {}\", \"views\": {}}}\n", i, i * 7, i * 31).into_bytes())
+            .collect();
+        let hash = fuzzy_hash(&text);
+        assert_eq!(hash, fuzzy_hash(&text));
+        assert!(hash.split(':').nth(1).unwrap().len() >= SIGNATURE_LENGTH / 2, "{}", hash);
+        assert_eq!(similarity(&hash, &hash), 100);
+
+        // A few changed bytes and a longer tail: still near-identical
+        let mut edited = text.clone();
+        edited[50_000..50_010].copy_from_slice(b"##########");
+        edited.extend_from_slice(&text[..2000]);
+        let score = similarity(&hash, &fuzzy_hash(&edited));
+        assert!(score >= 90, "{}", score);
+
+        let other: Vec<u8> = (0..text.len() as u32).map(|i| (i.wrapping_mul(2654435761) >> 11) as u8).collect();
+        assert_eq!(similarity(&hash, &fuzzy_hash(&other)), 0);
+        assert_eq!(fuzzy_hash(b""), "");
+        assert_eq!(similarity("", &hash), 0);
+        assert!(comparable("96:a:b", "192:c:d") && !comparable("96:a:b", "384:c:d"));
+
+        // A few short pieces only: equal signatures are weak evidence
+        let tiny = fuzzy_hash(b"\x89PNG\r\n\x1a\n tiny image");
+        assert!(similarity(&tiny, &tiny) < 50);
+    }
+}
@@ -0,0 +1,200 @@
+//! Housekeeping for long scans: temp files, log rotation, output space
+//!
+//! - Atomic writes (checkpoint, `progress.json`) go through a `.tmp` sibling
+//!   renamed into place; a run killed in between leaves the `.tmp` behind.
+//!   `remove_orphaned_temp_files` clears them when the next run starts.
+//! - The append-only logs (`fragments.jsonl`, `recovered_files.jsonl`,
+//!   `post_process.log`) are `RotatingFile`s: past `--log-max-mb` the file
+//!   moves to `NAME.1` (older ones to `NAME.2`, `NAME.3`) and a new one starts.
+//! - `SpaceWatch` warns when the filesystem of the output directory runs low,
+//!   before the recovered files fill the drive.
+
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Suffix of the temporary file of an atomic write
+pub const TEMP_SUFFIX: &str = ".tmp";
+/// Rotated generations kept next to a log (`NAME.1` newest)
+pub const ROTATED_LOGS_KEPT: usize = 3;
+/// Free space on the output filesystem below which `SpaceWatch` warns
+pub const LOW_SPACE_BYTES: u64 = 1 << 30;
+/// Time between two free-space checks
+pub const SPACE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Chunk statistics rows held for `chunk_stats.csv`; later chunks are not recorded
+pub const MAX_CHUNK_STATS_ROWS: usize = 1_000_000;
+/// Fragments held for assembly (about 1 KB each) before the scan warns
+pub const PENDING_FRAGMENTS_WARNING: usize = 100_000;
+
+/// Delete `*.tmp` files left in `dir` and its `reports` directory by an
+/// interrupted run; returns the files removed
+pub fn remove_orphaned_temp_files(dir: &Path) -> Vec<PathBuf> {
+    let mut removed = Vec::new();
+    for dir in [dir.to_path_buf(), dir.join("reports")] {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let orphaned = entry.file_type().is_ok_and(|kind| kind.is_file())
+                && path.file_name().is_some_and(|name| name.to_string_lossy().ends_with(TEMP_SUFFIX));
+            if orphaned && fs::remove_file(&path).is_ok() {
+                removed.push(path);
+            }
+        }
+    }
+    removed.sort();
+    removed
+}
+
+/// Bytes available to this user on the filesystem holding `path`
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    Some((stats.f_bavail as u64).saturating_mul(stats.f_frsize as u64))
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Periodic free-space check of the output filesystem
+#[derive(Debug)]
+pub struct SpaceWatch {
+    path: PathBuf,
+    threshold: u64,
+    last_check: Option<Instant>,
+    low: bool,
+}
+
+impl SpaceWatch {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), threshold: LOW_SPACE_BYTES, last_check: None, low: false }
+    }
+
+    pub fn with_threshold(mut self, bytes: u64) -> Self {
+        self.threshold = bytes;
+        self
+    }
+
+    /// Warning when free space dropped below the threshold since the last
+    /// warning (it re-arms once twice the threshold is free again); checks at
+    /// most every `SPACE_CHECK_INTERVAL`
+    pub fn poll(&mut self) -> Option<String> {
+        if self.last_check.is_some_and(|last| last.elapsed() < SPACE_CHECK_INTERVAL) {
+            return None;
+        }
+        self.last_check = Some(Instant::now());
+        let available = available_space(&self.path)?;
+        if available >= self.threshold.saturating_mul(2) {
+            self.low = false;
+        }
+        if available >= self.threshold || self.low {
+            return None;
+        }
+        self.low = true;
+        Some(format!(
+            "Output filesystem of {} has {} MB free; recovered files and logs may fail to write. \
+             Free space or stop the scan (--resume continues it)",
+            self.path.display(),
+            available / (1024 * 1024)
+        ))
+    }
+}
+
+/// Append-only log file rotated at a size limit
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    max_bytes: Option<u64>,
+}
+
+impl RotatingFile {
+    /// Create (or truncate) the log; rotated files of an earlier run are removed
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        for generation in 1..=ROTATED_LOGS_KEPT {
+            let _ = fs::remove_file(rotated_path(path, generation));
+        }
+        Ok(Self { path: path.to_path_buf(), file: File::create(path)?, max_bytes: None })
+    }
+
+    /// Open the log for appending, keeping earlier content
+    pub fn append(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { path: path.to_path_buf(), file, max_bytes: None })
+    }
+
+    /// Rotate once the file reaches `max_bytes` (None = never)
+    pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_bytes = max_bytes.filter(|&bytes| bytes > 0);
+        self
+    }
+
+    /// The file to write the next record to, rotated first if it is full
+    pub fn file(&mut self) -> std::io::Result<&mut File> {
+        let full = self.max_bytes.is_some_and(|max| self.file.metadata().is_ok_and(|meta| meta.len() >= max));
+        if full {
+            for generation in (1..ROTATED_LOGS_KEPT).rev() {
+                let _ = fs::rename(rotated_path(&self.path, generation), rotated_path(&self.path, generation + 1));
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            self.file = File::create(&self.path)?;
+        }
+        Ok(&mut self.file)
+    }
+}
+
+/// `NAME.N`
+pub fn rotated_path(path: &Path, generation: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", generation));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_temp_cleanup_and_rotation() {
+        let dir = std::env::temp_dir().join(format!("housekeeping_{}", std::process::id()));
+        fs::create_dir_all(dir.join("reports")).unwrap();
+        fs::write(dir.join("scan_checkpoint.tmp"), b"{").unwrap();
+        fs::write(dir.join("progress.json.tmp"), b"{").unwrap();
+        fs::write(dir.join("reports").join("report.json.tmp"), b"{").unwrap();
+        fs::write(dir.join("scan_checkpoint.json"), b"{}").unwrap();
+        let removed = remove_orphaned_temp_files(&dir);
+        assert_eq!(removed.len(), 3);
+        assert!(dir.join("scan_checkpoint.json").exists());
+
+        // 10-byte records, rotated past 25 bytes
+        let path = dir.join("fragments.jsonl");
+        let mut log = RotatingFile::create(&path).unwrap().with_max_bytes(Some(25));
+        for i in 0..20 {
+            log.file().unwrap().write_all(format!("record {:02}\n", i).as_bytes()).unwrap();
+        }
+        drop(log);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "record 18\nrecord 19\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "record 15\nrecord 16\nrecord 17\n");
+        assert!(rotated_path(&path, ROTATED_LOGS_KEPT).exists());
+        assert!(!rotated_path(&path, ROTATED_LOGS_KEPT + 1).exists());
+
+        // A new run starts over
+        RotatingFile::create(&path).unwrap();
+        assert!(!rotated_path(&path, 1).exists());
+
+        let watch = SpaceWatch::new(&dir).with_threshold(u64::MAX).poll();
+        let _ = fs::remove_dir_all(&dir);
+        if available_space(Path::new(".")).is_some() {
+            assert!(watch.unwrap().contains("MB free"));
+        }
+    }
+}
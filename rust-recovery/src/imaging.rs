@@ -0,0 +1,533 @@
+//! Disk-to-disk imaging of failing media (`rust-recovery image`)
+//!
+//! The source is copied to a destination image or device in passes, easiest
+//! data first, so a dying drive gives up its readable areas before it is
+//! stressed with retries:
+//!
+//! 1. copy: blocks in order; a failed block is marked non-trimmed and the
+//!    next `skip_size` bytes are left for later
+//! 2. copy: the areas skipped in pass 1, without skipping
+//! 3. scrape: failed blocks sector by sector; sectors that still fail are bad
+//! 4. retry: bad sectors read again, `retries` times
+//!
+//! Progress is kept in a GNU ddrescue compatible mapfile (`?` non-tried,
+//! `*` non-trimmed, `-` bad sector, `+` finished), saved every few seconds
+//! and after every pass. Running again with the same mapfile continues where
+//! the last session stopped; an interrupted session only repeats the reads
+//! since the last save. Bad sectors stay zero in a new destination.
+
+use crate::error::{RecoveryError, Result};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::time::{Duration, Instant};
+
+/// Minimum time between mapfile saves within a pass
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// State of a mapfile block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockStatus {
+    NonTried,
+    /// Failed as part of a larger read; not yet read sector by sector
+    NonTrimmed,
+    /// Sector failed on its own
+    BadSector,
+    Finished,
+}
+
+impl BlockStatus {
+    pub fn symbol(self) -> char {
+        match self {
+            Self::NonTried => '?',
+            Self::NonTrimmed => '*',
+            Self::BadSector => '-',
+            Self::Finished => '+',
+        }
+    }
+
+    /// ddrescue's non-scraped (`/`) areas are read sector by sector like non-trimmed ones
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '?' => Some(Self::NonTried),
+            '*' | '/' => Some(Self::NonTrimmed),
+            '-' => Some(Self::BadSector),
+            '+' => Some(Self::Finished),
+            _ => None,
+        }
+    }
+}
+
+/// Pass of the imaging session, recorded as the mapfile's current status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Copying,
+    Scraping,
+    Retrying,
+    Finished,
+}
+
+impl Phase {
+    pub fn symbol(self) -> char {
+        match self {
+            Self::Copying => '?',
+            Self::Scraping => '/',
+            Self::Retrying => '-',
+            Self::Finished => '+',
+        }
+    }
+
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '?' => Some(Self::Copying),
+            '*' | '/' => Some(Self::Scraping),
+            '-' => Some(Self::Retrying),
+            '+' => Some(Self::Finished),
+            // Filling and generating modes of ddrescue
+            'F' | 'G' => Some(Self::Copying),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapBlock {
+    pub pos: u64,
+    pub size: u64,
+    pub status: BlockStatus,
+}
+
+/// Status of every byte of the source, as contiguous blocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RescueMap {
+    size: u64,
+    blocks: Vec<MapBlock>,
+    pub current_pos: u64,
+    pub phase: Phase,
+    pub pass: u32,
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+impl RescueMap {
+    /// Map of a source of `size` bytes, nothing tried yet
+    pub fn new(size: u64) -> Self {
+        let blocks = if size > 0 { vec![MapBlock { pos: 0, size, status: BlockStatus::NonTried }] } else { Vec::new() };
+        Self { size, blocks, current_pos: 0, phase: Phase::Copying, pass: 1 }
+    }
+
+    /// Read a ddrescue mapfile of a source of `size` bytes; blocks missing
+    /// at the end are non-tried
+    pub fn parse(text: &str, size: u64) -> Result<Self> {
+        let invalid = |line: &str| RecoveryError::Parse(format!("Invalid mapfile line: {}", line));
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        let status_line = lines.next().ok_or_else(|| RecoveryError::Parse("Empty mapfile".to_string()))?;
+        let fields: Vec<&str> = status_line.split_whitespace().collect();
+        let current_pos = fields.first().and_then(|f| parse_number(f)).ok_or_else(|| invalid(status_line))?;
+        let phase = fields
+            .get(1)
+            .and_then(|f| f.chars().next())
+            .and_then(Phase::from_symbol)
+            .ok_or_else(|| invalid(status_line))?;
+        let pass = fields.get(2).and_then(|f| f.parse().ok()).unwrap_or(1);
+
+        let mut map = Self { size, blocks: Vec::new(), current_pos, phase, pass };
+        let mut end = 0;
+        for line in lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (Some(pos), Some(block_size), Some(status)) = (
+                fields.first().and_then(|f| parse_number(f)),
+                fields.get(1).and_then(|f| parse_number(f)),
+                fields.get(2).and_then(|f| f.chars().next()).and_then(BlockStatus::from_symbol),
+            ) else {
+                return Err(invalid(line));
+            };
+            if pos != end || pos + block_size > size {
+                return Err(RecoveryError::Parse(format!(
+                    "Mapfile block at 0x{:X} does not follow 0x{:X} within the source size 0x{:X}",
+                    pos, end, size
+                )));
+            }
+            map.push(MapBlock { pos, size: block_size, status });
+            end = pos + block_size;
+        }
+        if end < size {
+            map.push(MapBlock { pos: end, size: size - end, status: BlockStatus::NonTried });
+        }
+        Ok(map)
+    }
+
+    /// Mapfile text; `header` lines are written as comments first
+    pub fn render(&self, header: &[String]) -> String {
+        let mut text: String = header.iter().map(|line| format!("# {}\n", line)).collect();
+        text.push_str("# current_pos  current_status  current_pass\n");
+        text.push_str(&format!("0x{:08X}     {}               {}\n", self.current_pos, self.phase.symbol(), self.pass));
+        text.push_str("#      pos        size  status\n");
+        for block in &self.blocks {
+            text.push_str(&format!("0x{:08X}  0x{:08X}  {}\n", block.pos, block.size, block.status.symbol()));
+        }
+        text
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn blocks(&self) -> &[MapBlock] {
+        &self.blocks
+    }
+
+    /// Append a block, merging it with the last one when the status matches
+    fn push(&mut self, block: MapBlock) {
+        if block.size == 0 {
+            return;
+        }
+        match self.blocks.last_mut() {
+            Some(last) if last.status == block.status => last.size += block.size,
+            _ => self.blocks.push(block),
+        }
+    }
+
+    /// Start a block at `at` if one spans it
+    fn split_at(&mut self, at: u64) {
+        let index = self.blocks.partition_point(|block| block.pos + block.size <= at);
+        if let Some(block) = self.blocks.get(index).copied() {
+            if block.pos < at {
+                self.blocks[index].size = at - block.pos;
+                self.blocks.insert(index + 1, MapBlock { pos: at, size: block.pos + block.size - at, status: block.status });
+            }
+        }
+    }
+
+    /// Mark `[pos, pos + size)` with `status`
+    pub fn set(&mut self, pos: u64, size: u64, status: BlockStatus) {
+        let end = pos.saturating_add(size).min(self.size);
+        if pos >= end {
+            return;
+        }
+        self.split_at(pos);
+        self.split_at(end);
+        let first = self.blocks.partition_point(|block| block.pos < pos);
+        let last = self.blocks.partition_point(|block| block.pos < end);
+        self.blocks.splice(first..last, [MapBlock { pos, size: end - pos, status }]);
+
+        // Merge with equal neighbours
+        let mut index = first;
+        if index > 0 && self.blocks[index - 1].status == status {
+            self.blocks[index - 1].size += self.blocks[index].size;
+            self.blocks.remove(index);
+            index -= 1;
+        }
+        if index + 1 < self.blocks.len() && self.blocks[index + 1].status == status {
+            self.blocks[index].size += self.blocks[index + 1].size;
+            self.blocks.remove(index + 1);
+        }
+    }
+
+    /// `(pos, size)` of the blocks with `status`, in order
+    pub fn ranges(&self, status: BlockStatus) -> Vec<(u64, u64)> {
+        self.blocks.iter().filter(|block| block.status == status).map(|block| (block.pos, block.size)).collect()
+    }
+
+    pub fn bytes(&self, status: BlockStatus) -> u64 {
+        self.blocks.iter().filter(|block| block.status == status).map(|block| block.size).sum()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.bytes(BlockStatus::Finished) == self.size
+    }
+}
+
+/// Device or image being rescued
+pub trait SectorSource {
+    fn size(&self) -> u64;
+    /// Fill `buf` from `offset`; any error marks the whole read as failed
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()>;
+}
+
+/// Source read with pread (files and block devices)
+pub struct FileSource {
+    file: std::fs::File,
+    size: u64,
+}
+
+impl FileSource {
+    pub fn open(path: &std::path::Path) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let size = crate::disk::image_len(&file)?;
+        Ok(Self { file, size })
+    }
+}
+
+impl SectorSource for FileSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        crate::disk::read_exact_at(&self.file, buf, offset)
+    }
+}
+
+/// How hard to try
+#[derive(Debug, Clone)]
+pub struct ImagingPolicy {
+    /// Read size of the copy passes (a multiple of `sector_size`)
+    pub block_size: usize,
+    pub sector_size: usize,
+    /// Bytes left for pass 2 after a failed read in pass 1 (0 = no skipping)
+    pub skip_size: u64,
+    /// Read failed blocks sector by sector (off: they stay non-trimmed)
+    pub scrape: bool,
+    /// Extra passes over bad sectors
+    pub retries: u32,
+}
+
+impl Default for ImagingPolicy {
+    fn default() -> Self {
+        Self { block_size: 64 * 1024, sector_size: 512, skip_size: 1024 * 1024, scrape: true, retries: 0 }
+    }
+}
+
+/// Mapfile writer of an imaging session
+type SaveMap<'a> = Box<dyn FnMut(&RescueMap) -> io::Result<()> + 'a>;
+
+/// Copies the source into the destination and keeps the map current
+pub struct Imager<'a, S: SectorSource, W: Write + Seek> {
+    source: &'a S,
+    dest: W,
+    policy: ImagingPolicy,
+    map: RescueMap,
+    /// Called with the map every `SAVE_INTERVAL` and after every pass
+    save: SaveMap<'a>,
+    last_save: Instant,
+}
+
+impl<'a, S: SectorSource, W: Write + Seek> Imager<'a, S, W> {
+    pub fn new(
+        source: &'a S,
+        dest: W,
+        policy: ImagingPolicy,
+        map: RescueMap,
+        save: impl FnMut(&RescueMap) -> io::Result<()> + 'a,
+    ) -> Self {
+        Self { source, dest, policy, map, save: Box::new(save), last_save: Instant::now() }
+    }
+
+    pub fn map(&self) -> &RescueMap {
+        &self.map
+    }
+
+    /// Run the passes still pending in the map; `report` gets one line per pass
+    pub fn run(&mut self, report: &mut dyn FnMut(String)) -> io::Result<()> {
+        let passes: [(&str, Phase, BlockStatus); 3] = [
+            ("copy", Phase::Copying, BlockStatus::NonTried),
+            ("scrape", Phase::Scraping, BlockStatus::NonTrimmed),
+            ("retry", Phase::Retrying, BlockStatus::BadSector),
+        ];
+        let mut pass = 0;
+        for (name, phase, status) in passes {
+            let rounds = match phase {
+                Phase::Copying => 2,
+                Phase::Scraping => self.policy.scrape as u32,
+                _ => self.policy.retries,
+            };
+            for round in 0..rounds {
+                pass += 1;
+                if self.map.bytes(status) == 0 {
+                    continue;
+                }
+                let before = self.map.bytes(BlockStatus::Finished);
+                self.map.phase = phase;
+                self.map.pass = pass;
+                match phase {
+                    Phase::Copying => {
+                        let skip = if round == 0 { self.policy.skip_size } else { 0 };
+                        self.copy_pass(skip)?;
+                    }
+                    _ => self.sector_pass(status)?,
+                }
+                self.save_map()?;
+                report(format!(
+                    "Pass {} ({}): {:.1} MB rescued, {} bytes bad, {} bytes non-tried, {} bytes non-trimmed",
+                    pass,
+                    name,
+                    (self.map.bytes(BlockStatus::Finished) - before) as f64 / (1024.0 * 1024.0),
+                    self.map.bytes(BlockStatus::BadSector),
+                    self.map.bytes(BlockStatus::NonTried),
+                    self.map.bytes(BlockStatus::NonTrimmed)
+                ));
+            }
+        }
+        self.map.phase = Phase::Finished;
+        self.map.current_pos = self.map.size();
+        self.save_map()
+    }
+
+    fn save_map(&mut self) -> io::Result<()> {
+        self.dest.flush()?;
+        (self.save)(&self.map)?;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+
+    fn save_if_due(&mut self) -> io::Result<()> {
+        if self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.save_map()?;
+        }
+        Ok(())
+    }
+
+    /// Read `len` bytes at `pos` into the destination; false on a read error
+    fn transfer(&mut self, buf: &mut [u8], pos: u64) -> io::Result<bool> {
+        self.map.current_pos = pos;
+        if self.source.read_at(buf, pos).is_err() {
+            return Ok(false);
+        }
+        self.dest.seek(SeekFrom::Start(pos))?;
+        self.dest.write_all(buf)?;
+        Ok(true)
+    }
+
+    fn copy_pass(&mut self, skip_size: u64) -> io::Result<()> {
+        let mut buf = vec![0u8; self.policy.block_size];
+        for (start, size) in self.map.ranges(BlockStatus::NonTried) {
+            let end = start + size;
+            let mut pos = start;
+            while pos < end {
+                let len = (end - pos).min(self.policy.block_size as u64);
+                if self.transfer(&mut buf[..len as usize], pos)? {
+                    self.map.set(pos, len, BlockStatus::Finished);
+                    pos += len;
+                } else {
+                    self.map.set(pos, len, BlockStatus::NonTrimmed);
+                    pos += len + skip_size;
+                }
+                self.save_if_due()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read every sector of the `status` blocks on its own
+    fn sector_pass(&mut self, status: BlockStatus) -> io::Result<()> {
+        let mut buf = vec![0u8; self.policy.sector_size];
+        for (start, size) in self.map.ranges(status) {
+            let end = start + size;
+            let mut pos = start;
+            while pos < end {
+                // Sector-aligned reads; a block may start mid-sector in a foreign mapfile
+                let sector_end = ((pos / self.policy.sector_size as u64) + 1) * self.policy.sector_size as u64;
+                let len = sector_end.min(end) - pos;
+                let ok = self.transfer(&mut buf[..len as usize], pos)?;
+                self.map.set(pos, len, if ok { BlockStatus::Finished } else { BlockStatus::BadSector });
+                pos += len;
+                self.save_if_due()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Map and destination once done
+    pub fn into_parts(self) -> (RescueMap, W) {
+        (self.map, self.dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// In-memory source: sectors in `bad` fail the given number of times (u32::MAX = always)
+    struct FlakySource {
+        data: Vec<u8>,
+        bad: Mutex<HashMap<u64, u32>>,
+    }
+
+    impl SectorSource for FlakySource {
+        fn size(&self) -> u64 {
+            self.data.len() as u64
+        }
+
+        fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+            let mut bad = self.bad.lock().unwrap();
+            let first = offset / 512;
+            let last = (offset + buf.len() as u64 - 1) / 512;
+            let failing = (first..=last).find(|sector| bad.get(sector).is_some_and(|&f| f > 0));
+            if let Some(failures) = failing.and_then(|sector| bad.get_mut(&sector)) {
+                *failures = failures.saturating_sub(1);
+                return Err(io::Error::from_raw_os_error(5));
+            }
+            buf.copy_from_slice(&self.data[offset as usize..offset as usize + buf.len()]);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_imaging_passes_mapfile_and_resume() {
+        let data: Vec<u8> = (0..64 * 1024u32).map(|i| (i % 251) as u8).collect();
+        // Sector 20 is dead, sector 70 recovers on the second retry
+        let source = FlakySource { data: data.clone(), bad: Mutex::new(HashMap::from([(20, u32::MAX), (70, 3)])) };
+        let policy = ImagingPolicy { block_size: 4096, sector_size: 512, skip_size: 8192, scrape: true, retries: 1 };
+
+        let saves = std::cell::Cell::new(0);
+        let mut lines = Vec::new();
+        let dest = io::Cursor::new(vec![0u8; data.len()]);
+        let mut imager = Imager::new(&source, dest, policy.clone(), RescueMap::new(data.len() as u64), |_: &RescueMap| {
+            saves.set(saves.get() + 1);
+            Ok(())
+        });
+        imager.run(&mut |line| lines.push(line)).unwrap();
+        let (map, dest) = imager.into_parts();
+        assert_eq!(lines.len(), 4, "{:?}", lines);
+        assert!(saves.get() >= 5);
+
+        // Only the still-failing sector 70 and the dead sector 20 are bad
+        assert_eq!(map.ranges(BlockStatus::BadSector), vec![(20 * 512, 512), (70 * 512, 512)]);
+        assert_eq!(map.bytes(BlockStatus::Finished), data.len() as u64 - 1024);
+        let dest = dest.into_inner();
+        assert_eq!(dest[..20 * 512], data[..20 * 512]);
+        assert!(dest[20 * 512..21 * 512].iter().all(|&b| b == 0));
+        assert_eq!(dest[21 * 512..70 * 512], data[21 * 512..70 * 512]);
+
+        // The mapfile round-trips and a second session only retries the bad sectors
+        let text = map.render(&["Mapfile. Created by rust-recovery".to_string()]);
+        assert!(text.contains(&format!("0x{:08X}  0x{:08X}  -\n", 20 * 512, 512)));
+        let loaded = RescueMap::parse(&text, data.len() as u64).unwrap();
+        assert_eq!(loaded, map);
+        let mut resumed = Imager::new(&source, io::Cursor::new(dest), policy, loaded, |_: &RescueMap| Ok(()));
+        resumed.run(&mut |_| {}).unwrap();
+        let (map, dest) = resumed.into_parts();
+        assert_eq!(map.ranges(BlockStatus::BadSector), vec![(20 * 512, 512)]);
+        assert_eq!(dest.into_inner()[70 * 512..], data[70 * 512..]);
+
+        // ddrescue mapfiles: decimal numbers, non-scraped blocks, short maps
+        let foreign = RescueMap::parse("# ddrescue\n0 / 3\n0 1024 +\n1024 512 /\n", 4096).unwrap();
+        assert_eq!(foreign.phase, Phase::Scraping);
+        assert_eq!(foreign.ranges(BlockStatus::NonTrimmed), vec![(1024, 512)]);
+        assert_eq!(foreign.ranges(BlockStatus::NonTried), vec![(1536, 2560)]);
+        assert!(RescueMap::parse("0 ? 1\n0 8192 +\n", 4096).is_err());
+        assert!(RescueMap::parse("0 ? 1\n512 512 +\n", 4096).is_err());
+    }
+
+    #[test]
+    fn test_rescue_map_set_merges() {
+        let mut map = RescueMap::new(10_000);
+        map.set(1000, 1000, BlockStatus::Finished);
+        map.set(0, 1000, BlockStatus::Finished);
+        map.set(5000, 100, BlockStatus::BadSector);
+        map.set(9_990, 100, BlockStatus::Finished);
+        let statuses: Vec<(u64, u64, char)> = map.blocks().iter().map(|b| (b.pos, b.size, b.status.symbol())).collect();
+        assert_eq!(statuses, vec![(0, 2000, '+'), (2000, 3000, '?'), (5000, 100, '-'), (5100, 4890, '?'), (9990, 10, '+')]);
+        map.set(2000, 3000, BlockStatus::Finished);
+        map.set(5000, 100, BlockStatus::Finished);
+        assert_eq!(map.blocks()[0], MapBlock { pos: 0, size: 5100, status: BlockStatus::Finished });
+        assert!(!map.is_complete());
+    }
+}
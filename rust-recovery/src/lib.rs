@@ -1,7 +1,7 @@
 //! Rust-based file recovery tool - Stage 3 Implementation
 //!
 //! This library provides high-performance disk image scanning capabilities:
-//! - SIMD-accelerated pattern search (AVX2/SSE4.2 with scalar fallback)
+//! - SIMD-accelerated pattern search (AVX2/SSE4.2, NEON on aarch64, scalar fallback; `recovery-core`)
 //! - Parallel chunk scanner using rayon
 //! - Runtime SIMD dispatching
 //! - Panic isolation with catch_unwind
@@ -10,46 +10,82 @@
 //! - Enhanced validation and scoring system with entropy analysis
 
 pub mod cli;
+pub mod commands;
+pub mod compressed;
 pub mod disk;
 pub mod error;
-pub mod simd_search;
+pub use recovery_core::simd_search;
 pub mod types;
 pub mod scanner;
 pub mod matcher;
-pub mod entropy;
+pub use recovery_core::entropy;
+pub mod ewf;
+pub mod imaging;
 pub mod exfat;
+pub mod fat32;
+pub mod ntfs;
+pub mod partition;
+pub mod pipe;
+pub mod qcow2;
+pub mod vmdk;
+pub mod windows_device;
 pub mod fragment_linker;
 pub mod smart_separation;
 pub mod stream_solver;
 pub mod checkpoint;
+pub mod audit;
+pub mod scan_db;
+pub mod progress;
+pub mod diagnostics;
+pub mod exit_code;
+pub mod carver;
 pub mod tui;
 pub mod report;
 pub mod recovery;
-pub mod simd_search_asm;
-pub mod simd_block_scanner_asm;
+#[cfg(target_arch = "x86_64")]
+pub use recovery_core::simd_search_asm;
+#[cfg(target_arch = "aarch64")]
+pub use recovery_core::simd_neon;
+pub use recovery_core::simd_block_scanner_asm;
 pub mod types_aligned;
 pub mod numa;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod session;
+pub mod housekeeping;
+pub mod multi_image;
+pub mod logging;
+pub mod hashing;
+pub mod s3;
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
 
 // Re-export commonly used types
 pub use types::{Offset, Size, ClusterId};
-pub use types::{ScanConfig, ScanResult, ScanProgress, ScanStats, HotFragment, EnrichedLink};
+pub use types::{ScanConfig, ScanResult, ScanProgress, ScanStats, ScanIncident, ChunkStats, HotFragment, EnrichedLink};
 pub use types::{FragmentScore, ValidationResult};
 pub use types::{StreamFragment, StreamScoringWeights, AssembledStream};
 pub use disk::{DiskImage, FragmentSlice};
 pub use scanner::{ParallelScanner, ChunkInfo};
-pub use simd_search::{find_pattern_simd, count_pattern_simd, scan_block_simd, BlockScanResult};
+pub use simd_search::{find_pattern_simd, count_pattern_simd, scan_block_simd, scan_block64_simd, BlockScanResult};
+#[cfg(target_arch = "x86_64")]
 pub use simd_search_asm::find_pattern_avx2_asm;
-pub use simd_block_scanner_asm::{scan_block_avx2_asm, AlignedBlock, BlockScanResultExt};
+#[cfg(target_arch = "x86_64")]
+pub use simd_block_scanner_asm::scan_block_avx2_asm;
+pub use simd_block_scanner_asm::{AlignedBlock, BlockScanResultExt};
 pub use types_aligned::{HotFragmentAligned, ScanStatsAligned, AlignedBuffer};
 pub use numa::{NumaTopology, pin_thread_to_cpu};
 pub use matcher::{EnhancedMatcher, calculate_fragment_score, validate_data_chunk};
 pub use matcher::{detect_cyrillic, cyrillic_density, count_json_markers_fast, calculate_link_density};
 pub use entropy::{calculate_shannon_entropy, is_compressed_like, is_structured_text, get_entropy_category};
-pub use stream_solver::{assemble_streams, assemble_streams_with_weights};
+pub use stream_solver::{assemble_streams, assemble_streams_clustered, assemble_streams_sized, assemble_streams_with_weights, StreamAssembly};
 pub use checkpoint::{
-    Checkpoint, CheckpointManager, ResumeValidation, compute_image_hash, create_checkpoint,
+    Checkpoint, CheckpointManager, ResumeValidation, compute_image_hash, compute_pipe_hash, create_checkpoint,
     validate_resume, load_checkpoint, save_checkpoint_atomic, save_checkpoint_blocking,
 };
+pub use progress::{ProgressReporter, ProgressSnapshot, ProgressState};
 pub use tui::{TuiApp, TuiEvent, TuiApplication};
 pub use report::{ProfessionalReportGenerator, ReportContext, create_report_metadata, create_scan_results};
 pub use error::{RecoveryError, Result};
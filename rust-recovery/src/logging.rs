@@ -0,0 +1,198 @@
+//! Structured logging through `tracing`
+//!
+//! Library code logs events (`tracing::warn!` etc.) instead of printing;
+//! scanned chunks run inside a `chunk` span with their `offset` and `size`,
+//! so every event of a chunk carries them in the log file. `init` installs:
+//!
+//! - the console layer: while a dashboard is attached (`attach_dashboard`)
+//!   events go to its log panel; otherwise warnings and errors go to stderr
+//!   as `[WARN] message` and info events to stdout. Events with target
+//!   `DASHBOARD` are for the panel only and are not printed.
+//! - with `--log-file`, a file layer writing every event with timestamp,
+//!   level, span fields and event fields, rotated at `--log-max-mb`.
+//!
+//! `--log-level` filters both.
+
+use crate::housekeeping::RotatingFile;
+use crate::tui::TuiEvent;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::Layer;
+
+/// Target of events meant for the dashboard log panel only
+pub const DASHBOARD: &str = "dashboard";
+/// Accepted `--log-level` values
+pub const LOG_LEVEL_NAMES: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+static DASHBOARD_SENDER: Mutex<Option<UnboundedSender<TuiEvent>>> = Mutex::new(None);
+
+/// `--log-level` value as a filter
+pub fn parse_level(name: &str) -> Option<LevelFilter> {
+    match name {
+        "error" => Some(LevelFilter::ERROR),
+        "warn" => Some(LevelFilter::WARN),
+        "info" => Some(LevelFilter::INFO),
+        "debug" => Some(LevelFilter::DEBUG),
+        "trace" => Some(LevelFilter::TRACE),
+        _ => None,
+    }
+}
+
+/// Show events in the dashboard log panel until the guard is dropped
+pub fn attach_dashboard(sender: UnboundedSender<TuiEvent>) -> DashboardGuard {
+    *DASHBOARD_SENDER.lock().unwrap() = Some(sender);
+    DashboardGuard(())
+}
+
+/// Detaches the dashboard when dropped; events go to the console again
+pub struct DashboardGuard(());
+
+impl Drop for DashboardGuard {
+    fn drop(&mut self) {
+        *DASHBOARD_SENDER.lock().unwrap() = None;
+    }
+}
+
+/// Install the global subscriber; a second call keeps the first one
+pub fn init(level: LevelFilter, log_file: Option<&Path>, max_bytes: Option<u64>) -> std::io::Result<()> {
+    let file_layer = match log_file {
+        Some(path) => {
+            let file = Arc::new(Mutex::new(RotatingFile::append(path)?.with_max_bytes(max_bytes)));
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(move || LogFileWriter(Arc::clone(&file)))
+                    .with_filter(level),
+            )
+        }
+        None => None,
+    };
+    let subscriber = tracing_subscriber::registry()
+        .with(ConsoleLayer.with_filter(level))
+        .with(file_layer);
+    let _ = tracing::subscriber::set_global_default(subscriber);
+    Ok(())
+}
+
+/// Event message followed by its other fields as `key=value`
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        // Offsets read better in hex
+        if field.name().contains("offset") {
+            let _ = write!(self.fields, " {}=0x{:X}", field.name(), value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Text of an event: message and fields
+pub fn event_text(event: &Event<'_>) -> String {
+    let mut visitor = MessageVisitor::default();
+    event.record(&mut visitor);
+    visitor.message + &visitor.fields
+}
+
+/// Dashboard panel while attached, stdout/stderr otherwise
+struct ConsoleLayer;
+
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let text = event_text(event);
+        if let Some(sender) = DASHBOARD_SENDER.lock().unwrap().as_ref() {
+            let _ = sender.send(TuiEvent::LogMessage { message: text });
+            return;
+        }
+        if metadata.target() == DASHBOARD {
+            return;
+        }
+        match *metadata.level() {
+            Level::INFO => println!("{}", text),
+            level => eprintln!("[{}] {}", level, text),
+        }
+    }
+}
+
+/// `--log-file` writer
+struct LogFileWriter(Arc<Mutex<RotatingFile>>);
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().file()?.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap().file()?.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_file_spans_and_dashboard() {
+        let path = std::env::temp_dir().join(format!("rust_recovery_log_{}.log", std::process::id()));
+        let file = Arc::new(Mutex::new(RotatingFile::create(&path).unwrap()));
+        let writer = Arc::clone(&file);
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || LogFileWriter(Arc::clone(&writer)))
+                .with_filter(parse_level("info").unwrap()),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let _chunk = tracing::info_span!("chunk", offset = 0x1000u64, size = 4096u64).entered();
+            tracing::warn!(offset = 0x1200u64, "Read failed");
+            tracing::debug!("filtered out");
+        });
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(text.contains("WARN chunk{offset=4096 size=4096}"), "{}", text);
+        assert!(text.contains("Read failed offset=4608"));
+        assert!(!text.contains("filtered out"));
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let guard = attach_dashboard(sender);
+        let subscriber = tracing_subscriber::registry().with(ConsoleLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: DASHBOARD, offset = 0x2000u64, "Chunk completed");
+        });
+        drop(guard);
+        match receiver.try_recv() {
+            Ok(TuiEvent::LogMessage { message }) => assert_eq!(message, "Chunk completed offset=0x2000"),
+            other => panic!("unexpected {:?}", other.is_ok()),
+        }
+        assert!(parse_level("verbose").is_none());
+    }
+}
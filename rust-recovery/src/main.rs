@@ -1,37 +1,171 @@
 use rust_recovery::cli::Args;
-use clap::Parser;
+use rust_recovery::commands;
+use clap::{CommandFactory, Parser};
 use rust_recovery::disk::DiskImage;
+use rust_recovery::exfat;
+use rust_recovery::partition::{Partition, PartitionTable};
+use rust_recovery::pipe::PIPE_SPILL_FILE;
+use rust_recovery::scan_db::{ScanDb, ASSEMBLY_PAGE_FRAGMENTS, REPORT_LIST_LIMIT, SCAN_DB_FILE};
 use rust_recovery::error::{Result, RecoveryError};
-use rust_recovery::types::{Offset, ScanConfig, ScanProgress, StreamFragment, FragmentScore};
-use rust_recovery::scanner::ParallelScanner;
+use rust_recovery::types::{
+    AssembledStream, EnrichedLink, FileTimestamps, HotFragment, Offset, ScanConfig, ScanIncident, ScanProgress, Size,
+    StreamFragment, StreamScoringWeights, FragmentScore,
+};
+use rust_recovery::diagnostics::{DiagnosticCause, ScanDiagnostics};
+use rust_recovery::exit_code::{self, RunStatus, RunSummary};
+use rust_recovery::scanner::{
+    plan_overlap, scan_metadata, scan_partition, FragmentCache, KeywordFilter, KeywordProfile, MetadataPass,
+    ParallelScanner, RECONSTRUCTED_DIR,
+};
+use rust_recovery::scanner::hints::directory_hints;
+use rust_recovery::scanner::keywords::load_keyword_file;
+use rust_recovery::matcher::{EnhancedMatcher, PatternProfile};
 use rust_recovery::report;
-use rust_recovery::stream_solver;
+use rust_recovery::report::forensic::RECOVERED_FILES_DIR;
+use rust_recovery::report::charts::ClusterCharts;
+use rust_recovery::smart_separation::{ClusterConfig, FragmentClusterer, CLUSTER_SAMPLE_BYTES};
+use rust_recovery::stream_solver::{self, StreamAssembly};
 use tokio::runtime::Runtime;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 use tokio::sync::mpsc;
-use rust_recovery::tui::{TuiApplication, TuiApp, TuiEvent};
+use rust_recovery::tui::table::FragmentMark;
+use rust_recovery::tui::{QuitChoice, TuiApplication, TuiApp, TuiCommand, TuiEvent};
 use rust_recovery::report::{ProfessionalReportGenerator, create_report_metadata, create_scan_results};
-use rust_recovery::recovery::{clean_file_content, extract_title};
+use rust_recovery::matcher::trace::{MatcherTrace, TRACE_FILE};
+use rust_recovery::audit::{AuditAction, AuditLog, AUDIT_LOG_FILE};
+use rust_recovery::s3::{self, S3Client, S3Location};
+use rust_recovery::session::{Environment, SessionInfo, ToolInfo, SESSION_FILE, GIT_HASH};
+use rust_recovery::multi_image::{self, ImageOutcome, MultiImageSummary, MULTI_IMAGE_SUMMARY_FILE};
+use rust_recovery::housekeeping::{self, SpaceWatch, MAX_CHUNK_STATS_ROWS, PENDING_FRAGMENTS_WARNING};
+use rust_recovery::report::file_log::{RecoveredFileLog, RECOVERED_LOG_FILE};
+use rust_recovery::report::fragment_log::{FragmentLog, FRAGMENT_LOG_FILE};
+use rust_recovery::recovery::{
+    deduplicate, detect_language, email, reassemble_with, provenance_hash, remove_exact_duplicates, revalidate,
+    FileTypeRegistry, FilenamePolicy, FragmentExtent, PostProcessor, ProvenanceParams, Reassembled, RecoveryWriter,
+    TitleStore,
+    GapFill, Utf8Mode, POST_PROCESS_LOG_FILE,
+    TOOL_VERSION,
+};
+use rust_recovery::checkpoint::{
+    compute_image_hash, compute_pipe_hash, load_checkpoint, validate_resume, Checkpoint, CheckpointManager, SavedFragment, ScanState,
+};
+use rust_recovery::progress::{ProgressReporter, ProgressState, ProgressStream};
+use rust_recovery::logging::{self, DASHBOARD};
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, info, warn};
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    let mut args = parse_args();
+    // `rust-recovery verify|estimate|image|query|serve` run instead of a scan
+    if let Some(command) = args.command.take() {
+        let _ = logging::init(LevelFilter::INFO, None, None);
+        match commands::run(command) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(exit_code::error_code(&e).0);
+            }
+        }
+    }
+
+    // Scripts read the last stdout line: `RESULT status=... exit=...`
+    let level = logging::parse_level(&args.log_level).unwrap_or(LevelFilter::INFO);
+    if let Err(e) = logging::init(level, args.log_file.as_deref(), args.log_max_bytes()) {
+        let path = args.log_file.as_deref().unwrap_or(Path::new("")).display();
+        exit_with_error(&RecoveryError::Config(format!("Failed to open log file {}: {}", path, e)));
+    }
+    let images = args.image_paths().unwrap_or_else(|e| exit_with_error(&e));
+    // --serve: browse the output once the run is done (the staging directory of an s3:// run)
+    let served_dir = if args.s3_output_url().is_some() { args.s3_staging.clone() } else { args.output.clone() };
+    let serve = args.serve.then(|| (served_dir, args.serve_addr.clone()));
+    let serve_results = || {
+        if let Some((output, addr)) = &serve {
+            if let Err(e) = commands::serve::run(output, addr) {
+                eprintln!("Error: {}", e);
+            }
+        }
+    };
+    if images.len() > 1 {
+        match run_images(args, images) {
+            Ok((summary, path)) => {
+                println!("{}", summary.line(&path));
+                serve_results();
+                std::process::exit(summary.exit_code);
+            }
+            Err(e) => exit_with_error(&e),
+        }
+    }
+    // An empty --image-list leaves the path empty, which validation rejects
+    args.image = images.into_iter().next().unwrap_or_default();
+    match run(args) {
+        Ok(summary) => {
+            println!("{}", summary.line());
+            serve_results();
+            std::process::exit(summary.status.code());
+        }
+        Err(e) => exit_with_error(&e),
+    }
+}
+
+fn exit_with_error(e: &RecoveryError) -> ! {
+    eprintln!("Error: {}", e);
+    println!("{}", exit_code::error_line(e));
+    std::process::exit(exit_code::error_code(e).0);
+}
+
+/// Parse the command line; usage errors exit with `exit_code::INVALID_ARGUMENT`
+/// (clap's own code 2 is taken by `verify`), after the summary line for a scan
+fn parse_args() -> Args {
+    let argv: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    match Args::try_parse_from(&argv) {
+        Ok(args) => args,
+        // --help and --version
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => {
+            let _ = e.print();
+            let error = RecoveryError::InvalidArgument(e.kind().to_string());
+            let subcommand = argv
+                .get(1)
+                .and_then(|arg| arg.to_str())
+                .is_some_and(|name| Args::command().find_subcommand(name).is_some());
+            if !subcommand {
+                println!("{}", exit_code::error_line(&error));
+            }
+            std::process::exit(exit_code::error_code(&error).0);
+        }
     }
 }
 
-fn run() -> Result<()> {
-    // Parse command line arguments
-    let args = Args::parse();
+/// Scan checkpoint in the output directory, read by `--resume`
+const SCAN_CHECKPOINT_FILE: &str = "scan_checkpoint.json";
+/// Minimum time between periodic checkpoint saves
+const CHECKPOINT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
 
+fn run(mut args: Args) -> Result<RunSummary> {
     // Validate arguments
-    if let Err(e) = args.validate() {
-        eprintln!("Invalid arguments: {}", e);
-        std::process::exit(1);
+    args.validate().map_err(RecoveryError::InvalidArgument)?;
+
+    // --output s3://bucket/prefix: the run is written to --s3-staging and uploaded at the end
+    let remote = remote_output(&mut args)?;
+
+    // Writing recovered files onto the evidence disk overwrites the data being recovered
+    let conflict = match args.reads_stdin() {
+        true => None,
+        false => rust_recovery::disk::output_conflict(&args.image, &args.output)?,
+    };
+    if let Some(conflict) = conflict {
+        if !args.force {
+            return Err(RecoveryError::Config(format!(
+                "Refusing to write output: {}. Choose an output directory on another disk or pass --force",
+                conflict
+            )));
+        }
+        warn!("{} (--force)", conflict);
     }
 
     // Initialize output directory
@@ -40,37 +174,238 @@ fn run() -> Result<()> {
         fs::create_dir_all(&output_dir)
             .map_err(|e| RecoveryError::Config(format!("Failed to create output directory: {}", e)))?;
     }
+    let orphaned = housekeeping::remove_orphaned_temp_files(&output_dir);
+    if !orphaned.is_empty() {
+        println!("Removed {} temporary file(s) left by an interrupted run", orphaned.len());
+    }
 
-    // Create session info
-    let session_info = format!(
-        "version: 12.0\nimage_file: {}\nstart_time: {}\nparameters: {:?}\n",
-        args.image.display(),
-        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
-        args
-    );
-    
-    let session_path = output_dir.join("session.info");
-    fs::write(&session_path, session_info)
-        .map_err(|e| RecoveryError::Config(format!("Failed to save session info: {}", e)))?;
+    // Chain-of-custody log: continues the chain of earlier runs into this directory
+    let audit_path = output_dir.join(AUDIT_LOG_FILE);
+    let audit_log = Arc::new(AuditLog::open(&audit_path).map_err(|e| {
+        RecoveryError::Config(format!("Audit log {} cannot be continued: {} (move it aside to start a new chain)", audit_path.display(), e))
+    })?);
+    audit(&audit_log, AuditAction::RunStarted, serde_json::json!({
+        "tool_version": TOOL_VERSION,
+        "git_hash": GIT_HASH,
+        "command_line": std::env::args_os().map(|arg| arg.to_string_lossy().to_string()).collect::<Vec<_>>(),
+        "output_dir": output_dir.to_string_lossy(),
+        "remote_output": remote.as_ref().map(|(location, _)| location.url()),
+        "resume": args.resume,
+    }));
+
+    let start_time = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
 
     // Open disk image
     println!("Opening disk image...");
-    let disk = DiskImage::open(&args.image)?;
-    let image_size = disk.size().as_u64();
-    println!("  Image size: {} bytes ({:.2} GB)", 
-        image_size,
-        image_size as f64 / (1024.0 * 1024.0 * 1024.0)
-    );
+    let stream = if args.reads_stdin() { None } else { rust_recovery::compressed::stream_reader(&args.image)? };
+    let disk = if args.reads_stdin() {
+        DiskImage::open_pipe(std::io::stdin(), "-", output_dir.join(PIPE_SPILL_FILE))?
+    } else if let Some((compression, reader)) = stream {
+        // One frame or block for the whole image: decompressed front to back, like stdin
+        if let Some(flag) = args.seeking_option() {
+            return Err(RecoveryError::Config(format!(
+                "{} needs a seekable image; this {} image has no restart points and is read once, front to back",
+                flag,
+                compression.name()
+            )));
+        }
+        let path = args.image.to_string_lossy();
+        DiskImage::open_pipe(reader, &path, output_dir.join(PIPE_SPILL_FILE))?
+    } else if args.stream {
+        DiskImage::open_unmapped(&args.image)?
+    } else {
+        DiskImage::open(&args.image)?
+    };
+    // A pipe's size is known once it has been read: 0 until then
+    let image_size = if disk.pipe().is_some() { 0 } else { disk.size().as_u64() };
+    if disk.pipe().is_some() {
+        let source = if args.reads_stdin() { "stdin" } else { "decompressed stream" };
+        println!("  Image: {} (read once, front to back; size known at the end)", source);
+    } else {
+        println!("  Image size: {} bytes ({:.2} GB)", 
+            image_size,
+            image_size as f64 / (1024.0 * 1024.0 * 1024.0)
+        );
+    }
+    if let Some(ewf) = disk.ewf() {
+        println!("  Format: EWF ({} segments, {} KB chunks)", ewf.segment_count(), ewf.chunk_size() / 1024);
+        if !args.no_verify {
+            verify_ewf(ewf)?;
+        }
+    } else if let Some(image) = disk.compressed() {
+        println!(
+            "  Format: {} (offsets are positions in the decompressed image, {} restart points)",
+            image.compression().name(),
+            image.restart_points()
+        );
+    } else if let Some(format) = disk.format() {
+        println!("  Format: {} (offsets are guest disk positions)", format);
+    }
+    let partition_table = if disk.pipe().is_some() { None } else { read_partition_table(&disk) };
+    if let Some(ref table) = partition_table {
+        println!("  Partitions ({}): {}", table.scheme.name(), table.partitions.len());
+        for partition in &table.partitions {
+            println!("    {}", partition.describe());
+        }
+    }
+    let partition = args.partition.map(|number| selected_partition(partition_table.as_ref(), number)).transpose()?;
     println!();
 
+    // Pattern profile: built-in YouTube patterns unless --patterns is given
+    let matcher = match args.patterns {
+        Some(ref path) => {
+            let profile = PatternProfile::load(path)?;
+            println!("  Pattern profile: {} ({} patterns)", profile.name, profile.patterns.len());
+            EnhancedMatcher::with_profile(Arc::new(profile))
+        }
+        None => EnhancedMatcher::new(),
+    };
+    let matcher = match args.trace_range() {
+        Some((start, end)) => {
+            let trace_path = output_dir.join(TRACE_FILE);
+            let trace = MatcherTrace::create(&trace_path, start, end)
+                .map_err(|e| RecoveryError::Config(format!("Failed to create {}: {}", trace_path.display(), e)))?;
+            println!("  Matcher trace: 0x{:X}-0x{:X} -> {}", start, end, trace_path.display());
+            matcher.with_trace(Arc::new(trace))
+        }
+        None => matcher,
+    };
+    let matcher = if args.gpu { with_gpu_prefilter(matcher) } else { matcher };
+    let matcher = matcher.with_context(args.link_context);
+
+    // Overlap must cover the longest span one link depends on
+    let overlap = plan_overlap(&matcher, args.overlap_bytes().map(|b| b as usize));
+    if let Some(ref warning) = overlap.warning {
+        warn!("{}", warning);
+    }
+
     // Create scan configuration
     let mut scan_config = ScanConfig::new(
         args.chunk_max_bytes() as usize,
-        64 * 1024, // 64KB overlap
-        0,         // auto threads
+        overlap.overlap,
+        0, // auto threads
     );
     scan_config.reverse = args.reverse;
+    scan_config.min_file_size = args.target_size_min_bytes();
+    scan_config.max_file_size = args.target_size_max_bytes();
     scan_config.nvme_optimization = args.nvme;
+    scan_config.streaming = args.stream || disk.pipe().is_some();
+    scan_config.prioritize = args.priority;
+    if args.nvme && !cfg!(all(target_os = "linux", feature = "io-uring")) {
+        warn!("--nvme: io_uring reader not built (cargo build --features io-uring), using regular reads");
+    }
+    scan_config.stall_timeout = args.stall_timeout();
+    scan_config.max_read_bytes_per_sec = args.max_read_bytes_per_sec();
+    scan_config.io_priority = args.io_priority();
+    scan_config.target_script = args.target_script();
+    let transport = rust_recovery::disk::network_transport(&args.image);
+    scan_config.prefetch = args.prefetch(transport);
+    if let Some(ref prefetch) = scan_config.prefetch {
+        let ahead = format!(
+            "{} MB ahead, {} reads in flight, {} s read timeout",
+            prefetch.distance_bytes / (1024 * 1024),
+            prefetch.depth,
+            prefetch.timeout.as_secs()
+        );
+        match transport {
+            Some(name) => println!("{} device: prefetching {}", name, ahead),
+            None => println!("Prefetching {}", ahead),
+        }
+    }
+    scan_config.carve_types = args.carve_types();
+    scan_config.chunk_stats = args.chunk_stats;
+    scan_config.stream_links = args.scan_db;
+    scan_config.image_digest = args.image_digest().map(Arc::new);
+    scan_config.fragment_cache = args.fragment_cache(&output_dir).map(Arc::new);
+    scan_config.detectors = args.detect_types();
+    scan_config.artifacts = args.artifact_types();
+    if args.adaptive_chunks {
+        scan_config.min_chunk_size = Some(args.chunk_min_bytes() as usize);
+    }
+
+    let mut keywords = args.keyword.clone();
+    if let Some(ref keyword_file) = args.keyword_file {
+        keywords.extend(load_keyword_file(keyword_file)?);
+    }
+    scan_config.keywords = KeywordFilter::new(&keywords, args.keyword_only)?.map(Arc::new);
+    if let Some(ref path) = args.keyword_profile {
+        scan_config.keyword_profile = Some(Arc::new(KeywordProfile::load(path)?));
+    }
+    if let Some(ref partition) = partition {
+        scan_config.scan_ranges = Some(Arc::new(vec![(partition.start, partition.end())]));
+    }
+    if args.unallocated_only {
+        if let Some(ranges) = unallocated_ranges(&disk, partition.as_ref()) {
+            scan_config.scan_ranges = Some(Arc::new(ranges));
+        }
+    }
+    if args.enable_exfat {
+        scan_config.hint_ranges = exfat_hint_ranges(&disk, partition.as_ref(), &scan_config.carve_types).map(Arc::new);
+    }
+
+    // Continue from the checkpoint an interrupted run left in the output directory
+    let mut resumed_fragments = Vec::new();
+    if args.resume {
+        let (position, state) = load_resume_point(&args, &output_dir)?;
+        println!(
+            "Resuming {} scan from offset 0x{:X} ({} fragments found before the checkpoint)",
+            if state.reverse { "reverse" } else { "forward" },
+            position,
+            state.fragments_before(position).len()
+        );
+        scan_config.resume_from = Some(position);
+        resumed_fragments = state.fragments_before(position);
+    }
+
+    // Provenance inputs: image identity + parameters that shape recovered bytes
+    let image_hash = match disk.pipe() {
+        Some(pipe) => compute_pipe_hash(pipe)?,
+        None => compute_image_hash(&args.image)?,
+    };
+    let provenance_params = provenance_params(&args, &scan_config, &matcher);
+    audit(&audit_log, AuditAction::ImageOpened, serde_json::json!({
+        "path": args.image.to_string_lossy(),
+        "format": disk.format().unwrap_or("raw"),
+        "size_bytes": disk.pipe().is_none().then_some(image_size),
+        "image_hash": image_hash,
+    }));
+
+    // Session record: effective configuration and the machine, for reproducing the run
+    let session = SessionInfo {
+        tool: ToolInfo::current(),
+        start_time,
+        command_line: std::env::args_os().map(|arg| arg.to_string_lossy().to_string()).collect(),
+        image: [
+            ("path".to_string(), serde_json::json!(args.image.to_string_lossy())),
+            ("size_bytes".to_string(), serde_json::json!(disk.pipe().is_none().then_some(image_size))),
+            ("format".to_string(), serde_json::json!(disk.format().unwrap_or("raw"))),
+            ("image_hash".to_string(), serde_json::json!(image_hash)),
+        ]
+        .into_iter()
+        .collect(),
+        arguments: serde_json::to_value(&args).unwrap_or_default(),
+        scan: scan_settings(&scan_config),
+        provenance_parameters: provenance_params.clone(),
+        environment: Environment::capture(),
+    };
+    fs::write(output_dir.join(SESSION_FILE), session.to_yaml())
+        .map_err(|e| RecoveryError::Config(format!("Failed to save session info: {}", e)))?;
+    audit(&audit_log, AuditAction::ScanParameters, serde_json::json!({
+        "arguments": session.arguments,
+        "scan": session.scan,
+        "provenance_parameters": session.provenance_parameters,
+    }));
+
+    // progress.json for external tools polling the run
+    let mut reporter =
+        ProgressReporter::new(&output_dir, image_size)
+            .with_image(&args.image)
+            .with_interval(std::time::Duration::from_secs(args.progress_interval));
+    if let Some(ref path) = args.progress_stream {
+        reporter = reporter.with_stream(ProgressStream::new(path));
+    }
+    let progress = Arc::new(Mutex::new(reporter));
+    let _ = progress.lock().unwrap().set_state(ProgressState::Starting);
 
     // Create report generator
     let report_generator = ProfessionalReportGenerator::new(&output_dir);
@@ -78,10 +413,14 @@ fn run() -> Result<()> {
     // Create TUI if enabled
     let mut tui_app = None;
     let mut tui_sender = None;
+    let mut tui_commands = None;
+    let mut dashboard_log = None;
     
     if !args.no_live {
         // Create TUI event channel
         let (sender, receiver) = mpsc::unbounded_channel::<TuiEvent>();
+        // Log events go to the dashboard's log panel while it is shown
+        dashboard_log = Some(logging::attach_dashboard(sender.clone()));
         tui_sender = Some(sender);
         
         // Create TUI application
@@ -93,15 +432,17 @@ fn run() -> Result<()> {
         );
         app.target_files = args.early_exit as u32;
         
-        tui_app = Some(TuiApplication::new(app, receiver)?);
+        // Hotkeys (pause/skip/checkpoint) go back to the scan loop
+        let (command_tx, command_rx) = mpsc::unbounded_channel::<TuiCommand>();
+        tui_commands = Some(command_rx);
+        tui_app = Some(
+            TuiApplication::new(app, receiver)?
+                .with_commands(command_tx)
+                .with_viewer(disk.clone(), matcher.clone_fresh()),
+        );
     }
 
-    // Send initial log message
-    if let Some(ref sender) = tui_sender {
-        let _ = sender.send(TuiEvent::LogMessage {
-            message: "Starting disk recovery scan".to_string(),
-        });
-    }
+    info!(target: DASHBOARD, "Starting disk recovery scan");
 
     // Print configuration
     print_configuration(&args);
@@ -115,6 +456,10 @@ fn run() -> Result<()> {
     let scan_config_clone = scan_config.clone();
     let output_dir_clone = output_dir.clone();
     let tui_sender_clone = tui_sender.clone();
+    let provenance = (image_hash.clone(), provenance_params.clone());
+    let progress_clone = Arc::clone(&progress);
+    let matcher_clone = matcher.clone_fresh();
+    let audit_clone = Arc::clone(&audit_log);
 
     let scan_thread = std::thread::spawn(move || {
         let result = run_scan_pipeline(
@@ -123,6 +468,12 @@ fn run() -> Result<()> {
             &scan_config_clone,
             tui_sender_clone.as_ref(),
             &output_dir_clone,
+            &provenance,
+            &progress_clone,
+            &matcher_clone,
+            &audit_clone,
+            resumed_fragments,
+            tui_commands,
         );
 
         // Send completion event
@@ -134,26 +485,159 @@ fn run() -> Result<()> {
     });
 
     // Run TUI if enabled
-    if let Some(mut app) = tui_app {
-        // This will block until Q is pressed or scan completes
+    let mut dashboard = tui_app;
+    if let Some(app) = dashboard.as_mut() {
+        // Blocks until Q, or until the pipeline is done after a cancel
         if let Err(e) = app.run() {
             eprintln!("TUI Error: {}", e);
         }
     }
+    // Only a cancelled scan keeps the dashboard (shutdown screen) through the reports
+    if !dashboard.as_ref().is_some_and(TuiApplication::is_shutting_down) {
+        let keep_scanning = dashboard.as_ref().and_then(TuiApplication::quit_choice) == Some(QuitChoice::KeepScanning);
+        dashboard = None;
+        dashboard_log = None;
+        if keep_scanning {
+            eprintln!("Dashboard closed, the scan continues (Ctrl-C cancels it)");
+        }
+    }
 
     // Wait for scan to finish and get results
     // If TUI was quit early, we still wait for scan to complete
     let scan_results = scan_thread.join()
-        .map_err(|_| RecoveryError::Config("Scan thread panicked".to_string()))??;
+        .map_err(|_| RecoveryError::Config("Scan thread panicked".to_string()))
+        .and_then(|result| result);
+    let mut scan_results = match scan_results {
+        Ok(results) => results,
+        Err(e) => {
+            let _ = progress.lock().unwrap().set_state(ProgressState::Failed);
+            return Err(e);
+        }
+    };
+    let _ = progress.lock().unwrap().set_state(ProgressState::Reporting);
+
+    // Every file is written: the kept fragment bytes (and their spill
+    // directory, which is not part of the results) can go
+    if let Some(cache) = scan_config.fragment_cache.as_deref() {
+        let stats = cache.stats();
+        info!(
+            "Fragment cache: {} fragments kept ({} duplicates), {} reads from the cache, {} from the image, \
+             {:.1} MB spilled, {} dropped",
+            stats.stored,
+            stats.deduplicated,
+            stats.hits,
+            stats.misses,
+            stats.spilled_bytes as f64 / 1024.0 / 1024.0,
+            stats.dropped
+        );
+        cache.clear();
+    }
+
+    // Full validation of the saved files (repairs change their hashes, so before dedup)
+    if !args.links_only {
+        let file_types = FileTypeRegistry::builtin();
+        let dir = output_dir.join(RECOVERED_FILES_DIR);
+        let summary = revalidate(&dir, &mut scan_results.recovered_files, &file_types, !args.no_repair);
+        for file in scan_results.recovered_files.iter().filter(|file| file.repair.is_some()) {
+            audit(&audit_log, AuditAction::FileRepaired, serde_json::json!({
+                "path": format!("{}/{}", RECOVERED_FILES_DIR, file.filename),
+                "repair": file.repair,
+                "size_bytes": file.size_bytes,
+                "sha256": file.sha256,
+                "md5": file.md5,
+            }));
+        }
+        if summary.checked > 0 {
+            info!(
+                "Validation: {} files checked, {} upgraded, {} downgraded, {} repaired",
+                summary.checked,
+                summary.upgraded,
+                summary.downgraded,
+                summary.repaired
+            );
+        }
+    }
+
+    // Collapse duplicate files before they are counted and reported
+    let duplicate_groups = if args.no_dedup {
+        Vec::new()
+    } else {
+        let files = std::mem::take(&mut scan_results.recovered_files);
+        let (files, groups) = deduplicate(files, args.dedup_similarity);
+        scan_results.recovered_files = files;
+        if !groups.is_empty() {
+            let duplicates: usize = groups.iter().map(|group| group.duplicates.len()).sum();
+            let removed = remove_exact_duplicates(&output_dir.join(RECOVERED_FILES_DIR), &groups);
+            for group in &groups {
+                for duplicate in group.duplicates.iter().filter(|duplicate| duplicate.exact) {
+                    audit(&audit_log, AuditAction::FileRemoved, serde_json::json!({
+                        "path": format!("{}/{}", RECOVERED_FILES_DIR, duplicate.filename),
+                        "sha256": duplicate.sha256,
+                        "reason": format!("exact duplicate of {}", group.kept_filename),
+                    }));
+                }
+            }
+            info!(
+                "Duplicates: {} files collapsed into {} groups, {} exact duplicates removed from disk",
+                duplicates,
+                groups.len(),
+                removed
+            );
+        }
+        groups
+    };
+
+    // --verify-hash: hash what the scan did not read. A cancelled or stopped
+    // scan left the image partly unread, so no digest is reported
+    let interrupted = [DiagnosticCause::Cancelled, DiagnosticCause::TimeBudget]
+        .iter()
+        .any(|&cause| scan_results.diagnostics.bytes(cause) > 0);
+    let source_digest = match scan_config.image_digest.as_deref() {
+        Some(_) if interrupted => {
+            warn!("Image digest not computed: the scan did not run to the end");
+            None
+        }
+        Some(digest) => {
+            if let Some(app) = dashboard.as_mut() {
+                app.shutdown_step("Hashing the image");
+            }
+            match digest.finish(&disk) {
+                Ok(digest) => {
+                    info!("Image SHA-256: {} ({} bytes read again for the digest)", digest.sha256, digest.reread_bytes);
+                    audit(&audit_log, AuditAction::ImageDigest, serde_json::json!(digest));
+                    Some(digest)
+                }
+                Err(e) => {
+                    warn!("Image digest failed: {}", e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
 
     // Generate reports
-    println!("\nScanning complete. Generating reports...");
-    let metadata = create_report_metadata(
+    match dashboard.as_mut() {
+        Some(app) => app.shutdown_step("Writing reports"),
+        None => println!("\nScanning complete. Generating reports..."),
+    }
+    let mut metadata = create_report_metadata(
         &args.image.to_string_lossy(),
         &output_dir.to_string_lossy(),
-        "12.0",
+        TOOL_VERSION,
     );
-    
+    metadata.image_hash = image_hash;
+    metadata.parameters = provenance_params;
+    metadata.partitions = partition_table.map(|table| table.partitions).unwrap_or_default();
+    metadata.scanned_partition = args.partition;
+    metadata.source_digest = source_digest;
+    metadata.audit_chain_hash = Some(audit_log.chain_hash());
+    metadata.remote_output = remote.as_ref().map(|(location, _)| location.url());
+    if scan_results.diagnostics.bytes(DiagnosticCause::TimeBudget) > 0 {
+        metadata.resume_command = Some(resume_command());
+    }
+
+    let image_size = disk.size().as_u64();
     let mut scan_stats = create_scan_results(
         image_size,
         scan_results.bytes_scanned,
@@ -164,26 +648,253 @@ fn run() -> Result<()> {
         args.nvme,
     );
     scan_stats.files_recovered = scan_results.recovered_files.len() as u32;
+    // --links-only saves no files: the links are what it recovers
+    let recovered = if args.links_only { scan_results.links.count() } else { scan_results.recovered_files.len() };
+    let status = RunStatus::classify(&scan_results.diagnostics, recovered);
+    let summary_files = scan_results.recovered_files.len();
+    let resume_command = metadata.resume_command.clone();
+
+    let link_formats = args.link_export_formats();
+    let link_exports = (!link_formats.is_empty()).then(|| match scan_results.links {
+        ScanLinks::Memory(ref links) => report::export::export_links(
+            &output_dir,
+            links,
+            &scan_results.recovered_files,
+            &scan_results.clusters.listed,
+            &link_formats,
+        )
+        .map(|paths| (links.len(), paths))
+        .map_err(RecoveryError::from),
+        // Sorted and written by the database, one row at a time
+        ScanLinks::Database { .. } => ScanDb::open_read_only(&output_dir.join(SCAN_DB_FILE)).and_then(|db| {
+            db.export_links(&output_dir, &scan_results.recovered_files, &link_formats, args.links_only)
+        }),
+    });
 
     let report_paths = report_generator.generate_full_report(
         scan_stats,
         scan_results.clusters,
         scan_results.recovered_files,
-        scan_results.failure_reasons,
+        duplicate_groups,
+        scan_results.diagnostics.entries(),
+        scan_results.incidents,
+        scan_results.links.contexts(),
         metadata,
-    ).map_err(|e| RecoveryError::Config(format!("Report generation failed: {}", e)))?;
+    ).map_err(|e| {
+        let _ = progress.lock().unwrap().set_state(ProgressState::Failed);
+        RecoveryError::Config(format!("Report generation failed: {}", e))
+    })?;
+    let mut reports = serde_json::Map::new();
+    for (kind, path) in [
+        ("html", &report_paths.html_path),
+        ("json", &report_paths.json_path),
+        ("dfxml", &report_paths.dfxml_path),
+        ("body", &report_paths.body_path),
+    ] {
+        let sha256 = fs::read(path).map(|data| rust_recovery::matcher::sha256_hash(&data)).ok();
+        reports.insert(kind.to_string(), serde_json::json!({ "path": path.to_string_lossy(), "sha256": sha256 }));
+    }
+    audit(&audit_log, AuditAction::ReportGenerated, serde_json::Value::Object(reports));
+
+    // --output s3://...: upload the run; the audit log goes up after its last entry
+    let mut uploaded = match remote {
+        Some((ref location, ref client)) => {
+            match dashboard.as_mut() {
+                Some(app) => app.shutdown_step("Uploading to object storage"),
+                None => println!("Uploading to {}...", location.url()),
+            }
+            let uploaded = upload_output(client, location, &output_dir, args.s3_keep_local, &audit_log).map_err(|e| {
+                let _ = progress.lock().unwrap().set_state(ProgressState::Failed);
+                RecoveryError::Io(std::io::Error::other(format!("{}; the results are in {}", e, output_dir.display())))
+            })?;
+            Some(uploaded)
+        }
+        None => None,
+    };
+    audit(&audit_log, AuditAction::RunFinished, serde_json::json!({
+        "status": status.name(),
+        "files_recovered": summary_files,
+        "bytes_scanned": scan_results.bytes_scanned,
+    }));
+    if let Some((ref location, ref client)) = remote {
+        let key = location.key(Path::new(AUDIT_LOG_FILE));
+        match client.put_file(&location.bucket, &key, &audit_path) {
+            Ok(object) => uploaded = uploaded.map(|(objects, bytes)| (objects + 1, bytes + object.size_bytes)),
+            Err(e) => warn!("{}; the audit log is in {}", e, output_dir.display()),
+        }
+    }
+    let _ = progress.lock().unwrap().set_state(if status == RunStatus::Cancelled {
+        ProgressState::Cancelled
+    } else {
+        ProgressState::Completed
+    });
+    // Restore the terminal before printing
+    drop(dashboard);
+    drop(dashboard_log);
 
     println!("Reports generated:");
     println!("  HTML: {}", report_paths.html_path.display());
     println!("  JSON: {}", report_paths.json_path.display());
+    println!("  DFXML: {}", report_paths.dfxml_path.display());
+    println!("  Body file: {}", report_paths.body_path.display());
+    match link_exports {
+        Some(Ok((count, paths))) => {
+            for path in paths {
+                println!("  Links ({}): {}", count, path.display());
+            }
+        }
+        Some(Err(e)) => warn!("Link export failed: {}", e),
+        None => {}
+    }
+    if let (Some((location, _)), Some((objects, bytes))) = (&remote, uploaded) {
+        println!(
+            "Uploaded {} file(s) ({:.1} MB) to {}",
+            objects,
+            bytes as f64 / (1024.0 * 1024.0),
+            location.url()
+        );
+    }
+    if !scan_results.diagnostics.is_empty() {
+        println!("Skipped or unrecovered data:");
+        for entry in scan_results.diagnostics.entries() {
+            println!("  - {}", entry.summary());
+        }
+    }
+    if report_paths.is_degraded() {
+        println!("Warning: reports were generated in degraded mode:");
+        for note in &report_paths.degradations {
+            println!("  - {}", note);
+        }
+    }
+
+    if status == RunStatus::Cancelled {
+        println!("Recovery cancelled: pass --resume to scan the rest of the image");
+    } else if let Some(command) = &resume_command {
+        println!("Scan stopped at --max-duration; to scan the rest of the image run:");
+        println!("  {}", command);
+    } else {
+        println!("Recovery complete!");
+    }
+    Ok(RunSummary {
+        status,
+        files_recovered: summary_files,
+        bytes_scanned: scan_results.bytes_scanned,
+        image_size,
+        bad_sector_bytes: scan_results.diagnostics.bytes(DiagnosticCause::BadSectors),
+        duration_secs: scan_results.scan_duration.as_secs_f64(),
+        report: report_paths.json_path,
+    })
+}
+
+/// This command line with `--resume`, to continue a scan stopped by
+/// `--max-duration` (arguments with spaces or quotes are single-quoted)
+fn resume_command() -> String {
+    let mut argv: Vec<String> = std::env::args().collect();
+    if !argv.iter().any(|arg| arg == "--resume") {
+        argv.push("--resume".to_string());
+    }
+    argv.iter()
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"') {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-    // TUI cleanup is automatic via Drop, but we can ensure terminal is restored here if needed
-    // if let Some(mut app) = tui_app {
-    //     let _ = app.run(); // already ran
-    // }
+/// Scan several images, up to --parallel-images at a time, each into its own
+/// directory under --output; returns the combined summary and its path
+fn run_images(mut args: Args, images: Vec<PathBuf>) -> Result<(MultiImageSummary, PathBuf)> {
+    let started = std::time::Instant::now();
+    let mut first = args.clone();
+    first.image = images[0].clone();
+    first.validate().map_err(RecoveryError::InvalidArgument)?;
+    // --output s3://...: each image uploads its own directory, the summary goes up last
+    let remote = match args.s3_output_url() {
+        Some(url) => {
+            let location = S3Location::parse(&url).map_err(RecoveryError::InvalidArgument)?;
+            args.output = args.s3_staging.clone();
+            Some(location)
+        }
+        None => None,
+    };
+    fs::create_dir_all(&args.output)
+        .map_err(|e| RecoveryError::Config(format!("Failed to create output directory: {}", e)))?;
+    if !args.no_live {
+        warn!("The dashboard shows a single scan: {} images are scanned with --no-live", images.len());
+        args.no_live = true;
+    }
+    let output_dirs = multi_image::image_output_dirs(&args.output, &images);
+    let workers = args.parallel_images.min(images.len());
+    println!("Scanning {} images, {} at a time\n", images.len(), workers);
 
-    println!("Recovery complete!");
-    Ok(())
+    // Each running scan handles Ctrl-C itself; images not started yet are skipped
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let interrupt = Arc::clone(&interrupted);
+    std::thread::spawn(move || {
+        if let Ok(rt) = tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            if rt.block_on(tokio::signal::ctrl_c()).is_ok() {
+                interrupt.store(true, Ordering::SeqCst);
+            }
+        }
+    });
+
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<ImageOutcome>>> = Mutex::new(images.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let (Some(image), Some(output_dir)) = (images.get(index), output_dirs.get(index)) else {
+                    break;
+                };
+                let label = format!("[{}/{}] {}", index + 1, images.len(), image.display());
+                let outcome = if interrupted.load(Ordering::SeqCst) {
+                    ImageOutcome::skipped(image, output_dir)
+                } else {
+                    println!("{}: scanning into {}", label, output_dir.display());
+                    let mut image_args = args.clone();
+                    image_args.image = image.clone();
+                    image_args.output = output_dir.clone();
+                    if let Some(ref location) = remote {
+                        let name = output_dir.file_name().unwrap_or_default().to_string_lossy();
+                        image_args.output = PathBuf::from(location.child(&name).url());
+                        image_args.s3_staging = output_dir.clone();
+                    }
+                    ImageOutcome::new(image, output_dir, &run(image_args))
+                };
+                match outcome.error {
+                    Some(ref error) => eprintln!("{}: {}", label, error),
+                    None => println!("{}: {}, {} file(s)", label, outcome.status, outcome.files_recovered),
+                }
+                outcomes.lock().unwrap()[index] = Some(outcome);
+            });
+        }
+    });
+
+    let outcomes = outcomes.into_inner().unwrap_or_else(|e| e.into_inner());
+    let summary = MultiImageSummary::new(outcomes.into_iter().flatten().collect(), started.elapsed().as_secs_f64());
+    let summary_path = args.output.join(MULTI_IMAGE_SUMMARY_FILE);
+    summary.write(&summary_path)?;
+    if let Some(ref location) = remote {
+        let client = S3Client::new(args.s3_endpoint.as_deref(), args.s3_region.as_deref())?;
+        client.put_file(&location.bucket, &location.key(Path::new(MULTI_IMAGE_SUMMARY_FILE)), &summary_path)?;
+    }
+
+    println!("\nImages:");
+    for outcome in &summary.images {
+        println!(
+            "  {:<18} {:>5} file(s)  {}",
+            outcome.status,
+            outcome.files_recovered,
+            outcome.image.display()
+        );
+    }
+    println!("Summary: {}", summary_path.display());
+    Ok((summary, summary_path))
 }
 
 /// Scan results from the main pipeline
@@ -192,43 +903,103 @@ struct ScanResults {
     bytes_scanned: u64,
     candidates_found: u32,
     scan_duration: std::time::Duration,
-    clusters: Vec<report::DataCluster>,
+    clusters: report::ReportClusters,
     recovered_files: Vec<report::RecoveredFile>,
-    failure_reasons: Vec<String>,
+    diagnostics: ScanDiagnostics,
+    incidents: Vec<ScanIncident>,
+    /// Links extracted by the scan (this run only when resumed)
+    links: ScanLinks,
+}
+
+/// Links of a scan: in memory, or left in the scan database (`--scan-db`)
+#[derive(Debug, Clone)]
+enum ScanLinks {
+    Memory(Vec<EnrichedLink>),
+    /// Number of links (distinct URLs with `--links-only`) and the contexts
+    /// the report lists
+    Database { count: usize, contexts: Vec<report::LinkContext> },
+}
+
+impl ScanLinks {
+    fn count(&self) -> usize {
+        match self {
+            Self::Memory(links) => links.len(),
+            Self::Database { count, .. } => *count,
+        }
+    }
+
+    fn contexts(&self) -> Vec<report::LinkContext> {
+        match self {
+            Self::Memory(links) => report::LinkContext::collect(links),
+            Self::Database { contexts, .. } => contexts.clone(),
+        }
+    }
+}
+
+/// Check the decompressed media against the MD5/SHA1 stored at acquisition.
+/// A mismatch is reported but does not stop the recovery.
+fn verify_ewf(ewf: &rust_recovery::ewf::EwfImage) -> Result<()> {
+    if !ewf.has_stored_hash() {
+        println!("  Verification: no stored hash in the image");
+        return Ok(());
+    }
+
+    println!("  Verifying stored hashes...");
+    let verification = ewf.verify()?;
+    for (name, check) in [("MD5", &verification.md5), ("SHA1", &verification.sha1)] {
+        if let Some(check) = check {
+            let status = if check.matches() { "OK" } else { "MISMATCH" };
+            println!("    {}: {} ({})", name, check.computed, status);
+            if !check.matches() {
+                warn!("{} mismatch: stored {}, computed {}", name, check.stored, check.computed);
+            }
+        }
+    }
+    if verification.corrupt_chunks > 0 {
+        warn!("{} corrupt E01 chunks are read as zeros", verification.corrupt_chunks);
+    }
+    Ok(())
 }
 
 /// Main scanning pipeline
+#[allow(clippy::too_many_arguments)]
 fn run_scan_pipeline(
     disk: DiskImage,
     args: &Args,
     scan_config: &ScanConfig,
     tui_sender: Option<&mpsc::UnboundedSender<TuiEvent>>,
     output_dir: &Path,
+    provenance: &(String, ProvenanceParams),
+    reporter: &Mutex<ProgressReporter>,
+    matcher: &EnhancedMatcher,
+    audit_log: &AuditLog,
+    resumed_fragments: Vec<SavedFragment>,
+    tui_commands: Option<mpsc::UnboundedReceiver<TuiCommand>>,
 ) -> Result<ScanResults> {
     let start_time = std::time::Instant::now();
     
     // Test basic read operations first
     test_disk_access(&disk)?;
 
-    // Send initial status
-    if let Some(sender) = tui_sender {
-        let _ = sender.send(TuiEvent::LogMessage {
-            message: "Disk access verified, starting real-time scan".to_string(),
-        });
-    }
+    info!(target: DASHBOARD, "Disk access verified, starting real-time scan");
 
     // Run the actual scanner
-    let (bytes_scanned, candidates_found, recovered_files, clusters) = 
-        run_real_scan(disk, args, scan_config, tui_sender, output_dir)?;
+    let (bytes_scanned, candidates_found, recovered_files, clusters, incidents, diagnostics, links) =
+        run_real_scan(
+            disk,
+            args,
+            scan_config,
+            tui_sender,
+            output_dir,
+            provenance,
+            reporter,
+            matcher,
+            audit_log,
+            resumed_fragments,
+            tui_commands,
+        )?;
 
     let scan_duration = start_time.elapsed();
-    let mut failure_reasons = Vec::new();
-
-    // If no files recovered, add failure reasons
-    if recovered_files.is_empty() {
-        failure_reasons.push("No valid data structures found in scanned data".to_string());
-        failure_reasons.push("Try adjusting --target-size-min or --chunk-max".to_string());
-    }
 
     Ok(ScanResults {
         bytes_scanned,
@@ -236,45 +1007,595 @@ fn run_scan_pipeline(
         scan_duration,
         clusters,
         recovered_files,
-        failure_reasons,
+        diagnostics,
+        incidents,
+        links,
     })
 }
 
+/// (bytes scanned, candidates found, recovered files, clusters, watchdog incidents, diagnostics, links)
+type RealScanOutput = (
+    u64,
+    usize,
+    Vec<report::RecoveredFile>,
+    report::ReportClusters,
+    Vec<ScanIncident>,
+    ScanDiagnostics,
+    ScanLinks,
+);
+
+/// Input of the scan loop: scanner progress or a dashboard hotkey
+enum ScanInput {
+    Progress(ScanProgress),
+    Command(TuiCommand),
+}
+
+/// Next dashboard command; never resolves without a dashboard
+async fn next_command(commands: &mut Option<mpsc::UnboundedReceiver<TuiCommand>>) -> Option<TuiCommand> {
+    match commands {
+        Some(commands) => commands.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Assembles streams and writes recovered files: once when the scan ends,
+/// and with `--flush-interval` also for the fragments the scan has settled
+struct FileWriter<'a> {
+    disk: &'a DiskImage,
+    args: &'a Args,
+    provenance: &'a (String, ProvenanceParams),
+    file_types: FileTypeRegistry,
+    /// Stream solver settings, with the target size range
+    weights: StreamScoringWeights,
+    writer: RecoveryWriter,
+    filename_policy: FilenamePolicy,
+    /// Streams assembled so far, counted against --max-streams
+    streams_written: usize,
+    /// Fragments marked in the dashboard fragment table, by offset
+    marks: std::collections::HashMap<Offset, FragmentMark>,
+    files: Vec<report::RecoveredFile>,
+    log: Option<RecoveredFileLog>,
+    audit_log: &'a AuditLog,
+    post_processor: Option<PostProcessor>,
+    /// exFAT entry times by first data byte (`--enable-exfat`)
+    exfat_times: std::collections::HashMap<u64, FileTimestamps>,
+    /// Fragment bytes kept by the scan, read before the image (`--fragment-cache`)
+    fragment_cache: Option<Arc<FragmentCache>>,
+}
+
+impl<'a> FileWriter<'a> {
+    fn new(
+        disk: &'a DiskImage,
+        args: &'a Args,
+        provenance: &'a (String, ProvenanceParams),
+        output_dir: &Path,
+        audit_log: &'a AuditLog,
+        fragment_cache: Option<Arc<FragmentCache>>,
+    ) -> Self {
+        let mut log = None;
+        if args.flush_interval().is_some() {
+            let log_path = output_dir.join(RECOVERED_LOG_FILE);
+            match RecoveredFileLog::create(&log_path) {
+                Ok(file) => log = Some(file.with_max_bytes(args.log_max_bytes())),
+                Err(e) => warn!("Failed to create {}: {}", log_path.display(), e),
+            }
+        }
+        let post_processor = args.post_process_cmd.as_deref().map(|command| {
+            let processor = PostProcessor::new(command, args.pipe_output);
+            let log_path = output_dir.join(POST_PROCESS_LOG_FILE);
+            processor.with_log(&log_path, args.log_max_bytes()).unwrap_or_else(|e| {
+                warn!("Failed to create {}: {}", log_path.display(), e);
+                PostProcessor::new(command, args.pipe_output)
+            })
+        });
+        Self {
+            disk,
+            args,
+            provenance,
+            file_types: FileTypeRegistry::builtin().with_utf8_mode(args.utf8_mode()).with_boundary_trim(true),
+            weights: args.stream_weights(),
+            writer: RecoveryWriter::new(&output_dir.join(RECOVERED_FILES_DIR))
+                .with_max_files_per_dir(Some(args.max_files_per_dir)),
+            filename_policy: args.filename_policy(),
+            streams_written: 0,
+            marks: std::collections::HashMap::new(),
+            files: Vec::new(),
+            log,
+            audit_log,
+            post_processor,
+            exfat_times: if args.enable_exfat { exfat_entry_times(disk, args) } else { Default::default() },
+            fragment_cache,
+        }
+    }
+
+    /// `len` bytes at `offset`, from the fragment cache when it holds them
+    fn read(&self, offset: Offset, len: usize) -> Result<std::borrow::Cow<'a, [u8]>> {
+        let cached = self.fragment_cache.as_ref().and_then(|cache| cache.get(offset.as_u64(), len));
+        match cached {
+            Some(data) => Ok(data.into()),
+            None => self.disk.get_slice(offset, len).map(|slice| slice.data),
+        }
+    }
+
+    /// Set or clear the fragment table mark of the fragment at `offset`
+    fn mark(&mut self, offset: Offset, mark: Option<FragmentMark>) {
+        match mark {
+            Some(mark) => self.marks.insert(offset, mark),
+            None => self.marks.remove(&offset),
+        };
+    }
+
+    /// --semantic-scan: group `fragments` by content, reading the start of
+    /// each; their byte frequencies also feed the solver's edge scores
+    fn clusters(&self, fragments: &mut [StreamFragment]) -> Vec<Vec<usize>> {
+        let mut clusterer = FragmentClusterer::new(ClusterConfig::default());
+        for fragment in fragments.iter_mut() {
+            let len = fragment.size.as_u64().min(CLUSTER_SAMPLE_BYTES as u64) as usize;
+            let sample = match self.read(fragment.offset, len) {
+                Ok(data) => data.into_owned(),
+                Err(e) => {
+                    debug!("Semantic scan: fragment at 0x{:X} unreadable: {}", fragment.offset, e);
+                    Vec::new()
+                }
+            };
+            clusterer.add_fragment(fragment.offset.as_u64(), &sample, fragment.links.iter().cloned());
+            fragment.feature_vector = clusterer.features(clusterer.len() - 1).clone();
+        }
+        let clusters = clusterer.cluster();
+        info!("Semantic scan: {} fragments in {} clusters", fragments.len(), clusters.len());
+        clusters
+    }
+
+    /// Give e-mail fragments their Message-IDs and MIME boundaries as link
+    /// sets, so the solver joins the fragments of one message
+    fn email_continuity(&self, fragments: &mut [StreamFragment]) {
+        for fragment in fragments.iter_mut().filter(|f| matches!(f.file_type.as_str(), email::EML | email::MBOX)) {
+            match self.read(fragment.offset, fragment.size.as_u64() as usize) {
+                Ok(data) => fragment.links = email::continuity_keys(&data),
+                Err(e) => debug!("E-mail fragment at 0x{:X} unreadable: {}", fragment.offset, e),
+            }
+        }
+    }
+
+    /// Assemble `fragments` into at most the streams left of --max-streams,
+    /// add the carved files and write them all. Excluded fragments are left
+    /// out; force-recovered ones are saved whatever the size range and the
+    /// stream limit, alone if the solver left them out
+    fn flush(
+        &mut self,
+        fragments: Vec<StreamFragment>,
+        mut carved_streams: Vec<AssembledStream>,
+        diagnostics: &mut ScanDiagnostics,
+        reporter: &Mutex<ProgressReporter>,
+        tui_sender: Option<&mpsc::UnboundedSender<TuiEvent>>,
+    ) {
+        let marked = |offset: &Offset, mark: FragmentMark| self.marks.get(offset) == Some(&mark);
+        let (mut fragments, excluded): (Vec<_>, Vec<_>) =
+            fragments.into_iter().partition(|fragment| !marked(&fragment.offset, FragmentMark::Exclude));
+        carved_streams.retain(|stream| {
+            let keep = !marked(&stream.fragments[0].offset, FragmentMark::Exclude);
+            if !keep {
+                diagnostics.record(DiagnosticCause::OperatorExcluded, stream.size_bytes());
+            }
+            keep
+        });
+        for fragment in &excluded {
+            diagnostics.record(DiagnosticCause::OperatorExcluded, fragment.size.as_u64());
+        }
+        let forced: Vec<StreamFragment> = fragments
+            .iter()
+            .filter(|fragment| marked(&fragment.offset, FragmentMark::ForceRecover))
+            .cloned()
+            .collect();
+        // Split fragments come back as pieces, so compare extents
+        let overlaps = |stream: &AssembledStream, f: &StreamFragment| {
+            stream.fragments.iter().any(|piece| piece.offset < f.offset.end(f.size) && f.offset < piece.offset.end(piece.size))
+        };
+        let overlaps_forced = |stream: &AssembledStream| forced.iter().any(|f| overlaps(stream, f));
+
+        self.email_continuity(&mut fragments);
+        let quota = self.args.max_streams.saturating_sub(self.streams_written);
+        let StreamAssembly { mut streams, undersized, .. } = if quota > 0 && self.args.semantic_scan {
+            let clusters = self.clusters(&mut fragments);
+            stream_solver::assemble_streams_clustered(&fragments, &clusters, &self.weights, Some(quota))
+        } else if quota > 0 {
+            stream_solver::assemble_streams_sized(&fragments, &self.weights, Some(quota))
+        } else {
+            StreamAssembly::default()
+        };
+        self.streams_written += streams.len();
+        let (mut forced_streams, undersized): (Vec<_>, Vec<_>) = undersized.into_iter().partition(|stream| overlaps_forced(stream));
+        for stream in &undersized {
+            diagnostics.record(DiagnosticCause::OutsideSizeRange, stream.size_bytes());
+        }
+        // Forced fragments the solver left out (stream limit) are saved alone
+        for fragment in &forced {
+            if !streams.iter().chain(&forced_streams).any(|stream| overlaps(stream, fragment)) {
+                forced_streams.push(AssembledStream {
+                    confidence: fragment.base_score,
+                    total_score: fragment.base_score,
+                    reasons: fragment.fragment_score.reasons.clone(),
+                    fragments: vec![fragment.clone()],
+                });
+            }
+        }
+
+        // Fragments (or pieces of split ones) left once the stream limit is
+        // hit were never considered for a file
+        if streams.len() >= quota || !undersized.is_empty() {
+            let assembled: std::collections::HashSet<(Offset, Size)> = streams
+                .iter()
+                .chain(&undersized)
+                .chain(&forced_streams)
+                .flat_map(|stream| stream.fragments.iter().map(|f| (f.offset, f.size)))
+                .collect();
+            for fragment in &fragments {
+                for piece in stream_solver::split_fragment(fragment, self.weights.max_stream_size) {
+                    if !assembled.contains(&(piece.offset, piece.size)) {
+                        diagnostics.record(DiagnosticCause::OverQuotaStreams, piece.size.as_u64());
+                    }
+                }
+            }
+        }
+        // Carved files do not count against --max-streams; they are numbered
+        // by offset whatever order the chunks were scanned in (--priority)
+        carved_streams.sort_by_key(|stream| stream.fragments[0].offset);
+        let assembled_count = streams.len();
+        streams.append(&mut forced_streams);
+        streams.append(&mut carved_streams);
+        if streams.is_empty() {
+            return;
+        }
+
+        for (index, stream) in streams.into_iter().enumerate() {
+            let raw_size = stream.size_bytes();
+            let sized = index < assembled_count && !overlaps_forced(&stream);
+            let Some(mut file) = self.write_stream(stream, sized) else {
+                diagnostics.record(DiagnosticCause::OutsideSizeRange, raw_size);
+                continue;
+            };
+            let file_path = self.writer.dir().join(&file.filename);
+            if let Some(processor) = self.post_processor.as_mut().filter(|_| file_path.is_file()) {
+                let result = processor.run(&file_path);
+                if !result.succeeded() {
+                    let message = match (result.exit_code, &result.error) {
+                        (Some(code), _) => format!("Post-process {}: exit code {}", file.filename, code),
+                        (None, error) => format!("Post-process {}: {}", file.filename, error.as_deref().unwrap_or("failed")),
+                    };
+                    warn!("{}", message);
+                }
+                file.post_process = Some(result);
+            }
+            if file_path.is_file() {
+                audit(self.audit_log, AuditAction::FileWritten, serde_json::json!({
+                    "path": format!("{}/{}", RECOVERED_FILES_DIR, file.filename),
+                    "size_bytes": file.size_bytes,
+                    "sha256": file.sha256,
+                    "md5": file.md5,
+                    "recovered_at": file.recovery_time,
+                    "start_offset": file.start_offset,
+                    "fragments": file.fragments.len(),
+                }));
+            }
+            if let Some(ref mut log) = self.log {
+                if let Err(e) = log.append(&file) {
+                    warn!("Recovered file log stopped: {}", e);
+                    self.log = None;
+                }
+            }
+            let (filename, size_kb) = (file.filename.clone(), file.size_bytes / 1024);
+            self.files.push(file);
+
+            {
+                let mut reporter = reporter.lock().unwrap();
+                reporter.file_recovered();
+                let _ = reporter.tick();
+            }
+
+            if let Some(sender) = tui_sender {
+                let _ = sender.send(TuiEvent::FileRecovered { filename: filename.clone() });
+            }
+            info!(target: DASHBOARD, "Saved recovered file: {} ({} KB)", filename, size_kb);
+        }
+    }
+
+    /// Store the title of the file saved as `filename` (`--keep-title`). On an
+    /// output filesystem without extended attributes titles stay in the report only.
+    fn keep_title(&mut self, filename: &str, title: &str) {
+        let path = self.writer.dir().join(filename);
+        match self.filename_policy.keep_title(&path, title) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+                warn!("--keep-title {}: {}; titles are kept in the report only", self.filename_policy.keep_title.name(), e);
+                self.filename_policy.keep_title = TitleStore::None;
+            }
+            // A title too long for one attribute value
+            Err(e) => warn!("--keep-title {}: {}: {}", self.filename_policy.keep_title.name(), filename, e),
+        }
+    }
+
+    /// Rebuild one stream from the image, save it and describe it for the report;
+    /// None when `sized` and the cleaned file is outside the target size range
+    fn write_stream(&mut self, stream: AssembledStream, sized: bool) -> Option<report::RecoveredFile> {
+        let file_types = &self.file_types;
+        let file_id = self.files.len() + 1;
+        let file_type = stream.fragments[0].file_type.clone();
+        let fragments: Vec<FragmentExtent> = stream.fragments
+            .iter()
+            .map(|f| FragmentExtent::new(f.offset, f.size))
+            .collect();
+        // Concatenate fragments and clean junk/nulls (`verify` rebuilds files the same way)
+        let read = |offset, len| self.read(offset, len).ok();
+        let gap_fill = self.args.gap_fill();
+        let Reassembled { data: file_data, utf8_repaired, trimmed, gaps, extents_sha256, .. } =
+            reassemble_with(read, &fragments, &file_type, file_types, gap_fill);
+        // Cleaning shrinks a stream (or --utf8 replace grows it) after the solver sized it
+        let size_range = self.weights.min_stream_size..=self.weights.max_stream_size;
+        if sized && !size_range.contains(&(file_data.len() as u64)) {
+            return None;
+        }
+
+        // Generate filename with title if possible; the report keeps the title as written
+        let title = file_types.extract_title(&file_data, &file_type);
+        let extension = file_types.extension(&file_type);
+        let filename = self.filename_policy.file_name(file_id, title.as_deref(), extension);
+        if trimmed > 0 {
+            debug!("{}: {} bytes after the end of the document cut", filename, trimmed);
+        }
+        if !gaps.is_empty() {
+            let missing: u64 = gaps.iter().map(|gap| gap.len).sum();
+            debug!("{}: {} holes for {} missing bytes", filename, gaps.len(), missing);
+        }
+
+        let total_size_bytes = file_data.len() as u64;
+        let sha256 = rust_recovery::matcher::sha256_hash(&file_data);
+        let md5 = rust_recovery::matcher::md5_hash(&file_data);
+        let fuzzy_hash = rust_recovery::hashing::fuzzy_hash(&file_data);
+
+        let (image_hash, params) = self.provenance;
+        let mut file_params = params.clone();
+        file_params.insert("file_type".to_string(), file_type.clone());
+        let provenance_hash = provenance_hash(image_hash, &fragments, &extents_sha256, TOOL_VERSION, &file_params);
+
+        // A file starting where an exFAT entry's data starts keeps that entry's times
+        let timestamps = self.exfat_times.get(&stream.fragments[0].offset.as_u64()).copied();
+
+        // Physically save to disk, never over an existing file
+        let (filename, renamed_from, validation_status) = match self.writer.write(&filename, &file_data) {
+            Ok(written) => {
+                if let Some(title) = title.as_deref() {
+                    self.keep_title(&written.filename, title);
+                }
+                if let Some(times) = timestamps {
+                    if let Err(e) = times.apply(&self.writer.dir().join(&written.filename)) {
+                        warn!("Failed to set the times of {}: {}", written.filename, e);
+                    }
+                }
+                (
+                    written.filename,
+                    written.renamed_from,
+                    self.file_types.validate_repaired(&file_data, &file_type, utf8_repaired),
+                )
+            }
+            Err(e) => {
+                warn!("Failed to save {}: {}", filename, e);
+                (filename, None, report::ValidationStatus::Invalid)
+            }
+        };
+
+        Some(report::RecoveredFile {
+            id: file_id,
+            filename,
+            file_type,
+            confidence: stream.confidence as f64,
+            links: Vec::new(),
+            size_kb: total_size_bytes / 1024,
+            sha256,
+            md5,
+            fuzzy_hash,
+            size_bytes: total_size_bytes,
+            utf8_repaired_bytes: utf8_repaired as u64,
+            trimmed_bytes: trimmed as u64,
+            start_offset: stream.fragments.first().unwrap().offset,
+            end_offset: stream.fragments.last().unwrap().end_offset(),
+            validation_status,
+            recovery_time: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            fragments,
+            provenance_hash,
+            title_language: title.as_deref().map(|t| detect_language(t).code().to_string()),
+            title,
+            post_process: None,
+            repair: None,
+            renamed_from,
+            timestamps,
+            gaps,
+        })
+    }
+}
+
 /// Perform real disk scanning using ParallelScanner
+#[allow(clippy::too_many_arguments)]
 fn run_real_scan(
     disk: DiskImage,
-    _args: &Args,
+    args: &Args,
     scan_config: &ScanConfig,
     tui_sender: Option<&mpsc::UnboundedSender<TuiEvent>>,
-    _output_dir: &Path,
-) -> Result<(u64, usize, Vec<report::RecoveredFile>, Vec<report::DataCluster>)> {
+    output_dir: &Path,
+    provenance: &(String, ProvenanceParams),
+    reporter: &Mutex<ProgressReporter>,
+    matcher: &EnhancedMatcher,
+    audit_log: &AuditLog,
+    resumed_fragments: Vec<SavedFragment>,
+    mut tui_commands: Option<mpsc::UnboundedReceiver<TuiCommand>>,
+) -> Result<RealScanOutput> {
+    // Dual-range scan: filesystem metadata first, then the full surface
+    if args.metadata_first {
+        run_metadata_pass(&disk, output_dir, args, tui_sender, audit_log);
+    }
+
     let rt = Arc::new(Runtime::new().map_err(|e| RecoveryError::Config(e.to_string()))?);
-    let scanner = ParallelScanner::new(scan_config.clone());
-    
+    let scanner = ParallelScanner::with_matcher(scan_config.clone(), matcher.clone_fresh());
+    let control = scanner.control();
+
+    // Ctrl-C stops the scan; files found so far are still saved and reported,
+    // and the checkpoint lets --resume continue. A second Ctrl-C aborts
+    let interrupt = Arc::clone(&control);
+    rt.spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            interrupt.cancel();
+            warn!("Cancelling: saving the files found so far (Ctrl-C again to abort)");
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(exit_code::CANCELLED);
+            }
+        }
+    });
+
+    // --max-duration stops dispatching chunks (those in flight finish); dropping
+    // the guard at the end of the scan stops the timer
+    let budget_guard = args.time_budget().map(|budget| {
+        let (guard, done) = std::sync::mpsc::channel::<()>();
+        let control = Arc::clone(&control);
+        std::thread::spawn(move || {
+            if done.recv_timeout(budget) == Err(std::sync::mpsc::RecvTimeoutError::Timeout) {
+                control.stop();
+                warn!("--max-duration reached: finishing the chunks in flight and assembling the files found so far");
+            }
+        });
+        guard
+    });
+
     let (progress_tx, mut progress_rx) = mpsc::channel(100);
     
     let disk_clone = disk.clone();
     let scanner_clone = scanner.clone();
     let rt_clone = Arc::clone(&rt);
+
+    // Bytes and fragments of the checkpointed run are replayed ahead of the scan
+    let image_size = disk.size().as_u64();
+    let resumed_bytes = match scan_config.resume_from {
+        Some(position) if scan_config.reverse => image_size.saturating_sub(position.as_u64()),
+        Some(position) => position.as_u64(),
+        None => 0,
+    };
+    let replayed: Vec<_> = resumed_fragments.iter().map(SavedFragment::to_hot_fragment).collect();
     
     // Start scanner in a background thread
     let scan_handle = std::thread::spawn(move || {
         rt_clone.block_on(async {
+            if resumed_bytes > 0 {
+                let _ = progress_tx.send(ScanProgress::BytesScanned(resumed_bytes)).await;
+            }
+            for fragment in replayed {
+                let _ = progress_tx.send(ScanProgress::HotFragment(fragment)).await;
+            }
             scanner_clone.scan(&disk_clone, progress_tx).await
         })
     });
 
+    // Periodic checkpoints let an interrupted scan continue with --resume
+    let checkpoints = rt.block_on(async { CheckpointManager::start(output_dir.join(SCAN_CHECKPOINT_FILE), true) });
+    let mut scan_state = ScanState { reverse: scan_config.reverse, fragments: Vec::new() };
+    let mut resume_position = scan_config
+        .resume_from
+        .unwrap_or(Offset::new(if scan_config.reverse { image_size } else { 0 }));
+    {
+        // The checkpointed run scanned everything on the far side of the resume point
+        let mut reporter = reporter.lock().unwrap();
+        match scan_config.reverse {
+            true => reporter.add_scanned(resume_position, image_size.saturating_sub(resume_position.as_u64())),
+            false => reporter.add_scanned(Offset::new(0), resume_position.as_u64()),
+        }
+        reporter.set_position(resume_position);
+    }
+    let mut last_checkpoint = std::time::Instant::now();
+    let checkpoint_at = |position: Offset, state: &ScanState| {
+        Checkpoint::new(
+            args.image.to_string_lossy().to_string(),
+            provenance.0.clone(),
+            position,
+            serde_json::to_value(state).unwrap_or_default(),
+        )
+    };
+
     let mut total_bytes_scanned = 0u64;
     let mut candidates_count = 0usize;
-    let mut recovered_files = Vec::new();
+    let fragment_cache = scan_config.fragment_cache.clone();
+    let mut file_writer = FileWriter::new(&disk, args, provenance, output_dir, audit_log, fragment_cache);
+    let flush_interval = args.flush_interval();
+    let mut last_flush = std::time::Instant::now();
+    let mut space_watch = SpaceWatch::new(output_dir);
+    let mut chunk_stats_full = false;
     let mut clusters = Vec::new();
     let mut stream_fragments = Vec::new();
+    let mut carved_streams = Vec::new();
+    let mut carved_offsets = std::collections::HashSet::new();
+    let mut incidents = Vec::new();
+    let mut chunk_stats = Vec::new();
+    // Keyed by offset: chunk overlaps report a structure twice
+    let mut structures = std::collections::BTreeMap::new();
+    let mut diagnostics = ScanDiagnostics::new();
+    let log_path = output_dir.join(FRAGMENT_LOG_FILE);
+    let mut fragment_log = None;
+    if args.fragments_jsonl {
+        match FragmentLog::create(&log_path) {
+            Ok(log) => fragment_log = Some(log.with_max_bytes(args.log_max_bytes())),
+            Err(e) => warn!("Failed to create {}: {}", log_path.display(), e),
+        }
+    }
+    // --scan-db: links and fragments go to the database instead of the vectors above
+    let mut scan_db = match args.scan_db {
+        true => Some(ScanDb::open(&output_dir.join(SCAN_DB_FILE), scan_config.resume_from.is_some())?),
+        false => None,
+    };
+    let _ = reporter.lock().unwrap().set_state(ProgressState::Scanning);
 
-    // Process progress updates
-    while let Some(progress) = rt.block_on(async { progress_rx.recv().await }) {
+    // Process progress updates and dashboard hotkeys
+    while let Some(input) = rt.block_on(async {
+        tokio::select! {
+            progress = progress_rx.recv() => progress.map(ScanInput::Progress),
+            Some(command) = next_command(&mut tui_commands) => Some(ScanInput::Command(command)),
+        }
+    }) {
+        let progress = match input {
+            ScanInput::Progress(progress) => progress,
+            ScanInput::Command(command) => {
+                let message = match command {
+                    TuiCommand::Pause => {
+                        control.pause();
+                        "Workers pause after their current chunk".to_string()
+                    }
+                    TuiCommand::Resume => {
+                        control.resume();
+                        "Workers resumed".to_string()
+                    }
+                    TuiCommand::SkipChunk => format!("Skipping {} chunk(s) in progress", control.skip()),
+                    TuiCommand::Cancel => {
+                        control.cancel();
+                        "Scan cancelled, saving the files found so far".to_string()
+                    }
+                    TuiCommand::MarkFragment { offset, mark } => {
+                        file_writer.mark(offset, mark);
+                        match mark {
+                            Some(mark) => format!("Fragment 0x{:X} marked {}", offset.as_u64(), mark.name()),
+                            None => format!("Fragment 0x{:X} unmarked", offset.as_u64()),
+                        }
+                    }
+                    TuiCommand::SaveCheckpoint => {
+                        last_checkpoint = std::time::Instant::now();
+                        match rt.block_on(checkpoints.save(checkpoint_at(resume_position, &scan_state))) {
+                            Ok(()) => format!("Checkpoint saved at 0x{:X}", resume_position.as_u64()),
+                            Err(e) => format!("Checkpoint failed: {}", e),
+                        }
+                    }
+                };
+                info!(target: DASHBOARD, "{}", message);
+                continue;
+            }
+        };
         match progress {
             ScanProgress::BytesScanned(bytes) => {
                 total_bytes_scanned += bytes;
+                let _ = reporter.lock().unwrap().tick();
                 if let Some(sender) = tui_sender {
                     let _ = sender.send(TuiEvent::UpdatePosition {
                         position: total_bytes_scanned, 
@@ -283,131 +1604,719 @@ fn run_real_scan(
                 }
             }
             ScanProgress::HotFragment(fragment) => {
+                // Requeued windows overlap, so one file can be carved twice
+                if fragment.carved && !carved_offsets.insert(fragment.offset) {
+                    continue;
+                }
+                scan_state.fragments.push(SavedFragment::from(&fragment));
                 candidates_count += 1;
+                reporter.lock().unwrap().fragment_found();
+                if let Some(ref mut log) = fragment_log {
+                    if let Err(e) = log.append(candidates_count, &fragment) {
+                        warn!("Fragment log stopped, {} failed: {}", log_path.display(), e);
+                        fragment_log = None;
+                    }
+                }
                 
-                // Add to clusters for report
-                clusters.push(report::DataCluster {
-                    id: candidates_count,
-                    start_offset_hex: format!("0x{:X}", fragment.offset),
-                    end_offset_hex: format!("0x{:X}", fragment.offset + fragment.size as u64),
-                    size_bytes: fragment.size as u64,
-                    size_kb: (fragment.size / 1024) as u64,
-                    link_count: fragment.youtube_count as u32,
-                    density: fragment.cyrillic_density as f64,
-                    confidence: fragment.target_score as f64,
-                    links: Vec::new(), 
-                });
+                if let Some(ref db) = scan_db {
+                    // Clusters and solver input are read back from the database
+                    if let Err(e) = db.insert_fragment(candidates_count, &SavedFragment::from(&fragment)) {
+                        warn!("Failed to store fragment 0x{:X} in {}: {}", fragment.offset, SCAN_DB_FILE, e);
+                    }
+                } else {
+                    // Add to clusters for report
+                    clusters.push(report::DataCluster {
+                        id: candidates_count,
+                        start_offset_hex: format!("0x{:X}", fragment.offset),
+                        end_offset_hex: format!("0x{:X}", fragment.offset.end(fragment.size)),
+                        size_bytes: fragment.size,
+                        size_kb: fragment.size.as_kb(),
+                        link_count: fragment.youtube_count as u32,
+                        density: fragment.cyrillic_density as f64,
+                        confidence: fragment.target_score as f64,
+                        entropy: fragment.entropy as f64,
+                        links: Vec::new(),
+                    });
 
-                // Convert to StreamFragment for solver
-                let stream_frag = StreamFragment {
-                    offset: fragment.offset,
-                    size: fragment.size,
-                    base_score: fragment.target_score,
-                    file_type: fragment.file_type_guess.clone(),
-                    links: Vec::new(), // Optional: could extract links here
-                    feature_vector: rust_recovery::smart_separation::ByteFrequency::default(), 
-                    fragment_score: fragment.fragment_score.clone(),
-                };
-                stream_fragments.push(stream_frag);
+                    // --links-only: nothing is assembled, so fragments are not kept
+                    if !args.links_only {
+                        let stream_frag = stream_fragment(&fragment);
+                        if fragment.carved {
+                            carved_streams.push(carved_stream(stream_frag));
+                        } else {
+                            stream_fragments.push(stream_frag);
+                            if stream_fragments.len() == PENDING_FRAGMENTS_WARNING {
+                                warn!(
+                                    "{} fragments held in memory for assembly{}",
+                                    PENDING_FRAGMENTS_WARNING,
+                                    if flush_interval.is_none() { "; --flush-interval saves their files during the scan" } else { "" }
+                                );
+                            }
+                        }
+                    }
+                }
 
                 if let Some(sender) = tui_sender {
-                    let _ = sender.send(TuiEvent::FragmentFound {
-                        offset: fragment.offset,
-                    });
+                    let _ = sender.send(TuiEvent::FragmentFound { fragment: fragment.clone() });
                 }
             }
-            ScanProgress::ChunkCompleted(offset) => {
-                if let Some(sender) = tui_sender {
-                    let _ = sender.send(TuiEvent::LogMessage {
-                        message: format!("Chunk at 0x{:X} completed", offset),
-                    });
-                }
+            ScanProgress::ChunkCompleted(offset, len) => {
+                reporter.lock().unwrap().add_scanned(offset, len);
+                debug!("Chunk at 0x{:X} completed", offset);
             }
             ScanProgress::ChunkError(offset, err) => {
-                if let Some(sender) = tui_sender {
-                    let _ = sender.send(TuiEvent::LogMessage {
-                        message: format!("Error at 0x{:X}: {}", offset, err),
-                    });
+                info!(target: DASHBOARD, "Error at 0x{:X}: {}", offset, err);
+            }
+            ScanProgress::Incident(incident) => {
+                info!(
+                    target: DASHBOARD,
+                    "Chunk at 0x{:X} stalled, requeued ({} of {} windows abandoned)",
+                    incident.offset,
+                    incident.abandoned_windows,
+                    incident.requeued_windows
+                );
+                diagnostics.record(DiagnosticCause::StalledWindows, incident.unscanned_bytes);
+                incidents.push(incident);
+            }
+            ScanProgress::Skipped(_, bytes, cause) => {
+                diagnostics.record(cause, bytes);
+            }
+            ScanProgress::ChunkStats(stats) => {
+                if chunk_stats.len() < MAX_CHUNK_STATS_ROWS {
+                    chunk_stats.push(stats);
+                } else if !chunk_stats_full {
+                    chunk_stats_full = true;
+                    warn!("Chunk statistics limited to {} chunks, later chunks are not recorded", MAX_CHUNK_STATS_ROWS);
+                }
+            }
+            ScanProgress::Links(links) => {
+                if let Some(ref mut db) = scan_db {
+                    if let Err(e) = db.insert_links(&links) {
+                        warn!("Failed to store {} links in {}: {}", links.len(), SCAN_DB_FILE, e);
+                    }
+                }
+            }
+            ScanProgress::Structures(hits) => {
+                for hit in hits {
+                    structures.insert((hit.offset, hit.structure), hit);
+                }
+            }
+            ScanProgress::ResumePoint(position) => {
+                resume_position = position;
+                reporter.lock().unwrap().set_position(position);
+                if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                    let _ = rt.block_on(checkpoints.save_fire_and_forget(checkpoint_at(position, &scan_state)));
+                    last_checkpoint = std::time::Instant::now();
                 }
             }
         }
+
+        if let Some(message) = space_watch.poll() {
+            warn!("{}", message);
+        }
+
+        // --flush-interval: save the files of the fragments the scan has moved past,
+        // later chunks can no longer add to them
+        if flush_interval.is_some_and(|interval| last_flush.elapsed() >= interval) {
+            last_flush = std::time::Instant::now();
+            let settled = |fragment: &StreamFragment| match scan_config.reverse {
+                false => fragment.offset.end(fragment.size) <= resume_position,
+                true => fragment.offset >= resume_position,
+            };
+            let (mut ready, pending): (Vec<_>, Vec<_>) = stream_fragments.into_iter().partition(settled);
+            stream_fragments = pending;
+            if let Some(ref mut db) = scan_db {
+                take_stored_fragments(db, Some(resume_position), scan_config.reverse, &mut ready, &mut carved_streams);
+            }
+            file_writer.flush(ready, std::mem::take(&mut carved_streams), &mut diagnostics, reporter, tui_sender);
+        }
+    }
+
+    drop(budget_guard);
+
+    if let Some(sender) = tui_sender {
+        let _ = sender.send(TuiEvent::ShutdownStep { step: "Saving scan checkpoint".to_string() });
+    }
+    if let Err(e) = rt.block_on(checkpoints.save(checkpoint_at(resume_position, &scan_state))) {
+        warn!("Failed to save scan checkpoint: {}", e);
+    }
+    let _ = rt.block_on(checkpoints.shutdown());
+
+    if args.chunk_stats {
+        chunk_stats.sort_by_key(|stats| stats.offset);
+        let stats_path = output_dir.join("chunk_stats.csv");
+        let message = match report::write_chunk_stats_csv(&stats_path, &chunk_stats) {
+            Ok(()) => format!("Chunk statistics ({} chunks) written to {}", chunk_stats.len(), stats_path.display()),
+            Err(e) => format!("Failed to write {}: {}", stats_path.display(), e),
+        };
+        info!("{}", message);
+    }
+
+    if !args.detect.is_empty() {
+        let hits: Vec<_> = structures.into_values().collect();
+        let structures_path = output_dir.join("structures.csv");
+        let mut counts = std::collections::BTreeMap::new();
+        for hit in &hits {
+            *counts.entry(hit.structure).or_insert(0usize) += 1;
+        }
+        let summary: Vec<String> = counts.iter().map(|(name, count)| format!("{} {}", name, count)).collect();
+        let message = match report::write_structures_csv(&structures_path, &hits) {
+            Ok(()) => format!(
+                "Structures found: {} ({}) written to {}",
+                hits.len(),
+                if summary.is_empty() { "none".to_string() } else { summary.join(", ") },
+                structures_path.display()
+            ),
+            Err(e) => format!("Failed to write {}: {}", structures_path.display(), e),
+        };
+        info!("{}", message);
     }
 
     // Wait for scan to finish
-    let _ = scan_handle.join().map_err(|_| RecoveryError::Config("Scanner thread panicked".to_string()))?;
+    let scan_result = scan_handle.join().map_err(|_| RecoveryError::Config("Scanner thread panicked".to_string()))?;
+    let mut links = Vec::new();
+    if let Ok(result) = scan_result {
+        diagnostics.record(
+            DiagnosticCause::ExcludedRange,
+            disk.size().as_u64().saturating_sub(result.bytes_scanned + resumed_bytes),
+        );
+        links = result.links;
+    }
+    if args.links_only {
+        let found = links.len();
+        links = report::export::dedupe_links(links);
+        info!("Links: {} found, {} distinct URLs", found, links.len());
+    }
+
+    // Marks made while the scan wound down; later ones no longer apply
+    if let Some(ref mut commands) = tui_commands {
+        while let Ok(command) = commands.try_recv() {
+            if let TuiCommand::MarkFragment { offset, mark } = command {
+                file_writer.mark(offset, mark);
+            }
+        }
+    }
+    if let Some(sender) = tui_sender {
+        let _ = sender.send(TuiEvent::AssemblyStarted);
+    }
+
+    // --scan-db: the stored fragments are assembled a page at a time
+    let max_gap = args.stream_weights().max_gap;
+    let mut pending = stream_fragments.len();
+    if let Some(ref mut db) = scan_db {
+        if !args.links_only {
+            pending += db.pending_fragment_count().unwrap_or(0) as usize;
+            take_fragment_page(db, max_gap, &mut stream_fragments, &mut carved_streams);
+        }
+    }
 
     // --- ASSEMBLE STREAMS ---
-    if !stream_fragments.is_empty() {
+    if !stream_fragments.is_empty() || !carved_streams.is_empty() {
+        let _ = reporter.lock().unwrap().set_state(ProgressState::Assembling);
         if let Some(sender) = tui_sender {
-            let _ = sender.send(TuiEvent::LogMessage {
-                message: format!("Assembling {} fragments into streams...", stream_fragments.len()),
+            let _ = sender.send(TuiEvent::ShutdownStep {
+                step: format!("Assembling {} fragments and saving recovered files", pending),
             });
         }
+        while !stream_fragments.is_empty() || !carved_streams.is_empty() {
+            let (page, carved) = (std::mem::take(&mut stream_fragments), std::mem::take(&mut carved_streams));
+            file_writer.flush(page, carved, &mut diagnostics, reporter, tui_sender);
+            if let Some(db) = scan_db.as_mut().filter(|_| !args.links_only) {
+                take_fragment_page(db, max_gap, &mut stream_fragments, &mut carved_streams);
+            }
+        }
+    }
+    let recovered_files = file_writer.files;
 
-        let streams = stream_solver::assemble_streams(&stream_fragments);
-        
-        // Create output subdirectory for binary files
-        let bin_output_dir = _output_dir.join("01_RECOVERED_FILES");
-        if !bin_output_dir.exists() {
-            let _ = fs::create_dir_all(&bin_output_dir);
-        }
-
-        for (i, stream) in streams.into_iter().enumerate() {
-            let file_id = i + 1;
-            let file_type = stream.fragments[0].file_type.clone();
-            // Reconstruct file data by concatenating fragments
-            let mut raw_data = Vec::new();
-            for fragment in &stream.fragments {
-                if let Ok(slice) = disk.get_slice(Offset::new(fragment.offset), fragment.size) {
-                    raw_data.extend_from_slice(slice.data);
-                }
+    // The database is read one cluster at a time for the report's charts,
+    // which list the first clusters only; the links stay in it for the export
+    let mut clusters = report::ReportClusters::from(clusters);
+    let mut links = ScanLinks::Memory(links);
+    if let Some(ref db) = scan_db {
+        let mut charts = ClusterCharts::new(image_size);
+        let mut listed = Vec::new();
+        db.for_each_cluster(|cluster| {
+            charts.add(&cluster);
+            if listed.len() < REPORT_LIST_LIMIT {
+                listed.push(cluster);
             }
+        })?;
+        info!(
+            "Scan database: {} links, {} fragments in {}",
+            db.link_count()?,
+            charts.count(),
+            db.path().display()
+        );
+        clusters = report::ReportClusters { listed, charts: Some(charts) };
+        let count = if args.links_only { db.url_count()? } else { db.link_count()? };
+        links = ScanLinks::Database { count: count as usize, contexts: db.link_contexts(REPORT_LIST_LIMIT)? };
+    }
+
+    Ok((total_bytes_scanned, candidates_count, recovered_files, clusters, incidents, diagnostics, links))
+}
+
+/// Hot fragment as input of the stream solver
+fn stream_fragment(fragment: &HotFragment) -> StreamFragment {
+    StreamFragment {
+        offset: fragment.offset,
+        size: fragment.size,
+        base_score: fragment.target_score,
+        file_type: fragment.file_type_guess.clone(),
+        links: Vec::new(), // Optional: could extract links here
+        feature_vector: rust_recovery::smart_separation::ByteFrequency::default(),
+        fragment_score: fragment.fragment_score.clone(),
+    }
+}
+
+/// A carved file is complete: it is saved as a stream of its own
+fn carved_stream(fragment: StreamFragment) -> AssembledStream {
+    AssembledStream {
+        confidence: fragment.base_score,
+        total_score: fragment.base_score,
+        reasons: fragment.fragment_score.reasons.clone(),
+        fragments: vec![fragment],
+    }
+}
 
-            // Clean content (remove junk/nulls)
-            let file_data = clean_file_content(&raw_data, &file_type).into_owned();
+/// Move the fragments stored in the scan database (those settled before
+/// `settled_at`, or all) to the solver input and the carved streams
+fn take_stored_fragments(
+    db: &mut ScanDb,
+    settled_at: Option<Offset>,
+    reverse: bool,
+    fragments: &mut Vec<StreamFragment>,
+    carved: &mut Vec<AssembledStream>,
+) {
+    hand_over(db.take_fragments(settled_at, reverse), fragments, carved);
+}
+
+/// Move the next page of stored fragments (`ScanDb::take_fragment_page`)
+/// to the solver input and the carved streams
+fn take_fragment_page(
+    db: &mut ScanDb,
+    max_gap: u64,
+    fragments: &mut Vec<StreamFragment>,
+    carved: &mut Vec<AssembledStream>,
+) {
+    hand_over(db.take_fragment_page(ASSEMBLY_PAGE_FRAGMENTS, max_gap), fragments, carved);
+}
 
-            // Generate filename with title if possible
-            let mut filename = format!("recovered_{:04}.{}", file_id, file_type);
-            if let Some(title) = extract_title(&file_data, &file_type) {
-                filename = format!("recovered_{:04}_{}.{}", file_id, title, file_type);
+fn hand_over(
+    stored: Result<Vec<SavedFragment>>,
+    fragments: &mut Vec<StreamFragment>,
+    carved: &mut Vec<AssembledStream>,
+) {
+    match stored {
+        Ok(stored) => {
+            for fragment in stored.iter().map(SavedFragment::to_hot_fragment) {
+                match fragment.carved {
+                    true => carved.push(carved_stream(stream_fragment(&fragment))),
+                    false => fragments.push(stream_fragment(&fragment)),
+                }
             }
-            
-            let file_path = bin_output_dir.join(&filename);
+        }
+        Err(e) => warn!("Failed to read fragments from {}: {}", SCAN_DB_FILE, e),
+    }
+}
 
-            let total_size_bytes = file_data.len() as u64;
-            let sha256 = rust_recovery::matcher::sha256_hash(&file_data);
+/// Load and check the checkpoint `--resume` continues from
+fn load_resume_point(args: &Args, output_dir: &Path) -> Result<(Offset, ScanState)> {
+    let path = output_dir.join(SCAN_CHECKPOINT_FILE);
+    if !path.exists() {
+        return Err(RecoveryError::Config(format!("No checkpoint to resume from: {}", path.display())));
+    }
 
-            // Physically save to disk
-            let validation_status = if fs::write(&file_path, &file_data).is_ok() {
-                report::ValidationStatus::Valid
-            } else {
-                report::ValidationStatus::Invalid
-            };
-            
-            recovered_files.push(report::RecoveredFile {
-                id: file_id,
-                filename: filename.clone(),
-                file_type,
-                confidence: stream.confidence as f64,
-                links: Vec::new(),
-                size_kb: (total_size_bytes / 1024) as u64,
-                sha256,
-                start_offset: stream.fragments.first().unwrap().offset,
-                end_offset: stream.fragments.last().unwrap().offset + stream.fragments.last().unwrap().size as u64,
-                validation_status,
-                recovery_time: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    let checkpoint = load_checkpoint(&path)?;
+    let validation = validate_resume(&args.image, &checkpoint)?;
+    if !validation.is_valid {
+        return Err(RecoveryError::Config(format!(
+            "Cannot resume from {}: {}",
+            path.display(),
+            validation.reason.unwrap_or_default()
+        )));
+    }
+
+    let state = ScanState::from_checkpoint(&checkpoint)?;
+    if state.reverse != args.reverse {
+        return Err(RecoveryError::Config(format!(
+            "Cannot resume from {}: checkpoint was written by a {} scan",
+            path.display(),
+            if state.reverse { "reverse" } else { "forward" }
+        )));
+    }
+
+    Ok((checkpoint.position, state))
+}
+
+/// List files recoverable from exFAT/NTFS metadata before the carve starts
+/// (shown in the TUI and written to `metadata_files.json`); with
+/// `--reconstruct-deleted` also save the reconstructed deleted exFAT files
+fn run_metadata_pass(
+    disk: &DiskImage,
+    output_dir: &Path,
+    args: &Args,
+    tui_sender: Option<&mpsc::UnboundedSender<TuiEvent>>,
+    audit_log: &AuditLog,
+) {
+    let log = |message: String| info!("{}", message);
+
+    // The filesystem parsers work on the whole mapped image
+    let Some(mmap) = disk.get_mmap() else {
+        log("Metadata-first pass skipped: only raw image files are memory mapped".to_string());
+        return;
+    };
+
+    // The selected partition, else the first one with a supported filesystem
+    let table = read_partition_table(disk);
+    let partition = table.as_ref().and_then(|table| match args.partition {
+        Some(number) => table.partition(number),
+        None => table.partitions.iter().find(|partition| partition.filesystem.is_some()),
+    });
+
+    let started = std::time::Instant::now();
+    let pass = match partition {
+        Some(partition) => scan_partition(&mmap, partition, args.reconstruct_deleted),
+        None if args.partition.is_some() => None,
+        None => scan_metadata(&mmap, args.reconstruct_deleted),
+    };
+    let Some(mut pass) = pass else {
+        log(match partition {
+            Some(partition) => format!("Metadata-first pass: no exFAT/NTFS/FAT32 volume in partition #{}", partition.number),
+            None => "Metadata-first pass: no exFAT/NTFS/FAT32 volume found".to_string(),
+        });
+        return;
+    };
+    if args.reconstruct_deleted {
+        save_reconstructed_files(&mmap, &mut pass, output_dir, args.max_files_per_dir, &args.filename_policy(), audit_log);
+    }
+
+    if let Some(sender) = tui_sender {
+        for file in &pass.files {
+            let _ = sender.send(TuiEvent::MetadataFileFound {
+                filename: file.filename.clone(),
+                size: file.size,
+                data_offset: file.data_offset,
             });
+        }
+    }
 
-            if let Some(sender) = tui_sender {
-                let _ = sender.send(TuiEvent::FileRecovered { filename: filename.clone() });
-                let _ = sender.send(TuiEvent::LogMessage {
-                    message: format!("Saved recovered file: {} ({} KB)", filename, total_size_bytes / 1024),
-                });
+    let listing_path = output_dir.join("metadata_files.json");
+    if let Ok(json) = serde_json::to_string_pretty(&pass) {
+        let _ = fs::write(&listing_path, json);
+    }
+
+    log(format!(
+        "Metadata-first pass ({}{}): {} files ({:.1} MB) from {:.1} MB of metadata in {:.1}s, listed in {}",
+        pass.source.name(),
+        partition.map(|partition| format!(", partition #{}", partition.number)).unwrap_or_default(),
+        pass.files.len(),
+        pass.file_bytes() as f64 / 1024.0 / 1024.0,
+        pass.metadata_bytes() as f64 / 1024.0 / 1024.0,
+        started.elapsed().as_secs_f64(),
+        listing_path.display()
+    ));
+}
+
+/// MBR/GPT partition table of the image, `None` when it is not partitioned
+fn read_partition_table(disk: &DiskImage) -> Option<PartitionTable> {
+    let image_size = disk.size().as_u64();
+    PartitionTable::read(image_size, |offset, len| {
+        let len = len.min(usize::try_from(image_size.checked_sub(offset)?).unwrap_or(usize::MAX));
+        disk.get_slice(Offset::new(offset), len).ok().map(|slice| slice.data.into_owned())
+    })
+}
+
+/// Partition `number` of `--partition`
+fn selected_partition(table: Option<&PartitionTable>, number: u32) -> Result<Partition> {
+    let Some(table) = table else {
+        return Err(RecoveryError::Config(format!("--partition {}: the image has no MBR/GPT partition table", number)));
+    };
+    match table.partition(number) {
+        Some(partition) => {
+            println!("  Scanning partition {}", partition.describe());
+            Ok(partition.clone())
+        }
+        None => Err(RecoveryError::Config(format!(
+            "--partition {}: no such partition (found: {})",
+            number,
+            table.partitions.iter().map(|partition| partition.number.to_string()).collect::<Vec<_>>().join(", ")
+        ))),
+    }
+}
+
+/// exFAT volume of the selected partition, or the first one in the image
+fn exfat_volume(data: &[u8], partition: Option<&Partition>) -> Option<exfat::ExFatBootParams> {
+    match partition {
+        Some(partition) => exfat::parse_boot_sector_at(data, partition.start),
+        None => exfat::find_boot_sector(data),
+    }
+}
+
+/// Times of the files of the exFAT volume by their first data byte, for
+/// the recovered files starting there (`--enable-exfat`)
+fn exfat_entry_times(disk: &DiskImage, args: &Args) -> std::collections::HashMap<u64, FileTimestamps> {
+    let Some(mmap) = disk.get_mmap() else {
+        return Default::default();
+    };
+    let table = read_partition_table(disk);
+    let partition = args.partition.and_then(|number| table.as_ref()?.partition(number));
+    exfat_volume(&mmap, partition).map(|params| exfat::entry_times(&mmap, &params)).unwrap_or_default()
+}
+
+/// Free clusters of the exFAT volume for `--unallocated-only` (None = scan
+/// the whole image or `--partition`)
+fn unallocated_ranges(disk: &DiskImage, partition: Option<&Partition>) -> Option<Vec<(u64, u64)>> {
+    let scope = if partition.is_some() { "partition" } else { "image" };
+    let Some(mmap) = disk.get_mmap() else {
+        warn!("--unallocated-only: the image is not memory mapped (--stream, device or container image), scanning the whole {}", scope);
+        return None;
+    };
+    let Some(params) = exfat_volume(&mmap, partition) else {
+        warn!("--unallocated-only: no exFAT volume found, scanning the whole {}", scope);
+        return None;
+    };
+    let Some(bitmap) = exfat::read_allocation_bitmap(&mmap, &params) else {
+        warn!("--unallocated-only: exFAT Allocation Bitmap not found, scanning the whole {}", scope);
+        return None;
+    };
+    let ranges = bitmap.unallocated_ranges(&params, mmap.len() as u64);
+    let free_bytes: u64 = ranges.iter().map(|(start, end)| end - start).sum();
+    println!(
+        "  Unallocated only: {:.1} MB in {} free runs ({} of {} clusters allocated)",
+        free_bytes as f64 / 1024.0 / 1024.0,
+        ranges.len(),
+        bitmap.allocated_clusters(),
+        params.cluster_count
+    );
+    Some(ranges)
+}
+
+/// Ranges of the deleted exFAT files of a recovered or carved type, most
+/// recently modified first; their chunks are scanned first (`--enable-exfat`)
+fn exfat_hint_ranges(disk: &DiskImage, partition: Option<&Partition>, carve_types: &[String]) -> Option<Vec<(u64, u64)>> {
+    let Some(mmap) = disk.get_mmap() else {
+        warn!("--enable-exfat: the image is not memory mapped (--stream, device or container image), no directory hints");
+        return None;
+    };
+    let Some(params) = exfat_volume(&mmap, partition) else {
+        warn!("--enable-exfat: no exFAT volume found, no directory hints");
+        return None;
+    };
+    let file_types = FileTypeRegistry::builtin();
+    let hints = directory_hints(&mmap, &params, |extension| {
+        file_types.handler(extension).is_some() || carve_types.iter().any(|t| t == extension)
+    });
+    let hinted_bytes: u64 = hints.iter().map(|hint| hint.end - hint.start).sum();
+    println!(
+        "  exFAT hints: {} deleted files ({:.1} MB) scanned first",
+        hints.len(),
+        hinted_bytes as f64 / 1024.0 / 1024.0
+    );
+    (!hints.is_empty()).then(|| hints.iter().map(|hint| (hint.start, hint.end)).collect())
+}
+
+/// Save the reconstructed deleted files of the metadata pass to
+/// `reconstructed/` and record where each went
+fn save_reconstructed_files(
+    data: &[u8],
+    pass: &mut MetadataPass,
+    output_dir: &Path,
+    max_files_per_dir: usize,
+    policy: &FilenamePolicy,
+    audit_log: &AuditLog,
+) {
+    let mut writer = RecoveryWriter::new(&output_dir.join(RECONSTRUCTED_DIR)).with_max_files_per_dir(Some(max_files_per_dir));
+    let mut saved = 0;
+    for file in &mut pass.files {
+        let Some((start, end)) = file.reconstructed_range() else {
+            continue;
+        };
+        let Some(content) = data.get(start as usize..end as usize) else {
+            continue;
+        };
+        let name = Path::new(&file.filename);
+        let stem = name.file_stem().map(|stem| stem.to_string_lossy());
+        let extension = name
+            .extension()
+            .map(|ext| ext.to_string_lossy().replace(|c: char| !c.is_ascii_alphanumeric(), ""))
+            .filter(|ext| !ext.is_empty())
+            .unwrap_or_else(|| "bin".to_string());
+        match writer.write(&policy.file_name(saved + 1, stem.as_deref(), &extension), content) {
+            Ok(written) => {
+                let saved_as = format!("{}/{}", RECONSTRUCTED_DIR, written.filename);
+                audit(audit_log, AuditAction::FileWritten, serde_json::json!({
+                    "path": saved_as,
+                    "size_bytes": content.len(),
+                    "sha256": rust_recovery::matcher::sha256_hash(content),
+                    "md5": rust_recovery::matcher::md5_hash(content),
+                    "source": file.filename,
+                }));
+                if !file.times.is_empty() {
+                    if let Err(e) = file.times.apply(&writer.dir().join(&written.filename)) {
+                        warn!("Failed to set the times of {}: {}", saved_as, e);
+                    }
+                }
+                file.saved_as = Some(saved_as);
+                saved += 1;
+            }
+            Err(e) => warn!("Failed to save reconstructed {}: {}", file.filename, e),
+        }
+    }
+    info!("Reconstructed {} deleted exFAT files into {}", saved, writer.dir().display());
+}
+
+/// `--output s3://...`: the bucket location and its client; `args.output`
+/// becomes the staging directory the run is written to
+fn remote_output(args: &mut Args) -> Result<Option<(S3Location, S3Client)>> {
+    let Some(url) = args.s3_output_url() else {
+        return Ok(None);
+    };
+    let location = S3Location::parse(&url).map_err(RecoveryError::InvalidArgument)?;
+    let client = S3Client::new(args.s3_endpoint.as_deref(), args.s3_region.as_deref())?;
+    // Write-only keys may not see the bucket: only a warning before a long scan
+    if let Err(e) = client.check_bucket(&location.bucket) {
+        warn!("Bucket check failed, the upload after the scan may fail too: {}", e);
+    }
+    println!("Writing to {}, uploaded to {} after the reports", args.s3_staging.display(), location.url());
+    args.output = args.s3_staging.clone();
+    Ok(Some((location, client)))
+}
+
+/// Upload the output directory except the audit log; recovered files are
+/// removed from the staging directory once uploaded unless `keep_local`.
+/// Returns the objects and bytes uploaded
+fn upload_output(
+    client: &S3Client,
+    location: &S3Location,
+    output_dir: &Path,
+    keep_local: bool,
+    audit_log: &AuditLog,
+) -> Result<(usize, u64)> {
+    let files = s3::relative_files(output_dir)?;
+    let mut uploaded = (0, 0);
+    for relative in files.iter().filter(|path| **path != Path::new(AUDIT_LOG_FILE) && **path != Path::new(PIPE_SPILL_FILE)) {
+        let path = output_dir.join(relative);
+        let object = client.put_file(&location.bucket, &location.key(relative), &path)?;
+        let mut details = serde_json::to_value(&object).unwrap_or_default();
+        details["path"] = serde_json::json!(relative.to_string_lossy());
+        audit(audit_log, AuditAction::ObjectUploaded, details);
+        uploaded = (uploaded.0 + 1, uploaded.1 + object.size_bytes);
+        if !keep_local && relative.starts_with(RECOVERED_FILES_DIR) {
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Cannot remove uploaded {}: {}", path.display(), e);
             }
         }
     }
+    Ok(uploaded)
+}
+
+/// Append an entry to the audit log; a failed write is logged, not fatal
+fn audit(audit_log: &AuditLog, action: AuditAction, details: serde_json::Value) {
+    if let Err(e) = audit_log.record(action, details) {
+        warn!("Audit log write failed: {}", e);
+    }
+}
+
+/// Attach the GPU needle prefilter (`--gpu`); the CPU finder stays the fallback
+#[cfg(feature = "gpu")]
+fn with_gpu_prefilter(matcher: EnhancedMatcher) -> EnhancedMatcher {
+    match rust_recovery::gpu::GpuPrefilter::for_profile(matcher.profile()) {
+        Ok(Some(gpu)) => {
+            println!("  GPU prefilter: {} needles on OpenCL", gpu.table().needles.len());
+            matcher.with_gpu(Arc::new(gpu))
+        }
+        Ok(None) => {
+            warn!("--gpu: no OpenCL GPU found, using the CPU");
+            matcher
+        }
+        Err(e) => {
+            warn!("--gpu: {}, using the CPU", e);
+            matcher
+        }
+    }
+}
+
+#[cfg(not(feature = "gpu"))]
+fn with_gpu_prefilter(matcher: EnhancedMatcher) -> EnhancedMatcher {
+    warn!("--gpu: GPU prefilter not built (cargo build --features gpu), using the CPU");
+    matcher
+}
+
+/// Scanner settings for `session.yaml`
+fn scan_settings(scan_config: &ScanConfig) -> serde_json::Value {
+    serde_json::json!({
+        "chunk_size": scan_config.chunk_size,
+        "overlap_size": scan_config.overlap_size,
+        "threads": rayon::current_num_threads(),
+        "reverse": scan_config.reverse,
+        "nvme_optimization": scan_config.nvme_optimization,
+        "io_uring": cfg!(all(target_os = "linux", feature = "io-uring")),
+        "streaming": scan_config.streaming,
+        "prioritize": scan_config.prioritize,
+        "stall_timeout_secs": scan_config.stall_timeout.map(|timeout| timeout.as_secs()),
+        "max_read_bytes_per_sec": scan_config.max_read_bytes_per_sec,
+        "io_priority": scan_config.io_priority.map(|priority| priority.name()),
+        "target_script": scan_config.target_script.name(),
+        "prefetch": scan_config.prefetch.map(|prefetch| serde_json::json!({
+            "distance_bytes": prefetch.distance_bytes,
+            "depth": prefetch.depth,
+            "timeout_secs": prefetch.timeout.as_secs(),
+            "retries": prefetch.retries,
+        })),
+        "fragment_cache": scan_config.fragment_cache.as_ref().map(|cache| serde_json::json!({
+            "memory_bytes": cache.budget().0,
+            "spill_bytes": cache.budget().1,
+        })),
+        "carve_types": scan_config.carve_types,
+        "detectors": scan_config.detectors,
+        "artifacts": scan_config.artifacts,
+        "keywords": scan_config.keywords.as_ref().map(|filter| filter.keywords()),
+        "keyword_only": scan_config.keywords.as_ref().is_some_and(|filter| filter.restrict()),
+        "keyword_profile": scan_config.keyword_profile.as_ref().map(|profile| profile.spec()),
+        "min_chunk_size": scan_config.min_chunk_size,
+        "resume_from": scan_config.resume_from.map(|offset| offset.as_u64()),
+        "scan_ranges": scan_config.scan_ranges.as_ref().map(|ranges| ranges.len()),
+    })
+}
 
-    Ok((total_bytes_scanned, candidates_count, recovered_files, clusters))
+/// Parameters that influence recovered bytes (recorded in the report for re-derivation)
+fn provenance_params(args: &Args, scan_config: &ScanConfig, matcher: &EnhancedMatcher) -> ProvenanceParams {
+    let mut params = ProvenanceParams::new();
+    params.insert("chunk_size".to_string(), scan_config.chunk_size.to_string());
+    params.insert("overlap_size".to_string(), scan_config.overlap_size.to_string());
+    if let Some(min_chunk_size) = scan_config.min_chunk_size {
+        params.insert("min_chunk_size".to_string(), min_chunk_size.to_string());
+    }
+    params.insert("reverse".to_string(), scan_config.reverse.to_string());
+    params.insert("min_confidence".to_string(), scan_config.min_confidence.to_string());
+    params.insert("target_size_min_kb".to_string(), args.target_size_min.to_string());
+    params.insert("target_size_max_kb".to_string(), args.target_size_max.to_string());
+    if let Some(ref keywords) = scan_config.keywords {
+        params.insert("keywords".to_string(), keywords.keywords().join("\n"));
+        params.insert("keyword_only".to_string(), keywords.restrict().to_string());
+    }
+    if let Some(ref profile) = scan_config.keyword_profile {
+        params.insert("keyword_profile".to_string(), serde_json::to_string(profile.spec()).unwrap_or_default());
+    }
+    // Recorded only when text files are rewritten, so older reports verify unchanged
+    if args.utf8_mode() != Utf8Mode::Keep {
+        params.insert("utf8_mode".to_string(), args.utf8_mode().name().to_string());
+    }
+    // Likewise only when files may have holes (`verify` leaves them out otherwise)
+    if args.gap_fill() != GapFill::None {
+        params.insert("gap_fill".to_string(), args.gap_fill().name().to_string());
+    }
+    // Files of older reports were written untrimmed; `verify` rebuilds them that way
+    params.insert("trim_boundaries".to_string(), "true".to_string());
+    if args.tunes_solver() {
+        let weights = serde_json::to_string(&args.stream_weights()).unwrap_or_default();
+        params.insert("stream_weights".to_string(), weights);
+    }
+    if args.patterns.is_some() {
+        let profile = matcher.profile();
+        params.insert("pattern_profile".to_string(), format!("{}:{}", profile.name, profile.hash));
+    }
+    params
 }
 
 /// Test basic disk access
@@ -426,7 +2335,7 @@ fn test_disk_access(disk: &DiskImage) -> Result<()> {
 
 /// Print configuration information
 fn print_configuration(args: &Args) {
-    println!("Ultimate File Recovery - Rust Implementation v12.0");
+    println!("Ultimate File Recovery - Rust Implementation v{}", TOOL_VERSION);
     println!("{}", "=".repeat(60));
     println!();
     println!("Configuration:");
@@ -440,15 +2349,108 @@ fn print_configuration(args: &Args) {
         "  Chunk size range:   {} - {} KB",
         args.chunk_min, args.chunk_max
     );
+    match args.patterns {
+        Some(ref path) => println!("  Patterns:           {}", path.display()),
+        None => println!("  Patterns:           built-in (YouTube)"),
+    }
+    if args.tunes_solver() {
+        let weights = args.stream_weights();
+        let source = args.solver_config.as_ref().map(|path| path.display().to_string()).unwrap_or_else(|| "defaults".to_string());
+        println!(
+            "  Stream solver:      {} (max gap {} KB, lookback {}, min edge score {})",
+            source,
+            weights.max_gap / 1024,
+            weights.max_lookback,
+            weights.min_edge_score
+        );
+    }
+    match args.overlap {
+        Some(kb) => println!("  Chunk overlap:      {} KB", kb),
+        None => println!("  Chunk overlap:      auto"),
+    }
     println!("  Reverse scan:       {}", args.reverse);
     println!("  NVMe optimization:  {}", args.nvme);
+    println!("  Streaming reads:    {}", args.stream || args.reads_stdin());
     println!("  Enable exFAT:       {}", args.enable_exfat);
     println!("  Full exFAT recovery: {}", args.full_exfat_recovery);
     println!("  Links only:         {}", args.links_only);
     println!("  Semantic scan:      {}", args.semantic_scan);
+    println!("  Metadata first:     {}", args.metadata_first);
+    if let Some(partition) = args.partition {
+        println!("  Partition:          {}", partition);
+    }
+    if args.reconstruct_deleted {
+        println!("  Reconstruct deleted: true");
+    }
+    println!("  Priority order:     {}", args.priority);
+    println!("  Resume:             {}", args.resume);
+    println!("  Chunk stats:        {}", args.chunk_stats);
+    println!("  Filename translit:  {}", args.filename_translit);
+    println!("  Filename limit:     {} bytes", args.max_filename_bytes);
+    println!("  Keep titles:        {}", args.keep_title);
+    println!("  Invalid UTF-8:      {}", args.utf8_mode().name());
+    if args.gap_fill() != GapFill::None {
+        println!("  Gap fill:           {}", args.gap_fill().name());
+    }
+    if !args.carve.is_empty() {
+        println!("  Carve types:        {}", args.carve_types().join(", "));
+    }
+    if !args.detect.is_empty() {
+        println!("  Detectors:          {}", args.detect_types().join(", "));
+    }
+    if !args.artifacts.is_empty() {
+        println!("  Artifacts:          {}", args.artifact_types().join(", "));
+    }
+    if args.link_context > 0 {
+        println!("  Link context:       {} bytes", args.link_context);
+    }
+    if !args.keyword.is_empty() || args.keyword_file.is_some() {
+        let mut sources = args.keyword.clone();
+        sources.extend(args.keyword_file.iter().map(|path| format!("@{}", path.display())));
+        println!("  Keywords:           {}{}", sources.join(", "), if args.keyword_only { " (only)" } else { "" });
+    }
+    if let Some(ref path) = args.keyword_profile {
+        println!("  Keyword profile:    {}", path.display());
+    }
     println!("  Live dashboard:     {}", !args.no_live);
     if args.early_exit > 0 {
         println!("  Early exit after:   {} files", args.early_exit);
     }
+    if args.verify_hash || args.verify_hash_md5 {
+        println!("  Verify hash:        {}", if args.verify_hash_md5 { "SHA-256, MD5" } else { "SHA-256" });
+    }
+    if args.time_budget_secs > 0 {
+        println!("  Max duration:       {} s", args.time_budget_secs);
+    }
+    if args.max_read_mbps > 0.0 {
+        println!("  Read limit:         {} MB/s", args.max_read_mbps);
+    }
+    if let Some(priority) = args.io_priority() {
+        println!("  I/O priority:       {}", priority.name());
+    }
+    if let Some(mb) = args.fragment_cache_mb.filter(|&mb| mb > 0) {
+        println!("  Fragment cache:     {} MB in memory, {} MB on disk", mb, args.fragment_cache_spill_mb);
+    }
+    if args.fragments_jsonl {
+        println!("  Fragment log:       {}", args.output.join(FRAGMENT_LOG_FILE).display());
+    }
+    if let Some(ref command) = args.post_process_cmd {
+        println!(
+            "  Post-process:       {}{} ({})",
+            command,
+            if args.pipe_output { " (file on stdin)" } else { "" },
+            args.output.join(POST_PROCESS_LOG_FILE).display()
+        );
+    }
+    if args.max_files_per_dir > 0 {
+        println!("  Files per dir:      {}", args.max_files_per_dir);
+    }
+    if args.flush_interval_secs > 0 {
+        println!(
+            "  Flush interval:     {} s ({})",
+            args.flush_interval_secs,
+            args.output.join(RECOVERED_LOG_FILE).display()
+        );
+    }
     println!();
 }
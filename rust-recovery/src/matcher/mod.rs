@@ -1,14 +1,21 @@
-pub mod patterns;
-pub mod validator;
+pub use recovery_core::matcher::{cache, context, patterns, profile, validator};
+pub mod script;
+pub mod trace;
 
-use crate::matcher::patterns::{YOUTUBE_PATTERNS, TITLE_PATTERNS};
-use crate::matcher::validator::{is_valid_video_id, is_valid_json, is_probably_json, is_valid_youtube_url, is_probably_youtube_url};
-use crate::types::{EnrichedLink, FragmentScore, ValidationResult};
+pub use profile::{PatternProfile, PatternSpec, ProfileSpec, ValidationHook};
+pub use script::{Script, ScriptDensity};
+
+use crate::matcher::patterns::{max_match_len, TITLE_PATTERNS};
+use crate::matcher::profile::ProfilePattern;
+use crate::matcher::trace::{quote, MatcherTrace};
+use crate::matcher::validator::{is_valid_json, is_probably_json, is_valid_youtube_url, is_probably_youtube_url};
+use crate::scanner::KeywordRelevance;
+use crate::types::{EnrichedLink, FragmentScore, Offset, Size, ValidationResult};
 use crate::entropy::{calculate_shannon_entropy, is_compressed_like, is_structured_text, get_entropy_category};
 use ahash::AHashSet;
-use regex::bytes::Regex;
-use regex::bytes::RegexSet;
-use regex::bytes::RegexSetBuilder;
+use aho_corasick::AhoCorasick;
+use regex::bytes::Captures;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use html_escape::decode_html_entities;
 
@@ -26,6 +33,11 @@ pub fn sha256_hash(data: &[u8]) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+/// MD5 hex digest (body files and tools that index by MD5)
+pub fn md5_hash(data: &[u8]) -> String {
+    format!("{:x}", md5::Md5::digest(data))
+}
+
 /// Fast Cyrillic detection - checks for UTF-8 Cyrillic byte sequences (0xD0, 0xD1)
 /// SIMD-optimized: processes 32 bytes at a time when possible
 #[inline]
@@ -87,32 +99,10 @@ pub fn count_json_markers_fast(data: &[u8]) -> usize {
 }
 
 /// Calculate Cyrillic character density in data
-/// Returns a value between 0.0 and 1.0
+/// Returns a value between 0.0 and 1.0 (see `ScriptDensity` for other scripts)
 #[inline]
 pub fn cyrillic_density(data: &[u8]) -> f32 {
-    if data.is_empty() {
-        return 0.0;
-    }
-    
-    // Count UTF-8 Cyrillic character sequences
-    // Cyrillic letters in UTF-8 are 2-byte sequences: 0xD0 0x80-0xBF or 0xD1 0x80-0xBF
-    let mut cyrillic_chars = 0usize;
-    let mut i = 0;
-    
-    while i < data.len().saturating_sub(1) {
-        let b = data[i];
-        let next = data[i + 1];
-        
-        if (b == 0xD0 || b == 0xD1) && (0x80..=0xBF).contains(&next) {
-            cyrillic_chars += 1;
-            i += 2; // Skip both bytes of the character
-        } else {
-            i += 1;
-        }
-    }
-    
-    // Return density as ratio of Cyrillic chars to total possible chars
-    cyrillic_chars as f32 / (data.len() / 2).max(1) as f32
+    ScriptDensity::measure(data).cyrillic
 }
 
 /// Calculate YouTube link density (links per megabyte)
@@ -128,7 +118,7 @@ pub fn calculate_link_density(youtube_count: usize, data_size: usize) -> f32 {
 /// Calculate target score for a fragment (legacy function, kept for compatibility)
 pub fn calculate_target_score(
     youtube_count: usize,
-    cyrillic_density: f32,
+    script_density: f32,
     has_json_markers: bool,
     size: usize,
 ) -> f32 {
@@ -138,8 +128,8 @@ pub fn calculate_target_score(
     let link_density = calculate_link_density(youtube_count, size);
     score += link_density.min(100.0) * 0.4; // Max 40 points
     
-    // Cyrillic density (main factor for TXT)
-    score += cyrillic_density * 30.0; // Max 30 points
+    // Density of the target script (main factor for TXT)
+    score += script_density * 30.0; // Max 30 points
     
     // JSON structure markers
     if has_json_markers {
@@ -157,18 +147,21 @@ pub fn calculate_target_score(
     score
 }
 
-/// Enhanced fragment scoring with validation and entropy analysis
+/// Enhanced fragment scoring with validation and entropy analysis;
+/// text in the `target` script and keyword profile hits count towards the score
 pub fn calculate_fragment_score(
     data: &[u8],
     youtube_count: usize,
-    cyrillic_density: f32,
+    scripts: &ScriptDensity,
+    target: Script,
     json_markers: usize,
+    relevance: Option<&KeywordRelevance>,
 ) -> FragmentScore {
     let mut score = 0.0;
     let mut reasons = Vec::new();
     
     // Base target score
-    let base_score = calculate_target_score(youtube_count, cyrillic_density, json_markers > 0, data.len());
+    let base_score = calculate_target_score(youtube_count, scripts.of(target), json_markers > 0, data.len());
     score += base_score * 0.6; // 60% weight for base factors
     
     // Entropy analysis
@@ -230,6 +223,13 @@ pub fn calculate_fragment_score(
         reasons.push("target_size".to_string());
     }
     
+    // Case-relevant terms (--keyword-profile)
+    let keyword_relevance = relevance.map_or(0.0, |relevance| relevance.points);
+    if let Some(relevance) = relevance.filter(|relevance| !relevance.hits.is_empty()) {
+        score += relevance.points;
+        reasons.push(format!("relevance {} ({:+.1})", relevance.summary(), relevance.points));
+    }
+    
     // Ensure score doesn't go negative
     score = score.max(0.0);
     
@@ -242,6 +242,8 @@ pub fn calculate_fragment_score(
         has_structured_text: is_text_structured,
         is_compressed,
         reasons,
+        scripts: *scripts,
+        keyword_relevance,
     }
 }
 
@@ -317,155 +319,263 @@ pub fn validate_data_chunk(data: &[u8]) -> ValidationResult {
     result
 }
 
-/// Optimized pattern matcher with pre-compiled regex
-/// Clone is cheap because RegexSet is wrapped in Arc
+/// Bytes on each side of a link searched for its title
+const TITLE_CONTEXT_WINDOW: usize = 1000;
+/// Largest `--link-context`
+pub const MAX_LINK_CONTEXT: usize = 4096;
+
+/// Captured context as text. The side away from the link is cut to whole
+/// UTF-8 characters (the start of the bytes `before` it, else their end);
+/// invalid sequences are replaced and control characters become spaces
+fn context_text(bytes: &[u8], before: bool) -> String {
+    let is_continuation = |b: &u8| b & 0xC0 == 0x80;
+    let bytes = if before {
+        &bytes[bytes.iter().take(3).take_while(|b| is_continuation(b)).count()..]
+    } else {
+        let tail = bytes.len().saturating_sub(3);
+        let cut = (tail..bytes.len()).rev().find(|&i| !is_continuation(&bytes[i])).filter(|&lead| {
+            let len = match bytes[lead] {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            };
+            lead + len > bytes.len()
+        });
+        &bytes[..cut.unwrap_or(bytes.len())]
+    };
+    String::from_utf8_lossy(bytes).chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
+
+/// Byte range a single result can depend on, by the stage that reads it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtifactSpan {
+    pub source: &'static str,
+    pub bytes: usize,
+}
+
+/// Optimized pattern matcher over a `PatternProfile`
+/// Clone is cheap because the compiled profile is wrapped in Arc
 #[derive(Clone)]
 pub struct EnhancedMatcher {
-    /// Regex for fast needle search (to avoid scanning full chunk with RegexSet)
-    finder_regex: Regex,
+    /// Patterns, pre-filter set and needle regex (shared)
+    profile: Arc<PatternProfile>,
     
-    /// RegexSet for fast pre-filtering (Arc for cheap cloning)
-    pattern_set: Arc<RegexSet>,
-    
-    /// For thread-local deduplication
-    seen_ids: AHashSet<[u8; 11]>,
+    /// Values already reported and their image offsets (thread-local): the
+    /// same occurrence found by several needle windows or patterns is kept once
+    seen_ids: AHashSet<(Vec<u8>, u64)>,
+
+    /// Decision trace for one image range (`--trace-matcher`, shared)
+    trace: Option<Arc<MatcherTrace>>,
+
+    /// Bytes of text kept on each side of a link (`--link-context`, 0 = none)
+    context_bytes: usize,
+
+    /// Needle search on the GPU (`--gpu`, shared)
+    #[cfg(feature = "gpu")]
+    gpu: Option<Arc<crate::gpu::GpuPrefilter>>,
 }
 
 // Safety: EnhancedMatcher is Sync because:
-// 1. Regex and Arc<RegexSet> are Sync.
+// 1. Arc<PatternProfile> is Sync (compiled regexes and plain data).
 // 2. seen_ids (AHashSet) is used ONLY in scan_chunk which takes &mut self.
 // 3. clone_fresh takes &self but does not access seen_ids (creates new empty one).
 // Therefore sharing &EnhancedMatcher across threads is safe.
 unsafe impl Sync for EnhancedMatcher {}
 
 impl EnhancedMatcher {
-    /// Create a new matcher (compiles regex - call once, then clone)
+    /// Create a matcher for the built-in YouTube profile (compiled once, see `cache`)
     pub fn new() -> Self {
-        // Create RegexSet from all patterns
-        let pattern_strings: Vec<_> = YOUTUBE_PATTERNS
-            .iter()
-            .map(|p| p.regex.as_str())
-            .collect();
-        
-        let pattern_set = Arc::new(
-            RegexSetBuilder::new(&pattern_strings)
-                .size_limit(50 * 1024 * 1024)  // 50 MB
-                .build()
-                .expect("Failed to compile pattern set")
-        );
+        Self::with_profile(PatternProfile::youtube())
+    }
 
-        // Create a fast pre-filter regex for "needles" (common substrings)
-        // This is much faster than running the full RegexSet on every byte
-        // Added video-id (hyphen) to catch data-video-id attributes
-        let finder_regex = Regex::new(r"(?i)(?:youtube\.com|youtu\.be|video_id|video-id|v=|/v/|embed/|shorts/)").expect("Failed to compile finder regex");
-        
+    /// Create a matcher for a custom profile (see `profile`)
+    pub fn with_profile(profile: Arc<PatternProfile>) -> Self {
         Self {
-            finder_regex,
-            pattern_set,
+            profile,
             seen_ids: AHashSet::new(),
+            trace: None,
+            context_bytes: 0,
+            #[cfg(feature = "gpu")]
+            gpu: None,
         }
     }
+
+    /// Search needles on the GPU for chunks large enough (see `gpu`)
+    #[cfg(feature = "gpu")]
+    pub fn with_gpu(mut self, gpu: Arc<crate::gpu::GpuPrefilter>) -> Self {
+        self.gpu = Some(gpu);
+        self
+    }
+
+    /// Log the decisions taken in the trace's range (see `trace`)
+    pub fn with_trace(mut self, trace: Arc<MatcherTrace>) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Keep up to `bytes` of the text before and after each link
+    pub fn with_context(mut self, bytes: usize) -> Self {
+        self.context_bytes = bytes;
+        self
+    }
+
+    pub fn trace(&self) -> Option<&Arc<MatcherTrace>> {
+        self.trace.as_ref()
+    }
+
+    /// Profile this matcher extracts
+    pub fn profile(&self) -> &PatternProfile {
+        &self.profile
+    }
     
     /// Clone matcher with fresh deduplication cache (cheap - only clones Arc pointer)
     pub fn clone_fresh(&self) -> Self {
         Self {
-            finder_regex: self.finder_regex.clone(),
-            pattern_set: Arc::clone(&self.pattern_set),
-            seen_ids: AHashSet::new(),
+            trace: self.trace.clone(),
+            context_bytes: self.context_bytes,
+            #[cfg(feature = "gpu")]
+            gpu: self.gpu.clone(),
+            ..Self::with_profile(Arc::clone(&self.profile))
         }
     }
+
+    /// Needle (start, end) offsets in `data`, from the GPU when it takes the chunk
+    fn needle_hits(&self, finder: &AhoCorasick, data: &[u8]) -> Vec<(usize, usize)> {
+        #[cfg(feature = "gpu")]
+        if let Some(gpu) = &self.gpu {
+            if let Ok(Some(hits)) = gpu.find(data) {
+                return hits;
+            }
+        }
+        finder.find_iter(data).map(|m| (m.start(), m.end())).collect()
+    }
     
+    /// Spans of everything one link depends on: the regex window around the
+    /// needle, the structural context scored around the URL and the title search.
+    ///
+    /// A chunk boundary inside any of these changes the result, so chunks must
+    /// overlap by at least the largest one (see `scanner::overlap`).
+    pub fn artifact_spans(&self) -> Vec<ArtifactSpan> {
+        let profile = &self.profile;
+        let needle = profile.needles.iter().map(Vec::len).max().unwrap_or(0);
+        let window = profile.lookbehind + needle + profile.lookahead;
+        // Unbounded patterns are cut off by the window
+        let longest_url = profile
+            .patterns
+            .iter()
+            .map(|p| max_match_len(p.regex.as_str()).unwrap_or(window).min(window))
+            .max()
+            .unwrap_or(window);
+
+        let mut spans = vec![
+            ArtifactSpan { source: "link_window", bytes: window },
+            ArtifactSpan {
+                source: "link_context",
+                bytes: context::REACH_BEFORE + longest_url + context::REACH_AFTER,
+            },
+        ];
+        if profile.extract_titles {
+            spans.push(ArtifactSpan { source: "title_context", bytes: 2 * TITLE_CONTEXT_WINDOW });
+        }
+        if self.context_bytes > 0 {
+            spans.push(ArtifactSpan { source: "captured_context", bytes: 2 * self.context_bytes + longest_url });
+        }
+        spans
+    }
+
+    /// Largest entry of `artifact_spans`
+    pub fn max_artifact_span(&self) -> ArtifactSpan {
+        self.artifact_spans()
+            .into_iter()
+            .max_by_key(|span| span.bytes)
+            .unwrap_or(ArtifactSpan { source: "link_window", bytes: 0 })
+    }
+
     /// Scan data chunk with context using needle optimization
     pub fn scan_chunk(
         &mut self,
         data: &[u8],
-        base_offset: usize,
+        base_offset: Offset,
         deduplicate: bool,
+    ) -> Vec<EnrichedLink> {
+        self.scan_chunk_cancellable(data, base_offset, deduplicate, &AtomicBool::new(false))
+    }
+
+    /// `scan_chunk` that stops early once `cancel` is set (results are then partial)
+    pub fn scan_chunk_cancellable(
+        &mut self,
+        data: &[u8],
+        base_offset: Offset,
+        deduplicate: bool,
+        cancel: &AtomicBool,
     ) -> Vec<EnrichedLink> {
         let mut results = Vec::new();
+        let profile = Arc::clone(&self.profile);
+        let chunk = base_offset.as_u64();
+        let trace = self.trace.clone().filter(|trace| trace.overlaps(chunk, data.len()));
         
         // LIMITATION: Simple needle search might miss some obscure patterns.
-        // But for "youtube" and "video_id", it catches 99%.
+        // For the YouTube profile "youtube" and "video_id" catch 99%,
         // "v=" is added to catch parameter-only patterns.
         
         // Iterate over needle matches
-        for m in self.finder_regex.find_iter(data) {
-            let start = m.start();
-            let end = m.end();
-            
-            // Define context window around the match
-            // We need enough context before (for URL start) and after (for Video ID)
-            // URL can be long, so let's take e.g. 100 bytes before and 50 after
-            let window_start = start.saturating_sub(100);
-            let window_end = (end + 50).min(data.len());
-            
-            let window_data = &data[window_start..window_end];
-            
-            // Run RegexSet on this small window
-            let matches = self.pattern_set.matches(window_data);
-            if !matches.matched_any() {
-                continue;
-            }
-            
-            // Extract from window
-            for idx in matches.iter() {
-                let pattern = &YOUTUBE_PATTERNS[idx];
+        if let Some(finder) = &profile.finder {
+            for (needle_start, needle_end) in self.needle_hits(finder, data) {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // Define context window around the match
+                // We need enough context before (for URL start) and after (for the value)
+                let window_start = needle_start.saturating_sub(profile.lookbehind);
+                let window_end = (needle_end + profile.lookahead).min(data.len());
+                let window_data = &data[window_start..window_end];
                 
-                for cap in pattern.regex.captures_iter(window_data) {
-                     // Extract video ID
-                    let video_id_bytes = match cap.get(1) {
-                        Some(m) => m.as_bytes(),
-                        None => continue,
-                    };
-                    
-                    // Validate
-                    if !is_valid_video_id(video_id_bytes) {
+                // Run RegexSet on this small window
+                let matches = profile.pattern_set.matches(window_data);
+                let needle_at = chunk + needle_start as u64;
+                if let Some(trace) = trace.as_deref().filter(|trace| trace.contains(needle_at)) {
+                    let names: Vec<&str> = matches.iter().map(|idx| profile.patterns[idx].name.as_str()).collect();
+                    trace.event(needle_at, "needle", format_args!("chunk=0x{:08X} {}", chunk, quote(&data[needle_start..needle_end])));
+                    trace.event(
+                        chunk + window_start as u64,
+                        "window",
+                        format_args!(
+                            "chunk=0x{:08X} len={} patterns={}",
+                            chunk,
+                            window_data.len(),
+                            if names.is_empty() { "none".to_string() } else { names.join(",") }
+                        ),
+                    );
+                }
+                if !matches.matched_any() {
+                    continue;
+                }
+                
+                // Extract from window
+                for idx in matches.iter() {
+                    let pattern = &profile.patterns[idx];
+                    if !pattern.anchored {
                         continue;
                     }
-                    
-                    // Deduplicate
-                    if deduplicate {
-                        let mut id_array = [0u8; 11];
-                        id_array.copy_from_slice(video_id_bytes);
-                        
-                        if !self.seen_ids.insert(id_array) {
-                            continue; // Already seen
+                    for cap in pattern.regex.captures_iter(window_data) {
+                        let trace = trace.as_deref();
+                        if let Some(link) = self.build_link(&profile, pattern, &cap, data, window_start, base_offset, deduplicate, trace) {
+                            results.push(link);
                         }
                     }
-                    
-                    // Extract full URL
-                    let full_match = cap.get(0).unwrap();
-                    let url_bytes = full_match.as_bytes();
-                    
-                    // Safe UTF-8 conversion
-                    let url = String::from_utf8_lossy(url_bytes).into_owned();
-                    let video_id = String::from_utf8_lossy(video_id_bytes).into_owned();
-                    
-                    // Calculate absolute offset
-                    // window_start is offset into 'data'
-                    // full_match.start() is offset into 'window_data'
-                    let abs_offset = base_offset + window_start + full_match.start();
-                    
-                    // Confidence
-                    let confidence = (pattern.priority as f32) / 10.0;
-                    
-                    let mut link = EnrichedLink::new(
-                        url,
-                        video_id,
-                        abs_offset as u64,
-                        pattern.name.to_string(),
-                        confidence,
-                    );
-                    
-                    // Extract title from context (using larger context from original data if needed)
-                    // We can use 'data' directly since we have the index
-                    let match_pos = window_start + full_match.start();
-                    link.title = self.extract_title_from_context(
-                        data,
-                        match_pos,
-                        1000, 
-                    );
-                    
+                }
+            }
+        }
+
+        // Patterns without needles run over the whole chunk
+        for pattern in profile.patterns.iter().filter(|p| !p.anchored) {
+            for cap in pattern.regex.captures_iter(data) {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                if let Some(link) = self.build_link(&profile, pattern, &cap, data, 0, base_offset, deduplicate, trace.as_deref()) {
                     results.push(link);
                 }
             }
@@ -477,6 +587,113 @@ impl EnhancedMatcher {
         results
     }
     
+    /// Validate, deduplicate and enrich one regex match found in `data[window_start..]`
+    #[allow(clippy::too_many_arguments)]
+    fn build_link(
+        &mut self,
+        profile: &PatternProfile,
+        pattern: &ProfilePattern,
+        cap: &Captures,
+        data: &[u8],
+        window_start: usize,
+        base_offset: Offset,
+        deduplicate: bool,
+        trace: Option<&MatcherTrace>,
+    ) -> Option<EnrichedLink> {
+        let full_match = cap.get(0)?;
+        let traced_at = base_offset.as_u64() + (window_start + full_match.start()) as u64;
+        let trace = trace.filter(|trace| trace.contains(traced_at));
+        let reject = |value: &[u8], reason: &str| {
+            if let Some(trace) = trace {
+                trace.event(traced_at, "reject", format_args!("pattern={} value={} {}", pattern.name, quote(value), reason));
+            }
+        };
+
+        // Extract the value (video ID for the YouTube profile)
+        let Some(value) = cap.get(pattern.capture) else {
+            reject(full_match.as_bytes(), "no_capture");
+            return None;
+        };
+        let value_bytes = value.as_bytes();
+        
+        // Validate
+        if let Some(validate) = pattern.validator {
+            if !validate(value_bytes) {
+                reject(value_bytes, "validator");
+                return None;
+            }
+        }
+        
+        // Deduplicate occurrences; the same value elsewhere is a new link
+        let value_at = base_offset.as_u64() + (window_start + value.start()) as u64;
+        if deduplicate && !self.seen_ids.insert((value_bytes.to_vec(), value_at)) {
+            reject(value_bytes, "duplicate");
+            return None; // Already seen
+        }
+        
+        // Extract full URL
+        let url_bytes = full_match.as_bytes();
+        
+        // Safe UTF-8 conversion
+        let url = String::from_utf8_lossy(url_bytes).into_owned();
+        let video_id = String::from_utf8_lossy(value_bytes).into_owned();
+        
+        // Calculate absolute offset
+        // window_start is offset into 'data'
+        // full_match.start() is offset into the window
+        let match_pos = window_start + full_match.start();
+        let abs_offset = base_offset + Size::from_usize(match_pos);
+        
+        // Confidence: pattern priority adjusted by surrounding structure
+        let context = context::score_link_context(data, match_pos, match_pos + url_bytes.len());
+        let confidence = context.adjust((pattern.priority as f32) / 10.0);
+        
+        let mut link = EnrichedLink::new(
+            url,
+            video_id,
+            abs_offset,
+            pattern.name.clone(),
+            confidence,
+        );
+        
+        link.reasons = context.reasons;
+        if self.context_bytes > 0 {
+            let before = &data[match_pos.saturating_sub(self.context_bytes)..match_pos];
+            let after_start = match_pos + url_bytes.len();
+            let after = &data[after_start..(after_start + self.context_bytes).min(data.len())];
+            link.context_before = Some(context_text(before, true));
+            link.context_after = Some(context_text(after, false));
+        }
+
+        // Extract title from context (using larger context from original data if needed)
+        // We can use 'data' directly since we have the index
+        if profile.extract_titles {
+            link.title = self.extract_title_from_context(
+                data,
+                match_pos,
+                TITLE_CONTEXT_WINDOW,
+            );
+        }
+
+        if let Some(trace) = trace {
+            trace.event(
+                traced_at,
+                "link",
+                format_args!(
+                    "pattern={} value={} url={} confidence={:.2} reasons={} title={}",
+                    pattern.name,
+                    quote(value_bytes),
+                    quote(url_bytes),
+                    link.confidence,
+                    link.reasons.join(","),
+                    link.title.as_deref().map(|title| quote(title.as_bytes())).unwrap_or_else(|| "none".to_string())
+                ),
+            );
+        }
+        
+        Some(link)
+    }
+
     /// Extract title from context
     fn extract_title_from_context(
         &self,
@@ -524,4 +741,67 @@ impl Default for EnhancedMatcher {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(patterns: Vec<PatternSpec>) -> ProfileSpec {
+        ProfileSpec { name: "test".to_string(), extract_titles: false, patterns }
+    }
+
+    fn pattern(name: &str, regex: &str) -> PatternSpec {
+        PatternSpec {
+            name: name.to_string(),
+            regex: regex.to_string(),
+            priority: 5,
+            capture: 0,
+            validator: None,
+            needles: None,
+        }
+    }
+
+    #[test]
+    fn test_custom_profile_scan() {
+        let mut email = pattern("email", r"[\w.+-]+@[\w-]+(?:\.[\w-]+)+");
+        email.validator = Some("email".to_string());
+        email.needles = Some(vec!["@".to_string()]);
+        let mut token = pattern("app_token", r"token=(tok_[A-Za-z0-9]{8})");
+        token.capture = 1;
+        token.priority = 9;
+
+        let profile = Arc::new(PatternProfile::from_spec(&spec(vec![email, token])).unwrap());
+        let mut matcher = EnhancedMatcher::with_profile(profile);
+
+        let data = b"from: a.b@example.org; x@y; token=tok_AbCd1234 again a.b@example.org";
+        let links = matcher.scan_chunk(data, Offset::new(100), true);
+        let values: Vec<&str> = links.iter().map(|l| l.video_id.as_str()).collect();
+        // Repeated values are separate occurrences
+        assert_eq!(values, ["a.b@example.org", "tok_AbCd1234", "a.b@example.org"]);
+        assert_eq!(links[0].offset, Offset::new(106));
+        assert_eq!(links[2].offset, Offset::new(153));
+        assert_eq!(links[1].pattern_name, "app_token");
+        assert!(links.iter().all(|l| l.title.is_none()));
+        assert!(matcher.artifact_spans().iter().all(|s| s.source != "title_context"));
+    }
+
+    #[test]
+    fn test_link_context_capture() {
+        let mut email = pattern("email", r"[\w.+-]+@[\w-]+(?:\.[\w-]+)+");
+        email.needles = Some(vec!["@".to_string()]);
+        let profile = Arc::new(PatternProfile::from_spec(&spec(vec![email])).unwrap());
+        let mut matcher = EnhancedMatcher::with_profile(profile).with_context(5);
+
+        // Five bytes on each side start and end inside a Cyrillic letter
+        let data = "письмо: a.b@example.org —\nответ".as_bytes();
+        let links = matcher.scan_chunk(data, Offset::new(0), true);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].context_before.as_deref(), Some("о: "));
+        assert_eq!(links[0].context_after.as_deref(), Some(" — "));
+        assert!(matcher.artifact_spans().iter().any(|s| s.source == "captured_context"));
+        let fresh = matcher.clone_fresh().scan_chunk(data, Offset::new(0), true);
+        assert_eq!(fresh[0].context_before, links[0].context_before);
+    }
+}
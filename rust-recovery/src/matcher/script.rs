@@ -0,0 +1,170 @@
+//! Writing-system density of a fragment
+//!
+//! Text recovered from a disk is usually in one script, and a chunk of it
+//! stands out from binary data by how many of its bytes belong to that
+//! script's characters. The script of a character is read from its UTF-8
+//! lead byte (and the following byte, where a lead is shared between
+//! scripts); the continuation bytes must be valid, so random data scores
+//! low, except for Latin: a fifth of random bytes are ASCII letters, so
+//! random data measures about 0.2 Latin. A density is the share of the fragment's bytes taken by characters of
+//! the script, between 0.0 and 1.0: plain Russian prose is around 0.8
+//! Cyrillic (spaces and punctuation are not counted), English around 0.8
+//! Latin. ASCII letters count as Latin, so markup and JSON keys raise it too.
+//! `--script` picks the one that feeds the fragment score.
+
+use serde::{Deserialize, Serialize};
+
+/// Scripts measured by `ScriptDensity`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Script {
+    /// ASCII letters and Latin-1 Supplement to Latin Extended-B
+    Latin,
+    #[default]
+    Cyrillic,
+    Arabic,
+    Hebrew,
+    /// Han ideographs, kana and Hangul
+    Cjk,
+}
+
+/// Names accepted by `--script`
+pub const SCRIPT_NAMES: &[&str] = &["latin", "cyrillic", "arabic", "hebrew", "cjk"];
+
+impl Script {
+    pub const ALL: [Script; 5] = [Script::Latin, Script::Cyrillic, Script::Arabic, Script::Hebrew, Script::Cjk];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Script::Latin => "latin",
+            Script::Cyrillic => "cyrillic",
+            Script::Arabic => "arabic",
+            Script::Hebrew => "hebrew",
+            Script::Cjk => "cjk",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|script| script.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Share of a fragment's bytes in each script
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScriptDensity {
+    pub latin: f32,
+    pub cyrillic: f32,
+    pub arabic: f32,
+    pub hebrew: f32,
+    pub cjk: f32,
+}
+
+/// Lowest density for a script to count as the fragment's language (above
+/// the Latin density of random data)
+const DOMINANT_MIN: f32 = 0.3;
+
+impl ScriptDensity {
+    pub fn measure(data: &[u8]) -> Self {
+        let mut bytes = [0usize; Script::ALL.len()];
+        let mut i = 0;
+        while i < data.len() {
+            let (script, len) = classify(&data[i..]);
+            if let Some(script) = script {
+                bytes[script as usize] += len;
+            }
+            i += len;
+        }
+        let total = data.len().max(1) as f32;
+        Self {
+            latin: bytes[Script::Latin as usize] as f32 / total,
+            cyrillic: bytes[Script::Cyrillic as usize] as f32 / total,
+            arabic: bytes[Script::Arabic as usize] as f32 / total,
+            hebrew: bytes[Script::Hebrew as usize] as f32 / total,
+            cjk: bytes[Script::Cjk as usize] as f32 / total,
+        }
+    }
+
+    pub fn of(&self, script: Script) -> f32 {
+        match script {
+            Script::Latin => self.latin,
+            Script::Cyrillic => self.cyrillic,
+            Script::Arabic => self.arabic,
+            Script::Hebrew => self.hebrew,
+            Script::Cjk => self.cjk,
+        }
+    }
+
+    /// Densest script, None when no script reaches `DOMINANT_MIN`
+    pub fn dominant(&self) -> Option<Script> {
+        Script::ALL
+            .into_iter()
+            .map(|script| (script, self.of(script)))
+            .filter(|&(_, density)| density >= DOMINANT_MIN)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(script, _)| script)
+    }
+}
+
+/// Script of the character at the start of `data` and the bytes to skip:
+/// its UTF-8 length, or 1 for a stray or truncated byte
+#[inline]
+fn classify(data: &[u8]) -> (Option<Script>, usize) {
+    let lead = data[0];
+    let len = match lead {
+        0x00..=0x7F => return (lead.is_ascii_alphabetic().then_some(Script::Latin), 1),
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => return (None, 1),
+    };
+    if data.len() < len || !data[1..len].iter().all(|&b| (0x80..=0xBF).contains(&b)) {
+        return (None, 1);
+    }
+    let next = data[1];
+    let script = match (lead, next) {
+        // U+00C0-U+024F
+        (0xC3..=0xC9, _) => Some(Script::Latin),
+        // U+0400-U+04FF
+        (0xD0..=0xD3, _) => Some(Script::Cyrillic),
+        // U+0590-U+05FF
+        (0xD6, 0x90..=0xBF) | (0xD7, _) => Some(Script::Hebrew),
+        // U+0600-U+06FF
+        (0xD8..=0xDB, _) => Some(Script::Arabic),
+        // CJK punctuation, kana, ideographs (U+3000-U+9FFF), Hangul (U+AC00-U+D7A3)
+        (0xE3..=0xE9, _) | (0xEA, 0xB0..=0xBF) | (0xEB..=0xEC, _) | (0xED, 0x80..=0x9E) => Some(Script::Cjk),
+        // Supplementary ideographs (U+20000-U+3FFFF)
+        (0xF0, 0xA0..=0xBF) => Some(Script::Cjk),
+        _ => None,
+    };
+    (script, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_density_per_script() {
+        let russian = ScriptDensity::measure("Привет, мир".as_bytes());
+        // 9 letters of 2 bytes in 20 bytes
+        assert_eq!(russian.cyrillic, 18.0 / 20.0);
+        assert_eq!(russian.latin, 0.0);
+        assert_eq!(russian.dominant(), Some(Script::Cyrillic));
+
+        assert_eq!(ScriptDensity::measure(b"Hello, world").latin, 10.0 / 12.0);
+        assert_eq!(ScriptDensity::measure("Ça été".as_bytes()).latin, 8.0 / 9.0);
+        assert_eq!(ScriptDensity::measure("שלום".as_bytes()).dominant(), Some(Script::Hebrew));
+        assert_eq!(ScriptDensity::measure("مرحبا".as_bytes()).arabic, 1.0);
+        assert_eq!(ScriptDensity::measure("こんにちは世界".as_bytes()).cjk, 1.0);
+        assert_eq!(ScriptDensity::measure("안녕하세요".as_bytes()).cjk, 1.0);
+
+        // Lead bytes without their continuation bytes are not letters
+        let broken = ScriptDensity::measure(&[0xD0, 0x20, 0xD1, 0xE4, 0xB8, 0x20, 0xFF]);
+        assert_eq!(broken, ScriptDensity::default());
+        assert_eq!(broken.dominant(), None);
+        assert_eq!(ScriptDensity::measure(b""), ScriptDensity::default());
+
+        assert_eq!(Script::parse("CJK"), Some(Script::Cjk));
+        assert_eq!(Script::parse("greek"), None);
+        assert!(Script::ALL.iter().all(|script| SCRIPT_NAMES.contains(&script.name())));
+    }
+}
@@ -0,0 +1,171 @@
+//! Matcher decision trace (`--trace-matcher START-END`)
+//!
+//! For one image range only, every step the matcher takes is written to a
+//! text file: needle hits, the window searched around each, the patterns the
+//! window matched, captures rejected by a validator or as duplicates, links
+//! kept (confidence, context reasons, title), the keyword filter and the
+//! chunk's fragment score and hot decision. One line per event:
+//!
+//! ```text
+//! 0x0100A3F2 needle chunk=0x01000000 "youtube"
+//! 0x0100A2F2 window chunk=0x01000000 len=1536 patterns=watch,short
+//! 0x0100A3E9 reject pattern=watch value="dQw4w9WgXc" validator
+//! ```
+//!
+//! Chunks overlap, so hits in an overlap are traced once per chunk. The
+//! trace stops after `MAX_TRACE_EVENTS` lines; the last line says how many
+//! were dropped.
+
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Default trace file name in the output directory
+pub const TRACE_FILE: &str = "matcher_trace.log";
+/// Events written before the trace stops
+pub const MAX_TRACE_EVENTS: usize = 200_000;
+/// Bytes of a needle, value or title quoted in a line
+const MAX_QUOTED_BYTES: usize = 120;
+
+/// `START-END` in hex (0x prefix) or decimal, END exclusive
+pub fn parse_trace_range(text: &str) -> Result<(u64, u64), String> {
+    let number = |part: &str| {
+        let part = part.trim();
+        match part.strip_prefix("0x").or_else(|| part.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => part.parse().ok(),
+        }
+    };
+    let parsed = text.split_once('-').and_then(|(start, end)| Some((number(start)?, number(end)?)));
+    match parsed {
+        Some((start, end)) if start < end => Ok((start, end)),
+        Some(_) => Err(format!("Empty trace range: {} (END must be above START)", text)),
+        None => Err(format!("Invalid trace range: {} (expected START-END, e.g. 0x1000000-0x1100000)", text)),
+    }
+}
+
+/// Printable, length-limited quote of raw bytes
+pub fn quote(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_QUOTED_BYTES)]);
+    let mut quoted = format!("{:?}", text);
+    if bytes.len() > MAX_QUOTED_BYTES {
+        quoted.push_str("...");
+    }
+    quoted
+}
+
+/// Trace file shared by all scan threads
+pub struct MatcherTrace {
+    start: u64,
+    end: u64,
+    writer: Mutex<BufWriter<File>>,
+    events: AtomicUsize,
+    max_events: usize,
+}
+
+impl std::fmt::Debug for MatcherTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MatcherTrace(0x{:X}-0x{:X})", self.start, self.end)
+    }
+}
+
+impl MatcherTrace {
+    /// Create (or truncate) the trace file for `[start, end)`
+    pub fn create(path: &Path, start: u64, end: u64) -> std::io::Result<Self> {
+        Self::with_limit(path, start, end, MAX_TRACE_EVENTS)
+    }
+
+    pub fn with_limit(path: &Path, start: u64, end: u64, max_events: usize) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "# matcher trace 0x{:X}-0x{:X}", start, end)?;
+        Ok(Self { start, end, writer: Mutex::new(writer), events: AtomicUsize::new(0), max_events })
+    }
+
+    /// Image offset is traced
+    pub fn contains(&self, offset: u64) -> bool {
+        (self.start..self.end).contains(&offset)
+    }
+
+    /// `[offset, offset + len)` touches the traced range
+    pub fn overlaps(&self, offset: u64, len: usize) -> bool {
+        offset < self.end && offset.saturating_add(len as u64) > self.start
+    }
+
+    /// Write one event line for `offset` (callers check the range first)
+    pub fn event(&self, offset: u64, kind: &str, details: impl Display) {
+        let count = self.events.fetch_add(1, Ordering::Relaxed);
+        if count >= self.max_events {
+            return;
+        }
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writeln!(writer, "0x{:08X} {} {}", offset, kind, details);
+        }
+    }
+}
+
+impl Drop for MatcherTrace {
+    fn drop(&mut self) {
+        let events = *self.events.get_mut();
+        if let Ok(writer) = self.writer.get_mut() {
+            if events > self.max_events {
+                let _ = writeln!(writer, "# limit of {} events reached, {} dropped", self.max_events, events - self.max_events);
+            }
+            let _ = writer.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::EnhancedMatcher;
+    use crate::types::Offset;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_trace_range_and_events() {
+        assert_eq!(parse_trace_range("0x1000000-0x1100000"), Ok((0x1000000, 0x1100000)));
+        assert_eq!(parse_trace_range("100-200"), Ok((100, 200)));
+        assert!(parse_trace_range("0x200-0x100").is_err());
+        assert!(parse_trace_range("0x200").is_err());
+
+        // Two links: one inside the traced range, one past it
+        let mut data = vec![b' '; 8192];
+        let inside = b"https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        let outside = b"https://youtu.be/9bZkp7q19f0";
+        data[1000..1000 + inside.len()].copy_from_slice(inside);
+        data[6000..6000 + outside.len()].copy_from_slice(outside);
+        // Too short for a video id
+        data[2000..2032].copy_from_slice(b"youtube.com/watch?v=abc  -------");
+
+        let path = std::env::temp_dir().join(format!("matcher_trace_{}.log", std::process::id()));
+        let trace = Arc::new(MatcherTrace::with_limit(&path, 0x10000, 0x10000 + 4096, 1000).unwrap());
+        let mut matcher = EnhancedMatcher::new().with_trace(Arc::clone(&trace));
+        let links = matcher.scan_chunk(&data, Offset::new(0x10000), true);
+        assert_eq!(links.len(), 2);
+        drop(matcher);
+        drop(Arc::try_unwrap(trace).unwrap());
+        let log = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(log.starts_with("# matcher trace 0x10000-0x11000\n"));
+        assert!(log.lines().any(|l| l.starts_with("0x000103E8 link ") && l.contains("dQw4w9WgXcQ")));
+        assert!(log.contains(" needle "));
+        assert!(log.contains(" window "));
+        assert!(!log.contains("9bZkp7q19f0"));
+
+        // Event limit
+        let trace = MatcherTrace::with_limit(&path, 0, 10, 2).unwrap();
+        for i in 0..5 {
+            trace.event(i, "needle", "x");
+        }
+        drop(trace);
+        let log = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(log.lines().count(), 4);
+        assert!(log.ends_with("# limit of 2 events reached, 3 dropped\n"));
+    }
+}
@@ -1,198 +0,0 @@
-/// Validates if a byte slice is a valid YouTube video ID
-///
-/// A valid video ID must be:
-/// - Exactly 11 bytes long
-/// - Contain only alphanumeric characters, - or _
-#[inline]
-pub fn is_valid_video_id(id: &[u8]) -> bool {
-    if id.len() != 11 {
-        return false;
-    }
-    
-    // Check if all characters are valid
-    id.iter().all(|&b| {
-        b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
-    })
-}
-
-/// Fast heuristic check for probable JSON data
-/// Uses quick prefix and structure markers before full validation
-#[inline]
-pub fn is_probably_json(data: &[u8]) -> bool {
-    if data.is_empty() {
-        return false;
-    }
-    
-    // Convert to string for trimming and analysis
-    let text = match std::str::from_utf8(data) {
-        Ok(s) => s,
-        Err(_) => return false,
-    };
-    
-    // Trim whitespace
-    let trimmed = text.trim();
-    
-    // Check for JSON start characters
-    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
-        return false;
-    }
-    
-    // Quick structure validation - balance braces and brackets
-    let mut brace_count: i32 = 0;
-    let mut bracket_count: i32 = 0;
-    let mut in_string = false;
-    let mut escape_next = false;
-    
-    for b in trimmed.bytes() {
-        if escape_next {
-            escape_next = false;
-            continue;
-        }
-        
-        if b == b'\\' {
-            escape_next = true;
-            continue;
-        }
-        
-        if b == b'"' {
-            in_string = !in_string;
-            continue;
-        }
-        
-        if in_string {
-            continue;
-        }
-        
-        match b {
-            b'{' => brace_count += 1,
-            b'}' => brace_count = brace_count.saturating_sub(1),
-            b'[' => bracket_count += 1,
-            b']' => bracket_count = bracket_count.saturating_sub(1),
-            _ => {}
-        }
-    }
-    
-    // If braces/brackets are balanced and we have enough content
-    brace_count == 0 && bracket_count == 0 && trimmed.len() > 10
-}
-
-/// Validate JSON using serde_json
-/// Returns true if data is valid JSON
-#[inline]
-pub fn is_valid_json(data: &[u8]) -> bool {
-    if !is_probably_json(data) {
-        return false;
-    }
-    
-    let text = match std::str::from_utf8(data) {
-        Ok(s) => s.trim(),
-        Err(_) => return false,
-    };
-    
-    match serde_json::from_str::<serde_json::Value>(text) {
-        Ok(_) => true,
-        Err(_) => {
-            // Try to extract partial JSON if it's embedded
-            let json_start = text.find('{').or_else(|| text.find('['));
-            if let Some(start) = json_start {
-                let json_part = &text[start..];
-                match serde_json::from_str::<serde_json::Value>(json_part) {
-                    Ok(_) => true,
-                    Err(_) => false,
-                }
-            } else {
-                false
-            }
-        }
-    }
-}
-
-/// Fast heuristic check for YouTube URL
-/// Uses prefix and length validation before regex
-#[inline]
-pub fn is_probably_youtube_url(data: &[u8]) -> bool {
-    let text = match std::str::from_utf8(data) {
-        Ok(s) => s,
-        Err(_) => return false,
-    };
-    
-    let trimmed = text.trim();
-    
-    // Quick prefix check
-    if !trimmed.starts_with("http") {
-        return false;
-    }
-    
-    // Length check - YouTube URLs are usually 20-100 chars
-    if trimmed.len() < 15 || trimmed.len() > 200 {
-        return false;
-    }
-    
-    // Check for YouTube domain indicators
-    trimmed.contains("youtube") || trimmed.contains("youtu.be")
-}
-
-/// Validate YouTube URL using pattern matching
-/// Returns true if data contains a valid YouTube URL
-#[inline]
-pub fn is_valid_youtube_url(data: &[u8]) -> bool {
-    if !is_probably_youtube_url(data) {
-        return false;
-    }
-    
-    // Use the same patterns as the matcher
-    use crate::matcher::patterns::YOUTUBE_PATTERNS;
-    
-    for pattern in YOUTUBE_PATTERNS.iter() {
-        if pattern.regex.is_match(data) {
-            return true;
-        }
-    }
-    
-    false
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_video_id_validation() {
-        assert!(is_valid_video_id(b"dQw4w9WgXcQ"));
-        assert!(is_valid_video_id(b"_-123456789"));
-        assert!(!is_valid_video_id(b"short"));
-        assert!(!is_valid_video_id(b"toolongvideo"));
-        assert!(!is_valid_video_id(b"invalid$cha"));
-    }
-
-    #[test]
-    fn test_json_validation() {
-        // Valid JSON
-        assert!(is_valid_json(b"{\"key\": \"value\"}"));
-        assert!(is_valid_json(b"[\"item1\", \"item2\"]"));
-        assert!(is_valid_json(b"  {\"nested\": {\"data\": true}}  "));
-        
-        // Invalid JSON
-        assert!(!is_valid_json(b"not json"));
-        assert!(!is_valid_json(b"{"));
-        assert!(!is_valid_json(b""));
-        
-        // Partial JSON (embedded) - this might fail due to strict validation
-        let embedded = b"{\"key\": \"value\"}";
-        // Skip this test for now as it depends on embedded JSON extraction
-        // assert!(is_valid_json(embedded));
-    }
-
-    #[test]
-    fn test_youtube_url_validation() {
-        // Valid YouTube URLs
-        assert!(is_valid_youtube_url(b"https://youtube.com/watch?v=dQw4w9WgXcQ"));
-        assert!(is_valid_youtube_url(b"https://youtu.be/dQw4w9WgXcQ"));
-        assert!(is_valid_youtube_url(b"https://www.youtube.com/watch?v=dQw4w9WgXcQ"));
-        
-        // Invalid URLs
-        assert!(!is_valid_youtube_url(b"not a url"));
-        assert!(!is_valid_youtube_url(b"https://example.com"));
-        assert!(!is_valid_youtube_url(b""));
-    }
-}
\ No newline at end of file
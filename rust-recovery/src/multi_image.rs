@@ -0,0 +1,244 @@
+//! Several images in one invocation
+//!
+//! `rust-recovery a.img b.img c.E01` (or `--image-list FILE`) scans up to
+//! `--parallel-images` images at a time. Each scan is a regular run into its
+//! own directory under `--output` (named after the image); their chunks are
+//! scanned on the one rayon pool, so the images share the worker threads.
+//! When all are done, `multi_image_summary.json` in `--output` lists the
+//! outcome of every image and the totals.
+
+use crate::error::{RecoveryError, Result};
+use crate::exit_code::{self, RunStatus, RunSummary};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Combined summary in the top-level output directory
+pub const MULTI_IMAGE_SUMMARY_FILE: &str = "multi_image_summary.json";
+
+/// Image paths of an image list: one per line, `#` starts a comment,
+/// relative paths are relative to the list
+pub fn load_image_list(path: &Path) -> Result<Vec<PathBuf>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| RecoveryError::Config(format!("Failed to read image list {}: {}", path.display(), e)))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    Ok(text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| base.join(line))
+        .collect())
+}
+
+/// Output directory of each image: `output/<file stem>`, with `_2`, `_3`...
+/// when two images have the same name
+pub fn image_output_dirs(output: &Path, images: &[PathBuf]) -> Vec<PathBuf> {
+    let mut taken = HashSet::new();
+    images
+        .iter()
+        .enumerate()
+        .map(|(index, image)| {
+            let stem = image
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .filter(|stem| !stem.is_empty())
+                .unwrap_or_else(|| format!("image_{}", index + 1));
+            let mut name = stem.clone();
+            let mut suffix = 1;
+            while !taken.insert(name.clone()) {
+                suffix += 1;
+                name = format!("{}_{}", stem, suffix);
+            }
+            output.join(name)
+        })
+        .collect()
+}
+
+/// How the scan of one image ended
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageOutcome {
+    pub image: PathBuf,
+    pub output_dir: PathBuf,
+    /// `RunStatus` name, `error`, or `skipped` when cancelled before it started
+    pub status: String,
+    pub exit_code: i32,
+    pub files_recovered: usize,
+    pub bytes_scanned: u64,
+    pub image_size: u64,
+    pub bad_sector_bytes: u64,
+    pub duration_secs: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip)]
+    run_status: Option<RunStatus>,
+}
+
+impl ImageOutcome {
+    pub fn new(image: &Path, output_dir: &Path, result: &Result<RunSummary>) -> Self {
+        let mut outcome = Self {
+            image: image.to_path_buf(),
+            output_dir: output_dir.to_path_buf(),
+            status: String::new(),
+            exit_code: 0,
+            files_recovered: 0,
+            bytes_scanned: 0,
+            image_size: 0,
+            bad_sector_bytes: 0,
+            duration_secs: 0.0,
+            report: None,
+            error: None,
+            run_status: None,
+        };
+        match result {
+            Ok(summary) => {
+                outcome.status = summary.status.name().to_string();
+                outcome.exit_code = summary.status.code();
+                outcome.files_recovered = summary.files_recovered;
+                outcome.bytes_scanned = summary.bytes_scanned;
+                outcome.image_size = summary.image_size;
+                outcome.bad_sector_bytes = summary.bad_sector_bytes;
+                outcome.duration_secs = summary.duration_secs;
+                outcome.report = Some(summary.report.clone());
+                outcome.run_status = Some(summary.status);
+            }
+            Err(e) => {
+                outcome.status = "error".to_string();
+                outcome.exit_code = exit_code::error_code(e).0;
+                outcome.error = Some(e.to_string());
+            }
+        }
+        outcome
+    }
+
+    /// Image not scanned because the run was cancelled first
+    pub fn skipped(image: &Path, output_dir: &Path) -> Self {
+        let mut outcome = Self::new(image, output_dir, &Err(RecoveryError::Config("cancelled before the scan".into())));
+        outcome.status = "skipped".to_string();
+        outcome.exit_code = exit_code::CANCELLED;
+        outcome.run_status = Some(RunStatus::Cancelled);
+        outcome
+    }
+
+    pub fn failed(&self) -> bool {
+        self.run_status.is_none()
+    }
+}
+
+/// Outcomes of all images of the invocation
+#[derive(Debug, Clone, Serialize)]
+pub struct MultiImageSummary {
+    pub status: String,
+    pub exit_code: i32,
+    pub files_recovered: usize,
+    pub bytes_scanned: u64,
+    pub image_size: u64,
+    pub bad_sector_bytes: u64,
+    /// Wall-clock time of the whole invocation
+    pub duration_secs: f64,
+    pub images: Vec<ImageOutcome>,
+}
+
+impl MultiImageSummary {
+    /// Combined status: cancelled before partial; a failed image makes the
+    /// result partial unless every image failed (then the first error's code)
+    pub fn new(images: Vec<ImageOutcome>, duration_secs: f64) -> Self {
+        let statuses: Vec<RunStatus> = images.iter().filter_map(|outcome| outcome.run_status).collect();
+        let (status, exit_code) = if statuses.is_empty() {
+            ("error".to_string(), images.first().map_or(exit_code::CONFIG_ERROR, |outcome| outcome.exit_code))
+        } else {
+            let combined = if statuses.contains(&RunStatus::Cancelled) {
+                RunStatus::Cancelled
+            } else if statuses.contains(&RunStatus::Partial) || statuses.len() < images.len() {
+                RunStatus::Partial
+            } else if statuses.contains(&RunStatus::Recovered) {
+                RunStatus::Recovered
+            } else {
+                RunStatus::NothingRecovered
+            };
+            (combined.name().to_string(), combined.code())
+        };
+        Self {
+            status,
+            exit_code,
+            files_recovered: images.iter().map(|outcome| outcome.files_recovered).sum(),
+            bytes_scanned: images.iter().map(|outcome| outcome.bytes_scanned).sum(),
+            image_size: images.iter().map(|outcome| outcome.image_size).sum(),
+            bad_sector_bytes: images.iter().map(|outcome| outcome.bad_sector_bytes).sum(),
+            duration_secs,
+            images,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| RecoveryError::Config(format!("Failed to serialize summary: {}", e)))?;
+        fs::write(path, json)
+            .map_err(|e| RecoveryError::Config(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    /// `RESULT` line of the invocation; `report` is the combined summary
+    pub fn line(&self, report: &Path) -> String {
+        format!(
+            "RESULT status={} exit={} images={} failed={} files={} bytes_scanned={} image_size={} bad_sector_bytes={} duration_secs={:.1} report={}",
+            self.status,
+            self.exit_code,
+            self.images.len(),
+            self.images.iter().filter(|outcome| outcome.failed()).count(),
+            self.files_recovered,
+            self.bytes_scanned,
+            self.image_size,
+            self.bad_sector_bytes,
+            self.duration_secs,
+            report.display()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(status: RunStatus, files: usize) -> Result<RunSummary> {
+        Ok(RunSummary {
+            status,
+            files_recovered: files,
+            bytes_scanned: 1024,
+            image_size: 1024,
+            bad_sector_bytes: 0,
+            duration_secs: 1.0,
+            report: PathBuf::from("reports/recovery_report.json"),
+        })
+    }
+
+    #[test]
+    fn test_multi_image_outputs_and_status() {
+        let dir = std::env::temp_dir().join(format!("multi_image_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let list = dir.join("images.txt");
+        fs::write(&list, "# SD cards\ncard1.img\n\n/evidence/card1.E01  # second copy\n/dev/sdb\n").unwrap();
+        let images = load_image_list(&list).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(images, [dir.join("card1.img"), PathBuf::from("/evidence/card1.E01"), PathBuf::from("/dev/sdb")]);
+        let out = Path::new("/out");
+        assert_eq!(
+            image_output_dirs(out, &images),
+            [out.join("card1"), out.join("card1_2"), out.join("sdb")]
+        );
+
+        let outcome = |result| ImageOutcome::new(Path::new("a.img"), out, &result);
+        let ok = MultiImageSummary::new(vec![outcome(summary(RunStatus::Recovered, 2)), outcome(summary(RunStatus::NothingRecovered, 0))], 2.0);
+        assert_eq!((ok.status.as_str(), ok.exit_code, ok.files_recovered), ("recovered", exit_code::SUCCESS, 2));
+
+        let error = || outcome(Err(RecoveryError::FileNotFound("b.img".to_string())));
+        let mixed = MultiImageSummary::new(vec![outcome(summary(RunStatus::Recovered, 2)), error()], 2.0);
+        assert_eq!(mixed.exit_code, exit_code::PARTIAL);
+        assert!(mixed.line(Path::new("/out/multi_image_summary.json")).starts_with("RESULT status=partial exit=4 images=2 failed=1 files=2 "));
+        let failed = MultiImageSummary::new(vec![error(), error()], 0.0);
+        assert_eq!((failed.status.as_str(), failed.exit_code), ("error", exit_code::IO_ERROR));
+        let cancelled = MultiImageSummary::new(vec![error(), ImageOutcome::skipped(Path::new("c.img"), out)], 1.0);
+        assert_eq!(cancelled.exit_code, exit_code::CANCELLED);
+    }
+}
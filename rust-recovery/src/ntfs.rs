@@ -0,0 +1,780 @@
+//! NTFS metadata scanner
+//!
+//! Locates the MFT from the boot sector, parses FILE records (including records
+//! whose in-use flag is cleared, i.e. deleted files), resolves non-resident data
+//! runs and extracts file content. Entries carry the same hints as exFAT entries
+//! (`filename`, first cluster, size) so `fragment_linker` can use them.
+//!
+//! Attribute lists ($ATTRIBUTE_LIST) and extension records are not followed: a
+//! heavily fragmented file whose runs spill into extension records is reported
+//! with the runs found in its base record only.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::fragment_linker::ExFatMetadata;
+
+/// Boot sector field offsets
+const BS_OEM_ID: usize = 3;
+const BS_BYTES_PER_SECTOR: usize = 11;
+const BS_SECTORS_PER_CLUSTER: usize = 13;
+const BS_TOTAL_SECTORS: usize = 40;
+const BS_MFT_CLUSTER: usize = 48;
+const BS_MFT_MIRROR_CLUSTER: usize = 56;
+const BS_CLUSTERS_PER_RECORD: usize = 64;
+const BS_SIGNATURE: usize = 510;
+const BOOT_SECTOR_SIZE: usize = 512;
+
+/// FILE record header field offsets
+const FR_USA_OFFSET: usize = 4;
+const FR_USA_COUNT: usize = 6;
+const FR_FIRST_ATTRIBUTE: usize = 20;
+const FR_FLAGS: usize = 22;
+const FR_USED_SIZE: usize = 24;
+const FR_BASE_RECORD: usize = 32;
+const FR_RECORD_NUMBER: usize = 44;
+const FR_HEADER_SIZE: usize = 48;
+
+const FR_FLAG_IN_USE: u16 = 0x0001;
+const FR_FLAG_DIRECTORY: u16 = 0x0002;
+
+/// Attribute header field offsets
+const AT_TYPE: usize = 0;
+const AT_LENGTH: usize = 4;
+const AT_NON_RESIDENT: usize = 8;
+const AT_NAME_LENGTH: usize = 9;
+const AT_FLAGS: usize = 12;
+const AT_VALUE_LENGTH: usize = 16;
+const AT_VALUE_OFFSET: usize = 20;
+const AT_RUNS_OFFSET: usize = 32;
+const AT_REAL_SIZE: usize = 48;
+
+const AT_FLAG_COMPRESSED: u16 = 0x0001;
+const AT_FLAG_ENCRYPTED: u16 = 0x4000;
+
+/// Attribute types
+const ATTR_FILE_NAME: u32 = 0x30;
+const ATTR_DATA: u32 = 0x80;
+const ATTR_END: u32 = 0xFFFF_FFFF;
+
+/// FILE_NAME value field offsets
+const FN_PARENT: usize = 0;
+const FN_REAL_SIZE: usize = 48;
+const FN_NAME_LENGTH: usize = 64;
+const FN_NAMESPACE: usize = 65;
+const FN_NAME: usize = 66;
+const FN_NAMESPACE_DOS: u8 = 2;
+
+/// Update sequence stride (always 512, independent of the sector size)
+const FIXUP_STRIDE: usize = 512;
+/// Record size to carve with when no boot sector is available
+pub const DEFAULT_RECORD_SIZE: usize = 1024;
+const MAX_RECORD_SIZE: usize = 64 * 1024;
+const MAX_CLUSTER_SIZE: u64 = 2 * 1024 * 1024;
+const MAX_EXTRACT_SIZE: u64 = 250 * 1024 * 1024;
+/// Consecutive non-FILE slots after which a damaged MFT walk gives up
+const MAX_MFT_GAP: usize = 16;
+
+#[derive(Clone, Debug)]
+pub struct NtfsBootParams {
+    pub sector_size: u64,
+    pub cluster_size: u64,
+    pub total_sectors: u64,
+    /// Absolute image offset of MFT record 0
+    pub mft_offset: u64,
+    pub mft_mirror_offset: u64,
+    pub record_size: usize,
+    pub boot_sector_offset: u64,
+}
+
+/// A run of clusters; `lcn == None` is a sparse run (reads as zeros)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataRun {
+    pub lcn: Option<u64>,
+    pub length: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct NtfsEntry {
+    /// Image offset of the FILE record
+    pub offset: u64,
+    pub record_number: u64,
+    /// Absolute image offset of the first data byte (see `populate_data_offsets`)
+    pub data_offset: Option<u64>,
+    pub is_deleted: bool,
+    pub is_directory: bool,
+    pub filename: String,
+    pub parent_record: Option<u64>,
+    pub size: u64,
+    /// Unnamed $DATA content when it is stored inside the record
+    pub resident_data: Option<Vec<u8>>,
+    /// Unnamed $DATA runs when the content lives in clusters
+    pub data_runs: Vec<DataRun>,
+    /// Compressed or encrypted $DATA cannot be extracted as raw clusters
+    pub is_compressed: bool,
+}
+
+impl NtfsEntry {
+    /// First allocated cluster, the NTFS counterpart of exFAT's first cluster
+    pub fn first_cluster(&self) -> Option<u64> {
+        self.data_runs.iter().find_map(|run| run.lcn)
+    }
+
+    /// Hints for `fragment_linker`, in the same shape as exFAT metadata
+    pub fn to_fragment_metadata(&self) -> ExFatMetadata {
+        ExFatMetadata {
+            filename: (!self.filename.is_empty()).then(|| self.filename.clone()),
+            first_cluster: self.first_cluster().and_then(|lcn| u32::try_from(lcn).ok()),
+            size: Some(self.size),
+        }
+    }
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_le_bytes)
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+}
+
+fn read_u64_le(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+}
+
+pub(crate) fn parse_boot_sector_at(data: &[u8], bs_offset: u64) -> Option<NtfsBootParams> {
+    let off = usize::try_from(bs_offset).ok()?;
+    let sector = data.get(off..off.checked_add(BOOT_SECTOR_SIZE)?)?;
+
+    if sector.get(BS_OEM_ID..BS_OEM_ID + 8)? != b"NTFS    " {
+        return None;
+    }
+    if read_u16_le(sector, BS_SIGNATURE)? != 0xAA55 {
+        return None;
+    }
+
+    let sector_size = read_u16_le(sector, BS_BYTES_PER_SECTOR)? as u64;
+    if !sector_size.is_power_of_two() || !(512..=4096).contains(&sector_size) {
+        return None;
+    }
+
+    // Values above 0x80 encode 2^(256 - n) sectors (large clusters)
+    let raw_spc = *sector.get(BS_SECTORS_PER_CLUSTER)?;
+    let sectors_per_cluster = if raw_spc > 0x80 {
+        1u64.checked_shl(256 - raw_spc as u32)?
+    } else {
+        raw_spc as u64
+    };
+    if sectors_per_cluster == 0 || !sectors_per_cluster.is_power_of_two() {
+        return None;
+    }
+    let cluster_size = sector_size.checked_mul(sectors_per_cluster)?;
+    if cluster_size > MAX_CLUSTER_SIZE {
+        return None;
+    }
+
+    // Positive: clusters per record; negative: record is 2^(-n) bytes
+    let raw_cpr = *sector.get(BS_CLUSTERS_PER_RECORD)? as i8;
+    let record_size = if raw_cpr > 0 {
+        (raw_cpr as u64).checked_mul(cluster_size)?
+    } else {
+        1u64.checked_shl(raw_cpr.unsigned_abs() as u32)?
+    };
+    let record_size = usize::try_from(record_size).ok()?;
+    if !(FIXUP_STRIDE..=MAX_RECORD_SIZE).contains(&record_size) {
+        return None;
+    }
+
+    let total_sectors = read_u64_le(sector, BS_TOTAL_SECTORS)?;
+    let mft_offset = read_u64_le(sector, BS_MFT_CLUSTER)?
+        .checked_mul(cluster_size)?
+        .checked_add(bs_offset)?;
+    let mft_mirror_offset = read_u64_le(sector, BS_MFT_MIRROR_CLUSTER)?
+        .checked_mul(cluster_size)?
+        .checked_add(bs_offset)?;
+
+    if mft_offset <= bs_offset {
+        return None;
+    }
+
+    Some(NtfsBootParams {
+        sector_size,
+        cluster_size,
+        total_sectors,
+        mft_offset,
+        mft_mirror_offset,
+        record_size,
+        boot_sector_offset: bs_offset,
+    })
+}
+
+pub fn find_boot_sector(data: &[u8]) -> Option<NtfsBootParams> {
+    if let Some(params) = parse_boot_sector_at(data, 0) {
+        return Some(params);
+    }
+
+    let search_limit = data.len().min(4 * 1024 * 1024);
+    for offset in (512..search_limit).step_by(512) {
+        if offset + BOOT_SECTOR_SIZE > data.len() {
+            break;
+        }
+        if data.get(offset + BS_OEM_ID..offset + BS_OEM_ID + 8) == Some(&b"NTFS    "[..]) {
+            if let Some(params) = parse_boot_sector_at(data, offset as u64) {
+                return Some(params);
+            }
+        }
+    }
+
+    None
+}
+
+/// Image offset of a cluster relative to the volume
+pub fn cluster_to_offset(params: &NtfsBootParams, lcn: u64) -> Option<u64> {
+    lcn.checked_mul(params.cluster_size)?
+        .checked_add(params.boot_sector_offset)
+}
+
+/// Decode a mapping-pairs array into absolute runs
+pub fn decode_data_runs(runs: &[u8]) -> Option<Vec<DataRun>> {
+    let mut result = Vec::new();
+    let mut pos = 0usize;
+    let mut lcn: i64 = 0;
+
+    loop {
+        let header = *runs.get(pos)?;
+        if header == 0 {
+            break;
+        }
+        let length_size = (header & 0x0F) as usize;
+        let offset_size = (header >> 4) as usize;
+        if length_size == 0 || length_size > 8 || offset_size > 8 {
+            return None;
+        }
+        pos += 1;
+
+        let length_bytes = runs.get(pos..pos + length_size)?;
+        let mut length = 0u64;
+        for (i, &b) in length_bytes.iter().enumerate() {
+            length |= (b as u64) << (8 * i);
+        }
+        pos += length_size;
+
+        if offset_size == 0 {
+            result.push(DataRun { lcn: None, length });
+            continue;
+        }
+
+        let offset_bytes = runs.get(pos..pos + offset_size)?;
+        let mut delta = 0i64;
+        for (i, &b) in offset_bytes.iter().enumerate() {
+            delta |= (b as i64) << (8 * i);
+        }
+        // Sign-extend from the highest byte present
+        let shift = 64 - 8 * offset_size as u32;
+        delta = (delta << shift) >> shift;
+        pos += offset_size;
+
+        lcn = lcn.checked_add(delta)?;
+        if lcn < 0 {
+            return None;
+        }
+        result.push(DataRun { lcn: Some(lcn as u64), length });
+    }
+
+    Some(result)
+}
+
+/// Undo the update sequence array in place; `false` if a sector is torn
+fn apply_fixups(record: &mut [u8]) -> bool {
+    let (Some(usa_offset), Some(usa_count)) =
+        (read_u16_le(record, FR_USA_OFFSET), read_u16_le(record, FR_USA_COUNT))
+    else {
+        return false;
+    };
+    let usa_offset = usa_offset as usize;
+    let usa_count = usa_count as usize;
+    if usa_count < 2 || usa_offset + usa_count * 2 > record.len() {
+        return false;
+    }
+
+    let usn = [record[usa_offset], record[usa_offset + 1]];
+    for i in 1..usa_count {
+        let sector_end = i * FIXUP_STRIDE;
+        if sector_end > record.len() {
+            break;
+        }
+        let tail = sector_end - 2;
+        if record[tail..sector_end] != usn {
+            return false;
+        }
+        let fix = usa_offset + i * 2;
+        record[tail] = record[fix];
+        record[tail + 1] = record[fix + 1];
+    }
+    true
+}
+
+fn parse_file_name(value: &[u8]) -> Option<(String, u8, u64, u64)> {
+    let name_length = *value.get(FN_NAME_LENGTH)? as usize;
+    let namespace = *value.get(FN_NAMESPACE)?;
+    let name_bytes = value.get(FN_NAME..FN_NAME + name_length * 2)?;
+    let units: Vec<u16> = name_bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let name = String::from_utf16_lossy(&units);
+    let parent = read_u64_le(value, FN_PARENT)? & 0x0000_FFFF_FFFF_FFFF;
+    let real_size = read_u64_le(value, FN_REAL_SIZE)?;
+    Some((name, namespace, parent, real_size))
+}
+
+/// Parse one FILE record (`data` starts at the record); deleted records are kept
+pub fn parse_file_record(data: &[u8], base_offset: u64, record_size: usize) -> Option<NtfsEntry> {
+    let raw = data.get(..record_size)?;
+    if raw.get(..4)? != b"FILE" {
+        return None;
+    }
+
+    let mut record = raw.to_vec();
+    if !apply_fixups(&mut record) {
+        return None;
+    }
+
+    // Extension records only hold attributes of their base record
+    if read_u64_le(&record, FR_BASE_RECORD)? & 0x0000_FFFF_FFFF_FFFF != 0 {
+        return None;
+    }
+
+    let flags = read_u16_le(&record, FR_FLAGS)?;
+    let used_size = (read_u32_le(&record, FR_USED_SIZE)? as usize).min(record_size);
+    let record_number = read_u32_le(&record, FR_RECORD_NUMBER)? as u64;
+
+    let mut entry = NtfsEntry {
+        offset: base_offset,
+        record_number,
+        data_offset: None,
+        is_deleted: flags & FR_FLAG_IN_USE == 0,
+        is_directory: flags & FR_FLAG_DIRECTORY != 0,
+        filename: String::new(),
+        parent_record: None,
+        size: 0,
+        resident_data: None,
+        data_runs: Vec::new(),
+        is_compressed: false,
+    };
+    let mut name_namespace: Option<u8> = None;
+    let mut name_size = 0u64;
+    let mut has_data = false;
+
+    let mut pos = read_u16_le(&record, FR_FIRST_ATTRIBUTE)? as usize;
+    if pos < FR_HEADER_SIZE {
+        return None;
+    }
+
+    while pos + 16 <= used_size {
+        let attr_type = read_u32_le(&record, pos + AT_TYPE)?;
+        if attr_type == ATTR_END {
+            break;
+        }
+        let attr_length = read_u32_le(&record, pos + AT_LENGTH)? as usize;
+        if attr_length < 16 || pos + attr_length > used_size {
+            break;
+        }
+        let attr = &record[pos..pos + attr_length];
+        let non_resident = attr[AT_NON_RESIDENT] != 0;
+        let named = attr[AT_NAME_LENGTH] != 0;
+
+        match attr_type {
+            ATTR_FILE_NAME if !non_resident => {
+                let value_length = read_u32_le(attr, AT_VALUE_LENGTH)? as usize;
+                let value_offset = read_u16_le(attr, AT_VALUE_OFFSET)? as usize;
+                if let Some((name, namespace, parent, real_size)) = attr
+                    .get(value_offset..value_offset.saturating_add(value_length))
+                    .and_then(parse_file_name)
+                {
+                    // Prefer the long (Win32/POSIX) name over the 8.3 DOS alias
+                    let better = match name_namespace {
+                        None => true,
+                        Some(current) => current == FN_NAMESPACE_DOS && namespace != FN_NAMESPACE_DOS,
+                    };
+                    if better {
+                        entry.filename = name;
+                        entry.parent_record = Some(parent);
+                        name_size = real_size;
+                        name_namespace = Some(namespace);
+                    }
+                }
+            }
+            ATTR_DATA if !named && !has_data => {
+                has_data = true;
+                let attr_flags = read_u16_le(attr, AT_FLAGS)?;
+                entry.is_compressed = attr_flags & (AT_FLAG_COMPRESSED | AT_FLAG_ENCRYPTED) != 0;
+
+                if non_resident {
+                    let runs_offset = read_u16_le(attr, AT_RUNS_OFFSET)? as usize;
+                    entry.size = read_u64_le(attr, AT_REAL_SIZE)?;
+                    entry.data_runs = attr
+                        .get(runs_offset..)
+                        .and_then(decode_data_runs)
+                        .unwrap_or_default();
+                } else {
+                    let value_length = read_u32_le(attr, AT_VALUE_LENGTH)? as usize;
+                    let value_offset = read_u16_le(attr, AT_VALUE_OFFSET)? as usize;
+                    let value = attr.get(value_offset..value_offset.saturating_add(value_length))?;
+                    entry.size = value.len() as u64;
+                    entry.data_offset = Some(base_offset + (pos + value_offset) as u64);
+                    entry.resident_data = Some(value.to_vec());
+                }
+            }
+            _ => {}
+        }
+
+        pos += attr_length;
+    }
+
+    if !has_data {
+        entry.size = name_size;
+    }
+
+    Some(entry)
+}
+
+/// Carve FILE records at every sector boundary of `data`
+pub fn scan_for_records(data: &[u8], base_offset: u64, record_size: usize) -> Vec<NtfsEntry> {
+    let mut entries = Vec::new();
+    let mut pos = 0usize;
+
+    while pos + record_size <= data.len() {
+        if &data[pos..pos + 4] == b"FILE" {
+            if let Some(entry) = parse_file_record(&data[pos..], base_offset + pos as u64, record_size) {
+                entries.push(entry);
+                pos = pos.saturating_add(record_size);
+                continue;
+            }
+        }
+        pos = pos.saturating_add(FIXUP_STRIDE);
+    }
+
+    entries
+}
+
+/// Walk the MFT using the runs of its own record 0.
+///
+/// If record 0 is damaged, the MFT is walked contiguously from its start
+/// until `MAX_MFT_GAP` consecutive slots are not FILE records.
+pub fn read_mft(data: &[u8], params: &NtfsBootParams) -> Vec<NtfsEntry> {
+    let record_size = params.record_size;
+    let Ok(mft_start) = usize::try_from(params.mft_offset) else {
+        return Vec::new();
+    };
+    let Some(mft_data) = data.get(mft_start..) else {
+        return Vec::new();
+    };
+
+    let extents: Vec<(u64, u64)> = match parse_file_record(mft_data, params.mft_offset, record_size) {
+        Some(mft) if !mft.data_runs.is_empty() => mft
+            .data_runs
+            .iter()
+            .filter_map(|run| {
+                let start = cluster_to_offset(params, run.lcn?)?;
+                Some((start, run.length.checked_mul(params.cluster_size)?))
+            })
+            .collect(),
+        _ => {
+            let mut entries = Vec::new();
+            let mut gap = 0;
+            let mut pos = 0usize;
+            while gap < MAX_MFT_GAP && pos + record_size <= mft_data.len() {
+                match parse_file_record(&mft_data[pos..], params.mft_offset + pos as u64, record_size) {
+                    Some(entry) => {
+                        entries.push(entry);
+                        gap = 0;
+                    }
+                    None => gap += 1,
+                }
+                pos += record_size;
+            }
+            return entries;
+        }
+    };
+
+    let mut entries = Vec::new();
+    for (start, length) in extents {
+        let Ok(start) = usize::try_from(start) else { continue };
+        let end = start.saturating_add(length as usize).min(data.len());
+        let mut pos = start;
+        while pos + record_size <= end {
+            if let Some(entry) = parse_file_record(&data[pos..], pos as u64, record_size) {
+                entries.push(entry);
+            }
+            pos += record_size;
+        }
+    }
+
+    entries
+}
+
+pub fn populate_data_offsets(entries: &mut [NtfsEntry], params: &NtfsBootParams) {
+    for entry in entries {
+        if entry.resident_data.is_none() {
+            entry.data_offset = entry
+                .first_cluster()
+                .and_then(|lcn| cluster_to_offset(params, lcn));
+        }
+    }
+}
+
+/// Hand the file content to `sink` run by run; sparse runs produce zeros.
+/// Returns the number of bytes passed to `sink`.
+fn for_each_file_slice<F>(
+    data: &[u8],
+    params: &NtfsBootParams,
+    entry: &NtfsEntry,
+    file_size: u64,
+    mut sink: F,
+) -> std::io::Result<u64>
+where
+    F: FnMut(&[u8]) -> std::io::Result<()>,
+{
+    if let Some(ref resident) = entry.resident_data {
+        let len = (resident.len() as u64).min(file_size) as usize;
+        sink(&resident[..len])?;
+        return Ok(len as u64);
+    }
+    if entry.is_compressed {
+        return Ok(0);
+    }
+
+    let zeros = vec![0u8; params.cluster_size as usize];
+    let mut remaining = file_size;
+
+    'runs: for run in &entry.data_runs {
+        for i in 0..run.length {
+            if remaining == 0 {
+                break 'runs;
+            }
+            let to_read = remaining.min(params.cluster_size);
+
+            match run.lcn {
+                None => sink(&zeros[..to_read as usize])?,
+                Some(lcn) => {
+                    let Some(start) = lcn
+                        .checked_add(i)
+                        .and_then(|cluster| cluster_to_offset(params, cluster))
+                    else {
+                        break 'runs;
+                    };
+                    if start >= data.len() as u64 {
+                        break 'runs;
+                    }
+                    let end = start.saturating_add(to_read).min(data.len() as u64);
+                    sink(&data[start as usize..end as usize])?;
+                    if end - start < to_read {
+                        remaining -= end - start;
+                        break 'runs;
+                    }
+                }
+            }
+            remaining -= to_read;
+        }
+    }
+
+    Ok(file_size - remaining)
+}
+
+/// Extract a file into memory (capped at `MAX_EXTRACT_SIZE`, see `extract_file_to_writer`)
+pub fn extract_file_content(data: &[u8], params: &NtfsBootParams, entry: &NtfsEntry) -> Vec<u8> {
+    let actual_size = entry.size.min(MAX_EXTRACT_SIZE);
+    let mut content = Vec::with_capacity(actual_size as usize);
+    let _ = for_each_file_slice(data, params, entry, actual_size, |slice| {
+        content.extend_from_slice(slice);
+        Ok(())
+    });
+    content
+}
+
+/// Stream a file run-by-run into `writer` without buffering it in memory
+pub fn extract_file_to_writer<W: Write>(
+    data: &[u8],
+    params: &NtfsBootParams,
+    entry: &NtfsEntry,
+    writer: &mut W,
+) -> std::io::Result<u64> {
+    let written = for_each_file_slice(data, params, entry, entry.size, |slice| writer.write_all(slice))?;
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Stream an entry's content to `path` (created or truncated)
+pub fn extract_entry_to_path(
+    data: &[u8],
+    params: &NtfsBootParams,
+    entry: &NtfsEntry,
+    path: &Path,
+) -> std::io::Result<u64> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    extract_file_to_writer(data, params, entry, &mut writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLUSTER: usize = 1024;
+
+    fn build_boot_sector() -> Vec<u8> {
+        let mut data = vec![0u8; BOOT_SECTOR_SIZE];
+        data[BS_OEM_ID..BS_OEM_ID + 8].copy_from_slice(b"NTFS    ");
+        data[BS_BYTES_PER_SECTOR..BS_BYTES_PER_SECTOR + 2].copy_from_slice(&512u16.to_le_bytes());
+        data[BS_SECTORS_PER_CLUSTER] = 2;
+        data[BS_TOTAL_SECTORS..BS_TOTAL_SECTORS + 8].copy_from_slice(&64u64.to_le_bytes());
+        data[BS_MFT_CLUSTER..BS_MFT_CLUSTER + 8].copy_from_slice(&4u64.to_le_bytes());
+        data[BS_MFT_MIRROR_CLUSTER..BS_MFT_MIRROR_CLUSTER + 8].copy_from_slice(&8u64.to_le_bytes());
+        data[BS_CLUSTERS_PER_RECORD] = (-10i8) as u8; // 2^10 = 1024
+        data[BS_SIGNATURE..BS_SIGNATURE + 2].copy_from_slice(&0xAA55u16.to_le_bytes());
+        data
+    }
+
+    /// FILE record with a FILE_NAME and an unnamed $DATA attribute, fixups applied
+    fn build_record(record_number: u32, in_use: bool, name: &str, data_attr: &[u8]) -> Vec<u8> {
+        let mut record = vec![0u8; DEFAULT_RECORD_SIZE];
+        record[..4].copy_from_slice(b"FILE");
+        record[FR_USA_OFFSET..FR_USA_OFFSET + 2].copy_from_slice(&48u16.to_le_bytes());
+        record[FR_USA_COUNT..FR_USA_COUNT + 2].copy_from_slice(&3u16.to_le_bytes());
+        record[FR_FIRST_ATTRIBUTE..FR_FIRST_ATTRIBUTE + 2].copy_from_slice(&56u16.to_le_bytes());
+        let flags = if in_use { FR_FLAG_IN_USE } else { 0 };
+        record[FR_FLAGS..FR_FLAGS + 2].copy_from_slice(&flags.to_le_bytes());
+        record[FR_RECORD_NUMBER..FR_RECORD_NUMBER + 4].copy_from_slice(&record_number.to_le_bytes());
+
+        // FILE_NAME (resident)
+        let units: Vec<u16> = name.encode_utf16().collect();
+        let value_len = FN_NAME + units.len() * 2;
+        let fn_len = (24 + value_len + 7) & !7;
+        let mut pos = 56;
+        record[pos..pos + 4].copy_from_slice(&ATTR_FILE_NAME.to_le_bytes());
+        record[pos + 4..pos + 8].copy_from_slice(&(fn_len as u32).to_le_bytes());
+        record[pos + AT_VALUE_LENGTH..pos + AT_VALUE_LENGTH + 4].copy_from_slice(&(value_len as u32).to_le_bytes());
+        record[pos + AT_VALUE_OFFSET..pos + AT_VALUE_OFFSET + 2].copy_from_slice(&24u16.to_le_bytes());
+        let value = pos + 24;
+        record[value + FN_PARENT..value + FN_PARENT + 8].copy_from_slice(&5u64.to_le_bytes());
+        record[value + FN_NAME_LENGTH] = units.len() as u8;
+        record[value + FN_NAMESPACE] = 1;
+        for (i, unit) in units.iter().enumerate() {
+            let at = value + FN_NAME + i * 2;
+            record[at..at + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        pos += fn_len;
+
+        record[pos..pos + data_attr.len()].copy_from_slice(data_attr);
+        pos += data_attr.len();
+        record[pos..pos + 4].copy_from_slice(&ATTR_END.to_le_bytes());
+        record[FR_USED_SIZE..FR_USED_SIZE + 4].copy_from_slice(&((pos + 8) as u32).to_le_bytes());
+
+        // Update sequence: USN 0x0001, original sector tails saved in the array
+        record[48..50].copy_from_slice(&1u16.to_le_bytes());
+        for i in 1..3 {
+            let tail = i * FIXUP_STRIDE - 2;
+            let saved = [record[tail], record[tail + 1]];
+            record[48 + i * 2..50 + i * 2].copy_from_slice(&saved);
+            record[tail..tail + 2].copy_from_slice(&1u16.to_le_bytes());
+        }
+        record
+    }
+
+    fn non_resident_data(real_size: u64, runs: &[u8]) -> Vec<u8> {
+        let length = (64 + runs.len() + 1 + 7) & !7;
+        let mut attr = vec![0u8; length];
+        attr[..4].copy_from_slice(&ATTR_DATA.to_le_bytes());
+        attr[4..8].copy_from_slice(&(length as u32).to_le_bytes());
+        attr[AT_NON_RESIDENT] = 1;
+        attr[AT_RUNS_OFFSET..AT_RUNS_OFFSET + 2].copy_from_slice(&64u16.to_le_bytes());
+        attr[AT_REAL_SIZE..AT_REAL_SIZE + 8].copy_from_slice(&real_size.to_le_bytes());
+        attr[64..64 + runs.len()].copy_from_slice(runs);
+        attr
+    }
+
+    #[test]
+    fn test_decode_data_runs() {
+        // 2 clusters at 0x10, sparse 1 cluster, 3 clusters at 0x10 - 4 = 0x0C
+        let runs = [0x11, 0x02, 0x10, 0x01, 0x01, 0x11, 0x03, 0xFC, 0x00];
+        let decoded = decode_data_runs(&runs).unwrap();
+        assert_eq!(
+            decoded,
+            vec![
+                DataRun { lcn: Some(0x10), length: 2 },
+                DataRun { lcn: None, length: 1 },
+                DataRun { lcn: Some(0x0C), length: 3 },
+            ]
+        );
+        assert!(decode_data_runs(&[0x11, 0x02]).is_none());
+    }
+
+    #[test]
+    fn test_parse_deleted_resident_record() {
+        let mut data_attr = vec![0u8; 32];
+        data_attr[..4].copy_from_slice(&ATTR_DATA.to_le_bytes());
+        data_attr[4..8].copy_from_slice(&32u32.to_le_bytes());
+        data_attr[AT_VALUE_LENGTH..AT_VALUE_LENGTH + 4].copy_from_slice(&5u32.to_le_bytes());
+        data_attr[AT_VALUE_OFFSET..AT_VALUE_OFFSET + 2].copy_from_slice(&24u16.to_le_bytes());
+        data_attr[24..29].copy_from_slice(b"hello");
+        let record = build_record(42, false, "notes.txt", &data_attr);
+
+        let entry = parse_file_record(&record, 0x4000, DEFAULT_RECORD_SIZE).expect("record should parse");
+        assert!(entry.is_deleted);
+        assert_eq!(entry.record_number, 42);
+        assert_eq!(entry.filename, "notes.txt");
+        assert_eq!(entry.parent_record, Some(5));
+        assert_eq!(entry.size, 5);
+        assert_eq!(entry.resident_data.as_deref(), Some(&b"hello"[..]));
+
+        // Torn write: sector tail no longer matches the update sequence number
+        let mut torn = record.clone();
+        torn[FIXUP_STRIDE - 2] ^= 0xFF;
+        assert!(parse_file_record(&torn, 0, DEFAULT_RECORD_SIZE).is_none());
+    }
+
+    #[test]
+    fn test_read_mft_and_extract() {
+        let mut image = vec![0u8; 32 * CLUSTER];
+        image[..BOOT_SECTOR_SIZE].copy_from_slice(&build_boot_sector());
+        let params = find_boot_sector(&image).expect("boot sector should be found");
+        assert_eq!(params.cluster_size, CLUSTER as u64);
+        assert_eq!(params.mft_offset, 4 * CLUSTER as u64);
+
+        // $MFT: 2 records at cluster 4; file: 1500 bytes in clusters 20 and 10
+        let mft = build_record(0, true, "$MFT", &non_resident_data(2048, &[0x11, 0x02, 0x04, 0x00]));
+        let file = build_record(1, false, "video.json", &non_resident_data(1500, &[0x11, 0x01, 0x14, 0x11, 0x01, 0xF6, 0x00]));
+        image[4 * CLUSTER..5 * CLUSTER].copy_from_slice(&mft);
+        image[5 * CLUSTER..6 * CLUSTER].copy_from_slice(&file);
+        image[20 * CLUSTER..21 * CLUSTER].fill(b'A');
+        image[10 * CLUSTER..11 * CLUSTER].fill(b'B');
+
+        let mut entries = read_mft(&image, &params);
+        assert_eq!(entries.len(), 2);
+        populate_data_offsets(&mut entries, &params);
+
+        let entry = &entries[1];
+        assert!(entry.is_deleted);
+        assert_eq!(entry.first_cluster(), Some(20));
+        assert_eq!(entry.data_offset, Some(20 * CLUSTER as u64));
+        assert_eq!(entry.to_fragment_metadata().filename.as_deref(), Some("video.json"));
+
+        let content = extract_file_content(&image, &params, entry);
+        assert_eq!(content.len(), 1500);
+        assert!(content[..CLUSTER].iter().all(|&b| b == b'A'));
+        assert!(content[CLUSTER..].iter().all(|&b| b == b'B'));
+
+        let mut streamed = Vec::new();
+        assert_eq!(extract_file_to_writer(&image, &params, entry, &mut streamed).unwrap(), 1500);
+        assert_eq!(streamed, content);
+
+        assert_eq!(scan_for_records(&image, 0, DEFAULT_RECORD_SIZE).len(), 2);
+    }
+}
@@ -137,6 +137,25 @@ fn parse_cpu_list(cpu_list: &str) -> Vec<usize> {
     cpus
 }
 
+/// CPU list in sysfs notation, the inverse of `parse_cpu_list`
+pub fn format_cpu_list(cpus: &[usize]) -> String {
+    let mut sorted = cpus.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for cpu in sorted {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == cpu => *end = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 /// Parse memory size from meminfo
 fn parse_memory_size(meminfo: &str) -> Option<u64> {
     for line in meminfo.lines() {
@@ -187,5 +206,6 @@ mod tests {
     fn test_parse_cpu_list() {
         assert_eq!(parse_cpu_list("0-3,8-11"), vec![0, 1, 2, 3, 8, 9, 10, 11]);
         assert_eq!(parse_cpu_list("0,2,4"), vec![0, 2, 4]);
+        assert_eq!(format_cpu_list(&parse_cpu_list("0-3,8-11,13")), "0-3,8-11,13");
     }
 }
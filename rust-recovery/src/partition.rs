@@ -0,0 +1,369 @@
+//! MBR and GPT partition tables
+//!
+//! Scans treat the image as one flat range; the partition table says which
+//! volumes it holds. Each partition's filesystem (exFAT, NTFS, FAT32) is
+//! detected from the boot sector at its start, so the metadata pass reads the
+//! right one, and `--partition N` restricts the scan to a single partition.
+//!
+//! Partitions are numbered from 1 in table order. Logical partitions inside
+//! an MBR extended partition are numbered from 5, as Linux does. An MBR
+//! holding a protective (0xEE) entry defers to the GPT behind it. The GPT
+//! header and entry CRCs are not checked, so a table with a damaged checksum
+//! is still listed.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{exfat, fat32, ntfs};
+
+/// Sector size of MBR addressing; GPT is also tried with 4 KiB sectors
+pub const SECTOR_SIZE: u64 = 512;
+/// Bytes read at a partition start to detect its filesystem
+pub const BOOT_SECTOR_READ: usize = 512;
+
+const MBR_ENTRIES: usize = 446;
+const MBR_ENTRY_SIZE: usize = 16;
+const MBR_SIGNATURE: usize = 510;
+const MBR_PROTECTIVE: u8 = 0xEE;
+const MBR_EXTENDED: [u8; 3] = [0x05, 0x0F, 0x85];
+/// EBRs followed in an extended partition; more means a loop
+const MAX_LOGICAL_PARTITIONS: usize = 128;
+
+const GPT_SIGNATURE: &[u8] = b"EFI PART";
+const GPT_ENTRIES_LBA: usize = 72;
+const GPT_ENTRY_COUNT: usize = 80;
+const GPT_ENTRY_SIZE: usize = 84;
+const GPT_MIN_ENTRY_SIZE: usize = 128;
+/// Entries read from a GPT; the default table has 128
+const GPT_MAX_ENTRIES: usize = 1024;
+const GPT_NAME: usize = 56;
+const GPT_NAME_LEN: usize = 72;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionScheme {
+    Mbr,
+    Gpt,
+}
+
+impl PartitionScheme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Mbr => "MBR",
+            Self::Gpt => "GPT",
+        }
+    }
+}
+
+/// Filesystem whose boot sector starts a partition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filesystem {
+    ExFat,
+    Ntfs,
+    Fat32,
+}
+
+impl Filesystem {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ExFat => "exFAT",
+            Self::Ntfs => "NTFS",
+            Self::Fat32 => "FAT32",
+        }
+    }
+
+    /// Filesystem of the boot sector at the start of `data`
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if ntfs::parse_boot_sector_at(data, 0).is_some() {
+            Some(Self::Ntfs)
+        } else if exfat::parse_boot_sector_at(data, 0).is_some() {
+            Some(Self::ExFat)
+        } else if fat32::parse_boot_sector_at(data, 0).is_some() {
+            Some(Self::Fat32)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Partition {
+    /// Number given to `--partition`
+    pub number: u32,
+    /// Image offset of the first byte
+    pub start: u64,
+    /// Bytes, cut to the image end
+    pub size: u64,
+    /// MBR type byte (`0x07`) or GPT type GUID
+    pub type_id: String,
+    /// GPT partition name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub filesystem: Option<Filesystem>,
+}
+
+impl Partition {
+    pub fn end(&self) -> u64 {
+        self.start + self.size
+    }
+
+    pub fn size_mb(&self) -> f64 {
+        self.size as f64 / 1024.0 / 1024.0
+    }
+
+    /// One line for the console: number, range, size, filesystem and name
+    pub fn describe(&self) -> String {
+        format!(
+            "#{} 0x{:X}-0x{:X} ({:.1} MB) type {} {}{}",
+            self.number,
+            self.start,
+            self.end(),
+            self.size_mb(),
+            self.type_id,
+            self.filesystem.map_or("unknown filesystem", |fs| fs.name()),
+            self.name.as_deref().map(|name| format!(" \"{}\"", name)).unwrap_or_default()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartitionTable {
+    pub scheme: PartitionScheme,
+    pub partitions: Vec<Partition>,
+}
+
+impl PartitionTable {
+    /// Table of an image of `image_size` bytes, `None` when the image is not
+    /// partitioned (a bare volume, or no valid MBR). `read(offset, len)`
+    /// returns up to `len` image bytes at `offset`.
+    pub fn read(image_size: u64, read: impl Fn(u64, usize) -> Option<Vec<u8>>) -> Option<Self> {
+        let mbr = read(0, SECTOR_SIZE as usize)?;
+        if mbr.get(MBR_SIGNATURE..MBR_SIGNATURE + 2)? != [0x55, 0xAA] || Filesystem::detect(&mbr).is_some() {
+            return None;
+        }
+        let entries = mbr_entries(&mbr)?;
+
+        let (scheme, mut partitions) = if entries.iter().any(|entry| entry.kind == MBR_PROTECTIVE) {
+            (PartitionScheme::Gpt, gpt_partitions(&read)?)
+        } else {
+            (PartitionScheme::Mbr, mbr_partitions(&entries, &read))
+        };
+
+        partitions.retain_mut(|partition| {
+            partition.size = partition.size.min(image_size.saturating_sub(partition.start));
+            partition.size > 0
+        });
+        for partition in &mut partitions {
+            partition.filesystem = read(partition.start, BOOT_SECTOR_READ).and_then(|boot| Filesystem::detect(&boot));
+        }
+        Some(Self { scheme, partitions })
+    }
+
+    pub fn partition(&self, number: u32) -> Option<&Partition> {
+        self.partitions.iter().find(|partition| partition.number == number)
+    }
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+}
+
+fn read_u64_le(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+}
+
+/// Used entry of an MBR or EBR, in sectors relative to the table's base
+struct MbrEntry {
+    /// Table slot, from 1
+    slot: u32,
+    kind: u8,
+    first_sector: u64,
+    sectors: u64,
+}
+
+/// Used entries of the table in `sector`; `None` when a status byte or an
+/// entry is invalid (boot code of an unpartitioned disk)
+fn mbr_entries(sector: &[u8]) -> Option<Vec<MbrEntry>> {
+    let mut entries = Vec::new();
+    for (entry, slot) in sector.get(MBR_ENTRIES..MBR_SIGNATURE)?.chunks_exact(MBR_ENTRY_SIZE).zip(1..) {
+        if !matches!(entry[0], 0x00 | 0x80) {
+            return None;
+        }
+        let kind = entry[4];
+        if kind == 0 {
+            continue;
+        }
+        let first_sector = read_u32_le(entry, 8)? as u64;
+        let sectors = read_u32_le(entry, 12)? as u64;
+        if first_sector == 0 || sectors == 0 {
+            return None;
+        }
+        entries.push(MbrEntry { slot, kind, first_sector, sectors });
+    }
+    Some(entries)
+}
+
+/// Primary partitions numbered 1-4 by slot, then the logical partitions of
+/// the first extended partition from 5
+fn mbr_partitions(entries: &[MbrEntry], read: &impl Fn(u64, usize) -> Option<Vec<u8>>) -> Vec<Partition> {
+    let partition = |number, base: u64, entry: &MbrEntry| Partition {
+        number,
+        start: (base + entry.first_sector) * SECTOR_SIZE,
+        size: entry.sectors * SECTOR_SIZE,
+        type_id: format!("0x{:02X}", entry.kind),
+        name: None,
+        filesystem: None,
+    };
+
+    let mut partitions: Vec<Partition> = entries
+        .iter()
+        .filter(|entry| !MBR_EXTENDED.contains(&entry.kind))
+        .map(|entry| partition(entry.slot, 0, entry))
+        .collect();
+
+    // Each EBR holds a logical partition (relative to the EBR) and a link to
+    // the next EBR (relative to the extended partition)
+    let Some(extended) = entries.iter().find(|entry| MBR_EXTENDED.contains(&entry.kind)) else {
+        return partitions;
+    };
+    let mut ebr = extended.first_sector;
+    for number in (5..).take(MAX_LOGICAL_PARTITIONS) {
+        let Some(entries) = read(ebr * SECTOR_SIZE, SECTOR_SIZE as usize).as_deref().and_then(mbr_entries) else {
+            break;
+        };
+        if let Some(logical) = entries.iter().find(|entry| !MBR_EXTENDED.contains(&entry.kind)) {
+            partitions.push(partition(number, ebr, logical));
+        }
+        match entries.iter().find(|entry| MBR_EXTENDED.contains(&entry.kind)) {
+            Some(next) => ebr = extended.first_sector + next.first_sector,
+            None => break,
+        }
+    }
+    partitions
+}
+
+/// Partitions of the GPT at LBA 1, with 512-byte or 4 KiB sectors
+fn gpt_partitions(read: &impl Fn(u64, usize) -> Option<Vec<u8>>) -> Option<Vec<Partition>> {
+    let (lba_size, header) = [SECTOR_SIZE, 4096].into_iter().find_map(|lba_size| {
+        let header = read(lba_size, SECTOR_SIZE as usize)?;
+        (header.get(..8)? == GPT_SIGNATURE).then_some((lba_size, header))
+    })?;
+
+    let entries_lba = read_u64_le(&header, GPT_ENTRIES_LBA)?;
+    let entry_count = (read_u32_le(&header, GPT_ENTRY_COUNT)? as usize).min(GPT_MAX_ENTRIES);
+    let entry_size = read_u32_le(&header, GPT_ENTRY_SIZE)? as usize;
+    if entry_size < GPT_MIN_ENTRY_SIZE {
+        return None;
+    }
+    let entries = read(entries_lba.checked_mul(lba_size)?, entry_count * entry_size)?;
+
+    let partitions = entries
+        .chunks_exact(entry_size)
+        .zip(1..)
+        .filter(|(entry, _)| entry[..16].iter().any(|&b| b != 0))
+        .filter_map(|(entry, number)| {
+            let first_lba = read_u64_le(entry, 32)?;
+            let last_lba = read_u64_le(entry, 40)?;
+            let units: Vec<u16> = entry[GPT_NAME..GPT_NAME + GPT_NAME_LEN]
+                .chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .take_while(|&unit| unit != 0)
+                .collect();
+            let name = String::from_utf16_lossy(&units);
+            Some(Partition {
+                number,
+                start: first_lba.checked_mul(lba_size)?,
+                size: last_lba.checked_sub(first_lba)?.checked_add(1)?.checked_mul(lba_size)?,
+                type_id: format_guid(&entry[..16]),
+                name: (!name.is_empty()).then_some(name),
+                filesystem: None,
+            })
+        })
+        .collect();
+    Some(partitions)
+}
+
+/// GUID in its usual text form (the first three fields are little-endian)
+fn format_guid(bytes: &[u8]) -> String {
+    format!(
+        "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{}",
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+        bytes[8],
+        bytes[9],
+        bytes[10..16].iter().map(|b| format!("{:02X}", b)).collect::<String>()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(image: &[u8]) -> impl Fn(u64, usize) -> Option<Vec<u8>> + '_ {
+        |offset, len| {
+            let start = usize::try_from(offset).ok().filter(|&start| start < image.len())?;
+            Some(image[start..image.len().min(start + len)].to_vec())
+        }
+    }
+
+    fn mbr_entry(sector: &mut [u8], slot: usize, kind: u8, first_sector: u32, sectors: u32) {
+        let entry = MBR_ENTRIES + slot * MBR_ENTRY_SIZE;
+        sector[entry + 4] = kind;
+        sector[entry + 8..entry + 12].copy_from_slice(&first_sector.to_le_bytes());
+        sector[entry + 12..entry + 16].copy_from_slice(&sectors.to_le_bytes());
+        sector[MBR_SIGNATURE..MBR_SIGNATURE + 2].copy_from_slice(&[0x55, 0xAA]);
+    }
+
+    #[test]
+    fn test_mbr_and_gpt_tables() {
+        // Primary FAT32 at sector 8, extended (slot 3) at 32 with logical partitions at 34 and 50
+        let mut image = vec![0u8; 80 * 512];
+        mbr_entry(&mut image, 0, 0x0C, 8, 16);
+        mbr_entry(&mut image, 2, 0x0F, 32, 48);
+        mbr_entry(&mut image[32 * 512..], 0, 0x07, 2, 8);
+        mbr_entry(&mut image[32 * 512..], 1, 0x05, 16, 20);
+        mbr_entry(&mut image[48 * 512..], 0, 0x83, 2, 100);
+
+        let table = PartitionTable::read(image.len() as u64, reader(&image)).unwrap();
+        assert_eq!(table.scheme, PartitionScheme::Mbr);
+        let ranges: Vec<(u32, u64, u64)> = table.partitions.iter().map(|p| (p.number, p.start, p.end())).collect();
+        // The last logical partition is cut at the image end
+        assert_eq!(ranges, [(1, 8 * 512, 24 * 512), (5, 34 * 512, 42 * 512), (6, 50 * 512, 80 * 512)]);
+        assert_eq!(table.partition(5).unwrap().type_id, "0x07");
+
+        // Protective MBR and a GPT with one named partition at LBA 40
+        let mut image = vec![0u8; 64 * 512];
+        mbr_entry(&mut image, 0, MBR_PROTECTIVE, 1, 63);
+        image[512..520].copy_from_slice(GPT_SIGNATURE);
+        image[512 + GPT_ENTRIES_LBA..512 + GPT_ENTRIES_LBA + 8].copy_from_slice(&2u64.to_le_bytes());
+        image[512 + GPT_ENTRY_COUNT..512 + GPT_ENTRY_COUNT + 4].copy_from_slice(&128u32.to_le_bytes());
+        image[512 + GPT_ENTRY_SIZE..512 + GPT_ENTRY_SIZE + 4].copy_from_slice(&128u32.to_le_bytes());
+        let entry = &mut image[1024..1152];
+        entry[..16].copy_from_slice(&[0xA2, 0xA0, 0xD0, 0xEB, 0xE5, 0xB9, 0x33, 0x44, 0x87, 0xC0, 0x68, 0xB6, 0xB7, 0x26, 0x99, 0xC7]);
+        entry[32..40].copy_from_slice(&40u64.to_le_bytes());
+        entry[40..48].copy_from_slice(&59u64.to_le_bytes());
+        for (i, unit) in "Data".encode_utf16().enumerate() {
+            entry[GPT_NAME + 2 * i..GPT_NAME + 2 * i + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+
+        let table = PartitionTable::read(image.len() as u64, reader(&image)).unwrap();
+        assert_eq!(table.scheme, PartitionScheme::Gpt);
+        assert_eq!(table.partitions.len(), 1);
+        let partition = &table.partitions[0];
+        assert_eq!((partition.number, partition.start, partition.size), (1, 40 * 512, 20 * 512));
+        assert_eq!(partition.type_id, "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7");
+        assert_eq!(partition.name.as_deref(), Some("Data"));
+
+        // Boot code where the entries would be: not a partition table
+        let mut image = vec![0u8; 4 * 512];
+        image[MBR_ENTRIES] = 0x33;
+        image[MBR_SIGNATURE..MBR_SIGNATURE + 2].copy_from_slice(&[0x55, 0xAA]);
+        assert!(PartitionTable::read(image.len() as u64, reader(&image)).is_none());
+    }
+}
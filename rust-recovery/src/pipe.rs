@@ -0,0 +1,257 @@
+//! Images read from a pipe (`rust-recovery -`)
+//!
+//! `zcat disk.img.gz | rust-recovery -` scans an image that is never stored
+//! unpacked. A pipe is read once, front to back: the scanner asks for one
+//! window of chunks at a time (`fill`) and drops it once scanned (`release`),
+//! so memory holds a window, not the image. The bytes of hot fragments and
+//! carved files are copied to a spill file while they are still buffered
+//! (`retain`), and stream assembly reads them back from there after the scan.
+//! Any other byte behind the window can no longer be read.
+
+use crate::disk::read_exact_at;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+
+/// Bytes buffered when the pipe is opened: the image hash and the boot
+/// sector are read before the scan starts
+pub const PIPE_PREFETCH: u64 = 1024 * 1024;
+/// Spill file in the output directory; its `.tmp` suffix lets the next run
+/// clear one left by a killed scan
+pub const PIPE_SPILL_FILE: &str = "pipe_fragments.tmp";
+/// Largest single read from the pipe
+const PIPE_READ_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Bytes read from the pipe and not released yet
+struct Window {
+    /// Image offset of `data[0]`
+    start: u64,
+    data: Vec<u8>,
+    eof: bool,
+}
+
+impl Window {
+    fn end(&self) -> u64 {
+        self.start + self.data.len() as u64
+    }
+}
+
+/// Retained bytes, copied out of the window
+struct Spill {
+    path: PathBuf,
+    file: Option<File>,
+    len: u64,
+    /// Image offset -> (end, spill file offset) of disjoint ranges
+    extents: BTreeMap<u64, (u64, u64)>,
+}
+
+impl Spill {
+    /// Parts of `[start, end)` not spilled yet
+    fn gaps(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        let mut gaps = Vec::new();
+        let mut position = start;
+        if let Some((_, &(extent_end, _))) = self.extents.range(..=start).next_back() {
+            position = position.max(extent_end);
+        }
+        for (&extent_start, &(extent_end, _)) in self.extents.range(start..end) {
+            if extent_start > position {
+                gaps.push((position, extent_start));
+            }
+            position = position.max(extent_end);
+        }
+        if position < end {
+            gaps.push((position, end));
+        }
+        gaps
+    }
+
+    /// Spilled range holding `offset`: (end, spill file offset of `offset`)
+    fn find(&self, offset: u64) -> Option<(u64, u64)> {
+        let (&start, &(end, file_offset)) = self.extents.range(..=offset).next_back()?;
+        (offset < end).then_some((end, file_offset + offset - start))
+    }
+}
+
+/// Image streamed from a reader, addressed by offset from its first byte
+pub struct PipeImage {
+    reader: Mutex<Box<dyn Read + Send>>,
+    window: RwLock<Window>,
+    spill: Mutex<Spill>,
+}
+
+impl PipeImage {
+    /// Start reading `reader`; retained bytes go to `spill_path`, created on
+    /// first use and deleted with the image
+    pub fn new(reader: impl Read + Send + 'static, spill_path: PathBuf) -> io::Result<Self> {
+        let pipe = Self {
+            reader: Mutex::new(Box::new(reader)),
+            window: RwLock::new(Window { start: 0, data: Vec::new(), eof: false }),
+            spill: Mutex::new(Spill { path: spill_path, file: None, len: 0, extents: BTreeMap::new() }),
+        };
+        pipe.fill(PIPE_PREFETCH)?;
+        Ok(pipe)
+    }
+
+    /// Bytes read from the pipe so far: the image size once it ended
+    pub fn bytes_read(&self) -> u64 {
+        self.window.read().unwrap().end()
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.window.read().unwrap().eof
+    }
+
+    /// Read the pipe until `end` is buffered or the pipe ends; returns the bytes read so far
+    pub fn fill(&self, end: u64) -> io::Result<u64> {
+        let mut window = self.window.write().unwrap();
+        let mut reader = self.reader.lock().unwrap();
+        while !window.eof && window.end() < end {
+            let filled = window.data.len();
+            let want = (end - window.end()).min(PIPE_READ_SIZE);
+            // Decompressors return a few KB per read: append without zeroing `want` bytes each time
+            match reader.by_ref().take(want).read_to_end(&mut window.data) {
+                Ok(read) => window.eof = (read as u64) < want,
+                Err(e) => {
+                    window.data.truncate(filled);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(window.end())
+    }
+
+    /// Drop the buffered bytes before `offset`; fragments in them must have been retained
+    pub fn release(&self, offset: u64) {
+        let mut window = self.window.write().unwrap();
+        let released = offset.saturating_sub(window.start).min(window.data.len() as u64);
+        window.data.drain(..released as usize);
+        window.start += released;
+    }
+
+    /// Keep `[offset, offset + len)` readable after `release`. Bytes
+    /// released already are skipped.
+    pub fn retain(&self, offset: u64, len: u64) -> io::Result<()> {
+        let window = self.window.read().unwrap();
+        let start = offset.max(window.start);
+        let end = offset.saturating_add(len).min(window.end());
+        if start >= end {
+            return Ok(());
+        }
+
+        let mut spill = self.spill.lock().unwrap();
+        let gaps = spill.gaps(start, end);
+        if gaps.is_empty() {
+            return Ok(());
+        }
+        if spill.file.is_none() {
+            let file = File::options().read(true).write(true).create(true).truncate(true).open(&spill.path)?;
+            spill.file = Some(file);
+        }
+        for (gap_start, gap_end) in gaps {
+            let bytes = &window.data[(gap_start - window.start) as usize..(gap_end - window.start) as usize];
+            spill.file.as_mut().unwrap().write_all(bytes)?;
+            let file_offset = spill.len;
+            spill.extents.insert(gap_start, (gap_end, file_offset));
+            spill.len += bytes.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Fill `buf` from `offset`, from the window or the retained bytes; what
+    /// is in neither is zero-filled. Returns the number of zero-filled bytes.
+    pub fn read_tolerant(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let end = offset.saturating_add(buf.len() as u64);
+        if end > self.bytes_read() {
+            self.fill(end)?;
+        }
+
+        let window = self.window.read().unwrap();
+        let spill = self.spill.lock().unwrap();
+        let mut unreadable = 0;
+        let mut position = offset;
+        while position < end {
+            let at = (position - offset) as usize;
+            if (window.start..window.end()).contains(&position) {
+                let len = (end.min(window.end()) - position) as usize;
+                let from = (position - window.start) as usize;
+                buf[at..at + len].copy_from_slice(&window.data[from..from + len]);
+                position += len as u64;
+            } else if let (Some((extent_end, file_offset)), Some(file)) = (spill.find(position), spill.file.as_ref()) {
+                let len = (end.min(extent_end) - position) as usize;
+                read_exact_at(file, &mut buf[at..at + len], file_offset)?;
+                position += len as u64;
+            } else {
+                // Up to the next spilled range, the window or the end
+                let next_extent = spill.extents.range(position..).next().map_or(end, |(&start, _)| start);
+                let next_window = if window.start > position { window.start } else { end };
+                let len = (end.min(next_extent).min(next_window) - position) as usize;
+                buf[at..at + len].fill(0);
+                unreadable += len;
+                position += len as u64;
+            }
+        }
+        Ok(unreadable)
+    }
+
+    /// Like `read_tolerant`, but fails unless every byte is still readable
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        match self.read_tolerant(offset, buf)? {
+            0 => Ok(()),
+            missing => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} bytes at 0x{:X} were read from the pipe and not kept", missing, offset),
+            )),
+        }
+    }
+}
+
+impl Drop for PipeImage {
+    fn drop(&mut self) {
+        let spill = self.spill.get_mut().unwrap();
+        if spill.file.take().is_some() {
+            let _ = fs::remove_file(&spill.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipe_window_and_spill() {
+        let spill_path = std::env::temp_dir().join(format!("rust_recovery_pipe_{}.tmp", std::process::id()));
+        let image: Vec<u8> = (0..3 * PIPE_PREFETCH).map(|i| (i % 251) as u8).collect();
+        let pipe = PipeImage::new(io::Cursor::new(image.clone()), spill_path.clone()).unwrap();
+        assert_eq!(pipe.bytes_read(), PIPE_PREFETCH);
+
+        // Reading past the buffered end reads on
+        let mut buf = vec![0u8; 100];
+        pipe.read_at(PIPE_PREFETCH + 10, &mut buf).unwrap();
+        assert_eq!(buf, image[PIPE_PREFETCH as usize + 10..][..100]);
+
+        // Overlapping retained ranges are spilled once
+        pipe.retain(1000, 500).unwrap();
+        pipe.retain(1200, 600).unwrap();
+        assert_eq!(pipe.spill.lock().unwrap().len, 800);
+        pipe.release(PIPE_PREFETCH);
+
+        let mut buf = vec![0u8; 800];
+        pipe.read_at(1000, &mut buf).unwrap();
+        assert_eq!(buf, image[1000..1800]);
+        // Released and not retained: zero-filled, and an error for read_at
+        let mut buf = vec![1u8; 300];
+        assert_eq!(pipe.read_tolerant(1700, &mut buf).unwrap(), 200);
+        assert_eq!(&buf[..100], &image[1700..1800]);
+        assert!(buf[100..].iter().all(|&b| b == 0));
+        assert!(pipe.read_at(2000, &mut buf).is_err());
+
+        assert_eq!(pipe.fill(u64::MAX).unwrap(), 3 * PIPE_PREFETCH);
+        assert!(pipe.is_eof());
+        assert!(spill_path.exists());
+        drop(pipe);
+        assert!(!spill_path.exists());
+    }
+}
@@ -0,0 +1,386 @@
+//! Machine-readable scan progress (`progress.json` in the output directory)
+//!
+//! Wrapper scripts and dashboards poll this file instead of attaching to the TUI.
+//! Writes are throttled to one per interval and go through tmp + rename, so a
+//! reader never sees a half-written file.
+//!
+//! Chunks finish out of order and overlap their neighbours: `position` is the
+//! resume point (every chunk on the scanned side of it is done) and
+//! `bytes_scanned` counts each image byte once, however many chunks read it.
+//!
+//! Every write can also go as one NDJSON line to a `ProgressStream`
+//! (`--progress-stream PATH`): a FIFO, a Unix socket or a regular file. The
+//! stream never blocks the scan: without a reader the line is dropped and
+//! the next write tries to connect again.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::types::Offset;
+
+pub const PROGRESS_FILE_NAME: &str = "progress.json";
+pub const DEFAULT_WRITE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Pipeline stage reported to external tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressState {
+    Starting,
+    Scanning,
+    Assembling,
+    Reporting,
+    Completed,
+    /// Cancelled; the files found up to then were saved and reported
+    Cancelled,
+    Failed,
+}
+
+/// Contents of `progress.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressSnapshot {
+    pub state: ProgressState,
+    /// Scanned image; tells apart the lines of several images on one stream
+    #[serde(default)]
+    pub image: String,
+    /// Every chunk on the scanned side of this offset has completed
+    pub position: Offset,
+    /// Image bytes scanned, chunk overlap counted once
+    pub bytes_scanned: u64,
+    pub image_size: u64,
+    /// Scanned share of the image, 0.0 - 100.0
+    pub coverage_percent: f64,
+    /// Average speed since start, MB/s
+    pub speed_mbps: f64,
+    /// Seconds left at the average speed (None until something was scanned)
+    #[serde(default)]
+    pub eta_secs: Option<f64>,
+    pub fragments_found: u64,
+    pub files_recovered: u64,
+    pub elapsed_secs: f64,
+    /// Local time of this write
+    pub updated_at: String,
+}
+
+/// Tracks counters and periodically persists them to `progress.json`
+#[derive(Debug)]
+pub struct ProgressReporter {
+    path: PathBuf,
+    started: Instant,
+    interval: Duration,
+    last_write: Option<Instant>,
+    snapshot: ProgressSnapshot,
+    stream: Option<ProgressStream>,
+    /// Scanned ranges, start -> end, merged when they touch
+    scanned: BTreeMap<u64, u64>,
+}
+
+impl ProgressReporter {
+    pub fn new(output_dir: &Path, image_size: u64) -> Self {
+        Self {
+            path: output_dir.join(PROGRESS_FILE_NAME),
+            started: Instant::now(),
+            interval: DEFAULT_WRITE_INTERVAL,
+            last_write: None,
+            snapshot: ProgressSnapshot {
+                state: ProgressState::Starting,
+                position: Offset::new(0),
+                bytes_scanned: 0,
+                image_size,
+                coverage_percent: 0.0,
+                speed_mbps: 0.0,
+                eta_secs: None,
+                image: String::new(),
+                fragments_found: 0,
+                files_recovered: 0,
+                elapsed_secs: 0.0,
+                updated_at: String::new(),
+            },
+            stream: None,
+            scanned: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_image(mut self, image: &Path) -> Self {
+        self.snapshot.image = image.to_string_lossy().to_string();
+        self
+    }
+
+    /// Also send every write as an NDJSON line to `stream`
+    pub fn with_stream(mut self, stream: ProgressStream) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    /// Minimum time between throttled writes
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn snapshot(&self) -> &ProgressSnapshot {
+        &self.snapshot
+    }
+
+    /// Count `[offset, offset + len)` as scanned; bytes already counted (the
+    /// overlap with a neighbouring chunk, a rescan) are not counted again
+    pub fn add_scanned(&mut self, offset: Offset, len: u64) {
+        let mut start = offset.as_u64();
+        let mut end = start.saturating_add(len);
+        if start >= end {
+            return;
+        }
+        // Absorb the ranges that overlap or touch `[start, end)`
+        if let Some((&before, &before_end)) = self.scanned.range(..=start).next_back() {
+            if before_end >= start {
+                start = before;
+                end = end.max(before_end);
+            }
+        }
+        let touching: Vec<(u64, u64)> = self.scanned.range(start..=end).map(|(&s, &e)| (s, e)).collect();
+        for (range_start, range_end) in touching {
+            self.scanned.remove(&range_start);
+            self.snapshot.bytes_scanned -= range_end - range_start;
+            end = end.max(range_end);
+        }
+        self.scanned.insert(start, end);
+        self.snapshot.bytes_scanned += end - start;
+    }
+
+    /// Resume point reported by the scanner
+    pub fn set_position(&mut self, position: Offset) {
+        self.snapshot.position = position;
+    }
+
+    pub fn fragment_found(&mut self) {
+        self.snapshot.fragments_found += 1;
+    }
+
+    pub fn file_recovered(&mut self) {
+        self.snapshot.files_recovered += 1;
+    }
+
+    /// Change stage and write immediately (stage changes are never throttled)
+    pub fn set_state(&mut self, state: ProgressState) -> std::io::Result<()> {
+        self.snapshot.state = state;
+        self.flush()
+    }
+
+    /// Write if the interval has elapsed since the last write; returns whether it wrote
+    pub fn tick(&mut self) -> std::io::Result<bool> {
+        let due = match self.last_write {
+            Some(last) => last.elapsed() >= self.interval,
+            None => true,
+        };
+        if due {
+            self.flush()?;
+        }
+        Ok(due)
+    }
+
+    /// Write the current snapshot now
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.refresh_derived();
+
+        let serialized = serde_json::to_vec_pretty(&self.snapshot)?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&serialized)?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+
+        if let Some(ref mut stream) = self.stream {
+            let mut line = serde_json::to_vec(&self.snapshot)?;
+            line.push(b'\n');
+            stream.send(&line);
+        }
+
+        self.last_write = Some(Instant::now());
+        Ok(())
+    }
+
+    fn refresh_derived(&mut self) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let snapshot = &mut self.snapshot;
+
+        snapshot.elapsed_secs = elapsed;
+        snapshot.coverage_percent = if snapshot.image_size > 0 {
+            (snapshot.bytes_scanned as f64 / snapshot.image_size as f64 * 100.0).min(100.0)
+        } else {
+            0.0
+        };
+        snapshot.speed_mbps = if elapsed > 0.0 {
+            snapshot.bytes_scanned as f64 / 1024.0 / 1024.0 / elapsed
+        } else {
+            0.0
+        };
+        snapshot.eta_secs = (snapshot.bytes_scanned > 0 && elapsed > 0.0).then(|| {
+            let remaining = snapshot.image_size.saturating_sub(snapshot.bytes_scanned);
+            remaining as f64 * elapsed / snapshot.bytes_scanned as f64
+        });
+        snapshot.updated_at = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    }
+}
+
+/// NDJSON progress sink; reconnects on the next line after the reader went away
+#[derive(Debug)]
+pub struct ProgressStream {
+    path: PathBuf,
+    sink: Option<Box<dyn WriteSink>>,
+}
+
+trait WriteSink: Write + Send + std::fmt::Debug {}
+impl<T: Write + Send + std::fmt::Debug> WriteSink for T {}
+
+impl ProgressStream {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), sink: None }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn connect(&self) -> Option<Box<dyn WriteSink>> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+            let kind = fs::metadata(&self.path).ok().map(|meta| meta.file_type());
+            if kind.is_some_and(|kind| kind.is_socket()) {
+                let socket = std::os::unix::net::UnixStream::connect(&self.path).ok()?;
+                socket.set_nonblocking(true).ok()?;
+                return Some(Box::new(socket));
+            }
+            if kind.is_some_and(|kind| kind.is_fifo()) {
+                // Fails (ENXIO) while no reader has the FIFO open
+                let fifo = OpenOptions::new().write(true).custom_flags(libc::O_NONBLOCK).open(&self.path).ok()?;
+                return Some(Box::new(fifo));
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&self.path).ok()?;
+        Some(Box::new(file))
+    }
+
+    /// Send one line; dropped when no reader is connected or the reader is
+    /// behind. Lines fit in one pipe write (PIPE_BUF), so readers never get
+    /// half a line from a FIFO; a socket cut mid-line is closed
+    pub fn send(&mut self, line: &[u8]) {
+        if self.sink.is_none() {
+            self.sink = self.connect();
+        }
+        let Some(sink) = self.sink.as_mut() else {
+            return;
+        };
+        match sink.write(line) {
+            Ok(written) if written == line.len() => {}
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            _ => self.sink = None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_dir() -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        dir.push(format!("rust_recovery_progress_{unique}"));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_progress_file_contents() {
+        let dir = temp_dir();
+        let mut reporter = ProgressReporter::new(&dir, 4096);
+        reporter.add_scanned(Offset::new(0), 1024);
+        reporter.set_position(Offset::new(1024));
+        reporter.fragment_found();
+        reporter.set_state(ProgressState::Scanning).unwrap();
+
+        let data = fs::read(dir.join(PROGRESS_FILE_NAME)).unwrap();
+        let snapshot: ProgressSnapshot = serde_json::from_slice(&data).unwrap();
+        assert_eq!(snapshot.state, ProgressState::Scanning);
+        assert_eq!(snapshot.position, Offset::new(1024));
+        assert_eq!(snapshot.fragments_found, 1);
+        assert_eq!(snapshot.coverage_percent, 25.0);
+    }
+
+    #[test]
+    fn test_out_of_order_chunks_counted_once() {
+        let dir = temp_dir();
+        let mut reporter = ProgressReporter::new(&dir, 8192);
+        // Chunks of 2048 bytes reading 512 bytes of overlap, completed out of order
+        for offset in [6144, 2048, 4096, 0] {
+            reporter.add_scanned(Offset::new(offset), (2048 + 512).min(8192 - offset));
+        }
+        assert_eq!(reporter.snapshot().bytes_scanned, 8192);
+        assert_eq!(reporter.scanned.len(), 1);
+
+        // A rescan inside the scanned range adds nothing
+        reporter.add_scanned(Offset::new(1024), 4096);
+        assert_eq!(reporter.snapshot().bytes_scanned, 8192);
+
+        // Disjoint ranges stay apart until a chunk between them joins them
+        let mut reporter = ProgressReporter::new(&dir, 8192);
+        reporter.add_scanned(Offset::new(4096), 2560);
+        reporter.add_scanned(Offset::new(0), 2560);
+        assert_eq!((reporter.snapshot().bytes_scanned, reporter.scanned.len()), (5120, 2));
+        reporter.add_scanned(Offset::new(2048), 2560);
+        assert_eq!((reporter.snapshot().bytes_scanned, reporter.scanned.len()), (6656, 1));
+        reporter.flush().unwrap();
+        assert_eq!(reporter.snapshot().coverage_percent, 6656.0 / 8192.0 * 100.0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_tick_is_throttled() {
+        let dir = temp_dir();
+        let mut reporter = ProgressReporter::new(&dir, 0).with_interval(Duration::from_secs(3600));
+        assert!(reporter.tick().unwrap());
+        assert!(!reporter.tick().unwrap());
+    }
+
+    #[test]
+    fn test_progress_stream_lines() {
+        let dir = temp_dir();
+        let stream_path = dir.join("progress.ndjson");
+        let mut reporter = ProgressReporter::new(&dir, 4096).with_stream(ProgressStream::new(&stream_path));
+        reporter.set_state(ProgressState::Scanning).unwrap();
+        reporter.add_scanned(Offset::new(0), 1024);
+        reporter.set_state(ProgressState::Completed).unwrap();
+
+        let text = fs::read_to_string(&stream_path).unwrap();
+        let lines: Vec<ProgressSnapshot> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].eta_secs, None);
+        assert_eq!(lines[1].state, ProgressState::Completed);
+        assert!(lines[1].eta_secs.is_some());
+
+        // A FIFO without a reader drops the line instead of blocking
+        #[cfg(unix)]
+        {
+            let fifo = dir.join("progress.fifo");
+            let path = std::ffi::CString::new(fifo.to_str().unwrap()).unwrap();
+            assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o600) }, 0);
+            let mut stream = ProgressStream::new(&fifo);
+            stream.send(b"{}\n");
+            assert!(stream.sink.is_none());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
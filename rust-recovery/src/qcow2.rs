@@ -0,0 +1,273 @@
+//! QCOW2 (QEMU copy-on-write, versions 2 and 3) image reader
+//!
+//! Guest offsets are translated through the two-level cluster table: the L1
+//! table (kept in memory) points to L2 tables whose entries hold the host
+//! offset of each cluster. Unallocated and zero clusters read as zeros;
+//! compressed clusters are raw deflate streams. Backing files, encryption
+//! and external data files are not supported.
+
+use crate::disk::read_exact_at;
+use crate::error::{RecoveryError, Result};
+use flate2::{Decompress, FlushDecompress};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Magic at the start of QCOW2 images ("QFI\xfb")
+pub const QCOW2_MAGIC: &[u8; 4] = b"QFI\xfb";
+
+/// Version 3 header size up to `header_length`
+const HEADER_SIZE: usize = 104;
+/// Host offset bits of L1 and standard L2 entries
+const OFFSET_MASK: u64 = 0x00ff_ffff_ffff_fe00;
+const COMPRESSED_FLAG: u64 = 1 << 62;
+/// Version 3: cluster reads as zeros
+const ZERO_FLAG: u64 = 1;
+/// Incompatible features changing the layout: external data file,
+/// non-deflate compression, extended L2 entries
+const UNSUPPORTED_FEATURES: u64 = 0b1_1100;
+const MIN_CLUSTER_BITS: u32 = 9;
+const MAX_CLUSTER_BITS: u32 = 21;
+
+/// QCOW2 image opened for random access by guest offset
+pub struct Qcow2Image {
+    file: File,
+    file_len: u64,
+    cluster_bits: u32,
+    size: u64,
+    l1: Vec<u64>,
+}
+
+impl Qcow2Image {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let invalid = |what: String| RecoveryError::Parse(format!("QCOW2: {}", what));
+
+        let mut header = [0u8; HEADER_SIZE];
+        let header_len = (file_len as usize).min(HEADER_SIZE);
+        if header_len < 72 {
+            return Err(invalid("truncated header".to_string()));
+        }
+        read_exact_at(&file, &mut header[..header_len], 0)?;
+        if &header[..4] != QCOW2_MAGIC {
+            return Err(invalid("bad magic".to_string()));
+        }
+
+        let version = be_u32(&header[4..]);
+        if version != 2 && version != 3 {
+            return Err(invalid(format!("version {} not supported", version)));
+        }
+        if be_u64(&header[8..]) != 0 {
+            return Err(invalid("images with a backing file are not supported".to_string()));
+        }
+        if be_u32(&header[32..]) != 0 {
+            return Err(invalid("encrypted images are not supported".to_string()));
+        }
+        if version == 3 && be_u64(&header[72..]) & UNSUPPORTED_FEATURES != 0 {
+            return Err(invalid("incompatible features not supported".to_string()));
+        }
+
+        let cluster_bits = be_u32(&header[20..]);
+        if !(MIN_CLUSTER_BITS..=MAX_CLUSTER_BITS).contains(&cluster_bits) {
+            return Err(invalid(format!("cluster_bits {} out of range", cluster_bits)));
+        }
+
+        let l1_size = be_u32(&header[36..]) as u64;
+        let l1_offset = be_u64(&header[40..]);
+        if l1_offset.saturating_add(l1_size * 8) > file_len {
+            return Err(invalid("L1 table past the end of the image".to_string()));
+        }
+        let mut raw = vec![0u8; l1_size as usize * 8];
+        read_exact_at(&file, &mut raw, l1_offset)?;
+        let l1 = raw.chunks_exact(8).map(be_u64).collect();
+
+        Ok(Self {
+            file,
+            file_len,
+            cluster_bits,
+            size: be_u64(&header[24..]),
+            l1,
+        })
+    }
+
+    /// Virtual disk size in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn cluster_size(&self) -> usize {
+        1 << self.cluster_bits
+    }
+
+    /// Fill `buf` with guest bytes from `offset`; a corrupt cluster is an `InvalidData` error
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        self.read_range(offset, buf, false).map(|_| ())
+    }
+
+    /// Like `read_at`, but corrupt clusters are zero-filled.
+    /// Returns the number of zero-filled bytes.
+    pub fn read_tolerant(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_range(offset, buf, true)
+    }
+
+    fn read_range(&self, offset: u64, buf: &mut [u8], tolerant: bool) -> io::Result<usize> {
+        if offset.saturating_add(buf.len() as u64) > self.size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let cluster_size = self.cluster_size();
+        let mut scratch = Vec::new();
+        let mut done = 0;
+        let mut unreadable = 0;
+        while done < buf.len() {
+            let position = offset + done as u64;
+            let within = (position & (cluster_size as u64 - 1)) as usize;
+            let take = (cluster_size - within).min(buf.len() - done);
+            let out = &mut buf[done..done + take];
+
+            match self.read_cluster(position >> self.cluster_bits, within, out, &mut scratch) {
+                Ok(()) => {}
+                Err(e) if tolerant && e.kind() == io::ErrorKind::InvalidData => {
+                    out.fill(0);
+                    unreadable += take;
+                }
+                Err(e) => return Err(e),
+            }
+            done += take;
+        }
+        Ok(unreadable)
+    }
+
+    /// Read `out.len()` bytes at `within` of guest cluster `index`
+    fn read_cluster(&self, index: u64, within: usize, out: &mut [u8], scratch: &mut Vec<u8>) -> io::Result<()> {
+        let corrupt = |what: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("QCOW2 cluster {} {}", index, what))
+        };
+
+        let l2_bits = self.cluster_bits - 3;
+        let l1_entry = self.l1.get((index >> l2_bits) as usize).copied().unwrap_or(0);
+        let l2_offset = l1_entry & OFFSET_MASK;
+        if l2_offset == 0 {
+            out.fill(0);
+            return Ok(());
+        }
+
+        let l2_index = index & ((1 << l2_bits) - 1);
+        let entry_offset = l2_offset + l2_index * 8;
+        if entry_offset + 8 > self.file_len {
+            return Err(corrupt("has its L2 table past the end of the image"));
+        }
+        let mut entry = [0u8; 8];
+        read_exact_at(&self.file, &mut entry, entry_offset)?;
+        let entry = be_u64(&entry);
+
+        if entry & COMPRESSED_FLAG != 0 {
+            // Host offset below bit x, additional 512-byte sectors above it
+            let x = 62 - (self.cluster_bits - 8);
+            let host = entry & ((1 << x) - 1);
+            let sectors = (entry >> x) & ((1 << (self.cluster_bits - 8)) - 1);
+            let len = ((sectors + 1) * 512 - (host & 511)).min(self.file_len.saturating_sub(host));
+
+            scratch.resize(len as usize, 0);
+            read_exact_at(&self.file, scratch, host)?;
+            let mut cluster = vec![0u8; self.cluster_size()];
+            let mut inflater = Decompress::new(false);
+            match inflater.decompress(scratch, &mut cluster, FlushDecompress::Finish) {
+                Ok(_) if inflater.total_out() as usize == cluster.len() => {}
+                _ => return Err(corrupt("does not decompress")),
+            }
+            out.copy_from_slice(&cluster[within..within + out.len()]);
+            return Ok(());
+        }
+
+        let host = entry & OFFSET_MASK;
+        if host == 0 || entry & ZERO_FLAG != 0 {
+            out.fill(0);
+            return Ok(());
+        }
+        if host + (within + out.len()) as u64 > self.file_len {
+            return Err(corrupt("points past the end of the image"));
+        }
+        read_exact_at(&self.file, out, host + within as u64)
+    }
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn be_u64(bytes: &[u8]) -> u64 {
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&bytes[..8]);
+    u64::from_be_bytes(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// 4-cluster guest (512-byte clusters): standard, unallocated, compressed, zero
+    fn build_qcow2(guest: &[u8]) -> Vec<u8> {
+        const CLUSTER: usize = 512;
+        let mut image = vec![0u8; 4 * CLUSTER];
+        image[..4].copy_from_slice(QCOW2_MAGIC);
+        image[4..8].copy_from_slice(&3u32.to_be_bytes());
+        image[20..24].copy_from_slice(&9u32.to_be_bytes());
+        image[24..32].copy_from_slice(&(guest.len() as u64).to_be_bytes());
+        image[36..40].copy_from_slice(&1u32.to_be_bytes());
+        image[40..48].copy_from_slice(&(CLUSTER as u64).to_be_bytes());
+        image[100..104].copy_from_slice(&(HEADER_SIZE as u32).to_be_bytes());
+
+        // L1 at cluster 1 -> L2 at cluster 2; data from cluster 3 on
+        image[CLUSTER..CLUSTER + 8].copy_from_slice(&(2 * CLUSTER as u64).to_be_bytes());
+        let mut l2 = [0u64; 4];
+        l2[0] = 3 * CLUSTER as u64;
+        image[3 * CLUSTER..].copy_from_slice(&guest[..CLUSTER]);
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&guest[2 * CLUSTER..3 * CLUSTER]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let host = image.len() as u64;
+        let sectors = (compressed.len() as u64).div_ceil(512) - 1;
+        l2[2] = COMPRESSED_FLAG | (sectors << 61) | host;
+        image.extend_from_slice(&compressed);
+        l2[3] = ZERO_FLAG;
+
+        for (i, entry) in l2.iter().enumerate() {
+            image[2 * CLUSTER + i * 8..2 * CLUSTER + i * 8 + 8].copy_from_slice(&entry.to_be_bytes());
+        }
+        image
+    }
+
+    #[test]
+    fn test_read_qcow2_clusters() {
+        let mut guest: Vec<u8> = (0..2048).map(|i| (i % 251) as u8).collect();
+        guest[512..1024].fill(0);
+        guest[1536..].fill(0);
+
+        let mut path = std::env::temp_dir();
+        let unique = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        path.push(format!("rust_recovery_qcow2_{unique}.qcow2"));
+        std::fs::write(&path, build_qcow2(&guest)).unwrap();
+
+        let image = Qcow2Image::open(&path).unwrap();
+        assert_eq!(image.size(), 2048);
+        let mut buf = vec![0xAAu8; 2048];
+        image.read_at(0, &mut buf).unwrap();
+        assert_eq!(buf, guest);
+
+        let mut tail = [0u8; 100];
+        image.read_at(1000, &mut tail).unwrap();
+        assert_eq!(tail[..], guest[1000..1100]);
+        assert!(image.read_at(2000, &mut tail).is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
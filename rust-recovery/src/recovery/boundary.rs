@@ -0,0 +1,154 @@
+//! Logical end of assembled text files
+//!
+//! The last fragment of a stream usually runs on into the next cluster, so
+//! a recovered file ends in bytes of whatever was stored there. These scans
+//! find where the document itself ends; `FileTypeRegistry::trim` cuts the
+//! rest. Content that never reaches an end (a truncated document) is kept
+//! whole.
+
+/// End of the last complete top-level JSON value: values are followed
+/// while only whitespace or commas separate them (JSON Lines, concatenated
+/// documents). Strings and escapes are skipped, bracket kinds are not matched
+pub fn json_end(data: &[u8]) -> Option<usize> {
+    let start = data.iter().position(|&b| b == b'{' || b == b'[')?;
+    let mut end = value_end(data, start)?;
+    loop {
+        let next = data[end..].iter().position(|&b| !(b.is_ascii_whitespace() || b == b','));
+        match next.map(|skip| end + skip) {
+            Some(start) if data[start] == b'{' || data[start] == b'[' => match value_end(data, start) {
+                Some(value_end) => end = value_end,
+                None => return Some(end),
+            },
+            _ => return Some(end),
+        }
+    }
+}
+
+/// End of the bracketed value opening at `start` (None when it never closes)
+fn value_end(data: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, &byte) in data.iter().enumerate().skip(start) {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// End of the first `</html>` (ASCII case-insensitive), with the line break after it
+pub fn html_end(data: &[u8]) -> Option<usize> {
+    const CLOSING: &[u8] = b"</html>";
+    let at = data.windows(CLOSING.len()).position(|window| window.eq_ignore_ascii_case(CLOSING))?;
+    let end = at + CLOSING.len();
+    let line_break = match &data[end..] {
+        [b'\r', b'\n', ..] => 2,
+        [b'\n', ..] => 1,
+        _ => 0,
+    };
+    Some(end + line_break)
+}
+
+/// End of the last line-terminated CSV row with as many fields as the
+/// header; quoted fields may hold commas and line breaks
+pub fn csv_end(data: &[u8]) -> Option<usize> {
+    let mut rows = csv_rows(data).filter(|row| row.terminated);
+    let header = rows.next()?;
+    let mut end = header.end;
+    for row in rows {
+        if row.fields == header.fields {
+            end = row.end;
+        }
+    }
+    Some(end)
+}
+
+/// One CSV row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CsvRow {
+    pub fields: usize,
+    /// End after the line break
+    pub end: usize,
+    /// False for a last row without a line break (or inside an open quote)
+    pub terminated: bool,
+}
+
+/// Rows of `data`, the last one unterminated when the data does not end in
+/// a line break (whitespace after the last line break is no row)
+pub(crate) fn csv_rows(data: &[u8]) -> impl Iterator<Item = CsvRow> + '_ {
+    CsvRows { data, position: 0 }
+}
+
+struct CsvRows<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Iterator for CsvRows<'_> {
+    type Item = CsvRow;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.position;
+        if self.data[start..].iter().all(u8::is_ascii_whitespace) {
+            return None;
+        }
+        let mut fields = 1;
+        let mut quoted = false;
+        for index in start..self.data.len() {
+            match self.data[index] {
+                b'"' => quoted = !quoted,
+                b',' if !quoted => fields += 1,
+                b'\n' if !quoted => {
+                    self.position = index + 1;
+                    return Some(CsvRow { fields, end: index + 1, terminated: true });
+                }
+                _ => {}
+            }
+        }
+        self.position = self.data.len();
+        Some(CsvRow { fields, end: self.data.len(), terminated: false })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logical_ends() {
+        let json = b"{\"title\": \"a } in \\\" text\", \"v\": [1, {\"b\": 2}]}\n\x1a garbage}";
+        assert_eq!(json_end(json), Some(47));
+        let lines = b"{\"a\": 1}\n{\"a\": 2}\n{\"a\": 3";
+        assert_eq!(json_end(lines), Some(17));
+        assert_eq!(json_end(b"[1, [2, 3]], [4]]]"), Some(16));
+        assert_eq!(json_end(b"{\"open\": [1, 2"), None);
+        assert_eq!(json_end(b"no json"), None);
+
+        let html = b"<html><body>x</body></HTML>\r\nPK\x03\x04 next file";
+        assert_eq!(html_end(html), Some(29));
+        assert_eq!(html_end(b"<html><body>cut"), None);
+
+        let csv = b"id,title,url\n1,\"Hello, world\",http://a\n2,\"multi\nline\",http://b\n3,cut";
+        assert_eq!(csv_end(csv), Some(63));
+        assert_eq!(csv_end(b"id,title\n1,a\nbinary junk without commas\n"), Some(13));
+        assert_eq!(csv_end(b"no header line"), None);
+    }
+}
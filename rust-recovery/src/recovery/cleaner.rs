@@ -1,15 +1,7 @@
 use std::borrow::Cow;
 
-/// Clean recovered file content based on file type
-pub fn clean_file_content<'a>(data: &'a [u8], file_type: &str) -> Cow<'a, [u8]> {
-    match file_type {
-        "txt" | "json" | "html" | "css" | "js" | "xml" | "md" => clean_text_content(data),
-        _ => Cow::Borrowed(data),
-    }
-}
-
 /// Clean text content by removing null bytes and non-printable characters
-fn clean_text_content(data: &[u8]) -> Cow<'_, [u8]> {
+pub fn clean_text_content(data: &[u8]) -> Cow<'_, [u8]> {
     let needs_cleaning = data.iter().any(|&b| b == 0 || (b < 32 && b != b'\n' && b != b'\r' && b != b'\t'));
 
     if !needs_cleaning {
@@ -30,3 +22,81 @@ fn clean_text_content(data: &[u8]) -> Cow<'_, [u8]> {
 
     Cow::Owned(cleaned)
 }
+
+/// Names accepted by `--utf8`
+pub const UTF8_MODE_NAMES: &[&str] = &["keep", "replace", "strict"];
+
+/// What happens to invalid UTF-8 in recovered text files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Utf8Mode {
+    /// Bytes written as recovered
+    #[default]
+    Keep,
+    /// Every invalid sequence becomes U+FFFD
+    Replace,
+    /// Invalid sequences are dropped
+    Strict,
+}
+
+impl Utf8Mode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "keep" => Some(Self::Keep),
+            "replace" => Some(Self::Replace),
+            "strict" => Some(Self::Strict),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Replace => "replace",
+            Self::Strict => "strict",
+        }
+    }
+}
+
+/// Repair invalid UTF-8 (a sequence cut at a fragment boundary, junk between
+/// fragments) as `mode` says; returns the content and the invalid bytes
+/// replaced or dropped
+pub fn normalize_utf8(data: &[u8], mode: Utf8Mode) -> (Cow<'_, [u8]>, usize) {
+    if mode == Utf8Mode::Keep || std::str::from_utf8(data).is_ok() {
+        return (Cow::Borrowed(data), 0);
+    }
+    let mut repaired = Vec::with_capacity(data.len());
+    let mut touched = 0;
+    for chunk in data.utf8_chunks() {
+        repaired.extend_from_slice(chunk.valid().as_bytes());
+        if !chunk.invalid().is_empty() {
+            touched += chunk.invalid().len();
+            if mode == Utf8Mode::Replace {
+                repaired.extend_from_slice(char::REPLACEMENT_CHARACTER.encode_utf8(&mut [0; 3]).as_bytes());
+            }
+        }
+    }
+    (Cow::Owned(repaired), touched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_utf8_modes() {
+        // Cyrillic "при" cut after the first byte of "и", stray 0xFF
+        let data = b"\xD0\xBF\xD1\x80\xD0 ok \xFF\xFE end";
+        assert_eq!(normalize_utf8(data, Utf8Mode::Keep), (Cow::Borrowed(&data[..]), 0));
+
+        let (replaced, touched) = normalize_utf8(data, Utf8Mode::Replace);
+        assert_eq!(std::str::from_utf8(&replaced).unwrap(), "пр\u{FFFD} ok \u{FFFD}\u{FFFD} end");
+        assert_eq!(touched, 3);
+
+        let (stripped, touched) = normalize_utf8(data, Utf8Mode::Strict);
+        assert_eq!(std::str::from_utf8(&stripped).unwrap(), "пр ok  end");
+        assert_eq!(touched, 3);
+
+        assert!(matches!(normalize_utf8("чисто".as_bytes(), Utf8Mode::Strict), (Cow::Borrowed(_), 0)));
+        assert_eq!(Utf8Mode::from_name("strict"), Some(Utf8Mode::Strict));
+    }
+}
@@ -0,0 +1,228 @@
+//! Collapsing duplicate recovered files
+//!
+//! Reverse scans and chunk overlap assemble the same stream more than once.
+//! Files with the same SHA-256 are exact duplicates; files of the same type
+//! whose fuzzy hashes (`crate::hashing`) score at least `--dedup-similarity`
+//! are near-duplicates. Each group keeps one file (best validation, then
+//! highest confidence, then largest) and the report lists the others in
+//! `duplicate_groups`. Exact duplicates are removed from disk; near-duplicates
+//! stay there for review.
+
+use crate::hashing;
+use crate::report::{RecoveredFile, ValidationStatus};
+use crate::types::Offset;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::Path;
+use tracing::warn;
+
+/// A file collapsed into the kept file of its group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Duplicate {
+    pub id: usize,
+    pub filename: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub start_offset: Offset,
+    /// Fuzzy hash similarity to the kept file, 0-100 (100 when exact)
+    pub similarity: u8,
+    /// Same SHA-256 as the kept file (removed from disk)
+    pub exact: bool,
+}
+
+/// Recovered file and the duplicates collapsed into it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub kept_id: usize,
+    pub kept_filename: String,
+    pub sha256: String,
+    pub duplicates: Vec<Duplicate>,
+}
+
+fn validation_rank(status: &ValidationStatus) -> u8 {
+    match status {
+        ValidationStatus::Valid => 0,
+        ValidationStatus::MinorIssues => 1,
+        ValidationStatus::MajorIssues => 2,
+        ValidationStatus::Unknown => 3,
+        ValidationStatus::Invalid => 4,
+    }
+}
+
+/// Which of two files a group keeps: `Less` for `a`
+fn better(a: &RecoveredFile, b: &RecoveredFile) -> Ordering {
+    validation_rank(&a.validation_status)
+        .cmp(&validation_rank(&b.validation_status))
+        .then(b.confidence.total_cmp(&a.confidence))
+        .then(b.size_bytes.cmp(&a.size_bytes))
+        .then(a.id.cmp(&b.id))
+}
+
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Collapse duplicates of `files`; `min_similarity` 100 merges exact duplicates only.
+/// Returns the kept files in their original order and the groups by kept id.
+pub fn deduplicate(files: Vec<RecoveredFile>, min_similarity: u8) -> (Vec<RecoveredFile>, Vec<DuplicateGroup>) {
+    let mut parent: Vec<usize> = (0..files.len()).collect();
+
+    let mut by_sha: HashMap<&str, usize> = HashMap::new();
+    for (i, file) in files.iter().enumerate() {
+        if file.sha256.is_empty() {
+            continue;
+        }
+        if let Some(&first) = by_sha.get(file.sha256.as_str()) {
+            let (a, b) = (root(&mut parent, first), root(&mut parent, i));
+            parent[b] = a;
+        } else {
+            by_sha.insert(&file.sha256, i);
+        }
+    }
+
+    if min_similarity < 100 {
+        let mut by_type: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, file) in files.iter().enumerate().filter(|(_, file)| !file.fuzzy_hash.is_empty()) {
+            by_type.entry(file.file_type.as_str()).or_default().push(i);
+        }
+        for indices in by_type.values() {
+            for (n, &i) in indices.iter().enumerate() {
+                for &j in &indices[n + 1..] {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    if a == b || !hashing::comparable(&files[i].fuzzy_hash, &files[j].fuzzy_hash) {
+                        continue;
+                    }
+                    if hashing::similarity(&files[i].fuzzy_hash, &files[j].fuzzy_hash) >= min_similarity {
+                        parent[b] = a;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..files.len() {
+        members.entry(root(&mut parent, i)).or_default().push(i);
+    }
+    let mut dropped = vec![false; files.len()];
+    let mut groups = Vec::new();
+    for mut group in members.into_values().filter(|group| group.len() > 1) {
+        group.sort_by(|&a, &b| better(&files[a], &files[b]));
+        let kept = &files[group[0]];
+        let duplicates = group[1..]
+            .iter()
+            .map(|&i| {
+                dropped[i] = true;
+                let file = &files[i];
+                let exact = file.sha256 == kept.sha256;
+                Duplicate {
+                    id: file.id,
+                    filename: file.filename.clone(),
+                    sha256: file.sha256.clone(),
+                    size_bytes: file.size_bytes,
+                    start_offset: file.start_offset,
+                    similarity: if exact { 100 } else { hashing::similarity(&kept.fuzzy_hash, &file.fuzzy_hash) },
+                    exact,
+                }
+            })
+            .collect();
+        groups.push(DuplicateGroup {
+            kept_id: kept.id,
+            kept_filename: kept.filename.clone(),
+            sha256: kept.sha256.clone(),
+            duplicates,
+        });
+    }
+    groups.sort_by_key(|group| group.kept_id);
+
+    let kept = files
+        .into_iter()
+        .zip(dropped)
+        .filter_map(|(file, dropped)| (!dropped).then_some(file))
+        .collect();
+    (kept, groups)
+}
+
+/// Delete the exact duplicates of `groups` from `dir`; returns the files removed
+pub fn remove_exact_duplicates(dir: &Path, groups: &[DuplicateGroup]) -> usize {
+    let mut removed = 0;
+    for duplicate in groups.iter().flat_map(|group| &group.duplicates).filter(|d| d.exact) {
+        let path = super::writer::long_path(&dir.join(&duplicate.filename)).into_owned();
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                // With `--keep-title sidecar`
+                let _ = std::fs::remove_file(super::filename::sidecar_path(&path));
+                removed += 1;
+            }
+            // Not saved in the first place
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to remove duplicate {}: {}", duplicate.filename, e),
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: usize, data: &[u8], confidence: f64) -> RecoveredFile {
+        RecoveredFile {
+            id,
+            filename: format!("recovered_{:04}.json", id),
+            file_type: "json".to_string(),
+            confidence,
+            links: Vec::new(),
+            size_kb: data.len() as u64 / 1024,
+            sha256: crate::matcher::sha256_hash(data),
+            md5: String::new(),
+            fuzzy_hash: hashing::fuzzy_hash(data),
+            size_bytes: data.len() as u64,
+            utf8_repaired_bytes: 0,
+            trimmed_bytes: 0,
+            start_offset: Offset::new(id as u64 * 0x10000),
+            end_offset: Offset::new(id as u64 * 0x10000 + data.len() as u64),
+            validation_status: ValidationStatus::Valid,
+            recovery_time: String::new(),
+            fragments: Vec::new(),
+            provenance_hash: String::new(),
+            title: None,
+            title_language: None,
+            post_process: None,
+            repair: None,
+            renamed_from: None,
+            timestamps: None,
+            gaps: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_exact_and_near_duplicates() {
+        let text: Vec<u8> = (0..3000)
+            .flat_map(|i| format!("{{\"videoId\": \"v{}\", \"title\": \"Clip {}\"}}\n", i * 13, i).into_bytes())
+            .collect();
+        let mut near = text.clone();
+        near.extend_from_slice(b"{\"videoId\": \"tail\"}\n");
+        let other: Vec<u8> = (0..text.len() as u32).map(|i| (i.wrapping_mul(2654435761) >> 9) as u8).collect();
+
+        let files = vec![file(1, &text, 0.6), file(2, &other, 0.9), file(3, &text, 0.8), file(4, &near, 0.7)];
+        let (kept, groups) = deduplicate(files.clone(), 90);
+        assert_eq!(kept.iter().map(|f| f.id).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kept_id, 3);
+        let duplicates: Vec<(usize, bool)> = groups[0].duplicates.iter().map(|d| (d.id, d.exact)).collect();
+        assert_eq!(duplicates, [(4, false), (1, true)]);
+        assert!(groups[0].duplicates[0].similarity >= 90);
+
+        // Exact duplicates only
+        let (kept, groups) = deduplicate(files, 100);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(groups[0].duplicates.len(), 1);
+    }
+}
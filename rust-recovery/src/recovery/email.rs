@@ -0,0 +1,486 @@
+//! E-mail: RFC 822 messages (`.eml`) and mailboxes (`mbox`)
+//!
+//! The scanner types a chunk with `detect`: a mailbox by its `From ` line
+//! (`From sender@example.com Mon Jan  1 00:00:00 2024`), a message by
+//! three header fields of RFC 822 at line starts (`From:`, `Received:`,
+//! `Message-ID:`, ...), and the middle of a multipart message by a MIME
+//! boundary delimiter line followed by a part header. Messages are named
+//! after their `Subject` (RFC 2047 encoded words decoded: UTF-8, Latin-1,
+//! Windows-1251 and KOI8-R), a mailbox after the subject of its first
+//! message; `split_mbox` cuts a mailbox into its messages.
+//!
+//! A message longer than a chunk is found as several fragments, and in a
+//! mailbox those of different messages lie side by side. `continuity_keys`
+//! gives the Message-IDs (own, `In-Reply-To`, `References`) and the MIME
+//! boundaries a fragment declares or uses; they are its link set for the
+//! stream solver, so a fragment continuing a part (`--boundary` lines) is
+//! joined to the one that declared the boundary, and messages of one thread
+//! stay together.
+
+use super::cleaner::clean_text_content;
+use super::file_types::FileTypeHandler;
+use crate::report::ValidationStatus;
+use regex::bytes::Regex;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+pub const EML: &str = "eml";
+pub const MBOX: &str = "mbox";
+
+/// Bytes at the start of a chunk or file looked at by `detect` and the titles
+const SNIFF_BYTES: usize = 64 * 1024;
+/// Header fields looked for by `detect` (lower case)
+const HEADER_FIELDS: &[&str] = &[
+    "from",
+    "to",
+    "cc",
+    "subject",
+    "date",
+    "message-id",
+    "received",
+    "return-path",
+    "mime-version",
+    "content-type",
+    "in-reply-to",
+    "references",
+    "reply-to",
+    "delivered-to",
+    "x-mailer",
+];
+/// Distinct header fields that make a chunk a message
+const MIN_HEADER_FIELDS: usize = 3;
+
+fn header_field_regex() -> &'static Regex {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    FIELD.get_or_init(|| {
+        Regex::new(&format!(r"(?mi)^({}):[ \t]", HEADER_FIELDS.join("|").replace('-', r"\-"))).unwrap()
+    })
+}
+
+/// mbox separator line: `From <sender> <asctime date>`
+fn from_line_regex() -> &'static Regex {
+    static FROM: OnceLock<Regex> = OnceLock::new();
+    FROM.get_or_init(|| {
+        Regex::new(
+            r"(?m)^From \S+ +(?:Mon|Tue|Wed|Thu|Fri|Sat|Sun) (?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec) +\d{1,2} \d{1,2}:\d{2}(?::\d{2})?(?: [^\r\n]*?)? \d{4}\r?$",
+        )
+        .unwrap()
+    })
+}
+
+/// MIME boundary delimiter line (`--boundary`, `--boundary--` closes)
+fn delimiter_regex() -> &'static Regex {
+    static DELIMITER: OnceLock<Regex> = OnceLock::new();
+    DELIMITER.get_or_init(|| Regex::new(r"(?m)^--([0-9A-Za-z'()+_,./:=?-]{1,70}?)(--)?[ \t]*\r?$").unwrap())
+}
+
+/// `boundary` parameter of a `Content-Type` field
+fn boundary_param_regex() -> &'static Regex {
+    static BOUNDARY: OnceLock<Regex> = OnceLock::new();
+    BOUNDARY.get_or_init(|| Regex::new(r#"(?i)\bboundary\s*=\s*(?:"([^"\r\n]{1,70})"|([^\s;"]{1,70}))"#).unwrap())
+}
+
+/// Field that refers to messages by Message-ID, with its folded lines
+fn message_id_field_regex() -> &'static Regex {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    FIELD.get_or_init(|| Regex::new(r"(?mi)^(?:message-id|in-reply-to|references):((?:[^\r\n]|\r?\n[ \t])*)").unwrap())
+}
+
+fn head(data: &[u8]) -> &[u8] {
+    &data[..data.len().min(SNIFF_BYTES)]
+}
+
+/// Distinct `HEADER_FIELDS` at line starts of `data`
+fn header_field_count(data: &[u8]) -> usize {
+    let mut seen = 0u32;
+    for field in header_field_regex().captures_iter(data) {
+        let name = field[1].to_ascii_lowercase();
+        if let Some(index) = HEADER_FIELDS.iter().position(|known| known.as_bytes() == name.as_slice()) {
+            seen |= 1 << index;
+        }
+    }
+    seen.count_ones() as usize
+}
+
+/// Boundary with at least one letter or digit (not a `-----` ruler)
+fn is_boundary(boundary: &[u8]) -> bool {
+    boundary.iter().any(u8::is_ascii_alphanumeric)
+}
+
+/// E-mail type of a chunk, if any
+pub fn detect(data: &[u8]) -> Option<&'static str> {
+    let sample = head(data);
+    let fields = header_field_count(sample);
+    if fields >= 2 && from_line_regex().is_match(sample) {
+        return Some(MBOX);
+    }
+    if fields >= MIN_HEADER_FIELDS {
+        return Some(EML);
+    }
+    // Middle of a multipart message: a delimiter line and the part's header
+    let part = delimiter_regex().captures_iter(sample).any(|delimiter| {
+        let after = &sample[delimiter.get(0).unwrap().end()..];
+        let next = after.iter().skip_while(|&&b| b == b'\r' || b == b'\n').take(40).copied().collect::<Vec<u8>>();
+        is_boundary(&delimiter[1]) && next.to_ascii_lowercase().starts_with(b"content-")
+    });
+    part.then_some(EML)
+}
+
+/// Message-IDs and MIME boundaries of a fragment, as stream solver link keys
+/// (`message-id:<id>`, `boundary:<boundary>`), sorted and deduplicated
+pub fn continuity_keys(data: &[u8]) -> Vec<String> {
+    static ID: OnceLock<Regex> = OnceLock::new();
+    let id = ID.get_or_init(|| Regex::new(r"<[^<>\s]{1,250}>").unwrap());
+    let mut keys = Vec::new();
+    for field in message_id_field_regex().captures_iter(data) {
+        keys.extend(id.find_iter(&field[1]).map(|id| format!("message-id:{}", String::from_utf8_lossy(id.as_bytes()))));
+    }
+    for param in boundary_param_regex().captures_iter(data) {
+        let boundary = param.get(1).or(param.get(2)).unwrap().as_bytes();
+        keys.push(format!("boundary:{}", String::from_utf8_lossy(boundary)));
+    }
+    for delimiter in delimiter_regex().captures_iter(data) {
+        if is_boundary(&delimiter[1]) {
+            keys.push(format!("boundary:{}", String::from_utf8_lossy(&delimiter[1])));
+        }
+    }
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Messages of a mailbox, each from its `From ` line to the next one; text
+/// before the first `From ` line (a message cut at its start) is left out
+pub fn split_mbox(data: &[u8]) -> Vec<Range<usize>> {
+    let starts: Vec<usize> = from_line_regex().find_iter(data).map(|line| line.start()).collect();
+    starts.iter().enumerate().map(|(i, &start)| start..starts.get(i + 1).copied().unwrap_or(data.len())).collect()
+}
+
+/// Header block at the start of `message` (an mbox `From ` line skipped):
+/// its length up to the blank line, None when the message does not start
+/// with a header field; `terminated` when the blank line is there
+fn header_block(message: &[u8]) -> Option<(&[u8], bool)> {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    let field = FIELD.get_or_init(|| Regex::new(r"^[!-9;-~]{1,76}:").unwrap());
+    let message = if message.starts_with(b"From ") { &message[memchr::memchr(b'\n', message)? + 1..] } else { message };
+    if !field.is_match(message) {
+        return None;
+    }
+    let blank = memchr::memmem::find(message, b"\n\n")
+        .map(|end| end + 1)
+        .into_iter()
+        .chain(memchr::memmem::find(message, b"\n\r\n").map(|end| end + 1))
+        .min();
+    Some(match blank {
+        Some(end) => (&message[..end], true),
+        None => (message, false),
+    })
+}
+
+/// Unfolded value of header field `name` in `header`
+fn header_value(header: &[u8], name: &str) -> Option<String> {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    let re = FIELD.get_or_init(|| Regex::new(r"(?m)^([!-9;-~]{1,76}):[ \t]*((?:[^\r\n]|\r?\n[ \t])*)").unwrap());
+    let field = re.captures_iter(header).find(|field| field[1].eq_ignore_ascii_case(name.as_bytes()))?;
+    let value = String::from_utf8_lossy(&field[2]);
+    Some(value.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Boundary of the top-level multipart body declared in `header`
+fn top_boundary(header: &[u8]) -> Option<Vec<u8>> {
+    let content_type = header_value(header, "Content-Type")?;
+    let param = boundary_param_regex().captures(content_type.as_bytes())?;
+    Some(param.get(1).or(param.get(2))?.as_bytes().to_vec())
+}
+
+/// Bytes of the charset `charset` as text; None for an unknown charset
+fn decode_charset(bytes: &[u8], charset: &str) -> Option<String> {
+    // 0xC0-0xFF of KOI8-R
+    const KOI8R: &str = "юабцдефгхийклмнопярстужвьызшэщчъЮАБЦДЕФГХИЙКЛМНОПЯРСТУЖВЬЫЗШЭЩЧЪ";
+    let single_byte = |map: &dyn Fn(u8) -> char| bytes.iter().map(|&b| if b < 0x80 { b as char } else { map(b) }).collect();
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" | "us-ascii" => Some(String::from_utf8_lossy(bytes).into_owned()),
+        "iso-8859-1" | "latin1" => Some(single_byte(&|b| b as char)),
+        "windows-1251" | "cp1251" => Some(single_byte(&|b| match b {
+            0xC0..=0xFF => char::from_u32(0x0410 + (b - 0xC0) as u32).unwrap(),
+            0xA8 => 'Ё',
+            0xB8 => 'ё',
+            0xB9 => '№',
+            0xA0 => '\u{A0}',
+            _ => char::REPLACEMENT_CHARACTER,
+        })),
+        "koi8-r" => Some(single_byte(&|b| match b {
+            0xC0..=0xFF => KOI8R.chars().nth((b - 0xC0) as usize).unwrap(),
+            0xA3 => 'ё',
+            0xB3 => 'Ё',
+            _ => char::REPLACEMENT_CHARACTER,
+        })),
+        _ => None,
+    }
+}
+
+/// Base64 without line breaks; None on other characters
+fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &c in text.iter().take_while(|&&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// "Q" encoding of RFC 2047: `_` is a space, `=XX` a byte
+fn decode_q(text: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut i = 0;
+    while i < text.len() {
+        let hex = text.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (text[i], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'_', _) => bytes.push(b' '),
+            (b'=', Some(byte)) => {
+                bytes.push(byte);
+                i += 2;
+            }
+            (byte, _) => bytes.push(byte),
+        }
+        i += 1;
+    }
+    bytes
+}
+
+/// Header value with RFC 2047 encoded words (`=?charset?B|Q?text?=`)
+/// decoded; whitespace between two encoded words is dropped
+pub fn decode_encoded_words(value: &str) -> String {
+    static WORD: OnceLock<regex::Regex> = OnceLock::new();
+    let re = WORD.get_or_init(|| regex::Regex::new(r"=\?([^?\s]+)\?([BbQq])\?([^?\s]*)\?=").unwrap());
+    let mut decoded = String::new();
+    let mut last = 0;
+    for word in re.captures_iter(value) {
+        let whole = word.get(0).unwrap();
+        let between = &value[last..whole.start()];
+        if !(last > 0 && between.trim().is_empty()) {
+            decoded.push_str(between);
+        }
+        // `charset*language` (RFC 2231) names the charset first
+        let charset = word[1].split('*').next().unwrap_or_default();
+        let bytes = if word[2].eq_ignore_ascii_case("B") {
+            decode_base64(word[3].as_bytes())
+        } else {
+            Some(decode_q(word[3].as_bytes()))
+        };
+        match bytes.and_then(|bytes| decode_charset(&bytes, charset)) {
+            Some(text) => decoded.push_str(&text),
+            None => decoded.push_str(whole.as_str()),
+        }
+        last = whole.end();
+    }
+    decoded.push_str(&value[last..]);
+    decoded
+}
+
+/// Decoded subject of the message at the start of `content`
+fn subject(content: &[u8]) -> Option<String> {
+    let (header, _) = header_block(content)?;
+    let subject = decode_encoded_words(&header_value(header, "Subject")?);
+    (!subject.trim().is_empty()).then_some(subject)
+}
+
+/// Status of one message: valid with a complete header (a sender, and a
+/// date or Message-ID) and every multipart body closed; minor issues when
+/// cut inside the body or missing a field; major issues when it does not
+/// start with a header
+fn message_status(message: &[u8]) -> ValidationStatus {
+    let Some((header, terminated)) = header_block(message) else {
+        return ValidationStatus::MajorIssues;
+    };
+    let has = |name: &str| header_value(header, name).is_some();
+    let complete_header = terminated && (has("From") || has("Sender")) && (has("Date") || has("Message-ID"));
+    let closed = top_boundary(header).is_none_or(|boundary| {
+        let mut close = b"\n--".to_vec();
+        close.extend_from_slice(&boundary);
+        close.extend_from_slice(b"--");
+        memchr::memmem::find(message, &close).is_some()
+    });
+    if complete_header && closed {
+        ValidationStatus::Valid
+    } else {
+        ValidationStatus::MinorIssues
+    }
+}
+
+/// Single message
+pub struct EmlMessage;
+
+impl FileTypeHandler for EmlMessage {
+    fn file_types(&self) -> &[&'static str] {
+        &[EML]
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    /// End of the top-level multipart body's closing delimiter line, else the
+    /// next mailbox `From ` line; None for a single-part message
+    fn logical_end(&self, data: &[u8]) -> Option<usize> {
+        let (header, _) = header_block(data)?;
+        if let Some(boundary) = top_boundary(header) {
+            let mut close = b"\n--".to_vec();
+            close.extend_from_slice(&boundary);
+            close.extend_from_slice(b"--");
+            let at = memchr::memmem::find(data, &close)?;
+            let line_end = memchr::memchr(b'\n', &data[at + close.len()..]).map_or(data.len(), |end| at + close.len() + end + 1);
+            return Some(line_end);
+        }
+        from_line_regex().find_iter(data).map(|line| line.start()).find(|&start| start > 0)
+    }
+
+    fn extract_title(&self, content: &str) -> Option<String> {
+        subject(content.as_bytes())
+    }
+
+    fn validate(&self, data: &[u8]) -> ValidationStatus {
+        message_status(data)
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "eml"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+/// mbox mailbox
+pub struct Mailbox;
+
+impl FileTypeHandler for Mailbox {
+    fn file_types(&self) -> &[&'static str] {
+        &[MBOX]
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    /// Subject of the first message
+    fn extract_title(&self, content: &str) -> Option<String> {
+        let content = content.as_bytes();
+        let first = split_mbox(head(content)).into_iter().next()?;
+        subject(&content[first])
+    }
+
+    /// The worst status of its messages; minor issues when it starts inside a
+    /// message, major issues without any message
+    fn validate(&self, data: &[u8]) -> ValidationStatus {
+        let messages = split_mbox(data);
+        let Some(first) = messages.first() else {
+            return ValidationStatus::MajorIssues;
+        };
+        let cut_start = data[..first.start].iter().any(|b| !b.is_ascii_whitespace());
+        let statuses: Vec<ValidationStatus> = messages.into_iter().map(|message| message_status(&data[message])).collect();
+        if statuses.contains(&ValidationStatus::MajorIssues) {
+            ValidationStatus::MajorIssues
+        } else if cut_start || statuses.iter().any(|status| *status != ValidationStatus::Valid) {
+            ValidationStatus::MinorIssues
+        } else {
+            ValidationStatus::Valid
+        }
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "mbox"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recovery::FileTypeRegistry;
+
+    const MESSAGE: &str = "Return-Path: <anna@example.com>\r\n\
+        Received: from mx.example.com by mail.example.org; Mon, 1 Jan 2024 10:00:00 +0300\r\n\
+        From: =?UTF-8?B?0JDQvdC90LA=?= <anna@example.com>\r\n\
+        To: team@example.org\r\n\
+        Subject: =?windows-1251?Q?=CE=F2=F7=E5=F2?= =?UTF-8?B?INC30LAg0Y/QvdCy0LDRgNGM?=\r\n\
+        Date: Mon, 1 Jan 2024 10:00:00 +0300\r\n\
+        Message-ID: <report-1@example.com>\r\n\
+        MIME-Version: 1.0\r\n\
+        Content-Type: multipart/mixed; boundary=\"=_part_42\"\r\n\
+        \r\n\
+        --=_part_42\r\n\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        \r\n\
+        Отчёт во вложении.\r\n\
+        --=_part_42\r\n\
+        Content-Type: application/pdf\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        JVBERi0xLjQK\r\n\
+        --=_part_42--\r\n";
+
+    #[test]
+    fn test_messages_are_typed_split_and_linked() {
+        let registry = FileTypeRegistry::builtin();
+        let message = MESSAGE.as_bytes();
+        assert_eq!(detect(message), Some(EML));
+        assert_eq!(registry.extract_title(message, EML).as_deref(), Some("Отчет за январь"));
+        assert_eq!(registry.validate(message, EML), ValidationStatus::Valid);
+        assert_eq!(registry.extension(EML), "eml");
+        // Cut before the closing delimiter; the middle of the body is a part continuation
+        let cut = MESSAGE.find("JVBER").unwrap();
+        assert_eq!(registry.validate(&message[..cut], EML), ValidationStatus::MinorIssues);
+        let middle = MESSAGE.find("Отчёт").unwrap();
+        assert_eq!(detect(&message[middle..]), Some(EML));
+        assert_eq!(registry.validate(&message[middle..], EML), ValidationStatus::MajorIssues);
+        // Both halves share the boundary; the first also carries the Message-ID
+        assert_eq!(
+            continuity_keys(&message[..middle]),
+            vec!["boundary:=_part_42".to_string(), "message-id:<report-1@example.com>".to_string()]
+        );
+        assert_eq!(continuity_keys(&message[middle..]), vec!["boundary:=_part_42".to_string()]);
+        // Bytes after the closing delimiter are cut
+        let mut trailing = message.to_vec();
+        trailing.extend_from_slice(b"\0\0next cluster");
+        assert_eq!(EmlMessage.logical_end(&trailing), Some(message.len()));
+
+        let reply = "From: team@example.org\nSubject: Re: report\nDate: Tue, 2 Jan 2024 09:00:00 +0300\n\
+                     In-Reply-To: <report-1@example.com>\n\nThanks\n";
+        let mbox = format!(
+            "From anna@example.com Mon Jan  1 10:00:00 2024\n{}\nFrom team@example.org Tue Jan  2 09:00:00 2024\n{}",
+            MESSAGE.replace("\r\n", "\n"),
+            reply
+        );
+        let mbox = mbox.as_bytes();
+        assert_eq!(detect(mbox), Some(MBOX));
+        let messages = split_mbox(mbox);
+        assert_eq!(messages.len(), 2);
+        assert!(mbox[messages[1].clone()].starts_with(b"From team@example.org Tue"));
+        assert_eq!(registry.extract_title(mbox, MBOX).as_deref(), Some("Отчет за январь"));
+        assert_eq!(registry.validate(mbox, MBOX), ValidationStatus::Valid);
+        assert_eq!(registry.validate(&mbox[10..], MBOX), ValidationStatus::MinorIssues);
+        assert_eq!(registry.extension(MBOX), "mbox");
+        assert!(continuity_keys(&mbox[messages[1].clone()]).contains(&"message-id:<report-1@example.com>".to_string()));
+
+        assert_eq!(detect(b"From: someone\nplain notes\n------------\nmore"), None);
+    }
+}
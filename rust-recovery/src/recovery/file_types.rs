@@ -0,0 +1,469 @@
+//! Per-file-type handlers used when recovered files are written
+//!
+//! Stream assembly only knows a type name ("json", "png", ...). The handler
+//! registered for that name cleans the assembled content, extracts a title
+//! for the file name, validates the result and picks the extension. Types
+//! without a handler are written as assembled, under their own name. New
+//! types (subtitles, e-mail, ...) implement `FileTypeHandler` and are
+//! added with `FileTypeRegistry::register`; a later registration for the
+//! same name replaces the built-in one. Text types can also have invalid
+//! UTF-8 repaired after cleaning (`Utf8Mode`, off by default), and the
+//! bytes past the end of the document cut (`FileTypeRegistry::trim`).
+
+use super::boundary::{csv_end, html_end, json_end};
+use super::cleaner::{clean_text_content, normalize_utf8, Utf8Mode};
+use super::email::{EmlMessage, Mailbox};
+use super::messenger::{TelegramExport, WhatsAppExport};
+use super::office::{OfficeText, OoxmlDocument};
+use super::reconstructor::{extract_first_line, extract_html_title, extract_json_title};
+use super::revalidate::{csv_status, html_status, json_status, repair_csv, repair_html, repair_json, Repair};
+use crate::report::ValidationStatus;
+use std::borrow::Cow;
+
+/// Longest title kept for the report
+const MAX_TITLE_CHARS: usize = 200;
+/// Share of repaired UTF-8 bytes above which a valid file has minor issues
+pub const HEAVY_REPAIR_SHARE: f64 = 0.01;
+
+/// Cleaning, title, validation and extension of one or more file types
+pub trait FileTypeHandler: Send + Sync {
+    /// Type names handled, as assigned by the scanner and the carver
+    fn file_types(&self) -> &[&'static str];
+
+    /// Drop scan junk (nulls, control bytes) from the assembled content;
+    /// never longer than the input
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        Cow::Borrowed(data)
+    }
+
+    /// Length of the document in the cleaned content; the bytes after it
+    /// belong to the next cluster (None = end unknown, keep everything)
+    fn logical_end(&self, _data: &[u8]) -> Option<usize> {
+        None
+    }
+
+    /// Title as written in the content (whitespace is collapsed by the registry)
+    fn extract_title(&self, _content: &str) -> Option<String> {
+        None
+    }
+
+    /// Check the cleaned content
+    fn validate(&self, _data: &[u8]) -> ValidationStatus {
+        ValidationStatus::Valid
+    }
+
+    /// Mild fix for content that fails `validate` (a cut last record, an
+    /// unterminated string); None when there is nothing to try
+    fn repair(&self, _data: &[u8]) -> Option<(Vec<u8>, Repair)> {
+        None
+    }
+
+    /// Extension of the written file
+    fn preferred_extension(&self) -> &str;
+
+    /// Content is text: invalid UTF-8 is repaired by the registry's `Utf8Mode`
+    fn is_text(&self) -> bool {
+        false
+    }
+}
+
+/// Handlers by type name
+#[derive(Default)]
+pub struct FileTypeRegistry {
+    handlers: Vec<Box<dyn FileTypeHandler>>,
+    utf8_mode: Utf8Mode,
+    trim_boundaries: bool,
+}
+
+impl FileTypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry of the built-in text handlers, chat exports, e-mail and
+    /// carved Office documents included
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(PlainText));
+        registry.register(Box::new(Markdown));
+        registry.register(Box::new(Json));
+        registry.register(Box::new(Html));
+        registry.register(Box::new(Csv));
+        for file_type in ["css", "js", "xml"] {
+            registry.register(Box::new(SourceText(file_type)));
+        }
+        registry.register(Box::new(TelegramExport));
+        registry.register(Box::new(WhatsAppExport));
+        registry.register(Box::new(EmlMessage));
+        registry.register(Box::new(Mailbox));
+        for file_type in ["docx", "xlsx", "pptx"] {
+            registry.register(Box::new(OoxmlDocument(file_type)));
+        }
+        registry.register(Box::new(OfficeText));
+        registry
+    }
+
+    pub fn register(&mut self, handler: Box<dyn FileTypeHandler>) {
+        self.handlers.push(handler);
+    }
+
+    /// Repair invalid UTF-8 in text types after cleaning
+    pub fn with_utf8_mode(mut self, mode: Utf8Mode) -> Self {
+        self.utf8_mode = mode;
+        self
+    }
+
+    pub fn utf8_mode(&self) -> Utf8Mode {
+        self.utf8_mode
+    }
+
+    /// Cut text files at the logical end of their document (`trim`)
+    pub fn with_boundary_trim(mut self, trim: bool) -> Self {
+        self.trim_boundaries = trim;
+        self
+    }
+
+    pub fn trims_boundaries(&self) -> bool {
+        self.trim_boundaries
+    }
+
+    /// Handler for `file_type`, the latest registered first
+    pub fn handler(&self, file_type: &str) -> Option<&dyn FileTypeHandler> {
+        self.handlers
+            .iter()
+            .rev()
+            .find(|handler| handler.file_types().contains(&file_type))
+            .map(|handler| handler.as_ref())
+    }
+
+    /// Clean recovered file content based on file type
+    pub fn clean<'a>(&self, data: &'a [u8], file_type: &str) -> Cow<'a, [u8]> {
+        self.clean_counted(data, file_type).0
+    }
+
+    /// Cleaned content and the number of invalid UTF-8 bytes repaired
+    pub fn clean_counted<'a>(&self, data: &'a [u8], file_type: &str) -> (Cow<'a, [u8]>, usize) {
+        let Some(handler) = self.handler(file_type) else {
+            return (Cow::Borrowed(data), 0);
+        };
+        let cleaned = handler.clean(data);
+        if !handler.is_text() {
+            return (cleaned, 0);
+        }
+        match cleaned {
+            Cow::Borrowed(data) => normalize_utf8(data, self.utf8_mode),
+            Cow::Owned(data) => {
+                let (normalized, repaired) = normalize_utf8(&data, self.utf8_mode);
+                (Cow::Owned(normalized.into_owned()), repaired)
+            }
+        }
+    }
+
+    /// Cleaned content cut at the logical end of its document, and the
+    /// number of bytes cut; unchanged unless `with_boundary_trim` is set
+    pub fn trim<'a>(&self, data: Cow<'a, [u8]>, file_type: &str) -> (Cow<'a, [u8]>, usize) {
+        let end = self
+            .handler(file_type)
+            .filter(|_| self.trim_boundaries)
+            .and_then(|handler| handler.logical_end(&data))
+            .filter(|&end| end < data.len());
+        match (end, data) {
+            (Some(end), Cow::Borrowed(data)) => (Cow::Borrowed(&data[..end]), data.len() - end),
+            (Some(end), Cow::Owned(mut data)) => {
+                let trimmed = data.len() - end;
+                data.truncate(end);
+                (Cow::Owned(data), trimmed)
+            }
+            (None, data) => (data, 0),
+        }
+    }
+
+    /// Title of the content on one line, at most `MAX_TITLE_CHARS` long
+    pub fn extract_title(&self, data: &[u8], file_type: &str) -> Option<String> {
+        let handler = self.handler(file_type)?;
+        let title = handler.extract_title(&String::from_utf8_lossy(data))?;
+        let title: String = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        if title.is_empty() {
+            return None;
+        }
+        Some(title.chars().take(MAX_TITLE_CHARS).collect())
+    }
+
+    pub fn validate(&self, data: &[u8], file_type: &str) -> ValidationStatus {
+        match self.handler(file_type) {
+            Some(handler) => handler.validate(data),
+            None => ValidationStatus::Valid,
+        }
+    }
+
+    /// Repaired content and the repair made (see `FileTypeHandler::repair`)
+    pub fn repair(&self, data: &[u8], file_type: &str) -> Option<(Vec<u8>, Repair)> {
+        self.handler(file_type)?.repair(data)
+    }
+
+    /// `validate`, with a valid file downgraded to minor issues when more
+    /// than `HEAVY_REPAIR_SHARE` of it was repaired UTF-8
+    pub fn validate_repaired(&self, data: &[u8], file_type: &str, repaired_bytes: usize) -> ValidationStatus {
+        let status = self.validate(data, file_type);
+        let heavy = repaired_bytes as f64 > data.len().max(1) as f64 * HEAVY_REPAIR_SHARE;
+        if status == ValidationStatus::Valid && heavy {
+            ValidationStatus::MinorIssues
+        } else {
+            status
+        }
+    }
+
+    /// Extension for `file_type`: the type name itself without a handler
+    pub fn extension<'a>(&'a self, file_type: &'a str) -> &'a str {
+        self.handler(file_type).map_or(file_type, |handler| handler.preferred_extension())
+    }
+}
+
+/// Plain text; the first non-empty line is the title
+pub struct PlainText;
+
+impl FileTypeHandler for PlainText {
+    fn file_types(&self) -> &[&'static str] {
+        &["txt"]
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    fn extract_title(&self, content: &str) -> Option<String> {
+        extract_first_line(content)
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "txt"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+/// Markdown; the first non-empty line (usually the heading) is the title
+pub struct Markdown;
+
+impl FileTypeHandler for Markdown {
+    fn file_types(&self) -> &[&'static str] {
+        &["md"]
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    fn extract_title(&self, content: &str) -> Option<String> {
+        extract_first_line(content)
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "md"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+/// JSON; the first "title" or "name" value is the title. Balanced content
+/// that does not parse (a gap between fragments) has minor issues; a cut
+/// document is closed or loses its cut last record
+pub struct Json;
+
+impl FileTypeHandler for Json {
+    fn file_types(&self) -> &[&'static str] {
+        &["json"]
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    fn logical_end(&self, data: &[u8]) -> Option<usize> {
+        json_end(data)
+    }
+
+    fn extract_title(&self, content: &str) -> Option<String> {
+        extract_json_title(content)
+    }
+
+    fn validate(&self, data: &[u8]) -> ValidationStatus {
+        json_status(data)
+    }
+
+    fn repair(&self, data: &[u8]) -> Option<(Vec<u8>, Repair)> {
+        repair_json(data)
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "json"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+/// HTML; the `<title>` element is the title. Unbalanced tags are minor
+/// issues; a cut document has its open elements closed
+pub struct Html;
+
+impl FileTypeHandler for Html {
+    fn file_types(&self) -> &[&'static str] {
+        &["html", "htm"]
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    fn logical_end(&self, data: &[u8]) -> Option<usize> {
+        html_end(data)
+    }
+
+    fn extract_title(&self, content: &str) -> Option<String> {
+        extract_html_title(content)
+    }
+
+    fn validate(&self, data: &[u8]) -> ValidationStatus {
+        html_status(data)
+    }
+
+    fn repair(&self, data: &[u8]) -> Option<(Vec<u8>, Repair)> {
+        repair_html(data)
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "html"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+/// CSV; the header line is the title. Rows are checked against the
+/// header's column count; a cut last row is dropped
+pub struct Csv;
+
+impl FileTypeHandler for Csv {
+    fn file_types(&self) -> &[&'static str] {
+        &["csv"]
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    fn logical_end(&self, data: &[u8]) -> Option<usize> {
+        csv_end(data)
+    }
+
+    fn validate(&self, data: &[u8]) -> ValidationStatus {
+        csv_status(data)
+    }
+
+    fn repair(&self, data: &[u8]) -> Option<(Vec<u8>, Repair)> {
+        repair_csv(data)
+    }
+
+    fn extract_title(&self, content: &str) -> Option<String> {
+        extract_first_line(content)
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "csv"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+/// Other text types (css, js, xml): cleaned, no title
+pub struct SourceText(pub &'static str);
+
+impl FileTypeHandler for SourceText {
+    fn file_types(&self) -> &[&'static str] {
+        std::slice::from_ref(&self.0)
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    fn preferred_extension(&self) -> &str {
+        self.0
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// WebVTT subtitles: the first cue text is the title
+    struct Subtitles;
+
+    impl FileTypeHandler for Subtitles {
+        fn file_types(&self) -> &[&'static str] {
+            &["vtt", "txt"]
+        }
+
+        fn extract_title(&self, content: &str) -> Option<String> {
+            content.lines().skip_while(|line| !line.contains("-->")).nth(1).map(str::to_string)
+        }
+
+        fn preferred_extension(&self) -> &str {
+            "vtt"
+        }
+    }
+
+    #[test]
+    fn test_builtin_and_registered_handlers() {
+        let mut registry = FileTypeRegistry::builtin();
+        assert_eq!(registry.clean(b"{\"a\":\x001}\x01", "json").as_ref(), b"{\"a\":1} ");
+        assert_eq!(registry.clean(b"\x89PNG\x00", "png").as_ref(), b"\x89PNG\x00");
+        assert_eq!(
+            registry.extract_title(b"<html><title>  Saved \t page </title>", "htm").as_deref(),
+            Some("Saved page")
+        );
+        assert_eq!(registry.extract_title(b"\n\nfirst line\nsecond", "txt").as_deref(), Some("first line"));
+        assert_eq!(registry.extract_title(b"\x89PNG", "png"), None);
+        assert_eq!(registry.validate(b"{\"title\": \"x\"}", "json"), ValidationStatus::Valid);
+        assert_eq!(registry.validate(b"{\"title\": \"x\",}", "json"), ValidationStatus::MinorIssues);
+        assert_eq!(registry.validate(b"\x00\x01", "json"), ValidationStatus::MajorIssues);
+        assert_eq!((registry.extension("htm"), registry.extension("js"), registry.extension("png")), ("html", "js", "png"));
+
+        // A later handler takes over its types, the others keep the built-ins
+        registry.register(Box::new(Subtitles));
+        let vtt = b"WEBVTT\n\n00:00.000 --> 00:02.000\nHello there\n";
+        assert_eq!(registry.extract_title(vtt, "vtt").as_deref(), Some("Hello there"));
+        assert_eq!(registry.extension("txt"), "vtt");
+        assert_eq!(registry.clean(b"a\x00b", "txt").as_ref(), b"a\x00b");
+        assert_eq!(registry.clean(b"a\x00b", "md").as_ref(), b"ab");
+
+        // UTF-8 repair applies to text handlers only
+        let registry = FileTypeRegistry::builtin().with_utf8_mode(Utf8Mode::Replace);
+        let (json, repaired) = registry.clean_counted(b"{\"t\": \"\xD0\"}\x00", "json");
+        assert_eq!((json.as_ref(), repaired), ("{\"t\": \"\u{FFFD}\"}".as_bytes(), 1));
+        assert_eq!(registry.clean_counted(b"\x89PNG\xFF", "png").1, 0);
+        assert_eq!(registry.validate_repaired(&json, "json", 0), ValidationStatus::Valid);
+        assert_eq!(registry.validate_repaired(&json, "json", repaired), ValidationStatus::MinorIssues);
+
+        // Trailing bytes of the next cluster are cut only when asked for
+        let tail = Cow::Borrowed(&b"{\"a\": [1]}\n\x7FELF"[..]);
+        assert_eq!(registry.trim(tail.clone(), "json").1, 0);
+        let registry = registry.with_boundary_trim(true);
+        assert_eq!(registry.trim(tail, "json"), (Cow::Borrowed(&b"{\"a\": [1]}"[..]), 5));
+        assert_eq!(registry.trim(Cow::Owned(b"<html></html>\n".to_vec()), "html").1, 0);
+        assert_eq!(registry.trim(Cow::Borrowed(b"\x89PNG{}"), "png").1, 0);
+    }
+}
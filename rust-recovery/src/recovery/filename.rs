@@ -0,0 +1,215 @@
+//! Filenames for recovered files, built from their titles
+//!
+//! `recovered_0001_<title>.ext` has to be a valid name on the output drive
+//! whatever the title holds. `title_filename` keeps letters, digits, spaces,
+//! `-` and `_` (slashes, emoji and control characters go), and
+//! `--filename-translit` spells Cyrillic and accented Latin in ASCII. Windows
+//! device names (CON, NUL, COM1...) are never a name's stem. Names fit
+//! `--max-filename-bytes` bytes of UTF-8 (255 by default, the ext4 and
+//! NAME_MAX limit, which Cyrillic titles reach at half the characters), with
+//! room left for the `_N` the writer adds to a taken name; only the title is
+//! cut, never the id or the extension.
+//!
+//! The report keeps the title as written. `--keep-title` also stores it with
+//! the file: in the `user.dublincore.title` extended attribute (Linux) or in
+//! a `<name>.title.json` sidecar.
+
+use super::reconstructor::title_filename;
+use super::title::detect_language;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Default `--max-filename-bytes`
+pub const DEFAULT_MAX_FILENAME_BYTES: usize = 255;
+/// Shortest `--max-filename-bytes`: `recovered_0001.ext` and a rename suffix
+pub const MIN_FILENAME_BYTES: usize = 32;
+/// Kept free for the writer's `_N` on taken names (up to `_99999`)
+const RENAME_SUFFIX_BYTES: usize = 6;
+/// Appended to a title cut to fit
+const ELLIPSIS: &str = "...";
+/// Names Windows reserves for devices, with any extension
+const WINDOWS_DEVICE_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1",
+    "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+/// Extended attribute holding the title (freedesktop.org common attributes)
+#[cfg(target_os = "linux")]
+const TITLE_XATTR: &std::ffi::CStr = c"user.dublincore.title";
+
+/// Names accepted by `--keep-title`
+pub const TITLE_STORE_NAMES: &[&str] = &["none", "xattr", "sidecar"];
+
+/// Where the original title is kept next to the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TitleStore {
+    /// In the report only
+    #[default]
+    None,
+    /// `user.dublincore.title` extended attribute
+    Xattr,
+    /// `<name>.title.json` beside the file
+    Sidecar,
+}
+
+impl TitleStore {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "xattr" => Some(Self::Xattr),
+            "sidecar" => Some(Self::Sidecar),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Xattr => "xattr",
+            Self::Sidecar => "sidecar",
+        }
+    }
+}
+
+/// How titles become filenames
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenamePolicy {
+    /// Spell titles in ASCII (`--filename-translit`)
+    pub translit: bool,
+    /// Longest name in bytes of UTF-8 (`--max-filename-bytes`)
+    pub max_bytes: usize,
+    /// Where the title is kept besides the report (`--keep-title`)
+    pub keep_title: TitleStore,
+}
+
+impl Default for FilenamePolicy {
+    fn default() -> Self {
+        Self { translit: false, max_bytes: DEFAULT_MAX_FILENAME_BYTES, keep_title: TitleStore::None }
+    }
+}
+
+impl FilenamePolicy {
+    /// `recovered_0001_<title>.ext`, or `recovered_0001.ext` without a usable title
+    pub fn file_name(&self, id: usize, title: Option<&str>, extension: &str) -> String {
+        let base = format!("recovered_{:04}", id);
+        let stem = title.map(|title| title_filename(title, self.translit)).unwrap_or_default();
+        // `base_<stem>.<extension>` and the rename suffix
+        let budget = self.max_bytes.saturating_sub(base.len() + extension.len() + 2 + RENAME_SUFFIX_BYTES);
+        let stem = fit(&stem, budget);
+        let name = if stem.is_empty() {
+            format!("{}.{}", base, extension)
+        } else {
+            format!("{}_{}.{}", base, stem, extension)
+        };
+        windows_safe(name)
+    }
+
+    /// Store `title` with the file saved at `path`, as `keep_title` says
+    pub fn keep_title(&self, path: &Path, title: &str) -> io::Result<()> {
+        match self.keep_title {
+            TitleStore::None => Ok(()),
+            TitleStore::Xattr => set_title_xattr(path, title),
+            TitleStore::Sidecar => {
+                let sidecar = serde_json::json!({
+                    "file": path.file_name().map(|name| name.to_string_lossy()),
+                    "title": title,
+                    "title_language": detect_language(title).code(),
+                });
+                fs::write(super::writer::long_path(&sidecar_path(path)), serde_json::to_vec_pretty(&sidecar)?)
+            }
+        }
+    }
+}
+
+/// `<name>.title.json` beside `path`
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".title.json");
+    path.with_file_name(name)
+}
+
+/// `stem` in at most `budget` bytes, cut at a character and marked with `...`
+fn fit(stem: &str, budget: usize) -> String {
+    if stem.len() <= budget {
+        return stem.to_string();
+    }
+    let Some(room) = budget.checked_sub(ELLIPSIS.len()) else {
+        return String::new();
+    };
+    let cut = (0..=room).rev().find(|&at| stem.is_char_boundary(at)).unwrap_or(0);
+    let kept = stem[..cut].trim_end();
+    if kept.is_empty() {
+        String::new()
+    } else {
+        format!("{}{}", kept, ELLIPSIS)
+    }
+}
+
+/// `name` with `_` in front if Windows reserves its stem for a device
+fn windows_safe(name: String) -> String {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if WINDOWS_DEVICE_NAMES.iter().any(|device| device.eq_ignore_ascii_case(stem)) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_title_xattr(path: &Path, title: &str) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let result = unsafe { libc::setxattr(path.as_ptr(), TITLE_XATTR.as_ptr(), title.as_ptr().cast(), title.len(), 0) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_title_xattr(_path: &Path, _title: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "titles are only written to extended attributes on Linux"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filename_policy() {
+        let policy = FilenamePolicy::default();
+        // "й" decomposed (и + U+0306) normalizes to the same name as the composed form
+        let composed = policy.file_name(2, Some("Мой / клип: <1>"), "json");
+        assert_eq!(composed, "recovered_0002_Мой клип 1.json");
+        assert_eq!(policy.file_name(2, Some("Мои\u{306} / клип: <1>"), "json"), composed);
+        assert_eq!(policy.file_name(3, Some("???"), "png"), "recovered_0003.png");
+        assert_eq!(policy.file_name(4, Some("🎬 Café"), "html"), "recovered_0004_Café.html");
+
+        let ascii = FilenamePolicy { translit: true, ..FilenamePolicy::default() };
+        assert_eq!(ascii.file_name(4, Some("🎬 Café / Щука"), "html"), "recovered_0004_Cafe Shchuka.html");
+
+        // Cyrillic takes 2 bytes a letter: cut at a character, id and extension kept
+        let short = FilenamePolicy { max_bytes: 48, ..FilenamePolicy::default() };
+        let name = short.file_name(5, Some("Длинное название видео"), "json");
+        assert_eq!(name, "recovered_0005_Длинное на....json");
+        assert_eq!(name.len(), 48 - RENAME_SUFFIX_BYTES);
+        assert_eq!(FilenamePolicy { max_bytes: 28, ..short }.file_name(5, Some("Длинное"), "json"), "recovered_0005.json");
+
+        assert_eq!(windows_safe("nul.txt".to_string()), "_nul.txt");
+        assert_eq!(windows_safe("COM1".to_string()), "_COM1");
+        assert_eq!(windows_safe("console.txt".to_string()), "console.txt");
+        assert_eq!(TitleStore::from_name("sidecar"), Some(TitleStore::Sidecar));
+
+        let path = std::env::temp_dir().join(format!("recovered_title_{}.html", std::process::id()));
+        fs::write(&path, b"<title>x</title>").unwrap();
+        let sidecar = FilenamePolicy { keep_title: TitleStore::Sidecar, ..FilenamePolicy::default() };
+        sidecar.keep_title(&path, "Мой / клип").unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&fs::read(sidecar_path(&path)).unwrap()).unwrap();
+        assert_eq!(json["title"], "Мой / клип");
+        assert_eq!(json["title_language"], "ru");
+        let _ = fs::remove_file(sidecar_path(&path));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,108 @@
+//! Holes for missing data in assembled files (`--gap-fill`)
+//!
+//! The solver joins two fragments across up to `max_gap` bytes of the image
+//! that were not found hot, and an extent that cannot be read is left out.
+//! Concatenating what remains shifts every later byte of the file. With
+//! `--gap-fill zero` or `marker` the missing bytes become a hole of the same
+//! size instead, and the report lists each hole (`FileGap`) so a repair tool
+//! knows where the file is incomplete and which image bytes it would need.
+
+use crate::types::Offset;
+use serde::{Deserialize, Serialize};
+
+/// Names accepted by `--gap-fill`
+pub const GAP_FILL_NAMES: &[&str] = &["none", "zero", "marker"];
+
+/// Pattern repeated over a hole with `--gap-fill marker`
+pub const GAP_MARKER: &[u8] = b"<GAP>";
+
+/// How missing bytes between and inside the extents of a file are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GapFill {
+    /// Fragments are concatenated, nothing marks the missing bytes
+    #[default]
+    None,
+    /// Zero bytes
+    Zero,
+    /// `GAP_MARKER` repeated (and cut at the end of the hole)
+    Marker,
+}
+
+impl GapFill {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "zero" => Some(Self::Zero),
+            "marker" => Some(Self::Marker),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Zero => "zero",
+            Self::Marker => "marker",
+        }
+    }
+
+    /// Content of a hole of `len` bytes
+    pub fn hole(self, len: usize) -> Vec<u8> {
+        match self {
+            Self::None | Self::Zero => vec![0; len],
+            Self::Marker => GAP_MARKER.iter().copied().cycle().take(len).collect(),
+        }
+    }
+}
+
+/// Why bytes of a file are missing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapCause {
+    /// Image bytes between two fragments of the stream that the scan did not
+    /// find (their size is the distance between the fragments)
+    NotFound,
+    /// An extent of the file that could not be read
+    Unreadable,
+}
+
+/// Hole left in a saved file for missing bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileGap {
+    /// Offset of the hole in the saved file
+    pub offset: u64,
+    pub len: u64,
+    /// Where the missing bytes lie in the image
+    pub image_offset: Offset,
+    pub cause: GapCause,
+}
+
+/// Keep the parts of `gaps` inside the first `len` bytes of the file (after
+/// the end of the document was trimmed)
+pub fn clamp_gaps(gaps: &mut Vec<FileGap>, len: u64) {
+    gaps.retain(|gap| gap.offset < len);
+    if let Some(last) = gaps.last_mut() {
+        last.len = last.len.min(len - last.offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gap_fill_holes() {
+        assert_eq!(GapFill::from_name("marker"), Some(GapFill::Marker));
+        assert_eq!(GapFill::from_name("zeros"), None);
+        assert!(GAP_FILL_NAMES.iter().all(|name| GapFill::from_name(name).unwrap().name() == *name));
+        assert_eq!(GapFill::Zero.hole(3), vec![0, 0, 0]);
+        assert_eq!(GapFill::Marker.hole(7), b"<GAP><G".to_vec());
+
+        let gap = |offset, len| FileGap { offset, len, image_offset: Offset::new(0), cause: GapCause::NotFound };
+        let mut gaps = vec![gap(10, 5), gap(30, 20)];
+        clamp_gaps(&mut gaps, 40);
+        assert_eq!(gaps, vec![gap(10, 5), gap(30, 10)]);
+        clamp_gaps(&mut gaps, 12);
+        assert_eq!(gaps, vec![gap(10, 2)]);
+    }
+}
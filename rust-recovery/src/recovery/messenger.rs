@@ -0,0 +1,235 @@
+//! Messenger chat exports: Telegram `result.json` and WhatsApp chat `.txt`
+//!
+//! Both are common recovery targets and would otherwise be typed as plain
+//! "json" or "txt" and named after their first key or line. The scanner
+//! types a chunk with `detect`: a Telegram export by its message keys
+//! (`date_unixtime`, `from_id`, `text_entities`, ...), a WhatsApp export by
+//! lines starting with its timestamp (`31.12.23, 21:41 - Name: text` on
+//! Android, `[31.12.23, 21:41:05] Name: text` on iOS, any date order and
+//! 12- or 24-hour clock). The handlers name the file after the chat: the
+//! Telegram chat's `name` (the first chat of a full account export), the
+//! WhatsApp group subject or else its two most frequent senders.
+
+use super::boundary::json_end;
+use super::cleaner::clean_text_content;
+use super::file_types::FileTypeHandler;
+use super::reconstructor::extract_json_title;
+use super::revalidate::{json_status, repair_json, Repair};
+use crate::report::ValidationStatus;
+use memchr::memmem;
+use regex::Regex;
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+pub const TELEGRAM: &str = "telegram";
+pub const WHATSAPP: &str = "whatsapp";
+
+/// Bytes at the start of a chunk or file looked at by `detect` and the titles
+const SNIFF_BYTES: usize = 64 * 1024;
+/// Keys of Telegram export messages; two of them make a chunk Telegram
+const TELEGRAM_KEYS: &[&str] = &[
+    "\"date_unixtime\"",
+    "\"from_id\"",
+    "\"text_entities\"",
+    "\"reply_to_message_id\"",
+    "\"personal_chat\"",
+    "\"forwarded_from\"",
+];
+/// Timestamped lines that make a chunk a WhatsApp chat
+const WHATSAPP_MIN_LINES: usize = 3;
+
+/// Messenger export type of a chunk, if any
+pub fn detect(data: &[u8]) -> Option<&'static str> {
+    let sample = &data[..data.len().min(SNIFF_BYTES)];
+    let telegram_keys = TELEGRAM_KEYS.iter().filter(|key| memmem::find(sample, key.as_bytes()).is_some()).count();
+    if telegram_keys >= 2 {
+        return Some(TELEGRAM);
+    }
+    // Cheap check first: a WhatsApp line holds ", " after its date
+    memmem::find(sample, b", ")?;
+    let text = String::from_utf8_lossy(sample);
+    let lines = text.lines().filter(|line| whatsapp_line(line).is_some()).take(WHATSAPP_MIN_LINES).count();
+    (lines == WHATSAPP_MIN_LINES).then_some(WHATSAPP)
+}
+
+/// Timestamp prefix of a WhatsApp line, with the sender when there is one
+/// (system lines such as "Messages are end-to-end encrypted" have none)
+fn whatsapp_line(line: &str) -> Option<regex::Captures<'_>> {
+    static LINE: OnceLock<Regex> = OnceLock::new();
+    let re = LINE.get_or_init(|| {
+        Regex::new(
+            r"^\x{200E}?\[?\d{1,2}[./-]\d{1,2}[./-]\d{2,4},?\s\d{1,2}:\d{2}(?::\d{2})?(?:\s?[AaPp]\.?\s?[Mm]\.?)?\]?\s(?:-\s)?(?:([^:\n]{1,60}?):\s)?",
+        )
+        .unwrap()
+    });
+    re.captures(line)
+}
+
+/// First `SNIFF_BYTES` of `content`, cut at a character
+fn head(content: &str) -> &str {
+    let mut end = content.len().min(SNIFF_BYTES);
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    &content[..end]
+}
+
+/// Telegram export; JSON cleaning, trimming, validation and repair
+pub struct TelegramExport;
+
+impl FileTypeHandler for TelegramExport {
+    fn file_types(&self) -> &[&'static str] {
+        &[TELEGRAM]
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    fn logical_end(&self, data: &[u8]) -> Option<usize> {
+        json_end(data)
+    }
+
+    /// `name` of the first chat (it precedes the chat's `type`); "Saved
+    /// Messages" has no name
+    fn extract_title(&self, content: &str) -> Option<String> {
+        static CHAT: OnceLock<Regex> = OnceLock::new();
+        let re = CHAT.get_or_init(|| {
+            Regex::new(r#""name":\s*("(?:[^"\\]|\\.)*"|null),\s*"type":\s*"([a-z_]+)""#).unwrap()
+        });
+        let content = head(content);
+        let chat = re.captures(content)?;
+        match serde_json::from_str::<Option<String>>(&chat[1]) {
+            Ok(Some(name)) if !name.trim().is_empty() => Some(name),
+            _ if &chat[2] == "saved_messages" => Some("Saved Messages".to_string()),
+            _ => extract_json_title(content),
+        }
+    }
+
+    fn validate(&self, data: &[u8]) -> ValidationStatus {
+        json_status(data)
+    }
+
+    fn repair(&self, data: &[u8]) -> Option<(Vec<u8>, Repair)> {
+        repair_json(data)
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "json"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+/// WhatsApp chat export. A file that does not start with a timestamped
+/// line was cut mid-message (minor issues); one without any is not a chat
+pub struct WhatsAppExport;
+
+impl FileTypeHandler for WhatsAppExport {
+    fn file_types(&self) -> &[&'static str] {
+        &[WHATSAPP]
+    }
+
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        clean_text_content(data)
+    }
+
+    /// Group subject, else the two most frequent senders
+    fn extract_title(&self, content: &str) -> Option<String> {
+        static SUBJECT: OnceLock<Regex> = OnceLock::new();
+        let re = SUBJECT.get_or_init(|| {
+            Regex::new(r#"(?:created group|changed the subject (?:from .+? )?to|создал\S* группу|изменил\S* тему(?: с .+?)? на) [“"«]([^”"»\n]+)[”"»]"#)
+                .unwrap()
+        });
+        let content = head(content);
+        if let Some(subject) = re.captures(content) {
+            return Some(subject[1].to_string());
+        }
+        let mut senders: Vec<(&str, usize)> = Vec::new();
+        for sender in content.lines().filter_map(|line| whatsapp_line(line)?.get(1)) {
+            match senders.iter_mut().find(|(name, _)| *name == sender.as_str()) {
+                Some((_, count)) => *count += 1,
+                None => senders.push((sender.as_str(), 1)),
+            }
+        }
+        // Stable: ties keep the order of first appearance
+        senders.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let names: Vec<&str> = senders.iter().take(2).map(|(name, _)| *name).collect();
+        (!names.is_empty()).then(|| names.join(", "))
+    }
+
+    fn validate(&self, data: &[u8]) -> ValidationStatus {
+        let text = String::from_utf8_lossy(data);
+        let mut lines = text.lines().map(|line| line.trim_start_matches('\u{FEFF}')).filter(|line| !line.trim().is_empty());
+        match lines.next() {
+            Some(first) if whatsapp_line(first).is_some() => ValidationStatus::Valid,
+            Some(_) if lines.any(|line| whatsapp_line(line).is_some()) => ValidationStatus::MinorIssues,
+            _ => ValidationStatus::MajorIssues,
+        }
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "txt"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recovery::FileTypeRegistry;
+
+    #[test]
+    fn test_chat_exports_are_typed_and_titled() {
+        let telegram = r#"{
+ "name": "Семья \"Ивановы\"",
+ "type": "private_group",
+ "id": 4242,
+ "messages": [
+  {
+   "id": 1,
+   "type": "message",
+   "date": "2023-12-31T21:41:05",
+   "date_unixtime": "1704048065",
+   "from": "Мама",
+   "from_id": "user100",
+   "text": "С Новым годом!",
+   "text_entities": [{"type": "plain", "text": "С Новым годом!"}]
+  }
+ ]
+}"#;
+        let registry = FileTypeRegistry::builtin();
+        assert_eq!(detect(telegram.as_bytes()), Some(TELEGRAM));
+        assert_eq!(registry.extract_title(telegram.as_bytes(), TELEGRAM).as_deref(), Some("Семья \"Ивановы\""));
+        assert_eq!(registry.validate(telegram.as_bytes(), TELEGRAM), ValidationStatus::Valid);
+        assert_eq!(registry.extension(TELEGRAM), "json");
+        let saved = r#"{"name": null, "type": "saved_messages", "messages": [{"date_unixtime": "1", "from_id": "user1"}]}"#;
+        assert_eq!(registry.extract_title(saved.as_bytes(), TELEGRAM).as_deref(), Some("Saved Messages"));
+
+        let android = "31.12.23, 21:40 - Сообщения и звонки защищены сквозным шифрованием.\n\
+                       31.12.23, 21:41 - Аня: С Новым годом!\n\
+                       31.12.23, 21:42 - Петя: И тебя!\n\
+                       продолжение сообщения\n\
+                       31.12.23, 21:43 - Аня: 🎉\n";
+        assert_eq!(detect(android.as_bytes()), Some(WHATSAPP));
+        assert_eq!(registry.extract_title(android.as_bytes(), WHATSAPP).as_deref(), Some("Аня, Петя"));
+        assert_eq!(registry.validate(android.as_bytes(), WHATSAPP), ValidationStatus::Valid);
+        assert_eq!(registry.validate(&android.as_bytes()[80..], WHATSAPP), ValidationStatus::MinorIssues);
+        assert_eq!(registry.validate(b"just text\nmore text\n", WHATSAPP), ValidationStatus::MajorIssues);
+
+        let ios = "[12/31/23, 9:41:05\u{202F}PM] Alice: Happy new year\n\
+                   [12/31/23, 9:41:30\u{202F}PM] Bob created group \"Trip 2024\"\n\
+                   [12/31/23, 9:42:00\u{202F}PM] Bob: Same to you\n";
+        assert_eq!(detect(ios.as_bytes()), Some(WHATSAPP));
+        assert_eq!(registry.extract_title(ios.as_bytes(), WHATSAPP).as_deref(), Some("Trip 2024"));
+        assert_eq!(registry.extension(WHATSAPP), "txt");
+
+        assert_eq!(detect(b"{\"title\": \"x\", \"date\": \"2023-12-31\"}"), None);
+        assert_eq!(detect(b"Meeting on 31.12.23, 21:41 - nothing else"), None);
+    }
+}
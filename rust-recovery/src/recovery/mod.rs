@@ -1,5 +1,29 @@
+pub mod boundary;
 pub mod cleaner;
+pub mod dedup;
+pub mod email;
+pub mod file_types;
+pub mod filename;
+pub mod gaps;
+pub mod messenger;
+pub mod office;
+pub mod post_process;
+pub mod provenance;
 pub mod reconstructor;
+pub mod revalidate;
+pub mod title;
+pub mod verify;
+pub mod writer;
 
-pub use cleaner::clean_file_content;
-pub use reconstructor::extract_title;
+pub use dedup::{deduplicate, remove_exact_duplicates, Duplicate, DuplicateGroup};
+pub use cleaner::{clean_text_content, normalize_utf8, Utf8Mode, UTF8_MODE_NAMES};
+pub use file_types::{FileTypeHandler, FileTypeRegistry};
+pub use filename::{FilenamePolicy, TitleStore, DEFAULT_MAX_FILENAME_BYTES, MIN_FILENAME_BYTES, TITLE_STORE_NAMES};
+pub use gaps::{FileGap, GapCause, GapFill, GAP_FILL_NAMES};
+pub use post_process::{PostProcessResult, PostProcessor, POST_PROCESS_LOG_FILE};
+pub use provenance::{provenance_hash, ExtentDigest, FragmentExtent, ProvenanceParams, TOOL_VERSION};
+pub use reconstructor::title_filename;
+pub use revalidate::{revalidate, Repair, RevalidationSummary};
+pub use title::{detect_language, transliterate, TitleLanguage};
+pub use verify::{diff_ranges, find_report_entry, reassemble, reassemble_with, ByteDiff, Reassembled, ReportEntry};
+pub use writer::{long_path, RecoveryWriter, WrittenFile};
@@ -0,0 +1,261 @@
+//! Office documents carved by `carver.rs`
+//!
+//! An OOXML package (`docx`, `xlsx`, `pptx`) whose central directory was cut
+//! off or overwritten is carved up to its last complete local entry. Office
+//! will not open it, so it fails validation and the repair pass appends a
+//! central directory rebuilt from the local headers
+//! (`RebuildCentralDirectory`). A truncated OLE2 document (`doc`, `xls`,
+//! `ppt`) is carved as `office_text` instead: the range from its header to
+//! the end of its stored text, which is decoded when the file is written.
+
+use super::file_types::FileTypeHandler;
+use super::reconstructor::extract_first_line;
+use super::revalidate::Repair;
+use crate::carver::{ole_text, zip_eocd_after, zip_local_entries};
+use crate::report::ValidationStatus;
+use std::borrow::Cow;
+
+/// Text recovered from a truncated OLE2 document
+pub const OFFICE_TEXT: &str = "office_text";
+
+/// Local entries of the package followed by a central directory and an
+/// end record listing them; None without entries or past ZIP32 limits
+pub fn rebuild_central_directory(data: &[u8]) -> Option<Vec<u8>> {
+    let walk = zip_local_entries(data);
+    let entries = u16::try_from(walk.entries.len()).ok().filter(|&entries| entries > 0)?;
+    let mut rebuilt = data[..walk.end].to_vec();
+    let cd_offset = u32::try_from(rebuilt.len()).ok()?;
+    for entry in &walk.entries {
+        rebuilt.extend_from_slice(b"PK\x01\x02");
+        // Made by the version needed to extract, on MS-DOS
+        rebuilt.extend_from_slice(&entry.fields[..2]);
+        rebuilt.extend_from_slice(&entry.fields);
+        rebuilt.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        // Extra field and comment lengths, disk, internal and external attributes
+        rebuilt.extend_from_slice(&[0; 12]);
+        rebuilt.extend_from_slice(&u32::try_from(entry.offset).ok()?.to_le_bytes());
+        rebuilt.extend_from_slice(&entry.name);
+    }
+    let cd_size = u32::try_from(rebuilt.len()).ok()? - cd_offset;
+    rebuilt.extend_from_slice(b"PK\x05\x06\0\0\0\0");
+    rebuilt.extend_from_slice(&entries.to_le_bytes());
+    rebuilt.extend_from_slice(&entries.to_le_bytes());
+    rebuilt.extend_from_slice(&cd_size.to_le_bytes());
+    rebuilt.extend_from_slice(&cd_offset.to_le_bytes());
+    rebuilt.extend_from_slice(&0u16.to_le_bytes());
+    Some(rebuilt)
+}
+
+/// OOXML package: valid when its local entries are followed by a central
+/// directory and end record that end the file
+pub struct OoxmlDocument(pub &'static str);
+
+impl FileTypeHandler for OoxmlDocument {
+    fn file_types(&self) -> &[&'static str] {
+        std::slice::from_ref(&self.0)
+    }
+
+    fn validate(&self, data: &[u8]) -> ValidationStatus {
+        let walk = zip_local_entries(data);
+        if walk.entries.is_empty() {
+            ValidationStatus::Invalid
+        } else if walk.at_central && zip_eocd_after(data, walk.end) == Some(data.len()) {
+            ValidationStatus::Valid
+        } else {
+            ValidationStatus::MajorIssues
+        }
+    }
+
+    fn repair(&self, data: &[u8]) -> Option<(Vec<u8>, Repair)> {
+        Some((rebuild_central_directory(data)?, Repair::RebuildCentralDirectory))
+    }
+
+    fn preferred_extension(&self) -> &str {
+        self.0
+    }
+}
+
+/// Text of a truncated OLE2 document. Cleaning turns the carved container
+/// into its text, one string or paragraph per line; the document itself is
+/// lost, so the text has minor issues
+pub struct OfficeText;
+
+impl FileTypeHandler for OfficeText {
+    fn file_types(&self) -> &[&'static str] {
+        &[OFFICE_TEXT]
+    }
+
+    /// Content that is not a compound file (already decoded) is kept
+    fn clean<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        let Some((_, pieces)) = ole_text(data) else {
+            return Cow::Borrowed(data);
+        };
+        let mut text = String::new();
+        for piece in pieces {
+            if !piece.continues && !text.is_empty() {
+                text.push('\n');
+            }
+            let stored = &data[piece.range];
+            let chars: Vec<char> = match piece.utf16 {
+                true => char::decode_utf16(stored.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])))
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect(),
+                false => stored.iter().map(|&b| b as char).collect(),
+            };
+            // Paragraph, line and page breaks; table cell ends; field marks dropped
+            text.extend(chars.into_iter().filter_map(|c| match c {
+                '\r' | '\x0B' | '\x0C' => Some('\n'),
+                '\x07' => Some('\t'),
+                '\t' | '\n' => Some(c),
+                c if c.is_control() => None,
+                c => Some(c),
+            }));
+        }
+        // Never longer than the container (CJK text grows from UTF-16)
+        let mut end = text.len().min(data.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        Cow::Owned(text.into_bytes())
+    }
+
+    fn extract_title(&self, content: &str) -> Option<String> {
+        extract_first_line(content)
+    }
+
+    fn validate(&self, data: &[u8]) -> ValidationStatus {
+        match data.iter().any(|b| !b.is_ascii_whitespace()) {
+            true => ValidationStatus::MinorIssues,
+            false => ValidationStatus::MajorIssues,
+        }
+    }
+
+    fn preferred_extension(&self) -> &str {
+        "txt"
+    }
+
+    fn is_text(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carver::{carve, CarvedFile};
+    use crate::recovery::FileTypeRegistry;
+
+    /// FAT entry and DIFAT slot of an unused sector
+    const FREE: u32 = 0xFFFF_FFFF;
+
+    fn types(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    /// Stored local entry; with `descriptor` its CRC and sizes follow the data
+    fn zip_entry(name: &[u8], body: &[u8], descriptor: bool) -> Vec<u8> {
+        let len = body.len() as u32;
+        let mut entry = b"PK\x03\x04\x14\0".to_vec();
+        entry.extend_from_slice(&[if descriptor { 8 } else { 0 }, 0, 0, 0, 0, 0, 0, 0]);
+        let sizes = if descriptor { [0, 0, 0] } else { [0x1234_5678, len, len] };
+        sizes.iter().for_each(|field| entry.extend_from_slice(&field.to_le_bytes()));
+        entry.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        entry.extend_from_slice(&0u16.to_le_bytes());
+        entry.extend_from_slice(name);
+        entry.extend_from_slice(body);
+        if descriptor {
+            entry.extend_from_slice(b"PK\x07\x08");
+            [0x1234_5678, len, len].iter().for_each(|field| entry.extend_from_slice(&field.to_le_bytes()));
+        }
+        entry
+    }
+
+    /// Word 97 compound file: directory in sector 0, FAT in sector 1 and a
+    /// five-sector WordDocument stream whose text (UTF-16) starts at 0x800
+    fn word_document(text: &str) -> Vec<u8> {
+        let mut file = vec![0u8; 512];
+        file[..8].copy_from_slice(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1");
+        // Version 3, byte order, sector shifts, one FAT sector, directory at
+        // sector 0, mini stream cutoff, no mini FAT or DIFAT sectors
+        let header: [(usize, u32); 8] = [
+            (0x18, 0x0003_003E),
+            (0x1C, 0x0009_FFFE),
+            (0x20, 6),
+            (0x2C, 1),
+            (0x30, 0),
+            (0x38, 4096),
+            (0x3C, 0xFFFF_FFFE),
+            (0x44, 0xFFFF_FFFE),
+        ];
+        for (offset, value) in header {
+            file[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        file[0x4C..].chunks_exact_mut(4).for_each(|id| id.copy_from_slice(&FREE.to_le_bytes()));
+        file[0x4C..0x50].copy_from_slice(&1u32.to_le_bytes());
+
+        let mut directory = vec![0u8; 512];
+        let entries = [(0, "Root Entry", 5u8, 0xFFFF_FFFEu32, 0u32), (1, "WordDocument", 2, 2, 2560)];
+        for (entry, name, kind, start, size) in entries {
+            let entry = &mut directory[entry * 128..(entry + 1) * 128];
+            let name: Vec<u8> = name.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect();
+            entry[..name.len()].copy_from_slice(&name);
+            entry[0x40..0x42].copy_from_slice(&(name.len() as u16).to_le_bytes());
+            entry[0x42] = kind;
+            entry[0x74..0x78].copy_from_slice(&start.to_le_bytes());
+            entry[0x78..0x7C].copy_from_slice(&size.to_le_bytes());
+        }
+        let fat: Vec<u8> = [0xFFFF_FFFE, 0xFFFF_FFFD, 3, 4, 5, 6, 0xFFFF_FFFE]
+            .into_iter()
+            .chain(std::iter::repeat(FREE))
+            .take(128)
+            .flat_map(u32::to_le_bytes)
+            .collect();
+        let mut stream = vec![0u8; 2560];
+        let chars: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        stream[..4].copy_from_slice(b"\xEC\xA5\xC1\x00");
+        stream[0x18..0x1C].copy_from_slice(&0x800u32.to_le_bytes());
+        stream[0x4C..0x50].copy_from_slice(&(chars.len() as u32 / 2).to_le_bytes());
+        stream[0x800..0x800 + chars.len()].copy_from_slice(&chars);
+        [file, directory, fat, stream].concat()
+    }
+
+    #[test]
+    fn test_office_documents_carve_and_repair() {
+        let registry = FileTypeRegistry::builtin();
+        let entries = [
+            zip_entry(b"[Content_Types].xml", b"<Types/>", false),
+            zip_entry(b"word/document.xml", b"<w:document>Report</w:document>", true),
+        ]
+        .concat();
+        let complete = rebuild_central_directory(&entries).unwrap();
+        assert_eq!(registry.validate(&complete, "docx"), ValidationStatus::Valid);
+
+        // Kept once as docx when zip is carved too; without its directory it
+        // ends after the last entry and the repair restores the directory
+        let carved = carve(&complete, 1, &types(&["docx", "xlsx", "zip"]));
+        assert_eq!(carved, vec![CarvedFile { offset: 0, size: complete.len(), file_type: "docx", preview_of: None }]);
+        let cut = [&entries[..], &[0x20; 100]].concat();
+        assert_eq!(carve(&cut, 1, &types(&["docx", "zip"]))[0].size, entries.len());
+        assert_eq!(registry.validate(&entries, "docx"), ValidationStatus::MajorIssues);
+        assert_eq!(registry.repair(&entries, "docx"), Some((complete.clone(), Repair::RebuildCentralDirectory)));
+        assert_eq!(registry.extension("docx"), "docx");
+        assert!(carve(&complete, 1, &types(&["pptx"])).is_empty());
+
+        let doc = word_document("Отчёт за год\rВторой абзац\x07");
+        let carved = carve(&[&doc[..], &[0x20; 700]].concat(), 1, &types(&["doc", "xls", "ppt"]));
+        assert_eq!(carved, vec![CarvedFile { offset: 0, size: doc.len(), file_type: "doc", preview_of: None }]);
+
+        // Cut inside the text: the text carved so far, decoded
+        let truncated = &doc[..512 * 3 + 0x800 + 2 * 15];
+        let carved = carve(truncated, 1, &types(&["doc", "xls"]));
+        let text_file = CarvedFile { offset: 0, size: truncated.len(), file_type: OFFICE_TEXT, preview_of: Some("doc") };
+        assert_eq!(carved, vec![text_file]);
+        let text = registry.clean(truncated, OFFICE_TEXT);
+        assert_eq!(std::str::from_utf8(&text).unwrap(), "Отчёт за год\nВт");
+        assert_eq!(registry.clean(&text, OFFICE_TEXT), text);
+        assert_eq!(registry.extract_title(&text, OFFICE_TEXT).as_deref(), Some("Отчёт за год"));
+        assert_eq!(registry.validate(&text, OFFICE_TEXT), ValidationStatus::MinorIssues);
+        assert_eq!(registry.extension(OFFICE_TEXT), "txt");
+    }
+}
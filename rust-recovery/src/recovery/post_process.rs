@@ -0,0 +1,153 @@
+//! External post-processing of recovered files (`--post-process-cmd`)
+//!
+//! Each file is handed to a user command as soon as it is written, e.g. an
+//! AV scanner (`clamscan {}`) or an indexer. `{}` in the command is replaced
+//! by the shell-quoted file path (the path is appended when there is no
+//! `{}`); with `--pipe-output` the file's bytes are also written to the
+//! command's stdin. The command runs through `sh -c`, one file at a time,
+//! and its stdout/stderr go to `post_process.log` in the output directory.
+//! The exit code lands in the report entry of the file.
+
+use crate::housekeeping::RotatingFile;
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Replaced by the file path in the command
+pub const FILE_PLACEHOLDER: &str = "{}";
+/// Command output log in the output directory
+pub const POST_PROCESS_LOG_FILE: &str = "post_process.log";
+
+/// Outcome of the command for one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostProcessResult {
+    /// Exit code; None when the command did not start or was killed by a signal
+    pub exit_code: Option<i32>,
+    /// Why there is no exit code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PostProcessResult {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    fn failed(error: String) -> Self {
+        Self { exit_code: None, error: Some(error) }
+    }
+}
+
+/// Single-quote `text` for `sh`
+pub fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Runs the command on recovered files
+pub struct PostProcessor {
+    command: String,
+    pipe: bool,
+    log: Option<RotatingFile>,
+}
+
+impl PostProcessor {
+    pub fn new(command: &str, pipe: bool) -> Self {
+        Self { command: command.to_string(), pipe, log: None }
+    }
+
+    /// Append command output to `path` instead of discarding it, rotating
+    /// the log at `max_bytes`
+    pub fn with_log(mut self, path: &Path, max_bytes: Option<u64>) -> std::io::Result<Self> {
+        self.log = Some(RotatingFile::append(path)?.with_max_bytes(max_bytes));
+        Ok(self)
+    }
+
+    /// Command line for one file
+    pub fn command_line(&self, path: &Path) -> String {
+        let quoted = shell_quote(&path.to_string_lossy());
+        if self.command.contains(FILE_PLACEHOLDER) {
+            self.command.replace(FILE_PLACEHOLDER, &quoted)
+        } else if self.pipe {
+            self.command.clone()
+        } else {
+            format!("{} {}", self.command, quoted)
+        }
+    }
+
+    /// Run the command on the written file at `path` and wait for it
+    pub fn run(&mut self, path: &Path) -> PostProcessResult {
+        let command_line = self.command_line(path);
+        let output = match self.log.as_mut().map(RotatingFile::file) {
+            Some(Ok(log)) => {
+                let _ = writeln!(log, "== {}", command_line);
+                log.try_clone().ok()
+            }
+            _ => None,
+        };
+        let output_stdio = || output.as_ref().and_then(|file| file.try_clone().ok()).map_or_else(Stdio::null, Stdio::from);
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&command_line)
+            .stdin(if self.pipe { Stdio::piped() } else { Stdio::null() })
+            .stdout(output_stdio())
+            .stderr(output_stdio());
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => return PostProcessResult::failed(format!("failed to start: {}", e)),
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            match std::fs::read(path).and_then(|data| stdin.write_all(&data)) {
+                // A command may stop reading early (e.g. after a header check)
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return PostProcessResult::failed(format!("failed to feed stdin: {}", e));
+                }
+                _ => {}
+            }
+        }
+
+        match child.wait() {
+            Ok(status) => match status.code() {
+                Some(code) => PostProcessResult { exit_code: Some(code), error: None },
+                None => PostProcessResult::failed(format!("terminated: {}", status)),
+            },
+            Err(e) => PostProcessResult::failed(format!("failed to wait: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_post_process_exit_codes() {
+        let dir = std::env::temp_dir().join(format!("post_process_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("it's recovered.txt");
+        std::fs::write(&file, b"EICAR test\n").unwrap();
+        let log = dir.join(POST_PROCESS_LOG_FILE);
+
+        let mut grep = PostProcessor::new("grep -q EICAR {}", false).with_log(&log, None).unwrap();
+        assert_eq!(grep.run(&file), PostProcessResult { exit_code: Some(0), error: None });
+        let mut missing = PostProcessor::new("grep -q CLEAN", false);
+        assert_eq!(missing.run(&file).exit_code, Some(1));
+
+        let mut piped = PostProcessor::new("wc -c", true).with_log(&log, None).unwrap();
+        assert_eq!(piped.command_line(&file), "wc -c");
+        assert!(piped.run(&file).succeeded());
+        // Stops reading stdin at once
+        assert_eq!(PostProcessor::new("exit 3", true).run(&file).exit_code, Some(3));
+
+        let output = std::fs::read_to_string(&log).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(output.contains("== grep -q EICAR '"));
+        assert!(output.contains("it'\\''s recovered.txt'\n"));
+        assert!(output.lines().any(|line| line.trim() == "11"));
+    }
+}
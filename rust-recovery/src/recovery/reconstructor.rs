@@ -1,29 +1,32 @@
 use regex::Regex;
 use std::sync::OnceLock;
+use unicode_normalization::UnicodeNormalization;
 
-/// Extract a meaningful title from file content
-pub fn extract_title(data: &[u8], file_type: &str) -> Option<String> {
-    // Try to convert to UTF-8 string (lossy)
-    let content = String::from_utf8_lossy(data);
-    
-    match file_type {
-        "html" | "htm" => extract_html_title(&content),
-        "json" => extract_json_title(&content),
-        "txt" | "md" => extract_first_line(&content),
-        _ => None,
+use super::title::{detect_language, transliterate};
+
+/// Filename part for `title`; with `translit` Cyrillic is transliterated,
+/// accents are dropped ("é" as "e") and the result is plain ASCII
+pub fn title_filename(title: &str, translit: bool) -> String {
+    if translit {
+        let latin = transliterate(title, detect_language(title));
+        sanitize_filename(&latin.nfd().filter(char::is_ascii).collect::<String>())
+    } else {
+        sanitize_filename(title)
     }
 }
 
-fn extract_html_title(content: &str) -> Option<String> {
+/// Text of the `<title>` element
+pub fn extract_html_title(content: &str) -> Option<String> {
     static TITLE_REGEX: OnceLock<Regex> = OnceLock::new();
     let re = TITLE_REGEX.get_or_init(|| Regex::new(r"(?i)<title>(.*?)</title>").unwrap());
 
     re.captures(content)
         .and_then(|cap| cap.get(1))
-        .map(|m| sanitize_filename(m.as_str()))
+        .map(|m| m.as_str().to_string())
 }
 
-fn extract_json_title(content: &str) -> Option<String> {
+/// First "title" or "name" value of small JSON content
+pub fn extract_json_title(content: &str) -> Option<String> {
     // Simple heuristic for JSON titles
     if content.len() > 1024 * 10 
     { 
@@ -35,24 +38,26 @@ fn extract_json_title(content: &str) -> Option<String> {
 
     re.captures(content)
         .and_then(|cap| cap.get(2))
-        .map(|m| sanitize_filename(m.as_str()))
+        .map(|m| m.as_str().to_string())
 }
 
-fn extract_first_line(content: &str) -> Option<String> {
+/// First non-empty line
+pub fn extract_first_line(content: &str) -> Option<String> {
     content.lines()
         .find(|line| !line.trim().is_empty())
-        .map(|line| sanitize_filename(line))
+        .map(|line| line.to_string())
 }
 
 fn sanitize_filename(name: &str) -> String {
-    let sanitized: String = name.chars()
+    // NFC first: a decomposed "й" (и + combining breve) would lose its breve
+    let sanitized: String = name.nfc()
         .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
         .collect();
-    
-    let trimmed = sanitized.trim();
-    if trimmed.len() > 50 {
-        format!("{}...", &trimmed[..47])
+    let collapsed = sanitized.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if collapsed.chars().count() > 50 {
+        format!("{}...", collapsed.chars().take(47).collect::<String>())
     } else {
-        trimmed.to_string()
+        collapsed
     }
 }
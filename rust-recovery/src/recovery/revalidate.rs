@@ -0,0 +1,402 @@
+//! Validation pass over the written files, with mild repairs
+//!
+//! Once the scan has saved its files, each one is read back and checked by
+//! the handler of its type: JSON must parse (a document or JSON Lines),
+//! HTML tags must balance, CSV rows must have the header's column count.
+//! A file that fails may be repaired: a cut last record is dropped
+//! (`StripPartialRecord`) or an unterminated string and the brackets left
+//! open are closed (`CloseUnterminated`); an Office package that lost its
+//! central directory gets a rebuilt one (`RebuildCentralDirectory`). A repair is kept only when the
+//! result validates better; the file is replaced and the report records the
+//! repair, so `verify` applies it again to the rebuilt file.
+
+use super::boundary::{csv_end, csv_rows};
+use super::file_types::FileTypeRegistry;
+use crate::housekeeping::TEMP_SUFFIX;
+use crate::matcher::validator::is_probably_json;
+use crate::report::{RecoveredFile, ValidationStatus};
+use serde::de::IgnoredAny;
+use std::fs;
+use std::path::Path;
+
+/// Elements without an end tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+/// Elements whose end tag may be left out
+const OPTIONAL_END_ELEMENTS: &[&str] = &[
+    "html", "head", "body", "p", "li", "dt", "dd", "tr", "td", "th", "thead", "tbody", "tfoot", "option", "colgroup",
+];
+/// Elements whose content is not markup
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+/// A change made to a written file so it validates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repair {
+    /// The cut last record (JSON Lines line, array element, CSV row) was dropped
+    StripPartialRecord,
+    /// An unterminated string and the open brackets or elements were closed
+    CloseUnterminated,
+    /// A ZIP central directory was rebuilt from the local entries
+    RebuildCentralDirectory,
+}
+
+impl Repair {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::StripPartialRecord => "strip_partial_record",
+            Self::CloseUnterminated => "close_unterminated",
+            Self::RebuildCentralDirectory => "rebuild_central_directory",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "strip_partial_record" => Some(Self::StripPartialRecord),
+            "close_unterminated" => Some(Self::CloseUnterminated),
+            "rebuild_central_directory" => Some(Self::RebuildCentralDirectory),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of `revalidate`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RevalidationSummary {
+    pub checked: usize,
+    pub upgraded: usize,
+    pub downgraded: usize,
+    pub repaired: usize,
+}
+
+/// Re-read the files saved in `dir`, validate them in full and, with
+/// `repair`, fix those that fail; statuses, repairs and hashes are updated
+/// in `files`. Files that cannot be read (not saved) are left as they are
+pub fn revalidate(
+    dir: &Path,
+    files: &mut [RecoveredFile],
+    file_types: &FileTypeRegistry,
+    repair: bool,
+) -> RevalidationSummary {
+    let mut summary = RevalidationSummary::default();
+    for file in files.iter_mut() {
+        let path = super::writer::long_path(&dir.join(&file.filename)).into_owned();
+        let Ok(data) = fs::read(&path) else {
+            continue;
+        };
+        summary.checked += 1;
+        let mut status = file_types.validate_repaired(&data, &file.file_type, file.utf8_repaired_bytes as usize);
+
+        // A repair would move the holes listed in the gap map (`--gap-fill`)
+        let fix = file_types
+            .repair(&data, &file.file_type)
+            .filter(|_| repair && status != ValidationStatus::Valid && file.gaps.is_empty());
+        if let Some((repaired, kind)) = fix {
+            let repaired_status = file_types.validate(&repaired, &file.file_type);
+            if severity(&repaired_status) < severity(&status) && replace_file(&path, &repaired).is_ok() {
+                // Bytes were added or removed: usable, but not as recovered
+                status = ValidationStatus::MinorIssues;
+                file.repair = Some(kind.name().to_string());
+                file.size_bytes = repaired.len() as u64;
+                file.size_kb = file.size_bytes / 1024;
+                file.sha256 = crate::matcher::sha256_hash(&repaired);
+                file.md5 = crate::matcher::md5_hash(&repaired);
+                file.fuzzy_hash = crate::hashing::fuzzy_hash(&repaired);
+                summary.repaired += 1;
+            }
+        }
+
+        match severity(&status).cmp(&severity(&file.validation_status)) {
+            std::cmp::Ordering::Less => summary.upgraded += 1,
+            std::cmp::Ordering::Greater => summary.downgraded += 1,
+            std::cmp::Ordering::Equal => {}
+        }
+        file.validation_status = status;
+    }
+    summary
+}
+
+/// Write `data` to a `.tmp` sibling and rename it over `path`
+fn replace_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut temp = path.as_os_str().to_os_string();
+    temp.push(TEMP_SUFFIX);
+    fs::write(&temp, data)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+/// Order of statuses from best to worst
+fn severity(status: &ValidationStatus) -> u8 {
+    match status {
+        ValidationStatus::Valid => 0,
+        ValidationStatus::MinorIssues => 1,
+        ValidationStatus::Unknown => 2,
+        ValidationStatus::MajorIssues => 3,
+        ValidationStatus::Invalid => 4,
+    }
+}
+
+/// JSON that parses (one document, or JSON Lines) is valid; content that
+/// looks like JSON has minor issues
+pub fn json_status(data: &[u8]) -> ValidationStatus {
+    if json_parses(data) {
+        ValidationStatus::Valid
+    } else if is_probably_json(data) {
+        ValidationStatus::MinorIssues
+    } else {
+        ValidationStatus::MajorIssues
+    }
+}
+
+fn json_parses(data: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return false;
+    };
+    let text = text.trim();
+    if serde_json::from_str::<IgnoredAny>(text).is_ok() {
+        return true;
+    }
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty()).peekable();
+    text.contains('\n') && lines.peek().is_some() && lines.all(|line| serde_json::from_str::<IgnoredAny>(line).is_ok())
+}
+
+/// Brackets still open at the end of the data
+struct JsonTail {
+    open: Vec<u8>,
+    in_string: bool,
+    /// Last comma between the elements of the outermost value
+    last_separator: Option<usize>,
+}
+
+/// None when a bracket closes that was never opened
+fn json_tail(data: &[u8]) -> Option<JsonTail> {
+    let mut tail = JsonTail { open: Vec::new(), in_string: false, last_separator: None };
+    let mut escaped = false;
+    for (index, &byte) in data.iter().enumerate() {
+        if tail.in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => tail.in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => tail.in_string = true,
+            b'{' => tail.open.push(b'}'),
+            b'[' => tail.open.push(b']'),
+            b'}' | b']' => {
+                tail.open.pop()?;
+            }
+            b',' if tail.open.len() == 1 => tail.last_separator = Some(index),
+            _ => {}
+        }
+    }
+    Some(tail)
+}
+
+/// Drop a cut last JSON Lines line; close a cut document, or drop its cut
+/// last element and close it
+pub fn repair_json(data: &[u8]) -> Option<(Vec<u8>, Repair)> {
+    let text = std::str::from_utf8(data).ok()?.trim_end();
+    if let Some((lines, _)) = text.rsplit_once('\n') {
+        if json_parses(lines.as_bytes()) && !json_parses(text.as_bytes()) {
+            return Some((format!("{}\n", lines).into_bytes(), Repair::StripPartialRecord));
+        }
+    }
+
+    let data = text.as_bytes();
+    let tail = json_tail(data)?;
+    if tail.open.is_empty() {
+        return None;
+    }
+    let mut closed = data.to_vec();
+    if tail.in_string {
+        closed.push(b'"');
+    }
+    closed.extend(tail.open.iter().rev());
+    if json_parses(&closed) {
+        return Some((closed, Repair::CloseUnterminated));
+    }
+
+    let mut stripped = data[..tail.last_separator?].to_vec();
+    stripped.push(tail.open[0]);
+    json_parses(&stripped).then_some((stripped, Repair::StripPartialRecord))
+}
+
+/// Elements left open and tags that did not match, from a tag scan
+struct HtmlTags {
+    tags: usize,
+    open: Vec<String>,
+    mismatched: usize,
+    /// Start of a tag or comment the data ends in
+    cut_at: Option<usize>,
+}
+
+fn html_tags(data: &[u8]) -> HtmlTags {
+    let mut scan = HtmlTags { tags: 0, open: Vec::new(), mismatched: 0, cut_at: None };
+    let mut position = 0;
+    while let Some(offset) = memchr::memchr(b'<', &data[position..]) {
+        let start = position + offset;
+        let rest = &data[start + 1..];
+        let terminator: &[u8] = if rest.starts_with(b"!--") { b"-->" } else { b">" };
+        let Some(length) = find(rest, terminator, !rest.starts_with(b"!")) else {
+            scan.cut_at = Some(start);
+            break;
+        };
+        position = start + 1 + length + terminator.len();
+        let tag = &rest[..length];
+        let (closing, name) = match tag.first() {
+            Some(b'/') => (true, tag_name(&tag[1..])),
+            Some(byte) if byte.is_ascii_alphabetic() => (false, tag_name(tag)),
+            // Comments, doctype, processing instructions, a stray '<'
+            _ => continue,
+        };
+        scan.tags += 1;
+        if closing {
+            match scan.open.iter().rposition(|open| *open == name) {
+                Some(at) => {
+                    let unclosed = scan.open.drain(at..).skip(1);
+                    scan.mismatched += unclosed.filter(|open| !OPTIONAL_END_ELEMENTS.contains(&open.as_str())).count();
+                }
+                None => scan.mismatched += 1,
+            }
+        } else if !(VOID_ELEMENTS.contains(&name.as_str()) || tag.ends_with(b"/")) {
+            if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+                let end_tag = format!("</{}", name);
+                match find(&data[position..], end_tag.as_bytes(), false) {
+                    Some(length) => position += length,
+                    None => {
+                        scan.cut_at = Some(data.len());
+                        scan.open.push(name);
+                        break;
+                    }
+                }
+            }
+            scan.open.push(name);
+        }
+    }
+    scan
+}
+
+/// Lowercase element name at the start of a tag
+fn tag_name(tag: &[u8]) -> String {
+    let end = tag.iter().position(|b| !(b.is_ascii_alphanumeric() || *b == b'-')).unwrap_or(tag.len());
+    String::from_utf8_lossy(&tag[..end]).to_ascii_lowercase()
+}
+
+/// Offset of `needle` in `haystack` (ASCII case-insensitive); with `quotes`,
+/// not inside a quoted attribute value
+fn find(haystack: &[u8], needle: &[u8], quotes: bool) -> Option<usize> {
+    let mut quote = None;
+    for index in 0..haystack.len() {
+        let byte = haystack[index];
+        match quote {
+            Some(open) if byte == open => quote = None,
+            Some(_) => continue,
+            None if quotes && (byte == b'"' || byte == b'\'') => quote = Some(byte),
+            None if haystack[index..].len() >= needle.len()
+                && haystack[index..index + needle.len()].eq_ignore_ascii_case(needle) =>
+            {
+                return Some(index)
+            }
+            None => {}
+        }
+    }
+    None
+}
+
+/// Balanced tags are valid, markup with unclosed or stray tags has minor
+/// issues, content without tags major ones
+pub fn html_status(data: &[u8]) -> ValidationStatus {
+    let scan = html_tags(data);
+    let unclosed = scan.open.iter().filter(|open| !OPTIONAL_END_ELEMENTS.contains(&open.as_str())).count();
+    if scan.tags == 0 {
+        ValidationStatus::MajorIssues
+    } else if unclosed + scan.mismatched == 0 && scan.cut_at.is_none() {
+        ValidationStatus::Valid
+    } else {
+        ValidationStatus::MinorIssues
+    }
+}
+
+/// Drop a cut last tag and close the elements left open
+pub fn repair_html(data: &[u8]) -> Option<(Vec<u8>, Repair)> {
+    let scan = html_tags(data);
+    if scan.mismatched > 0 || (scan.open.is_empty() && scan.cut_at.is_none()) {
+        return None;
+    }
+    let mut closed = data[..scan.cut_at.unwrap_or(data.len())].to_vec();
+    for name in scan.open.iter().rev() {
+        closed.extend_from_slice(format!("</{}>", name).as_bytes());
+    }
+    Some((closed, Repair::CloseUnterminated))
+}
+
+/// Rows with the header's column count: all is valid, nine in ten or more
+/// minor issues
+pub fn csv_status(data: &[u8]) -> ValidationStatus {
+    let mut rows = csv_rows(data);
+    let Some(header) = rows.next().filter(|header| header.terminated) else {
+        return ValidationStatus::MajorIssues;
+    };
+    let (mut count, mut bad) = (0usize, 0usize);
+    for row in rows {
+        count += 1;
+        bad += usize::from(row.fields != header.fields);
+    }
+    if bad == 0 {
+        ValidationStatus::Valid
+    } else if bad * 10 <= count {
+        ValidationStatus::MinorIssues
+    } else {
+        ValidationStatus::MajorIssues
+    }
+}
+
+/// Drop the rows after the last row with the header's column count
+pub fn repair_csv(data: &[u8]) -> Option<(Vec<u8>, Repair)> {
+    let end = csv_end(data)?;
+    (end < data.len()).then(|| (data[..end].to_vec(), Repair::StripPartialRecord))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_validation_and_repairs() {
+        assert_eq!(json_status(b"{\"a\": [1, 2]}\n"), ValidationStatus::Valid);
+        assert_eq!(json_status(b"{\"a\": 1}\n{\"a\": 2}\n"), ValidationStatus::Valid);
+
+        let cut_line = b"{\"a\": 1}\n{\"a\": 2}\n{\"a\": 3, \"b";
+        assert_eq!(repair_json(cut_line), Some((b"{\"a\": 1}\n{\"a\": 2}\n".to_vec(), Repair::StripPartialRecord)));
+        let cut_string = b"{\"items\": [{\"title\": \"Intro";
+        let (closed, repair) = repair_json(cut_string).unwrap();
+        assert_eq!((closed.as_slice(), repair), (&b"{\"items\": [{\"title\": \"Intro\"}]}"[..], Repair::CloseUnterminated));
+        let cut_record = b"[{\"id\": 1}, {\"id\": 2}, {\"id\": ";
+        assert_eq!(repair_json(cut_record), Some((b"[{\"id\": 1}, {\"id\": 2}]".to_vec(), Repair::StripPartialRecord)));
+        assert_eq!(repair_json(b"{\"a\": 1}} trailing"), None);
+        assert_eq!(Repair::from_name(Repair::CloseUnterminated.name()), Some(Repair::CloseUnterminated));
+    }
+
+    #[test]
+    fn test_html_and_csv_validation() {
+        let page = b"<!DOCTYPE html><html><head><title>a</title><script>if (a<b) {}</script></head>\
+            <body><p>one<p>two<br><img src=\"x>y\"/><!-- <div> --></body></html>";
+        assert_eq!(html_status(page), ValidationStatus::Valid);
+        let cut = b"<html><body><div><p>text</p><span cla";
+        assert_eq!(html_status(cut), ValidationStatus::MinorIssues);
+        let (closed, repair) = repair_html(cut).unwrap();
+        assert_eq!(closed, b"<html><body><div><p>text</p></div></body></html>");
+        assert_eq!((html_status(&closed), repair), (ValidationStatus::Valid, Repair::CloseUnterminated));
+        assert_eq!(html_status(b"plain text"), ValidationStatus::MajorIssues);
+
+        assert_eq!(csv_status(b"id,title\n1,\"a, b\"\n2,c\n"), ValidationStatus::Valid);
+        let cut = b"id,title\n1,a\n2,b\n3";
+        assert_eq!(csv_status(cut), ValidationStatus::MajorIssues);
+        assert_eq!(repair_csv(cut), Some((b"id,title\n1,a\n2,b\n".to_vec(), Repair::StripPartialRecord)));
+    }
+}
@@ -0,0 +1,148 @@
+//! Title language detection and ASCII transliteration for filenames
+//!
+//! Titles are kept as written in the report; `--filename-translit` only
+//! changes the filename, for output filesystems or tools that mangle Cyrillic.
+
+/// Language of a recovered title, detected from its script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleLanguage {
+    Russian,
+    Ukrainian,
+    English,
+    Unknown,
+}
+
+impl TitleLanguage {
+    /// ISO 639-1 code ("und" when undetermined)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Russian => "ru",
+            Self::Ukrainian => "uk",
+            Self::English => "en",
+            Self::Unknown => "und",
+        }
+    }
+}
+
+/// Detect the title language from its letters. Cyrillic titles are Russian
+/// unless Ukrainian-only letters (і ї є ґ) outnumber Russian-only ones (ё ы э ъ).
+pub fn detect_language(title: &str) -> TitleLanguage {
+    let (mut cyrillic, mut latin, mut other) = (0, 0, 0);
+    let (mut russian, mut ukrainian) = (0, 0);
+
+    for c in title.chars().filter(|c| c.is_alphabetic()) {
+        match c.to_lowercase().next().unwrap_or(c) {
+            'і' | 'ї' | 'є' | 'ґ' => {
+                cyrillic += 1;
+                ukrainian += 1;
+            }
+            'ё' | 'ы' | 'э' | 'ъ' => {
+                cyrillic += 1;
+                russian += 1;
+            }
+            '\u{0400}'..='\u{04FF}' => cyrillic += 1,
+            'a'..='z' => latin += 1,
+            _ => other += 1,
+        }
+    }
+
+    if cyrillic > latin + other {
+        if ukrainian > russian {
+            TitleLanguage::Ukrainian
+        } else {
+            TitleLanguage::Russian
+        }
+    } else if latin > cyrillic + other {
+        TitleLanguage::English
+    } else {
+        TitleLanguage::Unknown
+    }
+}
+
+/// Replace Cyrillic letters with their Latin spelling (Ukrainian г/и as h/y).
+/// Other characters are kept.
+pub fn transliterate(text: &str, language: TitleLanguage) -> String {
+    let ukrainian = language == TitleLanguage::Ukrainian;
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let Some(latin) = cyrillic_to_latin(lower, ukrainian) else {
+            out.push(c);
+            continue;
+        };
+        let mut letters = latin.chars();
+        match letters.next() {
+            Some(first) if c != lower => {
+                out.push(first.to_ascii_uppercase());
+                out.push_str(letters.as_str());
+            }
+            _ => out.push_str(latin),
+        }
+    }
+    out
+}
+
+fn cyrillic_to_latin(c: char, ukrainian: bool) -> Option<&'static str> {
+    Some(match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' if ukrainian => "h",
+        'г' | 'ґ' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "yo",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'и' if ukrainian => "y",
+        'и' | 'і' => "i",
+        'ї' => "yi",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ы' => "y",
+        'э' => "e",
+        'ю' => "yu",
+        'я' => "ya",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_transliterate() {
+        assert_eq!(detect_language("Лучшие моменты"), TitleLanguage::Russian);
+        assert_eq!(detect_language("Їжак і ґудзик"), TitleLanguage::Ukrainian);
+        assert_eq!(detect_language("Never Gonna Give You Up"), TitleLanguage::English);
+        assert_eq!(detect_language("12345"), TitleLanguage::Unknown);
+
+        assert_eq!(transliterate("Щука Подъезд", TitleLanguage::Russian), "Shchuka Podezd");
+        assert_eq!(transliterate("Гриби", TitleLanguage::Ukrainian), "Hryby");
+        assert_eq!(transliterate("Mix: Гриби", TitleLanguage::Russian), "Mix: Gribi");
+
+        let long = "Длинное название видео ".repeat(4);
+        assert_eq!(crate::recovery::title_filename("Щука: итоги", true), "Shchuka itogi");
+        assert_eq!(crate::recovery::title_filename(&long, false).chars().count(), 50);
+        assert!(crate::recovery::title_filename(&long, true).is_ascii());
+    }
+}
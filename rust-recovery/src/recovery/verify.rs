@@ -0,0 +1,251 @@
+//! Re-scan verification of a recovered file (`rust-recovery verify`)
+//!
+//! The report records the image extents every file was assembled from. The
+//! file is rebuilt from those extents with the current assembly code and
+//! compared byte by byte with the saved output, so a disputed file can be
+//! shown to follow from the image (or where it does not).
+
+use crate::disk::DiskImage;
+use crate::error::{RecoveryError, Result};
+use crate::recovery::gaps::{clamp_gaps, FileGap, GapCause, GapFill};
+use crate::recovery::provenance::ExtentDigest;
+use crate::recovery::{FileTypeRegistry, FragmentExtent};
+use crate::report::{RecoveredFile, ReportMetadata};
+use crate::types::Offset;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Range of file bytes that differ between the rebuilt and the saved file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteDiff {
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Recovered file entry and the run it came from
+#[derive(Debug, Clone)]
+pub struct ReportEntry {
+    pub report_path: PathBuf,
+    pub metadata: ReportMetadata,
+    pub file: RecoveredFile,
+}
+
+/// The parts of a JSON report needed to rebuild its files
+#[derive(Deserialize)]
+struct ReportFiles {
+    metadata: ReportMetadata,
+    recovered_files: Vec<RecoveredFile>,
+}
+
+/// File rebuilt by `reassemble`
+#[derive(Debug, Clone)]
+pub struct Reassembled {
+    pub data: Vec<u8>,
+    /// Extents that could not be read and were left out
+    pub unreadable: Vec<FragmentExtent>,
+    /// Invalid UTF-8 bytes replaced or dropped by the registry's `Utf8Mode`
+    pub utf8_repaired: usize,
+    /// Bytes cut after the end of the document (`FileTypeRegistry::trim`)
+    pub trimmed: usize,
+    /// Holes left for missing bytes (`GapFill` other than none)
+    pub gaps: Vec<FileGap>,
+    /// `ExtentDigest` of the extents as read, before cleaning
+    pub extents_sha256: String,
+}
+
+/// Assemble a file from image extents: fragments are concatenated in order
+/// and the content is cleaned (and trimmed) by the handler of its type.
+/// Extents that cannot be read are left out and returned.
+pub fn reassemble(
+    disk: &DiskImage,
+    fragments: &[FragmentExtent],
+    file_type: &str,
+    file_types: &FileTypeRegistry,
+) -> Reassembled {
+    let read = |offset, len| disk.get_slice(offset, len).ok().map(|slice| slice.data);
+    reassemble_with(read, fragments, file_type, file_types, GapFill::None)
+}
+
+/// `reassemble` with the extents read by `read(offset, len)` (the fragment
+/// cache of a scan, else the image); None leaves the extent out. Unless
+/// `gap_fill` is none, the image bytes skipped between two extents and the
+/// unreadable extents become holes of their size; the bytes between holes
+/// are cleaned on their own, so the holes stay where they are
+pub fn reassemble_with<'d>(
+    mut read: impl FnMut(Offset, usize) -> Option<Cow<'d, [u8]>>,
+    fragments: &[FragmentExtent],
+    file_type: &str,
+    file_types: &FileTypeRegistry,
+    gap_fill: GapFill,
+) -> Reassembled {
+    let mut data = Vec::new();
+    let mut run = Vec::new();
+    let mut unreadable = Vec::new();
+    let mut gaps = Vec::new();
+    let mut digest = ExtentDigest::default();
+    let mut utf8_repaired = 0;
+    let mut hole = |data: &mut Vec<u8>, run: &mut Vec<u8>, len: u64, image_offset: Offset, cause: GapCause| {
+        let (cleaned, repaired) = file_types.clean_counted(run, file_type);
+        data.extend_from_slice(&cleaned);
+        utf8_repaired += repaired;
+        run.clear();
+        gaps.push(FileGap { offset: data.len() as u64, len, image_offset, cause });
+        data.extend(gap_fill.hole(len as usize));
+    };
+    let mut end: Option<u64> = None;
+    for fragment in fragments {
+        let start = fragment.offset.as_u64();
+        match end {
+            Some(end) if gap_fill != GapFill::None && start > end => {
+                hole(&mut data, &mut run, start - end, Offset::new(end), GapCause::NotFound)
+            }
+            _ => {}
+        }
+        let bytes = fragment.size.as_usize().and_then(|len| read(fragment.offset, len));
+        digest.update(bytes.as_deref());
+        match bytes {
+            Some(bytes) => run.extend_from_slice(&bytes),
+            None => {
+                unreadable.push(*fragment);
+                if gap_fill != GapFill::None {
+                    hole(&mut data, &mut run, fragment.size.as_u64(), fragment.offset, GapCause::Unreadable);
+                }
+            }
+        }
+        end = Some(start + fragment.size.as_u64());
+    }
+    let (cleaned, repaired) = file_types.clean_counted(&run, file_type);
+    utf8_repaired += repaired;
+    let data = if data.is_empty() {
+        cleaned
+    } else {
+        data.extend_from_slice(&cleaned);
+        Cow::Owned(data)
+    };
+    let (data, trimmed) = file_types.trim(data, file_type);
+    clamp_gaps(&mut gaps, data.len() as u64);
+    Reassembled { data: data.into_owned(), unreadable, utf8_repaired, trimmed, gaps, extents_sha256: digest.finish() }
+}
+
+/// Differing byte ranges; bytes past the end of the shorter input count as different
+pub fn diff_ranges(expected: &[u8], actual: &[u8]) -> Vec<ByteDiff> {
+    let mut diffs: Vec<ByteDiff> = Vec::new();
+    let common = expected.len().min(actual.len());
+    for i in (0..common).filter(|&i| expected[i] != actual[i]) {
+        match diffs.last_mut() {
+            Some(last) if last.offset + last.len == i as u64 => last.len += 1,
+            _ => diffs.push(ByteDiff { offset: i as u64, len: 1 }),
+        }
+    }
+
+    let longer = expected.len().max(actual.len());
+    if longer > common {
+        let tail = (longer - common) as u64;
+        match diffs.last_mut() {
+            Some(last) if last.offset + last.len == common as u64 => last.len += tail,
+            _ => diffs.push(ByteDiff { offset: common as u64, len: tail }),
+        }
+    }
+    diffs
+}
+
+/// Find the entry of a recovered file (by file name) in `report`, or in the
+/// newest JSON report of `output_dir/reports` that lists it
+pub fn find_report_entry(output_dir: &Path, filename: &str, report: Option<&Path>) -> Result<ReportEntry> {
+    let candidates = match report {
+        Some(path) => vec![path.to_path_buf()],
+        None => {
+            let reports_dir = output_dir.join("reports");
+            let mut reports: Vec<PathBuf> = fs::read_dir(&reports_dir)
+                .map_err(|e| RecoveryError::FileNotFound(format!("{}: {}", reports_dir.display(), e)))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            // recovery_report_YYYYMMDD_HHMMSS.json: newest last
+            reports.sort();
+            reports.reverse();
+            reports
+        }
+    };
+
+    for report_path in candidates {
+        let content = fs::read_to_string(&report_path)?;
+        // Minimal (degraded) reports do not record extents
+        let Ok(parsed) = serde_json::from_str::<ReportFiles>(&content) else {
+            if report.is_some() {
+                return Err(RecoveryError::Parse(format!("{} is not a full JSON report", report_path.display())));
+            }
+            continue;
+        };
+        // Sharded files are listed as `0002/NAME`
+        let listed_as = |f: &RecoveredFile| f.filename == filename || f.filename.rsplit('/').next() == Some(filename);
+        if let Some(file) = parsed.recovered_files.into_iter().find(listed_as) {
+            return Ok(ReportEntry { report_path, metadata: parsed.metadata, file });
+        }
+    }
+    Err(RecoveryError::FileNotFound(format!("{} is not listed in any report of {}", filename, output_dir.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Offset, Size};
+
+    #[test]
+    fn test_reassemble_and_diff() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("rust_recovery_verify_{}.img", std::process::id()));
+        let image: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&path, &image).unwrap();
+        let disk = DiskImage::open(&path).unwrap();
+
+        let fragments = [
+            FragmentExtent::new(Offset::new(1000), Size::new(100)),
+            FragmentExtent::new(Offset::new(4000), Size::new(500)),
+            FragmentExtent::new(Offset::new(200), Size::new(50)),
+        ];
+        let Reassembled { data: rebuilt, unreadable, utf8_repaired, .. } =
+            reassemble(&disk, &fragments, "bin", &FileTypeRegistry::builtin());
+        assert_eq!(unreadable, vec![fragments[1]]);
+        assert_eq!(utf8_repaired, 0);
+        assert_eq!(rebuilt.len(), 150);
+        assert_eq!(rebuilt[..100], image[1000..1100]);
+        assert_eq!(rebuilt[100..], image[200..250]);
+
+        let mut saved = rebuilt.clone();
+        saved[10] ^= 1;
+        saved[11] ^= 1;
+        saved[40] ^= 1;
+        saved.truncate(140);
+        assert_eq!(
+            diff_ranges(&rebuilt, &saved),
+            vec![ByteDiff { offset: 10, len: 2 }, ByteDiff { offset: 40, len: 1 }, ByteDiff { offset: 140, len: 10 }]
+        );
+        assert!(diff_ranges(&rebuilt, &rebuilt).is_empty());
+
+        // --gap-fill: the skipped and the unreadable bytes keep their place
+        let fragments = [
+            FragmentExtent::new(Offset::new(1000), Size::new(100)),
+            FragmentExtent::new(Offset::new(1120), Size::new(30)),
+            FragmentExtent::new(Offset::new(4000), Size::new(500)),
+        ];
+        let read = |offset, len| disk.get_slice(offset, len).ok().map(|slice| slice.data);
+        let rebuilt = reassemble_with(read, &fragments, "bin", &FileTypeRegistry::builtin(), GapFill::Zero);
+        assert_eq!(rebuilt.data.len(), 3500);
+        assert_eq!(rebuilt.data[100..120], [0; 20]);
+        assert_eq!(rebuilt.data[120..150], image[1120..1150]);
+        let gap = |offset, len, image_offset, cause| {
+            FileGap { offset, len, image_offset: Offset::new(image_offset), cause }
+        };
+        let expected = vec![
+            gap(100, 20, 1100, GapCause::NotFound),
+            gap(150, 2850, 1150, GapCause::NotFound),
+            gap(3000, 500, 4000, GapCause::Unreadable),
+        ];
+        assert_eq!(rebuilt.gaps, expected);
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,208 @@
+//! Destination of recovered files: unique names, no overwrites, sharding
+//!
+//! Files are created with `create_new`, so a name already on disk (an earlier
+//! run into the same output directory, or two titles that sanitize to the
+//! same name) is never overwritten, and names stay unique across the
+//! subdirectories: the file gets `_2`, `_3`... before the
+//! extension instead, and the name it was meant to have goes to the report
+//! (`renamed_from`). Names are compared case-insensitively, for output on
+//! FAT/exFAT/NTFS drives. With `--max-files-per-dir N` the first N files go
+//! into the directory itself and later ones into subdirectories `0002`,
+//! `0003`... of N files each; the report filename includes the subdirectory.
+//! On Windows, paths past `MAX_PATH` (long Cyrillic titles in a nested
+//! output directory) are opened through their `\\?\` form (`long_path`).
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Paths this long (in bytes, a bound on UTF-16 units) need the `\\?\`
+/// prefix: `MAX_PATH` minus room for an 8.3 name, the limit for directories
+const LONG_PATH_BYTES: usize = 248;
+
+/// `path` in a form Windows opens past `MAX_PATH`: absolute and `\\?\`
+/// prefixed when it is long, unchanged otherwise and on other platforms
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    if !cfg!(windows) || path.as_os_str().len() < LONG_PATH_BYTES {
+        return Cow::Borrowed(path);
+    }
+    match std::path::absolute(path).ok().and_then(|path| path.to_str().map(extended_length)) {
+        Some(extended) => Cow::Owned(PathBuf::from(extended)),
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// `\\?\` form of an absolute Windows path: `\\server\share` becomes
+/// `\\?\UNC\server\share`, `/` becomes `\` (the prefix turns off that
+/// conversion); already prefixed and `\\.\` device paths are kept
+fn extended_length(path: &str) -> String {
+    let path = path.replace('/', "\\");
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        path
+    } else if let Some(share) = path.strip_prefix(r"\\") {
+        format!(r"\\?\UNC\{}", share)
+    } else {
+        format!(r"\\?\{}", path)
+    }
+}
+
+/// `name` with `_N` before the extension
+fn numbered(name: &str, n: usize) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{}_{}.{}", stem, n, extension),
+        _ => format!("{}_{}", name, n),
+    }
+}
+
+/// Where a file was saved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrittenFile {
+    /// Path relative to the writer's directory (`0002/name` when sharded)
+    pub filename: String,
+    /// The requested name, when the file had to be renamed
+    pub renamed_from: Option<String>,
+}
+
+/// Saves recovered files into one directory
+#[derive(Debug)]
+pub struct RecoveryWriter {
+    dir: PathBuf,
+    max_files_per_dir: Option<usize>,
+    /// Lowercased names on disk or handed out, across all subdirectories
+    /// (None until the directory was listed)
+    taken: Option<HashSet<String>>,
+    shard: usize,
+    /// Files in the current subdirectory (None until counted)
+    shard_files: Option<usize>,
+}
+
+impl RecoveryWriter {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf(), max_files_per_dir: None, taken: None, shard: 1, shard_files: None }
+    }
+
+    /// Files per directory before the next subdirectory starts (None = no limit)
+    pub fn with_max_files_per_dir(mut self, max: Option<usize>) -> Self {
+        self.max_files_per_dir = max.filter(|&max| max > 0);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Names in the directory and its subdirectories
+    fn existing_names(&self) -> HashSet<String> {
+        let mut names = HashSet::new();
+        let mut dirs = vec![self.dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(long_path(&dir)).into_iter().flatten().flatten() {
+                let path = entry.path();
+                if path.is_dir() && dir == self.dir {
+                    dirs.push(path);
+                } else {
+                    names.insert(entry.file_name().to_string_lossy().to_lowercase());
+                }
+            }
+        }
+        names
+    }
+
+    /// Subdirectory for the next file ("" = the directory itself)
+    fn shard_prefix(&mut self) -> String {
+        let Some(max) = self.max_files_per_dir else {
+            return String::new();
+        };
+        loop {
+            let prefix = if self.shard == 1 { String::new() } else { format!("{:04}", self.shard) };
+            // Files of an earlier run into the same directory count too
+            let files = *self.shard_files.get_or_insert_with(|| {
+                fs::read_dir(long_path(&self.dir.join(&prefix)))
+                    .map(|entries| entries.flatten().filter(|entry| entry.path().is_file()).count())
+                    .unwrap_or(0)
+            });
+            if files < max {
+                return prefix;
+            }
+            self.shard += 1;
+            self.shard_files = None;
+        }
+    }
+
+    /// Save `data` under `name` or, if that is taken, the first free `name_N`
+    pub fn write(&mut self, name: &str, data: &[u8]) -> std::io::Result<WrittenFile> {
+        let prefix = self.shard_prefix();
+        let target_dir = self.dir.join(&prefix);
+        fs::create_dir_all(long_path(&target_dir))?;
+        if self.taken.is_none() {
+            self.taken = Some(self.existing_names());
+        }
+
+        for n in 1.. {
+            let candidate = if n == 1 { name.to_string() } else { numbered(name, n) };
+            if !self.taken.as_mut().is_some_and(|taken| taken.insert(candidate.to_lowercase())) {
+                continue;
+            }
+            let path = long_path(&target_dir.join(&candidate)).into_owned();
+            let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+            if let Err(e) = file.write_all(data) {
+                drop(file);
+                let _ = fs::remove_file(&path);
+                return Err(e);
+            }
+            if let Some(files) = self.shard_files.as_mut() {
+                *files += 1;
+            }
+            let filename = if prefix.is_empty() { candidate.clone() } else { format!("{}/{}", prefix, candidate) };
+            return Ok(WrittenFile { filename, renamed_from: (n > 1).then(|| name.to_string()) });
+        }
+        unreachable!("unbounded name search")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_names_and_sharding() {
+        let dir = std::env::temp_dir().join(format!("recovery_writer_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("recovered_0001_Video.json"), b"earlier run").unwrap();
+
+        let composed = "recovered_0002_Мой клип 1.json";
+
+        let mut writer = RecoveryWriter::new(&dir).with_max_files_per_dir(Some(3));
+        let first = writer.write("recovered_0001_Video.json", b"a").unwrap();
+        assert_eq!(first.filename, "recovered_0001_Video_2.json");
+        assert_eq!(first.renamed_from.as_deref(), Some("recovered_0001_Video.json"));
+        assert_eq!(fs::read(dir.join("recovered_0001_Video.json")).unwrap(), b"earlier run");
+        let upper = writer.write("RECOVERED_0001_VIDEO_2.JSON", b"b").unwrap();
+        assert_eq!(upper.filename, "RECOVERED_0001_VIDEO_2_2.JSON");
+        assert_eq!(writer.write(composed, b"c").unwrap().filename, format!("0002/{}", composed));
+
+        let names: Vec<String> = (0..4).map(|i| writer.write(&format!("f{}.bin", i), b"d").unwrap().filename).collect();
+        assert_eq!(names, ["0002/f0.bin", "0002/f1.bin", "0003/f2.bin", "0003/f3.bin"]);
+
+        // A later run into the same directory avoids the names in subdirectories too
+        let mut again = RecoveryWriter::new(&dir).with_max_files_per_dir(Some(3));
+        assert_eq!(again.write("f0.bin", b"e").unwrap().filename, "0003/f0_2.bin");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_extended_length_paths() {
+        assert_eq!(extended_length(r"C:\out\0002/Мой клип.json"), r"\\?\C:\out\0002\Мой клип.json");
+        assert_eq!(extended_length(r"\\nas\share\out\a.json"), r"\\?\UNC\nas\share\out\a.json");
+        assert_eq!(extended_length(r"\\?\C:\out\a.json"), r"\\?\C:\out\a.json");
+        let short = Path::new("out/a.json");
+        assert_eq!(long_path(short), short);
+    }
+}
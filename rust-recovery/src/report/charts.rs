@@ -0,0 +1,273 @@
+//! Chart data of the HTML report: histograms and the disk map
+//!
+//! Computed here and drawn as inline SVG by `templates/report.html`, so the
+//! report opens offline (no chart library, no network).
+
+use super::{DataCluster, RecoveredFile, ReportContext};
+
+/// Columns of the disk map; hot clusters are binned per column
+pub const DISK_MAP_COLUMNS: usize = 200;
+const ENTROPY_BINS: usize = 16;
+const SCORE_BINS: usize = 10;
+
+/// One bar of a histogram
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramBar {
+    /// Lower bound of the bin
+    pub start: f64,
+    /// Value range, e.g. "7.5-8.0"
+    pub label: String,
+    pub count: usize,
+    /// Height relative to the highest bar, 0-100
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub bars: Vec<HistogramBar>,
+    pub total: usize,
+}
+
+impl Histogram {
+    /// `bins` equal bins over `min..max`; values outside are clamped, non-finite ones skipped
+    pub fn new(values: impl IntoIterator<Item = f64>, min: f64, max: f64, bins: usize) -> Self {
+        let mut counts = Bins::new(min, max, bins);
+        values.into_iter().for_each(|value| counts.add(value));
+        counts.histogram()
+    }
+}
+
+/// Bin counts of a histogram, filled one value at a time
+#[derive(Debug, Clone, PartialEq)]
+struct Bins {
+    min: f64,
+    width: f64,
+    counts: Vec<usize>,
+}
+
+impl Bins {
+    fn new(min: f64, max: f64, bins: usize) -> Self {
+        Self { min, width: (max - min) / bins as f64, counts: vec![0; bins] }
+    }
+
+    fn add(&mut self, value: f64) {
+        if value.is_finite() {
+            let bin = ((value - self.min) / self.width).floor().clamp(0.0, (self.counts.len() - 1) as f64) as usize;
+            self.counts[bin] += 1;
+        }
+    }
+
+    fn histogram(&self) -> Histogram {
+        let highest = self.counts.iter().copied().max().unwrap_or(0).max(1) as f64;
+        let bars = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(bin, &count)| {
+                let start = self.min + bin as f64 * self.width;
+                HistogramBar {
+                    start,
+                    label: format!("{:.1}-{:.1}", start, start + self.width),
+                    count,
+                    height: count as f64 * 100.0 / highest,
+                }
+            })
+            .collect();
+        Histogram { bars, total: self.counts.iter().sum() }
+    }
+}
+
+/// One column of the disk map
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskMapColumn {
+    pub start: u64,
+    pub end: u64,
+    pub clusters: usize,
+    pub files: usize,
+    /// Highest cluster confidence in the column
+    pub best_score: f64,
+    /// Shade: 0 = no cluster, 1-4 by best score
+    pub level: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskMap {
+    pub image_size: u64,
+    pub columns: Vec<DiskMapColumn>,
+}
+
+impl DiskMap {
+    /// Hot clusters and recovered files binned over `image_size` bytes
+    pub fn new(image_size: u64, clusters: &[DataCluster], files: &[RecoveredFile], columns: usize) -> Self {
+        let mut map = Self::empty(image_size, columns);
+        clusters.iter().for_each(|cluster| map.add_cluster(cluster));
+        map.shaded(files)
+    }
+
+    fn empty(image_size: u64, columns: usize) -> Self {
+        let image_size = image_size.max(1);
+        let column_bytes = image_size.div_ceil(columns as u64).max(1);
+        let columns = (0..columns as u64)
+            .map(|column| DiskMapColumn {
+                start: column * column_bytes,
+                end: ((column + 1) * column_bytes).min(image_size),
+                clusters: 0,
+                files: 0,
+                best_score: 0.0,
+                level: 0,
+            })
+            .collect();
+        Self { image_size, columns }
+    }
+
+    fn column_of(&self, offset: u64) -> usize {
+        let column_bytes = self.image_size.div_ceil(self.columns.len() as u64).max(1);
+        ((offset / column_bytes) as usize).min(self.columns.len() - 1)
+    }
+
+    fn add_cluster(&mut self, cluster: &DataCluster) {
+        let Some(offset) = parse_hex(&cluster.start_offset_hex) else {
+            return;
+        };
+        let index = self.column_of(offset);
+        let column = &mut self.columns[index];
+        column.clusters += 1;
+        if cluster.confidence.is_finite() {
+            column.best_score = column.best_score.max(cluster.confidence);
+        }
+    }
+
+    /// Count `files` and shade the columns by their best cluster
+    fn shaded(mut self, files: &[RecoveredFile]) -> Self {
+        for file in files {
+            let index = self.column_of(file.start_offset.as_u64());
+            self.columns[index].files += 1;
+        }
+        for column in &mut self.columns {
+            if column.clusters > 0 {
+                column.level = 1 + (column.best_score.clamp(0.0, 0.999) * 4.0) as u8;
+            }
+        }
+        self
+    }
+
+    /// Image megabytes per column
+    pub fn column_mb(&self) -> f64 {
+        self.image_size as f64 / self.columns.len().max(1) as f64 / (1024.0 * 1024.0)
+    }
+}
+
+fn parse_hex(text: &str) -> Option<u64> {
+    u64::from_str_radix(text.trim_start_matches("0x"), 16).ok()
+}
+
+/// Everything the template draws
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportCharts {
+    /// Shannon entropy (bits/byte) of the hot clusters
+    pub entropy: Histogram,
+    /// Confidence of the hot clusters
+    pub scores: Histogram,
+    pub disk_map: DiskMap,
+}
+
+impl ReportCharts {
+    /// Charts of the clusters in `context`, or of every cluster of the run
+    /// when the listed ones are only the first (`context.cluster_charts`)
+    pub fn new(context: &ReportContext) -> Self {
+        if let Some(ref charts) = context.cluster_charts {
+            return charts.finish(&context.recovered_files);
+        }
+        let image_size = (context.scan_results.image_size_mb * 1024.0 * 1024.0).round() as u64;
+        let mut charts = ClusterCharts::new(image_size);
+        context.clusters.iter().for_each(|cluster| charts.add(cluster));
+        charts.finish(&context.recovered_files)
+    }
+}
+
+/// Cluster side of the charts, filled one cluster at a time so that the
+/// clusters of a scan database are counted without being held
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterCharts {
+    count: usize,
+    entropy: Bins,
+    scores: Bins,
+    disk_map: DiskMap,
+}
+
+impl ClusterCharts {
+    pub fn new(image_size: u64) -> Self {
+        Self {
+            count: 0,
+            entropy: Bins::new(0.0, 8.0, ENTROPY_BINS),
+            scores: Bins::new(0.0, 1.0, SCORE_BINS),
+            disk_map: DiskMap::empty(image_size, DISK_MAP_COLUMNS),
+        }
+    }
+
+    pub fn add(&mut self, cluster: &DataCluster) {
+        self.count += 1;
+        self.entropy.add(cluster.entropy);
+        self.scores.add(cluster.confidence);
+        self.disk_map.add_cluster(cluster);
+    }
+
+    /// Clusters added
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn finish(&self, files: &[RecoveredFile]) -> ReportCharts {
+        ReportCharts {
+            entropy: self.entropy.histogram(),
+            scores: self.scores.histogram(),
+            disk_map: self.disk_map.clone().shaded(files),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Size;
+
+    fn cluster(offset: u64, confidence: f64) -> DataCluster {
+        DataCluster {
+            id: 0,
+            start_offset_hex: format!("0x{:X}", offset),
+            end_offset_hex: format!("0x{:X}", offset + 4096),
+            size_bytes: Size::new(4096),
+            size_kb: 4,
+            link_count: 1,
+            density: 0.0,
+            confidence,
+            entropy: 4.2,
+            links: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_histograms_and_disk_map() {
+        let scores = Histogram::new([0.05, 0.95, 1.0, 2.0, f64::NAN], 0.0, 1.0, 10);
+        assert_eq!(scores.total, 4);
+        assert_eq!(scores.bars[0].count, 1);
+        assert_eq!(scores.bars[9].count, 3);
+        assert_eq!(scores.bars[9].height, 100.0);
+        assert_eq!(scores.bars[9].label, "0.9-1.0");
+
+        let clusters = [cluster(0, 0.2), cluster(0x800, 0.9), cluster(0xF000, 0.5)];
+        let map = DiskMap::new(0x10000, &clusters, &[], 16);
+        assert_eq!(map.columns.len(), 16);
+        assert_eq!((map.columns[0].clusters, map.columns[0].level), (2, 4));
+        assert_eq!(map.columns[1].level, 0);
+        assert_eq!((map.columns[15].start, map.columns[15].end, map.columns[15].level), (0xF000, 0x10000, 3));
+
+        // Streamed clusters chart the same as a listed slice
+        let mut charts = ClusterCharts::new(0x10000);
+        clusters.iter().for_each(|cluster| charts.add(cluster));
+        assert_eq!(charts.count(), 3);
+        let streamed = charts.finish(&[]);
+        assert_eq!(streamed.scores, Histogram::new(clusters.iter().map(|c| c.confidence), 0.0, 1.0, SCORE_BINS));
+        assert_eq!(streamed.disk_map, DiskMap::new(0x10000, &clusters, &[], DISK_MAP_COLUMNS));
+    }
+}
@@ -0,0 +1,400 @@
+//! Link export (`--export-links`): links.csv, links.jsonl and a Netscape
+//! bookmarks.html in the output directory
+//!
+//! Every link is grouped by where it was found: the recovered file whose
+//! extents hold its offset, else the hot-fragment cluster around it, else
+//! "unassigned". Rows are ordered by group (files, then clusters) and offset.
+//! `--links-only` exports each URL once (`dedupe_links`).
+
+use super::{DataCluster, RecoveredFile};
+use crate::types::EnrichedLink;
+use serde::Serialize;
+use std::collections::hash_map::{Entry, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Group of links outside every recovered file and cluster
+pub const UNASSIGNED_GROUP: &str = "unassigned";
+
+/// Export format names accepted by `--export-links`
+pub const LINK_EXPORT_NAMES: &[&str] = &["csv", "jsonl", "html"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkExportFormat {
+    Csv,
+    Jsonl,
+    /// Netscape bookmark file, importable by browsers
+    Bookmarks,
+}
+
+impl LinkExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(Self::Csv),
+            "jsonl" => Some(Self::Jsonl),
+            "html" => Some(Self::Bookmarks),
+            _ => None,
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Csv => "links.csv",
+            Self::Jsonl => "links.jsonl",
+            Self::Bookmarks => "bookmarks.html",
+        }
+    }
+}
+
+/// One exported link
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkRow<'a> {
+    pub group: String,
+    pub offset: u64,
+    pub url: &'a str,
+    pub value: &'a str,
+    pub title: Option<&'a str>,
+    pub confidence: f32,
+    pub pattern: &'a str,
+    /// Text around the match, with `--link-context`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_before: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_after: Option<&'a str>,
+}
+
+impl<'a> LinkRow<'a> {
+    pub fn new(group: String, link: &'a EnrichedLink) -> Self {
+        Self {
+            group,
+            offset: link.offset.as_u64(),
+            url: &link.url,
+            value: &link.video_id,
+            title: link.title.as_deref(),
+            confidence: link.confidence,
+            pattern: &link.pattern_name,
+            context_before: link.context_before.as_deref(),
+            context_after: link.context_after.as_deref(),
+        }
+    }
+}
+
+/// Start and end offset of a cluster from its hex fields
+fn cluster_range(cluster: &DataCluster) -> Option<(u64, u64)> {
+    let parse = |hex: &str| u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok();
+    Some((parse(&cluster.start_offset_hex)?, parse(&cluster.end_offset_hex)?))
+}
+
+/// Links with their group, ordered by group then offset
+pub fn link_rows<'a>(links: &'a [EnrichedLink], files: &[RecoveredFile], clusters: &[DataCluster]) -> Vec<LinkRow<'a>> {
+    let cluster_ranges: Vec<_> = clusters.iter().map(|c| (c.id, cluster_range(c))).collect();
+    let mut rows: Vec<(usize, LinkRow)> = links
+        .iter()
+        .map(|link| {
+            let offset = link.offset.as_u64();
+            let file = files.iter().position(|file| {
+                file.fragments
+                    .iter()
+                    .any(|extent| offset >= extent.offset.as_u64() && offset < extent.offset.as_u64() + extent.size.as_u64())
+            });
+            let cluster = || {
+                cluster_ranges
+                    .iter()
+                    .position(|(_, range)| range.is_some_and(|(start, end)| offset >= start && offset < end))
+            };
+            let (rank, group) = match (file, file.is_none().then(cluster).flatten()) {
+                (Some(index), _) => (index, files[index].filename.clone()),
+                (None, Some(index)) => (files.len() + index, format!("cluster_{}", cluster_ranges[index].0)),
+                (None, None) => (usize::MAX, UNASSIGNED_GROUP.to_string()),
+            };
+            (rank, LinkRow::new(group, link))
+        })
+        .collect();
+    rows.sort_by_key(|(rank, row)| (*rank, row.offset));
+    rows.into_iter().map(|(_, row)| row).collect()
+}
+
+/// One link per URL: the most confident occurrence (the first one found on
+/// a tie), with a title from any occurrence; ordered by offset
+pub fn dedupe_links(links: Vec<EnrichedLink>) -> Vec<EnrichedLink> {
+    let mut by_url: HashMap<String, EnrichedLink> = HashMap::new();
+    for link in links {
+        match by_url.entry(link.url.clone()) {
+            Entry::Vacant(entry) => {
+                entry.insert(link);
+            }
+            Entry::Occupied(mut entry) => {
+                let kept = entry.get_mut();
+                let title = kept.title.take().or_else(|| link.title.clone());
+                if link.confidence > kept.confidence || (link.confidence == kept.confidence && link.offset < kept.offset) {
+                    *kept = link;
+                }
+                kept.title = kept.title.take().or(title);
+            }
+        }
+    }
+    let mut links: Vec<EnrichedLink> = by_url.into_values().collect();
+    links.sort_by_key(|link| link.offset);
+    links
+}
+
+/// Quote a CSV field when it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const CSV_HEADER: &str = "group,offset,offset_hex,url,value,title,confidence,pattern,context_before,context_after\n";
+
+fn csv_line(row: &LinkRow) -> String {
+    format!(
+        "{},{},0x{:X},{},{},{},{:.3},{},{},{}\n",
+        csv_field(&row.group),
+        row.offset,
+        row.offset,
+        csv_field(row.url),
+        csv_field(row.value),
+        csv_field(row.title.unwrap_or("")),
+        row.confidence,
+        csv_field(row.pattern),
+        csv_field(row.context_before.unwrap_or("")),
+        csv_field(row.context_after.unwrap_or(""))
+    )
+}
+
+fn jsonl_line(row: &LinkRow) -> String {
+    serde_json::to_string(row).map(|line| line + "\n").unwrap_or_default()
+}
+
+const BOOKMARKS_HEADER: &str = "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+     <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+     <TITLE>Recovered links</TITLE>\n\
+     <H1>Recovered links</H1>\n\
+     <DL><p>\n";
+
+/// Bookmark of `row`, opening its folder when `group` (the folder open so
+/// far) differs
+fn bookmark(row: &LinkRow, group: &mut Option<String>) -> String {
+    let mut html = String::new();
+    if group.as_deref() != Some(row.group.as_str()) {
+        if group.is_some() {
+            html.push_str("    </DL><p>\n");
+        }
+        html.push_str(&format!("    <DT><H3>{}</H3>\n    <DL><p>\n", escape_html(&row.group)));
+        *group = Some(row.group.clone());
+    }
+    let href = if row.url.contains("://") { row.url.to_string() } else { format!("https://{}", row.url) };
+    html.push_str(&format!(
+        "        <DT><A HREF=\"{}\">{}</A>\n        <DD>confidence {:.2}, offset 0x{:X}\n",
+        escape_html(&href),
+        escape_html(row.title.unwrap_or(row.url)),
+        row.confidence,
+        row.offset
+    ));
+    html
+}
+
+fn bookmarks_footer(group: &Option<String>) -> String {
+    let close = if group.is_some() { "    </DL><p>\n" } else { "" };
+    format!("{}</DL><p>\n", close)
+}
+
+pub fn render_csv(rows: &[LinkRow]) -> String {
+    std::iter::once(CSV_HEADER.to_string()).chain(rows.iter().map(csv_line)).collect()
+}
+
+pub fn render_jsonl(rows: &[LinkRow]) -> String {
+    rows.iter().map(jsonl_line).collect()
+}
+
+/// Netscape bookmark file: one folder per group. Matches without a scheme
+/// (`youtu.be/...`) are linked as https
+pub fn render_bookmarks(rows: &[LinkRow]) -> String {
+    let mut group = None;
+    let mut html = BOOKMARKS_HEADER.to_string();
+    for row in rows {
+        html.push_str(&bookmark(row, &mut group));
+    }
+    html.push_str(&bookmarks_footer(&group));
+    html
+}
+
+/// Export files of the selected formats, written one row at a time (rows
+/// must come ordered by group, as `link_rows` orders them)
+pub struct LinkExportWriter {
+    outputs: Vec<(LinkExportFormat, PathBuf, BufWriter<File>)>,
+    /// Bookmark folder open so far
+    group: Option<String>,
+}
+
+impl LinkExportWriter {
+    /// Create the files in `output_dir` and write their headers
+    pub fn create(output_dir: &Path, formats: &[LinkExportFormat]) -> std::io::Result<Self> {
+        let mut outputs = Vec::new();
+        for &format in formats {
+            let path = output_dir.join(format.file_name());
+            let mut file = BufWriter::new(File::create(&path)?);
+            match format {
+                LinkExportFormat::Csv => file.write_all(CSV_HEADER.as_bytes())?,
+                LinkExportFormat::Jsonl => {}
+                LinkExportFormat::Bookmarks => file.write_all(BOOKMARKS_HEADER.as_bytes())?,
+            }
+            outputs.push((format, path, file));
+        }
+        Ok(Self { outputs, group: None })
+    }
+
+    pub fn write(&mut self, row: &LinkRow) -> std::io::Result<()> {
+        let mut group = self.group.clone();
+        for (format, _, file) in &mut self.outputs {
+            let text = match format {
+                LinkExportFormat::Csv => csv_line(row),
+                LinkExportFormat::Jsonl => jsonl_line(row),
+                LinkExportFormat::Bookmarks => bookmark(row, &mut group),
+            };
+            file.write_all(text.as_bytes())?;
+        }
+        self.group = group;
+        Ok(())
+    }
+
+    /// Close the files; returns their paths
+    pub fn finish(self) -> std::io::Result<Vec<PathBuf>> {
+        let mut written = Vec::new();
+        for (format, path, mut file) in self.outputs {
+            if format == LinkExportFormat::Bookmarks {
+                file.write_all(bookmarks_footer(&self.group).as_bytes())?;
+            }
+            file.flush()?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+/// Write the selected formats to `output_dir`; returns the files written
+pub fn export_links(
+    output_dir: &Path,
+    links: &[EnrichedLink],
+    files: &[RecoveredFile],
+    clusters: &[DataCluster],
+    formats: &[LinkExportFormat],
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut writer = LinkExportWriter::create(output_dir, formats)?;
+    for row in link_rows(links, files, clusters) {
+        writer.write(&row)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recovery::FragmentExtent;
+    use crate::report::ValidationStatus;
+    use crate::types::{Offset, Size};
+
+    #[test]
+    fn test_link_rows_and_formats() {
+        let link = |offset: u64, url: &str, title: Option<&str>| {
+            let mut link = EnrichedLink::new(url.to_string(), "dQw4w9WgXcQ".to_string(), Offset::new(offset), "watch".to_string(), 0.9);
+            link.title = title.map(str::to_string);
+            link
+        };
+        let mut links = vec![
+            link(9000, "https://x.test/a", None),
+            link(150, "youtu.be/dQw4w9WgXcQ", Some("Песня, \"live\" <1>")),
+            link(5000, "https://x.test/b", None),
+            link(100, "https://x.test/c", None),
+        ];
+        links[1].context_before = Some("смотри: ".to_string());
+        links[1].context_after = Some(", ещё".to_string());
+        let file = RecoveredFile {
+            id: 1,
+            filename: "recovered_0001.json".to_string(),
+            file_type: "json".to_string(),
+            confidence: 0.8,
+            links: Vec::new(),
+            size_kb: 1,
+            sha256: String::new(),
+            md5: String::new(),
+            fuzzy_hash: String::new(),
+            size_bytes: 1024,
+            utf8_repaired_bytes: 0,
+            trimmed_bytes: 0,
+            start_offset: Offset::new(0),
+            end_offset: Offset::new(1024),
+            validation_status: ValidationStatus::Valid,
+            recovery_time: String::new(),
+            fragments: vec![FragmentExtent::new(Offset::new(0), Size::new(1024))],
+            provenance_hash: String::new(),
+            title: None,
+            title_language: None,
+            post_process: None,
+            repair: None,
+            renamed_from: None,
+            timestamps: None,
+            gaps: Vec::new(),
+        };
+        let cluster = DataCluster {
+            id: 3,
+            start_offset_hex: "0x1000".to_string(),
+            end_offset_hex: "0x2000".to_string(),
+            size_bytes: Size::new(4096),
+            size_kb: 4,
+            link_count: 1,
+            density: 0.0,
+            confidence: 0.5,
+            entropy: 4.0,
+            links: Vec::new(),
+        };
+
+        let rows = link_rows(&links, &[file], &[cluster]);
+        let order: Vec<(&str, u64)> = rows.iter().map(|r| (r.group.as_str(), r.offset)).collect();
+        assert_eq!(
+            order,
+            vec![("recovered_0001.json", 100), ("recovered_0001.json", 150), ("cluster_3", 5000), (UNASSIGNED_GROUP, 9000)]
+        );
+
+        let csv = render_csv(&rows);
+        assert!(csv.contains("recovered_0001.json,150,0x96,youtu.be/dQw4w9WgXcQ,dQw4w9WgXcQ,\"Песня, \"\"live\"\" <1>\",0.900,watch,смотри: ,\", ещё\"\n"));
+        assert!(csv.contains(",0.900,watch,,\n"));
+        assert_eq!(render_jsonl(&rows).lines().count(), 4);
+        assert!(render_jsonl(&rows).lines().all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok()));
+
+        let html = render_bookmarks(&rows);
+        assert_eq!(html.matches("<H3>").count(), 3);
+        assert!(html.contains("<A HREF=\"https://youtu.be/dQw4w9WgXcQ\">Песня, &quot;live&quot; &lt;1&gt;</A>"));
+        assert_eq!(LinkExportFormat::from_name("html"), Some(LinkExportFormat::Bookmarks));
+
+        let mut repeated = links.clone();
+        repeated.push(link(20, "https://x.test/b", Some("B")));
+        repeated.push(link(7000, "youtu.be/dQw4w9WgXcQ", None));
+        repeated[0].confidence = 0.5;
+        repeated.push(link(30, "https://x.test/a", None));
+        let unique = dedupe_links(repeated);
+        let kept: Vec<(u64, &str, Option<&str>)> =
+            unique.iter().map(|l| (l.offset.as_u64(), l.url.as_str(), l.title.as_deref())).collect();
+        assert_eq!(
+            kept,
+            vec![
+                (20, "https://x.test/b", Some("B")),
+                (30, "https://x.test/a", None),
+                (100, "https://x.test/c", None),
+                (150, "youtu.be/dQw4w9WgXcQ", Some("Песня, \"live\" <1>")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Recovered file log (`--flush-interval`)
+//!
+//! Files written during the scan are appended to `recovered_files.jsonl` in
+//! the output directory, one `RecoveredFile` as in the JSON report per line,
+//! so a run that dies before the report is written still lists what it saved.
+//! A resumed scan rewrites the file.
+
+use super::RecoveredFile;
+use crate::housekeeping::RotatingFile;
+use std::io::Write;
+use std::path::Path;
+
+/// File name in the output directory
+pub const RECOVERED_LOG_FILE: &str = "recovered_files.jsonl";
+
+/// Append-only writer of `recovered_files.jsonl`
+pub struct RecoveredFileLog {
+    file: RotatingFile,
+}
+
+impl RecoveredFileLog {
+    /// Create (or truncate) the log
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(Self { file: RotatingFile::create(path)? })
+    }
+
+    /// Rotate at `max_bytes` (`--log-max-mb`)
+    pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.file = self.file.with_max_bytes(max_bytes);
+        self
+    }
+
+    /// Write one record with a single write and push it to disk, so the line
+    /// survives a crash right after the file it describes
+    pub fn append(&mut self, file: &RecoveredFile) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(file).map_err(std::io::Error::other)?;
+        line.push('\n');
+        let file = self.file.file()?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ValidationStatus;
+    use crate::types::Offset;
+
+    #[test]
+    fn test_recovered_file_log() {
+        let file = |id: usize| RecoveredFile {
+            id,
+            filename: format!("recovered_{:04}.json", id),
+            file_type: "json".to_string(),
+            confidence: 0.8,
+            links: Vec::new(),
+            size_kb: 1,
+            sha256: String::new(),
+            md5: String::new(),
+            fuzzy_hash: String::new(),
+            size_bytes: 1500,
+            utf8_repaired_bytes: 0,
+            trimmed_bytes: 0,
+            start_offset: Offset::new(0x1000),
+            end_offset: Offset::new(0x2000),
+            validation_status: ValidationStatus::Valid,
+            recovery_time: String::new(),
+            fragments: Vec::new(),
+            provenance_hash: String::new(),
+            title: Some("Рецепт".to_string()),
+            title_language: None,
+            post_process: None,
+            repair: None,
+            renamed_from: None,
+            timestamps: None,
+            gaps: Vec::new(),
+        };
+
+        let path = std::env::temp_dir().join(format!("recovered_files_{}.jsonl", std::process::id()));
+        let mut log = RecoveredFileLog::create(&path).unwrap();
+        log.append(&file(1)).unwrap();
+        log.append(&file(2)).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let files: Vec<RecoveredFile> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].filename, "recovered_0002.json");
+        assert_eq!(files[0].title.as_deref(), Some("Рецепт"));
+    }
+}
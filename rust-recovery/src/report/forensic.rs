@@ -0,0 +1,242 @@
+//! Forensic tool interop: DFXML and Sleuth Kit body file (`fls -m` format)
+//!
+//! Both list the recovered files with their image byte runs, sizes and
+//! hashes, so Autopsy, `mactime` and other DFXML consumers can import a run.
+//! Carved data carries no file system times: the body file writes 0
+//! (unknown) in every time field, DFXML records only when the run happened.
+//! A file starting at the data of an exFAT entry (`--enable-exfat`) has the
+//! entry's access, modification and creation times in both.
+//! Byte runs are the image extents a file was assembled from; text types
+//! are cleaned after assembly, so their runs can hold more bytes than the file.
+
+use super::{escape_html, sanitize_report_string, RecoveredFile, ReportContext};
+
+/// Directory of the recovered files inside the output directory
+pub const RECOVERED_FILES_DIR: &str = "01_RECOVERED_FILES";
+
+const DFXML_NAMESPACE: &str = "http://www.forensicswiki.org/wiki/Category:Digital_Forensics_XML";
+
+/// Text safe for XML 1.0 content and attributes
+fn escape_xml(value: &str) -> String {
+    escape_html(&sanitize_report_string(value)).replace('\n', "&#10;").replace('\t', "&#9;")
+}
+
+/// Path of a recovered file as listed by both formats
+fn recovered_path(file: &RecoveredFile) -> String {
+    format!("{}/{}", RECOVERED_FILES_DIR, file.filename)
+}
+
+/// Size in bytes, from `size_kb` for reports written before `size_bytes` existed
+fn file_size(file: &RecoveredFile) -> u64 {
+    if file.size_bytes > 0 { file.size_bytes } else { file.size_kb * 1024 }
+}
+
+/// DFXML 1.x document with one `fileobject` per recovered file
+pub fn render_dfxml(context: &ReportContext) -> String {
+    let metadata = &context.metadata;
+    let image_size = (context.scan_results.image_size_mb * 1024.0 * 1024.0).round() as u64;
+    let mut xml = String::from("<?xml version='1.0' encoding='UTF-8'?>\n");
+    xml.push_str(&format!(
+        "<dfxml xmloutputversion='1.0' xmlns='{}' xmlns:dc='http://purl.org/dc/elements/1.1/'>\n",
+        DFXML_NAMESPACE
+    ));
+    xml.push_str("  <metadata>\n    <dc:type>Carve Results</dc:type>\n  </metadata>\n");
+    xml.push_str(&format!(
+        "  <creator version='1.0'>\n    <program>{}</program>\n    <version>{}</version>\n",
+        escape_xml(&metadata.tool_name),
+        escape_xml(&metadata.version)
+    ));
+    xml.push_str(&format!(
+        "    <execution_environment>\n      <start_time>{}</start_time>\n    </execution_environment>\n  </creator>\n",
+        escape_xml(&metadata.timestamp)
+    ));
+    xml.push_str(&format!(
+        "  <source>\n    <image_filename>{}</image_filename>\n    <image_size>{}</image_size>\n",
+        escape_xml(&metadata.image_path),
+        image_size
+    ));
+    // --verify-hash: digests of the whole image
+    if let Some(digest) = &metadata.source_digest {
+        if let Some(md5) = &digest.md5 {
+            xml.push_str(&format!("    <hashdigest type='md5'>{}</hashdigest>\n", escape_xml(md5)));
+        }
+        xml.push_str(&format!("    <hashdigest type='sha256'>{}</hashdigest>\n", escape_xml(&digest.sha256)));
+    }
+    xml.push_str("  </source>\n");
+    for file in &context.recovered_files {
+        xml.push_str(&format!(
+            "  <fileobject>\n    <filename>{}</filename>\n    <filesize>{}</filesize>\n    <byte_runs>\n",
+            escape_xml(&recovered_path(file)),
+            file_size(file)
+        ));
+        let mut file_offset = 0;
+        for extent in &file.fragments {
+            xml.push_str(&format!(
+                "      <byte_run file_offset='{}' img_offset='{}' len='{}'/>\n",
+                file_offset,
+                extent.offset.as_u64(),
+                extent.size.as_u64()
+            ));
+            file_offset += extent.size.as_u64();
+        }
+        xml.push_str("    </byte_runs>\n");
+        if let Some(times) = &file.timestamps {
+            for (element, time) in [("mtime", times.modified), ("atime", times.accessed), ("crtime", times.created)] {
+                if let Some(time) = time {
+                    xml.push_str(&format!("    <{0}>{1}</{0}>\n", element, time.to_rfc3339()));
+                }
+            }
+        }
+        if !file.md5.is_empty() {
+            xml.push_str(&format!("    <hashdigest type='md5'>{}</hashdigest>\n", escape_xml(&file.md5)));
+        }
+        if !file.sha256.is_empty() {
+            xml.push_str(&format!("    <hashdigest type='sha256'>{}</hashdigest>\n", escape_xml(&file.sha256)));
+        }
+        xml.push_str("  </fileobject>\n");
+    }
+    xml.push_str("</dfxml>\n");
+    xml
+}
+
+/// Body file, one line per recovered file:
+/// `MD5|name|inode|mode|UID|GID|size|atime|mtime|ctime|crtime`.
+/// The inode column holds the file id; ctime (metadata change) is never known
+pub fn render_body_file(context: &ReportContext) -> String {
+    context
+        .recovered_files
+        .iter()
+        .map(|file| {
+            let name = recovered_path(file);
+            let times = file.timestamps.unwrap_or_default();
+            let epoch = |time: Option<chrono::DateTime<chrono::FixedOffset>>| time.map_or(0, |time| time.timestamp());
+            format!(
+                "{}|{}|{}|r/rrw-r--r--|0|0|{}|{}|{}|0|{}\n",
+                if file.md5.is_empty() { "0" } else { &file.md5 },
+                sanitize_report_string(&name).replace(['|', '\n', '\t'], "_"),
+                file.id,
+                file_size(file),
+                epoch(times.accessed),
+                epoch(times.modified),
+                epoch(times.created)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recovery::FragmentExtent;
+    use crate::report::{create_report_metadata, create_scan_results, ValidationStatus};
+    use crate::scanner::SourceDigest;
+    use crate::types::{Offset, Size};
+
+    #[test]
+    fn test_dfxml_and_body_file() {
+        let file = |id: usize, filename: &str, md5: &str, fragments: Vec<FragmentExtent>| RecoveredFile {
+            id,
+            filename: filename.to_string(),
+            file_type: "json".to_string(),
+            confidence: 0.9,
+            links: Vec::new(),
+            size_kb: 7,
+            sha256: "ab".repeat(32),
+            md5: md5.to_string(),
+            fuzzy_hash: String::new(),
+            size_bytes: 7000,
+            utf8_repaired_bytes: 0,
+            trimmed_bytes: 0,
+            start_offset: fragments[0].offset,
+            end_offset: Offset::new(0x40000),
+            validation_status: ValidationStatus::Valid,
+            recovery_time: String::new(),
+            fragments,
+            provenance_hash: String::new(),
+            title: None,
+            title_language: None,
+            post_process: None,
+            repair: None,
+            renamed_from: None,
+            timestamps: None,
+            gaps: Vec::new(),
+        };
+        let mut context = ReportContext {
+            metadata: create_report_metadata("/cases/disk <1>.img", "/out", "1.0.0"),
+            scan_results: create_scan_results(1 << 20, 1 << 20, 2, std::time::Duration::from_secs(1), false, false, false),
+            clusters: Vec::new(),
+            clusters_total: 0,
+            cluster_charts: None,
+            recovered_files: vec![
+                file(
+                    1,
+                    "recovered_0001_a|b.json",
+                    "d41d8cd98f00b204e9800998ecf8427e",
+                    vec![
+                        FragmentExtent::new(Offset::new(0x1000), Size::new(4096)),
+                        FragmentExtent::new(Offset::new(0x8000), Size::new(4096)),
+                    ],
+                ),
+                file(2, "recovered_0002.png", "", vec![FragmentExtent::new(Offset::new(0x20000), Size::new(7000))]),
+            ],
+            failure_reasons: Vec::new(),
+            diagnostics: Vec::new(),
+            incidents: Vec::new(),
+            duplicate_groups: Vec::new(),
+            success: true,
+            degradations: Vec::new(),
+            link_contexts: Vec::new(),
+        };
+        let time = |rfc3339: &str| Some(chrono::DateTime::parse_from_rfc3339(rfc3339).unwrap());
+        context.recovered_files[1].timestamps = Some(crate::types::FileTimestamps {
+            created: time("2023-12-31T21:41:05.370+03:00"),
+            modified: time("2024-01-01T00:00:00+00:00"),
+            accessed: None,
+        });
+        context.recovered_files[0].timestamps = Some(crate::types::FileTimestamps {
+            accessed: time("2024-01-02T08:30:00-05:00"),
+            ..Default::default()
+        });
+
+        let xml = render_dfxml(&context);
+        assert!(xml.contains("<image_filename>/cases/disk &lt;1&gt;.img</image_filename>"));
+        assert!(xml.contains("<image_size>1048576</image_size>"));
+        assert_eq!(xml.matches("<fileobject>").count(), 2);
+        assert!(xml.contains("<byte_run file_offset='4096' img_offset='32768' len='4096'/>"));
+        assert!(xml.contains("<hashdigest type='md5'>d41d8cd98f00b204e9800998ecf8427e</hashdigest>"));
+        assert_eq!(xml.matches("<hashdigest type='md5'>").count(), 1);
+        assert!(xml.ends_with("</dfxml>\n"));
+        assert!(xml.contains("<image_size>1048576</image_size>\n  </source>"));
+        assert!(xml.contains(
+            "</byte_runs>\n    <mtime>2024-01-01T00:00:00+00:00</mtime>\n    <crtime>2023-12-31T21:41:05.370+03:00</crtime>"
+        ));
+        assert!(xml.contains("</byte_runs>\n    <atime>2024-01-02T08:30:00-05:00</atime>\n    <hashdigest"));
+
+        context.metadata.source_digest = Some(SourceDigest {
+            sha256: "cd".repeat(32),
+            md5: Some("ef".repeat(16)),
+            bytes: 1 << 20,
+            reread_bytes: 0,
+        });
+        let xml = render_dfxml(&context);
+        assert!(xml.contains(&format!(
+            "    <hashdigest type='md5'>{}</hashdigest>\n    <hashdigest type='sha256'>{}</hashdigest>\n  </source>",
+            "ef".repeat(16),
+            "cd".repeat(32)
+        )));
+
+        let body = render_body_file(&context);
+        let lines: Vec<&str> = body.lines().collect();
+        // Only atime, in seconds since the epoch (UTC)
+        assert_eq!(
+            lines[0],
+            concat!(
+                "d41d8cd98f00b204e9800998ecf8427e|01_RECOVERED_FILES/recovered_0001_a_b.json|1|r/rrw-r--r--|0|0|7000|",
+                "1704202200|0|0|0"
+            )
+        );
+        // atime unknown, mtime, ctime never known, crtime
+        assert_eq!(lines[1], "0|01_RECOVERED_FILES/recovered_0002.png|2|r/rrw-r--r--|0|0|7000|0|1704067200|0|1704048065");
+        assert!(lines.iter().all(|line| line.split('|').count() == 11));
+    }
+}
@@ -0,0 +1,125 @@
+//! Live hot-fragment log (`--fragments-jsonl`)
+//!
+//! Every promoted hot fragment is appended to `fragments.jsonl` in the output
+//! directory as soon as the scan reports it, one complete JSON object per
+//! write, so `tail -f` and other tools can start on fragments before the scan
+//! ends. The `id` is the cluster id of the report. A resumed scan rewrites
+//! the file, replaying the checkpointed fragments first.
+
+use crate::housekeeping::RotatingFile;
+use crate::matcher::ScriptDensity;
+use crate::types::HotFragment;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+
+/// File name in the output directory
+pub const FRAGMENT_LOG_FILE: &str = "fragments.jsonl";
+
+/// One logged fragment
+#[derive(Debug, Serialize)]
+pub struct FragmentRow<'a> {
+    pub id: usize,
+    pub offset: u64,
+    pub offset_hex: String,
+    pub size: u64,
+    pub file_type: &'a str,
+    pub carved: bool,
+    pub target_score: f32,
+    pub overall_score: f32,
+    pub entropy: f32,
+    pub entropy_category: &'a str,
+    pub youtube_count: usize,
+    pub cyrillic_density: f32,
+    pub scripts: ScriptDensity,
+    pub json_markers: usize,
+    pub has_valid_json: bool,
+    pub reasons: &'a [String],
+}
+
+impl<'a> FragmentRow<'a> {
+    pub fn new(id: usize, fragment: &'a HotFragment) -> Self {
+        Self {
+            id,
+            offset: fragment.offset.as_u64(),
+            offset_hex: format!("0x{:X}", fragment.offset.as_u64()),
+            size: fragment.size.as_u64(),
+            file_type: &fragment.file_type_guess,
+            carved: fragment.carved,
+            target_score: fragment.target_score,
+            overall_score: fragment.fragment_score.overall_score,
+            entropy: fragment.entropy,
+            entropy_category: &fragment.entropy_category,
+            youtube_count: fragment.youtube_count,
+            cyrillic_density: fragment.cyrillic_density,
+            scripts: fragment.scripts,
+            json_markers: fragment.json_markers,
+            has_valid_json: fragment.has_valid_json,
+            reasons: &fragment.fragment_score.reasons,
+        }
+    }
+}
+
+/// JSON line of a fragment, newline included. Non-finite scores become null
+pub fn fragment_line(id: usize, fragment: &HotFragment) -> String {
+    let mut line = serde_json::to_string(&FragmentRow::new(id, fragment)).unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+/// Append-only writer of `fragments.jsonl`
+pub struct FragmentLog {
+    file: RotatingFile,
+}
+
+impl FragmentLog {
+    /// Create (or truncate) the log
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(Self { file: RotatingFile::create(path)? })
+    }
+
+    /// Rotate at `max_bytes` (`--log-max-mb`)
+    pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.file = self.file.with_max_bytes(max_bytes);
+        self
+    }
+
+    /// Write one fragment with a single write so readers never see half a line
+    pub fn append(&mut self, id: usize, fragment: &HotFragment) -> std::io::Result<()> {
+        self.file.file()?.write_all(fragment_line(id, fragment).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Offset, Size};
+
+    #[test]
+    fn test_fragment_log_lines() {
+        let mut fragment = HotFragment::new(Offset::new(0x10000), Size::new(4096));
+        fragment.file_type_guess = "json".to_string();
+        fragment.target_score = 42.5;
+        fragment.fragment_score.overall_score = 42.5;
+        fragment.fragment_score.reasons = vec!["keyword рецепт".to_string()];
+        let mut carved = HotFragment::new(Offset::new(0x20000), Size::new(69));
+        carved.carved = true;
+        carved.entropy = f32::NAN;
+
+        let path = std::env::temp_dir().join(format!("fragments_{}.jsonl", std::process::id()));
+        let mut log = FragmentLog::create(&path).unwrap();
+        log.append(1, &fragment).unwrap();
+        log.append(2, &carved).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let rows: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["offset_hex"], "0x10000");
+        assert_eq!(rows[0]["target_score"], 42.5);
+        assert_eq!(rows[0]["reasons"][0], "keyword рецепт");
+        assert_eq!(rows[1]["id"], 2);
+        assert_eq!(rows[1]["carved"], true);
+        assert!(rows[1]["entropy"].is_null());
+    }
+}
@@ -5,12 +5,22 @@
 //! data clusters, and comprehensive analysis results.
 
 // pub mod templates;
+pub mod charts;
+pub mod export;
+pub mod file_log;
+pub mod forensic;
+pub mod fragment_log;
 
 use askama::Template;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::fs;
 
+use self::charts::{ClusterCharts, ReportCharts};
+use crate::diagnostics::DiagnosticEntry;
+use crate::recovery::{DuplicateGroup, FragmentExtent, PostProcessResult, ProvenanceParams};
+use crate::types::{ChunkStats, Offset, ScanIncident, Size, StructureHit};
+
 /// Report context containing all data for template rendering
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReportContext {
@@ -20,12 +30,133 @@ pub struct ReportContext {
     pub scan_results: ScanResults,
     /// Data clusters found during scan
     pub clusters: Vec<DataCluster>,
+    /// Clusters of the run; more than `clusters` when the list was cut
+    #[serde(default)]
+    pub clusters_total: usize,
+    /// Charts over every cluster when the list was cut
+    #[serde(skip)]
+    pub cluster_charts: Option<ClusterCharts>,
     /// Recovered files information
     pub recovered_files: Vec<RecoveredFile>,
     /// Failure reasons (if any)
     pub failure_reasons: Vec<String>,
+    /// Skipped and unrecovered data by cause, with suggested flags
+    #[serde(default)]
+    pub diagnostics: Vec<DiagnosticEntry>,
+    /// Chunks the scan watchdog abandoned and requeued
+    #[serde(default)]
+    pub incidents: Vec<ScanIncident>,
+    /// Duplicate files collapsed into a kept file (not in `recovered_files`)
+    #[serde(default)]
+    pub duplicate_groups: Vec<DuplicateGroup>,
     /// Success status
     pub success: bool,
+    /// Fallbacks taken while producing this report (empty for a normal run)
+    #[serde(default)]
+    pub degradations: Vec<String>,
+    /// Text around each link (`--link-context`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub link_contexts: Vec<LinkContext>,
+}
+
+impl ReportContext {
+    /// Replace control characters, U+FFFD and overlong strings, zero non-finite scores
+    fn sanitize(&mut self) {
+        let metadata = &mut self.metadata;
+        for value in [
+            &mut metadata.tool_name,
+            &mut metadata.image_path,
+            &mut metadata.output_dir,
+            &mut metadata.image_hash,
+        ] {
+            *value = sanitize_report_string(value);
+        }
+        for value in metadata.parameters.values_mut() {
+            *value = sanitize_report_string(value);
+        }
+        for partition in &mut metadata.partitions {
+            partition.name = partition.name.as_deref().map(sanitize_report_string);
+        }
+
+        for cluster in &mut self.clusters {
+            cluster.links.iter_mut().for_each(|l| *l = sanitize_report_string(l));
+            cluster.density = finite_or_zero(cluster.density);
+            cluster.confidence = finite_or_zero(cluster.confidence);
+        }
+
+        for file in &mut self.recovered_files {
+            file.filename = sanitize_report_string(&file.filename);
+            file.title = file.title.as_deref().map(sanitize_report_string);
+            file.file_type = sanitize_report_string(&file.file_type);
+            file.links.iter_mut().for_each(|l| *l = sanitize_report_string(l));
+            file.confidence = finite_or_zero(file.confidence);
+        }
+
+        for group in &mut self.duplicate_groups {
+            group.kept_filename = sanitize_report_string(&group.kept_filename);
+            group.duplicates.iter_mut().for_each(|d| d.filename = sanitize_report_string(&d.filename));
+        }
+
+        self.failure_reasons
+            .iter_mut()
+            .for_each(|r| *r = sanitize_report_string(r));
+
+        for link in &mut self.link_contexts {
+            link.url = sanitize_report_string(&link.url);
+            link.context_before = sanitize_report_string(&link.context_before);
+            link.context_after = sanitize_report_string(&link.context_after);
+        }
+    }
+}
+
+/// Clusters handed to the report
+#[derive(Debug, Clone, Default)]
+pub struct ReportClusters {
+    /// Clusters listed in the report
+    pub listed: Vec<DataCluster>,
+    /// Charts over every cluster of the run, when only the first are listed
+    /// (scan database, `scan_db::REPORT_LIST_LIMIT`)
+    pub charts: Option<ClusterCharts>,
+}
+
+impl ReportClusters {
+    /// Clusters of the run, listed or not
+    pub fn total(&self) -> usize {
+        self.charts.as_ref().map_or(self.listed.len(), ClusterCharts::count)
+    }
+}
+
+impl From<Vec<DataCluster>> for ReportClusters {
+    fn from(listed: Vec<DataCluster>) -> Self {
+        Self { listed, charts: None }
+    }
+}
+
+/// A link with the text captured around it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkContext {
+    pub offset: Offset,
+    pub url: String,
+    pub context_before: String,
+    pub context_after: String,
+}
+
+impl LinkContext {
+    /// Contexts of the links that carry one, in offset order
+    pub fn collect(links: &[crate::types::EnrichedLink]) -> Vec<Self> {
+        let mut contexts: Vec<Self> = links
+            .iter()
+            .filter(|link| link.context_before.is_some() || link.context_after.is_some())
+            .map(|link| Self {
+                offset: link.offset,
+                url: link.url.clone(),
+                context_before: link.context_before.clone().unwrap_or_default(),
+                context_after: link.context_after.clone().unwrap_or_default(),
+            })
+            .collect();
+        contexts.sort_by_key(|link| link.offset);
+        contexts
+    }
 }
 
 /// Report metadata
@@ -41,6 +172,32 @@ pub struct ReportMetadata {
     pub image_path: String,
     /// Output directory
     pub output_dir: String,
+    /// Identity of the image (first MiB and length, `checkpoint::compute_image_hash`);
+    /// the bytes of each file are covered by its provenance hash
+    #[serde(default)]
+    pub image_hash: String,
+    /// Recovery parameters included in every provenance hash
+    #[serde(default)]
+    pub parameters: ProvenanceParams,
+    /// MBR/GPT partitions of the image (empty when not partitioned)
+    #[serde(default)]
+    pub partitions: Vec<crate::partition::Partition>,
+    /// Partition the scan was restricted to (`--partition`)
+    #[serde(default)]
+    pub scanned_partition: Option<u32>,
+    /// Command that continues a scan stopped by `--max-duration`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_command: Option<String>,
+    /// Digest of the whole image read during the scan (`--verify-hash`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_digest: Option<crate::scanner::SourceDigest>,
+    /// Hash of the last audit log entry before the report was written
+    /// (`crate::audit`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_chain_hash: Option<String>,
+    /// `--output s3://bucket/prefix` the run is uploaded to after the reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_output: Option<String>,
 }
 
 /// Scan results and statistics
@@ -80,7 +237,7 @@ pub struct DataCluster {
     /// End offset in bytes (hex)
     pub end_offset_hex: String,
     /// Cluster size in bytes
-    pub size_bytes: u64,
+    pub size_bytes: Size,
     /// Cluster size in KB
     pub size_kb: u64,
     /// Number of links found
@@ -89,6 +246,9 @@ pub struct DataCluster {
     pub density: f64,
     /// Confidence score (0.0 - 1.0)
     pub confidence: f64,
+    /// Shannon entropy, bits per byte
+    #[serde(default)]
+    pub entropy: f64,
     /// Links found in this cluster
     pub links: Vec<String>,
 }
@@ -110,18 +270,81 @@ pub struct RecoveredFile {
     pub size_kb: u64,
     /// SHA256 hash
     pub sha256: String,
+    /// MD5 hash (body file, hash sets indexed by MD5)
+    #[serde(default)]
+    pub md5: String,
+    /// Fuzzy hash (`hashing::fuzzy_hash`) for near-duplicate detection
+    #[serde(default)]
+    pub fuzzy_hash: String,
+    /// Exact size of the written file
+    #[serde(default)]
+    pub size_bytes: u64,
+    /// Invalid UTF-8 bytes replaced or dropped (`--utf8`)
+    #[serde(default)]
+    pub utf8_repaired_bytes: u64,
+    /// Bytes cut after the end of the document (next cluster's data)
+    #[serde(default)]
+    pub trimmed_bytes: u64,
     /// Start offset in disk image
-    pub start_offset: u64,
+    pub start_offset: Offset,
     /// End offset in disk image
-    pub end_offset: u64,
+    pub end_offset: Offset,
     /// File validation status
     pub validation_status: ValidationStatus,
     /// Recovery timestamp
     pub recovery_time: String,
+    /// Image extents the file was assembled from, in order
+    #[serde(default)]
+    pub fragments: Vec<FragmentExtent>,
+    /// H(image_hash ∥ fragments ∥ fragment bytes ∥ tool version ∥ parameters)
+    #[serde(default)]
+    pub provenance_hash: String,
+    /// Title found in the content, as written (the filename may be transliterated)
+    #[serde(default)]
+    pub title: Option<String>,
+    /// ISO 639-1 code of the title language ("und" if undetermined)
+    #[serde(default)]
+    pub title_language: Option<String>,
+    /// Exit code of `--post-process-cmd` on this file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_process: Option<PostProcessResult>,
+    /// Repair made by the validation pass (`recovery::Repair` name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repair: Option<String>,
+    /// Name the file was meant to have when it was saved as `filename` to
+    /// avoid overwriting another file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renamed_from: Option<String>,
+    /// Times of the exFAT entry whose data the file starts at, also set on
+    /// the saved file (`--enable-exfat`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamps: Option<crate::types::FileTimestamps>,
+    /// Holes left for missing bytes, in file order (`--gap-fill`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gaps: Vec<crate::recovery::FileGap>,
+}
+
+impl RecoveredFile {
+    /// Link to the saved file from the HTML report in `reports/`
+    pub fn href(&self) -> String {
+        format!("../{}/{}", forensic::RECOVERED_FILES_DIR, encode_url_path(&self.filename))
+    }
+}
+
+/// Percent-encode a relative path for an `href`, keeping `/` and unreserved characters
+pub fn encode_url_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
 }
 
 /// File validation status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ValidationStatus {
     /// File is valid
     Valid,
@@ -160,6 +383,7 @@ pub struct RecoveryStats {
 pub struct HtmlReportTemplate {
     pub context: ReportContext,
     pub stats: RecoveryStats,
+    pub charts: ReportCharts,
 }
 
 /// JSON report structure for machine-readable output
@@ -168,11 +392,21 @@ pub struct JsonReport {
     pub metadata: ReportMetadata,
     pub scan_results: ScanResults,
     pub clusters: Vec<DataCluster>,
+    #[serde(default)]
+    pub clusters_total: usize,
     pub recovered_files: Vec<RecoveredFile>,
     pub failure_reasons: Vec<String>,
+    #[serde(default)]
+    pub diagnostics: Vec<DiagnosticEntry>,
+    #[serde(default)]
+    pub incidents: Vec<ScanIncident>,
+    #[serde(default)]
+    pub duplicate_groups: Vec<DuplicateGroup>,
     pub stats: RecoveryStats,
     pub success: bool,
     pub report_checksum: String,
+    #[serde(default)]
+    pub degradations: Vec<String>,
 }
 
 /// Professional report generator
@@ -197,94 +431,154 @@ impl ProfessionalReportGenerator {
         }
     }
 
-    /// Generate full report (HTML + JSON)
+    /// Generate full report (HTML + JSON, DFXML + body file)
+    #[allow(clippy::too_many_arguments)]
     pub fn generate_full_report(
         &self,
         scan_results: ScanResults,
-        clusters: Vec<DataCluster>,
+        clusters: ReportClusters,
         recovered_files: Vec<RecoveredFile>,
-        failure_reasons: Vec<String>,
+        duplicate_groups: Vec<DuplicateGroup>,
+        diagnostics: Vec<DiagnosticEntry>,
+        incidents: Vec<ScanIncident>,
+        link_contexts: Vec<LinkContext>,
         metadata: ReportMetadata,
     ) -> Result<ReportPaths, ReportError> {
         let success = !recovered_files.is_empty();
+        let failure_reasons = if success {
+            Vec::new()
+        } else {
+            failure_reasons(&scan_results, &diagnostics)
+        };
         
         // Calculate recovery statistics
         let stats = self.calculate_recovery_stats(&recovered_files, scan_results.candidates_found);
         
         // Create report context
-        let context = ReportContext {
+        let mut context = ReportContext {
             metadata,
             scan_results,
-            clusters,
-            recovered_files: recovered_files.clone(),
+            clusters_total: clusters.total(),
+            clusters: clusters.listed,
+            cluster_charts: clusters.charts,
+            recovered_files,
             failure_reasons,
+            diagnostics,
+            incidents,
+            duplicate_groups,
             success,
+            degradations: Vec::new(),
+            link_contexts,
         };
 
         // Generate timestamp for filenames
         let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
         let report_name = format!("recovery_report_{}", timestamp);
 
-        // Generate HTML report
+        // Rendering problems must not discard the scan: each format falls back
+        // full -> sanitized -> minimal, and every fallback is recorded.
+        // HTML goes first so the sanitized context is reused for JSON.
         let html_path = self.reports_dir.join(format!("{}.html", report_name));
-        self.generate_html_report(&context, &stats, &html_path)?;
+        let html_content = match self.render_html_report(&context, &stats) {
+            Ok(html) => html,
+            Err(e) => {
+                context.degradations.push(format!(
+                    "HTML template failed ({}), strings sanitized and rendered again",
+                    e
+                ));
+                context.sanitize();
+                match self.render_html_report(&context, &stats) {
+                    Ok(html) => html,
+                    Err(e) => {
+                        context.degradations.push(format!(
+                            "HTML template failed after sanitizing ({}), minimal HTML written",
+                            e
+                        ));
+                        render_minimal_html(&context, &stats)
+                    }
+                }
+            }
+        };
+        fs::write(&html_path, html_content)?;
 
-        // Generate JSON report
         let json_path = self.reports_dir.join(format!("{}.json", report_name));
-        self.generate_json_report(&context, &stats, &json_path)?;
+        let json_content = match self.render_json_report(&context, &stats) {
+            Ok(json) => json,
+            Err(e) => {
+                context.degradations.push(format!(
+                    "JSON serialization failed ({}), strings sanitized and serialized again",
+                    e
+                ));
+                context.sanitize();
+                match self.render_json_report(&context, &stats) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        context.degradations.push(format!(
+                            "JSON serialization failed after sanitizing ({}), minimal JSON written",
+                            e
+                        ));
+                        render_minimal_json(&context, &stats)
+                    }
+                }
+            }
+        };
+        fs::write(&json_path, json_content)?;
+
+        // Forensic interop formats list the files only and cannot fail to render
+        let dfxml_path = self.reports_dir.join(format!("{}.dfxml", report_name));
+        fs::write(&dfxml_path, forensic::render_dfxml(&context))?;
+        let body_path = self.reports_dir.join(format!("{}.body", report_name));
+        fs::write(&body_path, forensic::render_body_file(&context))?;
 
         Ok(ReportPaths {
             html_path,
             json_path,
+            dfxml_path,
+            body_path,
+            degradations: context.degradations,
         })
     }
 
-    /// Generate HTML report using askama template
-    fn generate_html_report(
+    /// Render HTML report using askama template
+    fn render_html_report(
         &self,
         context: &ReportContext,
         stats: &RecoveryStats,
-        path: &Path,
-    ) -> Result<(), ReportError> {
+    ) -> Result<String, ReportError> {
         let template = HtmlReportTemplate {
             context: context.clone(),
             stats: stats.clone(),
+            charts: ReportCharts::new(context),
         };
 
-        let html_content = template.render()
-            .map_err(|e| ReportError::TemplateError(e.to_string()))?;
-
-        fs::write(path, html_content)
-            .map_err(|e| ReportError::IoError(e))?;
-
-        Ok(())
+        template.render()
+            .map_err(|e| ReportError::TemplateError(e.to_string()))
     }
 
-    /// Generate JSON report
-    fn generate_json_report(
+    /// Render JSON report
+    fn render_json_report(
         &self,
         context: &ReportContext,
         stats: &RecoveryStats,
-        path: &Path,
-    ) -> Result<(), ReportError> {
+    ) -> Result<String, ReportError> {
         let json_report = JsonReport {
             metadata: context.metadata.clone(),
             scan_results: context.scan_results.clone(),
             clusters: context.clusters.clone(),
+            clusters_total: context.clusters_total,
             recovered_files: context.recovered_files.clone(),
             failure_reasons: context.failure_reasons.clone(),
+            diagnostics: context.diagnostics.clone(),
+            incidents: context.incidents.clone(),
+            duplicate_groups: context.duplicate_groups.clone(),
             stats: stats.clone(),
             success: context.success,
             report_checksum: self.calculate_checksum(context, stats)?,
+            degradations: context.degradations.clone(),
         };
 
-        let json_content = serde_json::to_string_pretty(&json_report)
-            .map_err(|e| ReportError::SerializationError(e))?;
-
-        fs::write(path, json_content)
-            .map_err(|e| ReportError::IoError(e))?;
-
-        Ok(())
+        serde_json::to_string_pretty(&json_report)
+            .map_err(ReportError::SerializationError)
     }
 
     /// Calculate recovery statistics
@@ -350,6 +644,157 @@ impl ProfessionalReportGenerator {
 pub struct ReportPaths {
     pub html_path: std::path::PathBuf,
     pub json_path: std::path::PathBuf,
+    /// DFXML listing of the recovered files (`forensic::render_dfxml`)
+    pub dfxml_path: std::path::PathBuf,
+    /// Sleuth Kit body file of the recovered files (`forensic::render_body_file`)
+    pub body_path: std::path::PathBuf,
+    /// Fallbacks taken while generating (empty if both reports are complete)
+    pub degradations: Vec<String>,
+}
+
+impl ReportPaths {
+    pub fn is_degraded(&self) -> bool {
+        !self.degradations.is_empty()
+    }
+}
+
+/// Why nothing was recovered: one line per diagnostic cause, largest first
+fn failure_reasons(scan_results: &ScanResults, diagnostics: &[DiagnosticEntry]) -> Vec<String> {
+    if diagnostics.is_empty() {
+        return vec![format!(
+            "No fragment among {} candidates in {:.1} MB scanned could be assembled into a file",
+            scan_results.candidates_found, scan_results.bytes_scanned_mb
+        )];
+    }
+    diagnostics.iter().map(DiagnosticEntry::summary).collect()
+}
+
+/// Longest string kept by sanitizing (bytes)
+const MAX_REPORT_STRING_LEN: usize = 4096;
+
+/// Make a string safe for the report: drop control characters (except \n, \t),
+/// replace U+FFFD left by lossy UTF-8 decoding and cap the length
+fn sanitize_report_string(value: &str) -> String {
+    let mut sanitized = String::with_capacity(value.len().min(MAX_REPORT_STRING_LEN));
+    for c in value.chars() {
+        let c = match c {
+            '\n' | '\t' => c,
+            '\u{FFFD}' => '?',
+            c if c.is_control() => continue,
+            c => c,
+        };
+        if sanitized.len() + c.len_utf8() > MAX_REPORT_STRING_LEN {
+            sanitized.push('…');
+            break;
+        }
+        sanitized.push(c);
+    }
+    sanitized
+}
+
+fn finite_or_zero(value: f64) -> f64 {
+    if value.is_finite() { value } else { 0.0 }
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Template-free HTML: summary, recovered files and degradation notes
+fn render_minimal_html(context: &ReportContext, stats: &RecoveryStats) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html lang=\"ru\">\n<head><meta charset=\"UTF-8\"><title>Ultimate File Recovery</title></head>\n<body>\n",
+    );
+    html.push_str("<h1>Отчет Ultimate File Recovery (упрощенный)</h1>\n<ul>\n");
+    html.push_str(&format!(
+        "<li>Образ: {}</li>\n<li>Время: {}</li>\n<li>Кандидатов: {}</li>\n<li>Восстановлено: {}</li>\n<li>Успешность: {:.1}%</li>\n",
+        escape_html(&context.metadata.image_path),
+        escape_html(&context.metadata.timestamp),
+        context.scan_results.candidates_found,
+        context.recovered_files.len(),
+        finite_or_zero(stats.success_rate),
+    ));
+    html.push_str("</ul>\n<h2>Восстановленные файлы</h2>\n<ul>\n");
+    for file in &context.recovered_files {
+        html.push_str(&format!(
+            "<li>{}{} ({} KB, 0x{:X}-0x{:X}) SHA256 {}</li>\n",
+            escape_html(&file.filename),
+            file.title.as_deref().map(|t| format!(" — {}", escape_html(t))).unwrap_or_default(),
+            file.size_kb,
+            file.start_offset,
+            file.end_offset,
+            escape_html(&file.sha256),
+        ));
+    }
+    html.push_str("</ul>\n<h2>Дубликаты</h2>\n<ul>\n");
+    for group in &context.duplicate_groups {
+        for duplicate in &group.duplicates {
+            html.push_str(&format!(
+                "<li>{} → {} ({}%)</li>\n",
+                escape_html(&duplicate.filename),
+                escape_html(&group.kept_filename),
+                duplicate.similarity,
+            ));
+        }
+    }
+    html.push_str("</ul>\n<h2>Пропущенные данные</h2>\n<ul>\n");
+    for entry in &context.diagnostics {
+        html.push_str(&format!("<li>{}</li>\n", escape_html(&entry.summary())));
+    }
+    html.push_str("</ul>\n<h2>Деградация отчета</h2>\n<ul>\n");
+    for note in &context.degradations {
+        html.push_str(&format!("<li>{}</li>\n", escape_html(note)));
+    }
+    html.push_str("</ul>\n</body>\n</html>\n");
+    html
+}
+
+/// Minimal JSON built from `serde_json::Value` (cannot fail to serialize)
+fn render_minimal_json(context: &ReportContext, stats: &RecoveryStats) -> String {
+    let files: Vec<serde_json::Value> = context
+        .recovered_files
+        .iter()
+        .map(|file| {
+            serde_json::json!({
+                "filename": file.filename,
+                "title": file.title,
+                "size_kb": file.size_kb,
+                "sha256": file.sha256,
+                "start_offset": file.start_offset.as_u64(),
+                "end_offset": file.end_offset.as_u64(),
+                "provenance_hash": file.provenance_hash,
+            })
+        })
+        .collect();
+
+    let report = serde_json::json!({
+        "minimal": true,
+        "timestamp": context.metadata.timestamp,
+        "image_path": context.metadata.image_path,
+        "image_hash": context.metadata.image_hash,
+        "candidates_found": context.scan_results.candidates_found,
+        "files_recovered": context.recovered_files.len(),
+        "success_rate": finite_or_zero(stats.success_rate),
+        "success": context.success,
+        "recovered_files": files,
+        "failure_reasons": context.failure_reasons,
+        "diagnostics": context.diagnostics,
+        "duplicate_groups": context.duplicate_groups,
+        "incidents": context.incidents.len(),
+        "degradations": context.degradations,
+    });
+    format!("{:#}", report)
 }
 
 /// Report generation errors
@@ -380,6 +825,14 @@ pub fn create_report_metadata(
         tool_name: "Ultimate File Recovery".to_string(),
         image_path: image_path.to_string(),
         output_dir: output_dir.to_string(),
+        image_hash: String::new(),
+        parameters: ProvenanceParams::new(),
+        partitions: Vec::new(),
+        scanned_partition: None,
+        resume_command: None,
+        source_digest: None,
+        audit_chain_hash: None,
+        remote_output: None,
     }
 }
 
@@ -417,6 +870,28 @@ pub fn create_scan_results(
     }
 }
 
+/// Write per-chunk statistics as CSV (`ChunkStats::CSV_HEADER` columns)
+pub fn write_chunk_stats_csv(path: &Path, stats: &[ChunkStats]) -> std::io::Result<()> {
+    let mut csv = String::with_capacity((stats.len() + 1) * 64);
+    csv.push_str(ChunkStats::CSV_HEADER);
+    csv.push('\n');
+    for row in stats {
+        csv.push_str(&row.to_csv_row());
+        csv.push('\n');
+    }
+    fs::write(path, csv)
+}
+
+/// Write structures found by `--detect` as CSV (offset, hex offset, detector)
+pub fn write_structures_csv(path: &Path, hits: &[StructureHit]) -> std::io::Result<()> {
+    let mut csv = String::with_capacity((hits.len() + 1) * 32);
+    csv.push_str("offset,offset_hex,structure\n");
+    for hit in hits {
+        csv.push_str(&format!("{},0x{:X},{}\n", hit.offset.as_u64(), hit.offset.as_u64(), hit.structure));
+    }
+    fs::write(path, csv)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -429,6 +904,12 @@ mod tests {
         assert_eq!(metadata.version, "1.0.0");
     }
 
+    #[test]
+    fn test_encode_url_path() {
+        assert_eq!(encode_url_path("0001/recovered_0001.json"), "0001/recovered_0001.json");
+        assert_eq!(encode_url_path("Рецепт #1?.txt"), "%D0%A0%D0%B5%D1%86%D0%B5%D0%BF%D1%82%20%231%3F.txt");
+    }
+
     #[test]
     fn test_create_scan_results() {
         let results = create_scan_results(
@@ -447,4 +928,152 @@ mod tests {
         assert_eq!(results.scan_time_sec, 10.0);
         assert_eq!(results.avg_speed_mbps, 0.05);
     }
+
+    #[test]
+    fn test_write_chunk_stats_csv() {
+        let stats = ChunkStats {
+            offset: Offset::new(4096),
+            size: Size::new(2048),
+            duration_ms: 1.5,
+            links: 3,
+            json_markers: 12,
+            entropy: 4.25,
+            category: crate::entropy::entropy_category(4.25),
+            hot: true,
+        };
+        let path = std::env::temp_dir().join(format!("chunk_stats_{}.csv", std::process::id()));
+        write_chunk_stats_csv(&path, &[stats]).unwrap();
+
+        let csv = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], ChunkStats::CSV_HEADER);
+        assert_eq!(lines[1], "4096,2048,1.500,3,12,4.2500,structured_text,true");
+    }
+
+    fn sample_context() -> ReportContext {
+        let results = create_scan_results(1024, 1024, 1, std::time::Duration::from_secs(1), false, false, false);
+        ReportContext {
+            metadata: create_report_metadata("bad\u{0}\u{FFFD}.img", "/output", "1.0.0"),
+            scan_results: results,
+            clusters: Vec::new(),
+            clusters_total: 0,
+            cluster_charts: None,
+            recovered_files: vec![RecoveredFile {
+                id: 1,
+                filename: "<script>\u{7}.html".to_string(),
+                file_type: "html".to_string(),
+                confidence: f64::NAN,
+                links: vec!["https://youtu.be/dQw4w9WgXcQ".to_string()],
+                size_kb: 1,
+                sha256: "00".to_string(),
+                md5: String::new(),
+                fuzzy_hash: String::new(),
+                size_bytes: 1024,
+                utf8_repaired_bytes: 0,
+                trimmed_bytes: 0,
+                start_offset: Offset::new(0x1000),
+                end_offset: Offset::new(0x1400),
+                validation_status: ValidationStatus::Valid,
+                recovery_time: String::new(),
+                fragments: Vec::new(),
+                provenance_hash: String::new(),
+                title: Some("Видео\u{0}".to_string()),
+                title_language: Some("ru".to_string()),
+                post_process: None,
+                repair: None,
+                renamed_from: None,
+                timestamps: None,
+                gaps: Vec::new(),
+            }],
+            failure_reasons: Vec::new(),
+            diagnostics: Vec::new(),
+            incidents: Vec::new(),
+            duplicate_groups: vec![DuplicateGroup {
+                kept_id: 1,
+                kept_filename: "<script>\u{7}.html".to_string(),
+                sha256: "00".to_string(),
+                duplicates: vec![crate::recovery::Duplicate {
+                    id: 2,
+                    filename: "recovered_0002.html".to_string(),
+                    sha256: "00".to_string(),
+                    size_bytes: 1024,
+                    start_offset: Offset::new(0x2000),
+                    similarity: 100,
+                    exact: true,
+                }],
+            }],
+            success: true,
+            degradations: vec!["HTML template failed".to_string()],
+            link_contexts: vec![LinkContext {
+                offset: Offset::new(0x1010),
+                url: "https://youtu.be/dQw4w9WgXcQ".to_string(),
+                context_before: "смотри\u{FFFD} ".to_string(),
+                context_after: String::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_failure_reasons_from_diagnostics() {
+        let results = create_scan_results(1 << 20, 1 << 20, 4, std::time::Duration::from_secs(1), false, false, false);
+        let generic = failure_reasons(&results, &[]);
+        assert_eq!(generic.len(), 1);
+        assert!(generic[0].contains("4 candidates"));
+
+        let mut diagnostics = crate::diagnostics::ScanDiagnostics::new();
+        diagnostics.record(crate::diagnostics::DiagnosticCause::OverQuotaStreams, 8192);
+        let reasons = failure_reasons(&results, &diagnostics.entries());
+        assert_eq!(reasons.len(), 1);
+        assert!(reasons[0].contains("--max-streams"));
+    }
+
+    #[test]
+    fn test_sanitize_context() {
+        let mut context = sample_context();
+        context.sanitize();
+        assert_eq!(context.metadata.image_path, "bad?.img");
+        assert_eq!(context.recovered_files[0].filename, "<script>.html");
+        assert_eq!(context.recovered_files[0].confidence, 0.0);
+        assert_eq!(context.recovered_files[0].title.as_deref(), Some("Видео"));
+        assert_eq!(context.link_contexts[0].context_before, "смотри? ");
+
+        let long = "x".repeat(MAX_REPORT_STRING_LEN * 2);
+        assert!(sanitize_report_string(&long).len() <= MAX_REPORT_STRING_LEN + '…'.len_utf8());
+    }
+
+    #[test]
+    fn test_full_html_report() {
+        let context = sample_context();
+        let generator = ProfessionalReportGenerator {
+            output_dir: std::path::PathBuf::new(),
+            reports_dir: std::path::PathBuf::new(),
+        };
+        let stats = generator.calculate_recovery_stats(&context.recovered_files, 1);
+        let html = generator.render_html_report(&context, &stats).unwrap();
+        assert!(html.contains("<table class=\"sortable\" id=\"recovered-files\">"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains("class=\"file-mark\""));
+        assert!(html.contains("<table id=\"duplicate-groups\">"));
+        assert!(!html.contains("cdn."));
+    }
+
+    #[test]
+    fn test_minimal_reports() {
+        let context = sample_context();
+        let generator = ProfessionalReportGenerator {
+            output_dir: std::path::PathBuf::new(),
+            reports_dir: std::path::PathBuf::new(),
+        };
+        let stats = generator.calculate_recovery_stats(&context.recovered_files, 1);
+
+        let html = render_minimal_html(&context, &stats);
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains("HTML template failed"));
+
+        let json: serde_json::Value = serde_json::from_str(&render_minimal_json(&context, &stats)).unwrap();
+        assert_eq!(json["minimal"], true);
+        assert_eq!(json["recovered_files"][0]["start_offset"], 0x1000);
+        assert_eq!(json["degradations"][0], "HTML template failed");
+    }
 }
\ No newline at end of file
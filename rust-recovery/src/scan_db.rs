@@ -0,0 +1,718 @@
+//! Scan database (`--scan-db`): links and hot fragments in SQLite
+//!
+//! Without it every link, cluster and fragment of a scan stays in memory
+//! until the reports are written, so a scan finding tens of millions of
+//! links runs out of RAM. With it the scanner sends the links of each chunk
+//! as they are found and they are written, with every hot fragment, into
+//! `scan.db` in the output directory. Stream assembly takes its fragments
+//! back from the database, the report and the link exports read it at the
+//! end, and the file stays behind for queries after the run.
+//!
+//! A link read twice because it lies in the overlap of two chunks has the
+//! same image position and value both times; the database keeps one row per
+//! (position, value), preferring a titled and then a more confident
+//! occurrence, as the in-memory duplicate suppression does.
+//!
+//! At the end of the scan nothing is read back whole: assembly takes the
+//! fragments a page at a time (`take_fragment_page`), the report's charts
+//! read every cluster one row at a time while only the first
+//! `REPORT_LIST_LIMIT` are listed, and the link export (`export_links`) is
+//! a single query sorted by SQLite, written out row by row.
+//!
+//! `rust-recovery query` filters a finished database (`QueryFilter`) and
+//! exports the links it selects.
+
+use crate::checkpoint::SavedFragment;
+use crate::error::{RecoveryError, Result};
+use crate::report::export::{LinkExportFormat, LinkExportWriter, LinkRow, UNASSIGNED_GROUP};
+use crate::report::{DataCluster, LinkContext, RecoveredFile};
+use crate::types::{EnrichedLink, Offset, Size};
+use regex::Regex;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+
+/// Database file in the output directory
+pub const SCAN_DB_FILE: &str = "scan.db";
+
+/// Fragments handed to assembly per page at the end of a scan
+pub const ASSEMBLY_PAGE_FRAGMENTS: usize = 100_000;
+
+/// Clusters and link contexts the report lists from the database
+pub const REPORT_LIST_LIMIT: usize = 10_000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS links (
+    value_offset INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    offset INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT,
+    pattern TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    reasons TEXT NOT NULL,
+    context_before TEXT,
+    context_after TEXT,
+    PRIMARY KEY (value_offset, value)
+);
+CREATE INDEX IF NOT EXISTS links_offset ON links (offset);
+CREATE TABLE IF NOT EXISTS fragments (
+    offset INTEGER PRIMARY KEY,
+    id INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    link_count INTEGER NOT NULL,
+    cyrillic_density REAL NOT NULL,
+    score REAL NOT NULL,
+    file_type TEXT NOT NULL,
+    entropy REAL NOT NULL,
+    carved INTEGER NOT NULL,
+    reasons TEXT NOT NULL,
+    assembled INTEGER NOT NULL DEFAULT 0
+);
+";
+
+/// Rows selected by `rust-recovery query`
+#[derive(Debug, Clone, Default)]
+pub struct QueryFilter {
+    /// Image offsets `[start, end)`
+    pub range: Option<(u64, u64)>,
+    /// Lowest link confidence (fragment score)
+    pub min_confidence: f32,
+    /// Link pattern name (fragment file type)
+    pub pattern: Option<String>,
+    /// Links whose title matches; links without a title never do
+    pub title: Option<Regex>,
+    pub limit: Option<usize>,
+}
+
+impl QueryFilter {
+    /// SQL condition on `offset`, the confidence column and the pattern
+    /// column, with its parameters
+    fn condition(&self, confidence: &str, pattern: &str) -> (String, Vec<Value>) {
+        let mut conditions = vec![format!("{} >= ?", confidence)];
+        let mut values = vec![Value::Real(self.min_confidence as f64)];
+        if let Some((start, end)) = self.range {
+            conditions.push("offset >= ? AND offset < ?".to_string());
+            values.push(Value::Integer(start as i64));
+            values.push(Value::Integer(end as i64));
+        }
+        if let Some(ref name) = self.pattern {
+            conditions.push(format!("{} = ?", pattern));
+            values.push(Value::Text(name.clone()));
+        }
+        (conditions.join(" AND "), values)
+    }
+}
+
+/// Columns read by `saved_fragment`
+const FRAGMENT_COLUMNS: &str = "offset, size, link_count, cyrillic_density, score, file_type, entropy, carved, reasons";
+
+/// Link from a row of `LINK_COLUMNS`
+const LINK_COLUMNS: &str = "url, value, title, offset, pattern, confidence, reasons, context_before, context_after";
+
+fn enriched_link(row: &rusqlite::Row) -> rusqlite::Result<EnrichedLink> {
+    let mut link = EnrichedLink::new(
+        row.get(0)?,
+        row.get(1)?,
+        Offset::new(row.get::<_, i64>(3)? as u64),
+        row.get(4)?,
+        row.get(5)?,
+    );
+    link.title = row.get(2)?;
+    link.reasons = serde_json::from_str(&row.get::<_, String>(6)?).unwrap_or_default();
+    link.context_before = row.get(7)?;
+    link.context_after = row.get(8)?;
+    Ok(link)
+}
+
+/// Fragment from a row starting with `FRAGMENT_COLUMNS`
+fn saved_fragment(row: &rusqlite::Row) -> rusqlite::Result<SavedFragment> {
+    Ok(SavedFragment {
+        offset: Offset::new(row.get::<_, i64>(0)? as u64),
+        size: Size::new(row.get::<_, i64>(1)? as u64),
+        youtube_count: row.get::<_, i64>(2)? as usize,
+        cyrillic_density: row.get(3)?,
+        scripts: Default::default(),
+        target_score: row.get(4)?,
+        file_type: row.get(5)?,
+        entropy: row.get(6)?,
+        carved: row.get(7)?,
+        reasons: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
+    })
+}
+
+/// Open scan database
+pub struct ScanDb {
+    connection: Connection,
+    path: PathBuf,
+}
+
+impl From<rusqlite::Error> for RecoveryError {
+    fn from(error: rusqlite::Error) -> Self {
+        RecoveryError::Database(error.to_string())
+    }
+}
+
+impl ScanDb {
+    /// Open `path`, emptied first unless `keep` (a resumed scan adds to the
+    /// rows of the run it continues)
+    pub fn open(path: &Path, keep: bool) -> Result<Self> {
+        if !keep {
+            for suffix in ["", "-wal", "-shm", "-journal"] {
+                let mut file = path.as_os_str().to_os_string();
+                file.push(suffix);
+                match std::fs::remove_file(&file) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+        }
+        let connection = Connection::open(path)?;
+        // Rows are rewritten by a rerun anyway; durability per row is not worth an fsync
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "OFF")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection, path: path.to_path_buf() })
+    }
+
+    /// Open the database of a finished run for queries; it is not changed
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            return Err(RecoveryError::FileNotFound(format!(
+                "{} (scan databases are written by runs with --scan-db)",
+                path.display()
+            )));
+        }
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(Self { connection, path: path.to_path_buf() })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add the links of one chunk in a single transaction
+    pub fn insert_links(&mut self, links: &[EnrichedLink]) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            // The row kept for a (position, value) is the titled one, else the more confident one
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO links
+                 (value_offset, value, offset, url, title, pattern, confidence, reasons, context_before, context_after)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                 ON CONFLICT (value_offset, value) DO UPDATE SET
+                     offset = excluded.offset, url = excluded.url, title = excluded.title,
+                     pattern = excluded.pattern, confidence = excluded.confidence, reasons = excluded.reasons,
+                     context_before = excluded.context_before, context_after = excluded.context_after
+                 WHERE (excluded.title IS NOT NULL AND links.title IS NULL)
+                     OR ((excluded.title IS NULL) = (links.title IS NULL) AND excluded.confidence > links.confidence)",
+            )?;
+            for link in links {
+                let value_at = link.url.find(&link.video_id).unwrap_or(0) as u64;
+                insert.execute(params![
+                    (link.offset.as_u64() + value_at) as i64,
+                    link.video_id,
+                    link.offset.as_u64() as i64,
+                    link.url,
+                    link.title,
+                    link.pattern_name,
+                    link.confidence,
+                    serde_json::to_string(&link.reasons).unwrap_or_default(),
+                    link.context_before,
+                    link.context_after,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Add hot fragment `id`; a fragment already stored at its offset (replayed
+    /// on resume) is kept
+    pub fn insert_fragment(&self, id: usize, fragment: &SavedFragment) -> Result<()> {
+        self.connection
+            .prepare_cached(
+                "INSERT OR IGNORE INTO fragments
+                 (offset, id, size, link_count, cyrillic_density, score, file_type, entropy, carved, reasons)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?
+            .execute(params![
+                fragment.offset.as_u64() as i64,
+                id as i64,
+                fragment.size.as_u64() as i64,
+                fragment.youtube_count as i64,
+                fragment.cyrillic_density,
+                fragment.target_score,
+                fragment.file_type,
+                fragment.entropy,
+                fragment.carved,
+                serde_json::to_string(&fragment.reasons).unwrap_or_default(),
+            ])?;
+        Ok(())
+    }
+
+    pub fn link_count(&self) -> Result<u64> {
+        Ok(self.connection.query_row("SELECT COUNT(*) FROM links", [], |row| row.get::<_, i64>(0))? as u64)
+    }
+
+    pub fn fragment_count(&self) -> Result<u64> {
+        Ok(self.connection.query_row("SELECT COUNT(*) FROM fragments", [], |row| row.get::<_, i64>(0))? as u64)
+    }
+
+    /// Fragments not handed to assembly yet
+    pub fn pending_fragment_count(&self) -> Result<u64> {
+        let count = self.connection.query_row("SELECT COUNT(*) FROM fragments WHERE assembled = 0", [], |row| {
+            row.get::<_, i64>(0)
+        })?;
+        Ok(count as u64)
+    }
+
+    /// Distinct URLs (what `--links-only` exports)
+    pub fn url_count(&self) -> Result<u64> {
+        Ok(self.connection.query_row("SELECT COUNT(DISTINCT url) FROM links", [], |row| row.get::<_, i64>(0))? as u64)
+    }
+
+    /// Every link, ordered by offset
+    pub fn links(&self) -> Result<Vec<EnrichedLink>> {
+        self.query_links(&QueryFilter::default())
+    }
+
+    /// Links selected by `filter`, ordered by offset
+    pub fn query_links(&self, filter: &QueryFilter) -> Result<Vec<EnrichedLink>> {
+        let (condition, values) = filter.condition("confidence", "pattern");
+        let mut select = self.connection.prepare(&format!(
+            "SELECT {} FROM links WHERE {} ORDER BY offset, value",
+            LINK_COLUMNS, condition
+        ))?;
+        let rows = select.query_map(params_from_iter(values), enriched_link)?;
+        let mut links = Vec::new();
+        for link in rows {
+            let link = link?;
+            if let Some(ref title) = filter.title {
+                if !link.title.as_deref().is_some_and(|text| title.is_match(text)) {
+                    continue;
+                }
+            }
+            links.push(link);
+            if filter.limit.is_some_and(|limit| links.len() >= limit) {
+                break;
+            }
+        }
+        Ok(links)
+    }
+
+    /// Fragments selected by `filter` (score, file type, range), with their
+    /// report ids, ordered by offset
+    pub fn query_fragments(&self, filter: &QueryFilter) -> Result<Vec<(usize, SavedFragment)>> {
+        let (condition, mut values) = filter.condition("score", "file_type");
+        values.push(Value::Integer(filter.limit.map_or(-1, |limit| limit as i64)));
+        let mut select = self.connection.prepare(&format!(
+            "SELECT {}, id FROM fragments WHERE {} ORDER BY offset LIMIT ?",
+            FRAGMENT_COLUMNS, condition
+        ))?;
+        let fragments = select.query_map(params_from_iter(values), |row| {
+            Ok((row.get::<_, i64>(9)? as usize, saved_fragment(row)?))
+        })?;
+        Ok(fragments.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Fragments not handed to assembly yet, marked as handed out. With
+    /// `settled_at`, only those the scan has moved past: ending at or
+    /// before it, or starting at or after it when `reverse`
+    pub fn take_fragments(&mut self, settled_at: Option<Offset>, reverse: bool) -> Result<Vec<SavedFragment>> {
+        let (condition, bound) = match (settled_at, reverse) {
+            (None, _) => ("offset >= ?1", 0),
+            (Some(position), false) => ("offset + size <= ?1", position.as_u64() as i64),
+            (Some(position), true) => ("offset >= ?1", position.as_u64() as i64),
+        };
+        let transaction = self.connection.transaction()?;
+        let fragments = {
+            let mut select = transaction.prepare(&format!(
+                "SELECT {} FROM fragments WHERE assembled = 0 AND {} ORDER BY offset",
+                FRAGMENT_COLUMNS, condition
+            ))?;
+            let rows = select.query_map([bound], saved_fragment)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        transaction.execute(
+            &format!("UPDATE fragments SET assembled = 1 WHERE assembled = 0 AND {}", condition),
+            [bound],
+        )?;
+        transaction.commit()?;
+        Ok(fragments)
+    }
+
+    /// Next page of the fragments not handed to assembly yet, in offset
+    /// order, marked as handed out: at least `limit` (unless fewer are left),
+    /// then up to the first gap wider than `max_gap`, across which the
+    /// solver joins no two fragments
+    pub fn take_fragment_page(&mut self, limit: usize, max_gap: u64) -> Result<Vec<SavedFragment>> {
+        let transaction = self.connection.transaction()?;
+        let mut fragments: Vec<SavedFragment> = Vec::new();
+        {
+            let mut select = transaction.prepare(&format!(
+                "SELECT {} FROM fragments WHERE assembled = 0 ORDER BY offset",
+                FRAGMENT_COLUMNS
+            ))?;
+            let mut rows = select.query([])?;
+            let mut end = 0u64;
+            while let Some(row) = rows.next()? {
+                let fragment = saved_fragment(row)?;
+                if fragments.len() >= limit && fragment.offset.as_u64() > end.saturating_add(max_gap) {
+                    break;
+                }
+                end = end.max(fragment.offset.as_u64() + fragment.size.as_u64());
+                fragments.push(fragment);
+            }
+        }
+        if let Some(last) = fragments.last() {
+            transaction.execute(
+                "UPDATE fragments SET assembled = 1 WHERE assembled = 0 AND offset <= ?1",
+                [last.offset.as_u64() as i64],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(fragments)
+    }
+
+    /// Report clusters, one per hot fragment, in the order they were found
+    pub fn clusters(&self) -> Result<Vec<DataCluster>> {
+        let mut clusters = Vec::new();
+        self.for_each_cluster(|cluster| clusters.push(cluster))?;
+        Ok(clusters)
+    }
+
+    /// `clusters` one row at a time
+    pub fn for_each_cluster(&self, mut visit: impl FnMut(DataCluster)) -> Result<()> {
+        let mut select = self.connection.prepare(
+            "SELECT id, offset, size, link_count, cyrillic_density, score, entropy FROM fragments ORDER BY id",
+        )?;
+        let clusters = select.query_map([], |row| {
+            let offset = row.get::<_, i64>(1)? as u64;
+            let size = Size::new(row.get::<_, i64>(2)? as u64);
+            Ok(DataCluster {
+                id: row.get::<_, i64>(0)? as usize,
+                start_offset_hex: format!("0x{:X}", offset),
+                end_offset_hex: format!("0x{:X}", offset + size.as_u64()),
+                size_bytes: size,
+                size_kb: size.as_kb(),
+                link_count: row.get::<_, i64>(3)? as u32,
+                density: row.get::<_, f32>(4)? as f64,
+                confidence: row.get::<_, f32>(5)? as f64,
+                entropy: row.get::<_, f32>(6)? as f64,
+                links: Vec::new(),
+            })
+        })?;
+        for cluster in clusters {
+            visit(cluster?);
+        }
+        Ok(())
+    }
+
+    /// Contexts of the first `limit` links that carry one, in offset order
+    pub fn link_contexts(&self, limit: usize) -> Result<Vec<LinkContext>> {
+        let mut select = self.connection.prepare(&format!(
+            "SELECT {} FROM links WHERE context_before IS NOT NULL OR context_after IS NOT NULL
+             ORDER BY offset, value LIMIT ?1",
+            LINK_COLUMNS
+        ))?;
+        let links = select.query_map([limit as i64], enriched_link)?.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(LinkContext::collect(&links))
+    }
+
+    /// `report::export::export_links` over the database: every link (one
+    /// per URL with `distinct`, as `dedupe_links` picks it) grouped by the
+    /// recovered file or cluster that holds it. The rows are placed and
+    /// sorted by SQLite and written as they are read; returns the number
+    /// of links and the files written
+    pub fn export_links(
+        &self,
+        output_dir: &Path,
+        files: &[RecoveredFile],
+        formats: &[LinkExportFormat],
+        distinct: bool,
+    ) -> Result<(usize, Vec<PathBuf>)> {
+        // Extents of the recovered files, searched like the fragments below
+        self.connection.execute_batch(
+            "DROP TABLE IF EXISTS temp.file_extents;
+             CREATE TEMP TABLE file_extents (start INTEGER NOT NULL, end INTEGER NOT NULL, file INTEGER NOT NULL);
+             CREATE INDEX temp.file_extents_start ON file_extents (start);",
+        )?;
+        let mut longest_extent = 0u64;
+        {
+            let mut insert = self.connection.prepare("INSERT INTO temp.file_extents VALUES (?1, ?2, ?3)")?;
+            for (index, file) in files.iter().enumerate() {
+                for extent in &file.fragments {
+                    let start = extent.offset.as_u64();
+                    longest_extent = longest_extent.max(extent.size.as_u64());
+                    insert.execute(params![start as i64, (start + extent.size.as_u64()) as i64, index as i64])?;
+                }
+            }
+        }
+        let longest_fragment: i64 =
+            self.connection.query_row("SELECT COALESCE(MAX(size), 0) FROM fragments", [], |row| row.get(0))?;
+
+        // `dedupe_links`: the most confident occurrence of a URL (the first
+        // on a tie), its own title else the first one found
+        let selected = match distinct {
+            false => "SELECT * FROM links",
+            true => {
+                "SELECT url, value, COALESCE(title, first_title) AS title, offset, pattern, confidence, reasons,
+                     context_before, context_after
+                 FROM (SELECT *,
+                     ROW_NUMBER() OVER (PARTITION BY url ORDER BY confidence DESC, offset, value) AS n,
+                     FIRST_VALUE(title) OVER (PARTITION BY url ORDER BY title IS NULL, offset, value) AS first_title
+                     FROM links)
+                 WHERE n = 1"
+            }
+        };
+        // The first file holding the offset, else the first cluster; files
+        // come first, then clusters, then the unassigned links
+        let mut select = self.connection.prepare(&format!(
+            "WITH selected AS ({}),
+             placed AS (SELECT *,
+                 (SELECT MIN(x.file) FROM temp.file_extents x
+                     WHERE x.start <= s.offset AND x.start >= s.offset - ?1 AND s.offset < x.end) AS file,
+                 (SELECT MIN(f.id) FROM fragments f
+                     WHERE f.offset <= s.offset AND f.offset >= s.offset - ?2
+                         AND s.offset < f.offset + f.size) AS cluster
+                 FROM selected s)
+             SELECT {}, file, cluster FROM placed
+             ORDER BY CASE WHEN file IS NOT NULL THEN 0 WHEN cluster IS NOT NULL THEN 1 ELSE 2 END,
+                 COALESCE(file, cluster, 0), offset, value",
+            selected, LINK_COLUMNS
+        ))?;
+        let mut rows = select.query(params![longest_extent as i64, longest_fragment])?;
+
+        let mut writer = LinkExportWriter::create(output_dir, formats)?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            let link = enriched_link(row)?;
+            let group = match (row.get::<_, Option<i64>>(9)?, row.get::<_, Option<i64>>(10)?) {
+                (Some(file), _) => files[file as usize].filename.clone(),
+                (None, Some(cluster)) => format!("cluster_{}", cluster),
+                (None, None) => UNASSIGNED_GROUP.to_string(),
+            };
+            writer.write(&LinkRow::new(group, &link))?;
+            count += 1;
+        }
+        drop(rows);
+        drop(select);
+        self.connection.execute_batch("DROP TABLE temp.file_extents;")?;
+        Ok((count, writer.finish()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(offset: u64, size: u64, carved: bool) -> SavedFragment {
+        SavedFragment {
+            offset: Offset::new(offset),
+            size: Size::new(size),
+            youtube_count: 2,
+            cyrillic_density: 0.5,
+            scripts: Default::default(),
+            target_score: 70.0,
+            file_type: "json".to_string(),
+            entropy: 4.5,
+            carved,
+            reasons: vec!["json".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_links_and_fragments_round_trip() {
+        let path = std::env::temp_dir().join(format!("scan_db_{}.db", std::process::id()));
+        let mut db = ScanDb::open(&path, false).unwrap();
+
+        let link = |offset: u64, title: Option<&str>, confidence: f32| {
+            let mut link = EnrichedLink::new(
+                "https://youtu.be/dQw4w9WgXcQ".to_string(),
+                "dQw4w9WgXcQ".to_string(),
+                Offset::new(offset),
+                "short".to_string(),
+                confidence,
+            );
+            link.title = title.map(str::to_string);
+            link
+        };
+        // The same bytes read by two overlapping chunks, then a titled read of them
+        db.insert_links(&[link(100, None, 0.5), link(9000, None, 0.7)]).unwrap();
+        db.insert_links(&[link(100, None, 0.9), link(100, Some("Клип"), 0.6)]).unwrap();
+        db.insert_links(&[link(100, None, 0.95)]).unwrap();
+        let links = db.links().unwrap();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].title.as_deref(), Some("Клип"));
+        assert_eq!(links[0].confidence, 0.6);
+        assert_eq!(links[1].offset, Offset::new(9000));
+
+        db.insert_fragment(1, &fragment(0, 4096, false)).unwrap();
+        db.insert_fragment(2, &fragment(8192, 4096, true)).unwrap();
+        db.insert_fragment(3, &fragment(65536, 4096, false)).unwrap();
+        db.insert_fragment(9, &fragment(0, 4096, false)).unwrap();
+        assert_eq!(db.fragment_count().unwrap(), 3);
+
+        let settled = db.take_fragments(Some(Offset::new(12288)), false).unwrap();
+        assert_eq!(settled.iter().map(|f| f.offset.as_u64()).collect::<Vec<_>>(), [0, 8192]);
+        assert!(settled[1].carved);
+        let rest = db.take_fragments(None, false).unwrap();
+        assert_eq!(rest.len(), 1);
+        assert!(db.take_fragments(None, false).unwrap().is_empty());
+
+        let clusters = db.clusters().unwrap();
+        assert_eq!(clusters.iter().map(|c| c.id).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(clusters[2].end_offset_hex, "0x11000");
+
+        // A resumed scan keeps the rows, a new one starts empty
+        drop(db);
+        assert_eq!(ScanDb::open(&path, true).unwrap().link_count().unwrap(), 2);
+        assert_eq!(ScanDb::open(&path, false).unwrap().link_count().unwrap(), 0);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_fragment_pages_and_streamed_export() {
+        use crate::recovery::FragmentExtent;
+        use crate::report::export::{dedupe_links, export_links, LINK_EXPORT_NAMES};
+
+        let dir = std::env::temp_dir().join(format!("scan_db_export_{}", std::process::id()));
+        let (memory_dir, db_dir) = (dir.join("memory"), dir.join("db"));
+        std::fs::create_dir_all(&memory_dir).unwrap();
+        std::fs::create_dir_all(&db_dir).unwrap();
+        let mut db = ScanDb::open(&dir.join(SCAN_DB_FILE), false).unwrap();
+
+        // Two runs of fragments 4 KiB apart, 1 MiB between the runs
+        for (id, offset) in [0u64, 0x2000, 0x4000, 0x104000, 0x106000].into_iter().enumerate() {
+            db.insert_fragment(id + 1, &fragment(offset, 0x1000, false)).unwrap();
+        }
+        let offsets = |page: Vec<SavedFragment>| page.iter().map(|f| f.offset.as_u64()).collect::<Vec<_>>();
+        // A page goes past its limit up to a gap the solver cannot bridge
+        assert_eq!(offsets(db.take_fragment_page(2, 0x10000).unwrap()), [0, 0x2000, 0x4000]);
+        assert_eq!(db.pending_fragment_count().unwrap(), 2);
+        assert_eq!(offsets(db.take_fragment_page(2, 0x10000).unwrap()), [0x104000, 0x106000]);
+        assert!(db.take_fragment_page(2, 0x10000).unwrap().is_empty());
+
+        let link = |offset: u64, url: &str, title: Option<&str>, confidence: f32| {
+            let mut link = EnrichedLink::new(
+                url.to_string(),
+                url[url.len() - 11..].to_string(),
+                Offset::new(offset),
+                "short".to_string(),
+                confidence,
+            );
+            link.title = title.map(str::to_string);
+            link
+        };
+        let links = vec![
+            link(0x2100, "https://youtu.be/aaaaaaaaaaa", None, 0.9),
+            link(0x100, "https://youtu.be/bbbbbbbbbbb", None, 0.5),
+            link(0x4100, "https://youtu.be/aaaaaaaaaaa", Some("Клип"), 0.4),
+            link(0x105000, "https://youtu.be/ccccccccccc", None, 0.7),
+            link(0x200000, "https://youtu.be/bbbbbbbbbbb", Some("Песня"), 0.5),
+        ];
+        db.insert_links(&links).unwrap();
+        let files = [RecoveredFile {
+            id: 1,
+            filename: "recovered_0001.json".to_string(),
+            file_type: "json".to_string(),
+            confidence: 0.8,
+            links: Vec::new(),
+            size_kb: 4,
+            sha256: String::new(),
+            md5: String::new(),
+            fuzzy_hash: String::new(),
+            size_bytes: 0x1000,
+            utf8_repaired_bytes: 0,
+            trimmed_bytes: 0,
+            start_offset: Offset::new(0x4000),
+            end_offset: Offset::new(0x5000),
+            validation_status: crate::report::ValidationStatus::Valid,
+            recovery_time: String::new(),
+            fragments: vec![FragmentExtent::new(Offset::new(0x4000), Size::new(0x1000))],
+            provenance_hash: String::new(),
+            title: None,
+            title_language: None,
+            post_process: None,
+            repair: None,
+            renamed_from: None,
+            timestamps: None,
+            gaps: Vec::new(),
+        }];
+        let formats: Vec<_> = LINK_EXPORT_NAMES.iter().filter_map(|name| LinkExportFormat::from_name(name)).collect();
+
+        // The database writes what the in-memory export writes, also for --links-only;
+        // the run exports from a read-only connection
+        drop(db);
+        let db = ScanDb::open_read_only(&dir.join(SCAN_DB_FILE)).unwrap();
+        for distinct in [false, true] {
+            let selected = if distinct { dedupe_links(links.clone()) } else { links.clone() };
+            let clusters = db.clusters().unwrap();
+            export_links(&memory_dir, &selected, &files, &clusters, &formats).unwrap();
+            let (count, written) = db.export_links(&db_dir, &files, &formats, distinct).unwrap();
+            assert_eq!(count, selected.len());
+            for path in written {
+                let name = path.file_name().unwrap();
+                let expected = std::fs::read_to_string(memory_dir.join(name)).unwrap();
+                assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+            }
+        }
+        assert_eq!(db.url_count().unwrap(), 3);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_query_filter() {
+        let path = std::env::temp_dir().join(format!("scan_db_query_{}.db", std::process::id()));
+        let mut db = ScanDb::open(&path, false).unwrap();
+        let link = |offset: u64, pattern: &str, title: Option<&str>, confidence: f32| {
+            let mut link = EnrichedLink::new(
+                format!("https://youtu.be/v{:010}", offset),
+                format!("v{:010}", offset),
+                Offset::new(offset),
+                pattern.to_string(),
+                confidence,
+            );
+            link.title = title.map(str::to_string);
+            link
+        };
+        db.insert_links(&[
+            link(100, "short", Some("Мой клип"), 0.9),
+            link(200, "watch", Some("Рецепт"), 0.4),
+            link(0x10000, "short", None, 0.8),
+            link(0x20000, "short", Some("КЛИП 2"), 0.7),
+        ])
+        .unwrap();
+        db.insert_fragment(1, &fragment(0, 4096, false)).unwrap();
+        db.insert_fragment(2, &fragment(0x10000, 4096, true)).unwrap();
+        drop(db);
+
+        let db = ScanDb::open_read_only(&path).unwrap();
+        let offsets = |filter: QueryFilter| -> Vec<u64> {
+            db.query_links(&filter).unwrap().iter().map(|link| link.offset.as_u64()).collect()
+        };
+        assert_eq!(offsets(QueryFilter::default()), [100, 200, 0x10000, 0x20000]);
+        assert_eq!(offsets(QueryFilter { range: Some((150, 0x20000)), ..Default::default() }), [200, 0x10000]);
+        assert_eq!(offsets(QueryFilter { min_confidence: 0.75, ..Default::default() }), [100, 0x10000]);
+        assert_eq!(offsets(QueryFilter { pattern: Some("watch".to_string()), ..Default::default() }), [200]);
+        let title = regex::RegexBuilder::new("клип").case_insensitive(true).build().unwrap();
+        assert_eq!(offsets(QueryFilter { title: Some(title.clone()), ..Default::default() }), [100, 0x20000]);
+        assert_eq!(offsets(QueryFilter { title: Some(title), limit: Some(1), ..Default::default() }), [100]);
+
+        let fragments = db.query_fragments(&QueryFilter { range: Some((0x8000, 0x20000)), ..Default::default() }).unwrap();
+        assert_eq!(fragments.len(), 1);
+        assert_eq!(fragments[0].0, 2);
+        assert!(db.query_fragments(&QueryFilter { pattern: Some("html".to_string()), ..Default::default() }).unwrap().is_empty());
+        assert!(db.insert_fragment(3, &fragment(0x30000, 4096, false)).is_err());
+
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Chunk sizes by region entropy (`--adaptive-chunks`)
+//!
+//! Each chunk is cut into `SEGMENTS` segments and the first sectors of every
+//! segment are sampled. Structured text is scanned in `--chunk-min` pieces,
+//! so fragments and their scores come at a finer granularity; mixed data in
+//! pieces between the two sizes; empty and compressed segments stay in one
+//! piece (up to the whole chunk) for throughput. Every piece still reads the
+//! overlap past its end, within the chunk.
+
+use crate::disk::DiskImage;
+use crate::entropy::calculate_shannon_entropy;
+use crate::scanner::ChunkInfo;
+use crate::types::Offset;
+use memmap2::Mmap;
+use rayon::prelude::*;
+
+/// Segments sampled per chunk
+pub const SEGMENTS: usize = 8;
+/// Bytes sampled at the start of each segment (8 sectors)
+pub const SAMPLE_BYTES: usize = 8 * 512;
+
+/// What a sample looks like
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Zeros or one repeated byte
+    Empty,
+    /// Text and structured records (entropy up to 6 bits/byte)
+    Text,
+    Mixed,
+    /// Compressed or encrypted (above 7.5 bits/byte)
+    Compressed,
+}
+
+impl RegionKind {
+    pub fn classify(sample: &[u8]) -> Self {
+        let entropy = calculate_shannon_entropy(sample);
+        if entropy > 7.5 {
+            Self::Compressed
+        } else if entropy > 6.0 {
+            Self::Mixed
+        } else if entropy > 1.0 {
+            Self::Text
+        } else {
+            Self::Empty
+        }
+    }
+
+    /// Piece size for this kind between `min` and `max` (64-byte aligned)
+    pub fn piece_size(self, min: usize, max: usize) -> usize {
+        match self {
+            Self::Text => min,
+            // Geometric mean: 32 KiB and 2 MiB give 256 KiB
+            Self::Mixed => ((((min as f64) * (max as f64)).sqrt() as usize) / 64 * 64).clamp(min, max),
+            Self::Empty | Self::Compressed => max,
+        }
+    }
+}
+
+/// Pieces of `chunk`; `kind_at(offset, len)` classifies the segment at `offset`.
+/// Whole-chunk kinds of adjacent segments are merged back into one piece.
+pub fn split_chunk<F>(chunk: &ChunkInfo, min: usize, max: usize, overlap: usize, kind_at: F) -> Vec<ChunkInfo>
+where
+    F: Fn(u64, usize) -> RegionKind,
+{
+    let min = min.clamp(64, max.max(64));
+    let own = chunk.size.min(max);
+    let segment = own.div_ceil(SEGMENTS).max(min);
+    let chunk_end = chunk.offset + chunk.size as u64;
+
+    // Own ranges (offset, len), then the overlap is added
+    let mut own_ranges: Vec<(u64, usize, bool)> = Vec::new();
+    let mut start = 0;
+    while start < own {
+        let len = segment.min(own - start);
+        let offset = chunk.offset + start as u64;
+        let kind = kind_at(offset, len);
+        let piece = kind.piece_size(min, max);
+        let whole = piece >= len;
+        match own_ranges.last_mut() {
+            Some(last) if whole && last.2 => last.1 += len,
+            _ if whole => own_ranges.push((offset, len, true)),
+            _ => {
+                let mut piece_start = 0;
+                while piece_start < len {
+                    let piece_len = piece.min(len - piece_start);
+                    own_ranges.push((offset + piece_start as u64, piece_len, false));
+                    piece_start += piece_len;
+                }
+            }
+        }
+        start += len;
+    }
+
+    own_ranges
+        .into_iter()
+        .map(|(offset, len, _)| {
+            let end = (offset + len as u64 + overlap as u64).min(chunk_end);
+            ChunkInfo { offset, size: (end - offset) as usize }
+        })
+        .collect()
+}
+
+/// Split `chunks` by the entropy of their segments, sampled from the mapping
+/// when there is one. Unreadable samples keep their segment whole.
+pub fn refine(disk: &DiskImage, mmap: Option<&Mmap>, chunks: &[ChunkInfo], min: usize, max: usize, overlap: usize) -> Vec<ChunkInfo> {
+    let kind_at = |offset: u64, len: usize| {
+        let len = len.min(SAMPLE_BYTES);
+        match mmap {
+            Some(mmap) => {
+                let start = offset as usize;
+                RegionKind::classify(&mmap[start..(start + len).min(mmap.len())])
+            }
+            None => disk
+                .get_slice(Offset::new(offset), len)
+                .map_or(RegionKind::Empty, |slice| RegionKind::classify(&slice.data)),
+        }
+    };
+    chunks
+        .par_iter()
+        .map(|chunk| split_chunk(chunk, min, max, overlap, kind_at))
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunk_by_region() {
+        let text = b"{\"title\": \"clip\", \"url\": \"https://example.com/watch\"}".repeat(80);
+        let noise: Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        assert_eq!(RegionKind::classify(&[0u8; 4096]), RegionKind::Empty);
+        assert_eq!(RegionKind::classify(&text), RegionKind::Text);
+        assert_eq!(RegionKind::classify(&noise), RegionKind::Compressed);
+        assert_eq!(RegionKind::Mixed.piece_size(32 * 1024, 2048 * 1024), 256 * 1024);
+
+        // 8 segments of 1024: text in the third, compressed elsewhere
+        let chunk = ChunkInfo { offset: 8192, size: 8192 + 64 };
+        let kind = |offset: u64, _len: usize| if offset == 8192 + 2048 { RegionKind::Text } else { RegionKind::Compressed };
+        let pieces: Vec<(u64, usize)> = split_chunk(&chunk, 256, 8192, 64, kind).iter().map(|c| (c.offset, c.size)).collect();
+        assert_eq!(
+            pieces,
+            [(8192, 2048 + 64), (10240, 256 + 64), (10496, 320), (10752, 320), (11008, 320), (11264, 5120 + 64)]
+        );
+
+        // All one coarse kind: the chunk is kept as is
+        let whole = split_chunk(&chunk, 256, 8192, 64, |_, _| RegionKind::Empty);
+        assert_eq!((whole.len(), whole[0].offset, whole[0].size), (1, 8192, 8192 + 64));
+    }
+}
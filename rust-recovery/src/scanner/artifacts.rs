@@ -0,0 +1,377 @@
+//! Browser history artifacts (`--artifacts browser`)
+//!
+//! Chrome's `History` and Firefox's `places.sqlite` keep one row per visited
+//! URL with its title and last visit time, in the `urls` table (`id, url,
+//! title, visit_count, typed_count, last_visit_time, hidden`) and the
+//! `moz_places` table (`id, url, title, rev_host, visit_count, hidden, typed,
+//! frecency, last_visit_date, guid, ...`). A deleted row, a page on the
+//! database's freelist or a deleted database file keeps these records until
+//! the space is reused, so they are carved from the raw bytes rather than
+//! read through the b-tree. `id` is the rowid alias and stored as NULL, so
+//! the URL is the first value of a record body: every URL in a chunk is
+//! taken as the start of a body and the record header right in front of it
+//! is parsed back. The header must give the URL's length and the column
+//! layout of one of the two tables, and the values must be plausible (visit
+//! time between 1990 and 2100, Firefox's reversed host and 12-character
+//! guid). When a row is deleted, the first four bytes of its cell are
+//! overwritten with the freeblock header, which can take the record header's
+//! first bytes; such a record is still read from its URL column on, at a
+//! lower confidence.
+//!
+//! Each row becomes a link with the row's title. Its value is the last visit
+//! time (UTC, RFC 3339), empty for a URL never visited (a bookmark only).
+
+use crate::types::{EnrichedLink, Offset};
+use chrono::{DateTime, Datelike, Utc};
+use memchr::memmem;
+
+/// Artifact names accepted by `--artifacts`
+pub const ARTIFACT_NAMES: &[&str] = &["browser"];
+
+/// Pattern name of Chrome `urls` rows
+pub const CHROME_HISTORY: &str = "chrome_history";
+/// Pattern name of Firefox `moz_places` rows
+pub const FIREFOX_PLACES: &str = "firefox_places";
+
+/// URL schemes a row is looked for at, longest first
+const SCHEMES: &[&[u8]] = &[b"https", b"http", b"file", b"ftp"];
+/// Longest record header read back from a URL
+const MAX_HEADER: usize = 64;
+/// Longest URL taken from a row
+const MAX_URL: usize = 8192;
+/// Confidence of a row with its whole record header
+const INTACT_CONFIDENCE: f32 = 0.95;
+/// Confidence of a row whose header start was overwritten by a freeblock
+const FREEBLOCK_CONFIDENCE: f32 = 0.8;
+/// Microseconds from 1601-01-01 (Chrome's epoch) to 1970-01-01
+const WEBKIT_EPOCH_OFFSET_US: i64 = 11_644_473_600_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Browser {
+    Chrome,
+    Firefox,
+}
+
+/// Columns of a row, as indexes into the serial types from the URL on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    browser: Browser,
+    visits: usize,
+    typed: usize,
+    last_visit: usize,
+    /// Firefox `rev_host`
+    rev_host: Option<usize>,
+}
+
+/// Value of a record column
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value<'a> {
+    Null,
+    Int(i64),
+    Text(&'a [u8]),
+}
+
+/// Browser history rows in `data` (image offset `offset`) as links
+pub fn browser_history_links(data: &[u8], offset: u64) -> Vec<EnrichedLink> {
+    let mut links = Vec::new();
+    for separator in memmem::find_iter(data, b"://") {
+        let Some(scheme) = SCHEMES.iter().find(|scheme| data[..separator].ends_with(scheme)) else {
+            continue;
+        };
+        let url_at = separator - scheme.len();
+        if let Some(link) = read_row(data, url_at) {
+            links.push(EnrichedLink { offset: Offset::new(offset + url_at as u64), ..link });
+        }
+    }
+    links
+}
+
+/// SQLite varint at the start of `data`: value and length
+fn varint(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().take(9).enumerate() {
+        if i == 8 {
+            return Some(((value << 8) | byte as u64, 9));
+        }
+        value = (value << 7) | (byte & 0x7F) as u64;
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+/// Bytes of a value of serial type `serial`, None for the reserved types
+fn content_size(serial: u64) -> Option<usize> {
+    match serial {
+        0 | 8 | 9 => Some(0),
+        1..=4 => Some(serial as usize),
+        5 => Some(6),
+        6 | 7 => Some(8),
+        10 | 11 => None,
+        _ => Some(((serial - 12) / 2) as usize),
+    }
+}
+
+fn is_int(serial: u64) -> bool {
+    matches!(serial, 1..=6 | 8 | 9)
+}
+
+fn is_text(serial: u64) -> bool {
+    serial >= 13 && serial % 2 == 1
+}
+
+/// Table a record belongs to, from its serial types (URL column first)
+fn layout(types: &[u64]) -> Option<Layout> {
+    let title = is_text(types.get(1).copied()?) || types[1] == 0;
+    if !title {
+        return None;
+    }
+    // urls: url, title, visit_count, typed_count, last_visit_time, hidden (, favicon_id before Chrome 37)
+    if matches!(types.len(), 6 | 7) && types[2..].iter().all(|&serial| is_int(serial)) {
+        return Some(Layout { browser: Browser::Chrome, visits: 2, typed: 3, last_visit: 4, rev_host: None });
+    }
+    // moz_places: url, title, rev_host, visit_count, hidden, typed (, favicon_id before Firefox 55),
+    // frecency, last_visit_date, guid, ...
+    if types.len() < 9 || !is_text(types[2]) || !types[3..6].iter().all(|&serial| is_int(serial)) {
+        return None;
+    }
+    let guid = if types[8] == 37 { 8 } else if types.get(9) == Some(&37) && is_int(types[6]) { 9 } else { return None };
+    let last_visit = guid - 1;
+    if !is_int(types[last_visit - 1]) || !(is_int(types[last_visit]) || types[last_visit] == 0) {
+        return None;
+    }
+    Some(Layout { browser: Browser::Firefox, visits: 3, typed: 5, last_visit, rev_host: Some(2) })
+}
+
+/// Serial types of a record header from `start` to the URL at `url_at`
+fn header_types(data: &[u8], start: usize, url_at: usize) -> Option<Vec<u64>> {
+    let mut types = Vec::new();
+    let mut pos = start;
+    while pos < url_at {
+        let (serial, len) = varint(&data[pos..url_at])?;
+        types.push(serial);
+        pos += len;
+    }
+    (pos == url_at && is_text(types[0])).then_some(types)
+}
+
+/// Values of a record body at `body` with serial types `types`; None when it
+/// runs past `data`
+fn values<'a>(data: &'a [u8], body: usize, types: &[u64]) -> Option<Vec<Value<'a>>> {
+    let mut pos = body;
+    let mut values = Vec::with_capacity(types.len());
+    for &serial in types {
+        let size = content_size(serial)?;
+        let bytes = data.get(pos..pos + size)?;
+        values.push(match serial {
+            0 => Value::Null,
+            8 => Value::Int(0),
+            9 => Value::Int(1),
+            1..=6 => {
+                // Big-endian two's complement, sign-extended
+                let unsigned = bytes.iter().fold(0u64, |value, &byte| (value << 8) | byte as u64);
+                let shift = 64 - 8 * size as u32;
+                Value::Int(((unsigned << shift) as i64) >> shift)
+            }
+            _ if is_text(serial) => Value::Text(bytes),
+            _ => Value::Null,
+        });
+        pos += size;
+    }
+    Some(values)
+}
+
+/// Printable URL of a plausible length
+fn valid_url(url: &[u8]) -> Option<&str> {
+    if url.len() > MAX_URL || url.iter().any(|&byte| byte <= b' ' || byte == 0x7F) {
+        return None;
+    }
+    let url = std::str::from_utf8(url).ok()?;
+    let rest = &url[url.find("://")? + 3..];
+    (!rest.is_empty() || url.starts_with("file")).then_some(url)
+}
+
+/// Last visit time of a row, Ok(None) for a URL never visited and Err for
+/// a value out of range (not a real row)
+fn visit_time(browser: Browser, value: Value) -> Result<Option<DateTime<Utc>>, ()> {
+    let micros = match value {
+        Value::Null | Value::Int(0) => return Ok(None),
+        Value::Int(micros) => micros,
+        Value::Text(_) => return Err(()),
+    };
+    let unix_micros = match browser {
+        Browser::Chrome => micros.checked_sub(WEBKIT_EPOCH_OFFSET_US).ok_or(())?,
+        Browser::Firefox => micros,
+    };
+    let time = DateTime::from_timestamp_micros(unix_micros).ok_or(())?;
+    if (1990..2100).contains(&time.year()) {
+        Ok(Some(time))
+    } else {
+        Err(())
+    }
+}
+
+/// Row whose URL starts at `url_at`, as a link at offset 0. The record
+/// header is read back from the URL column; one with the header length and
+/// the NULL `id` in front is intact, else the first row found is taken
+fn read_row(data: &[u8], url_at: usize) -> Option<EnrichedLink> {
+    let mut overwritten = None;
+    for start in (url_at.saturating_sub(MAX_HEADER)..url_at).rev() {
+        let Some(types) = header_types(data, start, url_at) else {
+            continue;
+        };
+        let header_len = url_at - start + 2;
+        let intact = start >= 2 && data[start - 1] == 0 && data[start - 2] as usize == header_len;
+        let Some(link) = row_link(data, url_at, &types, intact) else {
+            continue;
+        };
+        if intact {
+            return Some(link);
+        }
+        overwritten.get_or_insert(link);
+    }
+    overwritten
+}
+
+/// Link of the record body at `url_at` with serial types `types` (URL
+/// column first), None when it is not a history row
+fn row_link(data: &[u8], url_at: usize, types: &[u64], intact: bool) -> Option<EnrichedLink> {
+    let layout = layout(types)?;
+    let values = values(data, url_at, &types[..=layout.last_visit])?;
+    let (Value::Text(url), title) = (values[0], values[1]) else {
+        return None;
+    };
+    let url = valid_url(url)?;
+    let count = |index: usize| match values[index] {
+        Value::Int(count) if (0..10_000_000).contains(&count) => Some(count),
+        _ => None,
+    };
+    let (visits, typed) = (count(layout.visits)?, count(layout.typed)?);
+    if let Some(index) = layout.rev_host {
+        match values[index] {
+            Value::Text(rev_host) if rev_host.ends_with(b".") => {}
+            _ => return None,
+        }
+    }
+    let visited = visit_time(layout.browser, values[layout.last_visit]).ok()?;
+
+    let title = match title {
+        Value::Text(title) => std::str::from_utf8(title).ok().map(str::trim).filter(|title| !title.is_empty()),
+        _ => None,
+    };
+    let (pattern, table) = match layout.browser {
+        Browser::Chrome => (CHROME_HISTORY, "urls"),
+        Browser::Firefox => (FIREFOX_PLACES, "moz_places"),
+    };
+    let value = visited.map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)).unwrap_or_default();
+    let confidence = if intact { INTACT_CONFIDENCE } else { FREEBLOCK_CONFIDENCE };
+    let mut link = EnrichedLink::new(url.to_string(), value, Offset::new(0), pattern.to_string(), confidence);
+    link.title = title.map(str::to_string);
+    link.reasons.push(format!("{} row: visits {}, typed {}", table, visits, typed));
+    if !intact {
+        link.reasons.push("record header overwritten by a freeblock".to_string());
+    }
+    Some(link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SQLite record of `values` (integers take the smallest serial type)
+    fn record(values: &[Value]) -> Vec<u8> {
+        let mut types = Vec::new();
+        let mut body = Vec::new();
+        for value in values {
+            match *value {
+                Value::Null => types.push(0u8),
+                Value::Int(0) => types.push(8),
+                Value::Int(1) => types.push(9),
+                Value::Int(int) if i8::try_from(int).is_ok() => {
+                    types.push(1);
+                    body.push(int as u8);
+                }
+                Value::Int(int) => {
+                    types.push(6);
+                    body.extend_from_slice(&int.to_be_bytes());
+                }
+                Value::Text(text) => {
+                    types.push((text.len() * 2 + 13) as u8);
+                    body.extend_from_slice(text);
+                }
+            }
+        }
+        let mut record = vec![(types.len() + 1) as u8];
+        record.extend(types);
+        record.extend(body);
+        record
+    }
+
+    #[test]
+    fn test_history_rows_are_carved_as_links() {
+        // 2024-01-02T03:04:05Z
+        let unix_micros = 1_704_164_645_000_000i64;
+        let chrome = record(&[
+            Value::Null,
+            Value::Text(b"https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            Value::Text("Песня - YouTube".as_bytes()),
+            Value::Int(3),
+            Value::Int(1),
+            Value::Int(unix_micros + WEBKIT_EPOCH_OFFSET_US),
+            Value::Int(0),
+        ]);
+        let firefox = record(&[
+            Value::Null,
+            Value::Text(b"http://example.org/a"),
+            Value::Null,
+            Value::Text(b"gro.elpmaxe."),
+            Value::Int(0),
+            Value::Int(0),
+            Value::Int(0),
+            Value::Int(100),
+            Value::Null,
+            Value::Text(b"abcdefghijkl"),
+            Value::Int(1),
+            Value::Int(47_359_000_000),
+        ]);
+        // A deleted Chrome row: the freeblock header took its first two bytes
+        let mut deleted = chrome.clone();
+        deleted[..2].copy_from_slice(&[0x0F, 0xA0]);
+
+        let mut page = vec![0u8; 100];
+        let chrome_at = page.len();
+        page.extend(&chrome);
+        let firefox_at = page.len();
+        page.extend(&firefox);
+        page.extend(b"\x00 see https://plain.test/ in text \x00");
+        let deleted_at = page.len();
+        page.extend(&deleted);
+
+        let links = browser_history_links(&page, 4096);
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].url, "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+        assert_eq!(links[0].offset.as_u64(), 4096 + chrome_at as u64 + 8);
+        assert_eq!(links[0].title.as_deref(), Some("Песня - YouTube"));
+        assert_eq!(links[0].video_id, "2024-01-02T03:04:05Z");
+        assert_eq!(links[0].pattern_name, CHROME_HISTORY);
+        assert_eq!(links[0].confidence, INTACT_CONFIDENCE);
+        assert_eq!(links[0].reasons, vec!["urls row: visits 3, typed 1".to_string()]);
+
+        assert_eq!(links[1].url, "http://example.org/a");
+        assert_eq!(links[1].offset.as_u64(), 4096 + firefox_at as u64 + 13);
+        assert_eq!(links[1].title, None);
+        assert_eq!(links[1].video_id, "");
+        assert_eq!(links[1].pattern_name, FIREFOX_PLACES);
+
+        assert_eq!(links[2].offset.as_u64(), 4096 + deleted_at as u64 + 8);
+        assert_eq!(links[2].video_id, "2024-01-02T03:04:05Z");
+        assert_eq!(links[2].confidence, FREEBLOCK_CONFIDENCE);
+
+        // A visit time out of range is not a row
+        let mut bogus = chrome.clone();
+        let time_at = bogus.len() - 8;
+        bogus[time_at..time_at + 8].copy_from_slice(&i64::MAX.to_be_bytes());
+        assert!(browser_history_links(&bogus, 0).is_empty());
+    }
+}
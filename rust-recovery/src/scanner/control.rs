@@ -0,0 +1,167 @@
+//! Operator control of a running scan (dashboard hotkeys)
+//!
+//! Pause holds workers before they pick up the next chunk; chunks already in
+//! flight finish. Skip raises the cancel flag of every chunk in flight: the
+//! workers abandon them like a stalled chunk, but they are reported as skipped
+//! instead of being requeued. Cancel (Ctrl-C) stops the whole scan: chunks in
+//! flight are abandoned and the rest are never started; none of them count
+//! as done for `--resume`. Stop (`--max-duration`) starts no new chunks but
+//! lets the ones in flight finish.
+
+use crate::diagnostics::DiagnosticCause;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Polling period of paused workers
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shared pause/skip/cancel state of one scanner
+#[derive(Debug, Default)]
+pub struct ScanControl {
+    paused: AtomicBool,
+    cancelled: AtomicBool,
+    /// Set with `cancelled` by `stop`
+    stopped: AtomicBool,
+    /// Cancel flags of the chunks in flight, by chunk offset
+    in_flight: Mutex<HashMap<u64, Arc<AtomicBool>>>,
+    /// Chunks cancelled by `skip` that have not been collected yet
+    skipped: Mutex<HashSet<u64>>,
+}
+
+impl ScanControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Block the calling worker while the scan is paused
+    pub fn wait_while_paused(&self) {
+        while self.is_paused() && !self.is_cancelled() {
+            std::thread::sleep(PAUSE_POLL_INTERVAL);
+        }
+    }
+
+    /// Abandon every chunk in flight; returns how many were cancelled
+    pub fn skip(&self) -> usize {
+        let in_flight = self.in_flight.lock().unwrap();
+        let mut skipped = self.skipped.lock().unwrap();
+        for (offset, cancel) in in_flight.iter() {
+            cancel.store(true, Ordering::Relaxed);
+            skipped.insert(*offset);
+        }
+        in_flight.len()
+    }
+
+    /// Stop the scan: abandon the chunks in flight and start no new ones
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        for cancel in self.in_flight.lock().unwrap().values() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Stop dispatching chunks; the chunks in flight run to completion
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// True once `stop` was called (the time budget ran out)
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// Diagnostic cause of the chunks left unscanned
+    pub fn cancel_cause(&self) -> DiagnosticCause {
+        if self.is_stopped() {
+            DiagnosticCause::TimeBudget
+        } else {
+            DiagnosticCause::Cancelled
+        }
+    }
+
+    /// Register a chunk's cancel flag for the lifetime of the returned guard
+    pub fn track(&self, offset: u64, cancel: Arc<AtomicBool>) -> ControlGuard<'_> {
+        self.in_flight.lock().unwrap().insert(offset, cancel);
+        ControlGuard { control: self, offset }
+    }
+}
+
+/// Registration of one in-flight chunk; unregisters on drop
+#[derive(Debug)]
+pub struct ControlGuard<'a> {
+    control: &'a ScanControl,
+    offset: u64,
+}
+
+impl ControlGuard<'_> {
+    /// True if the chunk was cancelled by `ScanControl::skip`
+    pub fn was_skipped(&self) -> bool {
+        self.control.skipped.lock().unwrap().remove(&self.offset)
+    }
+}
+
+impl Drop for ControlGuard<'_> {
+    fn drop(&mut self) {
+        self.control.in_flight.lock().unwrap().remove(&self.offset);
+        self.control.skipped.lock().unwrap().remove(&self.offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_cancels_in_flight_chunks() {
+        let control = ScanControl::new();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let guard = control.track(0x1000, Arc::clone(&cancel));
+
+        assert_eq!(control.skip(), 1);
+        assert!(cancel.load(Ordering::Relaxed));
+        assert!(guard.was_skipped());
+        drop(guard);
+
+        // Chunks registered after the skip are not affected
+        let fresh = control.track(0x2000, Arc::new(AtomicBool::new(false)));
+        assert!(!fresh.was_skipped());
+        drop(fresh);
+        assert_eq!(control.skip(), 0);
+
+        // Cancel raises the flags without marking the chunks skipped
+        let cancel = Arc::new(AtomicBool::new(false));
+        let guard = control.track(0x3000, Arc::clone(&cancel));
+        control.pause();
+        control.cancel();
+        assert!(cancel.load(Ordering::Relaxed) && control.is_cancelled());
+        assert!(!guard.was_skipped());
+        control.wait_while_paused();
+        assert_eq!(control.cancel_cause(), DiagnosticCause::Cancelled);
+
+        // Stop leaves the chunks in flight running
+        let control = ScanControl::new();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let _guard = control.track(0x4000, Arc::clone(&cancel));
+        control.stop();
+        assert!(control.is_cancelled() && !cancel.load(Ordering::Relaxed));
+        assert_eq!(control.cancel_cause(), DiagnosticCause::TimeBudget);
+    }
+}
@@ -0,0 +1,201 @@
+//! Fixed-size structure detectors evaluated in the block pass
+//!
+//! The chunk scanner already walks every chunk in 64-byte blocks. Detectors
+//! registered here get each block that starts on their alignment and test
+//! it with cheap byte-position predicates, so new on-disk structures are
+//! found without another walk over the data. The window size is a const
+//! parameter: the scanner uses `DetectorRegistry<BLOCK_SIZE>`.
+
+use crate::types::{Offset, StructureHit};
+
+/// Block size of the scanner pass
+pub const BLOCK_SIZE: usize = 64;
+
+/// Built-in detectors, by `--detect` name
+pub const DETECTOR_NAMES: &[&str] = &["ntfs_file", "sqlite", "exif"];
+
+/// Structure recognised from the first `N` bytes at an aligned position
+pub trait StructureDetector<const N: usize>: Send + Sync {
+    /// Name reported with each hit
+    fn name(&self) -> &'static str;
+
+    /// Image alignment of the structure (a power of two, multiple of `N`)
+    fn alignment(&self) -> u64;
+
+    /// Test the window starting at an aligned position
+    fn matches(&self, window: &[u8; N]) -> bool;
+}
+
+/// Detectors sharing one pass over `N`-byte windows
+pub struct DetectorRegistry<const N: usize> {
+    detectors: Vec<Box<dyn StructureDetector<N>>>,
+    /// Smallest alignment: windows off it are skipped without testing
+    min_alignment: u64,
+}
+
+impl<const N: usize> Default for DetectorRegistry<N> {
+    fn default() -> Self {
+        Self { detectors: Vec::new(), min_alignment: u64::MAX }
+    }
+}
+
+impl<const N: usize> DetectorRegistry<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, detector: Box<dyn StructureDetector<N>>) {
+        assert!(
+            detector.alignment().is_power_of_two() && detector.alignment() >= N as u64,
+            "detector alignment must be a power of two of at least the window size"
+        );
+        self.min_alignment = self.min_alignment.min(detector.alignment());
+        self.detectors.push(detector);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.detectors.is_empty()
+    }
+
+    /// Test the window at image offset `position`
+    #[inline]
+    pub fn check(&self, position: u64, window: &[u8; N], hits: &mut Vec<StructureHit>) {
+        if position & (self.min_alignment - 1) != 0 {
+            return;
+        }
+        for detector in &self.detectors {
+            if position & (detector.alignment() - 1) == 0 && detector.matches(window) {
+                hits.push(StructureHit { offset: Offset::new(position), structure: detector.name() });
+            }
+        }
+    }
+
+    /// Test every aligned window of `data` (image offset `offset`); used
+    /// where the SIMD block pass does not run
+    pub fn scan(&self, data: &[u8], offset: u64, hits: &mut Vec<StructureHit>) {
+        if self.is_empty() {
+            return;
+        }
+        let first = (offset.next_multiple_of(self.min_alignment) - offset) as usize;
+        let mut i = first;
+        while i + N <= data.len() {
+            if let Ok(window) = data[i..i + N].try_into() {
+                self.check(offset + i as u64, window, hits);
+            }
+            i += self.min_alignment as usize;
+        }
+    }
+}
+
+impl DetectorRegistry<BLOCK_SIZE> {
+    /// Registry of the built-in detectors named in `names`
+    pub fn from_names(names: &[String]) -> Self {
+        let mut registry = Self::new();
+        for name in names {
+            match name.as_str() {
+                "ntfs_file" => registry.register(Box::new(NtfsFileRecord)),
+                "sqlite" => registry.register(Box::new(SqliteHeader)),
+                "exif" => registry.register(Box::new(ExifJpeg)),
+                _ => {}
+            }
+        }
+        registry
+    }
+}
+
+/// NTFS MFT `FILE` record (1024-byte records)
+pub struct NtfsFileRecord;
+
+impl StructureDetector<BLOCK_SIZE> for NtfsFileRecord {
+    fn name(&self) -> &'static str {
+        "ntfs_file"
+    }
+
+    fn alignment(&self) -> u64 {
+        1024
+    }
+
+    fn matches(&self, window: &[u8; BLOCK_SIZE]) -> bool {
+        let usa_offset = u16::from_le_bytes([window[4], window[5]]);
+        let usa_count = u16::from_le_bytes([window[6], window[7]]);
+        let first_attribute = u16::from_le_bytes([window[0x14], window[0x15]]);
+        let flags = u16::from_le_bytes([window[0x16], window[0x17]]);
+        &window[..4] == b"FILE"
+            && (0x28..0x100).contains(&usa_offset)
+            && usa_count == 3
+            && (0x30..0x400).contains(&first_attribute)
+            && first_attribute.is_multiple_of(8)
+            && flags <= 0x0F
+    }
+}
+
+/// SQLite database header (page 1 of a database file)
+pub struct SqliteHeader;
+
+impl StructureDetector<BLOCK_SIZE> for SqliteHeader {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn alignment(&self) -> u64 {
+        512
+    }
+
+    fn matches(&self, window: &[u8; BLOCK_SIZE]) -> bool {
+        // Page size 1 means 65536
+        let page_size = u16::from_be_bytes([window[16], window[17]]);
+        &window[..16] == b"SQLite format 3\0"
+            && (page_size == 1 || (page_size.is_power_of_two() && page_size >= 512))
+            && matches!(window[18], 1 | 2)
+            && matches!(window[19], 1 | 2)
+            && window[21..24] == [64, 32, 32]
+    }
+}
+
+/// JPEG starting with an APP1 Exif segment and its TIFF header
+pub struct ExifJpeg;
+
+impl StructureDetector<BLOCK_SIZE> for ExifJpeg {
+    fn name(&self) -> &'static str {
+        "exif"
+    }
+
+    fn alignment(&self) -> u64 {
+        512
+    }
+
+    fn matches(&self, window: &[u8; BLOCK_SIZE]) -> bool {
+        window[..4] == [0xFF, 0xD8, 0xFF, 0xE1]
+            && &window[6..12] == b"Exif\0\0"
+            && matches!(&window[12..16], b"II*\0" | b"MM\0*")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_detectors_at_alignment() {
+        let mut data = vec![0u8; 4096];
+        data[1024..1028].copy_from_slice(b"FILE");
+        data[1028..1030].copy_from_slice(&0x30u16.to_le_bytes());
+        data[1030..1032].copy_from_slice(&3u16.to_le_bytes());
+        data[1044..1046].copy_from_slice(&0x38u16.to_le_bytes());
+        data[1046..1048].copy_from_slice(&1u16.to_le_bytes());
+
+        let sqlite = b"SQLite format 3\0\x10\x00\x01\x01\x00\x40\x20\x20";
+        data[2560..2560 + sqlite.len()].copy_from_slice(sqlite);
+        // Not on a 512-byte boundary: ignored
+        data[3100..3100 + sqlite.len()].copy_from_slice(sqlite);
+        data[3584..3600].copy_from_slice(b"\xFF\xD8\xFF\xE1\x00\x10Exif\0\0MM\0*");
+
+        let names: Vec<String> = DETECTOR_NAMES.iter().map(|n| n.to_string()).collect();
+        let registry = DetectorRegistry::from_names(&names);
+        let mut hits = Vec::new();
+        registry.scan(&data, 0x10000, &mut hits);
+
+        let found: Vec<(u64, &str)> = hits.iter().map(|h| (h.offset.as_u64(), h.structure)).collect();
+        assert_eq!(found, vec![(0x10400, "ntfs_file"), (0x10A00, "sqlite"), (0x10E00, "exif")]);
+    }
+}
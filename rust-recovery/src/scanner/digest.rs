@@ -0,0 +1,179 @@
+//! Full-image digest computed from the scan's own reads (`--verify-hash`)
+//!
+//! Every chunk the scanner reads is offered to `ImageDigest::feed`. Bytes
+//! that continue the hashed prefix go straight into SHA-256 (and MD5); a
+//! chunk that arrives ahead of it is copied aside until the gap closes, up
+//! to `PENDING_LIMIT` bytes. Overlap bytes already hashed are skipped.
+//! `finish` then reads only what the scan did not hand over (ranges outside
+//! `--partition` or `--unallocated-only`, chunks dropped over the limit,
+//! the whole image with `--reverse`) and hashes it in order. Unreadable
+//! sectors are hashed as the zeros the scan saw.
+
+use crate::disk::DiskImage;
+use crate::error::Result;
+use crate::types::Offset;
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Out-of-order chunk bytes held while waiting for the gap before them
+const PENDING_LIMIT: usize = 256 * 1024 * 1024;
+/// Read size of `finish`
+const CATCH_UP_BLOCK: usize = 4 * 1024 * 1024;
+
+/// Digest of the whole source image, for the report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceDigest {
+    pub sha256: String,
+    /// With `--verify-hash sha256,md5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+    /// Bytes hashed (the image size)
+    pub bytes: u64,
+    /// Bytes read again after the scan because the scan did not hand them over
+    pub reread_bytes: u64,
+}
+
+#[derive(Debug)]
+struct DigestState {
+    sha256: Sha256,
+    md5: Option<Md5>,
+    /// End of the hashed prefix
+    next: u64,
+    /// Chunks beyond `next`, by offset
+    pending: BTreeMap<u64, Vec<u8>>,
+    pending_bytes: usize,
+}
+
+impl DigestState {
+    fn update(&mut self, data: &[u8]) {
+        self.sha256.update(data);
+        if let Some(md5) = self.md5.as_mut() {
+            md5.update(data);
+        }
+        self.next += data.len() as u64;
+    }
+
+    /// Hash the part of `data` (starting at `offset <= next`) past `next`
+    fn extend(&mut self, offset: u64, data: &[u8]) {
+        let skip = (self.next - offset) as usize;
+        if skip < data.len() {
+            self.update(&data[skip..]);
+        }
+    }
+
+    /// Hash the pending chunks the prefix has reached
+    fn drain(&mut self) {
+        while let Some(entry) = self.pending.first_entry() {
+            if *entry.key() > self.next {
+                break;
+            }
+            let (offset, data) = entry.remove_entry();
+            self.pending_bytes -= data.len();
+            self.extend(offset, &data);
+        }
+    }
+}
+
+/// Running SHA-256 (and optionally MD5) of the image, fed by the scan
+#[derive(Debug)]
+pub struct ImageDigest {
+    state: Mutex<DigestState>,
+}
+
+impl ImageDigest {
+    pub fn new(md5: bool) -> Self {
+        Self {
+            state: Mutex::new(DigestState {
+                sha256: Sha256::new(),
+                md5: md5.then(Md5::new),
+                next: 0,
+                pending: BTreeMap::new(),
+                pending_bytes: 0,
+            }),
+        }
+    }
+
+    /// Offer the bytes the scan read at `offset`
+    pub fn feed(&self, offset: u64, data: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        if offset + data.len() as u64 <= state.next {
+            return;
+        }
+        if offset <= state.next {
+            state.extend(offset, data);
+            state.drain();
+            return;
+        }
+        // Ahead of the prefix: keep a copy unless the limit is reached
+        // (`finish` reads those bytes again)
+        let held = state.pending.get(&offset).map_or(0, Vec::len);
+        if data.len() > held && state.pending_bytes - held + data.len() <= PENDING_LIMIT {
+            state.pending_bytes = state.pending_bytes - held + data.len();
+            state.pending.insert(offset, data.to_vec());
+        }
+    }
+
+    /// Hash the rest of the image and return the digests; called once, after
+    /// the scan. The size is taken from the disk (a pipe knows it only at the end)
+    pub fn finish(&self, disk: &DiskImage) -> Result<SourceDigest> {
+        let mut state = self.state.lock().unwrap();
+        let image_size = disk.size().as_u64();
+        let mut buffer = vec![0u8; CATCH_UP_BLOCK];
+        let mut reread_bytes = 0;
+        while state.next < image_size {
+            // Read up to the next pending chunk, then let it extend the prefix
+            let stop = state.pending.keys().next().copied().unwrap_or(image_size).min(image_size);
+            let len = ((stop - state.next) as usize).min(CATCH_UP_BLOCK);
+            if len > 0 {
+                let block = &mut buffer[..len];
+                disk.read_tolerant(Offset::new(state.next), block)?;
+                state.update(block);
+                reread_bytes += len as u64;
+            }
+            state.drain();
+        }
+        Ok(SourceDigest {
+            sha256: format!("{:x}", std::mem::take(&mut state.sha256).finalize()),
+            md5: state.md5.take().map(|md5| format!("{:x}", md5.finalize())),
+            bytes: image_size,
+            reread_bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest_from_out_of_order_overlapping_chunks() {
+        let image: Vec<u8> = (0..100_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let path = std::env::temp_dir().join(format!("rust_recovery_digest_{}.img", std::process::id()));
+        std::fs::write(&path, &image).unwrap();
+        let disk = DiskImage::open(&path).unwrap();
+
+        // Chunks of 16 KiB with 1 KiB overlap, out of order, one missing
+        let digest = ImageDigest::new(true);
+        let chunk = |start: usize| &image[start..(start + 17 * 1024).min(image.len())];
+        for start in [16_384, 0, 49_152, 32_768] {
+            digest.feed(start as u64, chunk(start));
+        }
+        digest.feed(81_920, &image[81_920..]);
+        let result = digest.finish(&disk).unwrap();
+        assert_eq!(result.sha256, format!("{:x}", Sha256::digest(&image)));
+        assert_eq!(result.md5, Some(format!("{:x}", Md5::digest(&image))));
+        assert_eq!(result.bytes, image.len() as u64);
+        // Only the chunk at 65536, less the overlap of the one before, was read again
+        assert_eq!(result.reread_bytes, 81_920 - (49_152 + 17 * 1024));
+
+        let untouched = ImageDigest::new(false).finish(&disk).unwrap();
+        assert_eq!(untouched.sha256, result.sha256);
+        assert_eq!(untouched.md5, None);
+        assert_eq!(untouched.reread_bytes, image.len() as u64);
+        drop(disk);
+        let _ = std::fs::remove_file(&path);
+    }
+}
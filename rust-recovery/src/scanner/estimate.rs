@@ -0,0 +1,315 @@
+//! Sampling estimate of recoverable content (`rust-recovery estimate`)
+//!
+//! The image is split into equal strata and one window at a random position
+//! of each is scored like a scan chunk (matcher links, hot fragment). Counts
+//! are extrapolated to the whole image with 95% confidence intervals; windows
+//! that read as all zeros point to trimmed or wiped space. Windows are
+//! visited in random order, so a sample cut short by the time limit is still
+//! spread over the image.
+
+use crate::disk::DiskImage;
+use crate::scanner::ParallelScanner;
+use crate::types::Offset;
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Normal quantile of a two-sided 95% interval
+const Z_95: f64 = 1.96;
+/// Zero windows above this share with no hits: likely TRIMmed
+const TRIMMED_ZERO_FRACTION: f64 = 0.9;
+/// Windows with hits above this share: content is spread, scan everything
+const FULL_SCAN_HIT_FRACTION: f64 = 0.2;
+
+/// Sample size and limits
+#[derive(Debug, Clone)]
+pub struct EstimateConfig {
+    /// Share of the image to sample (0.01 = 1%)
+    pub sample_fraction: f64,
+    pub window_size: usize,
+    /// Same seed, same windows
+    pub seed: u64,
+    /// Windows not started by then are left out of the sample
+    pub time_limit: Duration,
+}
+
+impl Default for EstimateConfig {
+    fn default() -> Self {
+        Self {
+            sample_fraction: 0.02,
+            window_size: 1024 * 1024,
+            seed: 0,
+            time_limit: Duration::from_secs(9 * 60),
+        }
+    }
+}
+
+/// Extrapolated total with its 95% confidence interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub estimate: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Interval {
+    /// Total over `population` windows from the per-window values of a
+    /// simple random sample (finite population correction included)
+    pub fn extrapolate(values: &[f64], population: u64) -> Self {
+        let n = values.len() as f64;
+        let population = population as f64;
+        if values.is_empty() {
+            return Self { estimate: 0.0, low: 0.0, high: 0.0 };
+        }
+        let mean = values.iter().sum::<f64>() / n;
+        let estimate = mean * population;
+        if values.len() < 2 {
+            return Self { estimate, low: estimate, high: estimate };
+        }
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let correction = (1.0 - n / population).max(0.0);
+        let margin = Z_95 * population * (variance / n * correction).sqrt();
+        Self { estimate, low: (estimate - margin).max(0.0), high: estimate + margin }
+    }
+
+    pub fn scaled(self, factor: f64) -> Self {
+        Self { estimate: self.estimate * factor, low: self.low * factor, high: self.high * factor }
+    }
+}
+
+/// What to do next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recommendation {
+    /// Hits are spread over the image, or the sample cannot rule content out
+    FullScan,
+    /// Hits sit in a few regions
+    TargetedScan,
+    /// Mostly zeros and nothing found: the space was probably TRIMmed or wiped
+    LikelyTrimmed,
+}
+
+impl Recommendation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FullScan => "full_scan",
+            Self::TargetedScan => "targeted_scan",
+            Self::LikelyTrimmed => "likely_trimmed",
+        }
+    }
+
+    pub fn advice(self) -> &'static str {
+        match self {
+            Self::FullScan => "run a full scan; content is spread over the image or too sparse for the sample",
+            Self::TargetedScan => {
+                "content is concentrated in the regions below: scan with --priority (and --time-budget) \
+                 or --metadata-first, or --reverse when they lie in the upper half"
+            }
+            Self::LikelyTrimmed => "the image reads mostly as zeros with no hits; a scan is unlikely to recover anything",
+        }
+    }
+}
+
+/// Result of one sampled window
+#[derive(Debug, Clone, Copy)]
+struct WindowSample {
+    offset: u64,
+    links: usize,
+    hot: bool,
+    zero: bool,
+}
+
+impl WindowSample {
+    fn is_hit(&self) -> bool {
+        self.hot || self.links > 0
+    }
+}
+
+/// Sampling results extrapolated to the image
+#[derive(Debug, Clone)]
+pub struct Estimate {
+    pub image_size: u64,
+    pub window_size: usize,
+    pub windows_total: u64,
+    pub windows_sampled: u64,
+    /// Windows that could not be read (bad sectors)
+    pub windows_unreadable: u64,
+    pub bytes_sampled: u64,
+    /// Links a full scan should find (duplicates across windows included)
+    pub links: Interval,
+    /// Windows a full scan should score as hot fragments
+    pub hot_fragments: Interval,
+    /// Bytes in windows with links or a hot fragment: the likely recovery volume
+    pub recoverable_bytes: Interval,
+    pub zero_fraction: f64,
+    /// Sampled windows with links or a hot fragment, by offset
+    pub hit_regions: Vec<u64>,
+    /// The time limit ended sampling early
+    pub truncated: bool,
+    pub duration: Duration,
+    pub recommendation: Recommendation,
+}
+
+impl Estimate {
+    /// Last stdout line of `estimate`, for scripts
+    pub fn line(&self) -> String {
+        format!(
+            "ESTIMATE recommendation={} links={:.0} links_low={:.0} links_high={:.0} hot_fragments={:.0} \
+             recoverable_bytes={:.0} zero_fraction={:.3} bytes_sampled={} truncated={}",
+            self.recommendation.name(),
+            self.links.estimate,
+            self.links.low,
+            self.links.high,
+            self.hot_fragments.estimate,
+            self.recoverable_bytes.estimate,
+            self.zero_fraction,
+            self.bytes_sampled,
+            self.truncated
+        )
+    }
+}
+
+/// splitmix64: small, seedable, good enough to place windows
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in 0..bound (bound > 0)
+    fn below(&mut self, bound: u64) -> u64 {
+        ((self.next() as u128 * bound as u128) >> 64) as u64
+    }
+}
+
+/// Window indexes to sample, one per stratum, in random order
+pub fn sample_windows(windows_total: u64, sample_fraction: f64, seed: u64) -> Vec<u64> {
+    if windows_total == 0 {
+        return Vec::new();
+    }
+    let count = ((windows_total as f64 * sample_fraction).ceil() as u64).clamp(1, windows_total);
+    let mut rng = SplitMix64(seed);
+    let mut indexes: Vec<u64> = (0..count)
+        .map(|stratum| {
+            let start = (stratum as u128 * windows_total as u128 / count as u128) as u64;
+            let end = ((stratum + 1) as u128 * windows_total as u128 / count as u128) as u64;
+            start + rng.below(end - start)
+        })
+        .collect();
+    for i in (1..indexes.len()).rev() {
+        let j = rng.below(i as u64 + 1) as usize;
+        indexes.swap(i, j);
+    }
+    indexes
+}
+
+/// Score a random sample of `disk` with `scanner`'s matcher and extrapolate
+pub fn estimate(disk: &DiskImage, scanner: &ParallelScanner, config: &EstimateConfig) -> Estimate {
+    let started = Instant::now();
+    let image_size = disk.size().as_u64();
+    let window_size = config.window_size.max(1);
+    let windows_total = image_size.div_ceil(window_size as u64);
+    let indexes = sample_windows(windows_total, config.sample_fraction, config.seed);
+
+    // None: skipped by the time limit; Some(None): unreadable
+    let results: Vec<Option<Option<WindowSample>>> = indexes
+        .par_iter()
+        .map(|&index| {
+            if started.elapsed() >= config.time_limit {
+                return None;
+            }
+            let offset = index * window_size as u64;
+            let len = (image_size - offset).min(window_size as u64) as usize;
+            let Ok(slice) = disk.get_slice(Offset::new(offset), len) else {
+                return Some(None);
+            };
+            let data = &slice.data;
+            let (links, hot_fragment) = scanner.scan_window(data, offset);
+            Some(Some(WindowSample {
+                offset,
+                links: links.len(),
+                hot: hot_fragment.is_some(),
+                zero: data.iter().all(|&b| b == 0),
+            }))
+        })
+        .collect();
+
+    let truncated = results.iter().any(Option::is_none);
+    let windows_unreadable = results.iter().filter(|r| matches!(r, Some(None))).count() as u64;
+    let samples: Vec<WindowSample> = results.into_iter().flatten().flatten().collect();
+    let sampled = samples.len() as u64;
+
+    let indicator = |flag: bool| if flag { 1.0 } else { 0.0 };
+    let links: Vec<f64> = samples.iter().map(|s| s.links as f64).collect();
+    let hot: Vec<f64> = samples.iter().map(|s| indicator(s.hot)).collect();
+    let hits: Vec<f64> = samples.iter().map(|s| indicator(s.is_hit())).collect();
+    let share = |count: usize| if sampled == 0 { 0.0 } else { count as f64 / sampled as f64 };
+    let mut hit_regions: Vec<u64> = samples.iter().filter(|s| s.is_hit()).map(|s| s.offset).collect();
+    hit_regions.sort_unstable();
+    let zero_fraction = share(samples.iter().filter(|s| s.zero).count());
+    let hit_fraction = share(hit_regions.len());
+
+    let recommendation = if hit_regions.is_empty() && zero_fraction >= TRIMMED_ZERO_FRACTION {
+        Recommendation::LikelyTrimmed
+    } else if hit_regions.is_empty() || hit_fraction >= FULL_SCAN_HIT_FRACTION {
+        Recommendation::FullScan
+    } else {
+        Recommendation::TargetedScan
+    };
+
+    Estimate {
+        image_size,
+        window_size,
+        windows_total,
+        windows_sampled: sampled,
+        windows_unreadable,
+        bytes_sampled: samples.iter().map(|s| (image_size - s.offset).min(window_size as u64)).sum(),
+        links: Interval::extrapolate(&links, windows_total),
+        hot_fragments: Interval::extrapolate(&hot, windows_total),
+        recoverable_bytes: Interval::extrapolate(&hits, windows_total).scaled(window_size as f64),
+        zero_fraction,
+        hit_regions,
+        truncated,
+        duration: started.elapsed(),
+        recommendation,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ScanConfig;
+
+    #[test]
+    fn test_sample_windows_and_estimate() {
+        // One window per stratum, no repeats, reproducible from the seed
+        let mut windows = sample_windows(1000, 0.05, 7);
+        assert_eq!(windows, sample_windows(1000, 0.05, 7));
+        windows.sort_unstable();
+        assert_eq!(windows.len(), 50);
+        assert!(windows.iter().enumerate().all(|(stratum, &w)| w / 20 == stratum as u64));
+        assert_eq!(sample_windows(3, 0.01, 1).len(), 1);
+
+        let interval = Interval::extrapolate(&[1.0, 1.0, 1.0], 3);
+        assert_eq!((interval.estimate, interval.low, interval.high), (3.0, 3.0, 3.0));
+
+        // Zeros only: nothing to find
+        let mut path = std::env::temp_dir();
+        path.push(format!("rust_recovery_estimate_{}.img", std::process::id()));
+        std::fs::write(&path, vec![0u8; 64 * 4096]).unwrap();
+        let disk = DiskImage::open_unmapped(&path).unwrap();
+        let scanner = ParallelScanner::new(ScanConfig::default());
+        let config = EstimateConfig { sample_fraction: 0.25, window_size: 4096, ..Default::default() };
+        let result = estimate(&disk, &scanner, &config);
+        assert_eq!((result.windows_total, result.windows_sampled, result.bytes_sampled), (64, 16, 16 * 4096));
+        assert_eq!(result.zero_fraction, 1.0);
+        assert_eq!(result.recommendation, Recommendation::LikelyTrimmed);
+        assert!(!result.truncated);
+        assert!(result.line().starts_with("ESTIMATE recommendation=likely_trimmed links=0 "));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
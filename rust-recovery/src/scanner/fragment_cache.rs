@@ -0,0 +1,290 @@
+//! Fragment bytes kept from the scan for assembly (`--fragment-cache`)
+//!
+//! Assembly reads every fragment of a stream again (`reassemble`, the
+//! semantic clusterer, e-mail continuity). On a spinning disk that is a
+//! second seek storm over the whole image. The scanner instead offers the
+//! bytes of every hot and carved fragment to `FragmentCache::insert` while
+//! they are still in its chunk buffer, and the writer asks `get` first.
+//!
+//! Entries are content-addressed: a fragment is stored once under the
+//! SHA-256 of its bytes, and `(offset, len)` index entries point at it, so
+//! duplicate chunks (zeroed runs, copies of one file) share their memory.
+//! Entries are kept in memory up to the memory budget; the least recently
+//! used are then written to `fragment_cache/<sha256>` in the output
+//! directory, up to the spill budget, past which the oldest spilled ones
+//! are dropped (`get` misses and the caller reads the image). The spill
+//! directory is removed by `clear` and when the cache is dropped.
+
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Directory of spilled entries, in the output directory
+pub const FRAGMENT_CACHE_DIR: &str = "fragment_cache";
+
+type Key = [u8; 32];
+
+/// Counters of a cache, for the run summary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FragmentCacheStats {
+    /// Distinct fragments stored
+    pub stored: u64,
+    /// Fragments whose bytes were already stored
+    pub deduplicated: u64,
+    /// Reads answered from memory or the spill directory
+    pub hits: u64,
+    /// Reads left to the image
+    pub misses: u64,
+    /// Bytes written to the spill directory
+    pub spilled_bytes: u64,
+    /// Entries dropped over the spill budget
+    pub dropped: u64,
+}
+
+#[derive(Debug)]
+enum Slot {
+    Memory(Arc<[u8]>),
+    Spilled,
+}
+
+#[derive(Debug)]
+struct Entry {
+    len: usize,
+    slot: Slot,
+    /// Position in `memory_lru` or `spill_lru`
+    tick: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    /// Content key of the fragment at `(offset, len)`
+    index: BTreeMap<(u64, usize), Key>,
+    entries: HashMap<Key, Entry>,
+    /// Entries in memory, least recently used first
+    memory_lru: BTreeMap<u64, Key>,
+    /// Spilled entries, least recently used first
+    spill_lru: BTreeMap<u64, Key>,
+    tick: u64,
+    memory_bytes: usize,
+    spill_bytes: u64,
+    stats: FragmentCacheStats,
+}
+
+impl CacheState {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Mark `key` as just used
+    fn touch(&mut self, key: &Key) {
+        let tick = self.next_tick();
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+        let lru = match entry.slot {
+            Slot::Memory(_) => &mut self.memory_lru,
+            Slot::Spilled => &mut self.spill_lru,
+        };
+        lru.remove(&entry.tick);
+        lru.insert(tick, *key);
+        entry.tick = tick;
+    }
+}
+
+/// Fragment bytes shared by the scanner threads and the file writer
+#[derive(Debug)]
+pub struct FragmentCache {
+    memory_budget: usize,
+    spill_budget: u64,
+    spill_dir: PathBuf,
+    state: Mutex<CacheState>,
+}
+
+impl FragmentCache {
+    /// Cache holding up to `memory_budget` bytes in memory and `spill_budget`
+    /// more in `output_dir/fragment_cache` (0 = evicted entries are dropped).
+    /// A spill directory left by an interrupted run is removed
+    pub fn new(memory_budget: usize, spill_budget: u64, output_dir: &Path) -> Self {
+        let spill_dir = output_dir.join(FRAGMENT_CACHE_DIR);
+        let _ = fs::remove_dir_all(&spill_dir);
+        Self { memory_budget, spill_budget, spill_dir, state: Mutex::new(CacheState::default()) }
+    }
+
+    /// Keep the bytes the scan read at `offset`
+    pub fn insert(&self, offset: u64, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let key: Key = Sha256::digest(data).into();
+        let mut state = self.state.lock().unwrap();
+        state.index.insert((offset, data.len()), key);
+        if state.entries.contains_key(&key) {
+            state.stats.deduplicated += 1;
+            state.touch(&key);
+            return;
+        }
+        let tick = state.next_tick();
+        state.entries.insert(key, Entry { len: data.len(), slot: Slot::Memory(Arc::from(data)), tick });
+        state.memory_lru.insert(tick, key);
+        state.memory_bytes += data.len();
+        state.stats.stored += 1;
+        self.evict(&mut state);
+    }
+
+    /// Bytes `[offset, offset + len)` if they lie in one stored fragment
+    pub fn get(&self, offset: u64, len: usize) -> Option<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        let bytes = self.lookup(&mut state, offset, len);
+        match bytes {
+            Some(_) => state.stats.hits += 1,
+            None => state.stats.misses += 1,
+        }
+        bytes
+    }
+
+    fn lookup(&self, state: &mut CacheState, offset: u64, len: usize) -> Option<Vec<u8>> {
+        // The fragment starting closest before `offset` (the longest one at that start)
+        let (&(start, stored_len), &key) = state.index.range(..=(offset, usize::MAX)).next_back()?;
+        let skip = (offset - start) as usize;
+        if skip.checked_add(len)? > stored_len {
+            return None;
+        }
+        let bytes = match &state.entries.get(&key)?.slot {
+            Slot::Memory(data) => data[skip..skip + len].to_vec(),
+            Slot::Spilled => {
+                let mut file = File::open(self.spill_path(&key)).ok()?;
+                file.seek(SeekFrom::Start(skip as u64)).ok()?;
+                let mut bytes = vec![0u8; len];
+                file.read_exact(&mut bytes).ok()?;
+                bytes
+            }
+        };
+        state.touch(&key);
+        Some(bytes)
+    }
+
+    /// Move least recently used entries to disk until memory is within its
+    /// budget, then drop the oldest spilled ones over the spill budget
+    fn evict(&self, state: &mut CacheState) {
+        while state.memory_bytes > self.memory_budget {
+            let Some((_, key)) = state.memory_lru.pop_first() else {
+                break;
+            };
+            let Some(entry) = state.entries.get_mut(&key) else {
+                continue;
+            };
+            let Slot::Memory(data) = std::mem::replace(&mut entry.slot, Slot::Spilled) else {
+                continue;
+            };
+            state.memory_bytes -= entry.len;
+            let written = (entry.len as u64 <= self.spill_budget)
+                .then(|| fs::create_dir_all(&self.spill_dir).and_then(|_| fs::write(self.spill_path(&key), &data)));
+            match written {
+                Some(Ok(())) => {
+                    state.spill_lru.insert(entry.tick, key);
+                    state.spill_bytes += entry.len as u64;
+                    state.stats.spilled_bytes += entry.len as u64;
+                }
+                Some(Err(e)) => {
+                    warn!("Fragment cache: cannot spill to {}: {}", self.spill_dir.display(), e);
+                    state.entries.remove(&key);
+                    state.stats.dropped += 1;
+                }
+                None => {
+                    state.entries.remove(&key);
+                    state.stats.dropped += 1;
+                }
+            }
+        }
+        while state.spill_bytes > self.spill_budget {
+            let Some((_, key)) = state.spill_lru.pop_first() else {
+                break;
+            };
+            if let Some(entry) = state.entries.remove(&key) {
+                state.spill_bytes -= entry.len as u64;
+                state.stats.dropped += 1;
+                let _ = fs::remove_file(self.spill_path(&key));
+            }
+        }
+    }
+
+    fn spill_path(&self, key: &Key) -> PathBuf {
+        self.spill_dir.join(key.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+    }
+
+    /// Memory and spill budgets in bytes
+    pub fn budget(&self) -> (usize, u64) {
+        (self.memory_budget, self.spill_budget)
+    }
+
+    pub fn stats(&self) -> FragmentCacheStats {
+        self.state.lock().unwrap().stats
+    }
+
+    /// Bytes held in memory and in the spill directory
+    pub fn usage(&self) -> (usize, u64) {
+        let state = self.state.lock().unwrap();
+        (state.memory_bytes, state.spill_bytes)
+    }
+
+    /// Forget every entry and remove the spill directory (the counters are kept)
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        let stats = state.stats;
+        *state = CacheState { stats, ..Default::default() };
+        let _ = fs::remove_dir_all(&self.spill_dir);
+    }
+}
+
+impl Drop for FragmentCache {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.spill_dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fragment_cache_spills_and_dedups() {
+        let dir = std::env::temp_dir().join(format!("rust_recovery_fragment_cache_{}", std::process::id()));
+        let cache = FragmentCache::new(100, 150, &dir);
+        let a = vec![b'a'; 60];
+        let b = vec![b'b'; 60];
+        let c = vec![b'c'; 60];
+
+        cache.insert(0, &a);
+        cache.insert(4096, &a);
+        assert_eq!(cache.usage(), (60, 0));
+        // `a` (least recently used) goes to disk
+        cache.insert(8192, &b);
+        assert_eq!(cache.usage(), (60, 60));
+        assert!(dir.join(FRAGMENT_CACHE_DIR).is_dir());
+        assert_eq!(cache.get(4096 + 10, 20), Some(a[10..30].to_vec()));
+        assert_eq!(cache.get(8192, 60), Some(b.clone()));
+        // Past the end of the stored fragment, or not stored at all
+        assert_eq!(cache.get(8192 + 50, 20), None);
+        assert_eq!(cache.get(100_000, 4), None);
+
+        // `a` and `b` spilled: over the spill budget, `a` is dropped
+        cache.insert(12288, &c);
+        cache.insert(16384, &[b'd'; 60]);
+        assert_eq!(cache.get(0, 60), None);
+        assert_eq!(cache.get(8192, 60), Some(b));
+        assert_eq!(cache.get(12288, 1), Some(vec![b'c']));
+
+        let stats = cache.stats();
+        assert_eq!((stats.stored, stats.deduplicated, stats.hits, stats.misses), (4, 1, 4, 3));
+        assert_eq!((stats.spilled_bytes, stats.dropped), (180, 1));
+        cache.clear();
+        assert!(!dir.join(FRAGMENT_CACHE_DIR).exists());
+        assert_eq!(cache.get(12288, 1), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,92 @@
+//! Chunk order from exFAT directory hints (`--enable-exfat`)
+//!
+//! Before the full pass the exFAT directory tree is walked for deleted
+//! files. Their first clusters are where the data the scan is after most
+//! likely still lies, so the chunks holding deleted files of a target type
+//! are scanned first, the most recently modified file first (exFAT keeps no
+//! deletion time). `--early-exit` then stops within minutes instead of hours
+//! into the image. The other chunks keep their order, `--priority` included,
+//! and the resume point still only advances over the contiguous run of
+//! completed chunks.
+
+use crate::exfat::{self, ExFatBootParams};
+use crate::scanner::ChunkInfo;
+
+/// Deleted file named by the directory tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryHint {
+    pub filename: String,
+    /// Image range `[start, end)` the file occupied if it was contiguous
+    pub start: u64,
+    pub end: u64,
+    /// Raw exFAT LastModifiedTimestamp
+    pub modified: u32,
+}
+
+/// Lower-case extension of `filename` (`jpeg` as `jpg`, the carver's name)
+fn extension(filename: &str) -> Option<String> {
+    let (_, extension) = filename.rsplit_once('.')?;
+    let extension = extension.to_ascii_lowercase();
+    Some(if extension == "jpeg" { "jpg".to_string() } else { extension })
+}
+
+/// Deleted files in `data` whose extension `is_target` accepts, most
+/// recently modified first. Entries failing `mark_suspicious_entries` are
+/// skipped, among them deleted files whose clusters a live file now holds.
+pub fn directory_hints(data: &[u8], params: &ExFatBootParams, is_target: impl Fn(&str) -> bool) -> Vec<DirectoryHint> {
+    let (mut entries, _) = exfat::walk_directory_tree(data, params);
+    entries.retain(|e| !e.is_directory && e.size > 0);
+    exfat::mark_suspicious_entries(data, &mut entries, params);
+
+    let mut hints: Vec<DirectoryHint> = entries
+        .into_iter()
+        .filter(|e| e.is_deleted && !e.is_suspicious() && extension(&e.filename).is_some_and(|ext| is_target(&ext)))
+        .filter_map(|e| {
+            let start = exfat::cluster_to_offset(params, e.first_cluster)?;
+            let end = start.saturating_add(e.size).min(data.len() as u64);
+            (start < end).then_some(DirectoryHint { filename: e.filename, start, end, modified: e.modified })
+        })
+        .collect();
+    hints.sort_by_key(|hint| std::cmp::Reverse(hint.modified));
+    hints
+}
+
+/// `chunks` touching `ranges` first, by the first range they touch; the
+/// others follow in their order
+pub fn hint_order(chunks: &[ChunkInfo], ranges: &[(u64, u64)]) -> Vec<ChunkInfo> {
+    let mut by_offset: Vec<usize> = (0..chunks.len()).collect();
+    by_offset.sort_by_key(|&i| chunks[i].offset);
+    let longest = chunks.iter().map(|c| c.size as u64).max().unwrap_or(0);
+
+    let mut rank = vec![usize::MAX; chunks.len()];
+    for (index, &(start, end)) in ranges.iter().enumerate() {
+        // Only chunks starting within `longest` before the range can reach it
+        let first = by_offset.partition_point(|&i| chunks[i].offset + longest <= start);
+        for &i in by_offset[first..].iter().take_while(|&&i| chunks[i].offset < end) {
+            if chunks[i].offset + chunks[i].size as u64 > start {
+                rank[i] = rank[i].min(index);
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..chunks.len()).collect();
+    order.sort_by_key(|&i| rank[i]);
+    order.into_iter().map(|i| chunks[i].clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint_order() {
+        let chunks: Vec<ChunkInfo> = (0..6u64).map(|i| ChunkInfo { offset: i * 100, size: 120 }).collect();
+        // Ranges in hint order; (110, 130) lies in chunk 1 and in the overlap of chunk 0
+        let order: Vec<u64> = hint_order(&chunks, &[(450, 460), (110, 130)]).iter().map(|c| c.offset).collect();
+        assert_eq!(order, [400, 0, 100, 200, 300, 500]);
+
+        assert_eq!(hint_order(&chunks, &[]).len(), 6);
+        assert_eq!(extension("Clip.JPEG").as_deref(), Some("jpg"));
+        assert_eq!(extension("notes"), None);
+    }
+}
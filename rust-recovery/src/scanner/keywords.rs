@@ -0,0 +1,173 @@
+//! Keyword focus (`--keyword`, `--keyword-file`)
+//!
+//! Keywords are matched on the raw bytes, case-insensitively with Unicode
+//! case folding ("рецепт" finds "РЕЦЕПТ"); е and ё count as one letter and
+//! any whitespace run in a phrase matches any other. A link matches when its
+//! title or the bytes around it hold a keyword, a hot fragment when its chunk
+//! does. Matches are boosted; with `--keyword-only` everything else is
+//! dropped. Carved files are picked by signature and kept either way.
+
+use crate::error::{RecoveryError, Result};
+use crate::types::{EnrichedLink, FragmentScore};
+use regex::bytes::{RegexSet, RegexSetBuilder};
+use std::path::Path;
+
+/// Bytes on each side of a link searched for keywords
+pub const LINK_CONTEXT_WINDOW: usize = 1024;
+/// Confidence added to a link per keyword found (capped at 1.0)
+const LINK_BOOST: f32 = 0.1;
+/// Score added to a fragment per keyword found
+const FRAGMENT_BOOST: f32 = 15.0;
+
+/// Compiled keyword list
+#[derive(Debug)]
+pub struct KeywordFilter {
+    keywords: Vec<String>,
+    set: RegexSet,
+    /// Drop links and fragments without a keyword instead of only boosting matches
+    restrict: bool,
+}
+
+/// Case-insensitive pattern of one keyword
+fn keyword_pattern(keyword: &str) -> String {
+    let words: Vec<String> = keyword
+        .split_whitespace()
+        .map(|word| {
+            regex::escape(word)
+                .chars()
+                .map(|c| match c {
+                    'е' | 'ё' | 'Е' | 'Ё' => "[её]".to_string(),
+                    c => c.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    format!("(?i){}", words.join(r"\s+"))
+}
+
+/// Keywords of a file: one per line, blank lines and `#` comments skipped
+pub fn load_keyword_file(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+impl KeywordFilter {
+    /// None when no keyword is left after trimming; repeats (in any case) are dropped
+    pub fn new(keywords: &[String], restrict: bool) -> Result<Option<Self>> {
+        let mut list: Vec<String> = Vec::new();
+        for keyword in keywords.iter().map(|k| k.trim()).filter(|k| !k.is_empty()) {
+            if !list.iter().any(|k| k.to_lowercase() == keyword.to_lowercase()) {
+                list.push(keyword.to_string());
+            }
+        }
+        if list.is_empty() {
+            return Ok(None);
+        }
+        let set = RegexSetBuilder::new(list.iter().map(|k| keyword_pattern(k)))
+            .size_limit(64 * 1024 * 1024)
+            .build()
+            .map_err(|e| RecoveryError::InvalidArgument(format!("Keyword list: {}", e)))?;
+        Ok(Some(Self { keywords: list, set, restrict }))
+    }
+
+    pub fn keywords(&self) -> &[String] {
+        &self.keywords
+    }
+
+    pub fn restrict(&self) -> bool {
+        self.restrict
+    }
+
+    /// Distinct keywords found in any of `texts`, in list order
+    pub fn matches(&self, texts: &[&[u8]]) -> Vec<&str> {
+        let mut found = vec![false; self.keywords.len()];
+        for text in texts {
+            for index in self.set.matches(text).iter() {
+                found[index] = true;
+            }
+        }
+        self.keywords
+            .iter()
+            .zip(found)
+            .filter(|(_, found)| *found)
+            .map(|(keyword, _)| keyword.as_str())
+            .collect()
+    }
+
+    /// Boost links of a chunk whose title or surroundings hold a keyword;
+    /// with `restrict`, drop the others
+    pub fn apply_to_links(&self, chunk: &[u8], chunk_offset: u64, links: &mut Vec<EnrichedLink>) {
+        links.retain_mut(|link| {
+            let pos = (link.offset.as_u64().saturating_sub(chunk_offset) as usize).min(chunk.len());
+            let context = &chunk[pos.saturating_sub(LINK_CONTEXT_WINDOW)..(pos + LINK_CONTEXT_WINDOW).min(chunk.len())];
+            let title = link.title.as_deref().unwrap_or_default().as_bytes();
+            let found = self.matches(&[title, context]);
+            if found.is_empty() {
+                return !self.restrict;
+            }
+            let boosted = (link.confidence + LINK_BOOST * found.len() as f32).min(1.0);
+            link.reasons.push(format!("keyword {} ({:+.2})", found.join(", "), boosted - link.confidence));
+            link.confidence = boosted;
+            true
+        });
+    }
+
+    /// Boost the score of a chunk holding keywords; false when the chunk
+    /// has none and `restrict` drops it
+    pub fn apply_to_fragment(&self, chunk: &[u8], score: &mut FragmentScore) -> bool {
+        let found = self.matches(&[chunk]);
+        if found.is_empty() {
+            return !self.restrict;
+        }
+        score.overall_score += FRAGMENT_BOOST * found.len() as f32;
+        score.reasons.push(format!("keyword {}", found.join(", ")));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Offset;
+
+    #[test]
+    fn test_keyword_matching_and_focus() {
+        let keywords = ["Рецепт".to_string(), "  ".to_string(), "ёлка  и ель".to_string(), "рецепт".to_string()];
+        let filter = KeywordFilter::new(&keywords, false).unwrap().unwrap();
+        assert_eq!(filter.keywords(), &["Рецепт".to_string(), "ёлка  и ель".to_string()]);
+        assert_eq!(filter.matches(&["Лучший РЕЦЕПТ борща".as_bytes()]), vec!["Рецепт"]);
+        assert_eq!(filter.matches(&["Елка\nи ЁЛЬ".as_bytes(), b"none"]), vec!["ёлка  и ель"]);
+        assert!(filter.matches(&[b"\xD0\xA0\xFF recipe".as_slice()]).is_empty());
+        assert!(KeywordFilter::new(&[" ".to_string()], true).unwrap().is_none());
+
+        let mut chunk = vec![b' '; 8192];
+        let text = "рецепт пирога".as_bytes();
+        chunk[100..100 + text.len()].copy_from_slice(text);
+        let link = |offset: u64| {
+            EnrichedLink::new("youtu.be/dQw4w9WgXcQ".to_string(), "dQw4w9WgXcQ".to_string(), Offset::new(offset), "short".to_string(), 0.5)
+        };
+        let mut titled = link(1_000_000 + 6000);
+        titled.title = Some("Новогодняя ёлка и ель".to_string());
+        let mut links = vec![link(1_000_000 + 300), link(1_000_000 + 6000), titled];
+
+        filter.apply_to_links(&chunk, 1_000_000, &mut links);
+        let confidences: Vec<f32> = links.iter().map(|l| l.confidence).collect();
+        assert_eq!(confidences, vec![0.6, 0.5, 0.6]);
+        assert_eq!(links[0].reasons, vec!["keyword Рецепт (+0.10)".to_string()]);
+
+        let only = KeywordFilter::new(&keywords, true).unwrap().unwrap();
+        only.apply_to_links(&chunk, 1_000_000, &mut links);
+        assert_eq!(links.len(), 2);
+
+        let mut score = FragmentScore::default();
+        assert!(only.apply_to_fragment(&chunk, &mut score));
+        assert_eq!(score.overall_score, FRAGMENT_BOOST);
+        assert!(!only.apply_to_fragment(&[b' '; 64], &mut FragmentScore::default()));
+        assert!(filter.apply_to_fragment(&[b' '; 64], &mut FragmentScore::default()));
+    }
+}
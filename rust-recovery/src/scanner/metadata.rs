@@ -0,0 +1,263 @@
+//! Metadata-first pass of the dual-range scan
+//!
+//! On exFAT, NTFS and FAT32 volumes the directory tree (or the MFT) is a small
+//! part of the image and already names every file with its size and location.
+//! On a partitioned image the volume is the one in the selected partition
+//! (`--partition`), or the first partition whose filesystem is supported.
+//! Reading it before the full-surface carve lists the files recoverable by
+//! metadata within seconds instead of at the end of a multi-hour scan.
+//!
+//! With `--reconstruct-deleted`, deleted fragmented exFAT files are rebuilt
+//! from consecutive clusters (`exfat::reconstruct_deleted_file`) and listed
+//! with the guessed extent and its confidence; the saved file gets the
+//! modification and access times of its directory entry.
+
+use serde::Serialize;
+
+use crate::fragment_linker::FragmentLinker;
+use crate::partition::{Filesystem, Partition};
+use crate::types::FileTimestamps;
+use crate::{exfat, fat32, ntfs};
+
+/// Subdirectory of the output directory for reconstructed deleted files
+pub const RECONSTRUCTED_DIR: &str = "reconstructed";
+
+/// Filesystem whose metadata the pass read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataSource {
+    ExFat,
+    Ntfs,
+    Fat32,
+}
+
+impl MetadataSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ExFat => "exFAT",
+            Self::Ntfs => "NTFS",
+            Self::Fat32 => "FAT32",
+        }
+    }
+}
+
+/// File named by filesystem metadata with a known data location
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetadataFile {
+    pub filename: String,
+    pub size: u64,
+    /// Image offset of the directory entry / FILE record
+    pub record_offset: u64,
+    /// Image offset of the first data byte (`None` for NTFS resident data)
+    pub data_offset: Option<u64>,
+    pub is_deleted: bool,
+    /// Extent guessed for a deleted FAT-chained exFAT file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconstruction: Option<exfat::Reconstruction>,
+    /// Where the reconstructed file was saved, relative to the output directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved_as: Option<String>,
+    /// Times of the exFAT directory entry, given to the saved file
+    #[serde(skip_serializing_if = "FileTimestamps::is_empty")]
+    pub times: FileTimestamps,
+}
+
+impl MetadataFile {
+    /// Image range `[start, end)` of a reconstructed file
+    pub fn reconstructed_range(&self) -> Option<(u64, u64)> {
+        let start = self.data_offset?;
+        Some((start, start.saturating_add(self.reconstruction.as_ref()?.bytes)))
+    }
+}
+
+/// Result of the metadata pass
+#[derive(Debug, Clone, Serialize)]
+pub struct MetadataPass {
+    pub source: MetadataSource,
+    /// Image ranges `[start, end)` holding the metadata that was read
+    pub regions: Vec<(u64, u64)>,
+    pub files: Vec<MetadataFile>,
+}
+
+impl MetadataPass {
+    /// Bytes of metadata read
+    pub fn metadata_bytes(&self) -> u64 {
+        self.regions.iter().map(|(start, end)| end - start).sum()
+    }
+
+    /// Total size of the listed files
+    pub fn file_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+/// Read the directory tree (exFAT, FAT32) or MFT (NTFS) of the volume in `data`.
+///
+/// Returns `None` when no supported boot sector is found. Directories,
+/// empty files, compressed NTFS data and exFAT entries failing
+/// `mark_suspicious_entries` are not listed. `reconstruct_deleted` rebuilds
+/// deleted FAT-chained exFAT files.
+pub fn scan_metadata(data: &[u8], reconstruct_deleted: bool) -> Option<MetadataPass> {
+    if let Some(params) = ntfs::find_boot_sector(data) {
+        return Some(scan_ntfs(data, &params));
+    }
+    if let Some(params) = exfat::find_boot_sector(data) {
+        return Some(scan_exfat(data, &params, reconstruct_deleted));
+    }
+    fat32::find_boot_sector(data).map(|params| scan_fat32(data, &params))
+}
+
+/// `scan_metadata` for the volume of `partition`, by its detected filesystem
+pub fn scan_partition(data: &[u8], partition: &Partition, reconstruct_deleted: bool) -> Option<MetadataPass> {
+    match partition.filesystem? {
+        Filesystem::Ntfs => ntfs::parse_boot_sector_at(data, partition.start).map(|params| scan_ntfs(data, &params)),
+        Filesystem::ExFat => exfat::parse_boot_sector_at(data, partition.start)
+            .map(|params| scan_exfat(data, &params, reconstruct_deleted)),
+        Filesystem::Fat32 => fat32::parse_boot_sector_at(data, partition.start).map(|params| scan_fat32(data, &params)),
+    }
+}
+
+fn scan_exfat(data: &[u8], params: &exfat::ExFatBootParams, reconstruct_deleted: bool) -> MetadataPass {
+    let (mut entries, regions) = exfat::walk_directory_tree(data, params);
+    exfat::populate_data_offsets(&mut entries, params);
+    if reconstruct_deleted {
+        exfat::reconstruct_deleted_entries(data, &mut entries, params, &FragmentLinker::default());
+    }
+    exfat::mark_suspicious_entries(data, &mut entries, params);
+
+    let files = entries
+        .into_iter()
+        .filter(|e| !e.is_directory && e.size > 0 && !e.is_suspicious())
+        .filter_map(|e| {
+            Some(MetadataFile {
+                data_offset: Some(e.data_offset?),
+                filename: e.filename,
+                size: e.size,
+                record_offset: e.offset,
+                is_deleted: e.is_deleted,
+                reconstruction: e.reconstruction,
+                saved_as: None,
+                times: e.times,
+            })
+        })
+        .collect();
+
+    MetadataPass { source: MetadataSource::ExFat, regions, files }
+}
+
+fn scan_fat32(data: &[u8], params: &fat32::Fat32BootParams) -> MetadataPass {
+    let (mut entries, regions) = fat32::walk_directory_tree(data, params);
+    fat32::populate_data_offsets(&mut entries, params);
+
+    let files = entries
+        .into_iter()
+        .filter(|e| !e.is_directory && e.size > 0)
+        .filter_map(|e| {
+            Some(MetadataFile {
+                data_offset: Some(e.data_offset?),
+                filename: e.filename,
+                size: e.size,
+                record_offset: e.offset,
+                is_deleted: e.is_deleted,
+                reconstruction: None,
+                saved_as: None,
+                times: FileTimestamps::default(),
+            })
+        })
+        .collect();
+
+    MetadataPass { source: MetadataSource::Fat32, regions, files }
+}
+
+fn scan_ntfs(data: &[u8], params: &ntfs::NtfsBootParams) -> MetadataPass {
+    let mut entries = ntfs::read_mft(data, params);
+    ntfs::populate_data_offsets(&mut entries, params);
+
+    let record_size = params.record_size as u64;
+    let mut offsets: Vec<u64> = entries.iter().map(|e| e.offset).collect();
+    offsets.sort_unstable();
+    let mut regions: Vec<(u64, u64)> = Vec::new();
+    for offset in offsets {
+        let end = offset + record_size;
+        match regions.last_mut() {
+            Some(region) if region.1 >= offset => region.1 = region.1.max(end),
+            _ => regions.push((offset, end)),
+        }
+    }
+
+    let files = entries
+        .into_iter()
+        .filter(|e| !e.is_directory && e.size > 0 && !e.is_compressed)
+        .filter(|e| e.resident_data.is_some() || e.data_offset.is_some())
+        .map(|e| MetadataFile {
+            filename: e.filename,
+            size: e.size,
+            record_offset: e.offset,
+            data_offset: e.data_offset,
+            is_deleted: e.is_deleted,
+            reconstruction: None,
+            saved_as: None,
+            times: FileTimestamps::default(),
+        })
+        .collect();
+
+    MetadataPass { source: MetadataSource::Ntfs, regions, files }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exfat::testing::entry_set;
+
+    #[test]
+    fn test_scan_metadata_requires_volume() {
+        assert!(scan_metadata(&vec![0u8; 64 * 1024], false).is_none());
+
+        let pass = MetadataPass {
+            source: MetadataSource::ExFat,
+            regions: vec![(1024, 1536), (4096, 8192)],
+            files: Vec::new(),
+        };
+        assert_eq!(pass.metadata_bytes(), 512 + 4096);
+        assert_eq!(serde_json::to_value(&pass).unwrap()["source"], "exfat");
+    }
+
+    #[test]
+    fn test_live_file_on_deleted_clusters_is_kept() {
+        // 512-byte sectors and clusters, FAT in sector 1, 16 clusters from 1024, root in cluster 2
+        let mut image = vec![0u8; 1024 + 16 * 512];
+        image[3..11].copy_from_slice(b"EXFAT   ");
+        image[80] = 1;
+        image[84] = 1;
+        image[88] = 2;
+        image[92] = 16;
+        image[96] = 2;
+        image[108] = 9;
+        image[512 + 8..512 + 12].copy_from_slice(&u32::MAX.to_le_bytes());
+        // "new.txt" reuses clusters 5-6 of the deleted "old.txt"; "gone.txt" is intact
+        // "new.txt": created 2023-12-31 21:41:05.37 at UTC+3, modified 2024-01-01 00:00:01.5 at UTC-2
+        let mut new = entry_set("new.txt", false, false, 5, 600);
+        let created = (43u32 << 25) | (12 << 21) | (31 << 16) | (21 << 11) | (41 << 5) | 2;
+        new[8..12].copy_from_slice(&created.to_le_bytes());
+        new[12..16].copy_from_slice(&((44u32 << 25) | (1 << 21) | (1 << 16)).to_le_bytes());
+        new[20..24].copy_from_slice(&[137, 150, 0x80 | 12, 0x80 | (128 - 8)]);
+        let root = [entry_set("old.txt", true, false, 5, 2000), new, entry_set("gone.txt", true, false, 10, 100)];
+        let root = root.concat();
+        image[1024..1024 + root.len()].copy_from_slice(&root);
+
+        let pass = scan_metadata(&image, false).unwrap();
+        let listed: Vec<(&str, bool)> = pass.files.iter().map(|f| (f.filename.as_str(), f.is_deleted)).collect();
+        assert_eq!(listed, [("new.txt", false), ("gone.txt", true)]);
+        let json = serde_json::to_value(&pass).unwrap();
+        assert_eq!(
+            json["files"][0]["times"],
+            serde_json::json!({"created": "2023-12-31T21:41:05.370+03:00", "modified": "2024-01-01T00:00:01.500-02:00"})
+        );
+        assert!(json["files"][1].get("times").is_none());
+
+        let params = exfat::find_boot_sector(&image).unwrap();
+        let hints = crate::scanner::hints::directory_hints(&image, &params, |ext| ext == "txt");
+        let hinted: Vec<&str> = hints.iter().map(|hint| hint.filename.as_str()).collect();
+        assert_eq!(hinted, ["gone.txt"]);
+    }
+}
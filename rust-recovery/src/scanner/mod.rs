@@ -1,3 +1,34 @@
+pub mod adaptive;
+pub mod artifacts;
+pub mod control;
+pub mod detectors;
+pub mod digest;
+pub mod estimate;
+pub mod fragment_cache;
+pub mod hints;
+pub mod keywords;
+pub mod metadata;
+pub mod overlap;
 pub mod parallel;
+pub mod prefetch;
+pub mod priority;
+pub mod relevance;
+pub mod throttle;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+pub mod watchdog;
 
 pub use parallel::{ParallelScanner, ChunkInfo};
+pub use artifacts::ARTIFACT_NAMES;
+pub use control::ScanControl;
+pub use detectors::{DetectorRegistry, StructureDetector, DETECTOR_NAMES};
+pub use digest::{ImageDigest, SourceDigest};
+pub use estimate::{estimate, Estimate, EstimateConfig, Interval, Recommendation};
+pub use fragment_cache::{FragmentCache, FragmentCacheStats};
+pub use keywords::KeywordFilter;
+pub use metadata::{scan_metadata, scan_partition, MetadataFile, MetadataPass, MetadataSource, RECONSTRUCTED_DIR};
+pub use overlap::{plan_overlap, OverlapPlan};
+pub use prefetch::PrefetchConfig;
+pub use relevance::{KeywordProfile, KeywordRelevance};
+pub use throttle::{IoPriority, ReadThrottle, IO_PRIORITY_NAMES};
+pub use watchdog::Watchdog;
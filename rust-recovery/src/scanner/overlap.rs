@@ -0,0 +1,73 @@
+//! Chunk overlap sizing
+//!
+//! Adjacent chunks overlap so that an artifact crossing a chunk boundary is
+//! seen whole by the earlier chunk. The overlap therefore has to cover the
+//! longest span a single result depends on, which the matcher reports from
+//! its pattern set and context windows.
+
+use crate::matcher::{ArtifactSpan, EnhancedMatcher};
+
+/// Automatic overlap is rounded up to whole pages
+const OVERLAP_ALIGNMENT: usize = 4096;
+
+/// Overlap chosen for a scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlapPlan {
+    /// Overlap to use, in bytes
+    pub overlap: usize,
+    /// Largest artifact span the overlap must cover
+    pub required: ArtifactSpan,
+    /// Set when a user-chosen overlap is smaller than `required`
+    pub warning: Option<String>,
+}
+
+/// Minimum overlap for `matcher`, page-aligned
+pub fn required_overlap(matcher: &EnhancedMatcher) -> usize {
+    matcher.max_artifact_span().bytes.div_ceil(OVERLAP_ALIGNMENT) * OVERLAP_ALIGNMENT
+}
+
+/// Use `requested` as given (warning if too small), or size the overlap automatically
+pub fn plan_overlap(matcher: &EnhancedMatcher, requested: Option<usize>) -> OverlapPlan {
+    let required = matcher.max_artifact_span();
+    match requested {
+        Some(overlap) => OverlapPlan {
+            overlap,
+            required,
+            warning: (overlap < required.bytes).then(|| {
+                format!(
+                    "overlap of {} bytes is smaller than the {} span ({} bytes); \
+                     results crossing chunk boundaries may be lost",
+                    overlap, required.source, required.bytes
+                )
+            }),
+        },
+        None => OverlapPlan {
+            overlap: required_overlap(matcher),
+            required,
+            warning: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_overlap() {
+        let matcher = EnhancedMatcher::new();
+        let span = matcher.max_artifact_span();
+        assert_eq!(span.source, "title_context");
+
+        let auto = plan_overlap(&matcher, None);
+        assert!(auto.overlap >= span.bytes);
+        assert_eq!(auto.overlap % OVERLAP_ALIGNMENT, 0);
+        assert!(auto.warning.is_none());
+
+        let small = plan_overlap(&matcher, Some(512));
+        assert_eq!(small.overlap, 512);
+        assert!(small.warning.unwrap().contains("title_context"));
+
+        assert!(plan_overlap(&matcher, Some(64 * 1024)).warning.is_none());
+    }
+}
@@ -1,17 +1,36 @@
+use crate::carver;
+use crate::diagnostics::DiagnosticCause;
 use crate::disk::DiskImage;
+use crate::pipe::PipeImage;
+use crate::entropy::{calculate_shannon_entropy, entropy_category, is_compressed_like};
+use crate::scanner::control::ScanControl;
+use crate::scanner::detectors::{DetectorRegistry, BLOCK_SIZE};
+use crate::scanner::throttle::{self, ReadThrottle};
+use crate::scanner::prefetch::{self, PrefetchConfig, PrefetchStats};
+use crate::scanner::{adaptive, hints, priority};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::scanner::uring::{ChunkBuffer, UringReader};
+use crate::scanner::watchdog::Watchdog;
 use crate::error::Result;
 use crate::numa::{NumaTopology, pin_thread_to_cpu};
 use crate::types_aligned::{HotFragmentAligned, ScanStatsAligned};
-use crate::simd_block_scanner_asm::{scan_block_avx2_asm, AlignedBlock};
 use crate::types::{
-    EnrichedLink, HotFragment, ScanConfig, ScanProgress, ScanResult, Offset,
+    ChunkStats, EnrichedLink, HotFragment, ScanConfig, ScanIncident, ScanProgress, ScanResult, Offset, Size,
+    StructureHit,
 };
-use crate::matcher::{EnhancedMatcher, calculate_fragment_score};
+use crate::matcher::{EnhancedMatcher, ScriptDensity, calculate_fragment_score};
+use crate::simd_search::scan_block64_simd;
+use recovery_core::overlap::{suppress_duplicates, OverlapWindows};
 use rayon::prelude::*;
-use std::collections::HashMap;
-use std::time::Instant;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use memmap2::Mmap;
+#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
 use tokio::sync::mpsc::Sender;
+use tracing::warn;
 
 /// Information about a chunk to be scanned
 #[derive(Debug, Clone)]
@@ -20,11 +39,185 @@ pub struct ChunkInfo {
     pub size: usize,
 }
 
+/// Requeued chunks are rescanned in windows of chunk_size / REQUEUE_SPLIT
+const REQUEUE_SPLIT: usize = 16;
+const MIN_REQUEUE_WINDOW: usize = 64 * 1024;
+/// Bytes between cancel-flag checks in the block loop (multiple of 64)
+const CANCEL_CHECK_INTERVAL: usize = 1024 * 1024;
+/// Leading bytes of a non-promoted chunk sampled for the high-entropy diagnostic
+const ENTROPY_SAMPLE_SIZE: usize = 64 * 1024;
+/// Score of a carved file: its structure was walked from header to end
+const CARVED_FILE_SCORE: f32 = 90.0;
+/// Streaming mode: chunks per worker thread in one window
+const STREAM_WINDOW_CHUNKS_PER_THREAD: usize = 4;
+
+thread_local! {
+    /// pread buffer of the worker thread, reused from chunk to chunk
+    static READ_BUFFER: std::cell::RefCell<Vec<u8>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Statistics of a scanned chunk (whole chunk, overlap included)
+fn measure_chunk(
+    chunk_info: &ChunkInfo,
+    chunk_data: &[u8],
+    links: &[EnrichedLink],
+    hot: bool,
+    started: Instant,
+) -> ChunkStats {
+    let entropy = calculate_shannon_entropy(chunk_data);
+    ChunkStats {
+        offset: Offset::new(chunk_info.offset),
+        size: Size::from_usize(chunk_info.size),
+        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+        links: links.len(),
+        json_markers: chunk_data.iter().filter(|&&b| matches!(b, b'{' | b'}' | b'[' | b']')).count(),
+        entropy,
+        category: entropy_category(entropy),
+        hot,
+    }
+}
+
+/// Resume point of a scan: chunks are completed out of order, so it only
+/// advances over the contiguous run of completed chunks in scan direction
+struct ResumeWatermark {
+    /// Chunk offsets in scan direction
+    offsets: Vec<u64>,
+    done: Vec<bool>,
+    /// Completed prefix length
+    next: usize,
+    range_end: u64,
+    reverse: bool,
+}
+
+impl ResumeWatermark {
+    fn new(chunks: &[ChunkInfo], range_end: u64, reverse: bool) -> Self {
+        let mut offsets: Vec<u64> = chunks.iter().map(|c| c.offset).collect();
+        offsets.sort_unstable();
+        if reverse {
+            offsets.reverse();
+        }
+        Self { done: vec![false; offsets.len()], offsets, next: 0, range_end, reverse }
+    }
+
+    /// Forward: start of the first unfinished chunk. Reverse: start of the
+    /// lowest chunk of the finished top run.
+    fn position(&self) -> u64 {
+        match (self.reverse, self.next) {
+            (false, next) => self.offsets.get(next).copied().unwrap_or(self.range_end),
+            (true, 0) => self.range_end,
+            (true, next) => self.offsets[next - 1],
+        }
+    }
+
+    /// Mark a chunk done; returns the new position if it advanced
+    fn complete(&mut self, offset: u64) -> Option<Offset> {
+        let index = self.offsets.iter().position(|&o| o == offset)?;
+        self.done[index] = true;
+
+        let before = self.next;
+        while self.done.get(self.next) == Some(&true) {
+            self.next += 1;
+        }
+        (self.next > before).then(|| Offset::new(self.position()))
+    }
+}
+
+/// Result of scanning one chunk under the watchdog
+enum ChunkOutcome {
+    Done(Vec<EnrichedLink>),
+    /// Abandoned after the watchdog fired (time spent on the chunk)
+    Stalled(Duration),
+    /// Abandoned on operator request (`ScanControl::skip`), not requeued
+    Skipped,
+    /// Not scanned: the scan was cancelled (`ScanControl::cancel`)
+    Cancelled,
+}
+
+/// Split `len` bytes from `start_offset` into chunks of `chunk_size` + `overlap`
+fn split_range(start_offset: u64, len: usize, chunk_size: usize, overlap: usize) -> Vec<ChunkInfo> {
+    let mut chunks = Vec::new();
+    let mut offset = 0usize;
+
+    while offset < len {
+        let chunk_end = offset
+            .saturating_add(chunk_size)
+            .saturating_add(overlap)
+            .min(len);
+
+        if offset < chunk_end {
+            chunks.push(ChunkInfo {
+                offset: start_offset + offset as u64,
+                size: chunk_end - offset,
+            });
+        }
+
+        offset = offset.saturating_add(chunk_size);
+        if chunk_size == 0 {
+            break;
+        }
+    }
+
+    chunks
+}
+
+/// Chunk `index` of `split_range(start_offset, len, chunk_size, overlap)`,
+/// without building the list
+fn chunk_at(start_offset: u64, len: usize, chunk_size: usize, overlap: usize, index: usize) -> ChunkInfo {
+    let offset = index * chunk_size;
+    let end = offset.saturating_add(chunk_size).saturating_add(overlap).min(len);
+    ChunkInfo { offset: start_offset + offset as u64, size: end - offset }
+}
+
+/// Cut `chunks` down to the parts whose own bytes (before the overlap) fall
+/// in `ranges`; each part still reads `overlap` bytes past its range, within
+/// the chunk. `ranges` are sorted and disjoint.
+fn restrict_chunks(chunks: Vec<ChunkInfo>, ranges: &[(u64, u64)], chunk_size: usize, overlap: usize) -> Vec<ChunkInfo> {
+    let mut parts: Vec<ChunkInfo> = Vec::new();
+    for chunk in chunks {
+        let chunk_end = chunk.offset + chunk.size as u64;
+        let own_end = chunk.offset + chunk.size.min(chunk_size) as u64;
+        let first = ranges.partition_point(|&(_, end)| end <= chunk.offset);
+        let mut last_end = 0;
+        for &(start, end) in ranges[first..].iter().take_while(|&&(start, _)| start < own_end) {
+            let part_start = start.max(chunk.offset);
+            let part_end = end.saturating_add(overlap as u64).min(chunk_end);
+            match parts.last_mut() {
+                // Ranges closer than the overlap are read as one part
+                Some(part) if last_end > 0 && part_start <= last_end => {
+                    last_end = last_end.max(part_end);
+                    part.size = (last_end - part.offset) as usize;
+                }
+                _ => {
+                    parts.push(ChunkInfo { offset: part_start, size: (part_end - part_start) as usize });
+                    last_end = part_end;
+                }
+            }
+        }
+    }
+    parts
+}
+
+/// Bytes of `[start, end)` inside `ranges` (sorted and disjoint)
+fn covered_bytes(ranges: &[(u64, u64)], start: u64, end: u64) -> u64 {
+    let first = ranges.partition_point(|&(_, range_end)| range_end <= start);
+    ranges[first..]
+        .iter()
+        .take_while(|&&(range_start, _)| range_start < end)
+        .map(|&(range_start, range_end)| range_end.min(end) - range_start.max(start))
+        .sum()
+}
+
 /// Parallel file scanner with SIMD-accelerated pattern matching
 #[derive(Clone)]
 pub struct ParallelScanner {
     config: ScanConfig,
     enhanced_matcher: EnhancedMatcher,
+    /// Pause/skip state shared by all clones of the scanner
+    control: Arc<ScanControl>,
+    /// Structure detectors evaluated in the block pass (`ScanConfig::detectors`)
+    detectors: Arc<DetectorRegistry<BLOCK_SIZE>>,
+    /// Chunk dispatch limit (`ScanConfig::max_read_bytes_per_sec`), shared by all clones
+    throttle: Option<Arc<ReadThrottle>>,
 }
 
 /// Адаптивный prefetch на основе паттернов доступа
@@ -62,7 +255,10 @@ impl AdaptivePrefetcher {
     pub unsafe fn prefetch_next(&self, current_ptr: *const u8) {
         if self.confidence > 0.5 {
             let next_ptr = current_ptr.add(self.stride);
+            #[cfg(target_arch = "x86_64")]
             _mm_prefetch(next_ptr as *const i8, _MM_HINT_T0);
+            #[cfg(target_arch = "aarch64")]
+            std::arch::asm!("prfm pldl1keep, [{0}]", in(reg) next_ptr, options(nostack, preserves_flags, readonly));
         }
     }
 }
@@ -103,8 +299,11 @@ impl ParallelScanner {
         }
 
         let enhanced_matcher = EnhancedMatcher::new();
+        let detectors = Arc::new(DetectorRegistry::from_names(&config.detectors));
 
-        Self { config, enhanced_matcher }
+        let throttle = config.max_read_bytes_per_sec.map(|rate| Arc::new(ReadThrottle::new(rate)));
+
+        Self { config, enhanced_matcher, control: Arc::new(ScanControl::new()), detectors, throttle }
     }
 
     /// Public async scan method
@@ -113,8 +312,15 @@ impl ParallelScanner {
         let disk = disk.clone();
         
         tokio::task::spawn_blocking(move || {
-            let start_offset = Offset::new(0);
-            scanner.scan_streaming(&disk, start_offset, scanner.config.reverse, Some(sender))
+            let disk_size = disk.size().as_u64();
+            let reverse = scanner.config.reverse;
+            let (start, end) = match scanner.config.resume_from.map(|p| p.as_u64()) {
+                None => (0, disk_size),
+                Some(position) if !reverse => (position, disk_size),
+                // The chunk ending at the resume point still reads the overlap past it
+                Some(position) => (0, position.saturating_add(scanner.config.overlap_size as u64).min(disk_size)),
+            };
+            scanner.scan_range(&disk, Offset::new(start), Offset::new(end), reverse, Some(sender))
         })
         .await
         .map_err(|e| crate::error::RecoveryError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?
@@ -127,7 +333,16 @@ impl ParallelScanner {
                 .build_global();
         }
 
-        Self { config, enhanced_matcher: matcher }
+        let detectors = Arc::new(DetectorRegistry::from_names(&config.detectors));
+
+        let throttle = config.max_read_bytes_per_sec.map(|rate| Arc::new(ReadThrottle::new(rate)));
+
+        Self { config, enhanced_matcher: matcher, control: Arc::new(ScanControl::new()), detectors, throttle }
+    }
+
+    /// Pause/skip control of scans run by this scanner and its clones
+    pub fn control(&self) -> Arc<ScanControl> {
+        Arc::clone(&self.control)
     }
 
     /// Scan a disk image with progress updates via tokio channel
@@ -137,194 +352,850 @@ impl ParallelScanner {
         start: Offset,
         reverse: bool,
         sender: Option<Sender<ScanProgress>>,
+    ) -> Result<ScanResult> {
+        self.scan_range(disk, start, Offset::new(disk.size().as_u64()), reverse, sender)
+    }
+
+    /// Scan `[start, end)` of a disk image with progress updates via tokio channel
+    pub fn scan_range(
+        &self,
+        disk: &DiskImage,
+        start: Offset,
+        end: Offset,
+        reverse: bool,
+        sender: Option<Sender<ScanProgress>>,
     ) -> Result<ScanResult> {
         let start_time = Instant::now();
 
-        let disk_size = disk.size().as_u64();
+        let end_offset = end.as_u64().min(disk.size().as_u64());
         let start_offset = start.as_u64();
 
-        if disk_size == 0 || start_offset >= disk_size {
+        if start_offset >= end_offset {
             return Ok(ScanResult::default());
         }
 
-        let mmap = disk.get_mmap();
-        let data = &mmap[start_offset as usize..];
-
-        let numa_topology = NumaTopology::detect();
-        let mut chunks = Vec::new();
-        
-        if let Some(ref topo) = numa_topology {
-            // NUMA-aware distribution
-            let base_chunks = self.create_chunks(data, start_offset);
-            let distribution = topo.distribute_chunks(base_chunks.len());
-            
-            for (_node_id, chunk_ids) in distribution {
-                for id in chunk_ids {
-                    if let Some(chunk) = base_chunks.get(id) {
-                        chunks.push(chunk.clone());
+        let config = &self.config;
+        let scan_len = (end_offset - start_offset) as usize;
+        let stats = ScanStatsAligned::new();
+        let watchdog = config.stall_timeout.map(Watchdog::start);
+
+        // Chunks and requeue windows scanned, for overlap duplicate suppression
+        let mut spans = Vec::new();
+        let (mut links, incidents) = if let Some(pipe) = disk.pipe() {
+            self.scan_pipe(disk, pipe, start_offset, sender.as_ref(), watchdog.as_ref(), &stats, &mut spans)
+        } else if config.streaming {
+            self.scan_windows(disk, start_offset, scan_len, reverse, sender.as_ref(), watchdog.as_ref(), &stats, &mut spans)
+        } else {
+            // Mapped images are sliced in place; block devices have no mapping
+            // and every chunk is read into a buffer instead
+            let mmap = disk.get_mmap();
+
+            let numa_topology = NumaTopology::detect();
+            let mut chunks = Vec::new();
+
+            if let Some(ref topo) = numa_topology {
+                // NUMA-aware distribution
+                let base_chunks = self.create_chunks(scan_len, start_offset);
+                let distribution = topo.distribute_chunks(base_chunks.len());
+
+                for (_node_id, chunk_ids) in distribution {
+                    for id in chunk_ids {
+                        if let Some(chunk) = base_chunks.get(id) {
+                            chunks.push(chunk.clone());
+                        }
                     }
                 }
+            } else {
+                chunks = self.create_chunks(scan_len, start_offset);
             }
+
+            if reverse {
+                chunks.reverse();
+            }
+
+            self.scan_chunks(
+                disk,
+                mmap.as_deref(),
+                &chunks,
+                end_offset,
+                reverse,
+                sender.as_ref(),
+                watchdog.as_ref(),
+                &stats,
+                &mut spans,
+            )
+        };
+
+        // Links found twice because two chunks read them; repeats elsewhere stay
+        if config.deduplicate {
+            Self::suppress_overlap_duplicates(&mut links, &OverlapWindows::from_spans(spans));
+        }
+
+        if config.min_confidence > 0.0 {
+            links.retain(|l| l.confidence >= config.min_confidence);
+        }
+
+        links.sort_by_key(|l| l.offset);
+
+        // A pipe's end is only known once it has been read
+        let end_offset = if disk.pipe().is_some() { disk.size().as_u64() } else { end_offset };
+        let bytes_scanned = self.covered_bytes(start_offset, end_offset);
+        let duration = start_time.elapsed();
+
+        Ok(ScanResult {
+            links,
+            bytes_scanned,
+            duration_secs: duration.as_secs_f64(),
+            incidents,
+        })
+    }
+
+    /// Streaming mode (`ScanConfig::streaming`): scan `len` bytes from
+    /// `start_offset` one window of chunks at a time, without the mapping.
+    /// Chunks are read with pread into per-worker buffers, so memory depends
+    /// on the thread count and chunk size, not on the image size.
+    #[allow(clippy::too_many_arguments)]
+    fn scan_windows(
+        &self,
+        disk: &DiskImage,
+        start_offset: u64,
+        len: usize,
+        reverse: bool,
+        sender: Option<&Sender<ScanProgress>>,
+        watchdog: Option<&Watchdog>,
+        stats: &ScanStatsAligned,
+        spans: &mut Vec<(u64, u64)>,
+    ) -> (Vec<EnrichedLink>, Vec<ScanIncident>) {
+        let chunk_size = self.config.chunk_size.max(1);
+        let total = len.div_ceil(chunk_size);
+        let window = rayon::current_num_threads() * STREAM_WINDOW_CHUNKS_PER_THREAD;
+
+        let mut links = Vec::new();
+        let mut incidents = Vec::new();
+        let mut scanned = 0;
+        while scanned < total && !self.control.is_cancelled() {
+            let next = (scanned + window).min(total);
+            // Chunk indices of this window, in scan direction
+            let (first, last) = if reverse { (total - next, total - scanned) } else { (scanned, next) };
+            let mut chunks = self.restrict(
+                (first..last)
+                    .map(|i| chunk_at(start_offset, len, chunk_size, self.config.overlap_size, i))
+                    .collect(),
+            );
+            if reverse {
+                chunks.reverse();
+            }
+
+            // Resume points stop at the window edge (forward: start of the
+            // next window, reverse: end of this one)
+            let window_end = start_offset + (last * chunk_size).min(len) as u64;
+            let (window_links, window_incidents) =
+                self.scan_chunks(disk, None, &chunks, window_end, reverse, sender, watchdog, stats, spans);
+            links.extend(window_links);
+            incidents.extend(window_incidents);
+            scanned = next;
+        }
+
+        // Cancelled or stopped: the windows never built are reported in one piece
+        if scanned < total {
+            let (offset, bytes) = if reverse {
+                (start_offset, ((total - scanned) * chunk_size).min(len))
+            } else {
+                (start_offset + (scanned * chunk_size) as u64, len - scanned * chunk_size)
+            };
+            if let Some(s) = sender.filter(|s| !s.is_closed()) {
+                let _ = s.blocking_send(ScanProgress::Skipped(
+                    Offset::new(offset),
+                    self.covered_bytes(offset, offset + bytes as u64),
+                    self.control.cancel_cause(),
+                ));
+            }
+        }
+        (links, incidents)
+    }
+
+    /// Piped image (`DiskImage::open_pipe`): read forward from `start_offset`
+    /// one window of chunks at a time until the pipe ends. A window is
+    /// released once scanned; `scan_one_chunk` retained its fragments.
+    #[allow(clippy::too_many_arguments)]
+    fn scan_pipe(
+        &self,
+        disk: &DiskImage,
+        pipe: &PipeImage,
+        start_offset: u64,
+        sender: Option<&Sender<ScanProgress>>,
+        watchdog: Option<&Watchdog>,
+        stats: &ScanStatsAligned,
+        spans: &mut Vec<(u64, u64)>,
+    ) -> (Vec<EnrichedLink>, Vec<ScanIncident>) {
+        let chunk_size = self.config.chunk_size.max(1);
+        let overlap = self.config.overlap_size;
+        let window = (rayon::current_num_threads() * STREAM_WINDOW_CHUNKS_PER_THREAD * chunk_size) as u64;
+
+        let mut links = Vec::new();
+        let mut incidents = Vec::new();
+        let mut start = start_offset;
+        while !self.control.is_cancelled() {
+            // The last chunk of the window reads the overlap past it
+            let available = match pipe.fill(start + window + overlap as u64) {
+                Ok(available) => available,
+                Err(e) => {
+                    warn!(offset = start, "Pipe read failed, scan ends here: {}", e);
+                    break;
+                }
+            };
+            if available <= start {
+                break;
+            }
+            let window_end = (start + window).min(available);
+            let chunks = self.restrict(
+                split_range(start, (available - start) as usize, chunk_size, overlap)
+                    .into_iter()
+                    .take_while(|chunk| chunk.offset < window_end)
+                    .collect(),
+            );
+            let (window_links, window_incidents) =
+                self.scan_chunks(disk, None, &chunks, window_end, false, sender, watchdog, stats, spans);
+            links.extend(window_links);
+            incidents.extend(window_incidents);
+            pipe.release(window_end);
+            start = window_end;
+        }
+        (links, incidents)
+    }
+
+    /// Scan `chunks` in parallel with panic isolation and stall detection,
+    /// then requeue stalled ones. Resume points advance up to `range_end`;
+    /// the chunks and windows scanned are added to `spans`.
+    #[allow(clippy::too_many_arguments)]
+    fn scan_chunks(
+        &self,
+        disk: &DiskImage,
+        mmap: Option<&Mmap>,
+        chunks: &[ChunkInfo],
+        range_end: u64,
+        reverse: bool,
+        sender: Option<&Sender<ScanProgress>>,
+        watchdog: Option<&Watchdog>,
+        stats: &ScanStatsAligned,
+        spans: &mut Vec<(u64, u64)>,
+    ) -> (Vec<EnrichedLink>, Vec<ScanIncident>) {
+        let refined;
+        let chunks = match self.config.min_chunk_size {
+            Some(min) => {
+                refined = adaptive::refine(disk, mmap, chunks, min, self.config.chunk_size, self.config.overlap_size);
+                &refined[..]
+            }
+            None => chunks,
+        };
+        let prioritized;
+        let chunks = if self.config.prioritize {
+            prioritized = priority::prioritize(disk, mmap, chunks, self.enhanced_matcher.profile().finder.as_ref());
+            &prioritized[..]
         } else {
-            chunks = self.create_chunks(data, start_offset);
+            chunks
+        };
+        let hinted;
+        let chunks = match self.config.hint_ranges.as_deref() {
+            Some(ranges) => {
+                hinted = hints::hint_order(chunks, ranges);
+                &hinted[..]
+            }
+            None => chunks,
+        };
+        spans.extend(chunks.iter().map(|chunk| (chunk.offset, chunk.offset + chunk.size as u64)));
+        let watermark = Mutex::new(ResumeWatermark::new(chunks, range_end, reverse));
+        // The chunk's bytes are reported before the resume point it may advance
+        let completed = |chunk_info: &ChunkInfo| {
+            if let Some(s) = sender.filter(|s| !s.is_closed()) {
+                let offset = Offset::new(chunk_info.offset);
+                let _ = s.blocking_send(ScanProgress::ChunkCompleted(offset, chunk_info.size as u64));
+            }
+        };
+        let advance = |offset: u64| {
+            let position = watermark.lock().unwrap().complete(offset);
+            if let (Some(position), Some(s)) = (position, sender) {
+                if !s.is_closed() {
+                    let _ = s.blocking_send(ScanProgress::ResumePoint(position));
+                }
+            }
+        };
+
+        // Parallel scan with panic isolation, stall detection and stats tracking
+        let scan_chunk = |chunk_info: &ChunkInfo, preloaded: Option<&[u8]>| {
+            if self.control.is_cancelled() {
+                return self.chunk_cancelled(chunk_info, sender);
+            }
+            stats.add_chunk();
+
+            // Report progress
+            if let Some(s) = sender {
+                if !s.is_closed() {
+                    let _ = s.blocking_send(ScanProgress::BytesScanned(chunk_info.size as u64));
+                }
+            }
+
+            let outcome = self.scan_one_chunk(disk, mmap, chunk_info, preloaded, sender, watchdog, true);
+            if matches!(outcome, ChunkOutcome::Done(_)) {
+                completed(chunk_info);
+            }
+            // Skipped chunks are done as far as a resume is concerned
+            if matches!(outcome, ChunkOutcome::Done(_) | ChunkOutcome::Skipped) {
+                advance(chunk_info.offset);
+            }
+            outcome
+        };
+        let outcomes: Vec<ChunkOutcome> = match self.scan_queued(disk, chunks, &scan_chunk) {
+            Some(outcomes) => outcomes,
+            // par_iter splits the list between workers; par_bridge hands chunks out in
+            // priority order (or in image order, for the --verify-hash digest)
+            None if self.config.prioritize
+                || self.config.hint_ranges.is_some()
+                || self.config.image_digest.is_some() =>
+            {
+                let mut outcomes: Vec<(usize, ChunkOutcome)> = chunks
+                    .iter()
+                    .enumerate()
+                    .par_bridge()
+                    .map(|(index, chunk_info)| (index, scan_chunk(chunk_info, None)))
+                    .collect();
+                outcomes.sort_unstable_by_key(|(index, _)| *index);
+                outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+            }
+            None => chunks.par_iter().map(|chunk_info| scan_chunk(chunk_info, None)).collect(),
+        };
+
+        // Flatten results, collecting chunks abandoned by the watchdog
+        let mut links: Vec<EnrichedLink> = Vec::new();
+        let mut stalled = Vec::new();
+        for (chunk_info, outcome) in chunks.iter().zip(outcomes) {
+            match outcome {
+                ChunkOutcome::Done(chunk_links) => links.extend(chunk_links),
+                ChunkOutcome::Stalled(elapsed) => stalled.push((chunk_info, elapsed)),
+                ChunkOutcome::Skipped | ChunkOutcome::Cancelled => {}
+            }
         }
 
-        if reverse {
-            chunks.reverse();
+        // Requeue stalled chunks on the scalar path in smaller windows
+        let mut incidents = Vec::new();
+        for (chunk_info, elapsed) in stalled {
+            if self.control.is_cancelled() {
+                break;
+            }
+            let (chunk_links, incident) = self.requeue_chunk(disk, mmap, chunk_info, elapsed, sender, watchdog, spans);
+            links.extend(chunk_links);
+            completed(chunk_info);
+            advance(chunk_info.offset);
+
+            if let Some(s) = sender {
+                if !s.is_closed() {
+                    let _ = s.blocking_send(ScanProgress::Incident(incident.clone()));
+                }
+            }
+            incidents.push(incident);
+        }
+        (links, incidents)
+    }
+
+    /// Scan chunks as a reader thread hands them over: read-ahead with
+    /// `prefetch`, io_uring with `nvme_optimization`. `None` when the workers
+    /// read their own chunks (mapped or pread)
+    fn scan_queued<F>(&self, disk: &DiskImage, chunks: &[ChunkInfo], scan_chunk: &F) -> Option<Vec<ChunkOutcome>>
+    where
+        F: Fn(&ChunkInfo, Option<&[u8]>) -> ChunkOutcome + Sync,
+    {
+        match self.config.prefetch {
+            // A pipe is read front to back once: there is nothing to read ahead of
+            Some(ref config) if disk.pipe().is_none() => Some(self.scan_prefetched(disk, chunks, config, scan_chunk)),
+            _ => self.scan_uring(disk, chunks, scan_chunk),
         }
+    }
 
-        let stats = ScanStatsAligned::new();
-        let _total_chunks = chunks.len();
-        let config = &self.config;
-        let sender_clone = sender;
-        let matcher = &self.enhanced_matcher;
+    /// Read chunks ahead in scan order (see `scanner::prefetch`) and scan
+    /// each as it arrives
+    fn scan_prefetched<F>(&self, disk: &DiskImage, chunks: &[ChunkInfo], config: &PrefetchConfig, scan_chunk: &F) -> Vec<ChunkOutcome>
+    where
+        F: Fn(&ChunkInfo, Option<&[u8]>) -> ChunkOutcome + Sync,
+    {
+        let stats = PrefetchStats::default();
+        let queued = config.queued_chunks(self.config.chunk_size + self.config.overlap_size);
+        let (sender, receiver) = std::sync::mpsc::sync_channel(queued);
+        let outcomes = std::thread::scope(|scope| {
+            scope.spawn(|| prefetch::read_chunks(disk, chunks, config, &self.control, &stats, sender));
+            receiver
+                .into_iter()
+                .par_bridge()
+                .map(|(index, read)| {
+                    // A chunk that kept failing is read again on the regular path, bad sectors included
+                    let buffer = read.ok();
+                    (index, scan_chunk(&chunks[index], buffer.as_deref()))
+                })
+                .collect()
+        });
+        let retries = stats.retries.load(Ordering::Relaxed);
+        if retries > 0 {
+            warn!(
+                "Prefetch: {} reads retried ({} timed out), {} chunks left to the regular reads",
+                retries,
+                stats.timeouts.load(Ordering::Relaxed),
+                stats.failed.load(Ordering::Relaxed)
+            );
+        }
+        self.complete_outcomes(chunks, outcomes, scan_chunk)
+    }
 
-        // Parallel scan with panic isolation and stats tracking
-        let all_links: Vec<Vec<EnrichedLink>> = chunks
-            .par_iter()
-            .enumerate()
-            .filter_map(|(_i, chunk_info)| {
-                let chunk_start = (chunk_info.offset - start_offset) as usize;
-                let chunk_end = chunk_start + chunk_info.size;
-                let chunk_data = &data[chunk_start..chunk_end];
+    /// Scan the chunks a reader did not deliver (it stopped early) the
+    /// regular way and put all outcomes in chunk order
+    fn complete_outcomes<F>(&self, chunks: &[ChunkInfo], mut outcomes: Vec<(usize, ChunkOutcome)>, scan_chunk: &F) -> Vec<ChunkOutcome>
+    where
+        F: Fn(&ChunkInfo, Option<&[u8]>) -> ChunkOutcome + Sync,
+    {
+        let mut delivered = vec![false; chunks.len()];
+        outcomes.iter().for_each(|(index, _)| delivered[*index] = true);
+        let remaining: Vec<usize> = (0..chunks.len()).filter(|&i| !delivered[i]).collect();
+        outcomes.par_extend(remaining.into_par_iter().map(|index| (index, scan_chunk(&chunks[index], None))));
+
+        outcomes.sort_unstable_by_key(|(index, _)| *index);
+        outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+    }
+
+    /// With `nvme_optimization`, read a raw image or device through io_uring
+    /// and scan each chunk as its read completes. `None` when the regular
+    /// (mapped or pread) path is used instead.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn scan_uring<F>(&self, disk: &DiskImage, chunks: &[ChunkInfo], scan_chunk: &F) -> Option<Vec<ChunkOutcome>>
+    where
+        F: Fn(&ChunkInfo, Option<&[u8]>) -> ChunkOutcome + Sync,
+    {
+        // Container formats are read by guest offset, not file offset; pipes have no file
+        if !self.config.nvme_optimization || disk.format().is_some() || disk.pipe().is_some() {
+            return None;
+        }
+        let reader = match UringReader::open(std::path::Path::new(disk.path())) {
+            Ok(reader) => reader,
+            Err(e) => {
+                warn!("io_uring reader unavailable ({}), using regular reads", e);
+                return None;
+            }
+        };
+
+        let depth = UringReader::depth_for(self.config.chunk_size + self.config.overlap_size);
+        let (sender, receiver) = std::sync::mpsc::sync_channel(depth);
+        let (reading, outcomes) = std::thread::scope(|scope| {
+            // io_uring reads take the priority of the thread submitting them
+            let reading = scope.spawn(|| {
+                if let Some(priority) = self.config.io_priority {
+                    throttle::apply_once(priority);
+                }
+                reader.read_chunks(chunks, depth, sender)
+            });
+            let outcomes: Vec<(usize, ChunkOutcome)> = receiver
+                .into_iter()
+                .par_bridge()
+                .map(|(index, read)| {
+                    // A failed read is retried on the regular path, bad sectors included
+                    let buffer = read
+                        .map_err(|e| {
+                            warn!(offset = chunks[index].offset, "io_uring read failed: {}", e)
+                        })
+                        .ok();
+                    (index, scan_chunk(&chunks[index], buffer.as_ref().map(ChunkBuffer::data)))
+                })
+                .collect();
+            (reading.join(), outcomes)
+        });
+        match reading {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("io_uring reader stopped ({}), reading the remaining chunks regularly", e),
+            Err(_) => warn!("io_uring reader panicked, reading the remaining chunks regularly"),
+        }
 
-                stats.add_chunk();
+        Some(self.complete_outcomes(chunks, outcomes, scan_chunk))
+    }
+
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    fn scan_uring<F>(&self, _disk: &DiskImage, _chunks: &[ChunkInfo], _scan_chunk: &F) -> Option<Vec<ChunkOutcome>>
+    where
+        F: Fn(&ChunkInfo, Option<&[u8]>) -> ChunkOutcome + Sync,
+    {
+        None
+    }
+
+    /// Load, scan and report a single chunk under the watchdog. `preloaded`
+    /// holds the chunk bytes when they were already read (prefetch, io_uring).
+    #[allow(clippy::too_many_arguments)]
+    fn scan_one_chunk(
+        &self,
+        disk: &DiskImage,
+        mmap: Option<&Mmap>,
+        chunk_info: &ChunkInfo,
+        preloaded: Option<&[u8]>,
+        sender: Option<&Sender<ScanProgress>>,
+        watchdog: Option<&Watchdog>,
+        simd: bool,
+    ) -> ChunkOutcome {
+        // Events of the chunk carry its offset and size in the log file
+        let _span = tracing::debug_span!("chunk", offset = chunk_info.offset, size = chunk_info.size).entered();
+        self.control.wait_while_paused();
+        if let Some(priority) = self.config.io_priority {
+            throttle::apply_once(priority);
+        }
+        // --max-read-mbps: waiting for the bucket is not a stall, so before the watchdog
+        if let Some(ref throttle) = self.throttle {
+            throttle.acquire(chunk_info.size as u64, &self.control);
+        }
+        // Cancelled while paused or throttled
+        if self.control.is_cancelled() {
+            return self.chunk_cancelled(chunk_info, sender);
+        }
 
-                // Report progress
-                if let Some(ref s) = sender_clone {
+        let started = Instant::now();
+        // Raised by the watchdog on stall or by an operator skip. Registered
+        // before loading so hung device reads are detected too
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let _guard = watchdog.map(|w| w.watch_with_flag(chunk_info.offset, Arc::clone(&cancel_flag)));
+        let control_guard = self.control.track(chunk_info.offset, Arc::clone(&cancel_flag));
+        let cancel = cancel_flag.as_ref();
+
+        let chunk_data: Cow<[u8]> = match (preloaded, mmap) {
+            (Some(data), _) => Cow::Borrowed(data),
+            (None, Some(mmap)) => {
+                let chunk_start = chunk_info.offset as usize;
+                Cow::Borrowed(&mmap[chunk_start..chunk_start + chunk_info.size])
+            }
+            (None, None) => {
+                let mut buffer = READ_BUFFER.with(|b| b.take());
+                buffer.clear();
+                buffer.resize(chunk_info.size, 0);
+                let error = match disk.read_tolerant(Offset::new(chunk_info.offset), &mut buffer) {
+                    Ok(0) => None,
+                    Ok(unreadable) => Some((unreadable, format!("{} unreadable bytes zero-filled", unreadable))),
+                    Err(e) => {
+                        warn!(offset = chunk_info.offset, "Read failed: {}", e);
+                        Some((chunk_info.size, format!("Read failed: {}", e)))
+                    }
+                };
+                if let (Some((unreadable, message)), Some(s)) = (error, sender) {
                     if !s.is_closed() {
-                        let _ = s.blocking_send(ScanProgress::ChunkCompleted(chunk_info.offset));
-                        let _ = s.blocking_send(ScanProgress::BytesScanned(chunk_info.size as u64));
+                        let offset = Offset::new(chunk_info.offset);
+                        let _ = s.blocking_send(ScanProgress::ChunkError(offset, message));
+                        let _ = s.blocking_send(ScanProgress::Skipped(
+                            offset,
+                            unreadable as u64,
+                            DiagnosticCause::BadSectors,
+                        ));
                     }
                 }
+                Cow::Owned(buffer)
+            }
+        };
 
-                // Isolate panics with catch_unwind
-                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                    self.scan_chunk_with_matcher(chunk_data, chunk_info.offset, matcher.clone_fresh())
-                }));
-
-                match result {
-                    Ok((links, hot_fragment)) => {
-                        // Send hot fragment if found
-                        if let Some(ref fragment) = hot_fragment {
-                            if let Some(ref s) = sender_clone {
-                                if !s.is_closed() {
-                                    let _ = s.blocking_send(ScanProgress::HotFragment(fragment.clone()));
-                                }
-                            }
+        // --verify-hash: the digest reuses this read
+        if let Some(digest) = &self.config.image_digest {
+            digest.feed(chunk_info.offset, &chunk_data);
+        }
+
+        // Isolate panics with catch_unwind
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            let mut structures = Vec::new();
+            let (links, hot_fragment) = self.scan_chunk_with_matcher(
+                &chunk_data,
+                chunk_info.offset,
+                self.enhanced_matcher.clone_fresh(),
+                cancel,
+                simd,
+                &mut structures,
+            )?;
+            let carved = self.carve_chunk(mmap, &chunk_data, chunk_info);
+            Some((links, hot_fragment, carved, structures))
+        }));
+
+        let outcome = match result {
+            Ok(Some((links, hot_fragment, carved, structures))) => {
+                if let Some(s) = sender.filter(|s| !s.is_closed()) {
+                    if self.config.chunk_stats {
+                        let stats = measure_chunk(chunk_info, &chunk_data, &links, hot_fragment.is_some(), started);
+                        let _ = s.blocking_send(ScanProgress::ChunkStats(stats));
+                    }
+                    // Overlap hits are reported by both chunks and deduplicated by the receiver
+                    if !structures.is_empty() {
+                        let _ = s.blocking_send(ScanProgress::Structures(structures));
+                    }
+                    let carved_any = !carved.is_empty();
+                    for fragment in carved {
+                        self.retain_fragment(disk, &fragment);
+                        let _ = s.blocking_send(ScanProgress::HotFragment(fragment));
+                    }
+                    match hot_fragment {
+                        Some(fragment) => {
+                            self.retain_fragment(disk, &fragment);
+                            let _ = s.blocking_send(ScanProgress::HotFragment(fragment));
+                        }
+                        // Not promoted because it looks compressed or encrypted
+                        None if !carved_any
+                            && is_compressed_like(&chunk_data[..chunk_data.len().min(ENTROPY_SAMPLE_SIZE)]) =>
+                        {
+                            // Overlap is counted by the next chunk
+                            let _ = s.blocking_send(ScanProgress::Skipped(
+                                Offset::new(chunk_info.offset),
+                                chunk_info.size.min(self.config.chunk_size) as u64,
+                                DiagnosticCause::HighEntropy,
+                            ));
                         }
-                        Some(links)
+                        None => {}
                     }
-                    Err(_) => {
-                        eprintln!(
-                            "[WARN] Corrupted sector at offset 0x{:X}, skipping",
-                            chunk_info.offset
-                        );
-                        if let Some(ref s) = sender_clone {
-                            if !s.is_closed() {
-                                let _ = s.blocking_send(ScanProgress::ChunkError(
-                                    chunk_info.offset,
-                                    "Panic in chunk processing".to_string(),
-                                ));
-                            }
+                    // --scan-db: links go to the receiver chunk by chunk instead of into ScanResult
+                    if self.config.stream_links {
+                        let mut links = links;
+                        links.retain(|l| l.confidence >= self.config.min_confidence);
+                        if !links.is_empty() {
+                            let _ = s.blocking_send(ScanProgress::Links(links));
                         }
-                        Some(Vec::new())
+                        return ChunkOutcome::Done(Vec::new());
                     }
                 }
-            })
-            .collect();
+                ChunkOutcome::Done(links)
+            }
+            Ok(None) if control_guard.was_skipped() => {
+                if let Some(s) = sender.filter(|s| !s.is_closed()) {
+                    let _ = s.blocking_send(ScanProgress::Skipped(
+                        Offset::new(chunk_info.offset),
+                        chunk_info.size.min(self.config.chunk_size) as u64,
+                        DiagnosticCause::OperatorSkipped,
+                    ));
+                }
+                ChunkOutcome::Skipped
+            }
+            Ok(None) if self.control.is_cancelled() => self.chunk_cancelled(chunk_info, sender),
+            Ok(None) => ChunkOutcome::Stalled(started.elapsed()),
+            Err(_) => {
+                warn!(offset = chunk_info.offset, "Corrupted sector, skipping");
+                if let Some(s) = sender {
+                    if !s.is_closed() {
+                        let _ = s.blocking_send(ScanProgress::ChunkError(
+                            Offset::new(chunk_info.offset),
+                            "Panic in chunk processing".to_string(),
+                        ));
+                    }
+                }
+                ChunkOutcome::Done(Vec::new())
+            }
+        };
 
-        // Flatten results
-        let mut links: Vec<EnrichedLink> = all_links.into_iter().flatten().collect();
+        if let Cow::Owned(buffer) = chunk_data {
+            READ_BUFFER.with(|b| *b.borrow_mut() = buffer);
+        }
+        outcome
+    }
 
-        // Global deduplication and filtering
-        if config.deduplicate {
-            self.deduplicate_links(&mut links);
+    /// Keep the bytes of a fragment found in a piped image for assembly
+    fn retain_fragment(&self, disk: &DiskImage, fragment: &HotFragment) {
+        if let Some(pipe) = disk.pipe() {
+            if let Err(e) = pipe.retain(fragment.offset.as_u64(), fragment.size.as_u64()) {
+                warn!(offset = fragment.offset.as_u64(), "Failed to keep fragment bytes of the pipe: {}", e);
+            }
         }
+    }
 
-        if config.min_confidence > 0.0 {
-            links.retain(|l| l.confidence >= config.min_confidence);
+    /// Record a chunk left unscanned by `ScanControl::cancel` or `stop`
+    fn chunk_cancelled(&self, chunk_info: &ChunkInfo, sender: Option<&Sender<ScanProgress>>) -> ChunkOutcome {
+        if let Some(s) = sender.filter(|s| !s.is_closed()) {
+            let _ = s.blocking_send(ScanProgress::Skipped(
+                Offset::new(chunk_info.offset),
+                chunk_info.size.min(self.config.chunk_size) as u64,
+                self.control.cancel_cause(),
+            ));
         }
+        ChunkOutcome::Cancelled
+    }
 
-        links.sort_by_key(|l| l.offset);
+    /// Rescan a stalled chunk on the scalar path in smaller windows.
+    /// Windows that stall again are skipped and counted in the incident.
+    #[allow(clippy::too_many_arguments)]
+    fn requeue_chunk(
+        &self,
+        disk: &DiskImage,
+        mmap: Option<&Mmap>,
+        chunk_info: &ChunkInfo,
+        stalled_for: Duration,
+        sender: Option<&Sender<ScanProgress>>,
+        watchdog: Option<&Watchdog>,
+        spans: &mut Vec<(u64, u64)>,
+    ) -> (Vec<EnrichedLink>, ScanIncident) {
+        let window_size = (self.config.chunk_size / REQUEUE_SPLIT).max(MIN_REQUEUE_WINDOW);
+        let overlap = self.config.overlap_size.min(window_size / 2);
+        let windows = split_range(chunk_info.offset, chunk_info.size, window_size, overlap);
+        spans.extend(windows.iter().map(|window| (window.offset, window.offset + window.size as u64)));
+
+        warn!(offset = chunk_info.offset, "Requeuing chunk as {} scalar windows", windows.len());
+
+        let outcomes: Vec<ChunkOutcome> = windows
+            .par_iter()
+            .map(|window| self.scan_one_chunk(disk, mmap, window, None, sender, watchdog, false))
+            .collect();
 
-        let bytes_scanned = (data.len() as u64).min(disk_size - start_offset);
-        let duration = start_time.elapsed();
+        let mut links = Vec::new();
+        let mut abandoned_windows = 0;
+        let mut unscanned_bytes = 0u64;
+        for (window, outcome) in windows.iter().zip(outcomes) {
+            match outcome {
+                ChunkOutcome::Done(window_links) => links.extend(window_links),
+                ChunkOutcome::Stalled(_) => {
+                    warn!(offset = window.offset, "Window stalled again, {} bytes left unscanned", window.size);
+                    abandoned_windows += 1;
+                    unscanned_bytes += window.size as u64;
+                }
+                ChunkOutcome::Skipped | ChunkOutcome::Cancelled => {}
+            }
+        }
 
-        Ok(ScanResult {
-            links,
-            bytes_scanned,
-            duration_secs: duration.as_secs_f64(),
-        })
+        let incident = ScanIncident {
+            offset: Offset::new(chunk_info.offset),
+            size: Size::from_usize(chunk_info.size),
+            stalled_secs: stalled_for.as_secs_f64(),
+            requeued_windows: windows.len(),
+            abandoned_windows,
+            unscanned_bytes,
+        };
+        (links, incident)
     }
 
-    /// Scan a single chunk with enhanced matcher and return (links, optional hot_fragment)
+    /// Scan a single chunk with enhanced matcher and return (links, optional hot_fragment).
+    /// `simd = false` forces the scalar block path; `None` means `cancel` was raised.
+    /// Detector hits are appended to `structures`.
     fn scan_chunk_with_matcher(
         &self,
         chunk_data: &[u8],
         offset: u64,
         mut matcher: EnhancedMatcher,
-    ) -> (Vec<EnrichedLink>, Option<HotFragment>) {
+        cancel: &AtomicBool,
+        simd: bool,
+        structures: &mut Vec<StructureHit>,
+    ) -> Option<(Vec<EnrichedLink>, Option<HotFragment>)> {
         let mut json_markers = 0;
-        let mut cyrillic_count = 0;
         let mut prefetcher = AdaptivePrefetcher::new();
 
         // Use enhanced matcher for YouTube links
-        let links: Vec<EnrichedLink> = matcher.scan_chunk(chunk_data, offset as usize, self.config.deduplicate);
+        let mut links: Vec<EnrichedLink> =
+            matcher.scan_chunk_cancellable(chunk_data, Offset::new(offset), self.config.deduplicate, cancel);
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let trace = matcher.trace().filter(|trace| trace.overlaps(offset, chunk_data.len())).cloned();
+        if let Some(keywords) = &self.config.keywords {
+            let found = links.len();
+            keywords.apply_to_links(chunk_data, offset, &mut links);
+            if let Some(trace) = &trace {
+                trace.event(offset, "keywords", format_args!("kept {} of {} links", links.len(), found));
+            }
+        }
         let youtube_count = links.len();
+        if self.config.artifacts.iter().any(|artifact| artifact == "browser") {
+            // Not counted in the fragment score: history rows are links, not text
+            links.extend(super::artifacts::browser_history_links(chunk_data, offset));
+        }
 
         // Optimized block scan with prefetching
-        let block_size = 64; // Use 64 bytes for cache line alignment
+        let block_size = BLOCK_SIZE; // Use 64 bytes for cache line alignment
         let mut is_empty = true;
         let mut has_metadata = false;
+        // Detector windows must start on image block boundaries
+        let detect_in_blocks = !self.detectors.is_empty() && offset.is_multiple_of(block_size as u64);
 
         let mut i = 0;
-        while i + block_size <= chunk_data.len() {
+        while simd && i + block_size <= chunk_data.len() {
+            if i % CANCEL_CHECK_INTERVAL == 0 && cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+
             // Adaptive software prefetching
             unsafe {
                 prefetcher.record_access(i);
                 prefetcher.prefetch_next(chunk_data.as_ptr().add(i));
             }
 
-            // SIMD block scan (AVX2 ASM optimized)
-            unsafe {
-                let block_ptr = chunk_data.as_ptr().add(i) as *const AlignedBlock;
-                if is_x86_feature_detected!("avx2") {
-                    let res = scan_block_avx2_asm(&*block_ptr);
-                    if !res.is_empty {
-                        is_empty = false;
-                    }
-                    if res.has_metadata {
-                        has_metadata = true;
-                    }
-                    
-                    if res.hot_mask_low != 0 || res.hot_mask_high != 0 {
-                        json_markers += (res.hot_mask_low.count_ones() + res.hot_mask_high.count_ones()) as usize;
-                    }
+            // SIMD block scan (AVX2 ASM / NEON)
+            if let Some(res) = scan_block64_simd(&chunk_data[i..i + block_size]) {
+                if !res.is_empty {
+                    is_empty = false;
+                }
+                if res.has_metadata {
+                    has_metadata = true;
+                }
+
+                if res.hot_mask_low != 0 || res.hot_mask_high != 0 {
+                    json_markers += (res.hot_mask_low.count_ones() + res.hot_mask_high.count_ones()) as usize;
+                }
+            }
+
+            if detect_in_blocks {
+                if let Ok(window) = chunk_data[i..i + block_size].try_into() {
+                    self.detectors.check(offset + i as u64, window, structures);
                 }
             }
 
             i += block_size;
         }
 
+        // Blocks the loop did not cover (scalar path, unaligned chunk start)
+        if detect_in_blocks {
+            self.detectors.scan(&chunk_data[i..], offset + i as u64, structures);
+        } else {
+            self.detectors.scan(chunk_data, offset, structures);
+        }
+
         // Processing remainder
         for &b in &chunk_data[i..] {
             if b != 0 { is_empty = false; }
             if b == b'{' || b == b'}' || b == b'[' || b == b']' { json_markers += 1; }
-            if b >= 0xD0 && b <= 0xDF { cyrillic_count += 1; }
         }
 
-        let cyrillic_density = if chunk_data.is_empty() { 0.0 } else { cyrillic_count as f32 / chunk_data.len() as f32 };
-        let fragment_score = calculate_fragment_score(chunk_data, youtube_count, cyrillic_density, json_markers);
+        let scripts = ScriptDensity::measure(chunk_data);
+        let relevance = self.config.keyword_profile.as_ref().map(|profile| profile.relevance(chunk_data));
+        let mut fragment_score = calculate_fragment_score(
+            chunk_data,
+            youtube_count,
+            &scripts,
+            self.config.target_script,
+            json_markers,
+            relevance.as_ref(),
+        );
+        let keyword_match = match &self.config.keywords {
+            Some(keywords) if !is_empty => keywords.apply_to_fragment(chunk_data, &mut fragment_score),
+            _ => true,
+        };
         let target_score = fragment_score.overall_score;
+        let hot = target_score > 20.0 && !is_empty && keyword_match;
+        if let Some(trace) = &trace {
+            trace.event(
+                offset,
+                "fragment",
+                format_args!(
+                    "len={} links={} json_markers={} {}={:.2} score={:.1} reasons={} empty={} keyword_match={} hot={}",
+                    chunk_data.len(),
+                    youtube_count,
+                    json_markers,
+                    self.config.target_script.name(),
+                    scripts.of(self.config.target_script),
+                    target_score,
+                    fragment_score.reasons.join(","),
+                    is_empty,
+                    keyword_match,
+                    hot
+                ),
+            );
+        }
 
         // Create hot fragment if promising using Aligned version internally
-        let hot_fragment = if target_score > 20.0 && !is_empty {
+        let hot_fragment = if hot {
             let file_type = self.guess_file_type_fast(chunk_data);
             let mut aligned = HotFragmentAligned::new(offset, chunk_data.len() as u64);
             
             aligned.youtube_count = youtube_count as u32;
-            aligned.cyrillic_density = cyrillic_density;
+            aligned.cyrillic_density = scripts.cyrillic;
             aligned.json_markers = json_markers as u32;
             aligned.has_valid_json = fragment_score.is_valid_json;
             aligned.target_score = target_score;
@@ -332,9 +1203,10 @@ impl ParallelScanner {
             aligned.has_metadata = has_metadata;
             
             // Convert to standard HotFragment for compatibility with existing Result types
-            let mut fragment = HotFragment::new(aligned.offset, aligned.size as usize);
+            let mut fragment = HotFragment::new(Offset::new(aligned.offset), Size::new(aligned.size));
             fragment.youtube_count = aligned.youtube_count as usize;
             fragment.cyrillic_density = aligned.cyrillic_density;
+            fragment.scripts = scripts;
             fragment.json_markers = aligned.json_markers as usize;
             fragment.has_valid_json = aligned.has_valid_json;
             fragment.target_score = aligned.target_score;
@@ -342,12 +1214,59 @@ impl ParallelScanner {
             fragment.entropy = aligned.entropy;
             fragment.fragment_score = fragment_score;
 
+            if let Some(cache) = &self.config.fragment_cache {
+                cache.insert(offset, chunk_data);
+            }
             Some(fragment)
         } else {
             None
         };
 
-        (links, hot_fragment)
+        Some((links, hot_fragment))
+    }
+
+    /// Carve complete files whose header lies in the chunk (overlap headers
+    /// belong to the next chunk). Mapped images are carved up to the image
+    /// end so files crossing the chunk boundary are still found.
+    fn carve_chunk(&self, mmap: Option<&Mmap>, chunk_data: &[u8], chunk_info: &ChunkInfo) -> Vec<HotFragment> {
+        if self.config.carve_types.is_empty() {
+            return Vec::new();
+        }
+
+        let data: &[u8] = match mmap {
+            Some(mmap) => &mmap[chunk_info.offset as usize..],
+            None => chunk_data,
+        };
+        let header_limit = chunk_info.size.min(self.config.chunk_size);
+
+        carver::carve(data, header_limit, &self.config.carve_types)
+            .into_iter()
+            .map(|file| {
+                let content = &data[file.offset..file.offset + file.size];
+                if let Some(cache) = &self.config.fragment_cache {
+                    cache.insert(chunk_info.offset + file.offset as u64, content);
+                }
+                let mut fragment =
+                    HotFragment::new(Offset::new(chunk_info.offset + file.offset as u64), Size::from_usize(file.size));
+                fragment.file_type_guess = file.file_type.to_string();
+                fragment.entropy =
+                    crate::entropy::calculate_shannon_entropy(&content[..content.len().min(ENTROPY_SAMPLE_SIZE)]);
+                fragment.target_score = CARVED_FILE_SCORE;
+                fragment.fragment_score.overall_score = CARVED_FILE_SCORE;
+                fragment.fragment_score.reasons = vec![match file.preview_of {
+                    Some(raw) if file.file_type == "jpg" => format!("jpg preview of a {} whose end was not found", raw),
+                    Some(document) => format!("text of a {} whose end was not found", document),
+                    None => format!("{} header and end found", file.file_type),
+                }];
+                fragment.carved = true;
+                fragment
+            })
+            .collect()
+    }
+
+    /// Links and hot fragment of one window outside a scan (`estimate` samples)
+    pub fn scan_window(&self, data: &[u8], offset: u64) -> (Vec<EnrichedLink>, Option<HotFragment>) {
+        self.scan_chunk(data, offset, &[])
     }
 
     /// Legacy scan_chunk method (kept for compatibility)
@@ -358,57 +1277,51 @@ impl ParallelScanner {
         _patterns: &[Vec<u8>],
     ) -> (Vec<EnrichedLink>, Option<HotFragment>) {
         // Delegate to new method with a fresh matcher
-        self.scan_chunk_with_matcher(chunk_data, offset, self.enhanced_matcher.clone_fresh())
+        self.scan_chunk_with_matcher(
+            chunk_data,
+            offset,
+            self.enhanced_matcher.clone_fresh(),
+            &AtomicBool::new(false),
+            true,
+            &mut Vec::new(),
+        )
+        .unwrap_or_default()
     }
 
-    /// Create aligned chunks from data
-    fn create_chunks(&self, data: &[u8], start_offset: u64) -> Vec<ChunkInfo> {
-        let chunk_size = self.config.chunk_size;
-        let overlap = self.config.overlap_size;
-
-        let mut chunks = Vec::new();
-        let mut offset = 0usize;
-
-        while offset < data.len() {
-            let chunk_end = offset
-                .saturating_add(chunk_size)
-                .saturating_add(overlap)
-                .min(data.len());
-
-            if offset < chunk_end {
-                chunks.push(ChunkInfo {
-                    offset: start_offset + offset as u64,
-                    size: chunk_end - offset,
-                });
-            }
+    /// Create aligned chunks covering `len` bytes from `start_offset`
+    fn create_chunks(&self, len: usize, start_offset: u64) -> Vec<ChunkInfo> {
+        self.restrict(split_range(start_offset, len, self.config.chunk_size, self.config.overlap_size))
+    }
 
-            offset = offset.saturating_add(chunk_size);
-            if chunk_size == 0 {
-                break;
-            }
+    /// `chunks` cut to `ScanConfig::scan_ranges`
+    fn restrict(&self, chunks: Vec<ChunkInfo>) -> Vec<ChunkInfo> {
+        match self.config.scan_ranges.as_deref() {
+            Some(ranges) => restrict_chunks(chunks, ranges, self.config.chunk_size, self.config.overlap_size),
+            None => chunks,
         }
-
-        chunks
     }
 
-    /// Deduplicate links, keeping the best version of each
-    fn deduplicate_links(&self, links: &mut Vec<EnrichedLink>) {
-        let mut best_links: HashMap<String, EnrichedLink> = HashMap::new();
-
-        for link in links.drain(..) {
-            let video_id = link.video_id.clone();
-
-            best_links
-                .entry(video_id)
-                .and_modify(|existing| {
-                    if Self::is_better_link(&link, existing) {
-                        *existing = link.clone();
-                    }
-                })
-                .or_insert(link);
+    /// Bytes of `[start, end)` the scan covers (all of them without `ScanConfig::scan_ranges`)
+    fn covered_bytes(&self, start: u64, end: u64) -> u64 {
+        match self.config.scan_ranges.as_deref() {
+            Some(ranges) => covered_bytes(ranges, start, end),
+            None => end.saturating_sub(start),
         }
+    }
 
-        links.extend(best_links.into_values());
+    /// Keep the best report of each link found by two chunks in their
+    /// overlap. A link is identified by its value and the value's offset, as
+    /// patterns matching the same value may start at different bytes.
+    fn suppress_overlap_duplicates(links: &mut Vec<EnrichedLink>, windows: &OverlapWindows) {
+        suppress_duplicates(
+            links,
+            windows,
+            |link| {
+                let value_at = link.url.find(&link.video_id).unwrap_or(0);
+                (link.offset.as_u64() + value_at as u64, link.video_id.clone())
+            },
+            Self::is_better_link,
+        );
     }
 
     /// Check if new link is "better" than existing one
@@ -438,6 +1351,13 @@ impl ParallelScanner {
 
     /// Fast file type guessing based on content
     fn guess_file_type_fast(&self, data: &[u8]) -> String {
+        // Chat exports and e-mail before the generic json/txt guesses
+        if let Some(file_type) = crate::recovery::messenger::detect(data) {
+            return file_type.to_string();
+        }
+        if let Some(file_type) = crate::recovery::email::detect(data) {
+            return file_type.to_string();
+        }
         if let Some(&first) = data.first() {
             if first == b'{' || first == b'[' {
                 return "json".to_string();
@@ -465,13 +1385,31 @@ mod tests {
         let scanner = ParallelScanner::new(config);
 
         let data = vec![0u8; 5000];
-        let chunks = scanner.create_chunks(&data, 0);
+        let chunks = scanner.create_chunks(data.len(), 0);
 
         assert!(!chunks.is_empty());
         assert!(chunks[0].offset == 0);
         assert!(chunks[0].size > 0);
     }
 
+    #[test]
+    fn test_chunks_restricted_to_ranges() {
+        let chunks = split_range(0, 4096, 1024, 64);
+        let ranges = [(100, 200), (230, 300), (1000, 1100), (3000, 5000)];
+        let parts = restrict_chunks(chunks, &ranges, 1024, 64);
+        let parts: Vec<(u64, usize)> = parts.iter().map(|c| (c.offset, c.size)).collect();
+        // (100, 200) and (230, 300) are closer than the overlap; the range
+        // crossing 1024 is split at the chunk boundary like the chunks
+        assert_eq!(parts, [(100, 264), (1000, 88), (1024, 140), (3000, 136), (3072, 1024)]);
+        assert_eq!(covered_bytes(&ranges, 250, 3500), 50 + 100 + 500);
+
+        let mut config = ScanConfig::new(1024, 64, 0);
+        config.scan_ranges = Some(Arc::new(ranges.to_vec()));
+        let scanner = ParallelScanner::new(config);
+        assert_eq!(scanner.create_chunks(4096, 0).len(), 5);
+        assert_eq!(scanner.covered_bytes(0, 4096), 100 + 70 + 100 + 1096);
+    }
+
     #[test]
     fn test_chunk_alignment() {
         let config = ScanConfig::new(100, 64, 0);
@@ -480,4 +1418,92 @@ mod tests {
         // Chunk size should be aligned to 64 bytes
         assert_eq!(scanner.config.chunk_size % 64, 0);
     }
+
+    #[test]
+    fn test_resume_watermark() {
+        let chunks = split_range(1000, 4000, 1000, 64);
+
+        // Out-of-order completion only advances over the contiguous prefix
+        let mut forward = ResumeWatermark::new(&chunks, 5000, false);
+        assert_eq!(forward.complete(2000), None);
+        assert_eq!(forward.complete(1000), Some(Offset::new(3000)));
+        assert_eq!(forward.complete(4000), None);
+        assert_eq!(forward.complete(3000), Some(Offset::new(5000)));
+
+        let mut reverse = ResumeWatermark::new(&chunks, 5000, true);
+        assert_eq!(reverse.position(), 5000);
+        assert_eq!(reverse.complete(3000), None);
+        assert_eq!(reverse.complete(4000), Some(Offset::new(3000)));
+    }
+
+    #[test]
+    fn test_streaming_matches_mapped_scan() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("rust_recovery_streaming_{}.img", std::process::id()));
+        let mut image = vec![0u8; 1 << 20];
+        for i in 0..40usize {
+            // Some links straddle chunk boundaries
+            let link = format!("https://www.youtube.com/watch?v=dQw4w9WgX{:02}", i);
+            let at = i * 26_000 + 4000;
+            image[at..at + link.len()].copy_from_slice(link.as_bytes());
+        }
+        std::fs::write(&path, &image).unwrap();
+
+        let mut config = ScanConfig::new(4096, 256, 0);
+        config.stall_timeout = None;
+        let video_ids = |disk: &DiskImage, config: &ScanConfig| {
+            let scanner = ParallelScanner::new(config.clone());
+            let result = scanner.scan_range(disk, Offset::new(0), Offset::new(1 << 20), config.reverse, None).unwrap();
+            let mut ids: Vec<String> = result.links.into_iter().map(|l| l.video_id).collect();
+            ids.sort();
+            ids
+        };
+        let mapped = video_ids(&DiskImage::open(&path).unwrap(), &config);
+        assert_eq!(mapped.len(), 40);
+
+        let unmapped = DiskImage::open_unmapped(&path).unwrap();
+        assert!(unmapped.get_mmap().is_none());
+        config.streaming = true;
+        assert_eq!(video_ids(&unmapped, &config), mapped);
+        config.reverse = true;
+        assert_eq!(video_ids(&unmapped, &config), mapped);
+
+        // A pipe is read once, one window at a time
+        let piped = DiskImage::open_pipe(std::io::Cursor::new(image.clone()), "-", path.with_extension("spill")).unwrap();
+        config.reverse = false;
+        assert_eq!(video_ids(&piped, &config), mapped);
+        assert!(piped.pipe().unwrap().is_eof());
+
+        // Window chunks are the chunks of the full split
+        let chunks = split_range(7, 100_000, 4096, 256);
+        for (i, chunk) in chunks.iter().enumerate() {
+            let at = chunk_at(7, 100_000, 4096, 256, i);
+            assert_eq!((at.offset, at.size), (chunk.offset, chunk.size));
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_overlap_duplicates_suppressed() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("rust_recovery_overlap_{}.img", std::process::id()));
+        let mut image = vec![0u8; 64 * 1024];
+        let link = b"https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        // Read by chunks 0 and 1 (4096 + 256 overlap), then repeated elsewhere
+        for at in [4100, 20_000, 30_000] {
+            image[at..at + link.len()].copy_from_slice(link);
+        }
+        std::fs::write(&path, &image).unwrap();
+
+        let mut config = ScanConfig::new(4096, 256, 0);
+        config.stall_timeout = None;
+        let scanner = ParallelScanner::new(config);
+        let disk = DiskImage::open(&path).unwrap();
+        let result = scanner.scan_range(&disk, Offset::new(0), Offset::new(64 * 1024), false, None).unwrap();
+        let offsets: Vec<u64> = result.links.iter().map(|l| l.offset.as_u64()).collect();
+        assert_eq!(offsets, [4100, 20_000, 30_000]);
+
+        let _ = std::fs::remove_file(&path);
+    }
 }
@@ -0,0 +1,169 @@
+//! Read-ahead for images on network block devices (`--prefetch`)
+//!
+//! Over NBD or iSCSI every read waits for a network round trip, and the scan
+//! threads keep only one chunk read each in flight, so the link idles while
+//! they match. Here `depth` reader threads read the chunks in scan order
+//! (priority, hint and reverse order included) and queue completed ones for
+//! the scan workers, at most `distance_bytes` ahead of them. A read that fails,
+//! hits unreadable blocks or gets no reply within `timeout` is issued again,
+//! up to `retries` times with a growing pause; transient network errors
+//! usually clear by then. A chunk still failing is handed over as an error
+//! and the scanner reads it the regular way, zero-filling bad sectors. A hung
+//! read cannot be aborted: it is left on its own thread and the chunk is read
+//! again on a fresh one.
+
+use crate::disk::DiskImage;
+use crate::scanner::control::ScanControl;
+use crate::scanner::ChunkInfo;
+use crate::types::Offset;
+use std::io;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender};
+use std::time::Duration;
+use tracing::warn;
+
+/// Read-ahead on NBD and iSCSI devices when `--prefetch` is not given
+pub const DEFAULT_DISTANCE_BYTES: u64 = 256 * 1024 * 1024;
+/// Pause before the first retry; doubled for each further one
+const RETRY_PAUSE: Duration = Duration::from_millis(500);
+
+/// Completed read of one chunk: `(index, bytes)`
+pub type PrefetchedChunk = (usize, io::Result<Vec<u8>>);
+
+/// How far and how wide to read ahead
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefetchConfig {
+    /// Bytes of completed chunks queued ahead of the scan workers
+    pub distance_bytes: u64,
+    /// Reads in flight
+    pub depth: usize,
+    /// A read with no reply after this long is issued again
+    pub timeout: Duration,
+    /// Further attempts after a failed or timed-out read
+    pub retries: u32,
+}
+
+impl PrefetchConfig {
+    /// Completed chunks of `chunk_size` bytes queued ahead (at least one)
+    pub fn queued_chunks(&self, chunk_size: usize) -> usize {
+        (self.distance_bytes / chunk_size.max(1) as u64).max(1) as usize
+    }
+}
+
+/// Counters of a prefetched scan, for the log
+#[derive(Debug, Default)]
+pub struct PrefetchStats {
+    pub chunks: AtomicU64,
+    pub retries: AtomicU64,
+    pub timeouts: AtomicU64,
+    /// Chunks handed over as errors (read again by the scanner)
+    pub failed: AtomicU64,
+}
+
+/// Read `chunks` in order on `config.depth` threads, sending each as it
+/// completes; the channel's capacity bounds the read-ahead. Stops when the
+/// scan is cancelled or the receiver is gone
+pub fn read_chunks(
+    disk: &DiskImage,
+    chunks: &[ChunkInfo],
+    config: &PrefetchConfig,
+    control: &ScanControl,
+    stats: &PrefetchStats,
+    sender: SyncSender<PrefetchedChunk>,
+) {
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..config.depth.max(1) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                if control.is_cancelled() {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(chunk) = chunks.get(index) else {
+                    break;
+                };
+                let read = read_chunk(disk, chunk, config, stats);
+                stats.chunks.fetch_add(1, Ordering::Relaxed);
+                if sender.send((index, read)).is_err() {
+                    break;
+                }
+            });
+        }
+    });
+}
+
+/// Read one chunk with retries; an error once the attempts are used up
+fn read_chunk(disk: &DiskImage, chunk: &ChunkInfo, config: &PrefetchConfig, stats: &PrefetchStats) -> io::Result<Vec<u8>> {
+    let mut last_error = io::Error::other("not read");
+    for attempt in 0..=config.retries {
+        if attempt > 0 {
+            stats.retries.fetch_add(1, Ordering::Relaxed);
+            std::thread::sleep(RETRY_PAUSE * (1 << (attempt - 1).min(5)));
+        }
+        // On its own thread, so that a read that never returns can be left behind
+        let (reply, result) = mpsc::channel();
+        let (disk, offset, size) = (disk.clone(), chunk.offset, chunk.size);
+        std::thread::spawn(move || {
+            let mut buffer = vec![0u8; size];
+            let read = disk.read_tolerant(Offset::new(offset), &mut buffer);
+            let _ = reply.send(read.map(|unreadable| (buffer, unreadable)));
+        });
+        last_error = match result.recv_timeout(config.timeout) {
+            Ok(Ok((buffer, 0))) => return Ok(buffer),
+            Ok(Ok((_, unreadable))) => io::Error::other(format!("{} unreadable bytes", unreadable)),
+            Ok(Err(e)) => io::Error::other(e.to_string()),
+            Err(RecvTimeoutError::Timeout) => {
+                stats.timeouts.fetch_add(1, Ordering::Relaxed);
+                io::Error::new(io::ErrorKind::TimedOut, format!("no reply in {} s", config.timeout.as_secs_f64()))
+            }
+            Err(RecvTimeoutError::Disconnected) => io::Error::other("read thread panicked"),
+        };
+        warn!(offset = chunk.offset, attempt = attempt + 1, "Prefetch read failed: {}", last_error);
+    }
+    stats.failed.fetch_add(1, Ordering::Relaxed);
+    Err(last_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefetch_reads_every_chunk_in_scan_order() {
+        let image: Vec<u8> = (0..64 * 1024u32).map(|i| (i % 251) as u8).collect();
+        let path = std::env::temp_dir().join(format!("rust_recovery_prefetch_{}.img", std::process::id()));
+        std::fs::write(&path, &image).unwrap();
+        let disk = DiskImage::open_unmapped(&path).unwrap();
+
+        // Reverse order, as a --reverse scan hands them out
+        let chunks: Vec<ChunkInfo> =
+            (0..8).rev().map(|i| ChunkInfo { offset: i * 8192, size: 8192 }).collect();
+        let config = PrefetchConfig { distance_bytes: 16 * 1024, depth: 2, timeout: Duration::from_secs(10), retries: 1 };
+        assert_eq!(config.queued_chunks(8192), 2);
+        let (sender, receiver) = mpsc::sync_channel(config.queued_chunks(8192));
+        let control = ScanControl::new();
+        let stats = PrefetchStats::default();
+        let mut received = std::thread::scope(|scope| {
+            scope.spawn(|| read_chunks(&disk, &chunks, &config, &control, &stats, sender));
+            receiver.into_iter().collect::<Vec<_>>()
+        });
+        received.sort_by_key(|(index, _)| *index);
+        assert_eq!(received.len(), chunks.len());
+        for ((_, read), chunk) in received.iter().zip(&chunks) {
+            let start = chunk.offset as usize;
+            assert_eq!(read.as_ref().unwrap()[..], image[start..start + chunk.size]);
+        }
+        assert_eq!(stats.chunks.load(Ordering::Relaxed), 8);
+        assert_eq!(stats.failed.load(Ordering::Relaxed), 0);
+
+        // A chunk past the end fails every attempt and is handed over as an error
+        let beyond = ChunkInfo { offset: image.len() as u64, size: 8192 };
+        let config = PrefetchConfig { retries: 0, ..config };
+        assert!(read_chunk(&disk, &beyond, &config, &stats).is_err());
+        assert_eq!(stats.failed.load(Ordering::Relaxed), 1);
+        drop(disk);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,82 @@
+//! Chunk order by expected value (`--priority`)
+//!
+//! Before the full pass, the first sectors of every chunk are read and
+//! scored: matcher needle hits count most, then the share of non-zero bytes.
+//! Chunks are then scanned from the highest score down (ties keep the scan
+//! direction), so `--early-exit` and `--time-budget` stop after the most
+//! promising part of the image. The resume point still only advances over
+//! the contiguous run of completed chunks.
+
+use crate::disk::DiskImage;
+use crate::scanner::ChunkInfo;
+use crate::types::Offset;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use aho_corasick::AhoCorasick;
+
+/// Leading bytes of a chunk read for the prefilter (8 sectors)
+pub const PREFILTER_BYTES: usize = 8 * 512;
+/// One needle hit outweighs any amount of non-zero data
+const NEEDLE_WEIGHT: f32 = 10.0;
+
+/// Expected value of a chunk from its first sectors
+pub fn prefilter_score(sample: &[u8], finder: Option<&AhoCorasick>) -> f32 {
+    if sample.is_empty() {
+        return 0.0;
+    }
+    let non_zero = sample.iter().filter(|&&b| b != 0).count() as f32 / sample.len() as f32;
+    let hits = finder.map_or(0, |finder| finder.find_iter(sample).count());
+    hits as f32 * NEEDLE_WEIGHT + non_zero
+}
+
+/// Chunk indexes by descending score; equal scores keep their order
+pub fn priority_order(scores: &[f32]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+    order
+}
+
+/// Score the first sectors of `chunks` (from the mapping when there is one)
+/// and return them in priority order. Unreadable prefixes score 0.
+pub fn prioritize(disk: &DiskImage, mmap: Option<&Mmap>, chunks: &[ChunkInfo], finder: Option<&AhoCorasick>) -> Vec<ChunkInfo> {
+    let scores: Vec<f32> = chunks
+        .par_iter()
+        .map(|chunk| {
+            let len = chunk.size.min(PREFILTER_BYTES);
+            match mmap {
+                Some(mmap) => {
+                    let start = chunk.offset as usize;
+                    prefilter_score(&mmap[start..start + len], finder)
+                }
+                None => disk
+                    .get_slice(Offset::new(chunk.offset), len)
+                    .map_or(0.0, |slice| prefilter_score(&slice.data, finder)),
+            }
+        })
+        .collect();
+    priority_order(&scores).into_iter().map(|index| chunks[index].clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefilter_score_and_order() {
+        let finder = AhoCorasick::new(["youtube", "v="]).unwrap();
+        let zeros = [0u8; 512];
+        let mut text = [b' '; 512];
+        let mut half = [0u8; 512];
+        half[..256].fill(b'x');
+        text[100..111].copy_from_slice(b"youtube.com");
+
+        assert_eq!(prefilter_score(&zeros, Some(&finder)), 0.0);
+        assert_eq!(prefilter_score(&half, Some(&finder)), 0.5);
+        assert_eq!(prefilter_score(&text, Some(&finder)), NEEDLE_WEIGHT + 1.0);
+        assert_eq!(prefilter_score(&text, None), 1.0);
+        assert_eq!(prefilter_score(&[], Some(&finder)), 0.0);
+
+        let scores = [0.0, 0.5, NEEDLE_WEIGHT + 1.0, 0.5, 0.0];
+        assert_eq!(priority_order(&scores), vec![2, 1, 3, 0, 4]);
+    }
+}
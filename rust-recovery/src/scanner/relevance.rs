@@ -0,0 +1,224 @@
+//! Keyword relevance (`--keyword-profile`)
+//!
+//! A profile lists terms that matter to the case at hand (names, project
+//! codenames, account ids), each with a weight. Every chunk is searched for
+//! all of them in one pass of an Aho-Corasick automaton (SIMD prefilter in
+//! front), and each hit adds its term's weight to the fragment score, up to
+//! `max_hits` hits per term so a log line repeated a thousand times does not
+//! drown the rest. Terms match whole words only. Case is ignored for ASCII;
+//! other scripts match in lower case, upper case and with capitalized words
+//! ("иван петров", "ИВАН ПЕТРОВ", "Иван Петров"). Whitespace inside a term
+//! matches a single space.
+//!
+//! ```toml
+//! name = "case-117"
+//! max_hits = 10
+//!
+//! [[keywords]]
+//! term = "Иван Петров"
+//! weight = 8.0
+//!
+//! [[keywords]]
+//! term = "nightjar"
+//! ```
+
+use crate::error::{RecoveryError, Result};
+use aho_corasick::{AhoCorasick, MatchKind};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Score points of a hit when the term gives no weight
+const DEFAULT_WEIGHT: f32 = 5.0;
+/// Hits of one term counted per chunk when the profile gives no limit
+const DEFAULT_MAX_HITS: u32 = 10;
+
+fn default_weight() -> f32 {
+    DEFAULT_WEIGHT
+}
+
+fn default_max_hits() -> u32 {
+    DEFAULT_MAX_HITS
+}
+
+/// One term of a profile file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeywordSpec {
+    pub term: String,
+    /// Score points per hit
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+/// Keyword profile file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeywordProfileSpec {
+    #[serde(default)]
+    pub name: String,
+    /// Hits of one term counted per chunk
+    #[serde(default = "default_max_hits")]
+    pub max_hits: u32,
+    pub keywords: Vec<KeywordSpec>,
+}
+
+/// Compiled keyword profile
+#[derive(Debug)]
+pub struct KeywordProfile {
+    spec: KeywordProfileSpec,
+    automaton: AhoCorasick,
+    /// Term of each automaton pattern (a term has one pattern per case variant)
+    pattern_terms: Vec<usize>,
+}
+
+/// Weighted keyword hits of one chunk
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeywordRelevance {
+    /// `(term, hits)` of the terms found, hits capped at `max_hits`
+    pub hits: Vec<(String, u32)>,
+    /// Score points of the hits
+    pub points: f32,
+}
+
+impl KeywordRelevance {
+    /// `term×hits` list for score reasons
+    pub fn summary(&self) -> String {
+        self.hits.iter().map(|(term, hits)| format!("{}×{}", term, hits)).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// Spellings of `term` the automaton looks for: as given, lower case, upper
+/// case and with capitalized words
+fn case_variants(term: &str) -> Vec<String> {
+    let capitalized = term
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+    let mut variants = vec![term.to_string(), term.to_lowercase(), term.to_uppercase(), capitalized];
+    variants.sort();
+    variants.dedup();
+    variants
+}
+
+/// Letter or digit ending right before `pos`
+fn word_char_before(data: &[u8], pos: usize) -> bool {
+    let start = (pos.saturating_sub(4)..pos).rev().find(|&i| data[i] & 0xC0 != 0x80);
+    start
+        .and_then(|start| std::str::from_utf8(&data[start..pos]).ok())
+        .and_then(|text| text.chars().next())
+        .is_some_and(char::is_alphanumeric)
+}
+
+/// Letter or digit starting at `pos`
+fn word_char_at(data: &[u8], pos: usize) -> bool {
+    let len = match data.get(pos) {
+        None => return false,
+        Some(0x00..=0x7F) => 1,
+        Some(0xC0..=0xDF) => 2,
+        Some(0xE0..=0xEF) => 3,
+        Some(_) => 4,
+    };
+    data.get(pos..pos + len)
+        .and_then(|bytes| std::str::from_utf8(bytes).ok())
+        .and_then(|text| text.chars().next())
+        .is_some_and(char::is_alphanumeric)
+}
+
+impl KeywordProfile {
+    /// Load a TOML profile
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| RecoveryError::Config(format!("Keyword profile {}: {}", path.display(), e)))?;
+        let spec: KeywordProfileSpec =
+            toml::from_str(&text).map_err(|e| RecoveryError::Config(format!("{}: {}", path.display(), e)))?;
+        Self::from_spec(spec)
+    }
+
+    pub fn from_spec(mut spec: KeywordProfileSpec) -> Result<Self> {
+        let invalid = |msg: String| RecoveryError::Config(format!("keyword profile '{}': {}", spec.name, msg));
+        for keyword in &mut spec.keywords {
+            keyword.term = keyword.term.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if let Some(keyword) = spec.keywords.iter().find(|k| k.term.is_empty()) {
+            return Err(invalid(format!("empty term (weight {})", keyword.weight)));
+        }
+        if let Some(keyword) = spec.keywords.iter().find(|k| !k.weight.is_finite()) {
+            return Err(invalid(format!("term '{}': weight must be a number", keyword.term)));
+        }
+        if spec.keywords.is_empty() {
+            return Err(invalid("no keywords".to_string()));
+        }
+
+        let mut patterns = Vec::new();
+        let mut pattern_terms = Vec::new();
+        for (index, keyword) in spec.keywords.iter().enumerate() {
+            for variant in case_variants(&keyword.term) {
+                patterns.push(variant);
+                pattern_terms.push(index);
+            }
+        }
+        let automaton = AhoCorasick::builder()
+            .ascii_case_insensitive(true)
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(&patterns)
+            .map_err(|e| invalid(e.to_string()))?;
+        Ok(Self { spec, automaton, pattern_terms })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    pub fn spec(&self) -> &KeywordProfileSpec {
+        &self.spec
+    }
+
+    /// Weighted whole-word hits of the profile's terms in `data`
+    pub fn relevance(&self, data: &[u8]) -> KeywordRelevance {
+        let mut counts = vec![0u32; self.spec.keywords.len()];
+        for found in self.automaton.find_iter(data) {
+            if word_char_before(data, found.start()) || word_char_at(data, found.end()) {
+                continue;
+            }
+            let count = &mut counts[self.pattern_terms[found.pattern().as_usize()]];
+            *count = (*count + 1).min(self.spec.max_hits);
+        }
+        let mut relevance = KeywordRelevance::default();
+        for (keyword, hits) in self.spec.keywords.iter().zip(counts).filter(|(_, hits)| *hits > 0) {
+            relevance.points += keyword.weight * hits as f32;
+            relevance.hits.push((keyword.term.clone(), hits));
+        }
+        relevance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relevance_counts_weighted_whole_word_hits() {
+        let spec: KeywordProfileSpec = toml::from_str(
+            "name = \"case\"\nmax_hits = 2\n\n[[keywords]]\nterm = \"Иван  Петров\"\nweight = 8.0\n\n[[keywords]]\nterm = \"nightjar\"\n",
+        )
+        .unwrap();
+        let profile = KeywordProfile::from_spec(spec).unwrap();
+        assert_eq!(profile.spec().keywords[0].term, "Иван Петров");
+
+        let text = "ИВАН ПЕТРОВ wrote: NightJar is late. иван петров, Иван Петров и Иван Петровна. nightjars";
+        let relevance = profile.relevance(text.as_bytes());
+        // Three hits of the name capped at 2; "Петровна" and "nightjars" are other words
+        assert_eq!(relevance.hits, vec![("Иван Петров".to_string(), 2), ("nightjar".to_string(), 1)]);
+        assert_eq!(relevance.points, 2.0 * 8.0 + DEFAULT_WEIGHT);
+        assert_eq!(relevance.summary(), "Иван Петров×2, nightjar×1");
+        assert_eq!(profile.relevance(b"nothing here"), KeywordRelevance::default());
+
+        let empty = KeywordProfileSpec { name: "x".to_string(), max_hits: 1, keywords: Vec::new() };
+        assert!(KeywordProfile::from_spec(empty).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Limits on the scan's impact on a live system
+//!
+//! `--max-read-mbps` holds workers before they dispatch a chunk until a token
+//! bucket filled at that rate covers the chunk's bytes. The bucket holds one
+//! second of reads, so a scan that was paused does not burst afterwards;
+//! a chunk larger than the bucket leaves it in debt and the next chunk waits
+//! for it. `--io-nice` puts each worker thread in an I/O scheduling class
+//! (Linux `ioprio_set`, honoured by the BFQ and CFQ schedulers).
+
+use super::ScanControl;
+use std::cell::Cell;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Longest sleep between checks for a cancel
+const THROTTLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+thread_local! {
+    /// The worker thread's I/O priority was set
+    static IO_PRIORITY_SET: Cell<bool> = const { Cell::new(false) };
+}
+/// A failure to set the priority is reported by the first thread only
+static IO_PRIORITY_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Token bucket shared by the workers of one scan
+#[derive(Debug)]
+pub struct ReadThrottle {
+    bytes_per_sec: f64,
+    /// Bytes that may be read now (negative: owed by the last chunk) and when it was refilled
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl ReadThrottle {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1) as f64;
+        Self { bytes_per_sec, bucket: Mutex::new((bytes_per_sec, Instant::now())) }
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec as u64
+    }
+
+    /// Take `bytes` from the bucket; returns how long the caller must wait
+    pub fn reserve(&self, bytes: u64) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let (ref mut tokens, ref mut refilled) = *bucket;
+        let now = Instant::now();
+        *tokens = (*tokens + now.duration_since(*refilled).as_secs_f64() * self.bytes_per_sec).min(self.bytes_per_sec);
+        *refilled = now;
+        *tokens -= bytes as f64;
+        if *tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*tokens / self.bytes_per_sec)
+        }
+    }
+
+    /// Block the calling worker until `bytes` may be read, or the scan is cancelled
+    pub fn acquire(&self, bytes: u64, control: &ScanControl) {
+        let deadline = Instant::now() + self.reserve(bytes);
+        while !control.is_cancelled() {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            std::thread::sleep(left.min(THROTTLE_POLL_INTERVAL));
+        }
+    }
+}
+
+/// Names accepted by `--io-nice`
+pub const IO_PRIORITY_NAMES: &[&str] = &["idle", "best-effort", "best-effort:0-7"];
+
+/// I/O scheduling class of the worker threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoPriority {
+    /// Disk time only when no other process wants it
+    Idle,
+    /// Normal class at a level from 0 (highest) to 7 (lowest)
+    BestEffort(u8),
+}
+
+impl IoPriority {
+    /// `idle`, `best-effort` (level 7) or `best-effort:N`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.split_once(':') {
+            None if name == "idle" => Some(Self::Idle),
+            None if name == "best-effort" => Some(Self::BestEffort(7)),
+            Some(("best-effort", level)) => level.parse().ok().filter(|&level| level <= 7).map(Self::BestEffort),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Self::Idle => "idle".to_string(),
+            Self::BestEffort(level) => format!("best-effort:{}", level),
+        }
+    }
+
+    /// Put the calling thread in this class
+    #[cfg(target_os = "linux")]
+    pub fn apply_to_current_thread(self) -> io::Result<()> {
+        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+        const IOPRIO_CLASS_SHIFT: u32 = 13;
+        let value = match self {
+            Self::Idle => 3 << IOPRIO_CLASS_SHIFT,
+            Self::BestEffort(level) => (2 << IOPRIO_CLASS_SHIFT) | level as libc::c_long,
+        };
+        // Who 0 is the calling thread
+        let result = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, value) };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply_to_current_thread(self) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "I/O priorities are only set on Linux"))
+    }
+}
+
+/// Put the calling worker thread in `priority` unless it already is
+pub fn apply_once(priority: IoPriority) {
+    if IO_PRIORITY_SET.with(|set| set.replace(true)) {
+        return;
+    }
+    if let Err(e) = priority.apply_to_current_thread() {
+        if !IO_PRIORITY_WARNED.swap(true, Ordering::Relaxed) {
+            warn!("--io-nice {}: {}; worker threads keep their I/O priority", priority.name(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_throttle_and_io_priority() {
+        let throttle = ReadThrottle::new(1 << 20);
+        // One second of reads is available at once, then the bucket is in debt
+        assert_eq!(throttle.reserve(1 << 20), Duration::ZERO);
+        let wait = throttle.reserve(512 << 10);
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500), "{:?}", wait);
+
+        // A cancelled scan does not wait
+        let control = ScanControl::new();
+        control.cancel();
+        let started = Instant::now();
+        throttle.acquire(64 << 20, &control);
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        assert_eq!(IoPriority::parse("idle"), Some(IoPriority::Idle));
+        assert_eq!(IoPriority::parse("best-effort"), Some(IoPriority::BestEffort(7)));
+        assert_eq!(IoPriority::parse("best-effort:2"), Some(IoPriority::BestEffort(2)));
+        assert_eq!(IoPriority::parse("best-effort:8"), None);
+        assert_eq!(IoPriority::parse("realtime"), None);
+        assert_eq!(IoPriority::BestEffort(4).name(), "best-effort:4");
+    }
+}
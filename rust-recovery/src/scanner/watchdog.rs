@@ -0,0 +1,161 @@
+//! Watchdog for stalled chunk workers
+//!
+//! Every chunk is registered while a worker processes it. A background thread
+//! checks the registrations and, when a chunk makes no progress within the
+//! timeout (pathological regex input, hung device I/O), logs it and raises the
+//! chunk's cancel flag. Workers poll the flag and abandon the chunk; the scanner
+//! then requeues it on the scalar path with smaller windows.
+//!
+//! Rayon tasks cannot be killed: a worker blocked inside a single regex call or
+//! `pread` only notices the flag once that call returns.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Upper bound for the watchdog polling period
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct InFlight {
+    started: Instant,
+    cancel: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Default)]
+struct WatchdogState {
+    in_flight: Mutex<HashMap<u64, InFlight>>,
+    stop: AtomicBool,
+}
+
+/// Background monitor for in-flight chunks
+#[derive(Debug)]
+pub struct Watchdog {
+    timeout: Duration,
+    state: Arc<WatchdogState>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Start the monitor thread; chunks running longer than `timeout` are cancelled
+    pub fn start(timeout: Duration) -> Self {
+        let state = Arc::new(WatchdogState::default());
+        let poll = (timeout / 4).clamp(Duration::from_millis(10), MAX_POLL_INTERVAL);
+
+        let thread_state = Arc::clone(&state);
+        let handle = std::thread::Builder::new()
+            .name("scan-watchdog".to_string())
+            .spawn(move || {
+                while !thread_state.stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(poll);
+                    let in_flight = thread_state.in_flight.lock().unwrap();
+                    for (offset, entry) in in_flight.iter() {
+                        let elapsed = entry.started.elapsed();
+                        if elapsed >= timeout && !entry.cancel.swap(true, Ordering::Relaxed) {
+                            tracing::warn!(
+                                offset = *offset,
+                                "Worker stalled on chunk for {:.0}s, abandoning",
+                                elapsed.as_secs_f64()
+                            );
+                        }
+                    }
+                }
+            })
+            .ok();
+
+        Self { timeout, state, handle }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Register a chunk for the lifetime of the returned guard
+    pub fn watch(&self, offset: u64) -> WatchGuard<'_> {
+        self.watch_with_flag(offset, Arc::new(AtomicBool::new(false)))
+    }
+
+    /// Register a chunk whose cancel flag is shared with another canceller
+    pub fn watch_with_flag(&self, offset: u64, cancel: Arc<AtomicBool>) -> WatchGuard<'_> {
+        let started = Instant::now();
+        self.state.in_flight.lock().unwrap().insert(
+            offset,
+            InFlight {
+                started,
+                cancel: Arc::clone(&cancel),
+            },
+        );
+
+        WatchGuard {
+            state: &self.state,
+            offset,
+            started,
+            cancel,
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Registration of one chunk; unregisters on drop
+#[derive(Debug)]
+pub struct WatchGuard<'a> {
+    state: &'a WatchdogState,
+    offset: u64,
+    started: Instant,
+    cancel: Arc<AtomicBool>,
+}
+
+impl WatchGuard<'_> {
+    /// Flag the worker must poll; set by the watchdog on stall
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl Drop for WatchGuard<'_> {
+    fn drop(&mut self) {
+        self.state.in_flight.lock().unwrap().remove(&self.offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_chunk_is_cancelled() {
+        let watchdog = Watchdog::start(Duration::from_millis(20));
+
+        let stalled = watchdog.watch(0x1000);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !stalled.is_cancelled() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(stalled.is_cancelled());
+        drop(stalled);
+
+        let fresh = watchdog.watch(0x2000);
+        assert!(!fresh.is_cancelled());
+        assert!(watchdog.state.in_flight.lock().unwrap().contains_key(&0x2000));
+        drop(fresh);
+        assert!(watchdog.state.in_flight.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+//! Preview server for a finished run (`--serve`, `rust-recovery serve`)
+//!
+//! Serves the output directory read-only over HTTP so the results can be
+//! browsed from another machine before they are copied off the bench: `/`
+//! redirects to the newest HTML report, whose file names link to the
+//! recovered files, and every directory gets a plain listing. Only GET and
+//! HEAD are answered; paths are resolved inside the output directory and
+//! anything that leaves it (`..`, symlinks pointing out) is refused.
+
+use crate::error::{RecoveryError, Result};
+use std::fs::File;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tracing::{info, warn};
+
+/// Reports directory of a run, relative to the output directory
+const REPORTS_DIR: &str = "reports";
+
+/// Serve `root` on `addr` until Ctrl-C
+pub fn serve(root: &Path, addr: &str) -> Result<()> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| RecoveryError::Config(format!("Cannot serve {}: {}", root.display(), e)))?;
+    let server = Server::http(addr).map_err(|e| RecoveryError::Config(format!("Cannot listen on {}: {}", addr, e)))?;
+    let server = Arc::new(server);
+    // Ctrl-C ends the request loop; a scan before may have taken over SIGINT
+    let stopper = Arc::clone(&server);
+    std::thread::spawn(move || {
+        let Ok(rt) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+        if rt.block_on(tokio::signal::ctrl_c()).is_ok() {
+            stopper.unblock();
+        }
+    });
+    // stderr: after a scan, the RESULT line stays the last line on stdout
+    eprintln!("Serving {} on http://{}/ (Ctrl-C stops)", root.display(), addr);
+    for request in server.incoming_requests() {
+        let url = request.url().to_string();
+        let status = respond(&root, request);
+        info!("{} {}", status, url);
+    }
+    Ok(())
+}
+
+/// Answer one request; returns the status code sent
+fn respond(root: &Path, request: Request) -> u16 {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        return send(request, text_response(405, "Method not allowed"));
+    }
+    let url_path = request.url().split(['?', '#']).next().unwrap_or("/").to_string();
+    if url_path == "/" {
+        if let Some(report) = newest_report(root) {
+            let location = format!("/{}/{}", REPORTS_DIR, encode_segment(&report));
+            let response = Response::empty(302).with_header(header("Location", &location));
+            return send(request, response);
+        }
+    }
+    let Some(path) = resolve(root, &url_path) else {
+        return send(request, text_response(404, "Not found"));
+    };
+    if path.is_dir() {
+        if !url_path.ends_with('/') {
+            let response = Response::empty(301).with_header(header("Location", &format!("{}/", url_path)));
+            return send(request, response);
+        }
+        let body = listing(&path, &url_path);
+        let response = Response::from_string(body).with_header(header("Content-Type", "text/html; charset=utf-8"));
+        return send(request, response);
+    }
+    match File::open(&path) {
+        Ok(file) => {
+            let response = Response::from_file(file).with_header(header("Content-Type", content_type(&path)));
+            send(request, response)
+        }
+        Err(_) => send(request, text_response(404, "Not found")),
+    }
+}
+
+fn send<R: std::io::Read>(request: Request, response: Response<R>) -> u16 {
+    let status = response.status_code().0;
+    if let Err(e) = request.respond(response) {
+        warn!("Preview response failed: {}", e);
+    }
+    status
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn text_response(status: u16, text: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(text).with_status_code(StatusCode(status))
+}
+
+/// File under `root` named by the URL path; None for anything outside it
+fn resolve(root: &Path, url_path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(url_path)?;
+    let mut path = root.to_path_buf();
+    for component in Path::new(decoded.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    // Symlinks are followed only while they stay inside the root
+    let path = path.canonicalize().ok()?;
+    path.starts_with(root).then_some(path)
+}
+
+/// Decode `%XX` escapes; None for malformed escapes or invalid UTF-8
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// One path segment, percent-encoded for a URL
+fn encode_segment(segment: &str) -> String {
+    crate::report::encode_url_path(segment).replace('/', "%2F")
+}
+
+/// Newest `reports/*.html` by name (they carry a timestamp)
+fn newest_report(root: &Path) -> Option<String> {
+    std::fs::read_dir(root.join(REPORTS_DIR))
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".html"))
+        .max()
+}
+
+/// HTML listing of `dir`, directories first
+fn listing(dir: &Path, url_path: &str) -> String {
+    let mut entries: Vec<(bool, String)> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| {
+                    let entry = entry.ok()?;
+                    Some((!entry.path().is_dir(), entry.file_name().to_string_lossy().into_owned()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort();
+    let title = escape_html(&percent_decode(url_path).unwrap_or_else(|| url_path.to_string()));
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head><body>\n<h1>{0}</h1>\n<ul>\n",
+        title
+    );
+    if url_path != "/" {
+        html.push_str("<li><a href=\"../\">../</a></li>\n");
+    }
+    for (is_file, name) in entries {
+        let suffix = if is_file { "" } else { "/" };
+        html.push_str(&format!(
+            "<li><a href=\"{}{}\">{}{}</a></li>\n",
+            encode_segment(&name),
+            suffix,
+            escape_html(&name),
+            suffix
+        ));
+    }
+    html.push_str("</ul>\n</body></html>\n");
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Content type by extension; unknown files download as bytes
+fn content_type(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "txt" | "md" | "log" | "body" | "yaml" | "csv" => "text/plain; charset=utf-8",
+        "json" | "jsonl" => "application/json; charset=utf-8",
+        "xml" | "dfxml" => "application/xml; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "pdf" => "application/pdf",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_stay_inside_the_output_directory() {
+        let root = std::env::temp_dir().join(format!("rust_recovery_serve_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("01_RECOVERED_FILES")).unwrap();
+        std::fs::write(root.join("01_RECOVERED_FILES/Рецепт борща.txt"), b"x").unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let file = resolve(&root, "/01_RECOVERED_FILES/%D0%A0%D0%B5%D1%86%D0%B5%D0%BF%D1%82%20%D0%B1%D0%BE%D1%80%D1%89%D0%B0.txt");
+        assert_eq!(file, Some(root.join("01_RECOVERED_FILES/Рецепт борща.txt")));
+        assert_eq!(resolve(&root, "/"), Some(root.clone()));
+        assert_eq!(resolve(&root, "/../etc/passwd"), None);
+        assert_eq!(resolve(&root, "/01_RECOVERED_FILES/%2E%2E/%2E%2E/etc"), None);
+        assert_eq!(resolve(&root, "/missing.txt"), None);
+        assert_eq!(percent_decode("%zz"), None);
+
+        let html = listing(&root.join("01_RECOVERED_FILES"), "/01_RECOVERED_FILES/");
+        assert!(html.contains("<a href=\"%D0%A0%D0%B5%D1%86%D0%B5%D0%BF%D1%82%20%D0%B1%D0%BE%D1%80%D1%89%D0%B0.txt\">"));
+        assert_eq!(content_type(Path::new("a/recovered_0001.JSON")), "application/json; charset=utf-8");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,287 @@
+//! Session record (`session.yaml` in the output directory)
+//!
+//! Written when a scan starts: the tool version and git commit it was built
+//! from, the command line, the effective scan configuration and the machine
+//! it ran on (OS and kernel, CPU model and SIMD features, NUMA nodes, memory),
+//! so a run can be reproduced and a slow one compared with a fast one later.
+//! Fields that cannot be read on this platform are left out.
+
+use crate::numa::{format_cpu_list, NumaTopology};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// File name in the output directory
+pub const SESSION_FILE: &str = "session.yaml";
+
+/// Commit the binary was built from (`build.rs`)
+pub const GIT_HASH: &str = env!("RUST_RECOVERY_GIT_HASH");
+
+/// Build of the running binary
+#[derive(Debug, Serialize)]
+pub struct ToolInfo {
+    pub name: &'static str,
+    pub crate_version: &'static str,
+    /// Version recorded in reports and provenance hashes
+    pub tool_version: &'static str,
+    pub git_hash: &'static str,
+    pub debug_build: bool,
+    pub features: Vec<&'static str>,
+}
+
+impl ToolInfo {
+    pub fn current() -> Self {
+        let mut features = Vec::new();
+        if cfg!(feature = "io-uring") {
+            features.push("io-uring");
+        }
+        if cfg!(feature = "gpu") {
+            features.push("gpu");
+        }
+        Self {
+            name: env!("CARGO_PKG_NAME"),
+            crate_version: env!("CARGO_PKG_VERSION"),
+            tool_version: crate::recovery::TOOL_VERSION,
+            git_hash: GIT_HASH,
+            debug_build: cfg!(debug_assertions),
+            features,
+        }
+    }
+}
+
+/// One NUMA node
+#[derive(Debug, Serialize)]
+pub struct NumaNodeInfo {
+    pub id: usize,
+    /// CPU list in sysfs notation ("0-3,8-11")
+    pub cpus: String,
+    pub memory_mb: u64,
+}
+
+/// Machine the scan ran on
+#[derive(Debug, Default, Serialize)]
+pub struct Environment {
+    pub os: &'static str,
+    pub arch: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_release: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_model: Option<String>,
+    pub logical_cpus: usize,
+    /// SIMD features the search and entropy code can dispatch to
+    pub cpu_features: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_total_mb: Option<u64>,
+    pub numa_nodes: Vec<NumaNodeInfo>,
+}
+
+/// First line of a text file under /proc or /etc, trimmed
+fn read_line(path: &str) -> Option<String> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let line = text.lines().next()?.trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+/// Value of `key` in a `key: value` or `key=value` listing (cpuinfo, meminfo, os-release)
+fn lookup(text: &str, key: &str, separator: char) -> Option<String> {
+    text.lines().find_map(|line| {
+        let (name, value) = line.split_once(separator)?;
+        (name.trim() == key).then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn cpu_features() -> Vec<&'static str> {
+    #[allow(unused_mut)]
+    let mut features = Vec::new();
+    #[cfg(target_arch = "x86_64")]
+    {
+        macro_rules! detect {
+            ($($feature:tt),*) => {
+                $(if is_x86_feature_detected!($feature) {
+                    features.push($feature);
+                })*
+            };
+        }
+        detect!("sse2", "sse4.2", "popcnt", "avx", "avx2", "bmi2", "avx512f", "avx512bw");
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            features.push("neon");
+        }
+    }
+    features
+}
+
+impl Environment {
+    pub fn capture() -> Self {
+        let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+        let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+        let os_release = std::fs::read_to_string("/etc/os-release").unwrap_or_default();
+        let numa_nodes = NumaTopology::detect()
+            .map(|topology| {
+                topology
+                    .nodes
+                    .iter()
+                    .map(|node| NumaNodeInfo {
+                        id: node.node_id,
+                        cpus: format_cpu_list(&node.cpu_cores),
+                        memory_mb: node.memory_size_mb,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            os_release: lookup(&os_release, "PRETTY_NAME", '='),
+            kernel: read_line("/proc/sys/kernel/osrelease"),
+            hostname: read_line("/proc/sys/kernel/hostname"),
+            // x86 names the model, ARM only the implementer/part codes
+            cpu_model: lookup(&cpuinfo, "model name", ':').or_else(|| lookup(&cpuinfo, "Hardware", ':')),
+            logical_cpus: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            cpu_features: cpu_features(),
+            memory_total_mb: lookup(&meminfo, "MemTotal", ':')
+                .and_then(|value| value.trim_end_matches("kB").trim().parse::<u64>().ok())
+                .map(|kb| kb / 1024),
+            numa_nodes,
+        }
+    }
+}
+
+/// Everything recorded in `session.yaml`
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub tool: ToolInfo,
+    pub start_time: String,
+    pub command_line: Vec<String>,
+    pub image: BTreeMap<String, Value>,
+    /// Parsed command-line arguments, defaults included
+    pub arguments: Value,
+    /// Scanner settings derived from the arguments
+    pub scan: Value,
+    /// Parameters in every provenance hash of the run
+    pub provenance_parameters: BTreeMap<String, String>,
+    pub environment: Environment,
+}
+
+/// Top-level sections in file order (JSON values sort their keys)
+const SECTION_ORDER: &[&str] =
+    &["tool", "start_time", "command_line", "image", "arguments", "scan", "provenance_parameters", "environment"];
+
+impl SessionInfo {
+    pub fn to_yaml(&self) -> String {
+        let mut yaml = String::from("# rust-recovery session\n");
+        let Ok(Value::Object(mut sections)) = serde_json::to_value(self) else {
+            return yaml;
+        };
+        for key in SECTION_ORDER {
+            if let Some((key, value)) = sections.remove_entry(*key) {
+                write_yaml(&Value::Object([(key, value)].into_iter().collect()), 0, &mut yaml);
+            }
+        }
+        yaml
+    }
+}
+
+/// Scalar or empty collection, written on the key's line
+fn yaml_scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some("null".to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        // JSON string escapes are valid in YAML double-quoted scalars
+        Value::String(s) => Some(serde_json::to_string(s).unwrap_or_default()),
+        Value::Array(items) if items.is_empty() => Some("[]".to_string()),
+        Value::Object(map) if map.is_empty() => Some("{}".to_string()),
+        _ => None,
+    }
+}
+
+fn yaml_key(key: &str) -> String {
+    let plain = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if plain { key.to_string() } else { serde_json::to_string(key).unwrap_or_default() }
+}
+
+/// Block-style YAML of a JSON value
+fn write_yaml(value: &Value, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, item) in map {
+                match yaml_scalar(item) {
+                    Some(scalar) => out.push_str(&format!("{}{}: {}\n", pad, yaml_key(key), scalar)),
+                    None => {
+                        out.push_str(&format!("{}{}:\n", pad, yaml_key(key)));
+                        // Sequences sit at the key's indent, maps one level in
+                        let nested = if item.is_array() { indent } else { indent + 2 };
+                        write_yaml(item, nested, out);
+                    }
+                }
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for item in items {
+                match yaml_scalar(item) {
+                    Some(scalar) => out.push_str(&format!("{}- {}\n", pad, scalar)),
+                    None => {
+                        // The first line of the nested block shares the "- " line
+                        let mut nested = String::new();
+                        write_yaml(item, indent + 2, &mut nested);
+                        out.push_str(&format!("{}- {}", pad, &nested[indent + 2..]));
+                    }
+                }
+            }
+        }
+        scalar => out.push_str(&format!("{}{}\n", pad, yaml_scalar(scalar).unwrap_or_default())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_session_yaml() {
+        let mut yaml = String::new();
+        let value = json!({
+            "name": "disk \"1\".img",
+            "size": 4096,
+            "keywords": ["рецепт", "a: b"],
+            "empty": [],
+            "nodes": [{"id": 0, "cpus": "0-3"}, {"id": 1, "cpus": "4-7"}],
+            "scan": {"reverse": false, "stall": null},
+            "key with space": true
+        });
+        write_yaml(&value, 0, &mut yaml);
+        assert_eq!(
+            yaml,
+            concat!(
+                "empty: []\n",
+                "\"key with space\": true\n",
+                "keywords:\n",
+                "- \"рецепт\"\n",
+                "- \"a: b\"\n",
+                "name: \"disk \\\"1\\\".img\"\n",
+                "nodes:\n",
+                "- cpus: \"0-3\"\n",
+                "  id: 0\n",
+                "- cpus: \"4-7\"\n",
+                "  id: 1\n",
+                "scan:\n",
+                "  reverse: false\n",
+                "  stall: null\n",
+                "size: 4096\n",
+            )
+        );
+
+        let environment = Environment::capture();
+        assert!(environment.logical_cpus >= 1);
+        assert!(!ToolInfo::current().git_hash.is_empty());
+    }
+}
@@ -1,3 +1,19 @@
+//! Content features of fragments, and their grouping before assembly
+//!
+//! `--semantic-scan` groups the fragments of one flush with
+//! `FragmentClusterer` and the stream solver assembles each group on its own,
+//! so fragments of unrelated files are never chained. Two fragments are
+//! linked when their similarity, scaled by their distance on the image,
+//! reaches the threshold: word Jaccard when both are text, the cosine of
+//! their byte frequencies otherwise, or the overlap of their links when most
+//! are shared. Clusters are the connected components of those links.
+
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+/// Bytes read from the start of a fragment to cluster it
+pub const CLUSTER_SAMPLE_BYTES: usize = 64 * 1024;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct ByteFrequency {
     pub values: [f32; 256],
@@ -65,10 +81,203 @@ impl SmartSeparation {
     }
 }
 
+/// Linking settings of `FragmentClusterer`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClusterConfig {
+    /// Least distance-scaled similarity that links two fragments (0.0 - 1.0)
+    pub similarity_threshold: f32,
+    /// k in exp(-k * distance / 100 MB)
+    pub distance_decay: f32,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self { similarity_threshold: 0.75, distance_decay: 10.0 }
+    }
+}
+
+impl ClusterConfig {
+    /// Distance beyond which the decay alone keeps fragments apart
+    fn max_distance(&self) -> u64 {
+        if self.distance_decay <= 0.0 || self.similarity_threshold <= 0.0 {
+            return u64::MAX;
+        }
+        let mb = -self.similarity_threshold.min(1.0).ln() * 100.0 / self.distance_decay;
+        (mb as f64 * (1024.0 * 1024.0)) as u64
+    }
+
+    fn distance_factor(&self, distance: u64) -> f32 {
+        let mb = distance as f32 / (1024.0 * 1024.0);
+        (-self.distance_decay * mb / 100.0).exp()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ClusterFragment {
+    offset: u64,
+    features: ByteFrequency,
+    links: HashSet<String>,
+    /// Words of a mostly-text fragment
+    words: Option<HashSet<String>>,
+}
+
+/// Groups fragments by content and image distance
+#[derive(Debug, Clone, Default)]
+pub struct FragmentClusterer {
+    fragments: Vec<ClusterFragment>,
+    config: ClusterConfig,
+}
+
+impl FragmentClusterer {
+    pub fn new(config: ClusterConfig) -> Self {
+        Self { fragments: Vec::new(), config }
+    }
+
+    /// Add the fragment at `offset` from (a sample of) its bytes; its index is the number added before it
+    pub fn add_fragment<I>(&mut self, offset: u64, data: &[u8], links: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.fragments.push(ClusterFragment {
+            offset,
+            features: ByteFrequency::from_bytes(data),
+            links: links.into_iter().collect(),
+            words: extract_words(data),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.fragments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fragments.is_empty()
+    }
+
+    /// Byte frequencies of fragment `index`
+    pub fn features(&self, index: usize) -> &ByteFrequency {
+        &self.fragments[index].features
+    }
+
+    /// Similarity of fragments `a` and `b` scaled by their distance
+    pub fn affinity(&self, a: usize, b: usize) -> f32 {
+        let (left, right) = (&self.fragments[a], &self.fragments[b]);
+        let factor = self.config.distance_factor(left.offset.abs_diff(right.offset));
+        let content = match (&left.words, &right.words) {
+            (Some(left_words), Some(right_words)) => jaccard(left_words, right_words),
+            _ => left.features.cosine_similarity(&right.features),
+        };
+        let links = jaccard(&left.links, &right.links);
+        let similarity = if links > 0.5 { links.max(content) } else { content };
+        similarity * factor
+    }
+
+    /// Fragment indices of each cluster, in offset order; clusters are
+    /// ordered by their first fragment
+    pub fn cluster(&self) -> Vec<Vec<usize>> {
+        let count = self.fragments.len();
+        let mut order: Vec<usize> = (0..count).collect();
+        order.sort_by_key(|&index| self.fragments[index].offset);
+        let max_distance = self.config.max_distance();
+
+        // Only fragments within the decay distance are compared
+        let edges: Vec<(usize, usize)> = (0..count)
+            .into_par_iter()
+            .flat_map_iter(|i| {
+                let left = order[i];
+                let start = self.fragments[left].offset;
+                order[i + 1..]
+                    .iter()
+                    .take_while(move |&&right| self.fragments[right].offset - start <= max_distance)
+                    .filter(move |&&right| self.affinity(left, right) >= self.config.similarity_threshold)
+                    .map(move |&right| (left, right))
+            })
+            .collect();
+
+        let mut parent: Vec<usize> = (0..count).collect();
+        fn root(parent: &mut [usize], mut index: usize) -> usize {
+            while parent[index] != index {
+                parent[index] = parent[parent[index]];
+                index = parent[index];
+            }
+            index
+        }
+        for (left, right) in edges {
+            let (left, right) = (root(&mut parent, left), root(&mut parent, right));
+            parent[left.max(right)] = left.min(right);
+        }
+
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        let mut cluster_of = vec![usize::MAX; count];
+        for index in order {
+            let root = root(&mut parent, index);
+            if cluster_of[root] == usize::MAX {
+                cluster_of[root] = clusters.len();
+                clusters.push(Vec::new());
+            }
+            clusters[cluster_of[root]].push(index);
+        }
+        clusters
+    }
+}
+
+/// Lowercase words of more than 3 letters (Cyrillic too); None for mostly binary data
+fn extract_words(data: &[u8]) -> Option<HashSet<String>> {
+    let text = String::from_utf8_lossy(data);
+    let chars = text.chars().count();
+    let printable = text
+        .chars()
+        .filter(|&c| c != char::REPLACEMENT_CHARACTER && (!c.is_control() || c.is_whitespace()))
+        .count();
+    if printable < chars / 2 {
+        return None;
+    }
+    let words: HashSet<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 3 && word.chars().all(char::is_alphabetic))
+        .map(str::to_lowercase)
+        .collect();
+    (!words.is_empty()).then_some(words)
+}
+
+fn jaccard(left: &HashSet<String>, right: &HashSet<String>) -> f32 {
+    let union = left.union(right).count();
+    if union == 0 {
+        0.0
+    } else {
+        left.intersection(right).count() as f32 / union as f32
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_fragment_clusterer() {
+        let mut clusterer = FragmentClusterer::new(ClusterConfig::default());
+        let playlist = "youtube playlist watch video channel music";
+        let recipe = "рецепт торт мука сахар масло духовка";
+        clusterer.add_fragment(0, playlist.as_bytes(), Vec::new());
+        clusterer.add_fragment(8192, recipe.as_bytes(), Vec::new());
+        clusterer.add_fragment(4096, format!("{} watch", playlist).as_bytes(), Vec::new());
+        clusterer.add_fragment(12288, recipe.as_bytes(), Vec::new());
+        // Same text, but 1 GB away
+        clusterer.add_fragment(1 << 30, playlist.as_bytes(), Vec::new());
+        // Binary fragments fall back to byte frequencies
+        clusterer.add_fragment(16384, &[0xAB; 64], Vec::new());
+        clusterer.add_fragment(20480, &[0xAB, 0xAB, 0xAB, 0x01], Vec::new());
+        assert_eq!(clusterer.cluster(), vec![vec![0, 2], vec![1, 3], vec![5, 6], vec![4]]);
+
+        // Mostly shared links link fragments whatever their text
+        let mut clusterer = FragmentClusterer::new(ClusterConfig::default());
+        let links = || vec!["https://youtu.be/a".to_string(), "https://youtu.be/b".to_string()];
+        clusterer.add_fragment(0, playlist.as_bytes(), links());
+        clusterer.add_fragment(4096, recipe.as_bytes(), links());
+        assert!((clusterer.affinity(0, 1) - 1.0).abs() < 1e-3);
+        assert_eq!(clusterer.cluster(), vec![vec![0, 1]]);
+    }
+
     #[test]
     fn test_feature_vector_normalization() {
         let vector = ByteFrequency::from_bytes(&[0, 0, 1, 1]);
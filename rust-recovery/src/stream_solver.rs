@@ -1,6 +1,12 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
-use crate::types::{AssembledStream, StreamFragment, StreamScoringWeights};
+use rayon::prelude::*;
+
+use crate::types::{AssembledStream, Size, StreamFragment, StreamScoringWeights};
+
+/// Undersized paths dropped per stream the caller asked for before the
+/// solver gives up (each try is a full pass over the fragments)
+const MAX_UNDERSIZED_PER_STREAM: usize = 4;
 
 #[derive(Debug)]
 struct PathResult {
@@ -18,22 +24,51 @@ pub fn assemble_streams_with_weights(
     weights: &StreamScoringWeights,
     max_streams: Option<usize>,
 ) -> Vec<AssembledStream> {
+    assemble_streams_sized(fragments, weights, max_streams).streams
+}
+
+/// Streams within the size range of `weights`, and the paths dropped as too small
+#[derive(Debug, Default)]
+pub struct StreamAssembly {
+    pub streams: Vec<AssembledStream>,
+    /// Best paths below `min_stream_size`; their fragments are used up
+    pub undersized: Vec<AssembledStream>,
+    /// Fragments cut at `max_stream_size` before assembly
+    pub split_fragments: usize,
+}
+
+pub fn assemble_streams_sized(
+    fragments: &[StreamFragment],
+    weights: &StreamScoringWeights,
+    max_streams: Option<usize>,
+) -> StreamAssembly {
+    let mut assembly = StreamAssembly::default();
     if fragments.is_empty() {
-        return Vec::new();
+        return assembly;
     }
 
-    let mut remaining: Vec<StreamFragment> = fragments.to_vec();
-    let mut streams = Vec::new();
+    let mut pieces: Vec<StreamFragment> = Vec::with_capacity(fragments.len());
+    for fragment in fragments {
+        let split = split_fragment(fragment, weights.max_stream_size);
+        assembly.split_fragments += usize::from(split.len() > 1);
+        pieces.extend(split);
+    }
+    pieces.sort_by_key(|fragment| fragment.offset);
+    let link_sets: Vec<HashSet<String>> = pieces
+        .par_iter()
+        .map(|fragment| fragment.links.iter().cloned().collect())
+        .collect();
+
+    let mut graph = EdgeIndex::new(pieces.len());
     let limit = max_streams.unwrap_or(3).max(1);
 
-    while !remaining.is_empty() && streams.len() < limit {
-        remaining.sort_by_key(|fragment| fragment.offset);
-        let link_sets: Vec<HashSet<String>> = remaining
-            .iter()
-            .map(|fragment| fragment.links.iter().cloned().collect())
-            .collect();
+    while !graph.live.is_empty()
+        && assembly.streams.len() < limit
+        && assembly.undersized.len() < limit * MAX_UNDERSIZED_PER_STREAM
+    {
+        graph.refresh(&pieces, weights, &link_sets);
 
-        let path = match find_best_path(&remaining, weights, &link_sets) {
+        let path = match find_best_path(&pieces, weights, &graph) {
             Some(path) => path,
             None => break,
         };
@@ -42,72 +77,201 @@ pub fn assemble_streams_with_weights(
             break;
         }
 
-        let stream = build_stream(&path, &remaining);
-        streams.push(stream);
+        let stream = build_stream(&path, &pieces);
+        if stream.size_bytes() < weights.min_stream_size {
+            assembly.undersized.push(stream);
+        } else {
+            assembly.streams.push(stream);
+        }
+
+        graph.remove(&path.indices);
+    }
+
+    assembly
+}
+
+/// Candidate edges between the fragments not yet used by a stream.
+/// Indices are positions in the offset-sorted fragment list, so the live
+/// set iterates in disk order; each node keeps its scored incoming edges
+/// until a fragment inside its lookback window is used up
+struct EdgeIndex {
+    live: BTreeSet<usize>,
+    /// Scored edges `(from, score)` into each node, nearest first
+    incoming: Vec<Vec<(usize, f32)>>,
+    /// Lowest index the lookback of each node reached
+    window_start: Vec<usize>,
+    stale: Vec<usize>,
+}
+
+impl EdgeIndex {
+    fn new(count: usize) -> Self {
+        Self {
+            live: (0..count).collect(),
+            incoming: vec![Vec::new(); count],
+            window_start: (0..count).collect(),
+            stale: (0..count).collect(),
+        }
+    }
 
-        let used: HashSet<usize> = path.indices.iter().cloned().collect();
-        remaining = remaining
-            .into_iter()
-            .enumerate()
-            .filter_map(|(idx, fragment)| if used.contains(&idx) { None } else { Some(fragment) })
+    /// Re-score the incoming edges of stale nodes, in parallel
+    fn refresh(
+        &mut self,
+        fragments: &[StreamFragment],
+        weights: &StreamScoringWeights,
+        link_sets: &[HashSet<String>],
+    ) {
+        let live = &self.live;
+        let rescored: Vec<_> = self
+            .stale
+            .par_iter()
+            .map(|&i| {
+                let (window_start, edges) = score_incoming(i, live, fragments, weights, link_sets);
+                (i, window_start, edges)
+            })
             .collect();
+        for (i, window_start, edges) in rescored {
+            self.window_start[i] = window_start;
+            self.incoming[i] = edges;
+        }
+        self.stale.clear();
     }
 
-    streams
+    /// Drop `used` (sorted ascending) and mark the nodes whose window held one
+    fn remove(&mut self, used: &[usize]) {
+        for index in used {
+            self.live.remove(index);
+            self.incoming[*index] = Vec::new();
+        }
+        let first = match used.first() {
+            Some(&first) => first,
+            None => return,
+        };
+        for &i in self.live.range(first + 1..) {
+            let start = self.window_start[i];
+            let hit = match used.binary_search(&start) {
+                Ok(_) => true,
+                Err(at) => used.get(at).is_some_and(|&index| index < i),
+            };
+            if hit {
+                self.stale.push(i);
+            }
+        }
+    }
 }
 
-fn find_best_path(
+/// Scored edges into `i` from up to `max_lookback` live predecessors, and
+/// the lowest index looked at
+fn score_incoming(
+    i: usize,
+    live: &BTreeSet<usize>,
     fragments: &[StreamFragment],
     weights: &StreamScoringWeights,
     link_sets: &[HashSet<String>],
+) -> (usize, Vec<(usize, f32)>) {
+    let mut window_start = i;
+    let mut edges = Vec::new();
+    for &j in live.range(..i).rev().take(weights.max_lookback) {
+        window_start = j;
+        if fragments[i].offset >= fragments[j].end_offset() {
+            let gap = fragments[i].offset.as_u64() - fragments[j].end_offset().as_u64();
+            if gap > weights.max_gap {
+                break;
+            }
+        }
+        if let Some(score) = edge_score(&fragments[j], &fragments[i], weights, &link_sets[j], &link_sets[i]) {
+            edges.push((j, score));
+        }
+    }
+    (window_start, edges)
+}
+
+/// Assemble each cluster of `fragments` (indices, as `FragmentClusterer`
+/// returns them) on its own, so no stream mixes two clusters. Clusters are
+/// taken best fragment score first until `max_streams` streams are built
+pub fn assemble_streams_clustered(
+    fragments: &[StreamFragment],
+    clusters: &[Vec<usize>],
+    weights: &StreamScoringWeights,
+    max_streams: Option<usize>,
+) -> StreamAssembly {
+    let limit = max_streams.unwrap_or(3).max(1);
+    let best_score = |cluster: &Vec<usize>| {
+        cluster.iter().map(|&index| fragments[index].total_score()).fold(f32::MIN, f32::max)
+    };
+    let mut order: Vec<&Vec<usize>> = clusters.iter().filter(|cluster| !cluster.is_empty()).collect();
+    order.sort_by(|a, b| best_score(b).total_cmp(&best_score(a)));
+
+    let mut assembly = StreamAssembly::default();
+    for cluster in order {
+        let left = limit.saturating_sub(assembly.streams.len());
+        if left == 0 {
+            break;
+        }
+        let members: Vec<StreamFragment> = cluster.iter().map(|&index| fragments[index].clone()).collect();
+        let part = assemble_streams_sized(&members, weights, Some(left));
+        assembly.streams.extend(part.streams);
+        assembly.undersized.extend(part.undersized);
+        assembly.split_fragments += part.split_fragments;
+    }
+    assembly
+}
+
+/// `fragment` cut into pieces of at most `max_size` bytes
+pub fn split_fragment(fragment: &StreamFragment, max_size: u64) -> Vec<StreamFragment> {
+    let size = fragment.size.as_u64();
+    if size <= max_size || max_size == 0 {
+        return vec![fragment.clone()];
+    }
+    (0..size)
+        .step_by(max_size.min(usize::MAX as u64) as usize)
+        .map(|start| StreamFragment {
+            offset: fragment.offset.end(Size::new(start)),
+            size: Size::new(max_size.min(size - start)),
+            ..fragment.clone()
+        })
+        .collect()
+}
+
+fn find_best_path(
+    fragments: &[StreamFragment],
+    weights: &StreamScoringWeights,
+    graph: &EdgeIndex,
 ) -> Option<PathResult> {
     let count = fragments.len();
-    if count == 0 {
+    if graph.live.is_empty() {
         return None;
     }
 
-    let mut best_score = vec![0.0; count];
+    let mut best_score = vec![f32::MIN; count];
     let mut previous = vec![None; count];
     let mut edge_score_to = vec![0.0; count];
+    // Bytes of the best path ending at each fragment
+    let mut path_size = vec![0u64; count];
 
-    for i in 0..count {
+    for &i in &graph.live {
         let node_score = fragments[i].total_score();
+        let node_size = fragments[i].size.as_u64();
         best_score[i] = node_score;
-        let mut looked_back = 0usize;
-
-        for j in (0..i).rev() {
-            if looked_back >= weights.max_lookback {
-                break;
-            }
-            looked_back += 1;
+        path_size[i] = node_size;
 
-            if fragments[i].offset >= fragments[j].end_offset() {
-                let gap = fragments[i].offset - fragments[j].end_offset();
-                if gap > weights.max_gap {
-                    break;
-                }
+        for &(j, edge_score) in &graph.incoming[i] {
+            if path_size[j].saturating_add(node_size) > weights.max_stream_size {
+                continue;
             }
-
-            if let Some(edge_score) = edge_score(
-                &fragments[j],
-                &fragments[i],
-                weights,
-                &link_sets[j],
-                &link_sets[i],
-            ) {
-                let candidate = best_score[j] + edge_score + node_score;
-                if candidate > best_score[i] {
-                    best_score[i] = candidate;
-                    previous[i] = Some(j);
-                    edge_score_to[i] = edge_score;
-                }
+            let candidate = best_score[j] + edge_score + node_score;
+            if candidate > best_score[i] {
+                best_score[i] = candidate;
+                previous[i] = Some(j);
+                edge_score_to[i] = edge_score;
+                path_size[i] = path_size[j] + node_size;
             }
         }
     }
 
-    let (best_index, &total_score) = best_score
+    let (best_index, total_score) = graph
+        .live
         .iter()
-        .enumerate()
+        .map(|&index| (index, best_score[index]))
         .max_by(|(_, left), (_, right)| left.partial_cmp(right).unwrap())?;
 
     let mut indices_rev = Vec::new();
@@ -140,8 +304,8 @@ fn edge_score(
     left_links: &HashSet<String>,
     right_links: &HashSet<String>,
 ) -> Option<f32> {
-    let left_end = left.end_offset();
-    let right_start = right.offset;
+    let left_end = left.end_offset().as_u64();
+    let right_start = right.offset.as_u64();
     let (gap, overlap) = if right_start >= left_end {
         (right_start - left_end, 0)
     } else {
@@ -228,11 +392,11 @@ fn build_stream(path: &PathResult, fragments: &[StreamFragment]) -> AssembledStr
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{FragmentScore, StreamFragment, StreamScoringWeights};
+    use crate::types::{FragmentScore, Offset, StreamFragment, StreamScoringWeights};
 
     fn make_fragment(offset: u64, data: &[u8], file_type: &str) -> StreamFragment {
         StreamFragment::from_bytes(
-            offset,
+            Offset::new(offset),
             data,
             file_type,
             10.0,
@@ -245,6 +409,8 @@ mod tests {
                 has_structured_text: true,
                 is_compressed: false,
                 reasons: Vec::new(),
+                scripts: Default::default(),
+                keyword_relevance: 0.0,
             },
         )
     }
@@ -275,4 +441,69 @@ mod tests {
             assert!(stream.fragments.iter().all(|fragment| &fragment.file_type == file_type));
         }
     }
+
+    #[test]
+    fn test_stream_size_range() {
+        let fragments = vec![
+            make_fragment(0, &[b'a'; 100], "json"),
+            make_fragment(100, &[b'a'; 100], "json"),
+            make_fragment(200, &[b'a'; 100], "json"),
+            make_fragment(10_000, &[b'z'; 20], "html"),
+            make_fragment(20_000, &[b'q'; 250], "json"),
+        ];
+        let weights = StreamScoringWeights {
+            min_stream_size: 50,
+            max_stream_size: 150,
+            ..StreamScoringWeights::default()
+        };
+
+        let assembly = assemble_streams_sized(&fragments, &weights, Some(10));
+        // The 250-byte fragment is cut into 150 + 100
+        assert_eq!(assembly.split_fragments, 1);
+        assert!(assembly.streams.iter().all(|stream| (50..=150).contains(&stream.size_bytes())));
+        let assembled: u64 = assembly.streams.iter().map(AssembledStream::size_bytes).sum();
+        assert_eq!(assembled, 550);
+        assert_eq!(assembly.undersized.len(), 1);
+        assert_eq!(assembly.undersized[0].fragments[0].offset, Offset::new(10_000));
+    }
+
+    #[test]
+    fn test_clusters_are_assembled_apart() {
+        let fragments = vec![
+            make_fragment(0, b"aaaaaaaa", "json"),
+            make_fragment(8, b"aaaaaaaa", "json"),
+            make_fragment(16, b"aaaaaaaa", "json"),
+        ];
+        let weights = StreamScoringWeights::default();
+        assert_eq!(assemble_streams_with_weights(&fragments, &weights, Some(3)).len(), 1);
+
+        let assembly = assemble_streams_clustered(&fragments, &[vec![0, 2], vec![1]], &weights, Some(3));
+        let mut offsets: Vec<Vec<u64>> = assembly
+            .streams
+            .iter()
+            .map(|stream| stream.fragments.iter().map(|f| f.offset.as_u64()).collect())
+            .collect();
+        offsets.sort();
+        assert_eq!(offsets, vec![vec![0, 16], vec![8]]);
+        assert_eq!(assemble_streams_clustered(&fragments, &[vec![0, 2], vec![1]], &weights, Some(1)).streams.len(), 1);
+    }
+
+    #[test]
+    fn test_many_fragments() {
+        // Runs of adjacent fragments far apart from each other: one stream each
+        let mut fragments = Vec::new();
+        for run in 0..5u64 {
+            for piece in 0..2_000u64 {
+                fragments.push(make_fragment(run * 100_000_000 + piece * 8, b"aaaaaaaa", "json"));
+            }
+        }
+        let weights = StreamScoringWeights { max_lookback: 16, ..StreamScoringWeights::default() };
+
+        let streams = assemble_streams_with_weights(&fragments, &weights, Some(5));
+        assert_eq!(streams.len(), 5);
+        assert!(streams.iter().all(|stream| stream.fragments.len() == 2_000));
+        let mut starts: Vec<u64> = streams.iter().map(|stream| stream.fragments[0].offset.as_u64()).collect();
+        starts.sort();
+        assert_eq!(starts, vec![0, 100_000_000, 200_000_000, 300_000_000, 400_000_000]);
+    }
 }
@@ -5,14 +5,22 @@
 //!
 //! Hotkeys supported:
 //! - P: Pause/Resume scan
-//! - S: Skip to next chunk
-//! - V: View current fragment
+//! - S: Skip the chunks being scanned
+//! - V: Hex view of the last fragment found (arrows move, N next match, Esc closes)
+//! - F: Table of the fragments found: sort, mark to force-recover or exclude
 //! - C: Save checkpoint
-//! - Q: Quit application
+//! - Q: Quit application (asks whether to cancel the scan or keep it running)
+//! - Ctrl-C: Cancel the scan and quit
+//!
+//! P, S, C, Ctrl-C and the fragment marks are sent to the scan loop as `TuiCommand`s. After a
+//! cancel the dashboard stays up on a shutdown screen until the checkpoint,
+//! the recovered files and the reports are written.
 
+pub mod table;
+pub mod viewer;
 pub mod widgets;
 
-use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind};
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers};
 use crossterm::execute;
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::backend::CrosstermBackend;
@@ -21,7 +29,11 @@ use std::io;
 use tokio::sync::mpsc;
 // use widgets::{DashboardWidget, DiskHeatmapWidget, StatsWidget, LogsWidget}; // Simplified
 
-use crate::types::{Offset, ScanConfig};
+use crate::disk::DiskImage;
+use crate::matcher::EnhancedMatcher;
+use crate::types::{HotFragment, Offset, ScanConfig};
+use table::{FragmentMark, FragmentTable, TableAction};
+use viewer::FragmentView;
 
 /// TUI Application state
 #[derive(Debug, Clone)]
@@ -38,6 +50,8 @@ pub struct TuiApp {
     pub fragments_found: u32,
     /// Number of recovered files
     pub recovered_files: u32,
+    /// Files listed by the metadata-first pass
+    pub metadata_files: u32,
     /// Scan is in reverse mode
     pub is_reverse: bool,
     /// Scan is paused
@@ -60,6 +74,59 @@ pub struct TuiApp {
     pub disk_heatmap: DiskHeatmap,
     /// Scan configuration
     pub scan_config: ScanConfig,
+    /// The Q confirmation dialog is open
+    pub quit_dialog: bool,
+    /// The scan pipeline has finished
+    pub scan_completed: bool,
+    /// Set once the scan was cancelled from the dashboard
+    pub shutdown: Option<ShutdownProgress>,
+    /// Most recent fragment the scan reported
+    pub last_fragment: Option<HotFragment>,
+    /// The V viewer is open on this fragment
+    pub viewer: Option<FragmentView>,
+    /// Every fragment found, with the operator's marks
+    pub fragments: FragmentTable,
+    /// The F table is shown
+    pub table_open: bool,
+}
+
+/// Steps of the shutdown after a cancel: every step but the last is done
+#[derive(Debug, Clone)]
+pub struct ShutdownProgress {
+    pub started: std::time::Instant,
+    pub steps: Vec<String>,
+}
+
+impl ShutdownProgress {
+    fn new() -> Self {
+        Self {
+            started: std::time::Instant::now(),
+            steps: vec!["Stopping workers".to_string()],
+        }
+    }
+}
+
+/// Answer to the Q confirmation dialog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuitChoice {
+    /// Stop the scan; the dashboard shows the shutdown until reports are written
+    CancelScan,
+    /// Close the dashboard, the scan goes on in the terminal
+    KeepScanning,
+    /// Close the dialog
+    Back,
+}
+
+impl QuitChoice {
+    /// Choice for a key pressed while the dialog is open
+    pub fn from_key(code: KeyCode) -> Option<Self> {
+        match code {
+            KeyCode::Char('c') | KeyCode::Char('C') => Some(Self::CancelScan),
+            KeyCode::Char('k') | KeyCode::Char('K') => Some(Self::KeepScanning),
+            KeyCode::Char('b') | KeyCode::Char('B') | KeyCode::Esc => Some(Self::Back),
+            _ => None,
+        }
+    }
 }
 
 /// Top candidate information
@@ -187,6 +254,7 @@ impl TuiApp {
             start_time: std::time::Instant::now(),
             fragments_found: 0,
             recovered_files: 0,
+            metadata_files: 0,
             is_reverse: scan_config.reverse,
             paused: false,
             avg_speed_mbps: 0.0,
@@ -198,6 +266,13 @@ impl TuiApp {
             activity_log: Vec::new(),
             disk_heatmap: DiskHeatmap::new(total_size, image_path, output_dir),
             scan_config,
+            quit_dialog: false,
+            scan_completed: false,
+            shutdown: None,
+            last_fragment: None,
+            viewer: None,
+            fragments: FragmentTable::default(),
+            table_open: false,
         }
     }
 
@@ -257,10 +332,35 @@ impl TuiApp {
         self.recovered_files += 1;
     }
 
+    /// Mark file as listed by filesystem metadata
+    pub fn mark_metadata_file(&mut self, data_offset: Option<u64>) {
+        self.metadata_files += 1;
+        if let Some(offset) = data_offset {
+            self.disk_heatmap.mark_found_data(offset, self.total_size);
+        }
+    }
+
     /// Check if should stop (early exit)
     pub fn should_stop_early(&self) -> bool {
         self.target_files > 0 && self.recovered_files >= self.target_files
     }
+
+    /// Switch to the shutdown screen
+    pub fn begin_shutdown(&mut self) {
+        self.quit_dialog = false;
+        if self.shutdown.is_none() {
+            self.shutdown = Some(ShutdownProgress::new());
+        }
+    }
+
+    /// Start the next shutdown step (the previous one is done); logged
+    /// when the dashboard is not shutting down
+    pub fn shutdown_step(&mut self, step: &str) {
+        match self.shutdown {
+            Some(ref mut shutdown) => shutdown.steps.push(step.to_string()),
+            None => self.add_log(step),
+        }
+    }
 }
 
 /// TUI Event types for communication with main pipeline
@@ -268,24 +368,48 @@ impl TuiApp {
 pub enum TuiEvent {
     /// Update scan position
     UpdatePosition { position: u64, bytes_scanned: u64 },
-    /// Fragment found at offset
-    FragmentFound { offset: u64 },
+    /// Fragment found by the scan
+    FragmentFound { fragment: HotFragment },
     /// File recovered
     FileRecovered { filename: String },
+    /// File listed by the metadata-first pass
+    MetadataFileFound { filename: String, size: u64, data_offset: Option<u64> },
     /// Log message
     LogMessage { message: String },
+    /// The pipeline started a step of winding down (checkpoint, files)
+    ShutdownStep { step: String },
+    /// Streams are being assembled: fragment marks no longer apply
+    AssemblyStarted,
     /// Scan completed
     ScanCompleted,
     /// Error occurred
     Error { message: String },
 }
 
+/// Operator commands sent from the TUI back to the scan loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TuiCommand {
+    Pause,
+    Resume,
+    /// Abandon the chunks being scanned
+    SkipChunk,
+    SaveCheckpoint,
+    /// Stop the scan; the files found so far are still saved
+    Cancel,
+    /// Force-recover or exclude the fragment at `offset` (`None` clears the mark)
+    MarkFragment { offset: Offset, mark: Option<FragmentMark> },
+}
+
 /// TUI Application that handles rendering and input
 pub struct TuiApplication {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     app: TuiApp,
     receiver: mpsc::UnboundedReceiver<TuiEvent>,
+    commands: Option<mpsc::UnboundedSender<TuiCommand>>,
     should_quit: bool,
+    quit_choice: Option<QuitChoice>,
+    /// Image and matcher the V viewer reads fragments with
+    source: Option<(DiskImage, EnhancedMatcher)>,
 }
 
 impl TuiApplication {
@@ -305,11 +429,49 @@ impl TuiApplication {
             terminal,
             app,
             receiver,
+            commands: None,
             should_quit: false,
+            quit_choice: None,
+            source: None,
         })
     }
 
-    /// Run the TUI application
+    /// Send hotkey commands to the scan loop through `commands`
+    pub fn with_commands(mut self, commands: mpsc::UnboundedSender<TuiCommand>) -> Self {
+        self.commands = Some(commands);
+        self
+    }
+
+    /// Let V open fragments of `disk`, highlighting what `matcher` matches
+    pub fn with_viewer(mut self, disk: DiskImage, matcher: EnhancedMatcher) -> Self {
+        self.source = Some((disk, matcher));
+        self
+    }
+
+    fn send_command(&self, command: TuiCommand) {
+        if let Some(ref commands) = self.commands {
+            let _ = commands.send(command);
+        }
+    }
+
+    /// How the dashboard was closed with Q, if it was
+    pub fn quit_choice(&self) -> Option<QuitChoice> {
+        self.quit_choice
+    }
+
+    /// The scan was cancelled here: keep the dashboard until the reports are written
+    pub fn is_shutting_down(&self) -> bool {
+        self.app.shutdown.is_some()
+    }
+
+    /// Show a shutdown step run outside the scan pipeline (report writing)
+    pub fn shutdown_step(&mut self, step: &str) {
+        self.app.shutdown_step(step);
+        let _ = self.draw();
+    }
+
+    /// Run the TUI application. After a cancel it returns once the scan
+    /// pipeline has finished
     pub fn run(&mut self) -> Result<(), io::Error> {
         self.app.add_log("TUI initialized");
         
@@ -333,27 +495,67 @@ impl TuiApplication {
             match event::read()? {
                 Event::Key(key_event) => {
                     if key_event.kind == KeyEventKind::Press {
+                        let ctrl_c = key_event.code == KeyCode::Char('c')
+                            && key_event.modifiers.contains(KeyModifiers::CONTROL);
+                        if self.app.shutdown.is_some() {
+                            // Ctrl-C again: close the dashboard, the shutdown goes on in the terminal
+                            if ctrl_c {
+                                self.app.shutdown = None;
+                                self.should_quit = true;
+                            }
+                            return Ok(());
+                        }
+                        if let Some(ref mut view) = self.app.viewer {
+                            if !ctrl_c {
+                                if !view.handle_key(key_event.code) {
+                                    self.app.viewer = None;
+                                }
+                                return Ok(());
+                            }
+                            self.app.viewer = None;
+                        }
+                        if self.app.table_open && !ctrl_c {
+                            self.table_key(key_event.code);
+                            return Ok(());
+                        }
+                        if self.app.quit_dialog && !ctrl_c {
+                            if let Some(choice) = QuitChoice::from_key(key_event.code) {
+                                self.confirm_quit(choice);
+                            }
+                            return Ok(());
+                        }
                         match key_event.code {
                             KeyCode::Char('p') | KeyCode::Char('P') => {
                                 self.app.paused = !self.app.paused;
                                 let status = if self.app.paused { "PAUSED" } else { "RESUMED" };
                                 self.app.add_log(&format!("Scan {}", status));
+                                self.send_command(if self.app.paused { TuiCommand::Pause } else { TuiCommand::Resume });
                             }
                             KeyCode::Char('s') | KeyCode::Char('S') => {
-                                self.app.add_log("Skip to next chunk requested");
-                                // TODO: Implement skip logic
+                                self.app.add_log("Skip of the current chunks requested");
+                                self.send_command(TuiCommand::SkipChunk);
                             }
                             KeyCode::Char('v') | KeyCode::Char('V') => {
-                                self.app.add_log("View current fragment");
-                                // TODO: Implement view logic
+                                if let Some(fragment) = self.app.last_fragment.clone() {
+                                    self.open_viewer(&fragment);
+                                } else {
+                                    self.app.add_log("No fragment found yet");
+                                }
                             }
+                            KeyCode::Char('f') | KeyCode::Char('F') => self.app.table_open = true,
+                            // Raw mode delivers Ctrl-C as a key instead of SIGINT
+                            KeyCode::Char('c') if ctrl_c => self.confirm_quit(QuitChoice::CancelScan),
                             KeyCode::Char('c') | KeyCode::Char('C') => {
-                                self.app.add_log("Checkpoint saved");
-                                // TODO: Implement checkpoint logic
+                                self.app.add_log("Checkpoint requested");
+                                self.send_command(TuiCommand::SaveCheckpoint);
                             }
                             KeyCode::Char('q') | KeyCode::Char('Q') => {
-                                self.app.add_log("Quit requested");
-                                self.should_quit = true;
+                                // Nothing left to cancel once the scan is done
+                                if self.app.scan_completed {
+                                    self.should_quit = true;
+                                } else {
+                                    self.app.quit_dialog = true;
+                                }
                             }
                             _ => {}
                         }
@@ -366,6 +568,53 @@ impl TuiApplication {
         Ok(())
     }
 
+    fn open_viewer(&mut self, fragment: &HotFragment) {
+        match self.source {
+            Some((ref disk, ref mut matcher)) => self.app.viewer = Some(FragmentView::open(fragment, disk, matcher)),
+            None => self.app.add_log("Fragment viewer unavailable"),
+        }
+    }
+
+    fn table_key(&mut self, code: KeyCode) {
+        match self.app.fragments.handle_key(code) {
+            TableAction::None => {}
+            TableAction::Close => self.app.table_open = false,
+            TableAction::Mark { offset, mark } => {
+                self.send_command(TuiCommand::MarkFragment { offset, mark });
+            }
+            TableAction::Locked => self.app.add_log("Assembly has started, fragment marks no longer apply"),
+            TableAction::View(row) => self.open_viewer(&row.to_fragment()),
+        }
+    }
+
+    fn confirm_quit(&mut self, choice: QuitChoice) {
+        self.app.quit_dialog = false;
+        match choice {
+            QuitChoice::CancelScan => {
+                self.app.add_log("Cancel requested");
+                self.send_command(TuiCommand::Cancel);
+                // Stay up on the shutdown screen until the pipeline is done
+                if self.app.scan_completed {
+                    self.should_quit = true;
+                } else {
+                    self.app.begin_shutdown();
+                }
+            }
+            QuitChoice::KeepScanning => {
+                self.app.add_log("Quit requested");
+                // The scan keeps running after the dashboard closes
+                if self.app.paused {
+                    self.send_command(TuiCommand::Resume);
+                }
+                self.should_quit = true;
+            }
+            QuitChoice::Back => {}
+        }
+        if choice != QuitChoice::Back {
+            self.quit_choice = Some(choice);
+        }
+    }
+
     /// Process incoming events from the pipeline
     fn process_events(&mut self) -> Result<(), io::Error> {
         while let Ok(event) = self.receiver.try_recv() {
@@ -373,26 +622,42 @@ impl TuiApplication {
                 TuiEvent::UpdatePosition { position, bytes_scanned } => {
                     self.app.update_scan_stats(position, bytes_scanned);
                 }
-                TuiEvent::FragmentFound { offset } => {
+                TuiEvent::FragmentFound { fragment } => {
+                    let offset = fragment.offset.as_u64();
                     self.app.mark_fragment_found(offset);
                     self.app.add_log(&format!("Fragment found at 0x{:X}", offset));
+                    self.app.fragments.push(&fragment);
+                    self.app.last_fragment = Some(fragment);
                 }
                 TuiEvent::FileRecovered { filename } => {
                     self.app.mark_file_recovered();
                     self.app.add_log(&format!("File recovered: {}", filename));
                     
-                    if self.app.should_stop_early() {
+                    if self.app.should_stop_early() && self.app.shutdown.is_none() {
                         self.app.add_log("Early exit target reached");
                         self.should_quit = true;
                     }
                 }
+                TuiEvent::MetadataFileFound { filename, size, data_offset } => {
+                    self.app.mark_metadata_file(data_offset);
+                    self.app.add_log(&format!("Metadata: {} ({} KB)", filename, size / 1024));
+                }
                 TuiEvent::LogMessage { message } => {
                     self.app.add_log(&message);
                 }
+                TuiEvent::ShutdownStep { step } => {
+                    self.app.shutdown_step(&step);
+                }
+                TuiEvent::AssemblyStarted => {
+                    self.app.fragments.locked = true;
+                }
                 TuiEvent::ScanCompleted => {
                     self.app.add_log("Scan completed");
-                    // Auto-quit after completion or wait for user?
-                    // self.should_quit = true;
+                    self.app.scan_completed = true;
+                    // Reports are written after run() returns
+                    if self.app.shutdown.is_some() {
+                        self.should_quit = true;
+                    }
                 }
                 TuiEvent::Error { message } => {
                     self.app.add_log(&format!("ERROR: {}", message));
@@ -405,6 +670,23 @@ impl TuiApplication {
 
     /// Draw the TUI
     fn draw(&mut self) -> Result<(), io::Error> {
+        if let Some(ref shutdown) = self.app.shutdown {
+            self.terminal.draw(|f| {
+                let chunks = ratatui::layout::Layout::default()
+                    .direction(ratatui::layout::Direction::Vertical)
+                    .constraints([
+                        ratatui::layout::Constraint::Length(3),  // Header
+                        ratatui::layout::Constraint::Min(8),     // Shutdown steps
+                        ratatui::layout::Constraint::Length(10), // Logs
+                    ].as_ref())
+                    .split(f.size());
+                f.render_widget(crate::tui::widgets::create_dashboard_header(&self.app), chunks[0]);
+                f.render_widget(crate::tui::widgets::ShutdownWidget::render(shutdown), chunks[1]);
+                f.render_widget(crate::tui::widgets::LogsWidget::render(&self.app.activity_log), chunks[2]);
+            })?;
+            return Ok(());
+        }
+
         self.terminal.draw(|f| {
             let chunks = ratatui::layout::Layout::default()
                 .direction(ratatui::layout::Direction::Vertical)
@@ -452,6 +734,33 @@ impl TuiApplication {
 
             // Logs in chunk 3 (footer space, or create new chunk)
             // Let's adjust layout to 4 distinct sections
+
+            if self.app.table_open {
+                use crate::tui::widgets::FragmentTableWidget;
+                let size = f.size();
+                let area = crate::tui::widgets::centered_rect(FragmentTableWidget::WIDTH, size.height.saturating_sub(4), size);
+                let table = &mut self.app.fragments;
+                table.visible_rows = area.height.saturating_sub(FragmentTableWidget::CHROME_HEIGHT).max(1) as usize;
+                table.scroll_to_selected();
+                f.render_widget(ratatui::widgets::Clear, area);
+                f.render_widget(FragmentTableWidget::render(table), area);
+            }
+
+            if let Some(ref mut view) = self.app.viewer {
+                use crate::tui::widgets::FragmentViewerWidget;
+                let size = f.size();
+                let area = crate::tui::widgets::centered_rect(FragmentViewerWidget::WIDTH, size.height.saturating_sub(4), size);
+                view.visible_rows = area.height.saturating_sub(FragmentViewerWidget::CHROME_HEIGHT).max(1) as usize;
+                view.scroll_to_cursor();
+                f.render_widget(ratatui::widgets::Clear, area);
+                f.render_widget(FragmentViewerWidget::render(view), area);
+            }
+
+            if self.app.quit_dialog {
+                let area = crate::tui::widgets::centered_rect(72, 7, f.size());
+                f.render_widget(ratatui::widgets::Clear, area);
+                f.render_widget(crate::tui::widgets::QuitDialog::render(), area);
+            }
         })?;
 
         Ok(())
@@ -469,4 +778,30 @@ impl Drop for TuiApplication {
         );
         let _ = self.terminal.show_cursor();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quit_dialog_keys_and_shutdown_steps() {
+        assert_eq!(QuitChoice::from_key(KeyCode::Char('C')), Some(QuitChoice::CancelScan));
+        assert_eq!(QuitChoice::from_key(KeyCode::Char('k')), Some(QuitChoice::KeepScanning));
+        assert_eq!(QuitChoice::from_key(KeyCode::Esc), Some(QuitChoice::Back));
+        assert_eq!(QuitChoice::from_key(KeyCode::Char('q')), None);
+
+        let mut app = TuiApp::new(1 << 20, "image.dd".to_string(), "out".to_string(), ScanConfig::default());
+        // Before a cancel the pipeline steps only go to the log
+        app.shutdown_step("Saving scan checkpoint");
+        assert!(app.shutdown.is_none());
+        assert_eq!(app.activity_log.last().unwrap().message, "Saving scan checkpoint");
+
+        app.quit_dialog = true;
+        app.begin_shutdown();
+        app.shutdown_step("Saving scan checkpoint");
+        app.shutdown_step("Writing reports");
+        assert!(!app.quit_dialog);
+        let shutdown = app.shutdown.as_ref().unwrap();
+        assert_eq!(shutdown.steps, ["Stopping workers", "Saving scan checkpoint", "Writing reports"]);
+    }
+}
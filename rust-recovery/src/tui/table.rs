@@ -0,0 +1,229 @@
+//! Fragment table (F): the fragments found so far, marked before assembly
+//!
+//! Every fragment the scan reports is added to the table as it arrives,
+//! sorted by score (best first) or by offset. R marks the selected fragment
+//! to be force-recovered: its file is saved even when the stream solver
+//! leaves it out or its size is outside the target range. X excludes it from
+//! assembly. Marks go to the scan loop as `TuiCommand::MarkFragment` and
+//! apply until the assembly stage starts; V opens the hex viewer on the
+//! selected fragment.
+
+use crate::types::{HotFragment, Offset, Size};
+use crossterm::event::KeyCode;
+
+/// Operator decision on a fragment, applied when streams are assembled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FragmentMark {
+    /// Save its file whatever the solver and the size range say
+    ForceRecover,
+    /// Leave it out of assembly
+    Exclude,
+}
+
+impl FragmentMark {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ForceRecover => "force-recover",
+            Self::Exclude => "exclude",
+        }
+    }
+}
+
+/// Order of the table rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FragmentSort {
+    /// Highest score first
+    #[default]
+    Score,
+    Offset,
+}
+
+/// One fragment of the table
+#[derive(Debug, Clone, PartialEq)]
+pub struct FragmentRow {
+    pub offset: Offset,
+    pub size: Size,
+    pub score: f32,
+    pub file_type: String,
+    pub links: usize,
+    pub mark: Option<FragmentMark>,
+}
+
+impl FragmentRow {
+    pub fn from_fragment(fragment: &HotFragment) -> Self {
+        Self {
+            offset: fragment.offset,
+            size: fragment.size,
+            score: fragment.target_score,
+            file_type: fragment.file_type_guess.clone(),
+            links: fragment.youtube_count,
+            mark: None,
+        }
+    }
+
+    /// Fragment the viewer opens for this row
+    pub fn to_fragment(&self) -> HotFragment {
+        let mut fragment = HotFragment::new(self.offset, self.size);
+        fragment.target_score = self.score;
+        fragment.file_type_guess = self.file_type.clone();
+        fragment.youtube_count = self.links;
+        fragment
+    }
+}
+
+/// What a key pressed in the table asks of the dashboard
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableAction {
+    None,
+    Close,
+    /// Send the new mark of the fragment at `offset` to the scan loop
+    Mark { offset: Offset, mark: Option<FragmentMark> },
+    /// Assembly has started, the mark was not changed
+    Locked,
+    /// Open the viewer on this row
+    View(FragmentRow),
+}
+
+/// Rows in the current sort order and the selection
+#[derive(Debug, Clone, Default)]
+pub struct FragmentTable {
+    pub rows: Vec<FragmentRow>,
+    pub sort: FragmentSort,
+    pub selected: usize,
+    /// First row shown
+    pub top_row: usize,
+    /// Rows that fit, set when drawn
+    pub visible_rows: usize,
+    /// Assembly has started: marks no longer change anything
+    pub locked: bool,
+}
+
+impl FragmentTable {
+    /// Insert `fragment` at its place in the sort order; the selection stays on its row
+    pub fn push(&mut self, fragment: &HotFragment) {
+        let row = FragmentRow::from_fragment(fragment);
+        let at = match self.sort {
+            FragmentSort::Score => self.rows.partition_point(|other| other.score >= row.score),
+            FragmentSort::Offset => self.rows.partition_point(|other| other.offset <= row.offset),
+        };
+        if at <= self.selected && !self.rows.is_empty() {
+            self.selected += 1;
+        }
+        self.rows.insert(at, row);
+        self.scroll_to_selected();
+    }
+
+    pub fn marked(&self, mark: FragmentMark) -> usize {
+        self.rows.iter().filter(|row| row.mark == Some(mark)).count()
+    }
+
+    /// Switch between score and offset order, keeping the selected row
+    pub fn toggle_sort(&mut self) {
+        let selected = self.rows.get(self.selected).map(|row| row.offset);
+        self.sort = match self.sort {
+            FragmentSort::Score => FragmentSort::Offset,
+            FragmentSort::Offset => FragmentSort::Score,
+        };
+        match self.sort {
+            FragmentSort::Score => self.rows.sort_by(|a, b| b.score.total_cmp(&a.score)),
+            FragmentSort::Offset => self.rows.sort_by_key(|row| row.offset),
+        }
+        self.selected = selected
+            .and_then(|offset| self.rows.iter().position(|row| row.offset == offset))
+            .unwrap_or(0);
+        self.scroll_to_selected();
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> TableAction {
+        let last = self.rows.len().saturating_sub(1);
+        let page = self.visible_rows.max(1);
+        match code {
+            KeyCode::Esc | KeyCode::Char('f') | KeyCode::Char('F') => return TableAction::Close,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(page),
+            KeyCode::PageDown => self.selected = (self.selected + page).min(last),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = last,
+            KeyCode::Char('s') | KeyCode::Char('S') => self.toggle_sort(),
+            KeyCode::Char('r') | KeyCode::Char('R') => return self.toggle_mark(FragmentMark::ForceRecover),
+            KeyCode::Char('x') | KeyCode::Char('X') => return self.toggle_mark(FragmentMark::Exclude),
+            KeyCode::Char('v') | KeyCode::Char('V') => {
+                if let Some(row) = self.rows.get(self.selected) {
+                    return TableAction::View(row.clone());
+                }
+            }
+            _ => {}
+        }
+        self.scroll_to_selected();
+        TableAction::None
+    }
+
+    /// Set `mark` on the selected row, or clear it if already set
+    fn toggle_mark(&mut self, mark: FragmentMark) -> TableAction {
+        if self.locked {
+            return TableAction::Locked;
+        }
+        let Some(row) = self.rows.get_mut(self.selected) else {
+            return TableAction::None;
+        };
+        row.mark = if row.mark == Some(mark) { None } else { Some(mark) };
+        TableAction::Mark { offset: row.offset, mark: row.mark }
+    }
+
+    /// Scroll so that the selected row is shown
+    pub fn scroll_to_selected(&mut self) {
+        let rows = self.visible_rows.max(1);
+        if self.selected < self.top_row {
+            self.top_row = self.selected;
+        } else if self.selected >= self.top_row + rows {
+            self.top_row = self.selected + 1 - rows;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(offset: u64, score: f32) -> HotFragment {
+        let mut fragment = HotFragment::new(Offset::new(offset), Size::new(4096));
+        fragment.target_score = score;
+        fragment
+    }
+
+    #[test]
+    fn test_fragment_table() {
+        let mut table = FragmentTable { visible_rows: 2, ..FragmentTable::default() };
+        table.push(&fragment(0x1000, 5.0));
+        table.push(&fragment(0x3000, 9.0));
+        table.push(&fragment(0x2000, 7.0));
+        let offsets = |table: &FragmentTable| table.rows.iter().map(|row| row.offset.as_u64()).collect::<Vec<_>>();
+        assert_eq!(offsets(&table), [0x3000, 0x2000, 0x1000]);
+        // The selection followed 0x1000 down as better fragments arrived
+        assert_eq!((table.selected, table.top_row), (2, 1));
+
+        assert_eq!(
+            table.handle_key(KeyCode::Char('x')),
+            TableAction::Mark { offset: Offset::new(0x1000), mark: Some(FragmentMark::Exclude) }
+        );
+        table.handle_key(KeyCode::Up);
+        table.handle_key(KeyCode::Char('r'));
+        assert_eq!((table.marked(FragmentMark::ForceRecover), table.marked(FragmentMark::Exclude)), (1, 1));
+        assert_eq!(
+            table.handle_key(KeyCode::Char('r')),
+            TableAction::Mark { offset: Offset::new(0x2000), mark: None }
+        );
+
+        table.handle_key(KeyCode::Char('s'));
+        assert_eq!(offsets(&table), [0x1000, 0x2000, 0x3000]);
+        assert_eq!(table.selected, 1);
+        table.push(&fragment(0x1800, 1.0));
+        assert_eq!((table.rows[1].offset.as_u64(), table.selected), (0x1800, 2));
+        assert!(matches!(table.handle_key(KeyCode::Char('v')), TableAction::View(f) if f.offset.as_u64() == 0x2000));
+
+        table.locked = true;
+        assert_eq!(table.handle_key(KeyCode::Char('x')), TableAction::Locked);
+        assert_eq!(table.handle_key(KeyCode::Esc), TableAction::Close);
+    }
+}
@@ -0,0 +1,191 @@
+//! Hex viewer of the most recent hot fragment (V)
+//!
+//! V opens the first `VIEWER_BYTES` of the last fragment the scan reported,
+//! read through `DiskImage::get_slice`, as a hex + ASCII dump. The matcher
+//! runs over those bytes again so the links it matched are highlighted, and
+//! bytes are coloured by the entropy of their `ENTROPY_BLOCK`. Arrows move
+//! the cursor, PgUp/PgDn by a page, N jumps to the next match; Esc or V
+//! closes the viewer. The scan goes on meanwhile.
+
+use crate::disk::DiskImage;
+use crate::entropy::calculate_shannon_entropy;
+use crate::matcher::EnhancedMatcher;
+use crate::types::HotFragment;
+use crossterm::event::KeyCode;
+
+/// Bytes read from the start of the fragment
+pub const VIEWER_BYTES: usize = 4096;
+pub const BYTES_PER_ROW: usize = 16;
+/// Bytes whose entropy colours them
+pub const ENTROPY_BLOCK: usize = 256;
+
+/// Link the matcher found in the viewed bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewMatch {
+    /// Range in `FragmentView::data`
+    pub start: usize,
+    pub end: usize,
+    pub pattern: String,
+}
+
+/// Open viewer: the bytes, what is highlighted and the cursor
+#[derive(Debug, Clone)]
+pub struct FragmentView {
+    /// Image offset of `data[0]`
+    pub offset: u64,
+    pub size: u64,
+    pub file_type: String,
+    pub score: f32,
+    pub data: Vec<u8>,
+    /// In offset order
+    pub matches: Vec<ViewMatch>,
+    /// Shannon entropy of each `ENTROPY_BLOCK` of `data`
+    pub entropy: Vec<f32>,
+    /// Why the bytes could not be read
+    pub error: Option<String>,
+    pub cursor: usize,
+    /// First row shown
+    pub top_row: usize,
+    /// Rows that fit, set when drawn
+    pub visible_rows: usize,
+}
+
+impl FragmentView {
+    /// Read the start of `fragment` and match it again
+    pub fn open(fragment: &HotFragment, disk: &DiskImage, matcher: &mut EnhancedMatcher) -> Self {
+        let offset = fragment.offset.as_u64();
+        let len = fragment
+            .size
+            .as_u64()
+            .min(VIEWER_BYTES as u64)
+            .min(disk.size().as_u64().saturating_sub(offset)) as usize;
+        let (data, error) = match disk.get_slice(fragment.offset, len) {
+            Ok(slice) => (slice.data.into_owned(), None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+
+        let mut matches: Vec<ViewMatch> = matcher
+            .scan_chunk(&data, fragment.offset, false)
+            .into_iter()
+            .filter_map(|link| {
+                let start = link.offset.as_u64().checked_sub(offset)? as usize;
+                let end = (start + link.url.len()).min(data.len());
+                (start < end).then_some(ViewMatch { start, end, pattern: link.pattern_name })
+            })
+            .collect();
+        // Several patterns can match one link: keep the longest match
+        matches.sort_by_key(|m| (m.start, std::cmp::Reverse(m.end)));
+        matches.dedup_by_key(|m| m.start);
+
+        Self {
+            offset,
+            size: fragment.size.as_u64(),
+            file_type: fragment.file_type_guess.clone(),
+            score: fragment.target_score,
+            entropy: data.chunks(ENTROPY_BLOCK).map(calculate_shannon_entropy).collect(),
+            data,
+            matches,
+            error,
+            cursor: 0,
+            top_row: 0,
+            visible_rows: 16,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.data.len().div_ceil(BYTES_PER_ROW)
+    }
+
+    /// Match covering byte `index`
+    pub fn match_at(&self, index: usize) -> Option<&ViewMatch> {
+        self.matches.iter().find(|m| (m.start..m.end).contains(&index))
+    }
+
+    /// Entropy of the block holding byte `index`
+    pub fn entropy_at(&self, index: usize) -> f32 {
+        self.entropy.get(index / ENTROPY_BLOCK).copied().unwrap_or(0.0)
+    }
+
+    /// Move the cursor for `code`; false when the key closes the viewer
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        let last = self.data.len().saturating_sub(1);
+        let page = self.visible_rows.max(1) * BYTES_PER_ROW;
+        self.cursor = match code {
+            KeyCode::Esc | KeyCode::Char('v') | KeyCode::Char('V') => return false,
+            KeyCode::Left => self.cursor.saturating_sub(1),
+            KeyCode::Right => (self.cursor + 1).min(last),
+            KeyCode::Up => self.cursor.checked_sub(BYTES_PER_ROW).unwrap_or(self.cursor),
+            KeyCode::Down if self.cursor + BYTES_PER_ROW <= last => self.cursor + BYTES_PER_ROW,
+            KeyCode::PageUp => self.cursor.checked_sub(page).unwrap_or(self.cursor % BYTES_PER_ROW),
+            KeyCode::PageDown => (self.cursor + page).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            // Next match after the cursor, wrapping around
+            KeyCode::Char('n') | KeyCode::Char('N') => self
+                .matches
+                .iter()
+                .find(|m| m.start > self.cursor)
+                .or(self.matches.first())
+                .map_or(self.cursor, |m| m.start),
+            _ => self.cursor,
+        };
+        self.scroll_to_cursor();
+        true
+    }
+
+    /// Scroll so that the cursor row is shown
+    pub fn scroll_to_cursor(&mut self) {
+        let row = self.cursor / BYTES_PER_ROW;
+        let rows = self.visible_rows.max(1);
+        if row < self.top_row {
+            self.top_row = row;
+        } else if row >= self.top_row + rows {
+            self.top_row = row + 1 - rows;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Offset, Size};
+
+    #[test]
+    fn test_fragment_view() {
+        let path = std::env::temp_dir().join(format!("rust_recovery_viewer_{}.img", std::process::id()));
+        let mut image = vec![0u8; 16384];
+        let link = b"https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        image[8192 + 100..8192 + 100 + link.len()].copy_from_slice(link);
+        image[8192 + 3000..8192 + 3000 + link.len()].copy_from_slice(link);
+        std::fs::write(&path, &image).unwrap();
+        let disk = DiskImage::open(&path).unwrap();
+
+        let fragment = HotFragment::new(Offset::new(8192), Size::new(6000));
+        let mut view = FragmentView::open(&fragment, &disk, &mut EnhancedMatcher::new());
+        assert_eq!(view.data.len(), VIEWER_BYTES);
+        assert_eq!(view.rows(), VIEWER_BYTES / BYTES_PER_ROW);
+        assert_eq!(view.matches.iter().map(|m| m.start).collect::<Vec<_>>(), [100, 3000]);
+        assert!(view.match_at(110).is_some() && view.match_at(99).is_none());
+        assert!(view.entropy_at(100) > 1.0 && view.entropy_at(1024) == 0.0);
+
+        view.visible_rows = 10;
+        assert!(view.handle_key(KeyCode::Char('n')));
+        assert_eq!(view.cursor, 100);
+        view.handle_key(KeyCode::Char('n'));
+        assert_eq!((view.cursor, view.top_row), (3000, 3000 / BYTES_PER_ROW - 9));
+        view.handle_key(KeyCode::Char('n'));
+        assert_eq!(view.cursor, 100);
+        view.handle_key(KeyCode::Up);
+        view.handle_key(KeyCode::Left);
+        assert_eq!((view.cursor, view.top_row), (83, 5));
+        view.handle_key(KeyCode::End);
+        view.handle_key(KeyCode::Down);
+        assert_eq!(view.cursor, VIEWER_BYTES - 1);
+        assert!(!view.handle_key(KeyCode::Esc));
+
+        // Past the end of the image: what is left
+        let tail = FragmentView::open(&HotFragment::new(Offset::new(16000), Size::new(4096)), &disk, &mut EnhancedMatcher::new());
+        assert_eq!((tail.data.len(), tail.error.as_deref()), (384, None));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -3,11 +3,15 @@
 //! This module contains individual widget components for the TUI including
 //! disk heatmap, statistics, logs, and dashboard elements.
 
+use super::table::{FragmentMark, FragmentSort, FragmentTable};
+use super::viewer::{FragmentView, BYTES_PER_ROW};
 use ratatui::{
+    layout::{Constraint, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
-        Block, BorderType, Borders, Gauge, List, ListItem, Paragraph, Widget,
+        block::{Position, Title},
+        Block, BorderType, Borders, Gauge, List, ListItem, Paragraph, Row, Table, Widget,
     },
 };
 
@@ -59,13 +63,236 @@ impl DashboardFooter {
     }
     
     pub fn render() -> impl Widget {
-        Paragraph::new("Controls: [P]ause  [S]kip  [V]iew  [C]heckpoint  [Q]uit")
+        Paragraph::new("Controls: [P]ause  [S]kip  [V]iew  [F]ragments  [C]heckpoint  [Q]uit  [^C]ancel")
             .style(Style::default().fg(Color::Gray))
             .alignment(ratatui::layout::Alignment::Center)
             .block(Block::default().borders(Borders::ALL).border_type(BorderType::Plain))
     }
 }
 
+/// Q confirmation dialog, drawn over the dashboard
+pub struct QuitDialog;
+
+impl QuitDialog {
+    pub fn render() -> impl Widget {
+        Paragraph::new(vec![
+            Line::from(vec![
+                Span::styled(" [C] ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                Span::raw("Quit & cancel scan: save the files found so far"),
+            ]),
+            Line::from(vec![
+                Span::styled(" [K] ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::raw("Quit & keep scanning: the scan goes on in the terminal"),
+            ]),
+            Line::from(vec![
+                Span::styled(" [B] ", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+                Span::raw("Back to the dashboard (Esc)"),
+            ]),
+        ])
+        .style(Style::default().fg(Color::White).bg(Color::Black))
+        .block(create_block("Quit?", Borders::ALL).padding(ratatui::widgets::Padding::vertical(1)))
+    }
+}
+
+/// Shutdown screen after a cancel: steps done so far and the one running
+pub struct ShutdownWidget;
+
+impl ShutdownWidget {
+    pub fn render(shutdown: &super::ShutdownProgress) -> impl Widget + use<'_> {
+        let last = shutdown.steps.len().saturating_sub(1);
+        let mut lines: Vec<Line> = shutdown
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let (mark, color) = if i < last { ("  [done] ", Color::Green) } else { ("  [....] ", Color::Yellow) };
+                Line::from(vec![
+                    Span::styled(mark, Style::default().fg(color)),
+                    Span::styled(step.as_str(), Style::default().fg(Color::White)),
+                ])
+            })
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  {} elapsed. Files found so far are saved; --resume continues the scan. Ctrl-C closes the dashboard.",
+                format_duration(shutdown.started.elapsed().as_secs_f64())
+            ),
+            Style::default().fg(Color::Gray),
+        )));
+
+        Paragraph::new(lines).block(create_block("Shutting down", Borders::ALL))
+    }
+}
+
+/// Fragment table (F), drawn over the dashboard
+pub struct FragmentTableWidget;
+
+impl FragmentTableWidget {
+    pub const WIDTH: u16 = 78;
+    /// Lines besides the rows: borders and the header
+    pub const CHROME_HEIGHT: u16 = 3;
+
+    pub fn render(table: &FragmentTable) -> impl Widget + use<'_> {
+        let sort = match table.sort {
+            FragmentSort::Score => "score",
+            FragmentSort::Offset => "offset",
+        };
+        let title = format!(
+            "Fragments: {} by {} - {} force-recover, {} excluded{}",
+            table.rows.len(),
+            sort,
+            table.marked(FragmentMark::ForceRecover),
+            table.marked(FragmentMark::Exclude),
+            if table.locked { " (assembled, marks closed)" } else { "" }
+        );
+        let help = " Arrows select, [S]ort, [R] force-recover, [X] exclude, [V]iew, Esc close ";
+
+        let last_row = (table.top_row + table.visible_rows).min(table.rows.len());
+        let rows = table.rows[table.top_row.min(last_row)..last_row].iter().enumerate().map(|(i, row)| {
+            let (mark, color) = match row.mark {
+                Some(FragmentMark::ForceRecover) => ("recover", Color::Green),
+                Some(FragmentMark::Exclude) => ("exclude", Color::Red),
+                None => ("", Color::White),
+            };
+            let mut style = Style::default().fg(color);
+            if table.top_row + i == table.selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            Row::new(vec![
+                format!("0x{:010X}", row.offset.as_u64()),
+                format!("{} KB", row.size.as_u64().div_ceil(1024)),
+                format!("{:.2}", row.score),
+                row.file_type.clone(),
+                row.links.to_string(),
+                mark.to_string(),
+            ])
+            .style(style)
+        });
+        let widths = [
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Length(7),
+            Constraint::Min(12),
+            Constraint::Length(5),
+            Constraint::Length(7),
+        ];
+        Table::new(rows, widths)
+            .header(
+                Row::new(["Offset", "Size", "Score", "Type", "Links", "Mark"])
+                    .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            )
+            .style(Style::default().bg(Color::Black))
+            .block(
+                Block::default()
+                    .title(Span::styled(title, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+                    .title(Title::from(Span::styled(help, Style::default().fg(Color::Gray))).position(Position::Bottom))
+                    .borders(Borders::ALL),
+            )
+    }
+}
+
+/// Fragment viewer (V): hex + ASCII rows, matches highlighted, bytes coloured by entropy
+pub struct FragmentViewerWidget;
+
+impl FragmentViewerWidget {
+    /// Width of a viewer row: offset, hex, ASCII and borders
+    pub const WIDTH: u16 = 12 + 2 + 3 * BYTES_PER_ROW as u16 + 1 + 1 + BYTES_PER_ROW as u16 + 1 + 2;
+    /// Lines besides the rows: borders, blank line, status and legend
+    pub const CHROME_HEIGHT: u16 = 5;
+
+    pub fn render(view: &FragmentView) -> impl Widget + use<'_> {
+        let title = format!(
+            "Fragment 0x{:X} ({} KB, {}, score {:.2}) - first {} bytes",
+            view.offset,
+            view.size / 1024,
+            view.file_type,
+            view.score,
+            view.data.len()
+        );
+        let mut lines: Vec<Line> = Vec::new();
+        if let Some(ref error) = view.error {
+            lines.push(Line::from(Span::styled(format!("  Cannot read the fragment: {}", error), Style::default().fg(Color::Red))));
+        }
+
+        let last_row = (view.top_row + view.visible_rows).min(view.rows());
+        for row in view.top_row..last_row {
+            let start = row * BYTES_PER_ROW;
+            let bytes = &view.data[start..(start + BYTES_PER_ROW).min(view.data.len())];
+            let mut spans = vec![Span::styled(
+                format!("0x{:010X}  ", view.offset + start as u64),
+                Style::default().fg(Color::Gray),
+            )];
+            for (i, byte) in bytes.iter().enumerate() {
+                let gap = if i == BYTES_PER_ROW / 2 { " " } else { "" };
+                spans.push(Span::raw(gap));
+                spans.push(Span::styled(format!("{:02X}", byte), Self::byte_style(view, start + i)));
+                spans.push(Span::raw(" "));
+            }
+            // Short last row: keep the ASCII column aligned
+            let missing = BYTES_PER_ROW - bytes.len();
+            spans.push(Span::raw(" ".repeat(missing * 3 + usize::from(bytes.len() <= BYTES_PER_ROW / 2) + 1)));
+            for (i, &byte) in bytes.iter().enumerate() {
+                let c = if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' };
+                spans.push(Span::styled(c.to_string(), Self::byte_style(view, start + i)));
+            }
+            lines.push(Line::from(spans));
+        }
+
+        lines.push(Line::from(""));
+        let cursor = view.cursor;
+        let status = match view.data.get(cursor) {
+            Some(&byte) => format!(
+                "  0x{:X}: {:02X} | entropy {:.2} ({}){}",
+                view.offset + cursor as u64,
+                byte,
+                view.entropy_at(cursor),
+                crate::entropy::entropy_category(view.entropy_at(cursor)),
+                view.match_at(cursor).map(|m| format!(" | match: {}", m.pattern)).unwrap_or_default()
+            ),
+            None => "  (no bytes)".to_string(),
+        };
+        lines.push(Line::from(Span::styled(status, Style::default().fg(Color::White))));
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {} matches  ", view.matches.len()), Style::default().fg(Color::Magenta)),
+            Span::styled("text ", Style::default().fg(entropy_color(4.0))),
+            Span::styled("mixed ", Style::default().fg(entropy_color(7.0))),
+            Span::styled("compressed ", Style::default().fg(entropy_color(8.0))),
+            Span::styled(" Arrows move, [N]ext match, Esc close", Style::default().fg(Color::Gray)),
+        ]));
+
+        Paragraph::new(lines)
+            .style(Style::default().bg(Color::Black))
+            .block(
+                Block::default()
+                    .title(Span::styled(title, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+                    .borders(Borders::ALL),
+            )
+    }
+
+    fn byte_style(view: &FragmentView, index: usize) -> Style {
+        let mut style = Style::default().fg(entropy_color(view.entropy_at(index)));
+        if view.match_at(index).is_some() {
+            style = style.fg(Color::White).bg(Color::Magenta).add_modifier(Modifier::BOLD);
+        }
+        if index == view.cursor {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        style
+    }
+}
+
+/// Colour of bytes by the entropy of their block (categories of `entropy::entropy_category`)
+fn entropy_color(entropy: f32) -> Color {
+    match crate::entropy::entropy_category(entropy) {
+        "high_entropy_compressed" => Color::Red,
+        "medium_entropy_mixed" => Color::Yellow,
+        "structured_text" => Color::Green,
+        "low_entropy_repetitive" => Color::Cyan,
+        _ => Color::DarkGray,
+    }
+}
+
 /// Disk heatmap widget
 pub struct DiskHeatmapWidget;
 
@@ -123,6 +350,7 @@ impl StatsWidget {
             "Fragments:      {:<10} Clusters:        {}\n\
              Top candidate:  {}\n\
              Recovered:      {} files{}\n\
+             By metadata:    {} files\n\
              Checkpoint:     auto-saved at {:.1} GB",
             app.fragments_found,
             app.hot_clusters,
@@ -140,6 +368,7 @@ impl StatsWidget {
             } else {
                 String::new()
             },
+            app.metadata_files,
             app.bytes_scanned as f64 / 1024.0 / 1024.0 / 1024.0
         );
 
@@ -309,6 +538,13 @@ pub fn create_block(title: &str, borders: Borders) -> Block<'_> {
         .border_type(BorderType::Plain)
 }
 
+/// Area of `width` x `height` centered in `area` (clipped to it)
+pub fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
+
 /// Helper function to create styled text
 pub fn create_styled_text(text: &str, color: Color, modifier: Modifier) -> Vec<Line<'_>> {
     vec![Line::from(Span::styled(
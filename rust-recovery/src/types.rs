@@ -1,72 +1,8 @@
+use crate::diagnostics::DiagnosticCause;
 use crate::smart_separation::ByteFrequency;
+use serde::{Deserialize, Serialize};
 
-/// Newtype wrapper for byte offsets in disk images
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Offset(pub u64);
-
-impl Offset {
-    pub fn new(value: u64) -> Self {
-        Self(value)
-    }
-
-    pub fn as_u64(&self) -> u64 {
-        self.0
-    }
-
-    pub fn checked_add(&self, size: Size) -> Option<Offset> {
-        self.0.checked_add(size.0).map(Offset)
-    }
-}
-
-impl std::fmt::Display for Offset {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "0x{:X}", self.0)
-    }
-}
-
-/// Newtype wrapper for sizes in bytes
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Size(pub u64);
-
-impl Size {
-    pub fn new(value: u64) -> Self {
-        Self(value)
-    }
-
-    pub fn as_u64(&self) -> u64 {
-        self.0
-    }
-
-    pub fn as_usize(&self) -> Option<usize> {
-        usize::try_from(self.0).ok()
-    }
-}
-
-impl std::fmt::Display for Size {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} bytes", self.0)
-    }
-}
-
-/// Newtype wrapper for cluster IDs
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct ClusterId(pub u64);
-
-impl ClusterId {
-    pub fn new(value: u64) -> Self {
-        Self(value)
-    }
-
-    pub fn as_u64(&self) -> u64 {
-        self.0
-    }
-}
-
-impl std::fmt::Display for ClusterId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "cluster_{}", self.0)
-    }
-}
+pub use recovery_core::types::{ClusterId, FileTimestamps, Offset, Size};
 
 /// Scanner configuration
 #[derive(Debug, Clone)]
@@ -91,6 +27,83 @@ pub struct ScanConfig {
 
     /// NVMe optimization
     pub nvme_optimization: bool,
+
+    /// Abandon and requeue a chunk that makes no progress for this long (None = no watchdog)
+    pub stall_timeout: Option<std::time::Duration>,
+
+    /// File types carved by signature (see `carver::SIGNATURES`, empty = off)
+    pub carve_types: Vec<String>,
+
+    /// Checkpointed position to continue from (see `checkpoint::ScanState`)
+    pub resume_from: Option<Offset>,
+
+    /// Send `ScanProgress::ChunkStats` for every scanned chunk
+    pub chunk_stats: bool,
+
+    /// Structure detectors run in the block pass (see `scanner::detectors::DETECTOR_NAMES`, empty = off)
+    pub detectors: Vec<String>,
+
+    /// Artifacts carved into links (see `scanner::artifacts::ARTIFACT_NAMES`, empty = off)
+    pub artifacts: Vec<String>,
+
+    /// Read chunks with pread one window at a time instead of slicing the
+    /// mapping (images larger than RAM or the address space)
+    pub streaming: bool,
+
+    /// Scan chunks in order of their prefilter score (see `scanner::priority`)
+    pub prioritize: bool,
+
+    /// Boost (or with `--keyword-only` keep only) links and fragments with a
+    /// keyword (see `scanner::keywords`, None = off)
+    pub keywords: Option<std::sync::Arc<crate::scanner::KeywordFilter>>,
+
+    /// Weighted case terms whose hits add to the fragment score
+    /// (`--keyword-profile`, see `scanner::relevance`; None = off)
+    pub keyword_profile: Option<std::sync::Arc<crate::scanner::KeywordProfile>>,
+
+    /// Assembled files smaller than this are dropped (bytes)
+    pub min_file_size: u64,
+
+    /// Assembled files are split at this size (bytes; carved files are kept whole)
+    pub max_file_size: u64,
+
+    /// Split chunks down to this size where the data looks like text
+    /// (`--adaptive-chunks`, see `scanner::adaptive`; None = every chunk is chunk_size)
+    pub min_chunk_size: Option<usize>,
+
+    /// Scan only these sorted, disjoint image ranges `[start, end)` (the free
+    /// clusters with `--unallocated-only`; None = everything)
+    pub scan_ranges: Option<std::sync::Arc<Vec<(u64, u64)>>>,
+
+    /// Image ranges whose chunks are scanned first, in this order (deleted
+    /// exFAT files with `--enable-exfat`, see `scanner::hints`; None = off)
+    pub hint_ranges: Option<std::sync::Arc<Vec<(u64, u64)>>>,
+
+    /// Hold chunk dispatch to this read rate (`--max-read-mbps`, see `scanner::throttle`; None = no limit)
+    pub max_read_bytes_per_sec: Option<u64>,
+
+    /// I/O scheduling class of the worker threads (`--io-nice`; None = inherited)
+    pub io_priority: Option<crate::scanner::IoPriority>,
+
+    /// Script whose text density counts towards the fragment score (`--script`)
+    pub target_script: crate::matcher::Script,
+
+    /// Send the links of every chunk as `ScanProgress::Links` instead of
+    /// collecting them in `ScanResult::links` (`--scan-db`); overlap
+    /// duplicates are then left to the receiver
+    pub stream_links: bool,
+
+    /// Full-image digest fed with every chunk read (`--verify-hash`, see
+    /// `scanner::digest`; None = off)
+    pub image_digest: Option<std::sync::Arc<crate::scanner::ImageDigest>>,
+
+    /// Read chunks ahead of the workers on reader threads (`--prefetch`, on by
+    /// default for NBD and iSCSI devices; see `scanner::prefetch`; None = off)
+    pub prefetch: Option<crate::scanner::PrefetchConfig>,
+
+    /// Keeps the bytes of hot and carved fragments so assembly does not read
+    /// the image again (`--fragment-cache`, see `scanner::fragment_cache`; None = off)
+    pub fragment_cache: Option<std::sync::Arc<crate::scanner::FragmentCache>>,
 }
 
 impl Default for ScanConfig {
@@ -103,6 +116,28 @@ impl Default for ScanConfig {
             min_confidence: 0.0,
             reverse: false,
             nvme_optimization: false,
+            stall_timeout: Some(std::time::Duration::from_secs(5 * 60)),
+            carve_types: Vec::new(),
+            resume_from: None,
+            chunk_stats: false,
+            detectors: Vec::new(),
+            artifacts: Vec::new(),
+            streaming: false,
+            prioritize: false,
+            keywords: None,
+            keyword_profile: None,
+            min_file_size: 0,
+            max_file_size: u64::MAX,
+            min_chunk_size: None,
+            scan_ranges: None,
+            hint_ranges: None,
+            max_read_bytes_per_sec: None,
+            io_priority: None,
+            target_script: crate::matcher::Script::Cyrillic,
+            stream_links: false,
+            image_digest: None,
+            prefetch: None,
+            fragment_cache: None,
         }
     }
 }
@@ -126,15 +161,23 @@ impl ScanConfig {
 #[derive(Debug, Clone)]
 pub struct EnrichedLink {
     pub url: String,
+    /// Extracted value: the video ID, the capture of a custom pattern profile,
+    /// or the last visit time of a browser history row (`scanner::artifacts`)
     pub video_id: String,
     pub title: Option<String>,
-    pub offset: u64,
+    pub offset: Offset,
     pub pattern_name: String,
     pub confidence: f32,
+    /// Context adjustments applied to `confidence` (see `matcher::context`)
+    pub reasons: Vec<String>,
+    /// Text right before and after the match (`--link-context`), cut at
+    /// character boundaries; None when not captured
+    pub context_before: Option<String>,
+    pub context_after: Option<String>,
 }
 
 impl EnrichedLink {
-    pub fn new(url: String, video_id: String, offset: u64, pattern_name: String, confidence: f32) -> Self {
+    pub fn new(url: String, video_id: String, offset: Offset, pattern_name: String, confidence: f32) -> Self {
         Self {
             url,
             video_id,
@@ -142,6 +185,9 @@ impl EnrichedLink {
             offset,
             pattern_name,
             confidence,
+            reasons: Vec::new(),
+            context_before: None,
+            context_after: None,
         }
     }
 }
@@ -152,6 +198,73 @@ pub struct ScanResult {
     pub links: Vec<EnrichedLink>,
     pub bytes_scanned: u64,
     pub duration_secs: f64,
+    /// Chunks the watchdog had to abandon and requeue
+    pub incidents: Vec<ScanIncident>,
+}
+
+/// A chunk that stalled and was requeued on the scalar path in smaller windows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanIncident {
+    pub offset: Offset,
+    pub size: Size,
+    /// Time the worker spent on the chunk before it was abandoned
+    pub stalled_secs: f64,
+    /// Windows the chunk was split into for the rescan
+    pub requeued_windows: usize,
+    /// Windows that stalled again and were skipped
+    pub abandoned_windows: usize,
+    /// Bytes left unscanned because of abandoned windows
+    pub unscanned_bytes: u64,
+}
+
+impl ScanIncident {
+    /// True if the rescan covered the whole chunk
+    pub fn is_resolved(&self) -> bool {
+        self.abandoned_windows == 0
+    }
+}
+
+/// Per-chunk scan statistics (`--chunk-stats`), for tuning thresholds and
+/// mapping disk content without a rescan
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChunkStats {
+    pub offset: Offset,
+    pub size: Size,
+    pub duration_ms: f64,
+    pub links: usize,
+    /// JSON structure bytes (`{}[]`)
+    pub json_markers: usize,
+    pub entropy: f32,
+    /// `entropy::entropy_category` of the chunk
+    pub category: &'static str,
+    /// Promoted to a hot fragment
+    pub hot: bool,
+}
+
+impl ChunkStats {
+    pub const CSV_HEADER: &'static str = "offset,size,duration_ms,links,json_markers,entropy,category,hot";
+
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{:.3},{},{},{:.4},{},{}",
+            self.offset.as_u64(),
+            self.size.as_u64(),
+            self.duration_ms,
+            self.links,
+            self.json_markers,
+            self.entropy,
+            self.category,
+            self.hot
+        )
+    }
+}
+
+/// On-disk structure found by a block-pass detector (`--detect`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StructureHit {
+    pub offset: Offset,
+    /// Detector name
+    pub structure: &'static str,
 }
 
 /// Progress update sent via tokio channel
@@ -159,12 +272,24 @@ pub struct ScanResult {
 pub enum ScanProgress {
     /// Bytes processed
     BytesScanned(u64),
-    /// Chunk completed
-    ChunkCompleted(u64),
+    /// Chunk scanned: start offset and bytes read, overlap included
+    ChunkCompleted(Offset, u64),
     /// Hot fragment found
     HotFragment(HotFragment),
     /// Error in a chunk (non-fatal)
-    ChunkError(u64, String),
+    ChunkError(Offset, String),
+    /// Chunk stalled and was requeued (sent once the rescan finished)
+    Incident(ScanIncident),
+    /// Bytes at an offset that were skipped or could not be read
+    Skipped(Offset, u64, DiagnosticCause),
+    /// Every chunk on the scanned side of this offset has completed
+    ResumePoint(Offset),
+    /// Statistics of a scanned chunk (only with `ScanConfig::chunk_stats`)
+    ChunkStats(ChunkStats),
+    /// Structures found in a chunk (only with `ScanConfig::detectors`)
+    Structures(Vec<StructureHit>),
+    /// Links found in a chunk (only with `ScanConfig::stream_links`)
+    Links(Vec<EnrichedLink>),
 }
 
 /// Scan statistics
@@ -195,10 +320,13 @@ impl ScanStats {
 /// A "hot" fragment detected during scanning
 #[derive(Debug, Clone)]
 pub struct HotFragment {
-    pub offset: u64,
-    pub size: usize,
+    pub offset: Offset,
+    pub size: Size,
     pub youtube_count: usize,
+    /// Same as `scripts.cyrillic`; kept for scan databases and checkpoints
     pub cyrillic_density: f32,
+    /// Share of the fragment's bytes in each script
+    pub scripts: crate::matcher::ScriptDensity,
     pub json_markers: usize,
     pub has_valid_json: bool,
     pub target_score: f32,
@@ -206,15 +334,18 @@ pub struct HotFragment {
     pub entropy: f32,
     pub entropy_category: String,
     pub fragment_score: FragmentScore,
+    /// Complete file carved by signature rather than a scored chunk
+    pub carved: bool,
 }
 
 impl HotFragment {
-    pub fn new(offset: u64, size: usize) -> Self {
+    pub fn new(offset: Offset, size: Size) -> Self {
         Self {
             offset,
             size,
             youtube_count: 0,
             cyrillic_density: 0.0,
+            scripts: crate::matcher::ScriptDensity::default(),
             json_markers: 0,
             has_valid_json: false,
             target_score: 0.0,
@@ -222,11 +353,12 @@ impl HotFragment {
             entropy: 0.0,
             entropy_category: "unknown".to_string(),
             fragment_score: FragmentScore::default(),
+            carved: false,
         }
     }
 
     pub fn is_target_size(&self) -> bool {
-        let size_kb = self.size as f32 / 1024.0;
+        let size_kb = self.size.as_u64() as f32 / 1024.0;
         size_kb >= 15.0 && size_kb <= 350.0
     }
 
@@ -248,6 +380,10 @@ pub struct FragmentScore {
     pub has_structured_text: bool,
     pub is_compressed: bool,
     pub reasons: Vec<String>,
+    /// Share of the fragment's bytes in each script
+    pub scripts: crate::matcher::ScriptDensity,
+    /// Score points of `--keyword-profile` hits
+    pub keyword_relevance: f32,
 }
 
 impl Default for FragmentScore {
@@ -261,6 +397,8 @@ impl Default for FragmentScore {
             has_structured_text: false,
             is_compressed: false,
             reasons: Vec::new(),
+            scripts: crate::matcher::ScriptDensity::default(),
+            keyword_relevance: 0.0,
         }
     }
 }
@@ -280,8 +418,8 @@ impl FragmentScore {
 /// Fragment metadata for stream assembly
 #[derive(Debug, Clone)]
 pub struct StreamFragment {
-    pub offset: u64,
-    pub size: usize,
+    pub offset: Offset,
+    pub size: Size,
     pub base_score: f32,
     pub file_type: String,
     pub links: Vec<String>,
@@ -291,7 +429,7 @@ pub struct StreamFragment {
 
 impl StreamFragment {
     pub fn from_bytes(
-        offset: u64,
+        offset: Offset,
         data: &[u8],
         file_type: impl Into<String>,
         base_score: f32,
@@ -299,7 +437,7 @@ impl StreamFragment {
     ) -> Self {
         Self {
             offset,
-            size: data.len(),
+            size: Size::from_usize(data.len()),
             base_score,
             file_type: file_type.into(),
             links: Vec::new(),
@@ -316,8 +454,8 @@ impl StreamFragment {
         self
     }
 
-    pub fn end_offset(&self) -> u64 {
-        self.offset + self.size as u64
+    pub fn end_offset(&self) -> Offset {
+        self.offset.end(self.size)
     }
 
     pub fn total_score(&self) -> f32 {
@@ -329,8 +467,10 @@ impl StreamFragment {
     }
 }
 
-/// Scoring weights for stream assembly
-#[derive(Debug, Clone)]
+/// Scoring weights for stream assembly. `--solver-config` reads them from
+/// TOML; keys left out keep their default
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct StreamScoringWeights {
     pub max_gap: u64,
     pub max_overlap: u64,
@@ -343,6 +483,12 @@ pub struct StreamScoringWeights {
     pub structure_bonus: f32,
     pub min_edge_score: f32,
     pub max_lookback: usize,
+    /// Streams smaller than this are dropped (bytes, `--target-size-min`)
+    #[serde(skip)]
+    pub min_stream_size: u64,
+    /// Paths stop growing at this size and larger fragments are split (bytes, `--target-size-max`)
+    #[serde(skip)]
+    pub max_stream_size: u64,
 }
 
 impl Default for StreamScoringWeights {
@@ -359,10 +505,44 @@ impl Default for StreamScoringWeights {
             structure_bonus: 6.0,
             min_edge_score: 5.0,
             max_lookback: 200,
+            min_stream_size: 0,
+            max_stream_size: u64::MAX,
         }
     }
 }
 
+impl StreamScoringWeights {
+    /// Parse a `--solver-config` TOML file
+    pub fn load(path: &std::path::Path) -> crate::error::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let weights: Self = toml::from_str(&text)
+            .map_err(|e| crate::error::RecoveryError::Config(format!("{}: {}", path.display(), e)))?;
+        weights.check().map_err(|e| crate::error::RecoveryError::Config(format!("{}: {}", path.display(), e)))?;
+        Ok(weights)
+    }
+
+    /// Reject weights the solver cannot use
+    pub fn check(&self) -> Result<(), String> {
+        let weights = [
+            ("gap_penalty", self.gap_penalty),
+            ("overlap_penalty", self.overlap_penalty),
+            ("type_match_bonus", self.type_match_bonus),
+            ("type_mismatch_penalty", self.type_mismatch_penalty),
+            ("cosine_weight", self.cosine_weight),
+            ("jaccard_weight", self.jaccard_weight),
+            ("structure_bonus", self.structure_bonus),
+            ("min_edge_score", self.min_edge_score),
+        ];
+        if let Some((name, _)) = weights.iter().find(|(_, value)| !value.is_finite()) {
+            return Err(format!("{} must be a finite number", name));
+        }
+        if self.max_lookback == 0 {
+            return Err("max_lookback must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
 /// Assembled stream result
 #[derive(Debug, Clone)]
 pub struct AssembledStream {
@@ -372,6 +552,13 @@ pub struct AssembledStream {
     pub reasons: Vec<String>,
 }
 
+impl AssembledStream {
+    /// Bytes of the file before cleaning (fragments concatenated)
+    pub fn size_bytes(&self) -> u64 {
+        self.fragments.iter().map(|fragment| fragment.size.as_u64()).sum()
+    }
+}
+
 /// Validation results for a data chunk
 #[derive(Debug, Clone)]
 pub struct ValidationResult {
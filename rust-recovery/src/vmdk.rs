@@ -0,0 +1,394 @@
+//! VMDK (VMware virtual disk) image reader
+//!
+//! A VMDK is either a single hosted sparse extent (`monolithicSparse`,
+//! `streamOptimized`) or a text descriptor listing extents in guest order:
+//! `FLAT`/`VMFS` raw files, `SPARSE` extents and `ZERO` ranges. Sparse
+//! extents map grains (64 KiB by default) through the grain directory and
+//! grain tables; `streamOptimized` grains are zlib-compressed. Unallocated
+//! grains read as zeros. Delta disks (snapshots with a parent) and ESX
+//! `VMFSSPARSE` extents are not supported.
+
+use crate::disk::read_exact_at;
+use crate::error::{RecoveryError, Result};
+use flate2::{Decompress, FlushDecompress};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Magic of hosted sparse extents ("KDMV")
+pub const VMDK_SPARSE_MAGIC: &[u8; 4] = b"KDMV";
+/// First line of VMDK text descriptors
+pub const VMDK_DESCRIPTOR_MAGIC: &[u8] = b"# Disk DescriptorFile";
+
+const SECTOR: u64 = 512;
+/// `gdOffset` of stream-optimized extents: the real header is in the footer
+const GD_AT_END: u64 = u64::MAX;
+const COMPRESSED_GRAINS: u32 = 1 << 16;
+/// Stream-optimized grain marker: guest sector (u64) + compressed size (u32)
+const GRAIN_MARKER_SIZE: usize = 12;
+/// Largest descriptor read
+const MAX_DESCRIPTOR_SIZE: u64 = 1024 * 1024;
+
+/// Hosted sparse extent (`KDMV` header)
+struct SparseExtent {
+    file: File,
+    file_len: u64,
+    grain_size: u64,
+    gtes_per_gt: u64,
+    /// Sector offsets of the grain tables
+    directory: Vec<u32>,
+    compressed: bool,
+}
+
+enum ExtentData {
+    /// Raw data at this byte offset of the file
+    Flat(File, u64),
+    Sparse(SparseExtent),
+    Zero,
+}
+
+struct Extent {
+    /// Guest byte range `[start, start + size)`
+    start: u64,
+    size: u64,
+    data: ExtentData,
+}
+
+/// VMDK disk opened for random access by guest offset
+pub struct VmdkImage {
+    extents: Vec<Extent>,
+    size: u64,
+}
+
+impl VmdkImage {
+    /// Open a sparse `.vmdk` or a descriptor and the extents it names
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let mut magic = [0u8; 4];
+        read_exact_at(&file, &mut magic, 0)?;
+
+        let extents = if &magic == VMDK_SPARSE_MAGIC {
+            let sparse = SparseExtent::open(file)?;
+            let size = sparse.capacity;
+            vec![Extent { start: 0, size, data: ExtentData::Sparse(sparse.extent) }]
+        } else {
+            let len = file.metadata()?.len().min(MAX_DESCRIPTOR_SIZE);
+            let mut text = vec![0u8; len as usize];
+            read_exact_at(&file, &mut text, 0)?;
+            let base = path.parent().unwrap_or(Path::new("."));
+            parse_descriptor(&String::from_utf8_lossy(&text), base)?
+        };
+
+        let size = extents.last().map_or(0, |e| e.start + e.size);
+        Ok(Self { extents, size })
+    }
+
+    /// Virtual disk size in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn extent_count(&self) -> usize {
+        self.extents.len()
+    }
+
+    /// Fill `buf` with guest bytes from `offset`; a corrupt grain is an `InvalidData` error
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        self.read_range(offset, buf, false).map(|_| ())
+    }
+
+    /// Like `read_at`, but corrupt grains are zero-filled.
+    /// Returns the number of zero-filled bytes.
+    pub fn read_tolerant(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_range(offset, buf, true)
+    }
+
+    fn read_range(&self, offset: u64, buf: &mut [u8], tolerant: bool) -> io::Result<usize> {
+        if offset.saturating_add(buf.len() as u64) > self.size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let mut done = 0;
+        let mut unreadable = 0;
+        while done < buf.len() {
+            let position = offset + done as u64;
+            let index = self.extents.partition_point(|e| e.start + e.size <= position);
+            let extent = &self.extents[index];
+            let within = position - extent.start;
+            let take = ((extent.size - within) as usize).min(buf.len() - done);
+            let out = &mut buf[done..done + take];
+
+            match &extent.data {
+                ExtentData::Flat(file, base) => read_exact_at(file, out, base + within)?,
+                ExtentData::Sparse(sparse) => unreadable += sparse.read(within, out, tolerant)?,
+                ExtentData::Zero => out.fill(0),
+            }
+            done += take;
+        }
+        Ok(unreadable)
+    }
+}
+
+/// Sparse extent with the capacity from its header
+struct OpenedSparse {
+    extent: SparseExtent,
+    capacity: u64,
+}
+
+impl SparseExtent {
+    fn open(file: File) -> Result<OpenedSparse> {
+        let file_len = file.metadata()?.len();
+        let invalid = |what: &str| RecoveryError::Parse(format!("VMDK sparse extent: {}", what));
+
+        let mut header = [0u8; 512];
+        if file_len < header.len() as u64 {
+            return Err(invalid("truncated header"));
+        }
+        read_exact_at(&file, &mut header, 0)?;
+        if &header[..4] != VMDK_SPARSE_MAGIC {
+            return Err(invalid("bad magic"));
+        }
+
+        // Stream-optimized: header copy with the grain directory location in the
+        // footer, followed by the end-of-stream marker
+        if le_u64(&header[56..]) == GD_AT_END {
+            if file_len < 1536 {
+                return Err(invalid("missing footer"));
+            }
+            read_exact_at(&file, &mut header, file_len - 1024)?;
+            if &header[..4] != VMDK_SPARSE_MAGIC {
+                return Err(invalid("bad footer"));
+            }
+        }
+
+        let flags = le_u32(&header[8..]);
+        let capacity = le_u64(&header[12..]) * SECTOR;
+        let grain_size = le_u64(&header[20..]) * SECTOR;
+        let gtes_per_gt = le_u32(&header[44..]) as u64;
+        let gd_offset = le_u64(&header[56..]) * SECTOR;
+        if grain_size == 0 || grain_size > 64 * 1024 * 1024 || gtes_per_gt == 0 {
+            return Err(invalid("bad grain geometry"));
+        }
+
+        let grains = capacity.div_ceil(grain_size);
+        let tables = grains.div_ceil(gtes_per_gt);
+        if gd_offset.saturating_add(tables * 4) > file_len {
+            return Err(invalid("grain directory past the end of the file"));
+        }
+        let mut raw = vec![0u8; tables as usize * 4];
+        read_exact_at(&file, &mut raw, gd_offset)?;
+
+        Ok(OpenedSparse {
+            extent: SparseExtent {
+                file,
+                file_len,
+                grain_size,
+                gtes_per_gt,
+                directory: raw.chunks_exact(4).map(le_u32).collect(),
+                compressed: flags & COMPRESSED_GRAINS != 0,
+            },
+            capacity,
+        })
+    }
+
+    /// Read `out` from extent offset `offset`, grain by grain
+    fn read(&self, offset: u64, out: &mut [u8], tolerant: bool) -> io::Result<usize> {
+        let mut scratch = Vec::new();
+        let mut done = 0;
+        let mut unreadable = 0;
+        while done < out.len() {
+            let position = offset + done as u64;
+            let within = (position % self.grain_size) as usize;
+            let take = (self.grain_size as usize - within).min(out.len() - done);
+            let part = &mut out[done..done + take];
+
+            match self.read_grain(position / self.grain_size, within, part, &mut scratch) {
+                Ok(()) => {}
+                Err(e) if tolerant && e.kind() == io::ErrorKind::InvalidData => {
+                    part.fill(0);
+                    unreadable += take;
+                }
+                Err(e) => return Err(e),
+            }
+            done += take;
+        }
+        Ok(unreadable)
+    }
+
+    fn read_grain(&self, grain: u64, within: usize, out: &mut [u8], scratch: &mut Vec<u8>) -> io::Result<()> {
+        let corrupt = |what: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("VMDK grain {} {}", grain, what))
+        };
+
+        let table = self.directory.get((grain / self.gtes_per_gt) as usize).copied().unwrap_or(0) as u64;
+        if table == 0 {
+            out.fill(0);
+            return Ok(());
+        }
+        let entry_offset = table * SECTOR + (grain % self.gtes_per_gt) * 4;
+        if entry_offset + 4 > self.file_len {
+            return Err(corrupt("has its grain table past the end of the file"));
+        }
+        let mut entry = [0u8; 4];
+        read_exact_at(&self.file, &mut entry, entry_offset)?;
+
+        // 0: unallocated, 1: zeroed grain
+        let sector = le_u32(&entry) as u64;
+        if sector <= 1 {
+            out.fill(0);
+            return Ok(());
+        }
+        let host = sector * SECTOR;
+
+        if !self.compressed {
+            if host + (within + out.len()) as u64 > self.file_len {
+                return Err(corrupt("points past the end of the file"));
+            }
+            return read_exact_at(&self.file, out, host + within as u64);
+        }
+
+        let mut marker = [0u8; GRAIN_MARKER_SIZE];
+        if host + GRAIN_MARKER_SIZE as u64 > self.file_len {
+            return Err(corrupt("points past the end of the file"));
+        }
+        read_exact_at(&self.file, &mut marker, host)?;
+        let len = le_u32(&marker[8..]) as u64;
+        if host + GRAIN_MARKER_SIZE as u64 + len > self.file_len {
+            return Err(corrupt("is truncated"));
+        }
+        scratch.resize(len as usize, 0);
+        read_exact_at(&self.file, scratch, host + GRAIN_MARKER_SIZE as u64)?;
+
+        let mut data = vec![0u8; self.grain_size as usize];
+        let mut inflater = Decompress::new(true);
+        if inflater.decompress(scratch, &mut data, FlushDecompress::Finish).is_err()
+            || (inflater.total_out() as usize) < within + out.len()
+        {
+            return Err(corrupt("does not decompress"));
+        }
+        out.copy_from_slice(&data[within..within + out.len()]);
+        Ok(())
+    }
+}
+
+/// Extents of a text descriptor, with files relative to `base`
+fn parse_descriptor(text: &str, base: &Path) -> Result<Vec<Extent>> {
+    let invalid = |what: String| RecoveryError::Parse(format!("VMDK descriptor: {}", what));
+    if !text.starts_with("# Disk DescriptorFile") {
+        return Err(invalid("not a VMDK descriptor".to_string()));
+    }
+
+    let mut extents = Vec::new();
+    let mut start = 0;
+    for line in text.lines().map(str::trim) {
+        if let Some(parent) = line.strip_prefix("parentCID=") {
+            if !parent.eq_ignore_ascii_case("ffffffff") {
+                return Err(invalid("delta disks with a parent are not supported".to_string()));
+            }
+            continue;
+        }
+
+        // RW 4192256 SPARSE "disk-s001.vmdk" [offset]
+        let mut fields = line.splitn(3, ' ');
+        let (Some("RW" | "RDONLY" | "NOACCESS"), Some(sectors), Some(rest)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let sectors: u64 = sectors.parse().map_err(|_| invalid(format!("bad extent line `{}`", line)))?;
+        let (kind, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+        let (file_name, offset) = match rest.trim().strip_prefix('"').and_then(|r| r.split_once('"')) {
+            Some((name, offset)) => (Some(name), offset.trim().parse::<u64>().unwrap_or(0)),
+            None => (None, 0),
+        };
+        let open = || {
+            let name = file_name.ok_or_else(|| invalid(format!("extent without a file: `{}`", line)))?;
+            File::open(base.join(name)).map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => RecoveryError::FileNotFound(base.join(name).display().to_string()),
+                _ => RecoveryError::Io(e),
+            })
+        };
+
+        let data = match kind {
+            "FLAT" | "VMFS" => ExtentData::Flat(open()?, offset * SECTOR),
+            "SPARSE" => ExtentData::Sparse(SparseExtent::open(open()?)?.extent),
+            "ZERO" => ExtentData::Zero,
+            other => return Err(invalid(format!("{} extents are not supported", other))),
+        };
+        let size = sectors * SECTOR;
+        extents.push(Extent { start, size, data });
+        start += size;
+    }
+
+    if extents.is_empty() {
+        return Err(invalid("no extents".to_string()));
+    }
+    Ok(extents)
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn le_u64(bytes: &[u8]) -> u64 {
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sparse extent with 1-sector grains: grain 0 allocated, grain 1 not
+    fn build_sparse(guest: &[u8]) -> Vec<u8> {
+        let mut extent = vec![0u8; 4 * SECTOR as usize];
+        extent[..4].copy_from_slice(VMDK_SPARSE_MAGIC);
+        extent[4..8].copy_from_slice(&1u32.to_le_bytes());
+        extent[12..20].copy_from_slice(&((guest.len() as u64) / SECTOR).to_le_bytes());
+        extent[20..28].copy_from_slice(&1u64.to_le_bytes());
+        extent[44..48].copy_from_slice(&512u32.to_le_bytes());
+        extent[56..64].copy_from_slice(&1u64.to_le_bytes());
+        // Directory at sector 1 -> table at sector 2 -> grain 0 at sector 3
+        extent[512..516].copy_from_slice(&2u32.to_le_bytes());
+        extent[1024..1028].copy_from_slice(&3u32.to_le_bytes());
+        extent[1536..2048].copy_from_slice(&guest[..512]);
+        extent
+    }
+
+    #[test]
+    fn test_read_descriptor_extents() {
+        let mut guest: Vec<u8> = (0..1024).map(|i| (i % 251) as u8).collect();
+        guest[512..].fill(0);
+
+        let unique = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("rust_recovery_vmdk_{unique}"));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("disk-s001.vmdk"), build_sparse(&guest)).unwrap();
+        std::fs::write(dir.join("disk-flat.vmdk"), [7u8; 1024]).unwrap();
+        std::fs::write(
+            dir.join("disk.vmdk"),
+            "# Disk DescriptorFile\nversion=1\nparentCID=ffffffff\ncreateType=\"twoGbMaxExtentSparse\"\n\n\
+             RW 2 SPARSE \"disk-s001.vmdk\"\nRW 1 FLAT \"disk-flat.vmdk\" 1\nRW 1 ZERO\n",
+        )
+        .unwrap();
+
+        let image = VmdkImage::open(&dir.join("disk.vmdk")).unwrap();
+        assert_eq!(image.size(), 2048);
+        assert_eq!(image.extent_count(), 3);
+
+        let mut buf = vec![0xAAu8; 2048];
+        image.read_at(0, &mut buf).unwrap();
+        assert_eq!(buf[..1024], guest[..]);
+        assert!(buf[1024..1536].iter().all(|&b| b == 7));
+        assert!(buf[1536..].iter().all(|&b| b == 0));
+
+        let single = VmdkImage::open(&dir.join("disk-s001.vmdk")).unwrap();
+        let mut grain = [0u8; 16];
+        single.read_at(500, &mut grain).unwrap();
+        assert_eq!(grain[..], guest[500..516]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,239 @@
+//! Raw Windows devices (`\\.\PhysicalDrive2`, `\\.\E:`)
+//!
+//! A device handle reports no file length and reads only whole sectors at
+//! sector-aligned offsets. The capacity comes from
+//! `IOCTL_DISK_GET_LENGTH_INFO` and the sector size from
+//! `IOCTL_DISK_GET_DRIVE_GEOMETRY`; every read is widened to whole sectors,
+//! positioned with `SetFilePointerEx` and issued as one `ReadFile`. The
+//! handle has a single file pointer, so reads are serialized.
+
+use crate::disk::DEVICE_RETRY_BLOCK;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Sector size assumed when the drive geometry cannot be read
+const DEFAULT_SECTOR_SIZE: u32 = 512;
+
+/// True for a Win32 device namespace path (`\\.\PhysicalDrive2`, `//./E:`)
+pub fn is_device_path(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    let bytes = path.as_bytes();
+    bytes.len() > 4
+        && matches!(bytes[0], b'\\' | b'/')
+        && matches!(bytes[1], b'\\' | b'/')
+        && bytes[2] == b'.'
+        && matches!(bytes[3], b'\\' | b'/')
+}
+
+/// Sector-aligned `(start, len)` covering `len` bytes from `offset`
+pub fn aligned_span(offset: u64, len: usize, sector_size: u32) -> (u64, usize) {
+    let sector = u64::from(sector_size.max(1));
+    let start = offset - offset % sector;
+    let end = (offset + len as u64).div_ceil(sector) * sector;
+    (start, (end - start) as usize)
+}
+
+/// Raw device opened through its `\\.\` path
+#[derive(Debug)]
+pub struct WindowsDevice {
+    file: File,
+    size: u64,
+    sector_size: u32,
+    /// Held from `SetFilePointerEx` to the end of `ReadFile`
+    position: Mutex<()>,
+}
+
+impl WindowsDevice {
+    /// Open the device read-only and query its capacity and sector size
+    /// (needs an elevated prompt for physical drives)
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let size = sys::length(&file)?;
+        let sector_size = sys::sector_size(&file).unwrap_or(DEFAULT_SECTOR_SIZE).max(1);
+        Ok(Self { file, size, sector_size, position: Mutex::new(()) })
+    }
+
+    /// Capacity in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn sector_size(&self) -> u32 {
+        self.sector_size
+    }
+
+    /// Fill `buf` from `offset`; the sectors around it are read and the
+    /// requested bytes copied out
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+        if offset + buf.len() as u64 > self.size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let (start, len) = aligned_span(offset, buf.len(), self.sector_size);
+        let mut sectors = vec![0u8; len];
+        {
+            let _position = self.position.lock().unwrap_or_else(|e| e.into_inner());
+            sys::read_at(&self.file, start, &mut sectors)?;
+        }
+        let skip = (offset - start) as usize;
+        buf.copy_from_slice(&sectors[skip..skip + buf.len()]);
+        Ok(())
+    }
+
+    /// Like `read_at`, but blocks that fail to read are zero-filled;
+    /// returns the number of unreadable bytes
+    pub fn read_tolerant(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if self.read_at(offset, buf).is_ok() {
+            return Ok(0);
+        }
+        let mut unreadable = 0;
+        for (i, block) in buf.chunks_mut(DEVICE_RETRY_BLOCK).enumerate() {
+            if self.read_at(offset + (i * DEVICE_RETRY_BLOCK) as u64, block).is_err() {
+                block.fill(0);
+                unreadable += block.len();
+            }
+        }
+        Ok(unreadable)
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+
+    const IOCTL_DISK_GET_DRIVE_GEOMETRY: u32 = 0x0007_0000;
+    const IOCTL_DISK_GET_LENGTH_INFO: u32 = 0x0007_405C;
+    const FILE_BEGIN: u32 = 0;
+
+    /// DISK_GEOMETRY
+    #[repr(C)]
+    #[derive(Default)]
+    struct DiskGeometry {
+        cylinders: i64,
+        media_type: u32,
+        tracks_per_cylinder: u32,
+        sectors_per_track: u32,
+        bytes_per_sector: u32,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn DeviceIoControl(
+            device: *mut c_void,
+            control_code: u32,
+            in_buffer: *const c_void,
+            in_size: u32,
+            out_buffer: *mut c_void,
+            out_size: u32,
+            bytes_returned: *mut u32,
+            overlapped: *mut c_void,
+        ) -> i32;
+        fn SetFilePointerEx(file: *mut c_void, distance: i64, new_pointer: *mut i64, move_method: u32) -> i32;
+        fn ReadFile(file: *mut c_void, buffer: *mut c_void, to_read: u32, read: *mut u32, overlapped: *mut c_void) -> i32;
+    }
+
+    /// `control_code` with no input, filling `out`
+    fn query<T>(file: &File, control_code: u32, out: &mut T) -> io::Result<()> {
+        let mut returned = 0u32;
+        let ok = unsafe {
+            DeviceIoControl(
+                file.as_raw_handle() as *mut c_void,
+                control_code,
+                std::ptr::null(),
+                0,
+                out as *mut T as *mut c_void,
+                std::mem::size_of::<T>() as u32,
+                &mut returned,
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn length(file: &File) -> io::Result<u64> {
+        let mut length = 0i64;
+        query(file, IOCTL_DISK_GET_LENGTH_INFO, &mut length)?;
+        Ok(length as u64)
+    }
+
+    pub fn sector_size(file: &File) -> io::Result<u32> {
+        let mut geometry = DiskGeometry::default();
+        query(file, IOCTL_DISK_GET_DRIVE_GEOMETRY, &mut geometry)?;
+        Ok(geometry.bytes_per_sector)
+    }
+
+    /// Sector-aligned read of `buf.len()` bytes from `offset`
+    pub fn read_at(file: &File, offset: u64, mut buf: &mut [u8]) -> io::Result<()> {
+        let handle = file.as_raw_handle() as *mut c_void;
+        if unsafe { SetFilePointerEx(handle, offset as i64, std::ptr::null_mut(), FILE_BEGIN) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        while !buf.is_empty() {
+            let want = buf.len().min(u32::MAX as usize & !0xFFFF) as u32;
+            let mut read = 0u32;
+            let ok = unsafe {
+                ReadFile(handle, buf.as_mut_ptr() as *mut c_void, want, &mut read, std::ptr::null_mut())
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if read == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            buf = &mut buf[read as usize..];
+        }
+        Ok(())
+    }
+}
+
+/// Other platforms have no `\\.\` namespace
+#[cfg(not(windows))]
+mod sys {
+    use std::fs::File;
+    use std::io;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(io::ErrorKind::Unsupported, "\\\\.\\ device paths exist only on Windows")
+    }
+
+    pub fn length(_file: &File) -> io::Result<u64> {
+        Err(unsupported())
+    }
+
+    pub fn sector_size(_file: &File) -> io::Result<u32> {
+        Err(unsupported())
+    }
+
+    pub fn read_at(_file: &File, _offset: u64, _buf: &mut [u8]) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_paths_and_sector_spans() {
+        assert!(is_device_path(Path::new(r"\\.\PhysicalDrive2")));
+        assert!(is_device_path(Path::new("//./E:")));
+        assert!(!is_device_path(Path::new(r"\\?\C:\images\disk.img")));
+        assert!(!is_device_path(Path::new(r"\\server\share\disk.img")));
+        assert!(!is_device_path(Path::new("/dev/sda")));
+
+        assert_eq!(aligned_span(0, 512, 512), (0, 512));
+        assert_eq!(aligned_span(100, 10, 512), (0, 512));
+        assert_eq!(aligned_span(510, 4, 512), (0, 1024));
+        assert_eq!(aligned_span(4096 + 1, 4096, 4096), (4096, 8192));
+    }
+}
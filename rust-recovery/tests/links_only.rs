@@ -0,0 +1,91 @@
+//! `--links-only` end to end: a URL found in two fragments is exported once,
+//! and nothing is assembled or saved
+
+use std::path::Path;
+use std::process::Command;
+
+const CHUNK: usize = 2 << 20;
+
+/// One scan chunk of watch-history JSON whose first entries link `ids` in turn
+fn history_chunk(ids: &[&str]) -> Vec<u8> {
+    let title = "Смотрели видео про зайцев и лис в зимнем лесу. ".repeat(40);
+    let mut text = String::from("[");
+    for i in 0.. {
+        if text.len() > CHUNK - 8000 {
+            break;
+        }
+        let url = match i < 10 {
+            true => format!(",\"titleUrl\":\"https://www.youtube.com/watch?v={}\"", ids[i % ids.len()]),
+            false => String::new(),
+        };
+        text.push_str(&format!("{{\"header\":\"YouTube\",\"title\":\"{}\"{}}},\n", title, url));
+    }
+    text.push(']');
+    let mut chunk = text.into_bytes();
+    chunk.resize(CHUNK, b' ');
+    chunk
+}
+
+fn scan(image: &Path, output: &Path, extra: &[&str]) {
+    let run = Command::new(env!("CARGO_BIN_EXE_rust-recovery"))
+        .arg(image)
+        .arg("-o")
+        .arg(output)
+        .args(["--no-live", "--links-only", "--export-links", "csv"])
+        .args(extra)
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+}
+
+#[test]
+fn test_links_only_exports_each_url_once_and_saves_no_files() {
+    let dir = std::env::temp_dir().join(format!("rust_recovery_links_only_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let image = dir.join("history.img");
+    let mut data = history_chunk(&["dQw4w9WgXcQ", "aaaaaaaaaaa", "bbbbbbbbbbb"]);
+    data.extend(history_chunk(&["dQw4w9WgXcQ", "ccccccccccc"]));
+    std::fs::write(&image, data).unwrap();
+
+    // In memory and from the scan database
+    for (name, extra) in [("memory", &[][..]), ("scan_db", &["--scan-db"][..])] {
+        let output = dir.join(name);
+        scan(&image, &output, extra);
+
+        let csv = std::fs::read_to_string(output.join("links.csv")).unwrap();
+        let rows: Vec<(&str, &str)> = csv
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                (fields[0], fields[3])
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("cluster_1", "https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+                ("cluster_1", "https://www.youtube.com/watch?v=aaaaaaaaaaa"),
+                ("cluster_1", "https://www.youtube.com/watch?v=bbbbbbbbbbb"),
+                ("cluster_2", "https://www.youtube.com/watch?v=ccccccccccc"),
+            ],
+            "{}",
+            name
+        );
+
+        // Both fragments are reported, none is assembled into a file
+        assert!(!output.join("01_RECOVERED_FILES").exists(), "{}", name);
+        let report = std::fs::read_dir(output.join("reports"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&std::fs::read(report).unwrap()).unwrap();
+        assert_eq!(report["clusters"].as_array().unwrap().len(), 2, "{}", name);
+        assert_eq!(report["recovered_files"].as_array().unwrap().len(), 0, "{}", name);
+        assert_eq!(report["scan_results"]["files_recovered"], 0, "{}", name);
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}